@@ -0,0 +1,187 @@
+//! EIP-2930 access list generation
+//!
+//! The transaction runs once under [`AccessListTracer`], which records every
+//! account and storage slot the execution touches. Each recorded entry is then
+//! only kept in the list when pre-declaring it is cheaper than the cold access
+//! it replaces:
+//!
+//! * a cold account saves 100 gas (2600 cold access vs. 2400 in the list plus a
+//!   100 warm access), and so does each of its storage slots (2100 vs. 1900 + 100)
+//! * accounts that are warm anyway (sender, target, coinbase, precompiles and
+//!   created contracts) only pay off once enough of their slots are accessed to
+//!   cover the 2400 of the address entry
+//!
+//! The estimator runs the transaction again with the generated list and compares
+//! the gas used by both runs, so clients see the actual saving before deciding
+//! to include it.
+
+use crate::{
+    error::ServiceError,
+    foundry::{execute_on_fork, tx_env_for_fork, Fork, SimulationMode},
+    intrinsic::{ACCESS_LIST_ADDRESS_GAS, ACCESS_LIST_STORAGE_KEY_GAS},
+};
+use alloy::{
+    eips::eip2930::{AccessList, AccessListItem},
+    primitives::{Address, B256},
+    rpc::types::TransactionRequest,
+};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    precompile::{PrecompileSpecId, Precompiles},
+    primitives::{ExecutionResult, SpecId, TransactTo, U256},
+    Database, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::debug;
+
+/// Cost of the first access to an account (EIP-2929)
+pub const COLD_ACCOUNT_ACCESS_GAS: u64 = 2_600;
+
+/// Cost of the first access to a storage slot (EIP-2929)
+pub const COLD_SLOAD_GAS: u64 = 2_100;
+
+/// Cost of any later access to an account or storage slot (EIP-2929)
+pub const WARM_ACCESS_GAS: u64 = 100;
+
+/// Inspector recording the accounts and storage slots an execution accesses
+#[derive(Debug, Default)]
+pub struct AccessListTracer {
+    /// Accessed accounts and the storage slots read or written in each
+    accessed: BTreeMap<Address, BTreeSet<B256>>,
+    /// Contracts created during the execution, warm from their creation
+    created: BTreeSet<Address>,
+}
+
+impl AccessListTracer {
+    /// Record an account as accessed
+    fn access(&mut self, address: Address) -> &mut BTreeSet<B256> {
+        self.accessed.entry(address).or_default()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Ok(top) = interp.stack().peek(0) else {
+            return;
+        };
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                self.access(interp.contract.target_address).insert(B256::from(top));
+            }
+            opcode::BALANCE | opcode::EXTCODESIZE | opcode::EXTCODECOPY | opcode::EXTCODEHASH => {
+                self.access(Address::from_word(B256::from(top)));
+            }
+            _ => {}
+        }
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.access(inputs.target_address);
+        self.access(inputs.bytecode_address);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let Some(address) = outcome.address {
+            self.created.insert(address);
+        }
+        outcome
+    }
+
+    fn selfdestruct(&mut self, _contract: Address, target: Address, _value: U256) {
+        self.access(target);
+    }
+}
+
+/// Gas an access list entry saves over accessing the same state cold, negative if it costs more
+fn entry_saving(warm: bool, storage_keys: usize) -> i64 {
+    let slot_saving = (COLD_SLOAD_GAS - ACCESS_LIST_STORAGE_KEY_GAS - WARM_ACCESS_GAS) as i64;
+    let address_saving = if warm {
+        -(ACCESS_LIST_ADDRESS_GAS as i64)
+    } else {
+        (COLD_ACCOUNT_ACCESS_GAS - ACCESS_LIST_ADDRESS_GAS - WARM_ACCESS_GAS) as i64
+    };
+    address_saving + slot_saving * storage_keys as i64
+}
+
+/// Gas used by a successful execution
+///
+/// A transaction that reverts or halts has no saving to report; its revert data
+/// or halt reason is returned instead, as for an estimate.
+fn gas_used(result: ExecutionResult) -> Result<u64, ServiceError> {
+    match result {
+        ExecutionResult::Success { gas_used, .. } => Ok(gas_used),
+        ExecutionResult::Revert { output, .. } => Err(ServiceError::Reverted(output)),
+        ExecutionResult::Halt { reason, .. } => Err(ServiceError::Simulation(format!("execution halted: {:?}", reason))),
+    }
+}
+
+/// Build the access list that minimizes the gas of a transaction on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction, with the gas limit it is traced under
+/// * `mode` - Validation rules applied to the transaction
+///
+/// # Returns
+///
+/// * `Result<(AccessList, u64), ServiceError>` - The entries that save gas and the gas the
+///   traced execution used, or an error if the transaction fails validation or doesn't succeed
+pub async fn access_list_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    mode: SimulationMode,
+) -> Result<(AccessList, u64), ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+
+    // Accounts the EVM warms up before execution starts
+    let mut warm = BTreeSet::from([tx_env.caller]);
+    if let TransactTo::Call(to) = tx_env.transact_to {
+        warm.insert(to);
+    }
+    if fork.spec_id.is_enabled_in(SpecId::SHANGHAI) {
+        warm.insert(fork.block_env.coinbase);
+    }
+    let precompiles = Precompiles::new(PrecompileSpecId::from_spec_id(fork.spec_id));
+
+    let (result, tracer) = execute_on_fork(fork, tx_env, mode, Some(AccessListTracer::default())).await?;
+    let gas_used = gas_used(result)?;
+    let tracer = tracer.unwrap_or_default();
+
+    let items: Vec<AccessListItem> = tracer
+        .accessed
+        .into_iter()
+        .filter(|(address, slots)| {
+            let warm = warm.contains(address) || tracer.created.contains(address) || precompiles.contains(address);
+            entry_saving(warm, slots.len()) > 0
+        })
+        .map(|(address, slots)| AccessListItem {
+            address,
+            storage_keys: slots.into_iter().collect(),
+        })
+        .collect();
+    debug!("Generated an access list of {} accounts", items.len());
+    Ok((AccessList(items), gas_used))
+}
+
+/// Gas a transaction uses on a fork, without the headroom of an estimate
+///
+/// # Returns
+///
+/// * `Result<u64, ServiceError>` - The gas used, or an error if the transaction fails
+///   validation or doesn't succeed
+pub async fn gas_used_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    mode: SimulationMode,
+) -> Result<u64, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+    let (result, _) = execute_on_fork(fork, tx_env, mode, None::<AccessListTracer>).await?;
+    gas_used(result)
+}
@@ -0,0 +1,178 @@
+//! Bounded log of recent JSON-RPC requests, for replay debugging
+//!
+//! Requests are kept as received, except for signed material that stays valid
+//! after the request: the signed transactions of a bundle and the signatures of
+//! EIP-7702 authorizations. Anyone able to read the log could otherwise
+//! broadcast a searcher's bundle or delegate a user's account.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use serde::Serialize;
+use crate::correlation::correlation_id;
+
+/// Default number of requests retained by the audit log
+pub const DEFAULT_AUDIT_LOG_CAPACITY: usize = 1_000;
+
+/// A JSON-RPC request captured by the audit log
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Identifier handed back to clients so they can reference this request
+    pub request_id: String,
+
+    /// Unix timestamp (seconds) at which the request was received
+    pub received_at: u64,
+
+    /// The JSON-RPC request object as it was received, signed material redacted
+    pub payload: serde_json::Value,
+
+    /// Whether signed material was removed from the payload, which then can't be replayed
+    pub redacted: bool,
+
+    /// Correlation id the request was served under, to find its log lines and upstream calls
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+/// Bounded in-memory log of recently processed JSON-RPC requests
+///
+/// Every request item is recorded under a unique request id, so an operator
+/// can later replay the exact request a user reported. Once the log is full,
+/// the oldest entries are evicted first.
+#[derive(Debug)]
+pub struct AuditLog {
+    /// Maximum number of retained entries
+    capacity: usize,
+
+    /// Prefix that keeps request ids unique across restarts
+    epoch: u64,
+
+    /// Monotonic counter for request ids
+    counter: AtomicU64,
+
+    /// Retained entries, oldest first
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Create an audit log retaining at most `capacity` entries
+    ///
+    /// A capacity of zero disables recording; request ids are still issued.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            epoch: unix_now(),
+            counter: AtomicU64::new(0),
+            entries: Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_AUDIT_LOG_CAPACITY))),
+        }
+    }
+
+    /// Record a raw request and return the request id assigned to it
+    ///
+    /// The entry keeps the correlation id of the request being served, if any.
+    pub fn record(&self, payload: &serde_json::Value) -> String {
+        let sequence = self.counter.fetch_add(1, Ordering::Relaxed);
+        let request_id = format!("{:x}-{:x}", self.epoch, sequence);
+
+        if self.capacity > 0 {
+            let mut payload = payload.clone();
+            let redacted = redact_signed_material(&mut payload);
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if entries.len() >= self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(AuditEntry {
+                request_id: request_id.clone(),
+                received_at: unix_now(),
+                payload,
+                redacted,
+                correlation_id: correlation_id(),
+            });
+        }
+
+        request_id
+    }
+
+    /// Look up a previously recorded request by id
+    pub fn get(&self, request_id: &str) -> Option<AuditEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .find(|entry| entry.request_id == request_id)
+            .cloned()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUDIT_LOG_CAPACITY)
+    }
+}
+
+/// Placeholder of redacted values
+pub const REDACTED: &str = "[redacted]";
+
+/// Replace the signed material of a request with [`REDACTED`]
+///
+/// Covers the signed transactions (`txs`) of `eth_callBundle` and the signature
+/// fields of every `authorizationList` entry in the params.
+///
+/// # Returns
+///
+/// * `bool` - Whether anything was redacted
+fn redact_signed_material(payload: &mut serde_json::Value) -> bool {
+    let bundle = payload.get("method").and_then(|method| method.as_str()) == Some("eth_callBundle");
+    let Some(params) = payload.get_mut("params") else {
+        return false;
+    };
+    let mut redacted = false;
+    if bundle {
+        for txs in params.as_array_mut().into_iter().flatten().filter_map(|bundle| bundle.get_mut("txs")) {
+            for tx in txs.as_array_mut().into_iter().flatten() {
+                *tx = serde_json::json!(REDACTED);
+                redacted = true;
+            }
+        }
+    }
+    redact_authorizations(params, &mut redacted);
+    redacted
+}
+
+/// Redact the signatures of `authorizationList` entries, at any depth
+fn redact_authorizations(value: &mut serde_json::Value, redacted: &mut bool) {
+    match value {
+        serde_json::Value::Object(object) => {
+            for (key, member) in object.iter_mut() {
+                if key == "authorizationList" {
+                    for authorization in member.as_array_mut().into_iter().flatten() {
+                        for field in ["r", "s", "yParity", "v"] {
+                            if let Some(signature) = authorization.get_mut(field) {
+                                *signature = serde_json::json!(REDACTED);
+                                *redacted = true;
+                            }
+                        }
+                    }
+                } else {
+                    redact_authorizations(member, redacted);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| redact_authorizations(item, redacted)),
+        _ => {}
+    }
+}
+
+/// Current Unix time in seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,247 @@
+//! Pluggable simulation backends
+//!
+//! Estimates, calls (`eth_call`) and traces (`debug_traceCall`) are served by
+//! a [`SimulationBackend`], selected per chain with `SIMULATION_BACKEND`:
+//!
+//! * `revm` (default) - local simulation on a fork of the upstream state, with
+//!   the chain's adapter (see [`crate::chains`]). Independent of the node's own
+//!   estimation, and every fork state fetch is shared between requests.
+//! * `upstream` - passthrough to the upstream node's `eth_estimateGas`,
+//!   `eth_call` and `debug_traceCall`. Nothing runs locally, so chains whose
+//!   gas model the local EVM doesn't reproduce are served as their node
+//!   computes them, at the cost of one upstream call per request and of the
+//!   node's own estimation accuracy.
+//!
+//! Only these three operations go through the backend. The upstream backend
+//! is asked the client's request as sent, and its estimates are returned as
+//! the node computed them (no calldata floor, hooks or headroom on top).
+//! Reports that re-run the transaction locally (extended estimates, access
+//! lists, explanations) are rejected on chains it serves.
+
+use crate::{
+    chains::{AdapterContext, ChainAdapter},
+    error::ServiceError,
+    estimator::EstimateSource,
+    foundry::{simulate_on_fork, Fork, SimulationMode},
+    models::trace::{Trace, TraceOptions},
+    rpc::{upstream_failure, EthereumClient},
+    tracers::trace_on_fork,
+};
+use alloy::{
+    eips::BlockId,
+    primitives::{Bytes, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    transports::{RpcError, TransportErrorKind},
+};
+use futures::future::LocalBoxFuture;
+use revm::primitives::{ExecutionResult, HaltReason};
+use std::{str::FromStr, sync::Arc};
+use tracing::debug;
+
+/// Resources available to a simulation backend while serving a request
+pub struct BackendContext<'a> {
+    /// Client for the configured upstream node
+    pub eth_client: &'a EthereumClient,
+    /// Fork of the block the request runs against, with any pinned header values
+    pub fork: &'a Fork,
+    /// The same block, as the upstream node is asked for it
+    pub block: BlockId,
+}
+
+/// How transactions are estimated, called and traced
+pub trait SimulationBackend: Send + Sync {
+    /// Human-readable backend name, used in logs
+    fn name(&self) -> &'static str;
+
+    /// Where the estimates of this backend come from
+    fn source(&self) -> EstimateSource;
+
+    /// Estimate the gas limit of a transaction
+    fn estimate<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>>;
+
+    /// Execute a call and return its output, or an error if it reverted or halted
+    fn call<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<Bytes, ServiceError>>;
+
+    /// Trace a call with the requested tracer
+    fn trace<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+        options: &'a TraceOptions,
+    ) -> LocalBoxFuture<'a, Result<Trace, ServiceError>>;
+}
+
+/// Local REVM simulation on the fork, estimating with the chain's adapter
+pub struct RevmBackend {
+    /// Estimation strategy of the connected chain
+    pub adapter: Arc<dyn ChainAdapter>,
+}
+
+impl SimulationBackend for RevmBackend {
+    fn name(&self) -> &'static str {
+        "revm"
+    }
+
+    fn source(&self) -> EstimateSource {
+        EstimateSource::Simulation
+    }
+
+    fn estimate<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        debug!("Estimating with the {} chain adapter", self.adapter.name());
+        let ctx = AdapterContext {
+            eth_client: ctx.eth_client,
+            fork: ctx.fork,
+        };
+        self.adapter.estimate_gas(ctx, tx_request)
+    }
+
+    fn call<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<Bytes, ServiceError>> {
+        Box::pin(async move {
+            let (result, out_of_gas_frame) = simulate_on_fork(ctx.fork, tx_request, SimulationMode::Call).await?;
+            match result {
+                ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+                ExecutionResult::Revert { output, .. } => Err(ServiceError::Reverted(output)),
+                ExecutionResult::Halt { reason, .. } => match (reason, out_of_gas_frame) {
+                    // Name the frame that exhausted its gas, so callers can tell a low limit from a starved subcall
+                    (HaltReason::OutOfGas(_), Some(frame)) => Err(ServiceError::OutOfGas(frame)),
+                    (reason, _) => Err(ServiceError::Simulation(format!("execution halted: {:?}", reason))),
+                },
+            }
+        })
+    }
+
+    fn trace<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+        options: &'a TraceOptions,
+    ) -> LocalBoxFuture<'a, Result<Trace, ServiceError>> {
+        Box::pin(trace_on_fork(ctx.fork, tx_request, options, SimulationMode::Call))
+    }
+}
+
+/// Passthrough to the upstream node's own `eth_estimateGas`, `eth_call` and `debug_traceCall`
+pub struct UpstreamBackend;
+
+impl SimulationBackend for UpstreamBackend {
+    fn name(&self) -> &'static str {
+        "upstream"
+    }
+
+    fn source(&self) -> EstimateSource {
+        EstimateSource::Upstream
+    }
+
+    fn estimate<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        Box::pin(async move {
+            let gas = ctx
+                .eth_client
+                .provider
+                .estimate_gas(tx_request.clone())
+                .block(ctx.block)
+                .await
+                .map_err(|e| upstream_error("eth_estimateGas", e))?;
+            Ok(U256::from(gas))
+        })
+    }
+
+    fn call<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<Bytes, ServiceError>> {
+        Box::pin(async move {
+            ctx.eth_client
+                .provider
+                .call(tx_request.clone())
+                .block(ctx.block)
+                .await
+                .map_err(|e| upstream_error("eth_call", e))
+        })
+    }
+
+    fn trace<'a>(
+        &'a self,
+        ctx: BackendContext<'a>,
+        tx_request: &'a TransactionRequest,
+        options: &'a TraceOptions,
+    ) -> LocalBoxFuture<'a, Result<Trace, ServiceError>> {
+        Box::pin(async move {
+            let output: serde_json::Value = ctx
+                .eth_client
+                .provider
+                .client()
+                .request("debug_traceCall", (tx_request.clone(), ctx.block, options.clone()))
+                .await
+                .map_err(|e| upstream_error("debug_traceCall", e))?;
+            Ok(Trace::Upstream(output))
+        })
+    }
+}
+
+/// Map an upstream failure onto a service error
+///
+/// Errors answered by the node (reverts, invalid transactions) are the
+/// transaction's own; anything else means the node couldn't be reached.
+pub(crate) fn upstream_error(method: &str, e: RpcError<TransportErrorKind>) -> ServiceError {
+    match e {
+        RpcError::ErrorResp(payload) => match payload.as_revert_data() {
+            Some(data) => ServiceError::Reverted(data),
+            None => ServiceError::Simulation(payload.message.to_string()),
+        },
+        e => upstream_failure(&format!("Upstream {} failed", method), e),
+    }
+}
+
+/// Which simulation backend serves a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    /// Local REVM simulation ([`RevmBackend`])
+    #[default]
+    Revm,
+    /// The upstream node's own methods ([`UpstreamBackend`])
+    Upstream,
+}
+
+impl BackendKind {
+    /// Build the backend, with the chain's adapter for local simulation
+    pub fn backend(&self, adapter: Arc<dyn ChainAdapter>) -> Arc<dyn SimulationBackend> {
+        match self {
+            BackendKind::Revm => Arc::new(RevmBackend { adapter }),
+            BackendKind::Upstream => Arc::new(UpstreamBackend),
+        }
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "revm" => Ok(BackendKind::Revm),
+            "upstream" => Ok(BackendKind::Upstream),
+            other => Err(format!("Unknown simulation backend: {}", other)),
+        }
+    }
+}
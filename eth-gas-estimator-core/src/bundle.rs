@@ -0,0 +1,206 @@
+//! Flashbots-style bundle simulation (`eth_callBundle`)
+//!
+//! Searchers check a bundle before submitting it by asking a relay to simulate
+//! it. The same simulation runs here against the local fork: the signed
+//! transactions are decoded, their senders recovered, and they run back-to-back
+//! in a block built on top of the state block, each one seeing the state left
+//! by the previous. The coinbase balance is read around every transaction, so
+//! the response tells what the bundle pays the block builder:
+//!
+//! * `gasFees`: the priority fees, `gasUsed * (gasPrice - baseFee)`
+//! * `coinbaseDiff`: the change of the coinbase balance, negative when a
+//!   transaction takes funds from the coinbase
+//! * `ethSentToCoinbase`: the difference, paid by direct transfers
+//!
+//! As on a relay, a transaction failing validation (bad nonce, unfunded sender,
+//! a gas limit beyond what is left of the block's) fails the whole bundle, while
+//! reverts are reported per transaction.
+
+use crate::{
+    chains::precompiles::precompiles_register,
+    error::ServiceError,
+    foundry::{
+        abort_on_cancel, check_chain_id, child_block_env, configure_cfg_env, convert_tx_request_to_tx_env,
+        run_simulation, transaction_error, BlockOverrides, Fork, SimulationMode,
+    },
+};
+use alloy::{
+    consensus::TxEnvelope,
+    eips::eip2718::Decodable2718,
+    primitives::{Address, Bytes, B256, I256, U256},
+    rpc::types::TransactionRequest,
+};
+use revm::{
+    db::CacheDB,
+    primitives::{BlockEnv, ExecutionResult, TxEnv},
+    DatabaseCommit, DatabaseRef, Evm,
+};
+use tracing::{debug, info};
+
+/// Most transactions a single bundle may carry
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 100;
+
+/// A decoded transaction of a bundle
+#[derive(Debug, Clone)]
+pub struct BundleTransaction {
+    /// Hash of the signed transaction
+    pub hash: B256,
+    /// The transaction, with its recovered sender
+    pub request: TransactionRequest,
+}
+
+/// Outcome of one transaction of a simulated bundle
+#[derive(Debug)]
+pub struct BundleStep {
+    /// Execution result
+    pub result: ExecutionResult,
+    /// Effective gas price paid
+    pub gas_price: U256,
+    /// Change of the coinbase balance caused by the transaction
+    pub coinbase_diff: I256,
+}
+
+/// Outcome of a simulated bundle
+#[derive(Debug)]
+pub struct BundleExecution {
+    /// Environment of the block the bundle ran in
+    pub block_env: BlockEnv,
+    /// One step per transaction, in order
+    pub steps: Vec<BundleStep>,
+}
+
+/// Decode a signed raw transaction and recover its sender
+///
+/// # Arguments
+///
+/// * `raw` - The EIP-2718 encoded signed transaction
+///
+/// # Returns
+///
+/// * `Result<BundleTransaction, String>` - The transaction and its hash, or why it can't be decoded
+pub fn decode_bundle_transaction(raw: &Bytes) -> Result<BundleTransaction, String> {
+    let envelope = TxEnvelope::decode_2718(&mut raw.as_ref())
+        .map_err(|e| format!("invalid transaction encoding: {}", e))?;
+    let from = envelope
+        .recover_signer()
+        .map_err(|e| format!("invalid signature: {}", e))?;
+    Ok(BundleTransaction {
+        hash: *envelope.tx_hash(),
+        request: TransactionRequest::from_transaction_with_sender(envelope, from),
+    })
+}
+
+/// Simulate a bundle in a block following the fork's block
+///
+/// # Arguments
+///
+/// * `fork` - The fork whose state the bundle runs on
+/// * `tx_requests` - The bundle's transactions, in execution order
+/// * `overrides` - Header fields of the target block
+///
+/// # Returns
+///
+/// * `Result<BundleExecution, ServiceError>` - The target block and one step per transaction,
+///   or the error of the first transaction that failed validation
+pub async fn simulate_bundle_on_fork(
+    fork: &Fork,
+    tx_requests: &[TransactionRequest],
+    overrides: &BlockOverrides,
+) -> Result<BundleExecution, ServiceError> {
+    info!("Simulating a bundle of {} transactions on top of block: {:?}", tx_requests.len(), fork.block_number);
+
+    let block_env = child_block_env(&fork.block_env, overrides).map_err(ServiceError::InvalidParams)?;
+    let tx_envs = tx_requests
+        .iter()
+        .enumerate()
+        .map(|(index, tx_request)| {
+            check_chain_id(fork, tx_request)?;
+            convert_tx_request_to_tx_env(tx_request)
+                .map_err(|e| ServiceError::InvalidParams(format!("transaction {}: {}", index, e)))
+        })
+        .collect::<Result<Vec<TxEnv>, ServiceError>>()?;
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+
+    run_simulation(fork, move |backend| {
+        let cancellation = backend.cancellation();
+        let mut db = CacheDB::new(backend);
+        let coinbase = block_env.coinbase;
+        let mut gas_left: u64 = block_env.gas_limit.saturating_to();
+        let mut steps = Vec::with_capacity(tx_envs.len());
+        for (index, tx_env) in tx_envs.into_iter().enumerate() {
+            // The block must have room for the transaction's whole limit, as a builder checks
+            if tx_env.gas_limit > gas_left {
+                return Err(ServiceError::InvalidParams(format!(
+                    "transaction {}: gas limit {} exceeds the {} gas left in the block",
+                    index, tx_env.gas_limit, gas_left
+                )));
+            }
+            let caller = tx_env.caller;
+            let gas_price = effective_gas_price(&tx_env, block_env.basefee);
+            let balance_before = coinbase_balance(&db, coinbase)?;
+
+            let outcome = Evm::builder()
+                .with_db(&mut db)
+                .with_spec_id(spec_id)
+                .append_handler_register_box(precompiles_register(precompiles.clone()))
+                .append_handler_register_box(abort_on_cancel(cancellation.clone()))
+                .modify_cfg_env(|cfg| configure_cfg_env(cfg, SimulationMode::Estimate, &tx_env))
+                .with_block_env(block_env.clone())
+                .with_tx_env(tx_env)
+                .build()
+                .transact()
+                .map_err(|e| match transaction_error(caller, e) {
+                    ServiceError::Simulation(reason) => {
+                        ServiceError::Simulation(format!("transaction {}: {}", index, reason))
+                    }
+                    other => other,
+                })?;
+
+            // The coinbase is touched by every transaction, if only to be paid its fees
+            let balance_after = outcome
+                .state
+                .get(&coinbase)
+                .map(|account| account.info.balance)
+                .unwrap_or(balance_before);
+            // Commit so the next transaction sees this one's state changes
+            db.commit(outcome.state);
+            gas_left -= outcome.result.gas_used();
+            debug!("Bundle transaction {} simulated: {:?}", index, outcome.result);
+            steps.push(BundleStep {
+                result: outcome.result,
+                gas_price,
+                coinbase_diff: balance_diff(balance_before, balance_after),
+            });
+        }
+        Ok::<BundleExecution, ServiceError>(BundleExecution { block_env, steps })
+    })
+    .await
+}
+
+/// Gas price a transaction pays per unit of gas under the given base fee
+fn effective_gas_price(tx_env: &TxEnv, base_fee: U256) -> U256 {
+    match tx_env.gas_priority_fee {
+        Some(priority_fee) => tx_env.gas_price.min(base_fee.saturating_add(priority_fee)),
+        None => tx_env.gas_price,
+    }
+}
+
+/// Signed change from one balance to another
+fn balance_diff(before: U256, after: U256) -> I256 {
+    if after >= before {
+        I256::from_raw(after - before)
+    } else {
+        -I256::from_raw(before - after)
+    }
+}
+
+/// Balance of the coinbase in the bundle's state so far
+fn coinbase_balance<DB: DatabaseRef>(db: &DB, coinbase: Address) -> Result<U256, ServiceError>
+where
+    DB::Error: std::fmt::Display,
+{
+    db.basic_ref(coinbase)
+        .map(|info| info.map(|info| info.balance).unwrap_or_default())
+        .map_err(|e| ServiceError::RPCConnection(format!("Failed to read account {}: {}", coinbase, e)))
+}
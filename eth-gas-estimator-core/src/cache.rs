@@ -0,0 +1,155 @@
+//! Estimation result cache
+//!
+//! Identical estimates against the same block always produce the same result,
+//! so results are kept in an LRU keyed by the fork block hash and the
+//! canonicalized transaction request. The whole cache is dropped as soon as a
+//! request arrives for a new block, or once its block is orphaned by a reorg;
+//! results simulated on an orphaned block are never stored.
+
+use alloy::{
+    primitives::{keccak256, B256, U256},
+    rpc::types::TransactionRequest,
+};
+use lru::LruCache;
+use serde::Serialize;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tracing::debug;
+
+/// Default number of cached estimation results
+pub const DEFAULT_ESTIMATE_CACHE_CAPACITY: usize = 1024;
+
+/// Cached results for a single block
+struct CacheState {
+    /// Block the cached results were computed against
+    block_hash: B256,
+    /// Results keyed by the hash of the canonicalized request
+    entries: LruCache<B256, U256>,
+}
+
+/// Snapshot of the cache counters
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Maximum number of cached results
+    pub capacity: usize,
+    /// Number of currently cached results
+    pub size: usize,
+    /// Block the cached results belong to
+    pub block_hash: B256,
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that required a simulation
+    pub misses: u64,
+    /// Times the cache was dropped because the head moved
+    pub invalidations: u64,
+    /// Times the cache was dropped because its block was orphaned by a reorg
+    pub reorg_purges: u64,
+}
+
+/// LRU cache of estimation results for the current block
+pub struct EstimateCache {
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+    reorg_purges: AtomicU64,
+}
+
+impl EstimateCache {
+    /// Create a cache holding at most `capacity` results
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                block_hash: B256::ZERO,
+                entries: LruCache::new(capacity),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            reorg_purges: AtomicU64::new(0),
+        }
+    }
+
+    /// Look up the result for a request at `block_hash`
+    pub fn get(&self, block_hash: B256, tx_request: &TransactionRequest) -> Option<U256> {
+        let key = request_key(tx_request);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.roll_to(&mut state, block_hash);
+
+        let cached = key.and_then(|key| state.entries.get(&key).copied());
+        match cached {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        cached
+    }
+
+    /// Store the result for a request at `block_hash`
+    pub fn insert(&self, block_hash: B256, tx_request: &TransactionRequest, gas: U256) {
+        let Some(key) = request_key(tx_request) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.roll_to(&mut state, block_hash);
+        state.entries.put(key, gas);
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        CacheStats {
+            capacity: state.entries.cap().get(),
+            size: state.entries.len(),
+            block_hash: state.block_hash,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            reorg_purges: self.reorg_purges.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drop every cached result if they belong to a block a reorg orphaned
+    pub fn purge(&self, orphaned: &[B256]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if orphaned.contains(&state.block_hash) {
+            debug!("Block {} orphaned, dropping {} cached estimates", state.block_hash, state.entries.len());
+            self.reorg_purges.fetch_add(1, Ordering::Relaxed);
+            state.entries.clear();
+        }
+    }
+
+    /// Drop every cached result if `block_hash` is a different block
+    fn roll_to(&self, state: &mut CacheState, block_hash: B256) {
+        if state.block_hash != block_hash {
+            if !state.entries.is_empty() {
+                debug!("New block {}, dropping {} cached estimates", block_hash, state.entries.len());
+                self.invalidations.fetch_add(1, Ordering::Relaxed);
+            }
+            state.entries.clear();
+            state.block_hash = block_hash;
+        }
+    }
+}
+
+/// Cache key of a request: the hash of its canonical JSON encoding
+///
+/// Requests are parsed into `TransactionRequest` before reaching the cache, so
+/// formatting differences in the original JSON (hex casing, leading zeros,
+/// field order) don't produce distinct keys. A request that can't be encoded
+/// has no key and is never cached, rather than sharing one with every other
+/// such request.
+pub(crate) fn request_key(tx_request: &TransactionRequest) -> Option<B256> {
+    match serde_json::to_vec(tx_request) {
+        Ok(encoded) => Some(keccak256(encoded)),
+        Err(e) => {
+            debug!("Request can't be encoded, not caching it: {}", e);
+            None
+        }
+    }
+}
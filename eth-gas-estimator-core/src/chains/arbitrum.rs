@@ -0,0 +1,100 @@
+//! Arbitrum adapter
+//!
+//! Arbitrum gas limits include an L1 calldata component priced by the node
+//! itself, which a plain EVM simulation cannot reproduce. By default the
+//! estimate comes from the `NodeInterface.gasEstimateComponents` virtual
+//! contract, which splits the total into its L2 execution and L1 calldata
+//! parts; alternatively it can be delegated to the node's `eth_estimateGas`.
+
+use super::{AdapterContext, ArbitrumStrategy, ChainAdapter};
+use crate::error::ServiceError;
+use alloy::{
+    primitives::{address, Address, Bytes, TxKind, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use futures::future::LocalBoxFuture;
+use tracing::debug;
+
+/// Address of the `NodeInterface` virtual contract on every Arbitrum Nitro chain
+pub const NODE_INTERFACE: Address = address!("00000000000000000000000000000000000000C8");
+
+sol! {
+    /// Subset of the Arbitrum `NodeInterface` virtual contract
+    function gasEstimateComponents(address to, bool contractCreation, bytes data)
+        external
+        payable
+        returns (uint64 gasEstimate, uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate);
+}
+
+/// Adapter for Arbitrum Nitro chains
+pub struct ArbitrumAdapter {
+    /// How the estimate is obtained
+    pub strategy: ArbitrumStrategy,
+}
+
+impl ArbitrumAdapter {
+    /// Total gas (L2 execution plus L1 calldata) from `NodeInterface.gasEstimateComponents`
+    async fn gas_estimate_components(
+        ctx: &AdapterContext<'_>,
+        tx_request: &TransactionRequest,
+    ) -> Result<U256, ServiceError> {
+        let (to, contract_creation) = match tx_request.to {
+            Some(TxKind::Call(to)) => (to, false),
+            _ => (Address::ZERO, true),
+        };
+        let call = gasEstimateComponentsCall {
+            to,
+            contractCreation: contract_creation,
+            data: tx_request.input.input().cloned().unwrap_or_default(),
+        };
+
+        let mut request = TransactionRequest::default()
+            .to(NODE_INTERFACE)
+            .input(Bytes::from(call.abi_encode()).into());
+        request.from = tx_request.from;
+        request.value = tx_request.value;
+
+        let output = ctx
+            .eth_client
+            .provider
+            .call(request)
+            .await
+            .map_err(|e| ServiceError::Estimation(format!("NodeInterface call failed: {}", e)))?;
+        let components = gasEstimateComponentsCall::abi_decode_returns(&output, true)
+            .map_err(|e| ServiceError::Estimation(format!("Invalid NodeInterface response: {}", e)))?;
+        debug!(
+            "Arbitrum gas components: total {}, L1 {}, base fee {}, L1 base fee estimate {}",
+            components.gasEstimate, components.gasEstimateForL1, components.baseFee, components.l1BaseFeeEstimate
+        );
+        Ok(U256::from(components.gasEstimate))
+    }
+}
+
+impl ChainAdapter for ArbitrumAdapter {
+    fn name(&self) -> &'static str {
+        "arbitrum"
+    }
+
+    fn estimate_gas<'a>(
+        &'a self,
+        ctx: AdapterContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        Box::pin(async move {
+            match self.strategy {
+                ArbitrumStrategy::NodeInterface => Self::gas_estimate_components(&ctx, tx_request).await,
+                ArbitrumStrategy::Passthrough => {
+                    let gas = ctx
+                        .eth_client
+                        .estimate_gas(tx_request)
+                        .await
+                        .map_err(|e| ServiceError::Estimation(format!("Upstream estimation failed: {}", e)))?;
+                    Ok(U256::from(gas))
+                }
+            }
+        })
+    }
+}
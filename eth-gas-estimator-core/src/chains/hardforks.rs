@@ -0,0 +1,122 @@
+//! EVM hardfork (REVM `SpecId`) selection
+//!
+//! Simulations must run under the rules the chain actually enforces at the
+//! forked block, or e.g. a chain that hasn't activated Prague would be
+//! estimated with EIP-7702 and new gas costs. Known chains are auto-detected
+//! from their chain id and the block timestamp; anything else can be pinned.
+//!
+//! Ahead of an upgrade, simulations can instead run under the next fork's rules
+//! (the next scheduled fork of a known chain, or else the fork following the one
+//! in force), so integrators can check their transactions before activation.
+//! Only forks whose rules the bundled REVM implements can be previewed: Osaka
+//! isn't, so a chain already on Prague has nothing to preview.
+
+use revm::primitives::SpecId;
+
+/// Timestamp-activated forks of a chain, in activation order
+type Schedule = &'static [(u64, SpecId)];
+
+/// Ethereum mainnet
+const MAINNET: Schedule = &[
+    (1_681_338_455, SpecId::SHANGHAI),
+    (1_710_338_135, SpecId::CANCUN),
+    (1_746_612_311, SpecId::PRAGUE),
+];
+
+/// Sepolia testnet
+const SEPOLIA: Schedule = &[
+    (1_677_557_088, SpecId::SHANGHAI),
+    (1_706_655_072, SpecId::CANCUN),
+    (1_741_159_776, SpecId::PRAGUE),
+];
+
+/// Holesky testnet (launched post-merge)
+const HOLESKY: Schedule = &[
+    (1_696_000_704, SpecId::SHANGHAI),
+    (1_707_305_664, SpecId::CANCUN),
+    (1_740_434_112, SpecId::PRAGUE),
+];
+
+/// Hardforks in activation order, up to the latest one the bundled REVM implements
+const FORK_ORDER: &[SpecId] = &[
+    SpecId::FRONTIER,
+    SpecId::HOMESTEAD,
+    SpecId::TANGERINE,
+    SpecId::SPURIOUS_DRAGON,
+    SpecId::BYZANTIUM,
+    SpecId::PETERSBURG,
+    SpecId::ISTANBUL,
+    SpecId::BERLIN,
+    SpecId::LONDON,
+    SpecId::MERGE,
+    SpecId::SHANGHAI,
+    SpecId::CANCUN,
+    SpecId::PRAGUE,
+];
+
+/// Fork schedule and genesis spec of a known chain
+fn schedule(chain_id: u64) -> Option<(Schedule, SpecId)> {
+    match chain_id {
+        1 => Some((MAINNET, SpecId::MERGE)),
+        11_155_111 => Some((SEPOLIA, SpecId::MERGE)),
+        17_000 => Some((HOLESKY, SpecId::MERGE)),
+        _ => None,
+    }
+}
+
+/// Detect the active spec of a known chain at a block timestamp
+///
+/// # Returns
+///
+/// * `Option<SpecId>` - The active spec, or `None` for chains without a known schedule
+pub fn detect_spec_id(chain_id: u64, timestamp: u64) -> Option<SpecId> {
+    let (schedule, genesis_spec) = schedule(chain_id)?;
+    Some(
+        schedule
+            .iter()
+            .rev()
+            .find(|(activation, _)| timestamp >= *activation)
+            .map(|(_, spec)| *spec)
+            .unwrap_or(genesis_spec),
+    )
+}
+
+/// The first fork a known chain has scheduled after a block timestamp
+///
+/// # Returns
+///
+/// * `Option<SpecId>` - The scheduled spec, or `None` if the chain has no known schedule
+///   or nothing is scheduled yet
+pub fn scheduled_spec_id(chain_id: u64, timestamp: u64) -> Option<SpecId> {
+    let (schedule, _) = schedule(chain_id)?;
+    schedule
+        .iter()
+        .find(|(activation, _)| timestamp < *activation)
+        .map(|(_, spec)| *spec)
+}
+
+/// The hardfork following `spec_id`, if any is supported
+pub fn next_spec_id(spec_id: SpecId) -> Option<SpecId> {
+    let position = FORK_ORDER.iter().position(|spec| *spec == spec_id)?;
+    FORK_ORDER.get(position + 1).copied()
+}
+
+/// Parse a hardfork name (`cancun`, `prague`, ...) into a `SpecId`
+pub fn parse_spec_id(name: &str) -> Result<SpecId, String> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "frontier" => Ok(SpecId::FRONTIER),
+        "homestead" => Ok(SpecId::HOMESTEAD),
+        "tangerine" => Ok(SpecId::TANGERINE),
+        "spurious_dragon" | "spuriousdragon" => Ok(SpecId::SPURIOUS_DRAGON),
+        "byzantium" => Ok(SpecId::BYZANTIUM),
+        "petersburg" | "constantinople" => Ok(SpecId::PETERSBURG),
+        "istanbul" => Ok(SpecId::ISTANBUL),
+        "berlin" => Ok(SpecId::BERLIN),
+        "london" => Ok(SpecId::LONDON),
+        "merge" | "paris" => Ok(SpecId::MERGE),
+        "shanghai" => Ok(SpecId::SHANGHAI),
+        "cancun" => Ok(SpecId::CANCUN),
+        "prague" => Ok(SpecId::PRAGUE),
+        other => Err(format!("Unknown hardfork: {}", other)),
+    }
+}
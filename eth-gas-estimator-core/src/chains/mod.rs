@@ -0,0 +1,495 @@
+//! Chain adapters
+//!
+//! Different chain families need different estimation strategies: plain EVM
+//! chains are simulated locally with REVM, while some L2s and zk rollups have
+//! gas models that only their own nodes can compute. Each non-default adapter
+//! lives behind a cargo feature so mainnet-only deployments stay lean.
+
+use crate::{
+    backend::BackendKind,
+    error::ServiceError,
+    foundry::{estimate_gas_from_request_foundry, Fork},
+    intrinsic::transfer_gas,
+    rpc::EthereumClient,
+};
+use alloy::{eips::eip1559::BaseFeeParams, primitives::U256, rpc::types::TransactionRequest};
+use hardforks::{detect_spec_id, next_spec_id, parse_spec_id, scheduled_spec_id};
+use precompiles::{CustomPrecompile, CustomPrecompiles};
+use revm::primitives::SpecId;
+use futures::future::LocalBoxFuture;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+use tracing::debug;
+
+#[cfg(feature = "arbitrum")]
+pub mod arbitrum;
+pub mod hardforks;
+#[cfg(feature = "optimism")]
+pub mod optimism;
+pub mod precompiles;
+pub mod profile;
+#[cfg(feature = "zk")]
+pub mod zk;
+
+/// Resources available to a chain adapter while estimating
+pub struct AdapterContext<'a> {
+    /// Client for the configured upstream node
+    pub eth_client: &'a EthereumClient,
+    /// Fork of the head block the estimate is computed against
+    pub fork: &'a Fork,
+}
+
+/// Chain-specific gas estimation strategy
+pub trait ChainAdapter: Send + Sync {
+    /// Human-readable adapter name, used in logs
+    fn name(&self) -> &'static str;
+
+    /// Estimate the gas limit for a transaction on this chain
+    fn estimate_gas<'a>(
+        &'a self,
+        ctx: AdapterContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>>;
+
+    /// Upper bound of the L1 data fee of a transaction, on chains that charge it separately
+    ///
+    /// Only OP Stack chains charge it outside the gas limit (Arbitrum's L1
+    /// component is part of its gas estimate); other adapters answer `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `eth_client` - Client for the configured upstream node
+    /// * `tx_request` - The transaction, with its gas limit and fee fields set
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<U256>, ServiceError>` - The fee in wei, if the chain charges one, or
+    ///   an error if the node can't price it
+    fn l1_data_fee<'a>(
+        &'a self,
+        _eth_client: &'a EthereumClient,
+        _tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<Option<U256>, ServiceError>> {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+/// Default adapter for Ethereum and EVM-equivalent chains: local REVM simulation,
+/// skipped for plain ETH transfers (see [`crate::intrinsic`])
+pub struct EthereumAdapter;
+
+impl ChainAdapter for EthereumAdapter {
+    fn name(&self) -> &'static str {
+        "ethereum"
+    }
+
+    fn estimate_gas<'a>(
+        &'a self,
+        ctx: AdapterContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        Box::pin(async move {
+            // Plain transfers to EOAs cost their intrinsic gas, no simulation needed
+            if let Some(gas) = transfer_gas(ctx.fork, tx_request).await? {
+                debug!("Answered plain transfer from the intrinsic-gas fast path");
+                return Ok(U256::from(gas));
+            }
+            estimate_gas_from_request_foundry(ctx.fork, tx_request).await
+        })
+    }
+}
+
+/// EIP-1559 parameters the base fee of a chain is updated with
+///
+/// OP Stack chains use their post-Canyon defaults; every other chain uses Ethereum's.
+pub fn base_fee_params(chain_id: u64) -> BaseFeeParams {
+    match ChainFamily::of(chain_id) {
+        // Base Sepolia lets blocks grow further past their target
+        Some(ChainFamily::Optimism) if chain_id == 84532 => BaseFeeParams::base_sepolia_canyon(),
+        Some(ChainFamily::Optimism) => BaseFeeParams::optimism_canyon(),
+        _ => BaseFeeParams::ethereum(),
+    }
+}
+
+/// Chain families that need a dedicated adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainFamily {
+    /// OP Stack chains (OP Mainnet, Base, Zora, ...)
+    Optimism,
+    /// Arbitrum Nitro chains (Arbitrum One, Nova, ...)
+    Arbitrum,
+    /// zk rollups with non-EVM-equivalent gas accounting (zkSync Era, Polygon zkEVM)
+    Zk,
+}
+
+impl ChainFamily {
+    /// Chain ids belonging to each family
+    const CHAIN_IDS: &'static [(ChainFamily, &'static [u64])] = &[
+        (ChainFamily::Optimism, &[10, 8453, 7777777, 11155420, 84532]),
+        (ChainFamily::Arbitrum, &[42161, 42170, 421614]),
+        (ChainFamily::Zk, &[324, 300, 1101]),
+    ];
+
+    /// Look up the family of a chain id, if it needs a dedicated adapter
+    pub fn of(chain_id: u64) -> Option<Self> {
+        Self::CHAIN_IDS
+            .iter()
+            .find(|(_, ids)| ids.contains(&chain_id))
+            .map(|(family, _)| *family)
+    }
+
+    /// Cargo feature that compiles in this family's adapter
+    pub fn feature(&self) -> &'static str {
+        match self {
+            ChainFamily::Optimism => "optimism",
+            ChainFamily::Arbitrum => "arbitrum",
+            ChainFamily::Zk => "zk",
+        }
+    }
+}
+
+/// How the Arbitrum adapter obtains its estimate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArbitrumStrategy {
+    /// `NodeInterface.gasEstimateComponents` (L2 execution plus L1 calldata)
+    #[default]
+    NodeInterface,
+    /// The upstream node's `eth_estimateGas`
+    Passthrough,
+}
+
+impl FromStr for ArbitrumStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "node-interface" => Ok(ArbitrumStrategy::NodeInterface),
+            "passthrough" => Ok(ArbitrumStrategy::Passthrough),
+            other => Err(format!("Unknown Arbitrum estimation strategy: {}", other)),
+        }
+    }
+}
+
+/// Which EVM hardfork rules simulations run under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpecSelection {
+    /// Detect from the chain id and block timestamp; unknown chains use the latest spec
+    #[default]
+    Auto,
+    /// Always use the given spec
+    Fixed(SpecId),
+}
+
+impl FromStr for SpecSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(SpecSelection::Auto),
+            name => parse_spec_id(name).map(SpecSelection::Fixed),
+        }
+    }
+}
+
+/// Gas limit of the blocks simulations run in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockGasLimit {
+    /// The forked block's own gas limit
+    #[default]
+    Block,
+    /// Always use the given gas limit
+    Fixed(u64),
+}
+
+impl FromStr for BlockGasLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "block" => Ok(BlockGasLimit::Block),
+            limit => match limit.replace('_', "").parse::<u64>() {
+                Ok(0) => Err("Block gas limit must be positive".to_string()),
+                Ok(limit) => Ok(BlockGasLimit::Fixed(limit)),
+                Err(e) => Err(format!("Invalid block gas limit {}: {}", s.trim(), e)),
+            },
+        }
+    }
+}
+
+/// Whether estimates look for gas-dependent execution (see [`crate::gas_dependence`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GasDependenceDetection {
+    /// Inspect every estimate and search for a stable limit when needed
+    #[default]
+    On,
+    /// Run estimates uninspected and take the gas used as the limit
+    Off,
+}
+
+impl FromStr for GasDependenceDetection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "on" => Ok(GasDependenceDetection::On),
+            "off" => Ok(GasDependenceDetection::Off),
+            other => Err(format!("Unknown gas dependence detection {}, expected on or off", other)),
+        }
+    }
+}
+
+/// Per-chain adapter and simulation settings
+#[derive(Debug, Clone, Default)]
+pub struct ChainOptions {
+    /// Arbitrum strategy used for chains without an override
+    pub arbitrum_default: ArbitrumStrategy,
+    /// Arbitrum strategy overrides by chain id
+    pub arbitrum_overrides: HashMap<u64, ArbitrumStrategy>,
+    /// Hardfork selection used for chains without an override
+    pub spec_default: SpecSelection,
+    /// Hardfork selection overrides by chain id
+    pub spec_overrides: HashMap<u64, SpecSelection>,
+    /// Simulate under the next fork's rules ahead of its activation
+    pub upgrade_preview: bool,
+    /// Simulation backend used for chains without an override
+    pub backend_default: BackendKind,
+    /// Simulation backend overrides by chain id
+    pub backend_overrides: HashMap<u64, BackendKind>,
+    /// Block gas limit used for chains without an override
+    pub gas_limit_default: BlockGasLimit,
+    /// Block gas limit overrides by chain id
+    pub gas_limit_overrides: HashMap<u64, BlockGasLimit>,
+    /// Custom precompiles, for every chain (`None`) or for one chain id
+    pub precompiles: Vec<(Option<u64>, CustomPrecompile)>,
+    /// Gas dependence detection used for chains without an override
+    pub gas_dependence_default: GasDependenceDetection,
+    /// Gas dependence detection overrides by chain id
+    pub gas_dependence_overrides: HashMap<u64, GasDependenceDetection>,
+}
+
+impl ChainOptions {
+    /// Parse the Arbitrum strategy setting
+    ///
+    /// Accepts a single strategy applied to every Arbitrum chain
+    /// (`node-interface`), per-chain entries (`42161=node-interface,42170=passthrough`),
+    /// or both (`passthrough,42161=node-interface`).
+    pub fn with_arbitrum_strategies(mut self, setting: &str) -> Result<Self, String> {
+        parse_per_chain(setting, &mut self.arbitrum_default, &mut self.arbitrum_overrides)?;
+        Ok(self)
+    }
+
+    /// Parse the hardfork setting
+    ///
+    /// Same format as [`ChainOptions::with_arbitrum_strategies`], with `auto` or a
+    /// hardfork name as values, e.g. `auto,10=cancun`.
+    pub fn with_spec_ids(mut self, setting: &str) -> Result<Self, String> {
+        parse_per_chain(setting, &mut self.spec_default, &mut self.spec_overrides)?;
+        Ok(self)
+    }
+
+    /// Parse the simulation backend setting
+    ///
+    /// Same format as [`ChainOptions::with_arbitrum_strategies`], with `revm` or
+    /// `upstream` as values, e.g. `revm,324=upstream`.
+    pub fn with_simulation_backends(mut self, setting: &str) -> Result<Self, String> {
+        parse_per_chain(setting, &mut self.backend_default, &mut self.backend_overrides)?;
+        Ok(self)
+    }
+
+    /// Parse the simulation block gas limit setting
+    ///
+    /// Same format as [`ChainOptions::with_arbitrum_strategies`], with `block` or
+    /// a gas limit as values, e.g. `block,10=60000000`.
+    pub fn with_block_gas_limits(mut self, setting: &str) -> Result<Self, String> {
+        parse_per_chain(setting, &mut self.gas_limit_default, &mut self.gas_limit_overrides)?;
+        Ok(self)
+    }
+
+    /// Parse the gas dependence detection setting
+    ///
+    /// Same format as [`ChainOptions::with_arbitrum_strategies`], with `on` or
+    /// `off` as values, e.g. `on,137=off`.
+    pub fn with_gas_dependence_detection(mut self, setting: &str) -> Result<Self, String> {
+        parse_per_chain(setting, &mut self.gas_dependence_default, &mut self.gas_dependence_overrides)?;
+        Ok(self)
+    }
+
+    /// Parse the custom precompiles setting
+    ///
+    /// Comma-separated fixed precompiles, `address=gas` or `address=gas/output`,
+    /// each optionally prefixed by the chain id it applies to, e.g.
+    /// `0x0000000000000000000000000000000000000800=3000,1284:0x0000000000000000000000000000000000000801=2100/0x01`.
+    pub fn with_custom_precompiles(mut self, setting: &str) -> Result<Self, String> {
+        for entry in setting.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (chain_id, precompile) = match entry.split_once(':') {
+                Some((chain_id, precompile)) => {
+                    let chain_id = chain_id
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|e| format!("Invalid chain id {}: {}", chain_id, e))?;
+                    (Some(chain_id), precompile)
+                }
+                None => (None, entry),
+            };
+            self.precompiles.push((chain_id, precompile.parse()?));
+        }
+        Ok(self)
+    }
+
+    /// Register a custom precompile, for every chain or only for `chain_id`
+    pub fn with_precompile(mut self, chain_id: Option<u64>, precompile: CustomPrecompile) -> Self {
+        self.precompiles.push((chain_id, precompile));
+        self
+    }
+
+    /// Simulate under the next fork's rules ahead of its activation
+    ///
+    /// Set after the hardfork setting: a hardfork pinned globally or for a chain
+    /// must have a later supported fork to preview.
+    pub fn with_upgrade_preview(mut self, upgrade_preview: bool) -> Result<Self, String> {
+        if upgrade_preview {
+            let pinned = std::iter::once(&self.spec_default).chain(self.spec_overrides.values());
+            for selection in pinned {
+                if let SpecSelection::Fixed(spec_id) = selection {
+                    if next_spec_id(*spec_id).is_none() {
+                        return Err(format!("No fork after {:?} is supported to preview", spec_id));
+                    }
+                }
+            }
+        }
+        self.upgrade_preview = upgrade_preview;
+        Ok(self)
+    }
+
+    /// Hardfork rules for a block of a chain
+    ///
+    /// With auto-detection, chains without a bundled fork schedule use the
+    /// `detected` spec of their chain profile, or else the latest spec.
+    /// In upgrade preview, the next scheduled fork of an auto-detected chain, or
+    /// else the fork following the selected one, is used instead. When no later
+    /// fork is supported (e.g. an auto-detected chain already on Prague), the
+    /// selected one is kept.
+    pub fn spec_id(&self, chain_id: u64, timestamp: u64, detected: Option<SpecId>) -> SpecId {
+        let selection = self.spec_overrides.get(&chain_id).copied().unwrap_or(self.spec_default);
+        let active = match selection {
+            SpecSelection::Fixed(spec_id) => spec_id,
+            SpecSelection::Auto => detect_spec_id(chain_id, timestamp)
+                .or(detected)
+                .unwrap_or(SpecId::LATEST),
+        };
+        if !self.upgrade_preview {
+            return active;
+        }
+        let scheduled = match selection {
+            SpecSelection::Auto => scheduled_spec_id(chain_id, timestamp),
+            SpecSelection::Fixed(_) => None,
+        };
+        scheduled.or_else(|| next_spec_id(active)).unwrap_or(active)
+    }
+
+    /// The adapter for a chain of `family`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn ChainAdapter>, ServiceError>` - The family's adapter, configured for
+    ///   the chain, or [`ServiceError::UnsupportedChain`] if its feature was compiled out
+    fn family_adapter(&self, family: ChainFamily, chain_id: u64) -> Result<Arc<dyn ChainAdapter>, ServiceError> {
+        match family {
+            #[cfg(feature = "optimism")]
+            ChainFamily::Optimism => Ok(Arc::new(optimism::OptimismAdapter)),
+            #[cfg(feature = "arbitrum")]
+            ChainFamily::Arbitrum => Ok(Arc::new(arbitrum::ArbitrumAdapter {
+                strategy: self.arbitrum_strategy(chain_id),
+            })),
+            #[cfg(feature = "zk")]
+            ChainFamily::Zk => Ok(Arc::new(zk::ZkAdapter)),
+            #[allow(unreachable_patterns)]
+            family => Err(ServiceError::UnsupportedChain(format!(
+                "Chain {} requires the `{}` feature, which this build does not include",
+                chain_id,
+                family.feature()
+            ))),
+        }
+    }
+
+    /// Arbitrum strategy for a chain id
+    pub fn arbitrum_strategy(&self, chain_id: u64) -> ArbitrumStrategy {
+        self.arbitrum_overrides
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(self.arbitrum_default)
+    }
+
+    /// Simulation backend for a chain id
+    pub fn simulation_backend(&self, chain_id: u64) -> BackendKind {
+        self.backend_overrides
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(self.backend_default)
+    }
+
+    /// Custom precompiles of a chain
+    ///
+    /// Precompiles registered for the chain id come after those registered for
+    /// every chain, so they win when both use the same address.
+    pub fn precompiles(&self, chain_id: u64) -> CustomPrecompiles {
+        let (mut precompiles, specific): (Vec<_>, Vec<_>) = self
+            .precompiles
+            .iter()
+            .filter(|(only, _)| only.map_or(true, |only| only == chain_id))
+            .partition(|(only, _)| only.is_none());
+        precompiles.extend(specific);
+        Arc::new(precompiles.into_iter().map(|(_, precompile)| precompile.clone()).collect())
+    }
+
+    /// Gas limit of the simulated blocks of a chain, given the forked block's own limit
+    pub fn block_gas_limit(&self, chain_id: u64, block_gas_limit: u64) -> u64 {
+        match self.gas_limit_overrides.get(&chain_id).copied().unwrap_or(self.gas_limit_default) {
+            BlockGasLimit::Block => block_gas_limit,
+            BlockGasLimit::Fixed(limit) => limit,
+        }
+    }
+
+    /// Whether estimates on a chain look for gas-dependent execution
+    pub fn gas_dependence_detection(&self, chain_id: u64) -> bool {
+        let detection = self
+            .gas_dependence_overrides
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(self.gas_dependence_default);
+        detection == GasDependenceDetection::On
+    }
+}
+
+/// Parse a `default,chainId=value,...` setting into a default and per-chain overrides
+fn parse_per_chain<T: FromStr<Err = String>>(
+    setting: &str,
+    default: &mut T,
+    overrides: &mut HashMap<u64, T>,
+) -> Result<(), String> {
+    for entry in setting.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        match entry.split_once('=') {
+            Some((chain_id, value)) => {
+                let chain_id = chain_id
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|e| format!("Invalid chain id {}: {}", chain_id, e))?;
+                overrides.insert(chain_id, value.parse()?);
+            }
+            None => *default = entry.parse()?,
+        }
+    }
+    Ok(())
+}
+
+/// Select the adapter for a chain id
+///
+/// Chains outside every known family use the default [`EthereumAdapter`].
+/// Chains that belong to a family whose feature was compiled out are rejected
+/// with [`ServiceError::UnsupportedChain`] instead of being silently simulated
+/// with the wrong gas model.
+pub fn adapter_for_chain(chain_id: u64, options: &ChainOptions) -> Result<Arc<dyn ChainAdapter>, ServiceError> {
+    match ChainFamily::of(chain_id) {
+        None => Ok(Arc::new(EthereumAdapter)),
+        Some(family) => options.family_adapter(family, chain_id),
+    }
+}
@@ -0,0 +1,96 @@
+//! OP Stack adapter
+//!
+//! On OP Stack chains `eth_estimateGas` covers L2 execution only; the L1 data
+//! fee is charged separately from the sender's balance. Execution is plain EVM,
+//! so the estimate itself comes from local simulation. The adapter additionally
+//! exposes the L1 fee upper bound from the `GasPriceOracle` predeploy.
+
+use super::{AdapterContext, ChainAdapter};
+use crate::{
+    error::ServiceError,
+    foundry::estimate_gas_from_request_foundry,
+    rpc::{upstream_failure, EthereumClient},
+};
+use alloy::{
+    consensus::SignableTransaction,
+    primitives::{address, Address, Bytes, TxKind, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use futures::future::LocalBoxFuture;
+
+/// Address of the `GasPriceOracle` predeploy on every OP Stack chain
+pub const GAS_PRICE_ORACLE: Address = address!("420000000000000000000000000000000000000F");
+
+sol! {
+    /// Subset of the OP Stack `GasPriceOracle` predeploy
+    function getL1FeeUpperBound(uint256 unsignedTxSize) external view returns (uint256);
+}
+
+/// Adapter for OP Stack chains
+pub struct OptimismAdapter;
+
+impl OptimismAdapter {
+    /// Upper bound of the L1 data fee (in wei) for a transaction of the given unsigned size
+    pub async fn l1_fee_upper_bound(
+        eth_client: &EthereumClient,
+        unsigned_tx_size: usize,
+    ) -> Result<U256, ServiceError> {
+        let call = getL1FeeUpperBoundCall {
+            unsignedTxSize: U256::from(unsigned_tx_size),
+        };
+        let request = TransactionRequest::default()
+            .to(GAS_PRICE_ORACLE)
+            .input(Bytes::from(call.abi_encode()).into());
+
+        let output = eth_client
+            .provider
+            .call(request)
+            .await
+            .map_err(|e| upstream_failure("GasPriceOracle call failed", e))?;
+        getL1FeeUpperBoundCall::abi_decode_returns(&output, true)
+            .map(|decoded| decoded._0)
+            .map_err(|e| ServiceError::Estimation(format!("Invalid GasPriceOracle response: {}", e)))
+    }
+}
+
+/// Size of a transaction's unsigned EIP-2718 encoding, as `getL1FeeUpperBound` expects it
+///
+/// The request must carry its gas limit and fee fields; a missing nonce is
+/// encoded as zero, which is at most a few bytes smaller than the real one.
+pub fn unsigned_tx_size(tx_request: &TransactionRequest) -> Result<usize, ServiceError> {
+    let mut tx_request = tx_request.clone();
+    tx_request.nonce = Some(tx_request.nonce.unwrap_or_default());
+    tx_request.to = Some(tx_request.to.unwrap_or(TxKind::Create));
+    let tx = tx_request
+        .build_typed_tx()
+        .map_err(|_| ServiceError::Estimation("Transaction can't be encoded for the L1 fee".to_string()))?;
+    Ok(tx.encoded_for_signing().len())
+}
+
+impl ChainAdapter for OptimismAdapter {
+    fn name(&self) -> &'static str {
+        "optimism"
+    }
+
+    fn estimate_gas<'a>(
+        &'a self,
+        ctx: AdapterContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        Box::pin(estimate_gas_from_request_foundry(ctx.fork, tx_request))
+    }
+
+    fn l1_data_fee<'a>(
+        &'a self,
+        eth_client: &'a EthereumClient,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<Option<U256>, ServiceError>> {
+        Box::pin(async move {
+            let size = unsigned_tx_size(tx_request)?;
+            Self::l1_fee_upper_bound(eth_client, size).await.map(Some)
+        })
+    }
+}
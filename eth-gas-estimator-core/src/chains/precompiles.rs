@@ -0,0 +1,175 @@
+//! Custom precompiles
+//!
+//! Some EVM chains ship precompiles mainnet doesn't have (Moonbeam's
+//! Substrate bridges at `0x...0800`, Polygon zk bridge helpers, ...). REVM
+//! only knows the Ethereum set, so a call to one of them runs as a call to an
+//! empty account, and a contract checking the returned data reverts. Operators
+//! can register extra precompiles, for every chain or for one chain id:
+//!
+//! * with a fixed behavior, from configuration: every call costs a fixed amount
+//!   of gas and returns fixed data
+//! * with a handler, from code: [`CustomPrecompile::with_handler`] computes the
+//!   gas and output from the input, like a native precompile
+//!
+//! A custom precompile at the address of a built-in one replaces it.
+
+use alloy::primitives::{Address, Bytes};
+use revm::{
+    handler::register::HandleRegisterBox,
+    primitives::{
+        Env, Precompile, PrecompileError, PrecompileErrors, PrecompileOutput, PrecompileResult, StatefulPrecompile,
+        StatefulPrecompileArc,
+    },
+    ContextPrecompile, Database,
+};
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// Precompiles added to the EVM of a chain
+pub type CustomPrecompiles = Arc<Vec<CustomPrecompile>>;
+
+/// A precompile added to the EVM's built-in ones
+#[derive(Clone)]
+pub struct CustomPrecompile {
+    /// Address the precompile answers at
+    pub address: Address,
+    /// What the precompile does
+    behavior: Behavior,
+}
+
+/// How a custom precompile answers its calls
+#[derive(Clone)]
+enum Behavior {
+    /// Fixed gas and output
+    Fixed { gas: u64, output: Bytes },
+    /// Gas and output computed by a handler
+    Handler(StatefulPrecompileArc),
+}
+
+impl CustomPrecompile {
+    /// A precompile charging `gas` and returning `output` on every call
+    ///
+    /// Calls given less than `gas` run out of gas.
+    pub fn fixed(address: Address, gas: u64, output: Bytes) -> Self {
+        Self {
+            address,
+            behavior: Behavior::Fixed { gas, output },
+        }
+    }
+
+    /// A precompile computing its gas and output with `handler`
+    ///
+    /// The handler gets the call's input and gas limit, and returns the gas used
+    /// and the output, or a [`PrecompileErrors`] that fails the call.
+    pub fn with_handler<F>(address: Address, handler: F) -> Self
+    where
+        F: Fn(&Bytes, u64) -> PrecompileResult + Send + Sync + 'static,
+    {
+        Self {
+            address,
+            behavior: Behavior::Handler(Arc::new(HandlerPrecompile(handler))),
+        }
+    }
+
+    /// The precompile, as the EVM runs it
+    fn precompile(&self) -> Precompile {
+        match &self.behavior {
+            Behavior::Fixed { gas, output } => Precompile::Stateful(Arc::new(HandlerPrecompile({
+                let (gas, output) = (*gas, output.clone());
+                move |_: &Bytes, gas_limit: u64| match gas > gas_limit {
+                    true => Err(PrecompileErrors::Error(PrecompileError::OutOfGas)),
+                    false => Ok(PrecompileOutput::new(gas, output.clone())),
+                }
+            }))),
+            Behavior::Handler(handler) => Precompile::Stateful(handler.clone()),
+        }
+    }
+}
+
+impl fmt::Debug for CustomPrecompile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.behavior {
+            Behavior::Fixed { gas, output } => f
+                .debug_struct("CustomPrecompile")
+                .field("address", &self.address)
+                .field("gas", gas)
+                .field("output", output)
+                .finish(),
+            Behavior::Handler(_) => f
+                .debug_struct("CustomPrecompile")
+                .field("address", &self.address)
+                .field("handler", &"..")
+                .finish(),
+        }
+    }
+}
+
+impl FromStr for CustomPrecompile {
+    type Err = String;
+
+    /// Parse a fixed precompile: `address=gas`, or `address=gas/output` with hex output
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, behavior) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid precompile {}: expected address=gas[/output]", s.trim()))?;
+        let address = address
+            .trim()
+            .parse::<Address>()
+            .map_err(|e| format!("Invalid precompile address {}: {}", address.trim(), e))?;
+        let (gas, output) = behavior.split_once('/').unwrap_or((behavior, ""));
+        let gas = gas
+            .trim()
+            .replace('_', "")
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid precompile gas {}: {}", gas.trim(), e))?;
+        let output = match output.trim() {
+            "" => Bytes::new(),
+            output => output
+                .parse::<Bytes>()
+                .map_err(|e| format!("Invalid precompile output {}: {}", output, e))?,
+        };
+        Ok(Self::fixed(address, gas, output))
+    }
+}
+
+/// A closure run as a stateful precompile
+struct HandlerPrecompile<F>(F);
+
+impl<F> StatefulPrecompile for HandlerPrecompile<F>
+where
+    F: Fn(&Bytes, u64) -> PrecompileResult + Send + Sync,
+{
+    fn call(&self, bytes: &Bytes, gas_limit: u64, _env: &Env) -> PrecompileResult {
+        (self.0)(bytes, gas_limit)
+    }
+}
+
+/// Handler register adding custom precompiles to an EVM
+///
+/// # Arguments
+///
+/// * `precompiles` - The precompiles of the simulated chain
+///
+/// # Returns
+///
+/// * `HandleRegisterBox` - The register, to pass to `append_handler_register_box`
+///   once the EVM's database and external context are set
+pub fn precompiles_register<'a, EXT: 'a, DB: Database + 'a>(
+    precompiles: CustomPrecompiles,
+) -> HandleRegisterBox<'a, EXT, DB> {
+    Box::new(move |handler| {
+        if precompiles.is_empty() {
+            return;
+        }
+        let precompiles = precompiles.clone();
+        let load_builtins = handler.pre_execution.load_precompiles.clone();
+        handler.pre_execution.load_precompiles = Arc::new(move || {
+            let mut loaded = load_builtins();
+            loaded.extend(
+                precompiles
+                    .iter()
+                    .map(|precompile| (precompile.address, ContextPrecompile::Ordinary(precompile.precompile()))),
+            );
+            loaded
+        });
+    })
+}
@@ -0,0 +1,223 @@
+//! Chain profile detection
+//!
+//! The profile describes the upstream chain as far as simulations care: its
+//! chain id, the hardfork in force and the blob fee parameters. It is detected
+//! from the upstream at startup and refreshed periodically, so common networks
+//! need no manual configuration and upgrades are picked up without a restart:
+//!
+//! * chains with a bundled fork schedule (see [`super::hardforks`]) use it
+//! * otherwise the node's `eth_config` (EIP-7910) describes the active fork
+//!   and its blob schedule, where supported
+//! * otherwise the fork is inferred from the fields present in the head block
+//!   header (e.g. `requestsHash` only exists from Prague on); header fields only
+//!   show the forks a chain has at least, so this is reported but simulations
+//!   keep the chain options' rules (the latest spec, unless configured)
+//!
+//! A hardfork pinned with `EVM_SPEC` still takes precedence.
+
+use super::{hardforks::detect_spec_id, ChainFamily};
+use crate::{error::ServiceError, rpc::upstream_failure};
+use alloy::{
+    consensus::BlockHeader,
+    eips::BlockId,
+    network::AnyNetwork,
+    providers::{DynProvider, Provider},
+};
+use revm::primitives::SpecId;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Names of well-known chains
+const KNOWN_CHAINS: &[(u64, &str)] = &[
+    (1, "Ethereum Mainnet"),
+    (11_155_111, "Sepolia"),
+    (17_000, "Holesky"),
+    (560_048, "Hoodi"),
+    (10, "OP Mainnet"),
+    (8453, "Base"),
+    (42161, "Arbitrum One"),
+    (42170, "Arbitrum Nova"),
+    (324, "zkSync Era"),
+    (1101, "Polygon zkEVM"),
+    (137, "Polygon PoS"),
+    (100, "Gnosis"),
+    (31_337, "Anvil"),
+];
+
+/// Blob fee parameters (EIP-4844, EIP-7691)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobParams {
+    /// Target blobs per block
+    pub target: u64,
+    /// Maximum blobs per block
+    pub max: u64,
+    /// Denominator of the blob base fee update
+    pub update_fraction: u64,
+}
+
+impl BlobParams {
+    /// Cancun blob parameters
+    pub const CANCUN: BlobParams = BlobParams { target: 3, max: 6, update_fraction: 3_338_477 };
+
+    /// Prague blob parameters
+    pub const PRAGUE: BlobParams = BlobParams { target: 6, max: 9, update_fraction: 5_007_716 };
+
+    /// Default parameters of a hardfork, `None` before blobs existed
+    pub fn for_spec(spec_id: SpecId) -> Option<BlobParams> {
+        if spec_id.is_enabled_in(SpecId::PRAGUE) {
+            Some(BlobParams::PRAGUE)
+        } else if spec_id.is_enabled_in(SpecId::CANCUN) {
+            Some(BlobParams::CANCUN)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where the hardfork of a profile was determined from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpecSource {
+    /// The bundled fork schedule of a known chain
+    Bundled,
+    /// The node's `eth_config`
+    EthConfig,
+    /// Fields of the head block header
+    Header,
+}
+
+/// What simulations need to know about the upstream chain
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainProfile {
+    /// Chain id
+    pub chain_id: u64,
+    /// Name of a well-known chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'static str>,
+    /// Chain family needing a dedicated adapter, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<&'static str>,
+    /// Hardfork in force at detection
+    #[serde(serialize_with = "serialize_spec_id")]
+    pub spec_id: SpecId,
+    /// Where `specId` was determined from
+    pub spec_source: SpecSource,
+    /// Blob fee parameters, if the chain has blobs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_params: Option<BlobParams>,
+    /// Unix timestamp (seconds) of the detection
+    pub detected_at: u64,
+}
+
+impl ChainProfile {
+    /// Hardfork simulations follow, `None` if it was only inferred from header fields
+    pub fn simulation_spec_id(&self) -> Option<SpecId> {
+        (self.spec_source != SpecSource::Header).then_some(self.spec_id)
+    }
+}
+
+/// Serialize a `SpecId` as its lowercase hardfork name
+fn serialize_spec_id<S: serde::Serializer>(spec_id: &SpecId, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:?}", spec_id).to_ascii_lowercase())
+}
+
+/// Detect the profile of the chain behind `provider`
+///
+/// # Returns
+///
+/// * `Result<ChainProfile, ServiceError>` - The profile, or an error if the chain id or
+///   head block can't be fetched
+pub async fn detect_profile(provider: &DynProvider<AnyNetwork>) -> Result<ChainProfile, ServiceError> {
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .map_err(|e| upstream_failure("Failed to get chain id", e))?;
+    let head = provider
+        .get_block(BlockId::latest())
+        .await
+        .map_err(|e| upstream_failure("Failed to get the latest block", e))?
+        .ok_or_else(|| ServiceError::RPCConnection("Failed to get the latest block".to_string()))?;
+
+    let eth_config = match provider
+        .raw_request::<(), serde_json::Value>("eth_config".into(), ())
+        .await
+    {
+        Ok(config) => Some(config),
+        Err(e) => {
+            debug!("Upstream doesn't support eth_config: {}", e);
+            None
+        }
+    };
+    let current = eth_config.as_ref().map(|config| &config["current"]);
+
+    let (spec_id, spec_source) = match detect_spec_id(chain_id, head.header.timestamp()) {
+        Some(spec_id) => (spec_id, SpecSource::Bundled),
+        None => match current.and_then(eth_config_spec_id) {
+            Some(spec_id) => (spec_id, SpecSource::EthConfig),
+            None => (header_spec_id(&head.header), SpecSource::Header),
+        },
+    };
+    // Header-inferred forks aren't simulated, so neither are their blob parameters
+    let blob_params = current
+        .and_then(|current| serde_json::from_value::<EthConfigBlobSchedule>(current["blobSchedule"].clone()).ok())
+        .map(|schedule| BlobParams {
+            target: schedule.target,
+            max: schedule.max,
+            update_fraction: schedule.base_fee_update_fraction,
+        })
+        .or_else(|| BlobParams::for_spec(spec_id).filter(|_| spec_source != SpecSource::Header));
+
+    Ok(ChainProfile {
+        chain_id,
+        name: KNOWN_CHAINS.iter().find(|(id, _)| *id == chain_id).map(|(_, name)| *name),
+        family: ChainFamily::of(chain_id).map(|family| family.feature()),
+        spec_id,
+        spec_source,
+        blob_params,
+        detected_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    })
+}
+
+/// Blob schedule of an `eth_config` fork
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EthConfigBlobSchedule {
+    target: u64,
+    max: u64,
+    base_fee_update_fraction: u64,
+}
+
+/// Hardfork of an `eth_config` fork, recognized by the precompiles it introduced
+fn eth_config_spec_id(fork: &serde_json::Value) -> Option<SpecId> {
+    let precompiles = fork["precompiles"].as_object()?;
+    let has = |name: &str| precompiles.contains_key(name);
+    if has("P256VERIFY") {
+        Some(SpecId::OSAKA)
+    } else if has("BLS12_G1ADD") {
+        Some(SpecId::PRAGUE)
+    } else if has("KZG_POINT_EVALUATION") {
+        Some(SpecId::CANCUN)
+    } else {
+        None
+    }
+}
+
+/// Latest hardfork whose header fields are present in `header`
+fn header_spec_id<H: BlockHeader>(header: &H) -> SpecId {
+    if header.requests_hash().is_some() {
+        SpecId::PRAGUE
+    } else if header.blob_gas_used().is_some() {
+        SpecId::CANCUN
+    } else if header.withdrawals_root().is_some() {
+        SpecId::SHANGHAI
+    } else if header.base_fee_per_gas().is_some() && header.difficulty().is_zero() {
+        SpecId::MERGE
+    } else if header.base_fee_per_gas().is_some() {
+        SpecId::LONDON
+    } else {
+        SpecId::BERLIN
+    }
+}
@@ -0,0 +1,34 @@
+//! zk rollup adapter
+//!
+//! zkSync Era and Polygon zkEVM meter gas differently from the L1 EVM (custom
+//! opcode pricing, pubdata costs, native account abstraction), so REVM results
+//! would be misleading. The estimate is delegated to the upstream node.
+
+use super::{AdapterContext, ChainAdapter};
+use crate::error::ServiceError;
+use alloy::{primitives::U256, rpc::types::TransactionRequest};
+use futures::future::LocalBoxFuture;
+
+/// Adapter for zk rollups with non-EVM-equivalent gas accounting
+pub struct ZkAdapter;
+
+impl ChainAdapter for ZkAdapter {
+    fn name(&self) -> &'static str {
+        "zk"
+    }
+
+    fn estimate_gas<'a>(
+        &'a self,
+        ctx: AdapterContext<'a>,
+        tx_request: &'a TransactionRequest,
+    ) -> LocalBoxFuture<'a, Result<U256, ServiceError>> {
+        Box::pin(async move {
+            let gas = ctx
+                .eth_client
+                .estimate_gas(tx_request)
+                .await
+                .map_err(|e| ServiceError::Estimation(format!("Upstream estimation failed: {}", e)))?;
+            Ok(U256::from(gas))
+        })
+    }
+}
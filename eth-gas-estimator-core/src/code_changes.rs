@@ -0,0 +1,132 @@
+//! Self-destruct and code change detection
+//!
+//! An estimate only holds for the code it ran against. When a simulation
+//! destroys a contract or changes the code at an address, estimates for later
+//! interactions with that address may no longer be valid, so the verbose
+//! simulation report flags them in its `warnings` array:
+//!
+//! * `selfDestruct` - a contract executed `SELFDESTRUCT` and is destroyed. Since
+//!   Cancun (EIP-6780) that only happens to contracts created in the same
+//!   transaction; others merely send their balance away and keep their code
+//! * `codeChanged` - a contract was deployed (e.g. a CREATE2 redeploy) at an
+//!   address the transaction had already interacted with, or an EIP-7702
+//!   authorization changes the delegation of an account
+
+use crate::{
+    error::ServiceError,
+    foundry::{execute_on_fork, tx_env_for_fork, Fork, SimulationMode},
+    models::simulation::{SimulationWarning, WarningKind},
+};
+use alloy::{
+    primitives::{Address, B256},
+    rpc::types::TransactionRequest,
+};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    primitives::{SpecId, U256},
+    Database, EvmContext, Inspector,
+};
+use std::collections::BTreeSet;
+use tracing::debug;
+
+/// Inspector recording self-destructs and deployments at addresses already interacted with
+#[derive(Debug, Default)]
+pub struct CodeChangeTracer {
+    /// Addresses called or queried so far
+    touched: BTreeSet<Address>,
+    /// Contracts that executed `SELFDESTRUCT`
+    self_destructed: BTreeSet<Address>,
+    /// Contracts created during the execution
+    created: BTreeSet<Address>,
+    /// Addresses that received new code after being interacted with
+    redeployed: BTreeSet<Address>,
+}
+
+impl<DB: Database> Inspector<DB> for CodeChangeTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Ok(top) = interp.stack().peek(0) else {
+            return;
+        };
+        if matches!(
+            interp.current_opcode(),
+            opcode::BALANCE | opcode::EXTCODESIZE | opcode::EXTCODECOPY | opcode::EXTCODEHASH
+        ) {
+            self.touched.insert(Address::from_word(B256::from(top)));
+        }
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.touched.insert(inputs.target_address);
+        self.touched.insert(inputs.bytecode_address);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let (Some(address), true) = (outcome.address, outcome.result.is_ok()) {
+            if self.touched.contains(&address) {
+                self.redeployed.insert(address);
+            }
+            self.touched.insert(address);
+            self.created.insert(address);
+        }
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, _target: Address, _value: U256) {
+        self.self_destructed.insert(contract);
+    }
+}
+
+/// Detect the self-destructs and code changes of a transaction on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request
+///
+/// # Returns
+///
+/// * `Result<Vec<SimulationWarning>, ServiceError>` - One warning per affected address, or
+///   an error if the transaction fails validation
+pub async fn code_change_warnings_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+) -> Result<Vec<SimulationWarning>, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, SimulationMode::Estimate)?;
+    let (_, tracer) =
+        execute_on_fork(fork, tx_env, SimulationMode::Estimate, Some(CodeChangeTracer::default())).await?;
+    let tracer = tracer.unwrap_or_default();
+
+    // Authorizations take effect before execution, so they are read from the request
+    let mut code_changed = tracer.redeployed;
+    if fork.spec_id.is_enabled_in(SpecId::PRAGUE) {
+        let authorizations = tx_request.authorization_list.as_deref().unwrap_or_default();
+        code_changed.extend(authorizations.iter().filter_map(|auth| auth.recover_authority().ok()));
+    }
+
+    // Since Cancun (EIP-6780), only contracts created by the transaction itself are destroyed
+    let destroys_all = !fork.spec_id.is_enabled_in(SpecId::CANCUN);
+    let created = tracer.created;
+    let mut warnings: Vec<SimulationWarning> = tracer
+        .self_destructed
+        .into_iter()
+        .filter(|address| destroys_all || created.contains(address))
+        .map(|address| SimulationWarning {
+            kind: WarningKind::SelfDestruct,
+            address,
+            message: "Contract self-destructs; estimates for later interactions may be invalid".to_string(),
+        })
+        .collect();
+    warnings.extend(code_changed.into_iter().map(|address| SimulationWarning {
+        kind: WarningKind::CodeChanged,
+        address,
+        message: "Code at this address changes; estimates for later interactions may be invalid".to_string(),
+    }));
+    debug!("Simulation raised {} code change warnings", warnings.len());
+    Ok(warnings)
+}
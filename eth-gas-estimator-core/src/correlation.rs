@@ -0,0 +1,69 @@
+//! Request correlation ids
+//!
+//! Every request served gets a correlation id: the caller's `X-Request-Id`, or
+//! a generated one. Like the request deadline (see [`crate::timeouts`]), it is
+//! carried through the request's task, so upstream RPC calls made for the
+//! request send it in their own `X-Request-Id` header and error responses can
+//! quote it. A failed estimation can then be followed from the client through
+//! the estimator's logs to the upstream node's.
+//!
+//! Work a request starts on another task or thread, such as the storage
+//! batches of its simulation, takes the id along explicitly. Single account
+//! and storage fetches of the simulation forks are shared between requests and
+//! run on the fork's own task, so they carry no id.
+
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+/// Header carrying the correlation id, on requests and responses
+pub const CORRELATION_HEADER: &str = "x-request-id";
+
+/// Longest correlation id accepted from a caller
+pub const MAX_CORRELATION_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    /// Correlation id of the request being served by the current task
+    static CORRELATION_ID: String;
+}
+
+/// Run a future on behalf of the request with the given correlation id
+///
+/// # Arguments
+///
+/// * `correlation_id` - Correlation id of the request
+/// * `future` - The future to run
+pub async fn with_correlation_id<F: Future>(correlation_id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id, future).await
+}
+
+/// Correlation id of the request being served by the current task, if any
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(|correlation_id| correlation_id.clone()).ok()
+}
+
+/// Whether a caller-supplied correlation id can be used as is
+///
+/// Ids are echoed in headers and logs, so only short printable ASCII ids are accepted.
+pub fn is_valid_correlation_id(correlation_id: &str) -> bool {
+    !correlation_id.is_empty()
+        && correlation_id.len() <= MAX_CORRELATION_ID_LEN
+        && correlation_id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Generate a correlation id, unique across processes with overwhelming probability
+///
+/// Ids are a random per-process prefix followed by a sequence number.
+pub fn generate_correlation_id() -> String {
+    static PREFIX: OnceLock<u64> = OnceLock::new();
+    static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    let prefix = PREFIX.get_or_init(|| RandomState::new().build_hasher().finish());
+    format!("{:016x}-{:x}", prefix, SEQUENCE.fetch_add(1, Ordering::Relaxed))
+}
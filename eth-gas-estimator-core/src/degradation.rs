@@ -0,0 +1,224 @@
+//! Graceful degradation under upstream outages
+//!
+//! When the fork backend can't reach the upstream node (the head block, a
+//! nonce or account state can't be fetched), `eth_estimateGas` walks a
+//! configurable ladder instead of failing outright. Each step answers the
+//! request if it can, otherwise the next one is tried:
+//!
+//! * `staleCache` - the last estimate simulated for the same request, reported
+//!   with source `stale` and the block it was simulated at
+//! * `secondary` - the secondary provider's own `eth_estimateGas`, reported
+//!   with source `secondary`
+//!
+//! Once the ladder is exhausted (immediately, if it is empty) the request fails
+//! with `503 Service Unavailable` and a `Retry-After` header.
+
+use crate::{
+    cache::request_key,
+    error::ServiceError,
+    estimator::{EstimateOptions, GasEstimate},
+    foundry::BlockTarget,
+    rpc::EthereumClient,
+};
+use alloy::{primitives::B256, rpc::types::TransactionRequest};
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Default delay clients are asked to wait once the ladder is exhausted
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Default age after which a remembered estimate is no longer served
+pub const DEFAULT_STALE_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// A step of the degradation ladder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DegradationStep {
+    /// Serve the last estimate simulated for the same request
+    StaleCache,
+    /// Pass the request through to the secondary provider
+    Secondary,
+}
+
+impl FromStr for DegradationStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "staleCache" => Ok(DegradationStep::StaleCache),
+            "secondary" => Ok(DegradationStep::Secondary),
+            other => Err(format!("Unknown degradation step: {}", other)),
+        }
+    }
+}
+
+/// Last simulated estimate of a request
+struct RememberedEstimate {
+    estimate: GasEstimate,
+    recorded_at: Instant,
+}
+
+/// What the estimator does when the upstream node is unreachable
+pub struct DegradationPolicy {
+    /// Steps tried in order
+    steps: Vec<DegradationStep>,
+    /// Provider the `secondary` step passes requests to
+    secondary: Option<Arc<EthereumClient>>,
+    /// Delay announced in `Retry-After` once every step failed
+    retry_after: Duration,
+    /// Oldest remembered estimate still served
+    stale_max_age: Duration,
+    /// Last estimate of recent requests, kept only with the `staleCache` step
+    remembered: Option<Mutex<LruCache<B256, RememberedEstimate>>>,
+}
+
+impl Default for DegradationPolicy {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            secondary: None,
+            retry_after: DEFAULT_RETRY_AFTER,
+            stale_max_age: DEFAULT_STALE_MAX_AGE,
+            remembered: None,
+        }
+    }
+}
+
+impl DegradationPolicy {
+    /// Build a policy from the configured ladder
+    ///
+    /// # Arguments
+    ///
+    /// * `ladder` - Comma-separated steps, e.g. `staleCache,secondary`; empty to fail right away
+    /// * `secondary` - Provider of the `secondary` step, required when the step is listed
+    /// * `retry_after` - Delay announced once every step failed
+    /// * `stale_max_age` - Oldest remembered estimate still served
+    /// * `capacity` - Number of requests whose last estimate is remembered
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, ServiceError>` - The policy, or a `ServiceError::Configuration`
+    ///   describing the invalid ladder
+    pub fn new(
+        ladder: &str,
+        secondary: Option<Arc<EthereumClient>>,
+        retry_after: Duration,
+        stale_max_age: Duration,
+        capacity: usize,
+    ) -> Result<Self, ServiceError> {
+        let invalid = |reason: String| ServiceError::Configuration(format!("Invalid degradation ladder: {}", reason));
+        let steps = ladder
+            .split(',')
+            .filter(|step| !step.trim().is_empty())
+            .map(DegradationStep::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        if steps.contains(&DegradationStep::Secondary) && secondary.is_none() {
+            return Err(invalid("the secondary step needs a secondary RPC URL".to_string()));
+        }
+        // A step that can never answer would silently turn into a 503
+        let remembered = match (steps.contains(&DegradationStep::StaleCache), NonZeroUsize::new(capacity)) {
+            (true, Some(capacity)) => Some(Mutex::new(LruCache::new(capacity))),
+            (true, None) => {
+                return Err(invalid("the staleCache step needs room for at least one remembered estimate".to_string()))
+            }
+            (false, _) => None,
+        };
+        Ok(Self { steps, secondary, retry_after, stale_max_age, remembered })
+    }
+
+    /// Steps tried in order
+    pub fn steps(&self) -> &[DegradationStep] {
+        &self.steps
+    }
+
+    /// Provider of the `secondary` step, if configured
+    pub fn secondary(&self) -> Option<&EthereumClient> {
+        self.secondary.as_deref()
+    }
+
+    /// Delay announced once every step failed
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    /// Whether some step may still answer estimates while the upstream node is unreachable
+    ///
+    /// True with a secondary provider, or with a remembered estimate recent enough to be served.
+    pub fn can_serve(&self) -> bool {
+        self.steps.iter().any(|step| match step {
+            DegradationStep::StaleCache => self.remembered.as_ref().is_some_and(|remembered| {
+                let remembered = remembered.lock().unwrap_or_else(|e| e.into_inner());
+                remembered.iter().any(|(_, entry)| entry.recorded_at.elapsed() <= self.stale_max_age)
+            }),
+            DegradationStep::Secondary => self.secondary.is_some(),
+        })
+    }
+
+    /// Remember a simulated estimate, for plain head estimates only
+    pub fn remember(&self, tx_request: &TransactionRequest, options: &EstimateOptions, estimate: &GasEstimate) {
+        let Some(remembered) = self.remembered.as_ref().filter(|_| is_plain(options)) else {
+            return;
+        };
+        let Some(key) = request_key(tx_request) else {
+            return;
+        };
+        let entry = RememberedEstimate { estimate: *estimate, recorded_at: Instant::now() };
+        remembered.lock().unwrap_or_else(|e| e.into_inner()).put(key, entry);
+    }
+
+    /// The last estimate of a request, if recent enough to be served
+    pub fn stale(&self, tx_request: &TransactionRequest, options: &EstimateOptions) -> Option<GasEstimate> {
+        let remembered = self.remembered.as_ref().filter(|_| is_plain(options))?;
+        let mut remembered = remembered.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = remembered.get(&request_key(tx_request)?)?;
+        if entry.recorded_at.elapsed() > self.stale_max_age {
+            debug!("Remembered estimate is {:?} old, too stale to serve", entry.recorded_at.elapsed());
+            return None;
+        }
+        Some(entry.estimate)
+    }
+
+    /// Forget the estimates simulated at blocks a reorg removed from the canonical chain
+    pub fn purge(&self, orphaned: &[B256]) {
+        let Some(remembered) = self.remembered.as_ref() else {
+            return;
+        };
+        let mut remembered = remembered.lock().unwrap_or_else(|e| e.into_inner());
+        let keys: Vec<B256> = remembered
+            .iter()
+            .filter(|(_, entry)| orphaned.contains(&entry.estimate.block_hash))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &keys {
+            remembered.pop(key);
+        }
+        if !keys.is_empty() {
+            debug!("Forgot {} estimates from orphaned blocks", keys.len());
+        }
+    }
+}
+
+/// Whether an estimation failed because the upstream node (or the fork's state fetches from it) is unreachable
+pub fn is_outage(e: &eyre::Report) -> bool {
+    matches!(
+        e.downcast_ref::<ServiceError>(),
+        Some(ServiceError::RPCConnection(_) | ServiceError::Database(_))
+    )
+}
+
+/// Remembered estimates are keyed by the request alone, so only estimates at
+/// the head without pins, code overrides, a headroom override or unchecked
+/// authorizations are interchangeable
+fn is_plain(options: &EstimateOptions) -> bool {
+    options.block == BlockTarget::Latest
+        && options.pins.is_empty()
+        && options.code_overrides.is_empty()
+        && options.headroom.is_none()
+        && !options.skip_authorization_check
+}
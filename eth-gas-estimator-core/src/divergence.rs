@@ -0,0 +1,286 @@
+//! Shadow comparisons against the upstream node and divergence alarms
+//!
+//! A sample of locally simulated estimates is also sent, in the background, to
+//! the upstream node's own `eth_estimateGas`. When a sustained share of these
+//! comparisons diverges beyond a threshold, an alarm fires: a warning is
+//! logged, the alarm shows up at `/admin/divergence`, and a webhook is notified
+//! if configured. A notification is sent again once the alarm resolves.
+//!
+//! Divergence right after a network upgrade usually means a hardfork rule or a
+//! chain adapter is out of date, so this catches such bugs before users do.
+
+use crate::{
+    correlation::{correlation_id, with_correlation_id},
+    models::divergence::{Comparison, DivergenceAlarm, DivergenceStatus},
+    rpc::EthereumClient,
+};
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, U256},
+    rpc::types::TransactionRequest,
+};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info, warn};
+
+/// Default divergence (in percent of the upstream estimate) counted as divergent
+pub const DEFAULT_DIVERGENCE_THRESHOLD_PERCENT: f64 = 10.0;
+
+/// Default window over which the share of divergent comparisons is measured
+pub const DEFAULT_DIVERGENCE_WINDOW: Duration = Duration::from_secs(300);
+
+/// Default share of divergent comparisons in the window that fires the alarm
+pub const DEFAULT_DIVERGENCE_ALARM_RATIO: f64 = 0.5;
+
+/// Comparisons needed in the window before the alarm can fire
+const MIN_COMPARISONS: usize = 10;
+
+/// Shadow requests allowed in flight at once; further samples are skipped
+const MAX_IN_FLIGHT: usize = 8;
+
+/// When comparisons count as divergent and when the alarm fires
+#[derive(Debug, Clone)]
+pub struct DivergencePolicy {
+    /// Compare one in this many simulated estimates
+    pub sample_every: u64,
+    /// Divergence (in percent of the upstream estimate) counted as divergent
+    pub threshold_percent: f64,
+    /// Window over which the share of divergent comparisons is measured
+    pub window: Duration,
+    /// Share of divergent comparisons in the window that fires the alarm
+    pub alarm_ratio: f64,
+    /// URL notified (JSON `POST`) when the alarm fires or resolves
+    pub webhook_url: Option<String>,
+}
+
+/// State of the comparisons and the alarm
+#[derive(Default)]
+struct MonitorState {
+    /// Time and divergence flag of the comparisons in the window, oldest first
+    comparisons: VecDeque<(Instant, bool)>,
+    /// Latest divergent comparison
+    last_divergent: Option<Comparison>,
+    /// Unix timestamp (seconds) at which the alarm fired, while firing
+    firing_since: Option<u64>,
+}
+
+/// Compares sampled estimates with the upstream node and raises divergence alarms
+pub struct DivergenceMonitor {
+    policy: DivergencePolicy,
+    /// Simulated estimates seen, used for sampling
+    seen: AtomicU64,
+    /// Shadow requests in flight
+    in_flight: AtomicUsize,
+    state: Mutex<MonitorState>,
+    /// Client used for webhook notifications
+    http: reqwest::Client,
+}
+
+impl DivergenceMonitor {
+    /// Create a monitor with the given policy
+    pub fn new(policy: DivergencePolicy) -> Self {
+        Self {
+            policy,
+            seen: AtomicU64::new(0),
+            in_flight: AtomicUsize::new(0),
+            state: Mutex::new(MonitorState::default()),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Compare a simulated estimate with the upstream node's, if it is sampled
+    ///
+    /// The upstream request runs in the background; the caller isn't delayed.
+    /// The node is asked the client's request as sent, at the block the local
+    /// estimate ran against, so both see the same state and apply their own
+    /// defaults for what the client left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `eth_client` - Client of the upstream node
+    /// * `tx_request` - The request as the client sent it
+    /// * `block_number` - Block the local estimate ran against
+    /// * `local` - The locally simulated estimate
+    pub fn sample(
+        self: &Arc<Self>,
+        eth_client: Arc<EthereumClient>,
+        tx_request: &TransactionRequest,
+        block_number: u64,
+        local: U256,
+    ) {
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed);
+        if self.policy.sample_every == 0 || seen % self.policy.sample_every != 0 {
+            return;
+        }
+        if self.in_flight.fetch_add(1, Ordering::Relaxed) >= MAX_IN_FLIGHT {
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            debug!("Skipping shadow comparison, too many in flight");
+            return;
+        }
+
+        let monitor = self.clone();
+        let tx_request = tx_request.clone();
+        let shadow = async move {
+            match eth_client.estimate_gas(&tx_request, BlockId::number(block_number)).await {
+                Ok(upstream) => {
+                    let to = tx_request.to.and_then(|to| to.to().copied());
+                    monitor.compare(to, local.saturating_to(), upstream).await;
+                }
+                Err(e) => debug!("Shadow estimate failed upstream: {}", e),
+            }
+            monitor.in_flight.fetch_sub(1, Ordering::Relaxed);
+        };
+        // The shadow request is tagged like the client's, though it outlives it
+        match correlation_id() {
+            Some(correlation_id) => tokio::spawn(with_correlation_id(correlation_id, shadow)),
+            None => tokio::spawn(shadow),
+        };
+    }
+
+    /// Record a local estimate compared with the upstream node's, firing or resolving the alarm
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - Target of the estimated transaction, `None` for deployments
+    /// * `local` - The locally simulated estimate
+    /// * `upstream` - The upstream node's estimate
+    pub async fn compare(&self, to: Option<Address>, local: u64, upstream: u64) {
+        self.record(Comparison {
+            to,
+            local,
+            upstream,
+            divergence_percent: divergence_percent(local, upstream),
+        })
+        .await;
+    }
+
+    /// Record a comparison and fire or resolve the alarm as needed
+    async fn record(&self, comparison: Comparison) {
+        let divergent = comparison.divergence_percent > self.policy.threshold_percent;
+        if divergent {
+            debug!(
+                "Estimate diverges from upstream by {:.1}% (local {}, upstream {})",
+                comparison.divergence_percent, comparison.local, comparison.upstream
+            );
+        }
+
+        let alarm = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            state.comparisons.push_back((now, divergent));
+            self.expire(&mut state, now);
+            if divergent {
+                state.last_divergent = Some(comparison);
+            }
+
+            let (total, divergent_count) = counts(&state);
+            let sustained = total >= MIN_COMPARISONS
+                && divergent_count as f64 >= self.policy.alarm_ratio * total as f64;
+            match (sustained, state.firing_since) {
+                (true, None) => {
+                    state.firing_since = Some(unix_now());
+                    Some(self.alarm("firing", &state))
+                }
+                (false, Some(_)) => {
+                    state.firing_since = None;
+                    Some(self.alarm("resolved", &state))
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(alarm) = alarm {
+            if alarm.status == "firing" {
+                warn!(
+                    "Divergence alarm: {} of {} comparisons differ from upstream by more than {}%",
+                    alarm.divergent, alarm.comparisons, alarm.threshold_percent
+                );
+            } else {
+                info!("Divergence alarm resolved");
+            }
+            self.notify(&alarm).await;
+        }
+    }
+
+    /// Current state of the comparisons and the alarm
+    pub fn status(&self) -> DivergenceStatus {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut state, Instant::now());
+        let (comparisons, divergent) = counts(&state);
+        DivergenceStatus {
+            firing: state.firing_since.is_some(),
+            firing_since: state.firing_since,
+            window_secs: self.policy.window.as_secs(),
+            threshold_percent: self.policy.threshold_percent,
+            comparisons,
+            divergent,
+            last_divergent: state.last_divergent.clone(),
+        }
+    }
+
+    /// Build the notification for an alarm transition
+    fn alarm(&self, status: &'static str, state: &MonitorState) -> DivergenceAlarm {
+        let (comparisons, divergent) = counts(state);
+        DivergenceAlarm {
+            status,
+            comparisons,
+            divergent,
+            threshold_percent: self.policy.threshold_percent,
+            window_secs: self.policy.window.as_secs(),
+            example: state.last_divergent.clone(),
+        }
+    }
+
+    /// Post an alarm to the webhook, if configured
+    async fn notify(&self, alarm: &DivergenceAlarm) {
+        let Some(url) = &self.policy.webhook_url else {
+            return;
+        };
+        let sent = self
+            .http
+            .post(url)
+            .json(alarm)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            warn!("Failed to notify divergence webhook: {}", e);
+        }
+    }
+
+    /// Drop comparisons older than the window
+    fn expire(&self, state: &mut MonitorState, now: Instant) {
+        while state
+            .comparisons
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= self.policy.window)
+        {
+            state.comparisons.pop_front();
+        }
+    }
+}
+
+/// Number of comparisons in the window and how many of them diverged
+fn counts(state: &MonitorState) -> (usize, usize) {
+    let divergent = state.comparisons.iter().filter(|(_, divergent)| *divergent).count();
+    (state.comparisons.len(), divergent)
+}
+
+/// Difference between two estimates in percent of the upstream one
+fn divergence_percent(local: u64, upstream: u64) -> f64 {
+    if upstream == 0 {
+        return if local == 0 { 0.0 } else { 100.0 };
+    }
+    (local as f64 - upstream as f64).abs() / upstream as f64 * 100.0
+}
+
+/// Current Unix timestamp in seconds
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,247 @@
+#[cfg(feature = "actix")]
+use actix_web::{
+    http::{header::RETRY_AFTER, StatusCode},
+    HttpResponse, ResponseError,
+};
+#[cfg(feature = "actix")]
+use crate::correlation::correlation_id;
+use crate::gas_dependence::FailedFrame;
+use alloy::{
+    primitives::Bytes,
+    sol_types::{Revert, SolError},
+};
+#[cfg(feature = "actix")]
+use serde::Serialize;
+use std::{convert::Infallible, time::Duration};
+use thiserror::Error;
+
+/// Service-specific error types
+///
+/// This enum defines all possible errors that can occur in the gas estimation service.
+/// Each variant represents a specific error case and includes relevant details.
+#[derive(Error, Debug)]
+pub enum ServiceError {
+    #[error("RPC connection error: {0}")]
+    RPCConnection(String),
+
+    #[error("Failed to fetch fork state: {0}")]
+    Database(String),
+
+    #[error("Transaction simulation failed: {0}")]
+    Simulation(String),
+
+    #[error("Gas estimation failed: {0}")]
+    Estimation(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Unsupported chain: {0}")]
+    UnsupportedChain(String),
+
+    #[error("Request rejected: {0}")]
+    Rejected(String),
+
+    #[error("Chain mismatch: the transaction is for chain {requested}, the service serves chain {connected}")]
+    ChainMismatch { requested: u64, connected: u64 },
+
+    #[error("Invalid parameters: {0}")]
+    InvalidParams(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Out of gas in call to {} at depth {}", .0.address, .0.depth)]
+    OutOfGas(FailedFrame),
+
+    #[error("Invalid configuration: {0}")]
+    Configuration(String),
+
+    #[error("insufficient funds for gas * price + value: {0}")]
+    InsufficientFunds(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String, Duration),
+
+    #[error("execution reverted{}", revert_reason(.0).map(|reason| format!(": {}", reason)).unwrap_or_default())]
+    Reverted(Bytes),
+}
+
+/// Errors of databases that can't fail, such as the fixtures' in-memory state
+impl From<Infallible> for ServiceError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl ServiceError {
+    /// Recover the service error carried by an `eyre::Report`
+    ///
+    /// Estimator methods return `eyre::Result`; endpoints that answer with a
+    /// `ServiceError` use this to keep the original error kind (and status code).
+    pub fn from_report(report: eyre::Report) -> Self {
+        match report.downcast::<ServiceError>() {
+            Ok(service_error) => service_error,
+            Err(report) => ServiceError::Estimation(report.to_string()),
+        }
+    }
+}
+
+/// Reason of an `Error(string)` revert, if the revert data is one
+pub fn revert_reason(data: &Bytes) -> Option<String> {
+    Revert::abi_decode(data, true).ok().map(|revert| revert.reason)
+}
+
+/// Structured error response for the API
+///
+/// This structure defines the JSON format of error responses returned by the API.
+#[cfg(feature = "actix")]
+#[derive(Serialize)]
+struct ErrorResponse {
+    /// Human-readable error message
+    error: String,
+    
+    /// Machine-readable error code
+    error_code: String,
+    
+    /// Optional detailed error information: a message, or a JSON object for structured details
+    details: Option<serde_json::Value>,
+
+    /// Correlation id of the failed request, when served within one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+}
+
+/// HTTP responses of the errors, with the `actix` feature
+#[cfg(feature = "actix")]
+impl ResponseError for ServiceError {
+    /// Convert the error to an HTTP response
+    ///
+    /// This method generates an appropriate HTTP response based on the error type,
+    /// including status code and a JSON error body.
+    fn error_response(&self) -> HttpResponse {
+        let (status_code, error_code, details) = match self {
+            ServiceError::RPCConnection(details) => (
+                StatusCode::BAD_GATEWAY,
+                "RPC_CONNECTION_ERROR",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Database(details) => (
+                StatusCode::BAD_GATEWAY,
+                "STATE_FETCH_ERROR",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Simulation(details) => (
+                StatusCode::BAD_REQUEST,
+                "SIMULATION_ERROR",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Estimation(details) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "ESTIMATION_ERROR",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::NotFound(details) => (
+                StatusCode::NOT_FOUND,
+                "NOT_FOUND",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Unauthorized(details) => (
+                StatusCode::UNAUTHORIZED,
+                "UNAUTHORIZED",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::UnsupportedChain(details) => (
+                StatusCode::BAD_REQUEST,
+                "UNSUPPORTED_CHAIN",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Rejected(details) => (
+                StatusCode::FORBIDDEN,
+                "REQUEST_REJECTED",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::ChainMismatch { .. } => (
+                StatusCode::BAD_REQUEST,
+                "CHAIN_MISMATCH",
+                None,
+            ),
+            ServiceError::InvalidParams(details) => (
+                StatusCode::BAD_REQUEST,
+                "INVALID_PARAMS",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Timeout(details) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "TIMEOUT",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::OutOfGas(frame) => (
+                StatusCode::BAD_REQUEST,
+                "OUT_OF_GAS",
+                serde_json::to_value(frame).ok(),
+            ),
+            ServiceError::Configuration(details) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "CONFIGURATION_ERROR",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::InsufficientFunds(details) => (
+                StatusCode::BAD_REQUEST,
+                "INSUFFICIENT_FUNDS",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Unavailable(details, _) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+                Some(details.as_str().into()),
+            ),
+            ServiceError::Reverted(data) => (
+                StatusCode::BAD_REQUEST,
+                "EXECUTION_REVERTED",
+                Some(data.to_string().into()),
+            ),
+        };
+
+        let mut response = HttpResponse::build(status_code);
+        if let ServiceError::Unavailable(_, retry_after) = self {
+            response.insert_header((RETRY_AFTER, retry_after_secs(*retry_after).to_string()));
+        }
+        response.json(ErrorResponse {
+            error: self.to_string(),
+            error_code: error_code.to_string(),
+            details,
+            correlation_id: correlation_id(),
+        })
+    }
+
+    /// Get the HTTP status code for this error
+    fn status_code(&self) -> StatusCode {
+        match *self {
+            ServiceError::RPCConnection(_) => StatusCode::BAD_GATEWAY,
+            ServiceError::Database(_) => StatusCode::BAD_GATEWAY,
+            ServiceError::Simulation(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Estimation(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ServiceError::UnsupportedChain(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Rejected(_) => StatusCode::FORBIDDEN,
+            ServiceError::ChainMismatch { .. } => StatusCode::BAD_REQUEST,
+            ServiceError::InvalidParams(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ServiceError::OutOfGas(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Configuration(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ServiceError::InsufficientFunds(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Unavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::Reverted(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Whole seconds announced in a `Retry-After` header, at least one
+pub fn retry_after_secs(retry_after: Duration) -> u64 {
+    (retry_after.as_secs_f64().ceil() as u64).max(1)
+}
\ No newline at end of file
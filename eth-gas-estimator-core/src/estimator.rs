@@ -0,0 +1,2311 @@
+use crate::{
+    access_list::{access_list_on_fork, gas_used_on_fork},
+    audit::{AuditLog, DEFAULT_AUDIT_LOG_CAPACITY},
+    backend::{upstream_error, BackendContext, SimulationBackend},
+    bundle::{decode_bundle_transaction, simulate_bundle_on_fork, MAX_BUNDLE_TRANSACTIONS},
+    cache::{EstimateCache, DEFAULT_ESTIMATE_CACHE_CAPACITY},
+    code_changes::code_change_warnings_on_fork,
+    degradation::{is_outage, DegradationPolicy, DegradationStep},
+    chains::{adapter_for_chain, ChainAdapter, ChainOptions},
+    divergence::DivergenceMonitor,
+    error::ServiceError,
+    explain::{gas_breakdown_on_fork, summarize},
+    gas_dependence::recommend_gas_limit_with_diff,
+    hooks::EstimationHook,
+    intrinsic::TX_BASE_GAS,
+    memory::memory_usage_on_fork,
+    rpc::{chain_id_at, upstream_failure, EthereumClient},
+    foundry::{
+        account_on_fork, check_authorizations, check_chain_id, estimate_sequence_on_fork, simulate_blocks_on_fork, simulate_on_fork,
+        simulate_sequence_on_fork, AccountState, BlockCalls, BlockOverrides, BlockPins, BlockTarget, CodeOverrides, Fork,
+        ForkManager, SimulationMode, DEFAULT_HEAD_TTL,
+    },
+    models::{
+        access_list::AccessListReport,
+        bundle::{BundleTransactionResult, CallBundleParams, CallBundleResponse},
+        estimate::{EstimateWarning, EstimateWarningKind, ExtendedEstimate},
+        explain::{ExplainOptions, GasExplanation},
+        jsonrpc::BlobGasUsage,
+        ladder::{NonceLadder, NonceLadderStep},
+        sequence::{SequenceEstimate, SequenceStep},
+        simulate::{CallError, SimulatedBlock, SimulatedCall, SimulatedLog},
+        simulation::{SimulateOptions, SimulationReport},
+        trace::{CallFrame, DetailedEstimate, Trace, TraceOptions, TracerConfig},
+    },
+    provenance::{request_hash, ProvenanceClaim, ProvenanceSigner},
+    quotes::QuotePolicy,
+    simulation_limit::DEFAULT_MAX_CONCURRENT_SIMULATIONS,
+    storage_batch::StorageBatching,
+    timeouts::Timeouts,
+    tracers::{call_frames_and_touched_on_fork, touched_accounts_on_fork, traced_execution_on_fork},
+    units::DisplayUnits,
+};
+use alloy::{
+    eips::{eip4844::DATA_GAS_PER_BLOB, BlockId, BlockNumberOrTag},
+    primitives::{keccak256, Address, Bytes, B256, I256, U256, U64},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+};
+use eyre::Result;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    future::IntoFuture,
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+use lru::LruCache;
+use revm::primitives::{ExecutionResult, Output, SpecId};
+use tokio::sync::OnceCell;
+use tracing::{debug, instrument, info, error, warn};
+
+/// Gas unit constants
+pub const GWEI: u64 = 1_000_000_000;
+
+/// Default gas limit for simple Ethereum transfers (21,000 gas)
+pub const DEFAULT_GAS_LIMIT: u64 = 21_000;
+
+/// Calldata floor price per token (EIP-7623)
+pub const TOTAL_COST_FLOOR_PER_TOKEN: u64 = 10;
+
+/// Default number of transactions of a batch estimated at once
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 32;
+
+/// Default fraction of the block gas limit above which extended estimates carry a warning
+pub const DEFAULT_GAS_LIMIT_WARNING_FRACTION: f64 = 0.5;
+
+/// How many blocks' worth of gas an extended estimate may measure beyond the block gas limit
+pub const BLOCK_GAS_LIMIT_PROBE_MULTIPLE: u64 = 4;
+
+/// Senders whose pending nonce is remembered for the current head
+pub const MAX_PENDING_NONCES: usize = 10_000;
+
+/// Most blocks one multi-block simulation may run, as on geth
+pub const MAX_SIMULATED_BLOCKS: usize = 256;
+
+/// Most calls one multi-block simulation may run, across all its blocks
+pub const MAX_SIMULATED_CALLS: usize = 1_000;
+
+/// Gas estimator service that calculates gas requirements for Ethereum transactions
+///
+/// This service provides methods for estimating gas usage of Ethereum transactions
+/// using either local simulation with REVM or by falling back to RPC methods.
+#[derive(Clone)]
+pub struct GasEstimator {
+    /// Ethereum client for interacting with the blockchain
+    pub eth_client: Arc<EthereumClient>,
+    /// Long-lived simulation forks, shared between requests
+    pub forks: Arc<ForkManager>,
+    /// Log of recently processed requests, used for replay debugging
+    pub audit_log: Arc<AuditLog>,
+    /// Chain id of the connected chain, fetched from the provider on first use
+    chain_id: Arc<OnceCell<u64>>,
+    /// Adapter for the connected chain, resolved from its chain id on first use
+    chain_adapter: Arc<OnceCell<Arc<dyn ChainAdapter>>>,
+    /// Backend serving estimates, calls and traces, resolved from the chain id on first use
+    simulation_backend: Arc<OnceCell<Arc<dyn SimulationBackend>>>,
+    /// Per-chain adapter settings
+    chain_options: Arc<ChainOptions>,
+    /// Pipeline hooks, run in registration order
+    hooks: Arc<Vec<Arc<dyn EstimationHook>>>,
+    /// Service key signing simulation reports, if configured
+    provenance: Option<Arc<ProvenanceSigner>>,
+    /// Expiry and drift tolerance of issued quotes, replaceable at runtime
+    quote_policy: Arc<RwLock<QuotePolicy>>,
+    /// Results of recent estimates at the current block (disabled when `None`)
+    pub estimate_cache: Option<Arc<EstimateCache>>,
+    /// Whether failed simulations fall back to the upstream node's `eth_estimateGas`
+    upstream_fallback: bool,
+    /// What estimates fall back to when the upstream node is unreachable
+    degradation: Arc<DegradationPolicy>,
+    /// Rendering of human-readable amounts in responses (disabled when `None`)
+    display_units: Option<DisplayUnits>,
+    /// Shadow comparisons with the upstream node (disabled when `None`)
+    divergence: Option<Arc<DivergenceMonitor>>,
+    /// Where omitted nonces are resolved from
+    nonce_source: NonceSource,
+    /// Pending nonces of recent senders, with the head block they were looked up at
+    pending_nonces: Arc<Mutex<LruCache<Address, (B256, u64)>>>,
+    /// Safety margin added to estimates unless a request overrides it
+    headroom: Headroom,
+    /// Transactions of a batch estimated at once, at least 1
+    batch_concurrency: usize,
+    /// Fraction of the block gas limit above which extended estimates warn, 0 to disable
+    gas_limit_warning_fraction: f64,
+}
+
+/// Where the nonce of a request that omits it is resolved from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonceSource {
+    /// The sender's nonce at the head block
+    #[default]
+    Latest,
+    /// The upstream node's pending nonce, counting the sender's queued transactions
+    Pending,
+}
+
+impl FromStr for NonceSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "latest" => Ok(NonceSource::Latest),
+            "pending" => Ok(NonceSource::Pending),
+            other => Err(format!("Unknown nonce source: {}", other)),
+        }
+    }
+}
+
+/// Safety margin added to estimates for state drift between estimation and inclusion
+///
+/// The estimate is multiplied by `multiplier` (rounded up), then `buffer` gas
+/// is added; the result never exceeds the block gas limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Headroom {
+    /// Factor applied to the estimate, at least 1.0
+    pub multiplier: f64,
+    /// Gas added after the multiplier
+    pub buffer: u64,
+}
+
+impl Default for Headroom {
+    fn default() -> Self {
+        Self { multiplier: 1.0, buffer: 0 }
+    }
+}
+
+impl Headroom {
+    /// Create a headroom, rejecting multipliers below 1.0
+    pub fn new(multiplier: f64, buffer: u64) -> Result<Self, String> {
+        if !multiplier.is_finite() || multiplier < 1.0 {
+            return Err(format!("Gas multiplier must be at least 1.0, got {}", multiplier));
+        }
+        Ok(Self { multiplier, buffer })
+    }
+
+    /// Apply the margin to `gas`, capped at `block_gas_limit`
+    pub fn apply(&self, gas: U256, block_gas_limit: U256) -> U256 {
+        let scaled = (gas.saturating_to::<u64>() as f64 * self.multiplier).ceil() as u64;
+        U256::from(scaled.saturating_add(self.buffer)).min(block_gas_limit).max(gas)
+    }
+}
+
+/// Per-request estimation options
+#[derive(Debug, Clone, Default)]
+pub struct EstimateOptions {
+    /// Block the estimate runs against
+    pub block: BlockTarget,
+    /// Header values pinned by the client
+    pub pins: BlockPins,
+    /// Contract code run instead of the chain's
+    pub code_overrides: CodeOverrides,
+    /// Safety margin replacing the configured one
+    pub headroom: Option<Headroom>,
+    /// Accept EIP-7702 authorizations without recovering their signer, for draft
+    /// estimations with placeholder signatures
+    pub skip_authorization_check: bool,
+}
+
+/// Where a gas estimate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimateSource {
+    /// Local fork simulation (or a cached simulation result)
+    Simulation,
+    /// The upstream node's `eth_estimateGas`, after the simulation failed or with the upstream backend
+    Upstream,
+    /// An earlier simulation of the same request, served while the upstream node is unreachable
+    Stale,
+    /// The secondary provider's `eth_estimateGas`, while the upstream node is unreachable
+    Secondary,
+}
+
+impl EstimateSource {
+    /// Name of the source, as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EstimateSource::Simulation => "simulation",
+            EstimateSource::Upstream => "upstream",
+            EstimateSource::Stale => "stale",
+            EstimateSource::Secondary => "secondary",
+        }
+    }
+}
+
+/// A gas estimate tagged with its source
+#[derive(Debug, Clone, Copy)]
+pub struct GasEstimate {
+    /// Estimated gas limit
+    pub gas: U256,
+    /// Where the estimate came from
+    pub source: EstimateSource,
+    /// Head block the estimate holds for
+    pub block_number: u64,
+    /// Hash of the head block the estimate holds for
+    pub block_hash: B256,
+    /// Blob gas and blob base fee, for type-3 transactions
+    pub blob_gas: Option<BlobGasUsage>,
+    /// Gas the headroom added to the limit the transaction needs, included in `gas`
+    pub headroom: U256,
+}
+
+impl GasEstimate {
+    /// The limit the transaction needs, without the headroom
+    ///
+    /// Re-runs of the transaction use this limit, so padding the returned
+    /// estimate doesn't change what they observe.
+    pub fn unpadded(&self) -> U256 {
+        self.gas.saturating_sub(self.headroom)
+    }
+}
+
+/// Estimates of independent transactions against one shared fork
+pub struct BulkEstimate {
+    /// Block every estimate holds for
+    pub block_number: u64,
+    /// Hash of the block every estimate holds for
+    pub block_hash: B256,
+    /// One result per transaction, in order
+    pub results: Vec<Result<GasEstimate>>,
+}
+
+/// Results of simulating transactions back-to-back
+pub struct SimulatedSequence {
+    /// Block the transactions were simulated against
+    pub block_number: u64,
+    /// One result per transaction, in order
+    pub results: Vec<ExecutionResult>,
+}
+
+/// Builder for [`GasEstimator`]
+///
+/// Collects optional components (audit log sizing, caching, pipeline hooks) before the
+/// estimator is shared between requests.
+pub struct GasEstimatorBuilder {
+    eth_client: Arc<EthereumClient>,
+    rpc_url: String,
+    audit_log_capacity: usize,
+    chain_options: ChainOptions,
+    head_ttl: Duration,
+    hooks: Vec<Arc<dyn EstimationHook>>,
+    provenance: Option<Arc<ProvenanceSigner>>,
+    quote_policy: QuotePolicy,
+    estimate_cache_capacity: usize,
+    upstream_fallback: bool,
+    degradation: DegradationPolicy,
+    timeouts: Timeouts,
+    storage_batching: StorageBatching,
+    max_concurrent_simulations: usize,
+    display_units: Option<DisplayUnits>,
+    divergence: Option<Arc<DivergenceMonitor>>,
+    nonce_source: NonceSource,
+    headroom: Headroom,
+    batch_concurrency: usize,
+    gas_limit_warning_fraction: f64,
+}
+
+impl GasEstimatorBuilder {
+    /// Retain at most `capacity` requests in the audit log
+    pub fn audit_log_capacity(mut self, capacity: usize) -> Self {
+        self.audit_log_capacity = capacity;
+        self
+    }
+
+    /// Set per-chain adapter settings
+    pub fn chain_options(mut self, options: ChainOptions) -> Self {
+        self.chain_options = options;
+        self
+    }
+
+    /// Reuse a head block lookup for `ttl` before asking the node again
+    pub fn latest_block_ttl(mut self, ttl: Duration) -> Self {
+        self.head_ttl = ttl;
+        self
+    }
+
+    /// Register a pipeline hook; hooks run in registration order
+    pub fn hook(mut self, hook: Arc<dyn EstimationHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Sign simulation reports with the given service key
+    pub fn provenance_signer(mut self, signer: ProvenanceSigner) -> Self {
+        self.provenance = Some(Arc::new(signer));
+        self
+    }
+
+    /// Set how long issued quotes stay valid and how much drift they tolerate
+    pub fn quote_policy(mut self, policy: QuotePolicy) -> Self {
+        self.quote_policy = policy;
+        self
+    }
+
+    /// Cache up to `capacity` estimation results per block (0 disables the cache)
+    pub fn estimate_cache_capacity(mut self, capacity: usize) -> Self {
+        self.estimate_cache_capacity = capacity;
+        self
+    }
+
+    /// Bound upstream RPC calls made by the simulation forks and the simulations themselves
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Fetch storage misses of the simulation forks in batches of adjacent slots
+    pub fn storage_batching(mut self, batching: StorageBatching) -> Self {
+        self.storage_batching = batching;
+        self
+    }
+
+    /// Let at most `max` simulations run on the blocking thread pool at once (0 for unbounded)
+    pub fn max_concurrent_simulations(mut self, max: usize) -> Self {
+        self.max_concurrent_simulations = max;
+        self
+    }
+
+    /// Estimate at most `concurrency` transactions of a batch at once (0 is taken as 1)
+    pub fn batch_concurrency(mut self, concurrency: usize) -> Self {
+        self.batch_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Warn in extended estimates above `fraction` of the block gas limit (0 to only warn beyond it)
+    pub fn gas_limit_warning_fraction(mut self, fraction: f64) -> Self {
+        self.gas_limit_warning_fraction = fraction;
+        self
+    }
+
+    /// Add human-readable gwei/ETH strings next to wei values in verbose and REST responses
+    pub fn display_units(mut self, units: DisplayUnits) -> Self {
+        self.display_units = Some(units);
+        self
+    }
+
+    /// Compare a sample of simulated estimates with the upstream node's, raising divergence alarms
+    pub fn divergence_monitor(mut self, monitor: Arc<DivergenceMonitor>) -> Self {
+        self.divergence = Some(monitor);
+        self
+    }
+
+    /// Fall back to the upstream node's `eth_estimateGas` when a simulation can't fetch fork state
+    pub fn upstream_fallback(mut self, enabled: bool) -> Self {
+        self.upstream_fallback = enabled;
+        self
+    }
+
+    /// Walk the given degradation ladder when the upstream node is unreachable
+    pub fn degradation(mut self, policy: DegradationPolicy) -> Self {
+        self.degradation = policy;
+        self
+    }
+
+    /// Resolve omitted nonces from the head block or the upstream's pending nonce
+    pub fn nonce_source(mut self, source: NonceSource) -> Self {
+        self.nonce_source = source;
+        self
+    }
+
+    /// Add a safety margin to every estimate, unless a request sets its own
+    pub fn headroom(mut self, headroom: Headroom) -> Self {
+        self.headroom = headroom;
+        self
+    }
+
+    /// Build the estimator
+    pub fn build(self) -> GasEstimator {
+        let chain_options = Arc::new(self.chain_options);
+        let forks = Arc::new(ForkManager::new(
+            &self.rpc_url,
+            self.head_ttl,
+            chain_options.clone(),
+            self.timeouts,
+            self.storage_batching,
+            self.max_concurrent_simulations,
+        ));
+        let estimate_cache = NonZeroUsize::new(self.estimate_cache_capacity)
+            .map(|capacity| Arc::new(EstimateCache::new(capacity)));
+        let degradation = Arc::new(self.degradation);
+
+        // Estimates simulated on blocks a reorg orphaned are never served again
+        let (orphaned_cache, orphaned_degradation) = (estimate_cache.clone(), degradation.clone());
+        forks.on_reorg(Box::new(move |orphaned| {
+            if let Some(cache) = &orphaned_cache {
+                cache.purge(orphaned);
+            }
+            orphaned_degradation.purge(orphaned);
+        }));
+
+        GasEstimator {
+            eth_client: self.eth_client,
+            forks,
+            audit_log: Arc::new(AuditLog::new(self.audit_log_capacity)),
+            chain_id: Arc::new(OnceCell::new()),
+            chain_adapter: Arc::new(OnceCell::new()),
+            simulation_backend: Arc::new(OnceCell::new()),
+            chain_options,
+            hooks: Arc::new(self.hooks),
+            provenance: self.provenance,
+            quote_policy: Arc::new(RwLock::new(self.quote_policy)),
+            estimate_cache,
+            upstream_fallback: self.upstream_fallback,
+            degradation,
+            display_units: self.display_units,
+            divergence: self.divergence,
+            nonce_source: self.nonce_source,
+            pending_nonces: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::MIN.saturating_add(MAX_PENDING_NONCES - 1),
+            ))),
+            headroom: self.headroom,
+            batch_concurrency: self.batch_concurrency,
+            gas_limit_warning_fraction: self.gas_limit_warning_fraction,
+        }
+    }
+}
+
+impl GasEstimator {
+    /// Creates a new gas estimator with the provided client and RPC URL
+    pub fn new(eth_client: Arc<EthereumClient>, rpc_url: &str) -> Self {
+        Self::builder(eth_client, rpc_url).build()
+    }
+
+    /// Start building a gas estimator with the provided client and RPC URL
+    pub fn builder(eth_client: Arc<EthereumClient>, rpc_url: &str) -> GasEstimatorBuilder {
+        GasEstimatorBuilder {
+            eth_client,
+            rpc_url: rpc_url.to_string(),
+            audit_log_capacity: DEFAULT_AUDIT_LOG_CAPACITY,
+            chain_options: ChainOptions::default(),
+            head_ttl: DEFAULT_HEAD_TTL,
+            hooks: Vec::new(),
+            provenance: None,
+            quote_policy: QuotePolicy::default(),
+            estimate_cache_capacity: DEFAULT_ESTIMATE_CACHE_CAPACITY,
+            upstream_fallback: false,
+            degradation: DegradationPolicy::default(),
+            timeouts: Timeouts::default(),
+            storage_batching: StorageBatching::default(),
+            max_concurrent_simulations: DEFAULT_MAX_CONCURRENT_SIMULATIONS,
+            display_units: None,
+            divergence: None,
+            nonce_source: NonceSource::default(),
+            headroom: Headroom::default(),
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            gas_limit_warning_fraction: DEFAULT_GAS_LIMIT_WARNING_FRACTION,
+        }
+    }
+
+    /// Estimate gas for a transaction using fork-based simulation
+    ///
+    /// This method attempts to simulate the transaction execution using a forked
+    /// state of the blockchain and returns the estimated gas limit required.
+    /// When the simulation itself fails (e.g. missing state or an unsupported
+    /// precompile) and the upstream fallback is enabled, the request is forwarded
+    /// to the node's own `eth_estimateGas` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GasEstimate>` - The estimated gas limit, its source and the head block it
+    ///   holds for on success, or an error
+    pub async fn estimate_raw_gas(&self, tx_request: &TransactionRequest) -> Result<GasEstimate> {
+        self.estimate_raw_gas_with(tx_request, &EstimateOptions::default()).await
+    }
+
+    /// Estimate gas for a transaction with per-request options
+    ///
+    /// Like [`GasEstimator::estimate_raw_gas`], but the simulation sees the
+    /// pinned `PREVRANDAO` and coinbase instead of the head block's, so
+    /// estimates of randomness-dependent contracts are reproducible. Code
+    /// overrides run the client's code at the given addresses, e.g. a
+    /// not-yet-deployed implementation behind a proxy. Pinned or overridden
+    /// estimates bypass the result cache and are never answered by the upstream
+    /// node, which can't honor them. A headroom in the options replaces the
+    /// configured one. When the upstream node is unreachable, the configured
+    /// degradation ladder is walked (see [`crate::degradation`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - Header values and code to simulate with, and the safety margin to add
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GasEstimate>` - The estimated gas limit, its source and the head block it
+    ///   holds for on success, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn estimate_raw_gas_with(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+    ) -> Result<GasEstimate> {
+        debug!("Starting gas estimation for transaction request: {:?}", tx_request);
+        let estimate = match self.forks.fork_at(options.block).await {
+            Ok(head) => self.estimate_on_fork(tx_request, head, options).await,
+            Err(e) => Err(e.into()),
+        };
+        match estimate {
+            Ok(estimate) => {
+                if estimate.source == EstimateSource::Simulation && !self.forks.is_orphaned(estimate.block_hash) {
+                    self.degradation.remember(tx_request, options, &estimate);
+                }
+                Ok(estimate)
+            }
+            Err(e) if is_outage(&e) => self.degrade(tx_request, options, e).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Walk the degradation ladder after the upstream node failed to serve an estimate
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - The options of the failed estimate
+    /// * `outage` - Why the upstream node couldn't serve it
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GasEstimate>` - The estimate of the first step that could answer, or a
+    ///   `ServiceError::Unavailable` carrying the delay to announce in `Retry-After`
+    async fn degrade(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+        outage: eyre::Report,
+    ) -> Result<GasEstimate> {
+        warn!("Upstream node unreachable: {}", outage);
+        for step in self.degradation.steps() {
+            match step {
+                DegradationStep::StaleCache => {
+                    if let Some(estimate) = self.degradation.stale(tx_request, options) {
+                        info!("Serving stale estimate from block {}", estimate.block_number);
+                        return Ok(GasEstimate { source: EstimateSource::Stale, ..estimate });
+                    }
+                }
+                DegradationStep::Secondary => match self.estimate_on_secondary(tx_request, options).await {
+                    Ok(estimate) => {
+                        info!("Serving estimate from the secondary provider");
+                        return Ok(estimate);
+                    }
+                    Err(e) => warn!("Secondary provider failed: {}", e),
+                },
+            }
+        }
+        Err(ServiceError::Unavailable(outage.to_string(), self.degradation.retry_after()).into())
+    }
+
+    /// Pass an estimate through to the secondary provider's `eth_estimateGas`
+    ///
+    /// The pipeline hooks and headroom apply as they do to local estimates.
+    /// Estimates with code overrides aren't passed through, as the secondary
+    /// provider would run the chain's code instead.
+    async fn estimate_on_secondary(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+    ) -> Result<GasEstimate> {
+        if !options.code_overrides.is_empty() {
+            let reason = "Code overrides can't be estimated by the secondary provider";
+            return Err(ServiceError::Configuration(reason.to_string()).into());
+        }
+        let secondary = self
+            .degradation
+            .secondary()
+            .ok_or_else(|| ServiceError::Configuration("No secondary provider configured".to_string()))?;
+        let tx_request = self.prepare_request_with(tx_request, options)?;
+        let head = secondary.get_latest_block().await?;
+        let mut gas = U256::from(secondary.estimate_gas(&tx_request, options.block.block_id()).await?);
+
+        for hook in self.hooks.iter() {
+            gas = hook.post_process(&tx_request, gas)?;
+        }
+        let padded = options
+            .headroom
+            .unwrap_or(self.headroom)
+            .apply(gas, U256::from(head.header.gas_limit));
+        Ok(GasEstimate {
+            gas: padded,
+            source: EstimateSource::Secondary,
+            block_number: head.header.number,
+            block_hash: head.header.hash,
+            blob_gas: None,
+            headroom: padded - gas,
+        })
+    }
+
+    /// Estimate independent transactions against one shared fork
+    ///
+    /// Every transaction is estimated as if it were the only one sent on top of
+    /// the same latest head, so all estimates hold for the same block and the
+    /// fork's cached state is shared between them. At most the configured batch
+    /// concurrency of transactions are in flight at once, each simulated over a
+    /// private write layer on top of the fork's read-only state. A failing
+    /// transaction doesn't fail the others; its error is returned in its place.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_requests` - The transactions, each estimated independently
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BulkEstimate>` - One result per transaction in request order, or an error
+    ///   if the head block can't be fetched
+    #[instrument(skip(self, tx_requests), err)]
+    pub async fn estimate_bulk(&self, tx_requests: &[TransactionRequest]) -> Result<BulkEstimate> {
+        let head = self.forks.latest().await?;
+        debug!("Estimating {} transactions against block {}", tx_requests.len(), head.block_number);
+
+        let options = EstimateOptions::default();
+        let results = stream::iter(tx_requests)
+            .map(|tx_request| self.estimate_on_fork(tx_request, head.clone(), &options))
+            .buffered(self.batch_concurrency(tx_requests.len()))
+            .collect()
+            .await;
+        Ok(BulkEstimate {
+            block_number: head.block_number,
+            block_hash: head.block_hash,
+            results,
+        })
+    }
+
+    /// Estimate gas for a transaction against a given fork
+    async fn estimate_on_fork(
+        &self,
+        tx_request: &TransactionRequest,
+        head: Arc<Fork>,
+        options: &EstimateOptions,
+    ) -> Result<GasEstimate> {
+        let prepared = self.prepare_for_fork(&head, options, tx_request).await?;
+        let (estimate, head, tx_request) = self.estimate_prepared_on_fork(tx_request, prepared, head, options).await?;
+        if estimate.source == EstimateSource::Simulation {
+            self.inspect_touched(&head, &tx_request, estimate.unpadded()).await?;
+        }
+        Ok(estimate)
+    }
+
+    /// Prepare a request for a simulation against a given fork
+    ///
+    /// The request must be for the fork's chain; it then passes the request checks
+    /// and pre-simulation hooks, and gets the sender's nonce filled in.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TransactionRequest, ServiceError>` - The prepared request, or the error of
+    ///   the check or hook that rejected it
+    async fn prepare_for_fork(
+        &self,
+        head: &Fork,
+        options: &EstimateOptions,
+        tx_request: &TransactionRequest,
+    ) -> Result<TransactionRequest, ServiceError> {
+        // A transaction for another chain is never estimated on this one's state,
+        // nor passed through to the upstream node
+        check_chain_id(head, tx_request)?;
+        let tx_request = self.prepare_request_with(tx_request, options)?;
+        self.resolve_nonce_for(head, options.block, tx_request).await
+    }
+
+    /// Estimate gas for a request prepared by [`GasEstimator::prepare_for_fork`] against
+    /// that fork, short of the [`EstimationHook::inspect_touched`] stage
+    ///
+    /// # Arguments
+    ///
+    /// * `original` - The client's own request, forwarded to backends that estimate remotely
+    /// * `tx_request` - The prepared request
+    /// * `head` - The fork it was prepared for
+    /// * `options` - Block target, pinned header values, code overrides and headroom
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(GasEstimate, Arc<Fork>, TransactionRequest)>` - The estimate, with the fork
+    ///   and the request it was simulated with, or an error
+    async fn estimate_prepared_on_fork(
+        &self,
+        original: &TransactionRequest,
+        tx_request: TransactionRequest,
+        mut head: Arc<Fork>,
+        options: &EstimateOptions,
+    ) -> Result<(GasEstimate, Arc<Fork>, TransactionRequest)> {
+        let pins = &options.pins;
+        let overridden = !pins.is_empty() || !options.code_overrides.is_empty();
+
+        // The estimate holds until the next head; identical requests within the
+        // same block are answered from the cache
+        if !pins.is_empty() {
+            debug!("Simulating with pinned header values: {:?}", pins);
+        }
+        if !options.code_overrides.is_empty() {
+            debug!("Simulating with code overridden at: {:?}", options.code_overrides.keys());
+        }
+        head = fork_view(head, options)?;
+        let tx_request = with_default_blob_fee(tx_request, &head);
+        // Pending state moves within a block, so only latest estimates are cached and compared
+        let at_head = !overridden && options.block == BlockTarget::Latest;
+
+        // Pick the simulation backend for the connected chain
+        let backend = self.simulation_backend().await?;
+        let local = backend.source() == EstimateSource::Simulation;
+        // The node answers for itself, so only local simulations are cached
+        let cache = self
+            .estimate_cache
+            .as_ref()
+            .filter(|_| at_head && local)
+            .map(|cache| (cache, head.block_hash));
+        if !local && overridden {
+            return Err(ServiceError::InvalidParams(format!(
+                "Pinned header values and code overrides can't be simulated with the {} backend",
+                backend.name()
+            ))
+            .into());
+        }
+        let ctx = BackendContext {
+            eth_client: &self.eth_client,
+            fork: &head,
+            block: backend_block(&head, options.block),
+        };
+
+        // Like a node, a priced request without a gas limit is capped by what the sender can pay for;
+        // a remote backend is asked the client's own request and applies its own checks
+        let tx_request = if local {
+            with_gas_allowance(&head, with_effective_fee_cap(&head, tx_request)).await?
+        } else {
+            tx_request
+        };
+        let forwarded = if local { &tx_request } else { original };
+        let cached = cache.and_then(|(cache, block_hash)| cache.get(block_hash, &tx_request));
+
+        // Attempt to estimate gas (local simulation with REVM for EVM chains)
+        let (gas, source) = match cached {
+            Some(gas) => {
+                debug!("Serving cached estimate: {}", gas);
+                (gas, backend.source())
+            }
+            None => match backend.estimate(ctx, forwarded).await {
+                Ok(gas) => {
+                    debug!("{} backend estimated gas: {}", backend.name(), gas);
+                    // A reorg may have orphaned the block while the simulation ran
+                    let live = cache.filter(|(_, block_hash)| !self.forks.is_orphaned(*block_hash));
+                    if let Some((cache, block_hash)) = live {
+                        cache.insert(block_hash, &tx_request, gas);
+                    }
+                    // Comparing the upstream node with itself tells nothing
+                    if let Some(monitor) = self.divergence.as_ref().filter(|_| at_head && local) {
+                        monitor.sample(self.eth_client.clone(), original, head.block_number, gas);
+                    }
+                    (gas, backend.source())
+                },
+                // The fork couldn't fetch state: the node may still answer for itself, with the client's own request
+                Err(e @ (ServiceError::RPCConnection(_) | ServiceError::Database(_)))
+                    if self.upstream_fallback && local && !overridden =>
+                {
+                    warn!("Simulation failed with error: {}, falling back to upstream eth_estimateGas", e);
+                    // The node's own verdict (a revert) stands; a node that can't answer is still an outage,
+                    // left to the degradation ladder
+                    let gas = self
+                        .eth_client
+                        .provider
+                        .estimate_gas(original.clone())
+                        .block(options.block.block_id())
+                        .await
+                        .map_err(|fallback| {
+                            error!("Upstream estimation failed: {}", fallback);
+                            upstream_error("eth_estimateGas", fallback)
+                        })?;
+                    (U256::from(gas), EstimateSource::Upstream)
+                },
+                // Anything else reaches the caller as it is: the upstream node would fail the same
+                // balance check and revert the same way, a halt names the frame that ran out of gas,
+                // a timeout is answered as one and, without the fallback, an outage is left to the
+                // degradation ladder
+                Err(e) => {
+                    error!("Simulation failed with error: {}", e);
+                    return Err(e.into());
+                }
+            },
+        };
+
+        // The node's answer is final: the adjustments below are for simulated gas
+        let (gas, padded) = match source {
+            EstimateSource::Simulation => {
+                let gas = self.adjust_simulated_gas(&head, &tx_request, gas)?;
+                // The simulation checked the balance against the gas it ran with; the sender must
+                // also be able to pay for the calldata floor
+                self.check_funds(&head, &tx_request, gas).await?;
+                // Leave room for state drift until the transaction is included; only the
+                // returned limit is padded
+                let headroom = options.headroom.unwrap_or(self.headroom);
+                let padded = headroom.apply(gas, head.block_env.gas_limit);
+                if padded != gas {
+                    debug!("Added headroom {:?}: {} -> {}", headroom, gas, padded);
+                }
+                (gas, padded)
+            }
+            _ => (gas, gas),
+        };
+        let estimate = GasEstimate {
+            gas: padded,
+            source,
+            block_number: head.block_number,
+            block_hash: head.block_hash,
+            blob_gas: blob_gas_usage(&head, &tx_request),
+            headroom: padded - gas,
+        };
+        Ok((estimate, head, tx_request))
+    }
+
+    /// Expiry and drift tolerance applied to issued quotes
+    pub fn quote_policy(&self) -> QuotePolicy {
+        *self.quote_policy.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the quote policy; applies to quotes issued or validated from now on
+    pub fn set_quote_policy(&self, policy: QuotePolicy) {
+        *self.quote_policy.write().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    /// Point the upstream client and the simulation forks at a new RPC URL
+    ///
+    /// Takes effect for the next upstream request. The current fork and its
+    /// cached state are kept, so no warm-up is lost; for that reason the new
+    /// node must serve the chain the service serves, which is checked first.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ServiceError>` - Nothing, or [`ServiceError::Configuration`] if the new
+    ///   node serves another chain (the upstream is left unchanged)
+    pub async fn set_upstream_url(&self, rpc_url: &str) -> Result<(), ServiceError> {
+        let chain_id = chain_id_at(rpc_url, Timeouts::default().rpc).await?;
+        // Before any request, nothing chain-specific has been cached yet
+        let serving = self.chain_id.get().copied().or_else(|| self.forks.chain_id());
+        if let Some(serving) = serving.filter(|serving| *serving != chain_id) {
+            return Err(ServiceError::Configuration(format!(
+                "The new upstream serves chain {}, the service serves chain {}",
+                chain_id, serving
+            )));
+        }
+        self.eth_client.upstream.set(rpc_url)?;
+        self.forks.upstream().set(rpc_url)
+    }
+
+    /// Rendering of human-readable amounts, if enabled
+    pub fn display_units(&self) -> Option<DisplayUnits> {
+        self.display_units
+    }
+
+    /// Safety margin added to estimates by default
+    pub fn headroom(&self) -> Headroom {
+        self.headroom
+    }
+
+    /// Whether the degradation ladder may still answer estimates while the upstream node is unreachable
+    pub fn can_degrade(&self) -> bool {
+        self.degradation.can_serve()
+    }
+
+    /// Number of batch items worked on at once, for a batch of `len` items
+    pub fn batch_concurrency(&self, len: usize) -> usize {
+        self.batch_concurrency.min(len.max(1))
+    }
+
+    /// Check a request and run the pre-simulation stages of the pipeline hooks
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TransactionRequest, ServiceError>` - The (possibly mutated) request, or the
+    ///   error of the check or first hook that rejected it
+    fn prepare_request(&self, tx_request: &TransactionRequest) -> Result<TransactionRequest, ServiceError> {
+        self.prepare_request_with(tx_request, &EstimateOptions::default())
+    }
+
+    /// Check a request under per-request options and run the pre-simulation stages of
+    /// the pipeline hooks
+    ///
+    /// Every authorization's signer is recovered unless the options skip the check.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TransactionRequest, ServiceError>` - The (possibly mutated) request, or the
+    ///   error of the check or first hook that rejected it
+    fn prepare_request_with(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+    ) -> Result<TransactionRequest, ServiceError> {
+        check_authorizations(tx_request, !options.skip_authorization_check)?;
+        for hook in self.hooks.iter() {
+            hook.pre_validate(tx_request)?;
+        }
+        let mut tx_request = tx_request.clone();
+        for hook in self.hooks.iter() {
+            hook.mutate_request(&mut tx_request)?;
+            debug!("Applied mutate_request hook {}", hook.name());
+        }
+        Ok(tx_request)
+    }
+
+    /// Reject reports that re-run the transaction locally on chains served by the upstream backend
+    ///
+    /// Those chains use the upstream backend because the local EVM doesn't
+    /// reproduce their gas model, so a local re-run would contradict the estimate.
+    async fn require_local_simulation(&self, report: &str) -> Result<(), ServiceError> {
+        let backend = self.simulation_backend().await?;
+        if backend.source() != EstimateSource::Simulation {
+            return Err(ServiceError::UnsupportedChain(format!(
+                "{} need local simulation, which the {} backend of this chain doesn't run",
+                report,
+                backend.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Turn the gas a simulation needed into the limit the transaction needs
+    ///
+    /// Under Prague, data-heavy transactions pay at least the calldata floor,
+    /// which can exceed their execution gas. The hooks may then adjust (or veto)
+    /// the result. The headroom is left to the caller, which pads the limit it
+    /// returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `fork` - The fork the transaction was simulated on
+    /// * `tx_request` - The prepared transaction request
+    /// * `gas` - The gas the simulation needed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<U256, ServiceError>` - The gas limit, or the error of a vetoing hook
+    fn adjust_simulated_gas(&self, fork: &Fork, tx_request: &TransactionRequest, mut gas: U256) -> Result<U256, ServiceError> {
+        if fork.spec_id.is_enabled_in(SpecId::PRAGUE) {
+            let input = tx_request.input.input().map_or(&[][..], |input| &input[..]);
+            let floor = U256::from(calldata_floor_gas(input));
+            if gas < floor {
+                debug!("Raising estimate {} to the EIP-7623 calldata floor {}", gas, floor);
+                gas = floor;
+            }
+        }
+
+        for hook in self.hooks.iter() {
+            gas = hook.post_process(tx_request, gas)?;
+        }
+        Ok(gas)
+    }
+
+    /// Run the [`EstimationHook::inspect_touched`] stage of the hooks that opt in
+    ///
+    /// The accounts come from a traced run of the transaction with the estimated
+    /// limit, without headroom. It runs with `eth_call` semantics: which accounts are reached doesn't
+    /// depend on the sender's balance, which is checked separately.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ServiceError>` - Nothing, or the error of the trace or of the first
+    ///   hook that vetoed the estimate
+    async fn inspect_touched(&self, fork: &Fork, tx_request: &TransactionRequest, gas: U256) -> Result<(), ServiceError> {
+        if !self.inspects_touched() {
+            return Ok(());
+        }
+        let traced = TransactionRequest { gas: Some(gas.saturating_to()), ..tx_request.clone() };
+        let touched = touched_accounts_on_fork(fork, &traced, SimulationMode::Call).await?;
+        self.check_touched(tx_request, &touched)
+    }
+
+    /// Whether any hook opts in to the [`EstimationHook::inspect_touched`] stage
+    fn inspects_touched(&self) -> bool {
+        self.hooks.iter().any(|hook| hook.inspects_touched())
+    }
+
+    /// Pass the accounts a transaction touched to the hooks that inspect them
+    fn check_touched(&self, tx_request: &TransactionRequest, touched: &BTreeSet<Address>) -> Result<(), ServiceError> {
+        debug!("Transaction touched {} accounts", touched.len());
+        for hook in self.hooks.iter().filter(|hook| hook.inspects_touched()) {
+            hook.inspect_touched(tx_request, touched)?;
+        }
+        Ok(())
+    }
+
+    /// Reject senders that can't pay `gas` at the request's gas price plus its value
+    ///
+    /// Mirrors the node's balance check (blob fees included), so wallets get the
+    /// familiar `insufficient funds for gas * price + value` error instead of a
+    /// generic simulation failure. The gas is priced at the request's fee cap,
+    /// which [`with_effective_fee_cap`] has lowered to what the sender would pay
+    /// at the fork's base fee. Requests without a sender aren't checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `fork` - The fork the sender's balance is read from
+    /// * `tx_request` - The prepared transaction request
+    /// * `gas` - The gas limit the sender pays for
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ServiceError>` - `Ok` if the balance covers the cost, otherwise an
+    ///   `InsufficientFunds` error naming the balance and the cost
+    async fn check_funds(&self, fork: &Fork, tx_request: &TransactionRequest, gas: U256) -> Result<(), ServiceError> {
+        let Some(from) = tx_request.from else {
+            return Ok(());
+        };
+        let gas_price = tx_request.max_fee_per_gas.or(tx_request.gas_price).unwrap_or_default();
+        let blobs = tx_request.blob_versioned_hashes.as_ref().map_or(0, Vec::len) as u64;
+        let blob_fee = U256::from(blobs * DATA_GAS_PER_BLOB)
+            .saturating_mul(U256::from(tx_request.max_fee_per_blob_gas.unwrap_or_default()));
+        let cost = gas
+            .saturating_mul(U256::from(gas_price))
+            .saturating_add(blob_fee)
+            .saturating_add(tx_request.value.unwrap_or_default());
+
+        let balance = account_on_fork(fork, from).await?.balance;
+        if balance < cost {
+            return Err(ServiceError::InsufficientFunds(format!(
+                "address {} have {} want {}",
+                from, balance, cost
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fill in the sender's current nonce when the request omits it
+    ///
+    /// Simulating with the nonce the transaction will actually be sent with keeps
+    /// estimates consistent with execution (e.g. EIP-7702 authorizations signed by
+    /// the sender). Requests without a sender are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The prepared transaction request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TransactionRequest, ServiceError>` - The request with its nonce set, or an
+    ///   error if the nonce can't be fetched
+    async fn resolve_nonce(&self, tx_request: TransactionRequest) -> Result<TransactionRequest, ServiceError> {
+        if tx_request.from.is_none() || tx_request.nonce.is_some() {
+            return Ok(tx_request);
+        }
+        let head = self.forks.latest().await?;
+        self.resolve_nonce_for(&head, BlockTarget::Latest, tx_request).await
+    }
+
+    /// Fill in the sender's nonce for a simulation against the given block target
+    ///
+    /// Estimates at a block hash use the sender's nonce at that block, so they
+    /// don't depend on the head; other targets follow the configured nonce source.
+    async fn resolve_nonce_for(
+        &self,
+        fork: &Fork,
+        block: BlockTarget,
+        mut tx_request: TransactionRequest,
+    ) -> Result<TransactionRequest, ServiceError> {
+        let (Some(from), None) = (tx_request.from, tx_request.nonce) else {
+            return Ok(tx_request);
+        };
+        let nonce = match (block, self.nonce_source) {
+            (BlockTarget::Hash { .. }, _) | (_, NonceSource::Latest) => account_on_fork(fork, from).await?.nonce,
+            (_, NonceSource::Pending) => self.pending_nonce(fork, from).await?,
+        };
+        debug!("Resolved {:?} nonce of {}: {}", self.nonce_source, from, nonce);
+        tx_request.nonce = Some(nonce);
+        Ok(tx_request)
+    }
+
+    /// The upstream node's pending nonce of a sender, looked up once per head block
+    ///
+    /// Transactions the sender submits while the head stays the same are only
+    /// counted from the next head on. Until then the nonce lags behind the pool,
+    /// but never falls below the sender's nonce at the head.
+    async fn pending_nonce(&self, fork: &Fork, from: Address) -> Result<u64, ServiceError> {
+        let cached = self.pending_nonces.lock().unwrap_or_else(|e| e.into_inner()).get(&from).copied();
+        if let Some((block_hash, nonce)) = cached {
+            if block_hash == fork.block_hash {
+                return Ok(nonce);
+            }
+        }
+        let nonce = self
+            .eth_client
+            .provider
+            .get_transaction_count(from)
+            .pending()
+            .await
+            .map_err(|e| upstream_failure("Failed to get the pending nonce", e))?;
+        self.pending_nonces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .put(from, (fork.block_hash, nonce));
+        Ok(nonce)
+    }
+
+    /// Simulate a transaction and report the full outcome, signed when a key is configured
+    ///
+    /// Unlike [`GasEstimator::estimate_raw_gas`], reverts and halts are reported
+    /// rather than treated as errors, and the block the simulation ran against is
+    /// included so the result can be attributed.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request to simulate
+    /// * `options` - Optional parts of the report
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SimulationReport>` - The report on success, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn simulate(&self, tx_request: &TransactionRequest, options: SimulateOptions) -> Result<SimulationReport> {
+        let tx_request = self.resolve_nonce(self.prepare_request(tx_request)?).await?;
+        let fork = self.forks.latest().await?;
+        // The state diff comes from the recommendation's first run rather than another execution
+        let recommendation = recommend_gas_limit_with_diff(&fork, &tx_request, options.state_diff).await?;
+        let recommended_gas_limit = recommendation.gas_limit;
+        let gas_dependent = recommendation.dependence.detected();
+        let confidence = recommendation.confidence;
+        let limiting_frame = recommendation.limiting_frame;
+        let state_diff = recommendation.state_diff;
+
+        let (status, gas_used, output) = execution_summary(recommendation.result);
+        let memory_usage = memory_usage_on_fork(&fork, &tx_request).await?;
+        let warnings = code_change_warnings_on_fork(&fork, &tx_request).await?;
+
+        let claim = ProvenanceClaim {
+            chain_id: fork.chain_id,
+            block_number: fork.block_number,
+            block_hash: fork.block_hash,
+            request_hash: request_hash(&tx_request)?,
+            gas_used,
+            result_hash: keccak256(&output),
+        };
+        // Record the sender nonce so quote re-validation can detect state drift; the simulation
+        // has read the sender into the fork's cache already, so this costs no RPC
+        let sender_nonce = match tx_request.from {
+            Some(from) => Some(account_on_fork(&fork, from).await?.nonce),
+            None => None,
+        };
+
+        let provenance = self
+            .provenance
+            .as_ref()
+            .map(|signer| signer.sign(&claim))
+            .transpose()?;
+
+        Ok(SimulationReport {
+            chain_id: claim.chain_id,
+            block_number: claim.block_number,
+            block_hash: claim.block_hash,
+            request_hash: claim.request_hash,
+            status,
+            gas_used,
+            recommended_gas_limit,
+            gas_dependent,
+            confidence,
+            limiting_frame,
+            output,
+            result_hash: claim.result_hash,
+            memory_usage,
+            warnings,
+            state_diff,
+            base_fee_per_gas: fork.base_fee_per_gas,
+            base_fee_per_gas_gwei: self.display_units.map(|units| units.gwei(fork.base_fee_per_gas)),
+            valid_until_block: fork.block_number + self.quote_policy().ttl_blocks,
+            from: tx_request.from,
+            sender_nonce,
+            provenance,
+        })
+    }
+
+    /// Simulate transactions back-to-back on the latest fork
+    ///
+    /// Every transaction passes through the pipeline hooks first. Reverts and
+    /// halts are reported in the results rather than treated as errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_requests` - The transactions, in execution order
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SimulatedSequence>` - The block and one result per transaction, or an error
+    pub async fn simulate_sequence(&self, tx_requests: &[TransactionRequest]) -> Result<SimulatedSequence> {
+        let fork = self.forks.latest().await?;
+        self.simulate_sequence_at(&fork, tx_requests).await
+    }
+
+    /// Simulate transactions back-to-back on a given fork
+    ///
+    /// Like [`GasEstimator::simulate_sequence`], for callers comparing several
+    /// sequences against the same block.
+    ///
+    /// # Arguments
+    ///
+    /// * `fork` - The fork to simulate on
+    /// * `tx_requests` - The transactions, in execution order
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SimulatedSequence>` - The block and one result per transaction, or an error
+    pub async fn simulate_sequence_at(&self, fork: &Fork, tx_requests: &[TransactionRequest]) -> Result<SimulatedSequence> {
+        let prepared = tx_requests
+            .iter()
+            .map(|tx_request| self.prepare_request(tx_request))
+            .collect::<Result<Vec<_>, ServiceError>>()?;
+
+        let results = simulate_sequence_on_fork(fork, &prepared, SimulationMode::Estimate).await?;
+        Ok(SimulatedSequence {
+            block_number: fork.block_number,
+            results,
+        })
+    }
+
+    /// Estimate transactions that depend on each other's state changes
+    ///
+    /// Every transaction passes through the pipeline hooks first and runs on the
+    /// latest fork, on top of the state left by the earlier ones, so e.g. a swap
+    /// is estimated with the allowance its preceding approval grants. Like single
+    /// estimates, the limits include the calldata floor and the headroom. Reverts
+    /// and halts are reported in the results rather than treated as errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_requests` - The transactions, in execution order
+    ///
+    /// # Returns
+    ///
+    /// * `Result<SequenceEstimate>` - Per-transaction gas on success, or an error
+    #[instrument(skip(self, tx_requests), err)]
+    pub async fn estimate_sequence(&self, tx_requests: &[TransactionRequest]) -> Result<SequenceEstimate> {
+        let prepared = tx_requests
+            .iter()
+            .map(|tx_request| self.prepare_request(tx_request))
+            .collect::<Result<Vec<_>, ServiceError>>()?;
+
+        let fork = self.forks.latest().await?;
+        let sequence = estimate_sequence_on_fork(&fork, &prepared).await?;
+
+        // Each limit gets the calldata floor, hooks and headroom a single estimate gets
+        let transactions: Vec<SequenceStep> = sequence
+            .into_iter()
+            .zip(&prepared)
+            .enumerate()
+            .map(|(index, (sequenced, tx_request))| {
+                let (status, gas_used, _) = execution_summary(sequenced.result);
+                let gas_limit = sequenced
+                    .gas_limit
+                    .map(|gas| self.adjust_simulated_gas(&fork, tx_request, U256::from(gas)))
+                    .transpose()?
+                    .map(|gas| self.headroom.apply(gas, fork.block_env.gas_limit));
+                Ok(SequenceStep {
+                    index,
+                    status,
+                    gas_used: U256::from(gas_used),
+                    gas_limit,
+                })
+            })
+            .collect::<Result<_, ServiceError>>()?;
+
+        Ok(SequenceEstimate {
+            block_number: fork.block_number,
+            total_gas_used: transactions.iter().map(|step| step.gas_used).sum(),
+            transactions,
+        })
+    }
+
+    /// Estimate a chain of transactions a sender will send back-to-back
+    ///
+    /// Nonces are assigned sequentially from `start_nonce` (or the sender's pending
+    /// nonce) and the transactions are simulated as a dependent chain, so the
+    /// estimate for transaction N reflects the state left by transaction N-1.
+    /// Transactions still pending in the mempool aren't part of the fork, so the
+    /// assigned nonces are reported but not checked against the fork state.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Sender of every transaction
+    /// * `tx_requests` - The transactions, in sending order
+    /// * `start_nonce` - Nonce of the first transaction, if known
+    ///
+    /// # Returns
+    ///
+    /// * `Result<NonceLadder>` - Per-transaction gas on success, or an error
+    #[instrument(skip(self, tx_requests), err)]
+    pub async fn estimate_nonce_ladder(
+        &self,
+        from: Address,
+        tx_requests: &[TransactionRequest],
+        start_nonce: Option<u64>,
+    ) -> Result<NonceLadder> {
+        let start_nonce = match start_nonce {
+            Some(nonce) => nonce,
+            None => self
+                .eth_client
+                .provider
+                .get_transaction_count(from)
+                .pending()
+                .await
+                .map_err(|e| upstream_failure("Failed to get nonce", e))?,
+        };
+        debug!("Estimating nonce ladder of {} transactions from {} starting at nonce {}", tx_requests.len(), from, start_nonce);
+
+        let ladder: Vec<TransactionRequest> = tx_requests
+            .iter()
+            .map(|tx_request| {
+                let mut tx_request = tx_request.clone();
+                tx_request.from = Some(from);
+                tx_request.nonce = None;
+                tx_request
+            })
+            .collect();
+        let sequence = self.simulate_sequence(&ladder).await?;
+
+        let transactions: Vec<NonceLadderStep> = sequence
+            .results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| {
+                let (status, gas_used, _) = execution_summary(result);
+                NonceLadderStep {
+                    index,
+                    nonce: start_nonce + index as u64,
+                    status,
+                    gas_used: U256::from(gas_used),
+                }
+            })
+            .collect();
+
+        Ok(NonceLadder {
+            from,
+            block_number: sequence.block_number,
+            start_nonce,
+            total_gas: transactions.iter().map(|step| step.gas_used).sum(),
+            transactions,
+        })
+    }
+
+    /// Trace a call on the latest fork (`debug_traceCall` semantics)
+    ///
+    /// The call passes through the pipeline hooks first. Code overrides run the
+    /// client's code at their addresses, as for estimates.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The call to trace
+    /// * `options` - The tracer and its options
+    /// * `estimate_options` - Code to run instead of the chain's
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Trace>` - The tracer output, or an error
+    #[instrument(skip(self, tx_request, options, estimate_options), err)]
+    pub async fn trace_call(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &TraceOptions,
+        estimate_options: &EstimateOptions,
+    ) -> Result<Trace> {
+        let tx_request = self.prepare_request_with(tx_request, estimate_options)?;
+        let backend = self.simulation_backend().await?;
+        if backend.source() != EstimateSource::Simulation && !estimate_options.code_overrides.is_empty() {
+            return Err(ServiceError::InvalidParams(format!(
+                "Code overrides can't be simulated with the {} backend",
+                backend.name()
+            ))
+            .into());
+        }
+        let fork = fork_view(self.forks.latest().await?, estimate_options)?;
+        let tx_request = self.resolve_nonce_for(&fork, BlockTarget::Latest, tx_request).await?;
+        let ctx = BackendContext {
+            eth_client: &self.eth_client,
+            fork: &fork,
+            block: backend_block(&fork, BlockTarget::Latest),
+        };
+        Ok(backend.trace(ctx, &tx_request, options).await?)
+    }
+
+    /// Estimate gas and break the gas used down per call frame
+    ///
+    /// The frames are recorded by running the prepared request once more under
+    /// the estimated limit without headroom, on the fork it was estimated
+    /// against, so they show how the gas would be spent by the transaction as
+    /// sent. The same run supplies the touched accounts to the hooks that inspect
+    /// them. Estimates answered by the upstream node carry no frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - Block target, pinned header values, code overrides and headroom
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DetailedEstimate>` - The estimate with its call frames, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn estimate_detailed(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+    ) -> Result<DetailedEstimate> {
+        let traced = match self.forks.fork_at(options.block).await {
+            Ok(head) => self.estimate_traced_on_fork(tx_request, head, options).await,
+            Err(e) => Err(e.into()),
+        };
+        let (estimate, call_frames) = match traced {
+            Ok((estimate, call_frames)) => {
+                if estimate.source == EstimateSource::Simulation && !self.forks.is_orphaned(estimate.block_hash) {
+                    self.degradation.remember(tx_request, options, &estimate);
+                }
+                (estimate, call_frames)
+            }
+            Err(e) if is_outage(&e) => (self.degrade(tx_request, options, e).await?, None),
+            Err(e) => return Err(e),
+        };
+        Ok(DetailedEstimate {
+            gas: estimate.gas,
+            source: estimate.source,
+            block_number: U64::from(estimate.block_number),
+            block_hash: estimate.block_hash,
+            blob_gas: estimate.blob_gas,
+            call_frames,
+        })
+    }
+
+    /// Estimate gas on a fork like [`GasEstimator::estimate_on_fork`], recording the
+    /// call frames of simulated estimates in place of its touched-accounts run
+    async fn estimate_traced_on_fork(
+        &self,
+        tx_request: &TransactionRequest,
+        head: Arc<Fork>,
+        options: &EstimateOptions,
+    ) -> Result<(GasEstimate, Option<CallFrame>)> {
+        let prepared = self.prepare_for_fork(&head, options, tx_request).await?;
+        let (estimate, head, prepared) = self.estimate_prepared_on_fork(tx_request, prepared, head, options).await?;
+        if estimate.source != EstimateSource::Simulation {
+            return Ok((estimate, None));
+        }
+
+        let executed = TransactionRequest { gas: Some(estimate.unpadded().saturating_to()), ..prepared };
+        let (frame, touched) = call_frames_and_touched_on_fork(
+            &head,
+            &executed,
+            TracerConfig::default(),
+            SimulationMode::Estimate,
+            self.inspects_touched(),
+        )
+        .await?;
+        if let Some(touched) = &touched {
+            self.check_touched(&executed, touched)?;
+        }
+        Ok((estimate, Some(frame)))
+    }
+
+    /// Estimate gas and report what the transaction does under the estimate
+    ///
+    /// After estimation the transaction runs once more under the estimated limit
+    /// without headroom, on the fork it was estimated against, to collect its status, return data,
+    /// logs and the address of the contract it deploys, so clients don't need a
+    /// separate simulation. Estimates answered by the upstream node are executed
+    /// the same way, so their status may not be a success. With a `callTracer`
+    /// config, that execution also records the call tree.
+    ///
+    /// A transaction without a gas limit that fails to estimate within the block
+    /// gas limit is measured once more in a block with room for
+    /// [`BLOCK_GAS_LIMIT_PROBE_MULTIPLE`] blocks' worth of gas. If it succeeds
+    /// there, the gas it needs is reported with an `exceedsBlockGasLimit` warning
+    /// instead of the estimation error.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - Block target, pinned header values and headroom
+    /// * `trace` - `callTracer` options, if the call tree is requested
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ExtendedEstimate>` - The estimate with its execution outcome, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn estimate_extended(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+        trace: Option<TracerConfig>,
+    ) -> Result<ExtendedEstimate> {
+        self.require_local_simulation("Extended estimates").await?;
+        let head = self.forks.fork_at(options.block).await?;
+        // Prepared once, for the estimate and for the run under it
+        let prepared = self.prepare_for_fork(&head, options, tx_request).await?;
+        let estimated = self.estimate_prepared_on_fork(tx_request, prepared.clone(), head.clone(), options).await;
+
+        let (estimate, head, mut executed, block_gas_limit) = match estimated {
+            Ok((estimate, head, executed)) => {
+                if estimate.source == EstimateSource::Simulation {
+                    self.inspect_touched(&head, &executed, estimate.unpadded()).await?;
+                }
+                let block_gas_limit = head.block_env.gas_limit;
+                (estimate, head, executed, block_gas_limit)
+            }
+            Err(report) => {
+                let head = fork_view(head, options)?;
+                match self.gas_beyond_block_limit(&head, tx_request, &prepared, &report).await {
+                    Some((roomy, gas_used)) => {
+                        let estimate = GasEstimate {
+                            gas: U256::from(gas_used),
+                            source: EstimateSource::Simulation,
+                            block_number: roomy.block_number,
+                            block_hash: roomy.block_hash,
+                            blob_gas: blob_gas_usage(&roomy, &prepared),
+                            headroom: U256::ZERO,
+                        };
+                        (estimate, roomy, prepared, head.block_env.gas_limit)
+                    }
+                    None => return Err(report),
+                }
+            }
+        };
+        executed.gas = Some(estimate.unpadded().saturating_to());
+        let executed = with_default_blob_fee(executed, &head);
+        let (result, call_frames) = match trace {
+            Some(config) => {
+                let (result, frame) =
+                    traced_execution_on_fork(&head, &executed, config, SimulationMode::Estimate).await?;
+                (result, Some(frame))
+            }
+            None => (simulate_on_fork(&head, &executed, SimulationMode::Estimate).await?.0, None),
+        };
+
+        let contract_address = match &result {
+            ExecutionResult::Success { output: Output::Create(_, address), .. } => *address,
+            _ => None,
+        };
+        let logs = result.logs().to_vec();
+        let (status, gas_used, return_data) = execution_summary(result);
+        debug!("Transaction {} under its estimate with {} logs", status, logs.len());
+        let warnings = self.gas_limit_warnings(estimate.gas, block_gas_limit);
+
+        Ok(ExtendedEstimate {
+            gas: estimate.gas,
+            source: estimate.source,
+            status,
+            gas_used: U256::from(gas_used),
+            return_data,
+            logs,
+            contract_address,
+            block_number: U64::from(estimate.block_number),
+            block_hash: estimate.block_hash,
+            blob_gas: estimate.blob_gas,
+            call_frames,
+            warnings,
+        })
+    }
+
+    /// Gas a transaction needs beyond the block gas limit, if that's why its estimate failed
+    ///
+    /// Only requests without a gas limit are measured, and only when the estimate
+    /// failed in execution: a missing limit is capped at the block gas limit, so a
+    /// transaction needing more runs out of gas there.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(Arc<Fork>, u64)>` - The roomier fork view and the gas used in it, or `None`
+    ///   if the transaction doesn't succeed with more gas either
+    async fn gas_beyond_block_limit(
+        &self,
+        head: &Arc<Fork>,
+        tx_request: &TransactionRequest,
+        executed: &TransactionRequest,
+        report: &eyre::Report,
+    ) -> Option<(Arc<Fork>, u64)> {
+        let failed_in_execution = matches!(
+            report.downcast_ref::<ServiceError>(),
+            Some(ServiceError::Estimation(_) | ServiceError::Simulation(_) | ServiceError::OutOfGas(_))
+        );
+        if tx_request.gas.is_some() || !failed_in_execution {
+            return None;
+        }
+
+        let room = head.block_env.gas_limit.saturating_to::<u64>().saturating_mul(BLOCK_GAS_LIMIT_PROBE_MULTIPLE);
+        let roomy = Arc::new(head.with_block_gas_limit(room));
+        let mut probe = with_default_blob_fee(executed.clone(), &roomy);
+        probe.gas = Some(room);
+        // Whatever keeps the probe from running, the estimation error stands
+        let (result, _) = simulate_on_fork(&roomy, &probe, SimulationMode::Estimate).await.ok()?;
+        let gas_used = result.gas_used();
+        if !result.is_success() || U256::from(gas_used) <= head.block_env.gas_limit {
+            return None;
+        }
+        debug!("Transaction needs {} gas, beyond the block gas limit of {}", gas_used, head.block_env.gas_limit);
+        Some((roomy, gas_used))
+    }
+
+    /// Warnings for an estimate of `gas` in a block holding `block_gas_limit`
+    fn gas_limit_warnings(&self, gas: U256, block_gas_limit: U256) -> Vec<EstimateWarning> {
+        let fraction = self.gas_limit_warning_fraction;
+        if gas > block_gas_limit {
+            return vec![EstimateWarning {
+                kind: EstimateWarningKind::ExceedsBlockGasLimit,
+                message: format!(
+                    "Estimate of {} gas exceeds the block gas limit of {}: the transaction can never fit in a block",
+                    gas, block_gas_limit
+                ),
+            }];
+        }
+        let share = gas.saturating_to::<u64>() as f64 / block_gas_limit.saturating_to::<u64>().max(1) as f64;
+        match fraction > 0.0 && share > fraction {
+            true => vec![EstimateWarning {
+                kind: EstimateWarningKind::NearBlockGasLimit,
+                message: format!(
+                    "Estimate of {} gas is {:.0}% of the block gas limit of {}: the transaction may wait for a block with room for it",
+                    gas,
+                    share * 100.0,
+                    block_gas_limit
+                ),
+            }],
+            false => Vec::new(),
+        }
+    }
+
+    /// Estimate gas and explain what the estimate is made of
+    ///
+    /// After estimation the transaction runs once more under the estimated limit
+    /// without headroom, attributing its gas to intrinsic costs, categories of
+    /// operations and refunds (see [`crate::explain`]); the rest of the estimate,
+    /// headroom included, is margin.
+    /// The same run supplies the touched accounts to the hooks that inspect them.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - Whether to add a plain-text summary and a gas profile
+    /// * `estimate_options` - Block target, pinned header values, code overrides and headroom
+    ///
+    /// # Returns
+    ///
+    /// * `Result<GasExplanation>` - The estimate with its explanation, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn explain_estimate(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &ExplainOptions,
+        estimate_options: &EstimateOptions,
+    ) -> Result<GasExplanation> {
+        self.require_local_simulation("Estimate explanations").await?;
+        let head = self.forks.fork_at(estimate_options.block).await?;
+        let prepared = self.prepare_for_fork(&head, estimate_options, tx_request).await?;
+        let (estimate, head, prepared) =
+            self.estimate_prepared_on_fork(tx_request, prepared, head, estimate_options).await?;
+
+        let inspect_touched = estimate.source == EstimateSource::Simulation && self.inspects_touched();
+        let executed = TransactionRequest { gas: Some(estimate.unpadded().saturating_to()), ..prepared };
+        let breakdown = gas_breakdown_on_fork(&head, &executed, options.profile, inspect_touched).await?;
+        if let Some(touched) = &breakdown.touched {
+            self.check_touched(&executed, touched)?;
+        }
+
+        let mut explanation = GasExplanation {
+            gas: estimate.gas,
+            source: estimate.source,
+            status: breakdown.status,
+            gas_used: breakdown.gas_used,
+            intrinsic: breakdown.intrinsic,
+            execution: breakdown.execution,
+            refund: breakdown.refund,
+            calldata_floor: breakdown.calldata_floor,
+            margin: estimate.gas.saturating_to::<u64>().saturating_sub(breakdown.gas_used),
+            summary: None,
+            profile: breakdown.profile,
+            block_number: U64::from(estimate.block_number),
+            block_hash: estimate.block_hash,
+        };
+        if options.summary {
+            explanation.summary = Some(summarize(&explanation, self.headroom));
+        }
+        Ok(explanation)
+    }
+
+    /// Generate the EIP-2930 access list of a transaction and report what it saves
+    ///
+    /// The transaction is traced once to collect the accounts and storage slots
+    /// it accesses, then run once more with the generated list in place of its
+    /// own. The saving compares the gas used by the two runs, without headroom,
+    /// so it is exactly what including the list changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request parameters
+    /// * `options` - Block target, pinned header values and code overrides
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AccessListReport>` - The access list with the gas used with and without
+    ///   it, or an error
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn create_access_list(
+        &self,
+        tx_request: &TransactionRequest,
+        options: &EstimateOptions,
+    ) -> Result<AccessListReport> {
+        self.require_local_simulation("Access lists").await?;
+
+        let fork = fork_view(self.forks.fork_at(options.block).await?, options)?;
+        let tx_request = self.prepare_request_with(tx_request, options)?;
+        let tx_request = self.resolve_nonce_for(&fork, options.block, tx_request).await?;
+        let (access_list, without) = access_list_on_fork(&fork, &tx_request, SimulationMode::Estimate).await?;
+
+        let mut with_list = tx_request;
+        with_list.access_list = Some(access_list.clone());
+        let with = gas_used_on_fork(&fork, &with_list, SimulationMode::Estimate).await?;
+
+        let gas_saved = without as i64 - with as i64;
+        debug!("Access list of {} accounts saves {} gas", access_list.len(), gas_saved);
+        Ok(AccessListReport {
+            access_list,
+            gas_used: U256::from(with),
+            gas_used_without_access_list: U256::from(without),
+            gas_saved,
+            source: EstimateSource::Simulation,
+            block_number: U64::from(fork.block_number),
+            block_hash: fork.block_hash,
+        })
+    }
+
+    /// Simulate several blocks of calls on top of the latest block (`eth_simulateV1` semantics)
+    ///
+    /// State carries over from call to call and from block to block. Every call
+    /// passes through the pipeline hooks first. Reverts and halts are reported
+    /// per call rather than treated as errors. A simulation runs at most
+    /// [`MAX_SIMULATED_BLOCKS`] blocks and [`MAX_SIMULATED_CALLS`] calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocks` - The blocks of calls, in order
+    /// * `validation` - Validate calls as transactions (nonce, balance, base fee)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<SimulatedBlock>>` - One simulated block per input block, or an error
+    #[instrument(skip(self, blocks), err)]
+    pub async fn simulate_blocks(&self, blocks: Vec<BlockCalls>, validation: bool) -> Result<Vec<SimulatedBlock>> {
+        if blocks.len() > MAX_SIMULATED_BLOCKS {
+            return Err(ServiceError::InvalidParams(format!(
+                "Simulation may contain at most {} blocks",
+                MAX_SIMULATED_BLOCKS
+            ))
+            .into());
+        }
+        if blocks.iter().map(|block| block.calls.len()).sum::<usize>() > MAX_SIMULATED_CALLS {
+            return Err(ServiceError::InvalidParams(format!(
+                "Simulation may contain at most {} calls",
+                MAX_SIMULATED_CALLS
+            ))
+            .into());
+        }
+        let blocks = blocks
+            .into_iter()
+            .map(|block| {
+                let calls = block
+                    .calls
+                    .iter()
+                    .map(|tx_request| self.prepare_request(tx_request))
+                    .collect::<Result<Vec<_>, ServiceError>>()?;
+                Ok(BlockCalls { calls, ..block })
+            })
+            .collect::<Result<Vec<_>, ServiceError>>()?;
+        let mode = if validation { SimulationMode::Estimate } else { SimulationMode::Call };
+
+        let fork = self.forks.latest().await?;
+        let executions = simulate_blocks_on_fork(&fork, blocks, mode).await?;
+
+        let mut parent_hash = fork.block_hash;
+        let mut simulated = Vec::with_capacity(executions.len());
+        for execution in executions {
+            let number: u64 = execution.block_env.number.saturating_to();
+            // Simulated blocks aren't sealed; derive a stable stand-in hash from the parent
+            let hash = keccak256([parent_hash.as_slice(), &number.to_be_bytes()].concat());
+
+            let mut log_index = 0u64;
+            let mut gas_used = 0u64;
+            let calls = execution
+                .results
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| {
+                    gas_used += result.gas_used();
+                    simulated_call(result, number, hash, index as u64, &mut log_index)
+                })
+                .collect();
+
+            simulated.push(SimulatedBlock {
+                number: U64::from(number),
+                hash,
+                parent_hash,
+                timestamp: U64::from(execution.block_env.timestamp.saturating_to::<u64>()),
+                gas_limit: U64::from(execution.block_env.gas_limit.saturating_to::<u64>()),
+                gas_used: U64::from(gas_used),
+                miner: execution.block_env.coinbase,
+                base_fee_per_gas: execution.block_env.basefee,
+                calls,
+            });
+            parent_hash = hash;
+        }
+        Ok(simulated)
+    }
+
+    /// Simulate a bundle of signed transactions, as a Flashbots relay's `eth_callBundle` does
+    ///
+    /// The transactions run back-to-back in a block following the state block
+    /// (`latest` or `pending`), numbered `blockNumber`, with the requested header
+    /// fields. Every transaction passes through the pipeline hooks first. Reverts
+    /// and halts are reported in the results; a transaction failing validation
+    /// fails the bundle.
+    ///
+    /// # Arguments
+    ///
+    /// * `bundle` - The signed transactions and the target block
+    ///
+    /// # Returns
+    ///
+    /// * `Result<CallBundleResponse>` - Per-transaction gas, coinbase payments and outcomes,
+    ///   with the bundle's totals, or an error
+    #[instrument(skip(self, bundle), err)]
+    pub async fn call_bundle(&self, bundle: &CallBundleParams) -> Result<CallBundleResponse> {
+        if bundle.txs.is_empty() {
+            return Err(ServiceError::InvalidParams("Bundle must contain at least one transaction".to_string()).into());
+        }
+        if bundle.txs.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(ServiceError::InvalidParams(format!(
+                "Bundle may contain at most {} transactions",
+                MAX_BUNDLE_TRANSACTIONS
+            ))
+            .into());
+        }
+        let transactions = bundle
+            .txs
+            .iter()
+            .enumerate()
+            .map(|(index, raw)| {
+                decode_bundle_transaction(raw)
+                    .map_err(|e| ServiceError::InvalidParams(format!("transaction {}: {}", index, e)))
+            })
+            .collect::<Result<Vec<_>, ServiceError>>()?;
+        let prepared = transactions
+            .iter()
+            .map(|transaction| self.prepare_request(&transaction.request))
+            .collect::<Result<Vec<_>, ServiceError>>()?;
+
+        let target = match bundle.state_block_number {
+            None | Some(BlockNumberOrTag::Latest) => BlockTarget::Latest,
+            Some(BlockNumberOrTag::Pending) => BlockTarget::Pending,
+            Some(other) => {
+                return Err(ServiceError::InvalidParams(format!(
+                    "Bundles can only run on the latest or pending state, not {}",
+                    other
+                ))
+                .into())
+            }
+        };
+        let fork = self.forks.fork_at(target).await?;
+        let overrides = BlockOverrides {
+            number: Some(bundle.block_number.to()),
+            time: bundle.timestamp,
+            gas_limit: bundle.gas_limit,
+            fee_recipient: bundle.coinbase,
+            base_fee_per_gas: bundle.base_fee,
+            prev_randao: None,
+        };
+        let execution = simulate_bundle_on_fork(&fork, &prepared, &overrides).await?;
+
+        let base_fee = execution.block_env.basefee;
+        let mut total_gas_used = 0u64;
+        let (mut coinbase_diff, mut gas_fees) = (I256::ZERO, U256::ZERO);
+        let results: Vec<BundleTransactionResult> = execution
+            .steps
+            .into_iter()
+            .zip(&transactions)
+            .map(|(step, transaction)| {
+                let gas_used = step.result.gas_used();
+                let tx_gas_fees = U256::from(gas_used).saturating_mul(step.gas_price.saturating_sub(base_fee));
+                total_gas_used += gas_used;
+                coinbase_diff = coinbase_diff.saturating_add(step.coinbase_diff);
+                gas_fees = gas_fees.saturating_add(tx_gas_fees);
+                let (value, error, revert) = match step.result {
+                    ExecutionResult::Success { output, .. } => (Some(output.into_data()), None, None),
+                    ExecutionResult::Revert { output, .. } => (None, Some("execution reverted".to_string()), Some(output)),
+                    ExecutionResult::Halt { reason, .. } => (None, Some(format!("execution halted: {:?}", reason)), None),
+                };
+                BundleTransactionResult {
+                    tx_hash: transaction.hash,
+                    from_address: transaction.request.from.unwrap_or_default(),
+                    to_address: transaction.request.to.and_then(|to| to.to().copied()),
+                    gas_used: U64::from(gas_used),
+                    gas_price: step.gas_price,
+                    gas_fees: tx_gas_fees,
+                    coinbase_diff: step.coinbase_diff,
+                    eth_sent_to_coinbase: step.coinbase_diff.saturating_sub(I256::from_raw(tx_gas_fees)),
+                    value,
+                    error,
+                    revert,
+                }
+            })
+            .collect();
+
+        let hashes: Vec<u8> = transactions.iter().flat_map(|transaction| transaction.hash.0).collect();
+        Ok(CallBundleResponse {
+            bundle_hash: keccak256(hashes),
+            state_block_number: U64::from(fork.block_number),
+            block_number: bundle.block_number,
+            bundle_gas_price: if coinbase_diff.is_positive() {
+                coinbase_diff.into_raw().checked_div(U256::from(total_gas_used)).unwrap_or_default()
+            } else {
+                U256::ZERO
+            },
+            coinbase_diff,
+            eth_sent_to_coinbase: coinbase_diff.saturating_sub(I256::from_raw(gas_fees)),
+            gas_fees,
+            total_gas_used: U64::from(total_gas_used),
+            results,
+        })
+    }
+
+    /// Chain id of the connected chain, fetched (once) from the provider
+    ///
+    /// A failed fetch isn't cached: the next call asks the provider again.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64, ServiceError>` - The chain id, or an error if the provider can't be reached
+    pub async fn chain_id(&self) -> Result<u64, ServiceError> {
+        self.chain_id
+            .get_or_try_init(|| async {
+                self.eth_client
+                    .provider
+                    .get_chain_id()
+                    .await
+                    .map_err(|e| upstream_failure("Failed to get chain id", e))
+            })
+            .await
+            .copied()
+    }
+
+    /// Resolve (once) the chain adapter for the connected chain
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn ChainAdapter>, ServiceError>` - The adapter, or an error if the
+    ///   chain id can't be fetched or the chain's adapter was compiled out
+    pub async fn chain_adapter(&self) -> Result<Arc<dyn ChainAdapter>, ServiceError> {
+        self.chain_adapter
+            .get_or_try_init(|| async { adapter_for_chain(self.chain_id().await?, &self.chain_options) })
+            .await
+            .cloned()
+    }
+
+    /// Resolve (once) the simulation backend for the connected chain
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn SimulationBackend>, ServiceError>` - The backend, or an error if the
+    ///   chain id can't be fetched or the chain's adapter was compiled out
+    pub async fn simulation_backend(&self) -> Result<Arc<dyn SimulationBackend>, ServiceError> {
+        self.simulation_backend
+            .get_or_try_init(|| async {
+                let adapter = self.chain_adapter().await?;
+                let chain_id = self.chain_id().await?;
+                let backend = self.chain_options.simulation_backend(chain_id).backend(adapter);
+                info!("Serving chain {} with the {} simulation backend", chain_id, backend.name());
+                Ok(backend)
+            })
+            .await
+            .cloned()
+    }
+
+    /// Balance and nonce of an account, as served by `eth_getBalance`/`eth_getTransactionCount`
+    ///
+    /// Reads at the head block (`latest`, or its number or hash) are served from
+    /// the shared fork cache, which simulations populate anyway. Other blocks,
+    /// `pending`, and reads the fork fails to serve go to the upstream node.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The account to read
+    /// * `block` - The block to read at
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AccountState>` - The account's balance and nonce, or an error
+    #[instrument(skip(self), err)]
+    pub async fn account_state(&self, address: Address, block: BlockId) -> Result<AccountState> {
+        match self.forks.latest().await {
+            Ok(fork) if block_is_head(block, fork.block_number, fork.block_hash) => {
+                match account_on_fork(&fork, address).await {
+                    Ok(account) => return Ok(account),
+                    Err(e) => warn!("Fork read of {} failed: {}, falling back to upstream", address, e),
+                }
+            }
+            Ok(_) => debug!("Reading {} at {:?} from upstream", address, block),
+            Err(e) => warn!("Failed to get the latest fork: {}, falling back to upstream", e),
+        }
+
+        let provider = &self.eth_client.provider;
+        let (balance, nonce) = tokio::try_join!(
+            provider.get_balance(address).block_id(block).into_future(),
+            provider.get_transaction_count(address).block_id(block).into_future(),
+        )
+        .map_err(|e| upstream_failure(&format!("Failed to read account {}", address), e))?;
+        Ok(AccountState { balance, nonce })
+    }
+
+    /// Execute a call with the simulation backend and return its output (`eth_call` semantics)
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The call parameters
+    /// * `block` - The block to call against
+    /// * `pins` - Header values to simulate with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Bytes>` - The returned data on success, or an error if the call reverted or halted
+    pub async fn call(&self, tx_request: &TransactionRequest, block: BlockTarget, pins: &BlockPins) -> Result<Bytes> {
+        let options = EstimateOptions { block, pins: *pins, ..Default::default() };
+        self.call_with(tx_request, &options).await
+    }
+
+    /// Execute a call with per-request options (`eth_call` semantics)
+    ///
+    /// Like [`GasEstimator::call`], with the code overrides of the options
+    /// applied too. The options' headroom is ignored. The call passes through
+    /// the pre-simulation stages of the pipeline hooks first, so a request a
+    /// hook rejects can't be executed as a call instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The call parameters
+    /// * `options` - Block to call against, header values and code to simulate with
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Bytes>` - The returned data on success, or an error if the call reverted or halted
+    #[instrument(skip(self, tx_request), err)]
+    pub async fn call_with(&self, tx_request: &TransactionRequest, options: &EstimateOptions) -> Result<Bytes> {
+        debug!("Starting call for transaction request: {:?}", tx_request);
+        let tx_request = self.prepare_request_with(tx_request, options)?;
+
+        let backend = self.simulation_backend().await?;
+        let overridden = !options.pins.is_empty() || !options.code_overrides.is_empty();
+        if backend.source() != EstimateSource::Simulation && overridden {
+            return Err(ServiceError::InvalidParams(format!(
+                "Pinned header values and code overrides can't be simulated with the {} backend",
+                backend.name()
+            ))
+            .into());
+        }
+        let block = options.block;
+        let fork = fork_view(self.forks.fork_at(block).await?, options)?;
+        let tx_request = self.resolve_nonce_for(&fork, block, tx_request).await?;
+        let ctx = BackendContext {
+            eth_client: &self.eth_client,
+            fork: &fork,
+            block: backend_block(&fork, block),
+        };
+        Ok(backend.call(ctx, &tx_request).await?)
+    }
+}
+
+/// The view of a fork seeing the pinned header values and code overrides of the options
+fn fork_view(head: Arc<Fork>, options: &EstimateOptions) -> Result<Arc<Fork>, ServiceError> {
+    if options.pins.is_empty() && options.code_overrides.is_empty() {
+        return Ok(head);
+    }
+    Ok(Arc::new(head.with_pins(&options.pins).with_code_overrides(&options.code_overrides)?))
+}
+
+/// Minimum gas of a transaction with the given input under EIP-7623 (Prague)
+///
+/// Each zero byte of the input counts as one token and each other byte as
+/// four; the transaction pays at least 10 gas per token on top of the base cost.
+pub fn calldata_floor_gas(input: &[u8]) -> u64 {
+    let zero_bytes = input.iter().filter(|byte| **byte == 0).count() as u64;
+    let tokens = zero_bytes + (input.len() as u64 - zero_bytes) * 4;
+    TX_BASE_GAS + TOTAL_COST_FLOOR_PER_TOKEN * tokens
+}
+
+/// Blob gas of a type-3 transaction and the blob base fee at the fork's block
+///
+/// Returns `None` for transactions without blobs.
+fn blob_gas_usage(fork: &Fork, tx_request: &TransactionRequest) -> Option<BlobGasUsage> {
+    let blobs = tx_request.blob_versioned_hashes.as_ref().map_or(0, Vec::len) as u64;
+    if blobs == 0 {
+        return None;
+    }
+    let blob_base_fee = fork
+        .block_env
+        .blob_excess_gas_and_price
+        .as_ref()
+        .map_or(0, |blob| blob.blob_gasprice);
+    Some(BlobGasUsage {
+        blob_gas_used: U64::from(blobs * DATA_GAS_PER_BLOB),
+        blob_base_fee: U256::from(blob_base_fee),
+    })
+}
+
+/// Default the blob fee cap of a blob transaction to twice the blob base fee, as geth does
+fn with_default_blob_fee(mut tx_request: TransactionRequest, fork: &Fork) -> TransactionRequest {
+    let has_blobs = tx_request.blob_versioned_hashes.as_ref().is_some_and(|hashes| !hashes.is_empty());
+    if has_blobs && tx_request.max_fee_per_blob_gas.is_none() {
+        let blob_base_fee = fork
+            .block_env
+            .blob_excess_gas_and_price
+            .as_ref()
+            .map_or(0, |blob| blob.blob_gasprice);
+        tx_request.max_fee_per_blob_gas = Some(blob_base_fee.saturating_mul(2));
+    }
+    tx_request
+}
+
+/// Lower the fee cap of an EIP-1559 request to the price it would actually pay at the fork's base fee
+///
+/// A wallet's fee cap usually leaves room for several base fee increases, while
+/// the sender only pays `baseFee + maxPriorityFeePerGas` for the block it is
+/// simulated in. Balance checks and the gas allowance are priced with what the
+/// sender pays, so a sender able to afford the transaction at today's base fee
+/// isn't refused for the margin its wallet added. Fee caps below the base fee
+/// are left alone and rejected by the simulation, as the node would.
+fn with_effective_fee_cap(fork: &Fork, mut tx_request: TransactionRequest) -> TransactionRequest {
+    let Some(max_fee) = tx_request.max_fee_per_gas else {
+        return tx_request;
+    };
+    let base_fee: u128 = fork.base_fee_per_gas.saturating_to();
+    let effective = base_fee.saturating_add(tx_request.max_priority_fee_per_gas.unwrap_or_default());
+    if max_fee >= base_fee && effective < max_fee {
+        debug!("Pricing the request at its effective fee {} instead of its fee cap {}", effective, max_fee);
+        tx_request.max_fee_per_gas = Some(effective);
+    }
+    tx_request
+}
+
+/// Cap the gas limit of a priced request without one at what the sender can afford, as geth does
+///
+/// The simulation otherwise runs under the block gas limit, which a sender
+/// paying e.g. 10 gwei needs a sizeable balance to cover. A sender unable to
+/// pay even for a plain transfer gets the node's insufficient funds error.
+/// Unpriced requests run at a zero gas price and are left uncapped.
+async fn with_gas_allowance(fork: &Fork, mut tx_request: TransactionRequest) -> Result<TransactionRequest, ServiceError> {
+    let fee_cap = tx_request.max_fee_per_gas.or(tx_request.gas_price).unwrap_or_default();
+    let (Some(from), None, true) = (tx_request.from, tx_request.gas, fee_cap > 0) else {
+        return Ok(tx_request);
+    };
+    let blobs = tx_request.blob_versioned_hashes.as_ref().map_or(0, Vec::len) as u64;
+    let blob_fee = U256::from(blobs * DATA_GAS_PER_BLOB)
+        .saturating_mul(U256::from(tx_request.max_fee_per_blob_gas.unwrap_or_default()));
+    let balance = account_on_fork(fork, from).await?.balance;
+    let available = balance
+        .saturating_sub(tx_request.value.unwrap_or_default())
+        .saturating_sub(blob_fee);
+    let allowance = available / U256::from(fee_cap);
+    // Not even a plain transfer fits: the node's error, rather than a failed simulation
+    if allowance < U256::from(TX_BASE_GAS) {
+        let cost = U256::from(TX_BASE_GAS)
+            .saturating_mul(U256::from(fee_cap))
+            .saturating_add(blob_fee)
+            .saturating_add(tx_request.value.unwrap_or_default());
+        return Err(ServiceError::InsufficientFunds(format!(
+            "address {} have {} want {}",
+            from, balance, cost
+        )));
+    }
+    if allowance < fork.block_env.gas_limit {
+        debug!("Gas limit capped at {} by the balance of {}", allowance, from);
+        tx_request.gas = Some(allowance.saturating_to());
+    }
+    Ok(tx_request)
+}
+
+/// The block an upstream backend is asked for, matching the fork the request runs on
+///
+/// Latest requests are sent with the fork's block number, so the upstream
+/// node answers at the same block the response reports even if a new head has
+/// arrived since.
+fn backend_block(fork: &Fork, block: BlockTarget) -> BlockId {
+    match block {
+        BlockTarget::Latest => BlockId::number(fork.block_number),
+        BlockTarget::Pending => BlockId::pending(),
+        BlockTarget::Hash { .. } => block.block_id(),
+    }
+}
+
+/// Whether `block` designates the head block a fork is pinned to
+fn block_is_head(block: BlockId, head_number: u64, head_hash: B256) -> bool {
+    match block {
+        BlockId::Number(BlockNumberOrTag::Latest) => true,
+        BlockId::Number(BlockNumberOrTag::Number(number)) => number == head_number,
+        BlockId::Hash(hash) => hash.block_hash == head_hash,
+        BlockId::Number(_) => false,
+    }
+}
+
+/// Report one call of a simulated block, numbering its logs from `log_index`
+fn simulated_call(
+    result: ExecutionResult,
+    block_number: u64,
+    block_hash: B256,
+    transaction_index: u64,
+    log_index: &mut u64,
+) -> SimulatedCall {
+    match result {
+        ExecutionResult::Success { gas_used, output, logs, .. } => SimulatedCall {
+            status: U64::from(1),
+            return_data: output.into_data(),
+            gas_used: U64::from(gas_used),
+            logs: logs
+                .into_iter()
+                .map(|log| {
+                    let simulated = SimulatedLog {
+                        address: log.address,
+                        topics: log.data.topics().to_vec(),
+                        data: log.data.data,
+                        block_number: U64::from(block_number),
+                        block_hash,
+                        transaction_index: U64::from(transaction_index),
+                        log_index: U64::from(*log_index),
+                    };
+                    *log_index += 1;
+                    simulated
+                })
+                .collect(),
+            error: None,
+        },
+        ExecutionResult::Revert { gas_used, output } => SimulatedCall {
+            status: U64::ZERO,
+            return_data: output.clone(),
+            gas_used: U64::from(gas_used),
+            logs: Vec::new(),
+            error: Some(CallError {
+                code: 3,
+                message: "execution reverted".to_string(),
+                data: Some(output),
+            }),
+        },
+        ExecutionResult::Halt { reason, gas_used } => SimulatedCall {
+            status: U64::ZERO,
+            return_data: Bytes::new(),
+            gas_used: U64::from(gas_used),
+            logs: Vec::new(),
+            error: Some(CallError {
+                code: -32015,
+                message: format!("execution halted: {:?}", reason),
+                data: None,
+            }),
+        },
+    }
+}
+
+/// Split an execution result into its status label, gas used and output
+pub fn execution_summary(result: ExecutionResult) -> (&'static str, u64, Bytes) {
+    match result {
+        ExecutionResult::Success { gas_used, output, .. } => ("success", gas_used, output.into_data()),
+        ExecutionResult::Revert { gas_used, output } => ("revert", gas_used, output),
+        ExecutionResult::Halt { gas_used, .. } => ("halt", gas_used, Bytes::new()),
+    }
+}
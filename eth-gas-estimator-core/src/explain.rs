@@ -0,0 +1,391 @@
+//! Estimate explainability
+//!
+//! Support teams asked "why is this estimate 480k?" need more than a number
+//! but less than a trace. The transaction runs once under its estimate with
+//! [`GasCategoryTracer`], which attributes the gas of every operation to a
+//! category (compute, storage reads and writes, calls, logs, code deposit),
+//! and the explanation puts it next to the intrinsic gas, the refunds and the
+//! margin between the estimate and the gas used:
+//!
+//! * `intrinsic` - charged before any code runs: the base cost, call data,
+//!   contract creation, access list and authorizations
+//! * `execution` - the gas of each category and the number of operations
+//! * `refund` - returned at the end of the transaction
+//! * `margin` - the estimate above the gas used: gas the transaction needs
+//!   available but doesn't use, and the configured headroom
+//!
+//! The gas forwarded to a call is attributed to the callee's own operations,
+//! so the `calls` category only holds the cost of calling (account access,
+//! value transfer, new accounts) and precompile execution.
+//!
+//! On request the same execution is profiled per opcode and per contract (see
+//! [`crate::profile`]). It also lists the accounts the transaction touches for
+//! the pipeline hooks that inspect them, so an explanation doesn't run the
+//! transaction again for them.
+
+use crate::{
+    error::ServiceError,
+    estimator::{calldata_floor_gas, execution_summary, Headroom},
+    foundry::{execute_on_fork, tx_env_for_fork, Fork, SimulationMode},
+    intrinsic::{
+        ACCESS_LIST_ADDRESS_GAS, ACCESS_LIST_STORAGE_KEY_GAS, AUTHORIZATION_GAS, CALLDATA_NONZERO_BYTE_GAS,
+        CALLDATA_NONZERO_BYTE_GAS_FRONTIER, CALLDATA_ZERO_BYTE_GAS, CREATE_GAS, INITCODE_WORD_GAS, TX_BASE_GAS,
+    },
+    models::explain::{ExecutionGas, GasCategory, GasExplanation, GasProfile, IntrinsicGas},
+    profile::GasProfiler,
+    tracers::{touched_accounts, PrestateTracer},
+};
+use alloy::{
+    primitives::{Address, TxKind, U256},
+    rpc::types::TransactionRequest,
+};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, InterpreterResult},
+    primitives::{ExecutionResult, SpecId},
+    Database, EvmContext, Inspector,
+};
+use std::{cmp::Reverse, collections::BTreeSet};
+use tracing::debug;
+
+/// Inspector attributing the gas of every operation to a category
+#[derive(Debug, Default)]
+pub struct GasCategoryTracer {
+    /// Opcode of the operation being executed, and the gas left before it
+    pending: Option<(u8, u64)>,
+    /// Gas spent so far by each open frame, outermost first
+    frames: Vec<u64>,
+    /// Gas by category
+    execution: ExecutionGas,
+    /// Per-opcode and per-contract profile, if requested
+    profiler: Option<GasProfiler>,
+    /// Accounts touched, if requested
+    touched: Option<PrestateTracer>,
+}
+
+impl GasCategoryTracer {
+    /// Create a tracer, also profiling the gas per opcode and per contract if `profile` is set
+    pub fn new(profile: bool) -> Self {
+        Self {
+            profiler: profile.then(GasProfiler::default),
+            ..Self::default()
+        }
+    }
+
+    /// Category of an opcode
+    fn category(&mut self, op: u8) -> &mut GasCategory {
+        match op {
+            opcode::SLOAD | opcode::BALANCE | opcode::EXTCODESIZE | opcode::EXTCODECOPY | opcode::EXTCODEHASH => {
+                &mut self.execution.storage_reads
+            }
+            opcode::SSTORE => &mut self.execution.storage_writes,
+            opcode::CALL
+            | opcode::CALLCODE
+            | opcode::DELEGATECALL
+            | opcode::STATICCALL
+            | opcode::CREATE
+            | opcode::CREATE2
+            | opcode::SELFDESTRUCT => &mut self.execution.calls,
+            opcode::LOG0..=opcode::LOG4 => &mut self.execution.logs,
+            _ => &mut self.execution.compute,
+        }
+    }
+
+    /// Open a frame given `gas_limit`
+    fn enter(&mut self, gas_limit: u64) {
+        // The call opcode was charged the forwarded gas, which the callee's operations spend
+        if let Some(parent) = self.frames.last_mut() {
+            *parent = parent.saturating_sub(gas_limit);
+            self.execution.calls.gas = self.execution.calls.gas.saturating_sub(gas_limit);
+        }
+        self.frames.push(0);
+    }
+
+    /// Close the innermost frame
+    fn exit(&mut self, result: &InterpreterResult, created: bool) {
+        let spent = result.gas.spent();
+        // Gas spent outside the frame's operations: precompile execution, code
+        // deposit, or the gas left when the frame failed
+        let unaccounted = spent.saturating_sub(self.frames.pop().unwrap_or_default());
+        if created && result.is_ok() {
+            self.execution.code_deposit.gas += unaccounted;
+            self.execution.code_deposit.count += 1;
+        } else {
+            self.execution.calls.gas += unaccounted;
+        }
+        match self.frames.last_mut() {
+            Some(parent) => *parent += spent,
+            None => self.execution.total = spent,
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasCategoryTracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.pending = Some((interp.current_opcode(), interp.gas.remaining()));
+        if let Some(profiler) = &mut self.profiler {
+            profiler.step(interp, context);
+        }
+        if let Some(touched) = &mut self.touched {
+            touched.step(interp, context);
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.step_end(interp, context);
+        }
+        let Some((op, gas_left)) = self.pending.take() else {
+            return;
+        };
+        let cost = gas_left.saturating_sub(interp.gas.remaining());
+        let category = self.category(op);
+        category.gas += cost;
+        category.count += 1;
+        if let Some(frame) = self.frames.last_mut() {
+            *frame += cost;
+        }
+    }
+
+    fn call(&mut self, context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.call(context, inputs);
+        }
+        if let Some(touched) = &mut self.touched {
+            touched.call(context, inputs);
+        }
+        self.enter(inputs.gas_limit);
+        None
+    }
+
+    fn call_end(&mut self, context: &mut EvmContext<DB>, inputs: &CallInputs, outcome: CallOutcome) -> CallOutcome {
+        self.exit(&outcome.result, false);
+        match &mut self.profiler {
+            Some(profiler) => profiler.call_end(context, inputs, outcome),
+            None => outcome,
+        }
+    }
+
+    fn create(&mut self, context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.create(context, inputs);
+        }
+        if let Some(touched) = &mut self.touched {
+            touched.create(context, inputs);
+        }
+        self.enter(inputs.gas_limit);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.exit(&outcome.result, true);
+        let outcome = match &mut self.touched {
+            Some(touched) => touched.create_end(context, inputs, outcome),
+            None => outcome,
+        };
+        match &mut self.profiler {
+            Some(profiler) => profiler.create_end(context, inputs, outcome),
+            None => outcome,
+        }
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        if let Some(touched) = &mut self.touched {
+            Inspector::<DB>::selfdestruct(touched, contract, target, value);
+        }
+    }
+}
+
+/// Intrinsic gas of a transaction, by component
+///
+/// # Arguments
+///
+/// * `tx_request` - The transaction request
+/// * `spec_id` - Hard fork the transaction runs under
+pub fn intrinsic_gas(tx_request: &TransactionRequest, spec_id: SpecId) -> IntrinsicGas {
+    let input = tx_request.input.input().map_or(&[][..], |input| &input[..]);
+    let zero_bytes = input.iter().filter(|byte| **byte == 0).count() as u64;
+    let nonzero_byte_gas = match spec_id.is_enabled_in(SpecId::ISTANBUL) {
+        true => CALLDATA_NONZERO_BYTE_GAS,
+        false => CALLDATA_NONZERO_BYTE_GAS_FRONTIER,
+    };
+    let calldata = CALLDATA_ZERO_BYTE_GAS * zero_bytes + nonzero_byte_gas * (input.len() as u64 - zero_bytes);
+
+    let creation = match tx_request.to {
+        Some(TxKind::Call(_)) => 0,
+        _ if spec_id.is_enabled_in(SpecId::SHANGHAI) => CREATE_GAS + INITCODE_WORD_GAS * input.len().div_ceil(32) as u64,
+        _ => CREATE_GAS,
+    };
+    let access_list = tx_request.access_list.as_ref().map_or(0, |access_list| {
+        access_list
+            .iter()
+            .map(|item| ACCESS_LIST_ADDRESS_GAS + ACCESS_LIST_STORAGE_KEY_GAS * item.storage_keys.len() as u64)
+            .sum()
+    });
+    let authorizations = AUTHORIZATION_GAS * tx_request.authorization_list.as_ref().map_or(0, Vec::len) as u64;
+
+    IntrinsicGas {
+        total: TX_BASE_GAS + calldata + creation + access_list + authorizations,
+        base: TX_BASE_GAS,
+        calldata,
+        creation,
+        access_list,
+        authorizations,
+    }
+}
+
+/// Where the gas of a transaction goes
+#[derive(Debug)]
+pub struct GasBreakdown {
+    /// "success", "revert" or "halt"
+    pub status: &'static str,
+    /// Gas used
+    pub gas_used: u64,
+    /// Intrinsic gas
+    pub intrinsic: IntrinsicGas,
+    /// Execution gas by kind of operation
+    pub execution: ExecutionGas,
+    /// Gas refunded at the end of the transaction
+    pub refund: u64,
+    /// Gas added to reach the EIP-7623 calldata floor
+    pub calldata_floor: Option<u64>,
+    /// Execution gas per opcode and per contract, if requested
+    pub profile: Option<GasProfile>,
+    /// The sender, the recipient and every account touched during execution, if requested
+    pub touched: Option<BTreeSet<Address>>,
+}
+
+/// Break down the gas of a transaction on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request, with its gas limit
+/// * `profile` - Whether to also profile the gas per opcode and per contract
+/// * `touched` - Whether to also list the accounts the transaction touches
+///
+/// # Returns
+///
+/// * `Result<GasBreakdown, ServiceError>` - Where the gas goes, or an error if the
+///   transaction fails validation
+pub async fn gas_breakdown_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    profile: bool,
+    touched: bool,
+) -> Result<GasBreakdown, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, SimulationMode::Estimate)?;
+    let tracer = GasCategoryTracer {
+        touched: touched.then(PrestateTracer::default),
+        ..GasCategoryTracer::new(profile)
+    };
+    let (result, tracer) = execute_on_fork(fork, tx_env.clone(), SimulationMode::Estimate, Some(tracer)).await?;
+    let tracer = tracer.unwrap_or_default();
+    let execution = tracer.execution;
+    let profile = tracer.profiler.map(GasProfiler::into_profile);
+    let touched = tracer.touched.map(|tracer| touched_accounts(&tx_env, tracer));
+    let intrinsic = intrinsic_gas(tx_request, fork.spec_id);
+    let refund = match &result {
+        ExecutionResult::Success { gas_refunded, .. } => *gas_refunded,
+        _ => 0,
+    };
+    let (status, gas_used, _) = execution_summary(result);
+
+    let charged = (intrinsic.total + execution.total).saturating_sub(refund);
+    let input = tx_request.input.input().map_or(&[][..], |input| &input[..]);
+    let calldata_floor = (fork.spec_id.is_enabled_in(SpecId::PRAGUE) && calldata_floor_gas(input) > charged)
+        .then(|| calldata_floor_gas(input) - charged);
+    debug!(
+        "Gas used {}: {} intrinsic, {} execution, {} refunded",
+        gas_used, intrinsic.total, execution.total, refund
+    );
+    Ok(GasBreakdown {
+        status,
+        gas_used,
+        intrinsic,
+        execution,
+        refund,
+        calldata_floor,
+        profile,
+        touched,
+    })
+}
+
+/// Plain-text summary of an explanation
+///
+/// # Arguments
+///
+/// * `explanation` - The explanation
+/// * `headroom` - Safety margin the estimate was padded with
+pub fn summarize(explanation: &GasExplanation, headroom: Headroom) -> String {
+    let intrinsic = &explanation.intrinsic;
+    let intrinsic_parts: Vec<String> = [
+        (intrinsic.base, "base"),
+        (intrinsic.calldata, "calldata"),
+        (intrinsic.creation, "contract creation"),
+        (intrinsic.access_list, "access list"),
+        (intrinsic.authorizations, "authorizations"),
+    ]
+    .into_iter()
+    .filter(|(gas, _)| *gas > 0)
+    .map(|(gas, name)| format!("{} {}", gas, name))
+    .collect();
+
+    let execution = &explanation.execution;
+    let mut categories = [
+        (execution.storage_writes, "storage writes"),
+        (execution.storage_reads, "storage reads"),
+        (execution.calls, "calls"),
+        (execution.logs, "logs"),
+        (execution.code_deposit, "contracts deployed"),
+        (execution.compute, "other operations"),
+    ];
+    categories.sort_by_key(|(category, _)| Reverse(category.gas));
+    let execution_parts: Vec<String> = categories
+        .iter()
+        .filter(|(category, _)| category.gas > 0)
+        .map(|(category, name)| format!("{} for {} {}", category.gas, category.count, name))
+        .collect();
+
+    let mut lines = vec![
+        format!(
+            "Estimate of {} gas at block {}; the transaction uses {} gas ({}).",
+            explanation.gas, explanation.block_number, explanation.gas_used, explanation.status
+        ),
+        format!("Intrinsic: {} gas ({}).", intrinsic.total, intrinsic_parts.join(", ")),
+    ];
+    match execution_parts.is_empty() {
+        true => lines.push("Execution: no code runs.".to_string()),
+        false => lines.push(format!("Execution: {} gas ({}).", execution.total, execution_parts.join(", "))),
+    }
+    if explanation.refund > 0 {
+        lines.push(format!("Refunds: -{} gas.", explanation.refund));
+    }
+    if let Some(floor) = explanation.calldata_floor {
+        lines.push(format!("Calldata floor (EIP-7623): +{} gas.", floor));
+    }
+
+    let mut reasons = Vec::new();
+    if explanation.refund > 0 {
+        reasons.push("the limit must cover the gas spent before refunds".to_string());
+    }
+    if execution.calls.count > 0 {
+        reasons.push("calls forward at most 63/64 of the gas left".to_string());
+    }
+    if headroom != Headroom::default() {
+        reasons.push(format!("the configured headroom (x{} + {} gas)", headroom.multiplier, headroom.buffer));
+    }
+    match reasons.is_empty() {
+        true => lines.push(format!("Margin: {} gas above the gas used.", explanation.margin)),
+        false => lines.push(format!(
+            "Margin: {} gas above the gas used, as {}.",
+            explanation.margin,
+            reasons.join(" and ")
+        )),
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,245 @@
+//! Deterministic chain fixtures for the EIP-4844 and EIP-7702 paths
+//!
+//! Blob and authorization transactions are only valid with real KZG
+//! commitments and real authorization signatures, so testing them against a
+//! node means either crafting both by hand or settling for placeholders the
+//! EVM never validates. [`FixtureChain`] builds a fixed Prague chain state in
+//! memory instead:
+//!
+//! * the funded Anvil dev accounts, together with their private keys
+//! * helper contracts: [`COUNTER_ADDRESS`], incrementing its slot 0 on every
+//!   call (also the delegation target of the fixture authorizations), and
+//!   [`BLOB_HASH_STORE_ADDRESS`], storing the first blob hash of the
+//!   transaction in its slot 0
+//! * a blob sidecar whose commitments and proofs are computed with the
+//!   Ethereum KZG trusted setup
+//! * EIP-7702 authorizations signed with the dev keys
+//!
+//! The state loads into a revm [`InMemoryDB`], and [`FixtureChain::transact`]
+//! runs a transaction request through the estimator's own conversion and
+//! validation, so the code paths can be tested and benchmarked without Anvil.
+//! The fixtures never change between runs: the same request always yields the
+//! same hashes, signatures and gas.
+
+use crate::{
+    error::ServiceError,
+    estimator::GWEI,
+    foundry::{configure_cfg_env, convert_tx_request_to_tx_env, transaction_error, SimulationMode},
+};
+use alloy::{
+    consensus::{BlobTransactionSidecar, SidecarBuilder, SimpleCoder},
+    eips::eip7702::{Authorization, SignedAuthorization},
+    primitives::{address, b256, bytes, Address, Bytes, TxKind, B256, U256},
+    rpc::types::TransactionRequest,
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use revm::{
+    db::InMemoryDB,
+    primitives::{AccountInfo, BlobExcessGasAndPrice, BlockEnv, Bytecode, ExecutionResult, SpecId, MIN_BLOB_GASPRICE},
+    DatabaseCommit, Evm,
+};
+
+/// Chain ID of the fixture chain, the Anvil default
+pub const FIXTURE_CHAIN_ID: u64 = 31337;
+
+/// Number of the fixture block
+pub const FIXTURE_BLOCK_NUMBER: u64 = 1;
+
+/// Timestamp of the fixture block, the Prague activation on mainnet
+pub const FIXTURE_TIMESTAMP: u64 = 1_746_612_311;
+
+/// Base fee of the fixture block, 1 gwei
+pub const FIXTURE_BASE_FEE: u64 = GWEI;
+
+/// Gas limit of the fixture block
+pub const FIXTURE_GAS_LIMIT: u64 = 30_000_000;
+
+/// Hard fork of the fixture chain
+pub const FIXTURE_SPEC_ID: SpecId = SpecId::PRAGUE;
+
+/// Private keys of the first two Anvil dev accounts, `DEV_ACCOUNT` and `DEV_RECIPIENT`
+pub const DEV_PRIVATE_KEYS: [B256; 2] = [
+    b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"),
+    b256!("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"),
+];
+
+/// Address of the counter contract
+pub const COUNTER_ADDRESS: Address = address!("0000000000000000000000000000000000c0ffee");
+
+/// Runtime code of the counter contract: `sstore(0, add(sload(0), 1))`
+pub const COUNTER_CODE: Bytes = bytes!("60005460010160005500");
+
+/// Address of the blob hash store contract
+pub const BLOB_HASH_STORE_ADDRESS: Address = address!("0000000000000000000000000000000000b10b00");
+
+/// Runtime code of the blob hash store contract: `sstore(0, blobhash(0))`
+pub const BLOB_HASH_STORE_CODE: Bytes = bytes!("5f495f5500");
+
+/// Payload encoded into the fixture blob sidecar
+const BLOB_PAYLOAD: &[u8] = b"eth-gas-estimator blob fixture";
+
+/// A deterministic in-memory chain state for the exotic transaction types
+pub struct FixtureChain {
+    /// Signers of the funded dev accounts
+    signers: Vec<PrivateKeySigner>,
+    /// Blob sidecar with valid KZG commitments and proofs
+    sidecar: BlobTransactionSidecar,
+}
+
+impl Default for FixtureChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixtureChain {
+    /// Build the fixtures
+    ///
+    /// # Panics
+    ///
+    /// Panics if the KZG trusted setup can't be loaded.
+    pub fn new() -> Self {
+        let signers = DEV_PRIVATE_KEYS
+            .iter()
+            .map(|key| PrivateKeySigner::from_bytes(key).expect("Invalid fixture private key"))
+            .collect();
+        let sidecar = SidecarBuilder::<SimpleCoder>::from_slice(BLOB_PAYLOAD)
+            .build()
+            .expect("Failed to build the fixture blob sidecar");
+        Self { signers, sidecar }
+    }
+
+    /// Signers of the funded dev accounts, in the order of [`DEV_PRIVATE_KEYS`]
+    pub fn signers(&self) -> &[PrivateKeySigner] {
+        &self.signers
+    }
+
+    /// Address of the dev account at `index`
+    pub fn account(&self, index: usize) -> Address {
+        self.signers[index].address()
+    }
+
+    /// Balance of every dev account, 10000 ETH
+    pub fn balance(&self) -> U256 {
+        U256::from(10_000u64) * U256::from(10u64).pow(U256::from(18u64))
+    }
+
+    /// A fresh database holding the fixture state
+    pub fn db(&self) -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        for signer in &self.signers {
+            db.insert_account_info(signer.address(), AccountInfo::from_balance(self.balance()));
+        }
+        for (address, code) in [(COUNTER_ADDRESS, COUNTER_CODE), (BLOB_HASH_STORE_ADDRESS, BLOB_HASH_STORE_CODE)] {
+            let code = Bytecode::new_raw(code);
+            db.insert_account_info(address, AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code));
+        }
+        db
+    }
+
+    /// Environment of the fixture block, without excess blob gas
+    pub fn block_env(&self) -> BlockEnv {
+        BlockEnv {
+            number: U256::from(FIXTURE_BLOCK_NUMBER),
+            timestamp: U256::from(FIXTURE_TIMESTAMP),
+            gas_limit: U256::from(FIXTURE_GAS_LIMIT),
+            basefee: U256::from(FIXTURE_BASE_FEE),
+            prevrandao: Some(B256::ZERO),
+            blob_excess_gas_and_price: Some(BlobExcessGasAndPrice {
+                excess_blob_gas: 0,
+                blob_gasprice: u128::from(MIN_BLOB_GASPRICE),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Blob sidecar with valid KZG commitments and proofs
+    pub fn blob_sidecar(&self) -> &BlobTransactionSidecar {
+        &self.sidecar
+    }
+
+    /// Versioned hashes of the fixture blobs
+    pub fn blob_versioned_hashes(&self) -> Vec<B256> {
+        self.sidecar.versioned_hashes().collect()
+    }
+
+    /// An EIP-7702 authorization signed by a dev account
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the signing dev account
+    /// * `delegate` - Contract the account delegates to
+    /// * `nonce` - Nonce of the account when the authorization is applied
+    pub fn sign_authorization(&self, index: usize, delegate: Address, nonce: u64) -> SignedAuthorization {
+        let authorization = Authorization { chain_id: U256::from(FIXTURE_CHAIN_ID), address: delegate, nonce };
+        let signature = self.signers[index]
+            .sign_hash_sync(&authorization.signature_hash())
+            .expect("Failed to sign the fixture authorization");
+        authorization.into_signed(signature)
+    }
+
+    /// A blob transaction from the first dev account to the blob hash store
+    pub fn blob_request(&self) -> TransactionRequest {
+        TransactionRequest {
+            from: Some(self.account(0)),
+            to: Some(TxKind::Call(BLOB_HASH_STORE_ADDRESS)),
+            gas: Some(100_000),
+            max_fee_per_gas: Some(u128::from(2 * FIXTURE_BASE_FEE)),
+            max_priority_fee_per_gas: Some(u128::from(GWEI)),
+            max_fee_per_blob_gas: Some(u128::from(GWEI)),
+            blob_versioned_hashes: Some(self.blob_versioned_hashes()),
+            sidecar: Some(self.sidecar.clone()),
+            chain_id: Some(FIXTURE_CHAIN_ID),
+            ..Default::default()
+        }
+    }
+
+    /// An authorization transaction from the first dev account to the second,
+    /// which delegates to the counter contract
+    pub fn authorization_request(&self) -> TransactionRequest {
+        TransactionRequest {
+            from: Some(self.account(0)),
+            to: Some(TxKind::Call(self.account(1))),
+            gas: Some(100_000),
+            max_fee_per_gas: Some(u128::from(2 * FIXTURE_BASE_FEE)),
+            max_priority_fee_per_gas: Some(u128::from(GWEI)),
+            authorization_list: Some(vec![self.sign_authorization(1, COUNTER_ADDRESS, 0)]),
+            chain_id: Some(FIXTURE_CHAIN_ID),
+            ..Default::default()
+        }
+    }
+
+    /// Execute a transaction on a fixture database and commit its changes
+    ///
+    /// The request goes through the same conversion and validation as an
+    /// estimate, at the fixture block.
+    ///
+    /// # Arguments
+    ///
+    /// * `db` - The database, as returned by [`FixtureChain::db`]
+    /// * `tx_request` - The transaction request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ExecutionResult, ServiceError>` - The execution result, or an error if the
+    ///   transaction fails validation
+    pub fn transact(&self, db: &mut InMemoryDB, tx_request: &TransactionRequest) -> Result<ExecutionResult, ServiceError> {
+        let tx_env =
+            convert_tx_request_to_tx_env(tx_request).map_err(|e| ServiceError::Simulation(e.to_string()))?;
+        let caller = tx_env.caller;
+        let outcome = Evm::builder()
+            .with_db(&mut *db)
+            .with_spec_id(FIXTURE_SPEC_ID)
+            .modify_cfg_env(|cfg| {
+                configure_cfg_env(cfg, SimulationMode::Estimate, &tx_env);
+                cfg.chain_id = FIXTURE_CHAIN_ID;
+            })
+            .with_block_env(self.block_env())
+            .with_tx_env(tx_env)
+            .build()
+            .transact()
+            .map_err(|e| transaction_error(caller, e))?;
+        db.commit(outcome.state);
+        Ok(outcome.result)
+    }
+}
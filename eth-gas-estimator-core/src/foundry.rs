@@ -0,0 +1,2458 @@
+use crate::{
+    chains::{
+        precompiles::{precompiles_register, CustomPrecompiles},
+        profile::{detect_profile, BlobParams, ChainProfile},
+        ChainOptions,
+    },
+    correlation::correlation_id,
+    estimator::DEFAULT_GAS_LIMIT,
+    rpc::{timeout_rpc_client, upstream_failure, UpstreamUrl},
+    simulation_limit::{SimulationLimiter, SimulationStats},
+    storage_batch::{StorageBatcher, StorageBatching},
+    timeouts::{deadline, within_deadline, Timeouts},
+    error::ServiceError,
+    gas_dependence::{recommend_gas_limit, FailedFrame, GasDependenceInspector},
+    models::simulation::AccountDiff,
+    state_diff::account_diffs,
+};
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag, RpcBlockHash},
+    network::{AnyNetwork, AnyRpcBlock},
+    primitives::{Address, Bytes, U256, U64, TxKind, B256},
+    providers:: { DynProvider, Provider as AlloyProvider, ProviderBuilder },
+    rpc::{client::Waiter, types::TransactionRequest},
+    transports::TransportResult,
+    consensus::BlockHeader,
+};
+use foundry_fork_db::{cache::BlockchainDbMeta, BlockchainDb, DatabaseError, SharedBackend};
+use serde::Deserialize;
+use revm::{
+    db::{AccountState as DbAccountState, CacheDB},
+    handler::register::HandleRegisterBox,
+    inspector_handle_register,
+    interpreter::{opcode, InstructionResult},
+    primitives::{
+        fake_exponential, AccountInfo, BlockEnv, Bytecode, CfgEnv, Bytes as RevmBytes, Eip7702Bytecode, EVMError, ExecutionResult, HaltReason, InvalidTransaction, SpecId,
+        KECCAK_EMPTY, MIN_BLOB_GASPRICE,
+        TransactTo, TxEnv, Address as RevmAddress, U256 as RevmU256, B256 as RevmB256, AccessListItem, AccessList, BlobExcessGasAndPrice, AuthorizationList,
+    },
+    Database, DatabaseCommit, DatabaseRef, Evm, Inspector,
+};
+use lru::LruCache;
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, VecDeque},
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    runtime::Handle,
+    sync::{Mutex, OnceCell, Semaphore},
+};
+use tracing::{debug, info, error, warn};
+
+/// Build a concrete AnyNetwork provider for simulation purposes.
+/// 
+/// This creates a provider specifically for use with the Foundry fork system, allowing
+/// us to simulate transactions against a fork of the current network state.
+/// 
+/// # Arguments
+///
+/// * `upstream` - URL of the Ethereum RPC endpoint to connect to
+/// * `timeout` - Upper bound on each RPC call
+///
+/// # Returns
+///
+/// * A provider that can be used for blockchain interactions, or an error
+fn build_any_provider(
+    upstream: &UpstreamUrl,
+    timeout: Duration,
+) -> Result<impl AlloyProvider<AnyNetwork> + Clone + Unpin + 'static, ServiceError> {
+    // Create a new provider using the AnyNetwork type for flexibility
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .on_client(timeout_rpc_client(upstream, timeout)?);
+
+    Ok(provider)
+}
+
+/// How a simulated transaction is validated before execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationMode {
+    /// Full transaction validation, as the transaction would be included on chain
+    Estimate,
+    /// `eth_call` semantics: balance and base fee checks are skipped, and a
+    /// missing gas limit defaults to the block gas limit
+    Call,
+}
+
+/// Estimate gas usage for a transaction by simulating it using Foundry's fork database
+///
+/// This function simulates the transaction on the given fork to determine the
+/// exact gas required. Transactions whose execution depends on the gas they
+/// receive are re-simulated until the recommended limit is stable (see
+/// [`crate::gas_dependence`]).
+///
+/// # Arguments
+///
+/// * `fork` - The (shared) fork to simulate against
+/// * `tx_request` - The transaction request to simulate
+///
+/// # Returns
+///
+/// * `Result<U256, ServiceError>` - The estimated gas on success, or an error
+///   ([`ServiceError::Reverted`] if the transaction reverts, [`ServiceError::OutOfGas`]
+///   if it reverts after a subcall ran out of gas)
+pub async fn estimate_gas_from_request_foundry(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+) -> Result<U256, ServiceError> {
+    let recommendation = recommend_gas_limit(fork, tx_request).await?;
+
+    // Log the outcome based on the execution result
+    match &recommendation.result {
+        ExecutionResult::Success { gas_used, .. } => {
+            // For success, just log debug (or info)
+            debug!("EVM simulation SUCCESS with gas_used: {}", gas_used);
+        }
+        ExecutionResult::Revert { gas_used, output } => {
+            error!("EVM simulation REVERTED with gas_used: {}", gas_used);
+            return Err(match recommendation.limiting_frame {
+                // A subcall ran out of gas before the revert, e.g. a `require` on a call starved of gas:
+                // the revert is down to the gas available, not the transaction's own logic
+                Some(frame) => ServiceError::OutOfGas(frame),
+                // A reverting transaction has no gas limit to recommend; report its revert data, as nodes do
+                None => ServiceError::Reverted(output.clone()),
+            });
+        }
+        ExecutionResult::Halt { reason, gas_used } => {
+            // Halting under the largest possible limit, the transaction can't succeed with any limit
+            error!("EVM simulation HALTED with gas_used: {}", gas_used);
+            return Err(match (reason, recommendation.limiting_frame) {
+                (HaltReason::OutOfGas(_), Some(frame)) => ServiceError::OutOfGas(frame),
+                _ => ServiceError::Simulation(format!("execution halted: {:?}", reason)),
+            });
+        }
+    };
+
+    let gas = U256::from(recommendation.gas_limit);
+    debug!("Gas estimation completed successfully: {:?} (confidence: {:?})", gas, recommendation.confidence);
+    Ok(gas)
+}
+
+/// A fork of the chain pinned at a single block
+///
+/// The backend lazily fetches account and storage state at the pinned block
+/// and caches it, so every simulation against the same fork shares that state.
+pub struct Fork {
+    /// Chain id of the forked chain
+    pub chain_id: u64,
+    /// Number of the block the fork is pinned to
+    pub block_number: u64,
+    /// Hash of the block the fork is pinned to
+    pub block_hash: B256,
+    /// Base fee of the block the fork is pinned to
+    pub base_fee_per_gas: U256,
+    /// Hardfork rules in force at the pinned block
+    pub spec_id: SpecId,
+    /// Upper bound on a single simulation against this fork
+    pub simulation_timeout: Duration,
+    /// EVM block environment derived from the pinned block
+    pub block_env: BlockEnv,
+    /// Backend serving (and caching) state at the pinned block
+    pub backend: SharedBackend,
+    /// State the backend has cached so far, shared with it
+    pub state: BlockchainDb,
+    /// Batched fetcher of storage misses, if batching is enabled
+    pub storage_batcher: Option<Arc<StorageBatcher>>,
+    /// Bound on simulations running at once, shared by every fork
+    pub simulation_limiter: Arc<SimulationLimiter>,
+    /// Contract code substituted for the chain's, empty unless set by a client
+    pub code_overrides: Arc<HashMap<RevmAddress, Bytecode>>,
+    /// Precompiles the chain adds to the EVM's built-in ones
+    pub precompiles: CustomPrecompiles,
+    /// Whether estimates look for gas-dependent execution
+    pub gas_dependence_detection: bool,
+}
+
+impl Fork {
+    /// A view of this fork with header values pinned by the client
+    ///
+    /// The view shares the backend (and its cached state) with this fork; only
+    /// the block environment the EVM sees differs.
+    pub fn with_pins(&self, pins: &BlockPins) -> Fork {
+        let mut block_env = self.block_env.clone();
+        if let Some(prev_randao) = pins.prev_randao {
+            block_env.prevrandao = Some(convert_b256(prev_randao));
+        }
+        if let Some(coinbase) = pins.coinbase {
+            block_env.coinbase = convert_address(coinbase);
+        }
+        Fork {
+            block_env,
+            backend: self.backend.clone(),
+            state: self.state.clone(),
+            storage_batcher: self.storage_batcher.clone(),
+            simulation_limiter: self.simulation_limiter.clone(),
+            code_overrides: self.code_overrides.clone(),
+            precompiles: self.precompiles.clone(),
+            ..*self
+        }
+    }
+
+    /// A view of this fork running the client's code at the overridden addresses
+    ///
+    /// Like [`Fork::with_pins`], the view shares the backend with this fork; the
+    /// overridden accounts keep their chain balance, nonce and storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - Code to run instead of the chain's, by address
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Fork, ServiceError>` - The view, or an error if some code is malformed
+    pub fn with_code_overrides(&self, overrides: &CodeOverrides) -> Result<Fork, ServiceError> {
+        let mut code_overrides = (*self.code_overrides).clone();
+        for (address, code) in overrides {
+            // Typed like code loaded from the chain: EIP-7702 designators and EOF containers are recognized
+            let bytecode = Bytecode::new_raw_checked(convert_bytes(code.clone())).map_err(|e| {
+                ServiceError::InvalidParams(format!("Invalid code override for {}: {:?}", address, e))
+            })?;
+            code_overrides.insert(convert_address(*address), bytecode);
+        }
+        Ok(Fork {
+            block_env: self.block_env.clone(),
+            backend: self.backend.clone(),
+            state: self.state.clone(),
+            storage_batcher: self.storage_batcher.clone(),
+            simulation_limiter: self.simulation_limiter.clone(),
+            code_overrides: Arc::new(code_overrides),
+            precompiles: self.precompiles.clone(),
+            ..*self
+        })
+    }
+
+    /// A view of this fork whose block holds `gas_limit` gas
+    ///
+    /// Like [`Fork::with_pins`], the view shares the backend with this fork. It
+    /// lets a transaction that needs more gas than a block holds run to the end,
+    /// so what it needs can be measured.
+    pub fn with_block_gas_limit(&self, gas_limit: u64) -> Fork {
+        let mut block_env = self.block_env.clone();
+        block_env.gas_limit = RevmU256::from(gas_limit);
+        Fork {
+            block_env,
+            backend: self.backend.clone(),
+            state: self.state.clone(),
+            storage_batcher: self.storage_batcher.clone(),
+            simulation_limiter: self.simulation_limiter.clone(),
+            code_overrides: self.code_overrides.clone(),
+            precompiles: self.precompiles.clone(),
+            ..*self
+        }
+    }
+}
+
+/// Contract code a client runs instead of the chain's, by address
+///
+/// Lets clients estimate against code that isn't deployed yet, such as the
+/// next implementation behind an upgradeable proxy. An empty code turns the
+/// account into an EOA.
+pub type CodeOverrides = BTreeMap<Address, Bytes>;
+
+/// Header values a client pins for a simulation instead of the head block's
+///
+/// Contracts reading `PREVRANDAO` (or paying `COINBASE`) behave differently
+/// from block to block; pinning those values keeps repeated estimates
+/// reproducible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockPins {
+    /// `PREVRANDAO` value (default: the head block's)
+    pub prev_randao: Option<B256>,
+    /// Block beneficiary (default: the head block's)
+    pub coinbase: Option<Address>,
+}
+
+impl BlockPins {
+    /// Whether nothing is pinned
+    pub fn is_empty(&self) -> bool {
+        self.prev_randao.is_none() && self.coinbase.is_none()
+    }
+}
+
+/// Block a simulation runs against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlockTarget {
+    /// The head block
+    #[default]
+    Latest,
+    /// The upstream's pending block, on top of its pending state
+    Pending,
+    /// The block with the given hash (EIP-1898), on top of its state
+    Hash {
+        /// Hash of the block
+        hash: B256,
+        /// Whether the block must be on the canonical chain
+        require_canonical: bool,
+    },
+}
+
+impl BlockTarget {
+    /// The block tag (or hash) the upstream node is asked for
+    pub fn block_id(self) -> BlockId {
+        match self {
+            BlockTarget::Latest => BlockId::latest(),
+            BlockTarget::Pending => BlockId::pending(),
+            BlockTarget::Hash { hash, require_canonical } => {
+                BlockId::Hash(RpcBlockHash::from_hash(hash, Some(require_canonical)))
+            }
+        }
+    }
+}
+
+/// Header fields of the upstream's pending block
+///
+/// Read from the raw response, since the pending block has no hash (and, on
+/// some nodes, no miner) yet.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingHeader {
+    number: U64,
+    timestamp: U64,
+    gas_limit: U64,
+    #[serde(default)]
+    base_fee_per_gas: Option<U256>,
+    #[serde(default)]
+    miner: Option<Address>,
+    #[serde(default)]
+    mix_hash: Option<B256>,
+    #[serde(default)]
+    excess_blob_gas: Option<U64>,
+}
+
+/// The upstream's pending block, with its transactions in full
+#[derive(Debug, Deserialize)]
+struct PendingBlock {
+    #[serde(flatten)]
+    header: PendingHeader,
+    /// Kept raw, so a transaction of a type this service can't read is skipped on its own
+    #[serde(default)]
+    transactions: Vec<serde_json::Value>,
+}
+
+/// Fetch the block at `number`, and the hash of the canonical block at `check` in the same batch
+///
+/// # Returns
+///
+/// * `TransportResult<(Option<AnyRpcBlock>, Option<(u64, Option<B256>)>)>` - The block, if the
+///   node has it, and the canonical hash at `check`, if asked for
+async fn fetch_head(
+    provider: &DynProvider<AnyNetwork>,
+    number: u64,
+    check: Option<u64>,
+) -> TransportResult<(Option<AnyRpcBlock>, Option<(u64, Option<B256>)>)> {
+    let mut batch = provider.client().new_batch();
+    let head: Waiter<Option<AnyRpcBlock>> =
+        batch.add_call("eth_getBlockByNumber", &(BlockNumberOrTag::Number(number), false))?;
+    let checked = check
+        .map(|number| {
+            batch
+                .add_call::<_, Option<AnyRpcBlock>>("eth_getBlockByNumber", &(BlockNumberOrTag::Number(number), false))
+                .map(|waiter| (number, waiter))
+        })
+        .transpose()?;
+    batch.send().await?;
+    let head = head.await?;
+    let checked = match checked {
+        Some((number, waiter)) => Some((number, waiter.await?.map(|block| block.header.hash))),
+        None => None,
+    };
+    Ok((head, checked))
+}
+
+/// Called with the hashes of the blocks a reorg removed from the canonical chain
+pub type ReorgListener = Box<dyn Fn(&[B256]) + Send + Sync>;
+
+/// Number of recent heads remembered to find the blocks a reorg orphaned
+pub const MAX_TRACKED_HEADS: usize = 64;
+
+/// Number of blocks whose backend and cached state are kept for reuse
+pub const MAX_SHARED_BLOCK_STATES: usize = 8;
+
+/// Number of forks at blocks requested by hash kept for reuse, one per shared block state
+const MAX_HASH_FORKS: NonZeroUsize = NonZeroUsize::MIN.saturating_add(MAX_SHARED_BLOCK_STATES - 1);
+
+/// Backend and cached state of one block, shared by every fork at that block
+#[derive(Clone)]
+struct BlockState {
+    /// State fetched so far
+    db: BlockchainDb,
+    /// Backend fetching missing state at the block
+    backend: SharedBackend,
+    /// Batched fetcher of storage misses, if batching is enabled
+    storage_batcher: Option<Arc<StorageBatcher>>,
+}
+
+/// Long-lived owner of the simulation forks
+///
+/// Keeps one `SharedBackend` per block and hands it out to every simulation
+/// at that block, only spawning a new one once the chain has moved on.
+/// Heads are tracked by hash, so a reorg replacing the head at the same
+/// height gets a new fork too; the recent heads it orphaned are reported to
+/// the reorg listeners, which purge what they cached for them.
+pub struct ForkManager {
+    /// RPC URL used for creating simulation forks (and fetching their state)
+    upstream: UpstreamUrl,
+    /// Provider shared by every fork backend, built on first use
+    provider: OnceCell<DynProvider<AnyNetwork>>,
+    /// Chain id of the upstream node, fetched on first use
+    chain_id: OnceCell<u64>,
+    /// How long a head block lookup is trusted before the node is asked again
+    head_ttl: Duration,
+    /// Per-chain settings (hardfork selection)
+    chain_options: Arc<ChainOptions>,
+    /// Upstream RPC and simulation timeouts
+    timeouts: Timeouts,
+    /// How the forks batch storage misses
+    storage_batching: StorageBatching,
+    /// Storage batches allowed in flight across all forks
+    storage_batch_permits: Arc<Semaphore>,
+    /// Bound on simulations running at once across all forks
+    simulation_limiter: Arc<SimulationLimiter>,
+    /// Fork at the most recently seen head block, with the time the head was last checked
+    current: RwLock<Option<(Arc<Fork>, Instant)>>,
+    /// Held by the request looking the head up, so concurrent requests share the lookup
+    head_lookup: Mutex<()>,
+    /// Fork at the pending block, with the hash of the head it was built on
+    pending: std::sync::Mutex<Option<(B256, Arc<Fork>)>>,
+    /// Forks at the blocks most recently requested by hash
+    at_hash: std::sync::Mutex<LruCache<B256, Arc<Fork>>>,
+    /// Backends and cached state of recent blocks, by block hash
+    block_states: std::sync::Mutex<LruCache<B256, BlockState>>,
+    /// Recently seen heads (number and hash), oldest first
+    heads: std::sync::Mutex<VecDeque<(u64, B256)>>,
+    /// Recent heads found orphaned by a reorg, oldest first
+    orphaned: RwLock<VecDeque<B256>>,
+    /// Reorgs detected since startup
+    reorgs: AtomicU64,
+    /// Notified of the blocks each reorg orphaned
+    reorg_listeners: RwLock<Vec<ReorgListener>>,
+    /// Profile of the upstream chain, once detected
+    profile: RwLock<Option<Arc<ChainProfile>>>,
+}
+
+/// Default time a head block lookup is reused for
+pub const DEFAULT_HEAD_TTL: Duration = Duration::from_millis(1000);
+
+impl ForkManager {
+    /// Create a fork manager for the given RPC endpoint
+    ///
+    /// No connection is made until the first fork is requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc_url` - The Ethereum RPC URL to use for forking
+    /// * `head_ttl` - How long a head block lookup is shared between requests
+    /// * `chain_options` - Per-chain settings, used to pick the hardfork of each fork
+    /// * `timeouts` - Bounds on upstream RPC calls and simulations
+    /// * `storage_batching` - How storage misses are batched
+    /// * `max_concurrent_simulations` - Simulations allowed to run at once, 0 for unbounded
+    pub fn new(
+        rpc_url: &str,
+        head_ttl: Duration,
+        chain_options: Arc<ChainOptions>,
+        timeouts: Timeouts,
+        storage_batching: StorageBatching,
+        max_concurrent_simulations: usize,
+    ) -> Self {
+        Self {
+            upstream: UpstreamUrl::new(rpc_url),
+            provider: OnceCell::new(),
+            chain_id: OnceCell::new(),
+            head_ttl,
+            chain_options,
+            timeouts,
+            storage_batching,
+            storage_batch_permits: Arc::new(Semaphore::new(storage_batching.concurrency)),
+            simulation_limiter: Arc::new(SimulationLimiter::new(max_concurrent_simulations)),
+            current: RwLock::new(None),
+            head_lookup: Mutex::new(()),
+            pending: std::sync::Mutex::new(None),
+            at_hash: std::sync::Mutex::new(LruCache::new(MAX_HASH_FORKS)),
+            block_states: std::sync::Mutex::new(LruCache::new(
+                NonZeroUsize::MIN.saturating_add(MAX_SHARED_BLOCK_STATES - 1),
+            )),
+            heads: std::sync::Mutex::new(VecDeque::new()),
+            orphaned: RwLock::new(VecDeque::new()),
+            reorgs: AtomicU64::new(0),
+            reorg_listeners: RwLock::new(Vec::new()),
+            profile: RwLock::new(None),
+        }
+    }
+
+    /// Get the provider, building it on first use
+    async fn provider(&self) -> Result<&DynProvider<AnyNetwork>, ServiceError> {
+        self.provider
+            .get_or_try_init(|| async {
+                debug!("Building provider for RPC URL: {}", self.upstream.get());
+                build_any_provider(&self.upstream, self.timeouts.rpc).map(|provider| provider.erased())
+            })
+            .await
+    }
+
+    /// Upstream URL forks fetch their state from, replaceable at runtime
+    ///
+    /// Replacing it keeps the current fork and its cache; only state not yet
+    /// cached is fetched from the new upstream.
+    pub fn upstream(&self) -> &UpstreamUrl {
+        &self.upstream
+    }
+
+    /// Chain id of the forks created so far, `None` before the first one
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id.get().copied()
+    }
+
+    /// Running, queued and panicked simulations across all forks
+    pub fn simulation_stats(&self) -> SimulationStats {
+        self.simulation_limiter.stats()
+    }
+
+    /// Profile of the upstream chain, if it was detected
+    pub fn profile(&self) -> Option<Arc<ChainProfile>> {
+        self.profile.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Detect the upstream chain's profile and use it for forks created from now on
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<ChainProfile>, ServiceError>` - The detected profile, or an error if
+    ///   the upstream can't be reached (the previous profile is kept)
+    pub async fn refresh_profile(&self) -> Result<Arc<ChainProfile>, ServiceError> {
+        let profile = Arc::new(detect_profile(self.provider().await?).await?);
+        let previous = self.profile.write().unwrap_or_else(|e| e.into_inner()).replace(profile.clone());
+        if previous.map_or(true, |previous| previous.spec_id != profile.spec_id || previous.blob_params != profile.blob_params) {
+            info!(
+                "Chain profile: chain {} ({}), hardfork {:?} ({:?}), blob parameters {:?}",
+                profile.chain_id,
+                profile.name.unwrap_or("unknown"),
+                profile.spec_id,
+                profile.spec_source,
+                profile.blob_params
+            );
+        }
+        Ok(profile)
+    }
+
+    /// Refresh the chain profile periodically in the background
+    pub fn spawn_profile_refresh(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The profile was just detected at startup
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.refresh_profile().await {
+                    warn!("Chain profile refresh failed, keeping the previous profile: {}", e);
+                }
+            }
+        });
+        info!("Refreshing the chain profile every {:?}", interval);
+    }
+
+    /// Get a fork at the latest block, reusing the current one if the head hasn't moved
+    ///
+    /// The head is only looked up again once `head_ttl` has passed since the last
+    /// lookup, so requests arriving within the same block share one RPC round trip.
+    /// A lookup only asks for the head's number; the head block is fetched once the
+    /// number has changed. While one request looks the head up, the others are served the previous
+    /// fork rather than waiting for the node; only the very first lookup is waited for.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<Fork>, ServiceError>` - The fork, or an error if the node is unreachable
+    pub async fn latest(&self) -> Result<Arc<Fork>, ServiceError> {
+        if let Some(fork) = self.fresh_head() {
+            return Ok(fork);
+        }
+        // A single lookup at a time, so concurrent requests never spawn duplicate backends
+        let _lookup = match self.head_lookup.try_lock() {
+            Ok(lookup) => lookup,
+            Err(_) => {
+                if let Some(fork) = self.current_fork() {
+                    debug!("Head lookup in flight, reusing fork at block {}", fork.block_number);
+                    return Ok(fork);
+                }
+                let lookup = self.head_lookup.lock().await;
+                // The lookup waited for may have produced the fork
+                if let Some(fork) = self.fresh_head() {
+                    return Ok(fork);
+                }
+                lookup
+            }
+        };
+
+        // Only the head's number is polled; the block is fetched once the chain has moved on
+        let provider = self.provider().await?;
+        let number = provider
+            .get_block_number()
+            .await
+            .map_err(|e| upstream_failure("Failed to get the block number", e))?;
+        let fork = match self.current_fork() {
+            // A reorg replacing the head at the same height is found once the next block builds on another parent
+            Some(fork) if fork.block_number == number => {
+                debug!("Head unchanged, reusing fork at block {}", fork.block_number);
+                fork
+            }
+            _ => {
+                // A head that doesn't follow the last one seen can't vouch for it: its canonical
+                // hash is fetched in the same round trip
+                let last_seen = self.heads.lock().unwrap_or_else(|e| e.into_inner()).back().map(|(seen, _)| *seen);
+                let check = last_seen.filter(|seen| *seen != number && seen + 1 != number);
+                let (latest, checked) = fetch_head(provider, number, check)
+                    .await
+                    .map_err(|e| upstream_failure("Failed to get latest block", e))?;
+                let latest = latest.ok_or_else(|| ServiceError::RPCConnection("Failed to get latest block".to_string()))?;
+                self.track_head(provider, &latest, checked).await;
+                Arc::new(self.fork_from_block(provider, latest).await?)
+            }
+        };
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Some((fork.clone(), Instant::now()));
+        Ok(fork)
+    }
+
+    /// Block number of the upstream node's head, without building a fork at it
+    ///
+    /// A head checked less than `head_ttl` ago is answered without asking the
+    /// node; otherwise only the block number is fetched, so probes never spawn
+    /// a fork backend.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64, ServiceError>` - The head's number, or an error if the node is unreachable
+    pub async fn head_number(&self) -> Result<u64, ServiceError> {
+        if let Some(fork) = self.fresh_head() {
+            return Ok(fork.block_number);
+        }
+        self.provider()
+            .await?
+            .get_block_number()
+            .await
+            .map_err(|e| upstream_failure("Failed to get the block number", e))
+    }
+
+    /// The current fork, if its head was checked less than `head_ttl` ago
+    fn fresh_head(&self) -> Option<Arc<Fork>> {
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        let (fork, checked_at) = current.as_ref()?;
+        let age = checked_at.elapsed();
+        (age < self.head_ttl).then(|| {
+            debug!("Reusing fork at block {} (head checked {:?} ago)", fork.block_number, age);
+            fork.clone()
+        })
+    }
+
+    /// The fork at the most recently seen head, however long ago it was checked
+    fn current_fork(&self) -> Option<Arc<Fork>> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).as_ref().map(|(fork, _)| fork.clone())
+    }
+
+    /// Register a listener notified of the blocks each reorg orphans
+    pub fn on_reorg(&self, listener: ReorgListener) {
+        self.reorg_listeners.write().unwrap_or_else(|e| e.into_inner()).push(listener);
+    }
+
+    /// Whether a recent head was removed from the canonical chain by a reorg
+    pub fn is_orphaned(&self, block_hash: B256) -> bool {
+        self.orphaned.read().unwrap_or_else(|e| e.into_inner()).contains(&block_hash)
+    }
+
+    /// Number of reorgs detected since startup
+    pub fn reorgs(&self) -> u64 {
+        self.reorgs.load(Ordering::Relaxed)
+    }
+
+    /// Record a new head, and the recent heads it orphaned if it doesn't build on them
+    ///
+    /// Recent heads are checked newest first until one is still canonical (the
+    /// fork point), so a plain new block costs no RPC call, and neither does a
+    /// head that skipped blocks when `checked` holds the canonical hash at the
+    /// newest one. Only the heads of a reorg are fetched one by one. A head
+    /// whose status can't be fetched stops the walk and is kept.
+    async fn track_head(&self, provider: &DynProvider<AnyNetwork>, head: &AnyRpcBlock, checked: Option<(u64, Option<B256>)>) {
+        let seen: Vec<(u64, B256)> =
+            self.heads.lock().unwrap_or_else(|e| e.into_inner()).iter().rev().copied().collect();
+        let mut orphaned = Vec::new();
+        for (number, hash) in seen {
+            let canonical = if number == head.header.number {
+                Ok(Some(head.header.hash))
+            } else if number + 1 == head.header.number {
+                Ok(Some(head.header.parent_hash))
+            } else if let Some((_, canonical)) = checked.filter(|(checked, _)| *checked == number) {
+                Ok(canonical)
+            } else {
+                provider
+                    .get_block(BlockId::number(number))
+                    .await
+                    .map(|block| block.map(|block| block.header.hash))
+            };
+            match canonical {
+                Ok(Some(canonical)) if canonical == hash => break,
+                Ok(_) => orphaned.push(hash),
+                Err(e) => {
+                    warn!("Failed to check whether block {} is still canonical: {}", number, e);
+                    break;
+                }
+            }
+        }
+
+        {
+            let mut heads = self.heads.lock().unwrap_or_else(|e| e.into_inner());
+            heads.retain(|(_, hash)| !orphaned.contains(hash));
+            heads.push_back((head.header.number, head.header.hash));
+            while heads.len() > MAX_TRACKED_HEADS {
+                heads.pop_front();
+            }
+        }
+        if orphaned.is_empty() {
+            return;
+        }
+
+        warn!(
+            "Reorg at block {}: {} block(s) orphaned: {:?}",
+            head.header.number,
+            orphaned.len(),
+            orphaned
+        );
+        self.reorgs.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut states = self.block_states.lock().unwrap_or_else(|e| e.into_inner());
+            for hash in &orphaned {
+                states.pop(hash);
+            }
+        }
+        {
+            let mut recorded = self.orphaned.write().unwrap_or_else(|e| e.into_inner());
+            recorded.extend(orphaned.iter().copied());
+            while recorded.len() > MAX_TRACKED_HEADS {
+                recorded.pop_front();
+            }
+        }
+        for listener in self.reorg_listeners.read().unwrap_or_else(|e| e.into_inner()).iter() {
+            listener(&orphaned);
+        }
+    }
+
+    /// The fork at the most recently seen head, without looking the head up again
+    pub async fn current(&self) -> Option<Arc<Fork>> {
+        self.current_fork()
+    }
+
+    /// Get a fork at the upstream's pending block, rebuilt whenever the head moves
+    ///
+    /// The fork reads state at the head's hash, with the pending block's
+    /// transactions applied on top (see [`apply_pending_transactions`]), so its
+    /// state stays the same while the node's pending block keeps changing.
+    /// Nodes without a pending block ahead of the head get the latest fork.
+    /// The pending block is fetched and replayed without holding the cache lock;
+    /// requests racing for a new head share the first fork stored.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<Fork>, ServiceError>` - The fork, or an error if the node is unreachable
+    pub async fn pending(&self) -> Result<Arc<Fork>, ServiceError> {
+        let head = self.latest().await?;
+        if let Some(fork) = self.cached_pending(head.block_hash) {
+            debug!("Reusing pending fork at block {}", fork.block_number);
+            return Ok(fork);
+        }
+
+        let provider = self.provider().await?;
+        let block: Option<PendingBlock> = provider
+            .raw_request("eth_getBlockByNumber".into(), ("pending", true))
+            .await
+            .map_err(|e| upstream_failure("Failed to get the pending block", e))?;
+        let fork = match block.filter(|block| block.header.number.to::<u64>() > head.block_number) {
+            Some(block) => Arc::new(self.create_pending_fork(provider, &head, block).await?),
+            None => {
+                debug!("No pending block ahead of block {}, using the latest fork", head.block_number);
+                head.clone()
+            }
+        };
+
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.as_ref() {
+            Some((parent_hash, stored)) if *parent_hash == head.block_hash => Ok(stored.clone()),
+            _ => {
+                *pending = Some((head.block_hash, fork.clone()));
+                Ok(fork)
+            }
+        }
+    }
+
+    /// The cached pending fork, if it was built on the given head
+    fn cached_pending(&self, head_hash: B256) -> Option<Arc<Fork>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .filter(|(parent_hash, _)| *parent_hash == head_hash)
+            .map(|(_, fork)| fork.clone())
+    }
+
+    /// Spawn a new backend at the head, with the pending block's environment and transactions
+    ///
+    /// The pending block has no hash yet, so the fork carries the hash of the head it builds on.
+    /// Its state cache is its own, so the pending transactions never leak into the head's forks.
+    async fn create_pending_fork(
+        &self,
+        provider: &DynProvider<AnyNetwork>,
+        head: &Fork,
+        block: PendingBlock,
+    ) -> Result<Fork, ServiceError> {
+        let PendingBlock { header, transactions } = block;
+        let number = header.number.to::<u64>();
+        info!("Creating fork at pending block: {}", number);
+
+        let meta = BlockchainDbMeta::default().with_chain_id(head.chain_id);
+        let db = BlockchainDb::new(meta, None);
+        // State is read at the head's hash, never at the moving pending tag
+        let backend =
+            SharedBackend::spawn_backend(provider.clone(), db.clone(), Some(BlockId::hash(head.block_hash))).await;
+        // Batched reads would overwrite the pending transactions' slots with the head's
+        let storage_batcher = None;
+
+        let basefee = header.base_fee_per_gas.unwrap_or(head.base_fee_per_gas);
+        let blob_params = self
+            .profile()
+            .filter(|profile| profile.chain_id == head.chain_id)
+            .and_then(|profile| profile.blob_params)
+            .or_else(|| BlobParams::for_spec(head.spec_id));
+
+        let mut block_env = head.block_env.clone();
+        block_env.number = convert_u256(U256::from(number));
+        block_env.timestamp = convert_u256(U256::from(header.timestamp.to::<u64>()));
+        let gas_limit = self.chain_options.block_gas_limit(head.chain_id, header.gas_limit.to::<u64>());
+        block_env.gas_limit = convert_u256(U256::from(gas_limit));
+        block_env.basefee = convert_u256(basefee);
+        if let Some(miner) = header.miner {
+            block_env.coinbase = convert_address(miner);
+        }
+        if let Some(mix_hash) = header.mix_hash {
+            block_env.prevrandao = Some(convert_b256(mix_hash));
+        }
+        if let Some((excess, params)) = header.excess_blob_gas.zip(blob_params) {
+            let excess = excess.to::<u64>();
+            block_env.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice {
+                blob_gasprice: fake_exponential(MIN_BLOB_GASPRICE, excess, params.update_fraction),
+                excess_blob_gas: excess,
+            });
+        }
+        debug!("Pending block environment configured: {:?}", block_env);
+
+        let fork = Fork {
+            chain_id: head.chain_id,
+            block_number: number,
+            block_hash: head.block_hash,
+            base_fee_per_gas: basefee,
+            spec_id: head.spec_id,
+            simulation_timeout: head.simulation_timeout,
+            block_env,
+            backend,
+            state: db,
+            storage_batcher,
+            simulation_limiter: head.simulation_limiter.clone(),
+            code_overrides: Arc::default(),
+            precompiles: head.precompiles.clone(),
+            gas_dependence_detection: head.gas_dependence_detection,
+        };
+
+        let tx_requests: Vec<TransactionRequest> = transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                serde_json::from_value(transaction)
+                    .map_err(|e| debug!("Skipping unreadable pending transaction: {}", e))
+                    .ok()
+            })
+            .collect();
+        let applied = apply_pending_transactions(&fork, tx_requests).await?;
+        debug!("Applied {} pending transactions on block {}", applied, head.block_number);
+        Ok(fork)
+    }
+
+    /// Backend and state cache of a block, shared by every fork at that block
+    ///
+    /// Spawned on first use and kept for the last [`MAX_SHARED_BLOCK_STATES`]
+    /// blocks, so forks at the same block (the head, the same block requested by
+    /// hash, a head coming back after a reorg) fetch each account and storage
+    /// slot from the upstream once. State is read at the block's hash, so it
+    /// can't drift to another block while shared. The backend is spawned without
+    /// holding the cache lock, so a slow block doesn't hold up forks at other blocks.
+    async fn block_state(
+        &self,
+        provider: &DynProvider<AnyNetwork>,
+        block: &AnyRpcBlock,
+        chain_id: u64,
+    ) -> BlockState {
+        let hash = block.header.hash;
+        let cached = self.block_states.lock().unwrap_or_else(|e| e.into_inner()).get(&hash).cloned();
+        if let Some(state) = cached {
+            debug!("Reusing the cached state of block {}", hash);
+            return state;
+        }
+
+        // Create BlockchainDbMeta identifier for the block
+        let meta = BlockchainDbMeta::default()
+            .with_chain_id(chain_id)
+            .with_block(block);
+
+        // Create a new blockchain database
+        debug!("Initializing blockchain database");
+        let db = BlockchainDb::new(meta, None);
+
+        // Spawn the backend with the database instance
+        // This creates a shared backend that can fetch missing data from the RPC provider
+        debug!("Spawning shared backend");
+        let state_at = BlockId::hash(hash);
+        let backend = SharedBackend::spawn_backend(provider.clone(), db.clone(), Some(state_at)).await;
+        debug!("Shared backend spawned successfully");
+        let storage_batcher = StorageBatcher::new(
+            provider.clone(),
+            hash,
+            db.clone(),
+            self.storage_batching,
+            self.storage_batch_permits.clone(),
+        );
+
+        let state = BlockState { db, backend, storage_batcher };
+        let mut states = self.block_states.lock().unwrap_or_else(|e| e.into_inner());
+        // A request racing for the same block may have stored its state first; every fork shares that one
+        if let Some(stored) = states.get(&hash) {
+            return stored.clone();
+        }
+        states.put(hash, state.clone());
+        state
+    }
+
+    /// Get a fork at the block with the given hash
+    ///
+    /// State is read at that exact block, so estimates against it are the same
+    /// whatever the head. The forks of the last few hashes requested are reused.
+    /// The lookups and the backend spawn of a new fork run without holding the
+    /// cache lock, so a slow block doesn't hold up requests at other hashes.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - Hash of the block to fork at
+    /// * `require_canonical` - Whether to reject blocks off the upstream's canonical chain
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<Fork>, ServiceError>` - The fork, or an error if the block is unknown,
+    ///   not canonical when required, or the node is unreachable
+    pub async fn at_hash(&self, hash: B256, require_canonical: bool) -> Result<Arc<Fork>, ServiceError> {
+        let provider = self.provider().await?;
+        let cached = self.at_hash.lock().unwrap_or_else(|e| e.into_inner()).get(&hash).cloned();
+        let fork = match cached {
+            Some(fork) => {
+                debug!("Reusing fork at block {} ({})", fork.block_number, hash);
+                fork
+            }
+            None => {
+                // Requests racing for the same new hash each build a fork, on the block's shared state
+                let fork = Arc::new(self.create_fork_at_hash(provider, hash).await?);
+                self.at_hash.lock().unwrap_or_else(|e| e.into_inner()).put(hash, fork.clone());
+                fork
+            }
+        };
+
+        // Checked on every request, since the block may have been reorged out since
+        if require_canonical {
+            let canonical = self.canonical_hash(fork.block_number).await?;
+            if canonical != Some(hash) {
+                return Err(ServiceError::InvalidParams(format!(
+                    "Block {} is not canonical (requireCanonical: false accepts it)",
+                    hash
+                )));
+            }
+        }
+        Ok(fork)
+    }
+
+    /// Hash of the upstream's canonical block at `number`, `None` if it has no such block yet
+    pub async fn canonical_hash(&self, number: u64) -> Result<Option<B256>, ServiceError> {
+        let block = self
+            .provider()
+            .await?
+            .get_block(BlockId::number(number))
+            .await
+            .map_err(|e| upstream_failure(&format!("Failed to get block {}", number), e))?;
+        Ok(block.map(|block| block.header.hash))
+    }
+
+    /// Get a fork at the given block target
+    pub async fn fork_at(&self, target: BlockTarget) -> Result<Arc<Fork>, ServiceError> {
+        match target {
+            BlockTarget::Latest => self.latest().await,
+            BlockTarget::Pending => self.pending().await,
+            BlockTarget::Hash { hash, require_canonical } => self.at_hash(hash, require_canonical).await,
+        }
+    }
+
+    /// Create a fork at the block with the given hash
+    ///
+    /// The block doesn't need to be canonical, as long as the upstream node still has it.
+    async fn create_fork_at_hash(&self, provider: &DynProvider<AnyNetwork>, hash: B256) -> Result<Fork, ServiceError> {
+        let block = provider
+            .get_block(BlockId::hash(hash))
+            .await
+            .map_err(|e| upstream_failure(&format!("Failed to get block {}", hash), e))?
+            .ok_or_else(|| ServiceError::InvalidParams(format!("Unknown block hash {}", hash)))?;
+        self.fork_from_block(provider, block).await
+    }
+
+    /// Create a fork at a fetched block, on the block's shared state
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The provider state is fetched with
+    /// * `block` - The block to fork at
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Fork, ServiceError>` - The fork, or an error if the node is unreachable
+    async fn fork_from_block(
+        &self,
+        provider: &DynProvider<AnyNetwork>,
+        block: AnyRpcBlock,
+    ) -> Result<Fork, ServiceError> {
+        let number = block.header.number;
+        debug!("Block fetched: number: {:?}, hash: {:?}", block.header.number, block.header.hash);
+
+        info!("Creating fork at block: {:?}", block.header.number);
+
+        // A failed lookup fails this fork and is retried by the next one, never cached
+        let chain_id = *self
+            .chain_id
+            .get_or_try_init(|| async {
+                provider
+                    .get_chain_id()
+                    .await
+                    .map_err(|e| upstream_failure("Failed to get the chain id", e))
+            })
+            .await?;
+        debug!("Using chain id: {}", chain_id);
+        let BlockState { db, backend, storage_batcher } = self.block_state(provider, &block, chain_id).await;
+
+        // The chain profile only applies to the chain it was detected on
+        let profile = self.profile().filter(|profile| profile.chain_id == chain_id);
+        let spec_id = self.chain_options.spec_id(
+            chain_id,
+            block.header.timestamp,
+            profile.as_ref().and_then(|profile| profile.simulation_spec_id()),
+        );
+        debug!("Simulating with hardfork {:?}", spec_id);
+        let blob_params = profile
+            .and_then(|profile| profile.blob_params)
+            .or_else(|| BlobParams::for_spec(spec_id));
+
+        // Configure EVM environment using the block's parameters
+        let basefee = block.header.base_fee_per_gas.map(U256::from).unwrap_or_default();
+        debug!("Block base fee: {:?}", basefee);
+
+        // The forked block's own limit, unless one is configured for the chain
+        let gas_limit = U256::from(self.chain_options.block_gas_limit(chain_id, block.header.gas_limit()));
+        debug!("Block gas limit: {:?}", gas_limit);
+
+        // Create the block environment from the block data
+        let block_env = BlockEnv {
+            number: convert_u256(U256::from(block.header.number)),
+            coinbase: convert_address(block.header.beneficiary),
+            timestamp: convert_u256(U256::from(block.header.timestamp)),
+            gas_limit: convert_u256(gas_limit),
+            basefee: convert_u256(basefee),
+            // Chains that don't report a mix hash are simulated with a zero PREVRANDAO
+            prevrandao: {
+                let prevrandao = block.header.mix_hash.unwrap_or_default();
+                debug!("Block prevrandao (mix_hash): {:?}", prevrandao);
+                Some(prevrandao)
+            },
+            difficulty: convert_u256(block.header.difficulty),
+            blob_excess_gas_and_price: block
+                .header
+                .excess_blob_gas
+                .zip(blob_params)
+                .map(|(excess, params)| {
+                    debug!("Block excess blob gas: {}, blob parameters: {:?}", excess, params);
+                    BlobExcessGasAndPrice {
+                        blob_gasprice: fake_exponential(MIN_BLOB_GASPRICE, excess, params.update_fraction),
+                        excess_blob_gas: excess,
+                    }
+                }),
+        };
+        debug!("EVM block environment configured: {:?}", block_env);
+
+        Ok(Fork {
+            chain_id,
+            block_number: number,
+            block_hash: block.header.hash,
+            base_fee_per_gas: basefee,
+            spec_id,
+            simulation_timeout: self.timeouts.simulation,
+            block_env,
+            backend,
+            state: db,
+            storage_batcher,
+            simulation_limiter: self.simulation_limiter.clone(),
+            code_overrides: Arc::default(),
+            precompiles: self.chain_options.precompiles(chain_id),
+            gas_dependence_detection: self.chain_options.gas_dependence_detection(chain_id),
+        })
+    }
+}
+
+/// Simulate a transaction on a specific fork and return the raw execution result
+///
+/// An out-of-gas halt is attributed to the call frame that exhausted its gas
+/// rather than the whole transaction. Tracking frames slows execution down, so
+/// only a transaction that ran out of gas is executed again to find it.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request to simulate
+/// * `mode` - Validation rules applied to the transaction
+///
+/// # Returns
+///
+/// * `Result<(ExecutionResult, Option<FailedFrame>), ServiceError>` - The REVM execution
+///   result and the call frame that first ran out of gas (if any), or an error
+pub async fn simulate_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    mode: SimulationMode,
+) -> Result<(ExecutionResult, Option<FailedFrame>), ServiceError> {
+    info!("Estimating gas with local fork DB at block: {:?}", fork.block_number);
+
+    let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+    let (result, _) = execute_on_fork(fork, tx_env.clone(), mode, None::<GasDependenceInspector>).await?;
+    let out_of_gas_frame = match &result {
+        ExecutionResult::Halt { reason: HaltReason::OutOfGas(_), .. } => {
+            out_of_gas_frame_on_fork(fork, tx_env, mode).await?
+        }
+        _ => None,
+    };
+    Ok((result, out_of_gas_frame))
+}
+
+/// Execute a failed transaction again with its call frames tracked, to find the frame that first ran out of gas
+///
+/// # Returns
+///
+/// * `Result<Option<FailedFrame>, ServiceError>` - The frame, if one ran out of gas, or an error
+pub(crate) async fn out_of_gas_frame_on_fork(
+    fork: &Fork,
+    tx_env: TxEnv,
+    mode: SimulationMode,
+) -> Result<Option<FailedFrame>, ServiceError> {
+    let (_, inspector) = execute_on_fork(fork, tx_env, mode, Some(GasDependenceInspector::default())).await?;
+    Ok(inspector.and_then(|inspector| inspector.out_of_gas_frame().cloned()))
+}
+
+/// Balance and nonce of an account
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccountState {
+    /// Balance in wei
+    pub balance: U256,
+    /// Transaction count
+    pub nonce: u64,
+}
+
+/// Read an account at a fork's pinned block
+///
+/// Served from the fork's shared cache; on a miss the backend fetches the
+/// account from the upstream node and caches it for later simulations.
+/// Accounts that don't exist read as zero balance and nonce.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to read from
+/// * `address` - The account to read
+///
+/// # Returns
+///
+/// * `Result<AccountState, ServiceError>` - The account's balance and nonce, or an error
+pub async fn account_on_fork(fork: &Fork, address: Address) -> Result<AccountState, ServiceError> {
+    // The fork backend blocks while it fetches, keep it off the async runtime
+    let info = run_simulation(fork, move |backend| {
+        backend
+            .basic_ref(address)
+            .map_err(|e| ServiceError::RPCConnection(format!("Failed to read account {}: {}", address, e)))
+    })
+    .await?;
+    Ok(info
+        .map(|info| AccountState { balance: info.balance, nonce: info.nonce })
+        .unwrap_or_default())
+}
+
+/// Whether a contract is deployed at an address, at a fork's pinned block
+///
+/// Read like [`account_on_fork`], from the fork's shared cache.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to read from
+/// * `address` - The account to check
+///
+/// # Returns
+///
+/// * `Result<bool, ServiceError>` - Whether the account has code, or an error
+pub async fn has_code_on_fork(fork: &Fork, address: Address) -> Result<bool, ServiceError> {
+    let info = run_simulation(fork, move |backend| {
+        backend
+            .basic_ref(address)
+            .map_err(|e| ServiceError::RPCConnection(format!("Failed to read account {}: {}", address, e)))
+    })
+    .await?;
+    Ok(info.is_some_and(|info| info.code_hash != KECCAK_EMPTY))
+}
+
+/// Reject a transaction bound to another chain than the fork's
+///
+/// A transaction without a `chainId` runs on any chain. One carrying the id of
+/// another chain would otherwise be simulated against the connected chain's
+/// state, producing an estimate that means nothing where it will be sent.
+///
+/// # Arguments
+///
+/// * `fork` - The fork the transaction would be simulated on
+/// * `tx_request` - The transaction
+///
+/// # Returns
+///
+/// * `Result<(), ServiceError>` - Nothing, or [`ServiceError::ChainMismatch`]
+pub fn check_chain_id(fork: &Fork, tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+    match tx_request.chain_id {
+        Some(requested) if requested != fork.chain_id => Err(ServiceError::ChainMismatch {
+            requested,
+            connected: fork.chain_id,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Reject a transaction carrying more blobs than its fork allows
+///
+/// A transaction may carry as many blobs as a block holds under the fork's
+/// hardfork: 6 under Cancun (EIP-4844), 9 under Prague (EIP-7691).
+///
+/// # Arguments
+///
+/// * `fork` - The fork the transaction would be simulated on
+/// * `tx_request` - The transaction
+///
+/// # Returns
+///
+/// * `Result<(), ServiceError>` - Nothing, or [`ServiceError::InvalidParams`]
+pub fn check_blob_count(fork: &Fork, tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+    let blobs = tx_request.blob_versioned_hashes.as_ref().map_or(0, Vec::len) as u64;
+    match BlobParams::for_spec(fork.spec_id) {
+        Some(params) if blobs > params.max => Err(ServiceError::InvalidParams(format!(
+            "transaction carries {} blobs, more than the {} allowed under {:?}",
+            blobs, params.max, fork.spec_id
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Reject a transaction carrying an EIP-7702 authorization that can't be applied
+///
+/// The EVM silently skips authorizations whose signer can't be recovered, which
+/// would estimate a transaction that delegates nothing.
+///
+/// # Arguments
+///
+/// * `tx_request` - The transaction
+/// * `recover_signers` - Whether to recover each authorization's signer; drafts with
+///   placeholder signatures only get their `yParity` checked
+///
+/// # Returns
+///
+/// * `Result<(), ServiceError>` - Nothing, or [`ServiceError::InvalidParams`] naming the
+///   index of the first invalid authorization
+pub fn check_authorizations(tx_request: &TransactionRequest, recover_signers: bool) -> Result<(), ServiceError> {
+    let authorizations = tx_request.authorization_list.iter().flatten();
+    for (index, authorization) in authorizations.enumerate() {
+        if authorization.y_parity() > 1 {
+            return Err(ServiceError::InvalidParams(format!(
+                "Invalid authorization {}: yParity must be 0 or 1",
+                index
+            )));
+        }
+        if recover_signers {
+            let authority = authorization.recover_authority().map_err(|e| {
+                ServiceError::InvalidParams(format!("Invalid authorization {}: invalid signature: {}", index, e))
+            })?;
+            debug!("Authorization {} is signed by {}", index, authority);
+        }
+    }
+    Ok(())
+}
+
+/// Build the EVM transaction environment for simulating `tx_request` on `fork`
+///
+/// In `Call` mode a missing gas limit defaults to the block gas limit.
+pub(crate) fn tx_env_for_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    mode: SimulationMode,
+) -> Result<TxEnv, ServiceError> {
+    check_chain_id(fork, tx_request)?;
+    check_blob_count(fork, tx_request)?;
+    debug!("Converting transaction request into EVM transaction environment");
+    let mut tx_env = convert_tx_request_to_tx_env(tx_request)
+        .map_err(|e| ServiceError::Simulation(e.to_string()))?;
+    // Like `eth_call` and `eth_estimateGas` on a node, a missing gas limit is capped by the block
+    if tx_request.gas.is_none() {
+        tx_env.gas_limit = fork.block_env.gas_limit.saturating_to();
+    }
+    debug!("Transaction environment configured: {:?}", tx_env);
+    Ok(tx_env)
+}
+
+/// Execute one transaction on a fresh `CacheDB` over the fork, optionally under an inspector
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_env` - The transaction to execute
+/// * `mode` - Validation rules applied to the transaction
+/// * `inspector` - Inspector observing the execution, if any
+///
+/// # Returns
+///
+/// * `Result<(ExecutionResult, Option<I>), ServiceError>` - The execution result and the
+///   inspector (with whatever it recorded), or an error
+pub(crate) async fn execute_on_fork<I>(
+    fork: &Fork,
+    tx_env: TxEnv,
+    mode: SimulationMode,
+    inspector: Option<I>,
+) -> Result<(ExecutionResult, Option<I>), ServiceError>
+where
+    I: Inspector<CacheDB<CancellableBackend>> + Send + 'static,
+{
+    let (result, inspector, _) = execute_and_diff_on_fork(fork, tx_env, mode, inspector, false).await?;
+    Ok((result, inspector))
+}
+
+/// Execute one transaction like [`execute_on_fork`], optionally diffing the state it changes
+///
+/// The diff is read from the same execution, so asking for it costs no extra run.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_env` - The transaction to execute
+/// * `mode` - Validation rules applied to the transaction
+/// * `inspector` - Inspector observing the execution, if any
+/// * `state_diff` - Whether to diff the changed accounts
+///
+/// # Returns
+///
+/// * `Result<(ExecutionResult, Option<I>, Option<BTreeMap<Address, AccountDiff>>), ServiceError>` -
+///   The execution result, the inspector and the state diff if asked for, or an error
+pub(crate) async fn execute_and_diff_on_fork<I>(
+    fork: &Fork,
+    tx_env: TxEnv,
+    mode: SimulationMode,
+    inspector: Option<I>,
+    state_diff: bool,
+) -> Result<(ExecutionResult, Option<I>, Option<BTreeMap<Address, AccountDiff>>), ServiceError>
+where
+    I: Inspector<CacheDB<CancellableBackend>> + Send + 'static,
+{
+    // Each simulation gets its own CacheDB so writes never leak into the shared fork
+    let block_env = fork.block_env.clone();
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+
+    // Execute the simulation in a blocking task to avoid blocking the async runtime
+    debug!("Starting blocking REVM simulation");
+    run_simulation(fork, move |backend| {
+        debug!("Inside spawn_blocking: creating CacheDB and EVM instance");
+        // The internal REVM call is synchronous, so keep it in blocking code
+        let cancellation = backend.cancellation();
+        backend.prefetch_access_list(&tx_env.access_list);
+        let mut db = CacheDB::new(backend);
+        advance_sender_nonce(&mut db, &tx_env)?;
+
+        let caller = tx_env.caller;
+        let transact_error = |e| transaction_error(caller, e);
+        // The outcome isn't committed, so the CacheDB still holds the state before the transaction
+        let diff = |db: &CacheDB<CancellableBackend>, state| state_diff.then(|| account_diffs(db, state)).transpose();
+
+        match inspector {
+            Some(mut inspector) => {
+                let mut evm = Evm::builder()
+                    .with_db(db)
+                    .with_spec_id(spec_id)
+                    .with_external_context(&mut inspector)
+                    .append_handler_register(inspector_handle_register)
+                    .append_handler_register_box(precompiles_register(precompiles))
+                    .append_handler_register_box(abort_on_cancel(cancellation))
+                    .modify_cfg_env(|cfg| configure_cfg_env(cfg, mode, &tx_env))
+                    .with_block_env(block_env)
+                    .with_tx_env(tx_env)
+                    .build();
+                debug!("Inspected EVM instance built, starting transaction simulation");
+                let outcome = evm.transact().map_err(transact_error)?;
+                let (db, _) = evm.into_db_and_env_with_handler_cfg();
+                let diff = diff(&db, outcome.state)?;
+                Ok((outcome.result, Some(inspector), diff))
+            }
+            None => {
+                let mut evm = Evm::builder()
+                    .with_db(db)
+                    .with_spec_id(spec_id)
+                    .append_handler_register_box(precompiles_register(precompiles))
+                    .append_handler_register_box(abort_on_cancel(cancellation))
+                    .modify_cfg_env(|cfg| configure_cfg_env(cfg, mode, &tx_env))
+                    .with_block_env(block_env)
+                    .with_tx_env(tx_env)
+                    .build();
+                debug!("EVM instance built, starting transaction simulation");
+                let outcome = evm.transact().map_err(transact_error)?;
+                let (db, _) = evm.into_db_and_env_with_handler_cfg();
+                let diff = diff(&db, outcome.state)?;
+                Ok((outcome.result, None, diff))
+            }
+        }
+    })
+    .await
+}
+
+/// Map a transaction the EVM refused to execute onto a service error
+///
+/// A sender unable to pay for the transaction gets the node's
+/// `insufficient funds` error rather than a generic simulation failure.
+pub(crate) fn transaction_error<E: std::fmt::Debug + Into<ServiceError>>(caller: RevmAddress, e: EVMError<E>) -> ServiceError {
+    error!("EVM simulation failed: {:?}", e);
+    match e {
+        EVMError::Transaction(InvalidTransaction::LackOfFundForMaxFee { fee, balance }) => {
+            ServiceError::InsufficientFunds(format!("address {} have {} want {}", caller, balance, fee))
+        }
+        // The backend couldn't fetch state from the upstream node, or the simulation ran out of time
+        EVMError::Database(e) => e.into(),
+        e => ServiceError::Simulation(format!("EVM simulation failed: {:?}", e)),
+    }
+}
+
+/// Map a failed transaction of a sequence or multi-block simulation onto a service error
+///
+/// # Arguments
+///
+/// * `location` - Where the transaction is, e.g. `transaction 2`, prefixed to simulation errors
+/// * `e` - The EVM's error
+fn located_error(location: &str, e: EVMError<BackendError>) -> ServiceError {
+    error!("EVM simulation of {} failed: {:?}", location, e);
+    match e {
+        // The backend couldn't fetch state from the upstream node, or the simulation ran out of time
+        EVMError::Database(e) => e.into(),
+        e => ServiceError::Simulation(format!("{}: EVM simulation failed: {:?}", location, e)),
+    }
+}
+
+/// Start the sender at the transaction's nonce when it is ahead of the fork state
+///
+/// A nonce counting the sender's pending transactions is ahead of the state at
+/// the head block; the transaction is simulated as if those had been mined
+/// (without their state changes), as nodes do for `eth_estimateGas`.
+pub(crate) fn advance_sender_nonce(db: &mut CacheDB<CancellableBackend>, tx_env: &TxEnv) -> Result<(), ServiceError> {
+    let Some(nonce) = tx_env.nonce else {
+        return Ok(());
+    };
+    let mut info = db
+        .basic_ref(tx_env.caller)
+        .map_err(|e| ServiceError::RPCConnection(format!("Failed to read account {}: {}", tx_env.caller, e)))?
+        .unwrap_or_default();
+    if info.nonce < nonce {
+        debug!("Advancing the nonce of {} from {} to {}", tx_env.caller, info.nonce, nonce);
+        info.nonce = nonce;
+        db.insert_account_info(tx_env.caller, info);
+    }
+    Ok(())
+}
+
+/// Run a simulation against a fork on the blocking thread pool, bounded by the fork's simulation timeout
+///
+/// The simulation first waits for a permit of the fork's [`SimulationLimiter`];
+/// the wait counts against the timeout, and the permit is held until the EVM
+/// has stopped. The bound is shortened to the request's remaining budget, and
+/// nothing is started once the deadline has passed. On timeout, or when the
+/// caller stops waiting, the simulation's backend handle is cancelled: the
+/// EVM fails at its next state read, jump or call (see [`abort_on_cancel`]),
+/// so it neither fetches state nobody will use nor keeps its thread busy.
+///
+/// A panic in the simulation (in revm or in conversion code) is caught on the
+/// blocking thread, counted by the fork's limiter (see [`SimulationStats::panics`])
+/// and reported as a simulation error.
+pub(crate) async fn run_simulation<T, F>(fork: &Fork, simulation: F) -> Result<T, ServiceError>
+where
+    F: FnOnce(CancellableBackend) -> Result<T, ServiceError> + Send + 'static,
+    T: Send + 'static,
+{
+    let timeout = within_deadline(fork.simulation_timeout);
+    if timeout.is_zero() {
+        return Err(ServiceError::Timeout("Request deadline exceeded before the simulation".to_string()));
+    }
+    let backend = CancellableBackend::new(fork, deadline());
+    // Cancels the backend handle however this future ends: completed, timed out or dropped
+    let _cancel_on_drop = backend.cancel_on_drop();
+    let block_number = fork.block_number;
+    let limiter = fork.simulation_limiter.clone();
+    // The blocking thread logs under the caller's span and subscriber (e.g. a replay capturing its logs)
+    let span = tracing::Span::current();
+    let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
+    let task = async move {
+        let permit = limiter.acquire().await;
+        tokio::task::spawn_blocking(move || {
+            // Held until the EVM stops, shortly after the caller gives up
+            let _permit = permit;
+            tracing::dispatcher::with_default(&dispatch, || {
+                let _entered = span.enter();
+                panic::catch_unwind(AssertUnwindSafe(|| simulation(backend))).unwrap_or_else(|payload| {
+                    limiter.record_panic();
+                    let message = panic_message(payload.as_ref());
+                    error!("Simulation at block {} panicked: {}", block_number, message);
+                    Err(ServiceError::Simulation(format!("Simulation at block {} panicked: {}", block_number, message)))
+                })
+            })
+        })
+        .await
+    };
+    match tokio::time::timeout(timeout, task).await {
+        Ok(joined) => joined.map_err(|e| {
+            error!("spawn_blocking task failed: {:?}", e);
+            ServiceError::Simulation(format!("spawn_blocking failed: {e:?}"))
+        })?,
+        Err(_) => {
+            error!("EVM simulation timed out after {:?}", timeout);
+            Err(ServiceError::Timeout(format!("EVM simulation exceeded {} ms", timeout.as_millis())))
+        }
+    }
+}
+
+/// Message of a caught panic
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic payload")
+}
+
+/// Error reading state through a [`CancellableBackend`]
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum BackendError {
+    /// The fork backend failed to fetch the state
+    #[error(transparent)]
+    Fetch(#[from] DatabaseError),
+    /// The simulation was abandoned, so no more state is fetched for it
+    #[error("Simulation abandoned, state read cancelled")]
+    Cancelled,
+    /// The request the simulation runs for reached its deadline
+    #[error("Request deadline exceeded while reading state")]
+    DeadlineExceeded,
+}
+
+impl From<BackendError> for ServiceError {
+    fn from(e: BackendError) -> Self {
+        match e {
+            BackendError::Fetch(e) => ServiceError::Database(format!("{:?}", e)),
+            // Nobody waits for an abandoned simulation; one that ran out of time answers with a timeout
+            e @ (BackendError::Cancelled | BackendError::DeadlineExceeded) => ServiceError::Timeout(e.to_string()),
+        }
+    }
+}
+
+/// Handle on a fork's backend used by a single simulation
+///
+/// Reads are served by the fork's `SharedBackend` until the handle is
+/// cancelled or the deadline of the request it reads for passes; from then on
+/// every read fails, so a simulation nobody waits for stops at its next state
+/// access and drops its backend handle. The backend fetches misses on its own
+/// task, where the request's deadline isn't seen, so a miss is waited for no
+/// longer than the deadline and left to fill the fork's cache in the
+/// background. Storage misses go through the fork's [`StorageBatcher`] first,
+/// if it has one. Accounts whose code is overridden are read with the fork's
+/// override instead of their chain code.
+#[derive(Clone)]
+pub(crate) struct CancellableBackend {
+    backend: SharedBackend,
+    state: BlockchainDb,
+    storage_batcher: Option<Arc<StorageBatcher>>,
+    code_overrides: Arc<HashMap<RevmAddress, Bytecode>>,
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    correlation_id: Option<String>,
+    runtime: Handle,
+}
+
+/// Cancels a [`CancellableBackend`] when dropped
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl CancellableBackend {
+    /// A handle on `fork`'s backend, reading for a request with the given deadline
+    ///
+    /// The request's correlation id is kept for the storage batches the handle
+    /// starts, since reads happen on a blocking thread outside the request's task.
+    /// Must be called from within the Tokio runtime misses are waited on.
+    fn new(fork: &Fork, deadline: Option<Instant>) -> Self {
+        Self {
+            backend: fork.backend.clone(),
+            state: fork.state.clone(),
+            storage_batcher: fork.storage_batcher.clone(),
+            code_overrides: fork.code_overrides.clone(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline,
+            correlation_id: correlation_id(),
+            runtime: Handle::current(),
+        }
+    }
+
+    /// A guard cancelling this handle (and its clones) once dropped
+    fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop(self.cancelled.clone())
+    }
+
+    /// Cancellation of this handle, for [`abort_on_cancel`]
+    pub(crate) fn cancellation(&self) -> Cancellation {
+        Cancellation { cancelled: self.cancelled.clone(), deadline: self.deadline }
+    }
+
+    fn check(&self) -> Result<(), BackendError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(BackendError::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(BackendError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// Read through the fork backend, waiting for a miss no longer than the deadline
+    ///
+    /// # Arguments
+    ///
+    /// * `cached` - Whether the fork has the state cached, so the read won't fetch
+    /// * `read` - The read
+    fn read<T: Send + 'static>(
+        &self,
+        cached: bool,
+        read: impl FnOnce(&SharedBackend) -> Result<T, DatabaseError> + Send + 'static,
+    ) -> Result<T, BackendError> {
+        let remaining = match self.deadline {
+            Some(deadline) if !cached => deadline.saturating_duration_since(Instant::now()),
+            _ => return Ok(read(&self.backend)?),
+        };
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let backend = self.backend.clone();
+        self.runtime.spawn_blocking(move || {
+            let _ = sender.send(read(&backend));
+        });
+        match receiver.recv_timeout(remaining) {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(BackendError::DeadlineExceeded),
+        }
+    }
+
+    /// Fetch the storage keys of an access list in one batch, if the fork batches storage
+    fn prefetch_access_list(&self, access_list: &[AccessListItem]) {
+        if let Some(batcher) = &self.storage_batcher {
+            let slots = access_list.iter().flat_map(|item| {
+                item.storage_keys.iter().map(|key| (item.address, RevmU256::from_be_bytes(key.0)))
+            });
+            batcher.prefetch_slots(slots, self.deadline, self.correlation_id.as_deref());
+        }
+    }
+
+    /// Read an account through the fork backend
+    fn basic(&self, address: RevmAddress) -> Result<Option<AccountInfo>, BackendError> {
+        let cached = self.state.accounts().read().contains_key(&address);
+        self.read(cached, move |backend| backend.basic_ref(address))
+    }
+}
+
+/// Whether the simulation of a [`CancellableBackend`] should stop
+#[derive(Clone)]
+pub(crate) struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// Whether the handle was cancelled
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the handle was cancelled or the request's deadline has passed
+    ///
+    /// Reads the clock, so it is kept off the hottest instructions.
+    fn is_expired(&self) -> bool {
+        self.is_cancelled() || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl DatabaseRef for CancellableBackend {
+    type Error = BackendError;
+
+    fn basic_ref(&self, address: RevmAddress) -> Result<Option<AccountInfo>, Self::Error> {
+        self.check()?;
+        Ok(match self.code_overrides.get(&address) {
+            // An overridden account exists even if it has no state on chain
+            Some(code) => {
+                let mut info = self.basic(address)?.unwrap_or_default();
+                info.code_hash = code.hash_slow();
+                info.code = Some(code.clone());
+                Some(info)
+            }
+            // REVM loads the delegate itself, through the simulation's CacheDB, once the account is called
+            None => self.basic(address)?.map(resolve_delegation),
+        })
+    }
+
+    fn code_by_hash_ref(&self, code_hash: RevmB256) -> Result<Bytecode, Self::Error> {
+        self.check()?;
+        // Code is stored with the accounts, never fetched by hash
+        Ok(self.backend.code_by_hash_ref(code_hash)?)
+    }
+
+    fn storage_ref(&self, address: RevmAddress, index: RevmU256) -> Result<RevmU256, Self::Error> {
+        self.check()?;
+        if let Some(batcher) = &self.storage_batcher {
+            batcher.prefetch(address, index, self.deadline, self.correlation_id.as_deref());
+        }
+        let cached = self
+            .state
+            .storage()
+            .read()
+            .get(&address)
+            .is_some_and(|storage| storage.contains_key(&index));
+        self.read(cached, move |backend| backend.storage_ref(address, index))
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<RevmB256, Self::Error> {
+        self.check()?;
+        self.read(false, move |backend| backend.block_hash_ref(number))
+    }
+}
+
+/// Handler register stopping the EVM once its backend handle is cancelled or its request's deadline passes
+///
+/// A cancelled handle fails every state read, but a loop over state the EVM
+/// has already loaded reads none. Jumps check for cancellation too, and calls
+/// and creations for the deadline as well, so an abandoned simulation stops
+/// within a few instructions and releases its blocking thread and simulation
+/// permit. The EVM fails with the matching [`BackendError`].
+///
+/// # Arguments
+///
+/// * `cancellation` - The handle's cancellation, from [`CancellableBackend::cancellation`]
+///
+/// # Returns
+///
+/// * `HandleRegisterBox` - The register, to pass to `append_handler_register_box`
+pub(crate) fn abort_on_cancel<'a, EXT: 'a, DB: Database<Error = BackendError> + 'a>(
+    cancellation: Cancellation,
+) -> HandleRegisterBox<'a, EXT, DB> {
+    Box::new(move |handler| {
+        for op in [opcode::JUMP, opcode::JUMPI] {
+            let cancellation = cancellation.clone();
+            handler.instruction_table.update_boxed(op, move |instruction, interpreter, host| {
+                if cancellation.is_cancelled() {
+                    host.context.evm.error = Err(BackendError::Cancelled);
+                    interpreter.instruction_result = InstructionResult::FatalExternalError;
+                    return;
+                }
+                instruction(interpreter, host);
+            });
+        }
+        for op in [
+            opcode::CALL,
+            opcode::CALLCODE,
+            opcode::DELEGATECALL,
+            opcode::STATICCALL,
+            opcode::CREATE,
+            opcode::CREATE2,
+        ] {
+            let cancellation = cancellation.clone();
+            handler.instruction_table.update_boxed(op, move |instruction, interpreter, host| {
+                if cancellation.is_expired() {
+                    host.context.evm.error = Err(if cancellation.is_cancelled() {
+                        BackendError::Cancelled
+                    } else {
+                        BackendError::DeadlineExceeded
+                    });
+                    interpreter.instruction_result = InstructionResult::FatalExternalError;
+                    return;
+                }
+                instruction(interpreter, host);
+            });
+        }
+    })
+}
+
+/// Type the EIP-7702 delegation designator (`0xef0100 || address`) of an account as such
+///
+/// An account delegated on chain must run its delegate's code when called and
+/// stay an EOA as a sender. REVM only does both for designators typed as
+/// [`Bytecode::Eip7702`], so a designator loaded as plain code would halt on
+/// its `0xef` prefix instead.
+pub fn resolve_delegation(mut info: AccountInfo) -> AccountInfo {
+    if let Some(code) = info.code.as_ref().filter(|code| !code.is_eip7702()) {
+        if let Ok(designator) = Eip7702Bytecode::new_raw(code.original_bytes()) {
+            info.code = Some(Bytecode::Eip7702(designator));
+        }
+    }
+    info
+}
+
+/// Apply the validation rules of a simulation mode to the EVM configuration of a transaction
+///
+/// An unpriced transaction (see [`is_unpriced`]) skips the base fee check, as on a node.
+pub(crate) fn configure_cfg_env(cfg: &mut CfgEnv, mode: SimulationMode, tx_env: &TxEnv) {
+    cfg.disable_balance_check = mode == SimulationMode::Call;
+    cfg.disable_base_fee = mode == SimulationMode::Call || is_unpriced(tx_env);
+}
+
+/// Whether a transaction was requested without any fee, in which case it runs at a zero gas price
+pub(crate) fn is_unpriced(tx_env: &TxEnv) -> bool {
+    tx_env.gas_price.is_zero() && tx_env.gas_priority_fee.is_none_or(|fee| fee.is_zero())
+}
+
+/// Simulate transactions back-to-back on a specific fork, each one seeing the state left by the previous
+///
+/// All transactions share one `CacheDB` and every result is committed before
+/// the next transaction runs, so e.g. transaction N sees the nonce and balances
+/// left behind by transaction N-1. A missing gas limit defaults to the block gas limit.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_requests` - The transactions, in execution order
+/// * `mode` - Validation rules applied to every transaction
+///
+/// # Returns
+///
+/// * `Result<Vec<ExecutionResult>, ServiceError>` - One result per transaction, or the
+///   error of the first transaction that failed validation
+pub async fn simulate_sequence_on_fork(
+    fork: &Fork,
+    tx_requests: &[TransactionRequest],
+    mode: SimulationMode,
+) -> Result<Vec<ExecutionResult>, ServiceError> {
+    info!("Simulating {} sequential transactions at block: {:?}", tx_requests.len(), fork.block_number);
+
+    let tx_envs = sequence_tx_envs(fork, tx_requests)?;
+    let block_env = fork.block_env.clone();
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+
+    debug!("Starting blocking REVM sequence simulation");
+    run_simulation(fork, move |backend| {
+        let cancellation = backend.cancellation();
+        let db = CacheDB::new(backend);
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_spec_id(spec_id)
+            .append_handler_register_box(precompiles_register(precompiles))
+            .append_handler_register_box(abort_on_cancel(cancellation))
+            .with_block_env(block_env)
+            .build();
+
+        let mut results = Vec::with_capacity(tx_envs.len());
+        for (index, tx_env) in tx_envs.into_iter().enumerate() {
+            configure_cfg_env(evm.cfg_mut(), mode, &tx_env);
+            *evm.tx_mut() = tx_env;
+            // Commit so the next transaction sees this one's state changes
+            let result = evm
+                .transact_commit()
+                .map_err(|e| located_error(&format!("transaction {}", index), e))?;
+            debug!("Transaction {} simulated: {:?}", index, result);
+            results.push(result);
+        }
+        Ok::<Vec<ExecutionResult>, ServiceError>(results)
+    })
+    .await
+}
+
+/// Transaction environments of a sequence; a missing gas limit defaults to the block gas limit
+fn sequence_tx_envs(fork: &Fork, tx_requests: &[TransactionRequest]) -> Result<Vec<TxEnv>, ServiceError> {
+    let block_gas_limit: u64 = fork.block_env.gas_limit.saturating_to();
+    tx_requests
+        .iter()
+        .enumerate()
+        .map(|(index, tx_request)| {
+            check_chain_id(fork, tx_request)?;
+            let mut tx_env = convert_tx_request_to_tx_env(tx_request)
+                .map_err(|e| ServiceError::Simulation(format!("transaction {}: {}", index, e)))?;
+            if tx_request.gas.is_none() {
+                tx_env.gas_limit = block_gas_limit;
+            }
+            Ok(tx_env)
+        })
+        .collect()
+}
+
+/// Apply a pending block's transactions to the state cache of its fork
+///
+/// The transactions run in block order on the head state, each one on the
+/// state left by the earlier ones. One that fails validation (e.g. its nonce
+/// was taken by a replacement) is skipped, as the node would drop it. The
+/// accounts and storage slots they changed are written to the fork's cache,
+/// which its backend serves before reading the head from the upstream, so
+/// every simulation on the fork sees the same pending state.
+///
+/// # Arguments
+///
+/// * `fork` - The pending fork, reading state at its head
+/// * `tx_requests` - The pending block's transactions, in block order
+///
+/// # Returns
+///
+/// * `Result<usize, ServiceError>` - Number of transactions applied, or an error if
+///   the upstream couldn't be read or the replay ran out of time
+async fn apply_pending_transactions(fork: &Fork, tx_requests: Vec<TransactionRequest>) -> Result<usize, ServiceError> {
+    let tx_envs: Vec<TxEnv> = tx_requests
+        .iter()
+        .filter_map(|tx_request| {
+            convert_tx_request_to_tx_env(tx_request)
+                .map_err(|e| debug!("Skipping pending transaction: {}", e))
+                .ok()
+        })
+        .collect();
+    if tx_envs.is_empty() {
+        return Ok(0);
+    }
+    let block_env = fork.block_env.clone();
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+    let state = fork.state.clone();
+
+    run_simulation(fork, move |backend| {
+        let cancellation = backend.cancellation();
+        let mut evm = Evm::builder()
+            .with_db(CacheDB::new(backend))
+            .with_spec_id(spec_id)
+            .append_handler_register_box(precompiles_register(precompiles))
+            .append_handler_register_box(abort_on_cancel(cancellation))
+            .with_block_env(block_env)
+            .build();
+
+        let mut applied = 0;
+        for (index, tx_env) in tx_envs.into_iter().enumerate() {
+            configure_cfg_env(evm.cfg_mut(), SimulationMode::Estimate, &tx_env);
+            *evm.tx_mut() = tx_env;
+            match evm.transact_commit() {
+                Ok(_) => applied += 1,
+                // Missing state would leave the pending block half applied
+                Err(EVMError::Database(e)) => return Err(e.into()),
+                Err(e) => debug!("Skipping pending transaction {}: {:?}", index, e),
+            }
+        }
+
+        let db = evm.db_mut();
+        let mut accounts = state.accounts().write();
+        let mut storage = state.storage().write();
+        for (address, account) in &db.accounts {
+            let slots = account.storage.iter().map(|(slot, value)| (*slot, *value));
+            match account.account_state {
+                // Only read, so already the head's state
+                DbAccountState::None => continue,
+                DbAccountState::NotExisting => {
+                    accounts.insert(*address, AccountInfo::default());
+                    storage.insert(*address, Default::default());
+                }
+                DbAccountState::StorageCleared => {
+                    accounts.insert(*address, with_code(&account.info, db));
+                    storage.insert(*address, slots.collect());
+                }
+                DbAccountState::Touched => {
+                    accounts.insert(*address, with_code(&account.info, db));
+                    storage.entry(*address).or_default().extend(slots);
+                }
+            }
+        }
+        Ok(applied)
+    })
+    .await
+}
+
+/// An account with its code attached, as the fork cache serves it
+fn with_code(info: &AccountInfo, db: &CacheDB<CancellableBackend>) -> AccountInfo {
+    let mut info = info.clone();
+    if info.code.is_none() {
+        info.code = db.contracts.get(&info.code_hash).cloned();
+    }
+    info
+}
+
+/// Outcome and gas limit of one transaction in an estimated sequence
+#[derive(Debug)]
+pub struct SequencedTransaction {
+    /// Outcome under the requested (or block) gas limit
+    pub result: ExecutionResult,
+    /// Smallest gas limit under which the transaction succeeds, `None` if it doesn't
+    pub gas_limit: Option<u64>,
+}
+
+/// Estimate transactions that depend on each other's state changes
+///
+/// Like [`simulate_sequence_on_fork`], every transaction runs on the state left
+/// by the earlier ones and its result is committed before the next one runs.
+/// In addition, the smallest gas limit under which each successful transaction
+/// still succeeds on that state is searched, `eth_estimateGas` style. Every run
+/// reads through an empty overlay of the committed state, so neither the search
+/// nor the sequence copies the state accumulated so far.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_requests` - The transactions, in execution order
+///
+/// # Returns
+///
+/// * `Result<Vec<SequencedTransaction>, ServiceError>` - One outcome per transaction, or
+///   the error of the first transaction that failed validation
+pub async fn estimate_sequence_on_fork(
+    fork: &Fork,
+    tx_requests: &[TransactionRequest],
+) -> Result<Vec<SequencedTransaction>, ServiceError> {
+    info!("Estimating {} dependent transactions at block: {:?}", tx_requests.len(), fork.block_number);
+
+    let tx_envs = sequence_tx_envs(fork, tx_requests)?;
+    let block_env = fork.block_env.clone();
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+
+    run_simulation(fork, move |backend| {
+        let cancellation = backend.cancellation();
+        let transact = |db: &CacheDB<CancellableBackend>, tx_env: TxEnv| {
+            Evm::builder()
+                .with_db(CacheDB::new(db))
+                .with_spec_id(spec_id)
+                .append_handler_register_box(precompiles_register(precompiles.clone()))
+                .append_handler_register_box(abort_on_cancel(cancellation.clone()))
+                .modify_cfg_env(|cfg| configure_cfg_env(cfg, SimulationMode::Estimate, &tx_env))
+                .with_block_env(block_env.clone())
+                .with_tx_env(tx_env)
+                .build()
+                .transact()
+        };
+
+        let mut db = CacheDB::new(backend);
+        let mut estimates = Vec::with_capacity(tx_envs.len());
+        for (index, tx_env) in tx_envs.into_iter().enumerate() {
+            let outcome =
+                transact(&db, tx_env.clone()).map_err(|e| located_error(&format!("transaction {}", index), e))?;
+
+            // Limits too low to even pass validation count as failing
+            let succeeds = |gas_limit: u64| {
+                let mut candidate = tx_env.clone();
+                candidate.gas_limit = gas_limit;
+                transact(&db, candidate).is_ok_and(|outcome| outcome.result.is_success())
+            };
+            let gas_limit = outcome.result.is_success().then(|| {
+                // Refunds are deducted from the gas used, so it may not be enough as a limit
+                let gas_used = outcome.result.gas_used();
+                if succeeds(gas_used) {
+                    return gas_used;
+                }
+                let (mut lower, mut upper) = (gas_used, tx_env.gas_limit);
+                while upper - lower > 1 {
+                    let middle = lower + (upper - lower) / 2;
+                    if succeeds(middle) {
+                        upper = middle;
+                    } else {
+                        lower = middle;
+                    }
+                }
+                upper
+            });
+            debug!("Transaction {} estimated at {:?}: {:?}", index, gas_limit, outcome.result);
+
+            // Commit so the next transaction sees this one's state changes
+            db.commit(outcome.state);
+            estimates.push(SequencedTransaction {
+                result: outcome.result,
+                gas_limit,
+            });
+        }
+        Ok::<Vec<SequencedTransaction>, ServiceError>(estimates)
+    })
+    .await
+}
+
+/// Header fields overridden in a simulated block; unset fields follow the parent block
+#[derive(Debug, Clone, Default)]
+pub struct BlockOverrides {
+    /// Block number (default: parent + 1)
+    pub number: Option<u64>,
+    /// Block timestamp (default: parent + 12 seconds)
+    pub time: Option<u64>,
+    /// Block gas limit
+    pub gas_limit: Option<u64>,
+    /// Block beneficiary
+    pub fee_recipient: Option<Address>,
+    /// Base fee per gas
+    pub base_fee_per_gas: Option<U256>,
+    /// `PREVRANDAO` value
+    pub prev_randao: Option<B256>,
+}
+
+/// One block of calls to simulate
+#[derive(Debug, Clone, Default)]
+pub struct BlockCalls {
+    /// Header fields of the block
+    pub overrides: BlockOverrides,
+    /// The calls, in execution order
+    pub calls: Vec<TransactionRequest>,
+}
+
+/// Outcome of one simulated block
+pub struct BlockExecution {
+    /// Environment the block's calls ran in
+    pub block_env: BlockEnv,
+    /// One result per call, in order
+    pub results: Vec<ExecutionResult>,
+}
+
+/// Simulate several blocks of calls on top of a fork, carrying state from call to call and block to block
+///
+/// The first block is a child of the fork block. Every block inherits its
+/// parent's header except for the overridden fields, with the number and
+/// timestamp advancing by one and twelve seconds unless overridden; overridden
+/// numbers and timestamps must keep increasing. A call without a gas limit gets
+/// the gas left in its block.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate on top of
+/// * `blocks` - The blocks, in order
+/// * `mode` - Validation rules applied to every call
+///
+/// # Returns
+///
+/// * `Result<Vec<BlockExecution>, ServiceError>` - One outcome per block, or the error of
+///   the first call that failed validation
+pub async fn simulate_blocks_on_fork(
+    fork: &Fork,
+    blocks: Vec<BlockCalls>,
+    mode: SimulationMode,
+) -> Result<Vec<BlockExecution>, ServiceError> {
+    info!("Simulating {} blocks on top of block: {:?}", blocks.len(), fork.block_number);
+
+    let mut parent = fork.block_env.clone();
+    let mut planned = Vec::with_capacity(blocks.len());
+    for (block_index, block) in blocks.into_iter().enumerate() {
+        let block_env = child_block_env(&parent, &block.overrides)
+            .map_err(|e| ServiceError::InvalidParams(format!("block {}: {}", block_index, e)))?;
+        let tx_envs = block
+            .calls
+            .iter()
+            .enumerate()
+            .map(|(index, tx_request)| {
+                check_chain_id(fork, tx_request)?;
+                convert_tx_request_to_tx_env(tx_request)
+                    .map(|tx_env| (tx_env, tx_request.gas.is_none()))
+                    .map_err(|e| ServiceError::Simulation(format!("block {} call {}: {}", block_index, index, e)))
+            })
+            .collect::<Result<Vec<(TxEnv, bool)>, ServiceError>>()?;
+        parent = block_env.clone();
+        planned.push((block_env, tx_envs));
+    }
+
+    let spec_id = fork.spec_id;
+    let precompiles = fork.precompiles.clone();
+
+    debug!("Starting blocking REVM multi-block simulation");
+    run_simulation(fork, move |backend| {
+        let cancellation = backend.cancellation();
+        let db = CacheDB::new(backend);
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .with_spec_id(spec_id)
+            .append_handler_register_box(precompiles_register(precompiles))
+            .append_handler_register_box(abort_on_cancel(cancellation))
+            .build();
+
+        let mut executions = Vec::with_capacity(planned.len());
+        for (block_index, (block_env, tx_envs)) in planned.into_iter().enumerate() {
+            let block_gas_limit: u64 = block_env.gas_limit.saturating_to();
+            *evm.block_mut() = block_env.clone();
+
+            let mut gas_used = 0u64;
+            let mut results = Vec::with_capacity(tx_envs.len());
+            for (index, (mut tx_env, default_gas)) in tx_envs.into_iter().enumerate() {
+                let remaining = block_gas_limit.saturating_sub(gas_used);
+                if default_gas {
+                    tx_env.gas_limit = remaining;
+                } else if tx_env.gas_limit > remaining {
+                    return Err(ServiceError::InvalidParams(format!(
+                        "block {} call {}: gas limit {} exceeds the {} gas left in the block",
+                        block_index, index, tx_env.gas_limit, remaining
+                    )));
+                }
+                configure_cfg_env(evm.cfg_mut(), mode, &tx_env);
+                *evm.tx_mut() = tx_env;
+                // Commit so later calls and blocks see this call's state changes
+                let result = evm
+                    .transact_commit()
+                    .map_err(|e| located_error(&format!("block {} call {}", block_index, index), e))?;
+                gas_used += result.gas_used();
+                results.push(result);
+            }
+            debug!("Block {} simulated, {} gas used", block_index, gas_used);
+            executions.push(BlockExecution { block_env, results });
+        }
+        Ok::<Vec<BlockExecution>, ServiceError>(executions)
+    })
+    .await
+}
+
+/// Environment of a block following `parent`, with `overrides` applied
+pub(crate) fn child_block_env(parent: &BlockEnv, overrides: &BlockOverrides) -> Result<BlockEnv, String> {
+    let parent_number: u64 = parent.number.saturating_to();
+    let parent_time: u64 = parent.timestamp.saturating_to();
+    let number = overrides.number.unwrap_or(parent_number + 1);
+    let time = overrides.time.unwrap_or(parent_time + 12);
+    if number <= parent_number {
+        return Err(format!("block number {} must be greater than {}", number, parent_number));
+    }
+    if time <= parent_time {
+        return Err(format!("timestamp {} must be greater than {}", time, parent_time));
+    }
+
+    let mut block_env = parent.clone();
+    block_env.number = convert_u256(U256::from(number));
+    block_env.timestamp = convert_u256(U256::from(time));
+    if let Some(gas_limit) = overrides.gas_limit {
+        block_env.gas_limit = convert_u256(U256::from(gas_limit));
+    }
+    if let Some(fee_recipient) = overrides.fee_recipient {
+        block_env.coinbase = convert_address(fee_recipient);
+    }
+    if let Some(base_fee) = overrides.base_fee_per_gas {
+        block_env.basefee = convert_u256(base_fee);
+    }
+    if let Some(prev_randao) = overrides.prev_randao {
+        block_env.prevrandao = Some(convert_b256(prev_randao));
+    }
+    Ok(block_env)
+}
+
+/// Converts an Alloy TransactionRequest to REVM's TxEnv
+///
+/// This function translates between the Alloy and REVM type systems to prepare
+/// a transaction for simulation in the EVM.
+/// 
+/// While this might seem redundant, its important as I am preparing for the REVM 0.20.x rewrite 
+/// which will have a different type system. The conversion *should* be more straightforward
+/// once 0.20.x goes stable, and there are type conversions available on the REVM git already.
+///
+/// # Arguments
+///
+/// * `request` - The Alloy transaction request to convert
+///
+/// # Returns
+///
+/// * `Result<TxEnv, eyre::Error>` - The converted transaction environment or an error
+pub fn convert_tx_request_to_tx_env(request: &TransactionRequest) -> Result<TxEnv, eyre::Error> {
+    debug!("Starting conversion of TransactionRequest to TxEnv: {:?}", request);
+
+    // 1) 'from' => caller
+    let caller = match request.from {
+        Some(addr) => {
+            debug!("Using 'from' address: {:?}", addr);
+            addr
+        }
+        None => {
+            error!("Transaction request missing 'from' field");
+            eyre::bail!("Transaction request missing 'from' field")
+        }
+    };
+
+    // 2) 'to' => TxKind::Call(...) or TxKind::Create
+    let transact_to = match request.to {
+        Some(tx_kind) => match tx_kind {
+            TxKind::Call(addr) => {
+                debug!("Transaction type: Call, destination: {:?}", addr);
+                TransactTo::Call(convert_address(addr))
+            }
+            TxKind::Create => {
+                debug!("Transaction type: Create");
+                TransactTo::Create
+            }
+        },
+        None => {
+            debug!("Transaction 'to' field missing, defaulting to Create");
+            TransactTo::Create
+        }
+    };
+
+    // 3) value
+    let value = request.value.unwrap_or_default();
+    debug!("Transaction value: {:?}", value);
+
+    // 4) data from request.input
+    let data = match request.input.input() {
+        Some(bytes) => {
+            debug!("Transaction input data found, length: {}", bytes.len());
+            convert_bytes(bytes.clone())
+        }
+        None => {
+            debug!("No transaction input data found, using empty Bytes");
+            RevmBytes::default()
+        }
+    };
+
+    // 5) gas limit
+    let gas_limit = request.gas.unwrap_or(DEFAULT_GAS_LIMIT);
+    debug!("Transaction gas limit: {}", gas_limit);
+
+    // 6) gas pricing
+    let gas_price = if let Some(max_fee) = request.max_fee_per_gas {
+        debug!("EIP-1559 transaction detected, using max_fee_per_gas: {:?}", max_fee);
+        convert_u256(U256::from(max_fee))
+    } else if let Some(price) = request.gas_price {
+        debug!("Legacy transaction detected, using gas_price: {:?}", price);
+        convert_u256(U256::from(price))
+    } else {
+        // Like a node, an unpriced request runs at a zero gas price and skips the base fee check
+        debug!("No gas price specified, simulating at a zero gas price");
+        RevmU256::ZERO
+    };
+
+    let gas_priority_fee = request.max_priority_fee_per_gas.map(|fee| {
+        debug!("Using max_priority_fee_per_gas: {:?}", fee);
+        convert_u256(U256::from(fee))
+    });
+
+    // 7) Access list
+    let access_list = match &request.access_list {
+        Some(alist) => {
+            debug!("Access list provided with {} entries", alist.len());
+            convert_access_list(alist)
+        }
+        None => {
+            debug!("No access list provided, using empty list");
+            Vec::new()
+        }
+    };
+
+    // 8) EIP-4844
+    let blob_hashes = request
+        .blob_versioned_hashes
+        .clone()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|hash| {
+            debug!("Converting blob versioned hash: {:?}", hash);
+            convert_b256(hash)
+        })
+        .collect();
+
+    let max_fee_per_blob_gas = request.max_fee_per_blob_gas.map(|fee| {
+        debug!("Using max_fee_per_blob_gas: {:?}", fee);
+        convert_u256(U256::from(fee))
+    });
+
+    // 9) EIP-7702 authorization
+    let authorization_list = match &request.authorization_list {
+        Some(list) => {
+            debug!("Found EIP-7702 authorization list with {} items", list.len());
+            let revm_auth_list = AuthorizationList::Signed(list.to_vec());
+            Some(revm_auth_list)
+        }
+        None => {
+            debug!("No authorization list provided");
+            None
+        }
+    };
+
+    // 10) Build the final TxEnv
+    let tx_env = TxEnv {
+        caller: convert_address(caller),
+        gas_limit,
+        gas_price,
+        transact_to,
+        value: convert_u256(value),
+        data,
+        nonce: request.nonce, // Option<u64>
+        chain_id: request.chain_id,
+        access_list,
+        gas_priority_fee,
+        blob_hashes,
+        max_fee_per_blob_gas,
+        authorization_list,
+    };
+
+    debug!("TxEnv conversion complete: {:?}", tx_env);
+    Ok(tx_env)
+}
+
+// ----- Helper functions for type conversion -----
+
+/// Convert an Alloy Address to a REVM Address
+fn convert_address(address: Address) -> RevmAddress {
+    let mut bytes = [0u8; 20];
+    bytes.copy_from_slice(address.as_slice());
+    RevmAddress::from(bytes)
+}
+
+/// Convert an Alloy U256 to a REVM U256
+fn convert_u256(value: U256) -> RevmU256 {
+    let bytes = value.to_be_bytes::<32>();
+    RevmU256::from_be_bytes(bytes)
+}
+
+/// Convert Alloy Bytes to REVM Bytes
+fn convert_bytes(bytes: Bytes) -> RevmBytes {
+    RevmBytes::from(bytes.to_vec())
+}
+
+/// Convert an Alloy B256 to a REVM B256
+fn convert_b256(hash: B256) -> RevmB256 {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(hash.as_slice());
+    RevmB256::from(bytes)
+}
+
+/// Convert an Alloy AccessList to a REVM AccessList
+fn convert_access_list(access_list: &AccessList) -> Vec<AccessListItem> {
+    access_list.0.iter().map(|item| {
+        AccessListItem {
+            address: convert_address(item.address),
+            storage_keys: item.storage_keys.iter().map(|key| {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(key.as_slice());
+                RevmB256::from(bytes)
+            }).collect(),
+        }
+    }).collect()
+}
\ No newline at end of file
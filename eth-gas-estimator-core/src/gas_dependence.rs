@@ -0,0 +1,455 @@
+//! Gas limit recommendation for gas-dependent contracts
+//!
+//! Most transactions behave the same with any sufficient gas limit, so the gas
+//! used by one simulation is the answer. Contracts that branch on `gasleft()`,
+//! or make calls with fixed gas amounts (e.g. `try c.f{gas: 50_000}()`), can
+//! instead take a different path when given exactly that much gas, so even a
+//! binary search can pick a limit that changes what the transaction does.
+//!
+//! The first simulation runs under an inspector that detects these patterns.
+//! When one is found, the transaction is re-simulated with increasing limits
+//! until its outcome matches the unconstrained run, and the result carries a
+//! confidence flag. Limits grow by a quarter per re-simulation, then the search
+//! narrows down between the last failing and the first stable limit; both
+//! phases share a budget of [`MAX_RESIMULATIONS`] re-simulations per estimate.
+//!
+//! * `high` - no gas-dependent behavior was observed; the gas used is the limit
+//! * `medium` - gas-dependent behavior was observed, and the recommended limit
+//!   reproduces the unconstrained outcome
+//! * `low` - gas-dependent behavior was observed, and no tested limit below the
+//!   original one reproduced the unconstrained outcome within the budget; the
+//!   original limit is returned
+//!
+//! Nested calls need the same treatment even without such patterns: a call only
+//! receives 63/64 of the caller's remaining gas (EIP-150), so a limit equal to
+//! the gas used can starve a deep subcall while the top level still succeeds
+//! (e.g. when the failure is caught). A limit is only accepted once every frame
+//! (call or contract creation) that succeeded in the unconstrained run also
+//! succeeds under it, and frames that failed there fail the same way; a subcall
+//! whose out-of-gas failure is caught even under the original limit is part of
+//! the transaction's normal outcome, not a reason to give up. The search
+//! narrows down to the minimum such limit. The frame that fails just below it is
+//! reported, so callers can tell which subcall the budget is bound by.
+//!
+//! Forks of chains with detection turned off ([`crate::chains::ChainOptions::with_gas_dependence_detection`])
+//! skip the inspector: the gas used by a single uninspected run is the limit.
+
+use crate::{
+    error::ServiceError,
+    foundry::{
+        execute_and_diff_on_fork, execute_on_fork, out_of_gas_frame_on_fork, tx_env_for_fork, Fork, SimulationMode,
+    },
+    models::simulation::AccountDiff,
+};
+use alloy::{
+    primitives::{Address, FixedBytes},
+    rpc::types::TransactionRequest,
+};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, CreateInputs, CreateOutcome, InstructionResult, Interpreter},
+    primitives::{ExecutionResult, TxEnv},
+    Database, EvmContext, Inspector,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// Maximum number of re-simulations per estimate, growing and narrowing the limit together
+pub const MAX_RESIMULATIONS: u32 = 24;
+
+/// How much a candidate limit grows per re-simulation (1/4th, i.e. 25%)
+const GROWTH_DIVISOR: u64 = 4;
+
+/// How much a recommended gas limit can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// No gas-dependent behavior observed
+    High,
+    /// Gas-dependent behavior observed; the limit reproduces the unconstrained outcome
+    Medium,
+    /// Gas-dependent behavior observed; no stable limit found below the original one
+    Low,
+}
+
+/// Gas-dependent patterns observed during execution
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GasDependence {
+    /// `GAS` was used for something other than forwarding gas to a call (`gasleft()`)
+    pub reads_gas_left: bool,
+    /// A call was made with an explicit gas amount instead of forwarding all gas
+    pub fixed_gas_calls: bool,
+}
+
+impl GasDependence {
+    /// Whether execution may change with the gas limit
+    pub fn detected(&self) -> bool {
+        self.reads_gas_left || self.fixed_gas_calls
+    }
+}
+
+/// A call frame that did not succeed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedFrame {
+    /// Contract whose code ran in the frame
+    pub address: Address,
+    /// First four bytes of the call data, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<FixedBytes<4>>,
+    /// Call depth, 0 being the top-level call
+    pub depth: usize,
+    /// Gas the frame was given
+    pub gas_limit: u64,
+    /// Why the frame failed, e.g. "OutOfGas" or "Revert"
+    pub reason: String,
+    /// Whether the frame ran out of gas
+    pub out_of_gas: bool,
+}
+
+impl FailedFrame {
+    /// Whether two frames are the same call, ignoring the gas they were given
+    fn same_frame(&self, other: &FailedFrame) -> bool {
+        self.address == other.address && self.selector == other.selector && self.depth == other.depth
+    }
+}
+
+/// Inspector detecting `gasleft()` reads, fixed-gas calls and failed call and create frames
+///
+/// Solidity forwards all remaining gas to a call by emitting `GAS` right before
+/// the call opcode. Any other use of `GAS` is a `gasleft()` read, and a call not
+/// preceded by `GAS` was given an explicit gas amount.
+#[derive(Debug, Default)]
+pub struct GasDependenceInspector {
+    previous_opcode: Option<u8>,
+    observed: GasDependence,
+    /// Open call and create frames, outermost first
+    frames: Vec<FailedFrame>,
+    /// Whether any call was made below the top level
+    nested_calls: bool,
+    /// Frames that failed, in the order they returned
+    failed_frames: Vec<FailedFrame>,
+}
+
+impl GasDependenceInspector {
+    /// The frame that exhausted its gas first, i.e. the innermost out-of-gas frame
+    ///
+    /// Once a subcall runs out of gas its callers are left with little gas and
+    /// may run out too, so the first out-of-gas frame to return is the culprit.
+    pub fn out_of_gas_frame(&self) -> Option<&FailedFrame> {
+        self.failed_frames.iter().find(|frame| frame.out_of_gas)
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasDependenceInspector {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let is_call = matches!(op, opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL);
+        let after_gas = self.previous_opcode == Some(opcode::GAS);
+
+        if after_gas && !is_call {
+            self.observed.reads_gas_left = true;
+        }
+        if is_call && !after_gas {
+            self.observed.fixed_gas_calls = true;
+        }
+        self.previous_opcode = Some(op);
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let selector = inputs.input.get(..4).map(FixedBytes::<4>::from_slice);
+        self.open_frame(inputs.bytecode_address, selector, inputs.gas_limit);
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.close_frame(outcome.result.result);
+        outcome
+    }
+
+    fn create(&mut self, context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        // The creator is loaded while it runs, so its nonce gives the address being created
+        let nonce = context
+            .journaled_state
+            .state
+            .get(&inputs.caller)
+            .map_or(0, |account| account.info.nonce);
+        self.open_frame(inputs.created_address(nonce), None, inputs.gas_limit);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.close_frame(outcome.result.result);
+        outcome
+    }
+}
+
+impl GasDependenceInspector {
+    /// Track a call or create frame being entered
+    fn open_frame(&mut self, address: Address, selector: Option<FixedBytes<4>>, gas_limit: u64) {
+        let depth = self.frames.len();
+        self.nested_calls |= depth > 0;
+        self.frames.push(FailedFrame {
+            address,
+            selector,
+            depth,
+            gas_limit,
+            reason: String::new(),
+            out_of_gas: false,
+        });
+    }
+
+    /// Record the innermost open frame as failed if it didn't succeed
+    fn close_frame(&mut self, result: InstructionResult) {
+        if let Some(mut frame) = self.frames.pop() {
+            if !result.is_ok() {
+                frame.reason = format!("{:?}", result);
+                frame.out_of_gas = is_out_of_gas(result);
+                self.failed_frames.push(frame);
+            }
+        }
+    }
+}
+
+/// Whether an instruction result means the frame ran out of gas
+fn is_out_of_gas(result: InstructionResult) -> bool {
+    matches!(
+        result,
+        InstructionResult::OutOfGas
+            | InstructionResult::MemoryOOG
+            | InstructionResult::MemoryLimitOOG
+            | InstructionResult::PrecompileOOG
+            | InstructionResult::InvalidOperandOOG
+    )
+}
+
+/// Recommended gas limit for a transaction
+#[derive(Debug)]
+pub struct GasRecommendation {
+    /// Result of the unconstrained simulation
+    pub result: ExecutionResult,
+    /// Gas used by the unconstrained simulation
+    pub gas_used: u64,
+    /// Recommended gas limit
+    pub gas_limit: u64,
+    /// Gas-dependent patterns observed
+    pub dependence: GasDependence,
+    /// How much `gas_limit` can be trusted
+    pub confidence: Confidence,
+    /// Number of re-simulations performed
+    pub iterations: u32,
+    /// Frame that fails when the gas limit is lowered below `gas_limit`, or that
+    /// ran out of gas in an execution that failed under the original limit
+    pub limiting_frame: Option<FailedFrame>,
+    /// State changes of the unconstrained simulation, when asked for
+    pub state_diff: Option<BTreeMap<Address, AccountDiff>>,
+}
+
+/// Simulate a transaction and recommend a gas limit that preserves its behavior
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request to simulate
+///
+/// # Returns
+///
+/// * `Result<GasRecommendation, ServiceError>` - The recommendation, or an error
+pub async fn recommend_gas_limit(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+) -> Result<GasRecommendation, ServiceError> {
+    recommend_gas_limit_with_diff(fork, tx_request, false).await
+}
+
+/// Recommend a gas limit like [`recommend_gas_limit`], optionally diffing the state
+///
+/// The diff is read from the unconstrained simulation the recommendation starts
+/// from, so callers that need both don't execute the transaction again.
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request to simulate
+/// * `state_diff` - Whether to fill [`GasRecommendation::state_diff`]
+///
+/// # Returns
+///
+/// * `Result<GasRecommendation, ServiceError>` - The recommendation, or an error
+pub async fn recommend_gas_limit_with_diff(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    state_diff: bool,
+) -> Result<GasRecommendation, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, SimulationMode::Estimate)?;
+    let original_limit = tx_env.gas_limit;
+
+    if !fork.gas_dependence_detection {
+        let (result, _, state_diff) = execute_and_diff_on_fork(
+            fork,
+            tx_env.clone(),
+            SimulationMode::Estimate,
+            None::<GasDependenceInspector>,
+            state_diff,
+        )
+        .await?;
+        let gas_used = result.gas_used();
+        // Frames weren't tracked; a failed execution runs again to name the frame that ran out of gas
+        let limiting_frame = match result.is_success() {
+            true => None,
+            false => out_of_gas_frame_on_fork(fork, tx_env, SimulationMode::Estimate).await?,
+        };
+        return Ok(GasRecommendation {
+            result,
+            gas_used,
+            gas_limit: gas_used,
+            dependence: GasDependence::default(),
+            confidence: Confidence::High,
+            iterations: 0,
+            limiting_frame,
+            state_diff,
+        });
+    }
+
+    let (result, inspector, state_diff) = execute_and_diff_on_fork(
+        fork,
+        tx_env.clone(),
+        SimulationMode::Estimate,
+        Some(GasDependenceInspector::default()),
+        state_diff,
+    )
+    .await?;
+    let inspector = inspector.unwrap_or_default();
+    let dependence = inspector.observed;
+    let gas_used = result.gas_used();
+
+    let recommendation = |result, gas_limit, confidence, iterations, limiting_frame| GasRecommendation {
+        result,
+        gas_used,
+        gas_limit,
+        dependence,
+        confidence,
+        iterations,
+        limiting_frame,
+        state_diff,
+    };
+
+    // Failed executions are reported as-is, naming the frame that ran out of gas
+    if !result.is_success() {
+        let frame = inspector.out_of_gas_frame().cloned();
+        return Ok(recommendation(result, gas_used, Confidence::High, 0, frame));
+    }
+
+    // Without gas-dependent patterns or nested calls, the gas used is enough
+    if !dependence.detected() && !inspector.nested_calls {
+        return Ok(recommendation(result, gas_used, Confidence::High, 0, None));
+    }
+    debug!("Gas-dependent execution or nested calls detected: {:?}", dependence);
+
+    let stable_confidence = if dependence.detected() { Confidence::Medium } else { Confidence::High };
+    let reference_failures = inspector.failed_frames;
+
+    // Grow the limit until it reproduces the unconstrained outcome
+    let mut candidate = gas_used;
+    let mut failing: Option<(u64, Option<FailedFrame>)> = None;
+    let mut iterations = 0;
+    let mut stable = None;
+    while iterations < MAX_RESIMULATIONS && candidate < original_limit {
+        iterations += 1;
+        match try_limit(fork, &tx_env, candidate, &result, &reference_failures).await? {
+            None => {
+                stable = Some(candidate);
+                break;
+            }
+            Some(frame) => failing = Some((candidate, frame)),
+        }
+        candidate += (candidate / GROWTH_DIVISOR).max(1);
+    }
+
+    let Some(mut upper) = stable else {
+        debug!("No stable gas limit below {} after {} iterations", original_limit, iterations);
+        let frame = failing.and_then(|(_, frame)| frame);
+        return Ok(recommendation(result, original_limit, Confidence::Low, iterations, frame));
+    };
+
+    // Narrow down to the minimum stable limit between the last failing and the stable candidate
+    let mut limiting_frame = None;
+    if let Some((mut lower, frame)) = failing {
+        limiting_frame = frame;
+        while upper - lower > 1 && iterations < MAX_RESIMULATIONS {
+            iterations += 1;
+            let middle = lower + (upper - lower) / 2;
+            match try_limit(fork, &tx_env, middle, &result, &reference_failures).await? {
+                None => upper = middle,
+                Some(frame) => {
+                    lower = middle;
+                    limiting_frame = frame;
+                }
+            }
+        }
+    }
+
+    debug!("Gas limit {} is stable after {} iterations", upper, iterations);
+    Ok(recommendation(result, upper, stable_confidence, iterations, limiting_frame))
+}
+
+/// Re-simulate a transaction under a gas limit and compare it with the unconstrained run
+///
+/// # Returns
+///
+/// * `Result<Option<Option<FailedFrame>>, ServiceError>` - `None` if the limit reproduces
+///   the unconstrained outcome, otherwise the first frame that newly failed (if any)
+async fn try_limit(
+    fork: &Fork,
+    tx_env: &TxEnv,
+    gas_limit: u64,
+    reference: &ExecutionResult,
+    reference_failures: &[FailedFrame],
+) -> Result<Option<Option<FailedFrame>>, ServiceError> {
+    let mut constrained = tx_env.clone();
+    constrained.gas_limit = gas_limit;
+    let (outcome, inspector) = execute_on_fork(
+        fork,
+        constrained,
+        SimulationMode::Estimate,
+        Some(GasDependenceInspector::default()),
+    )
+    .await?;
+    let failures = inspector.map(|inspector| inspector.failed_frames).unwrap_or_default();
+
+    let same_failures = failures.len() == reference_failures.len()
+        && failures
+            .iter()
+            .zip(reference_failures)
+            .all(|(frame, reference)| frame.same_frame(reference) && frame.reason == reference.reason);
+    if same_outcome(reference, &outcome) && same_failures {
+        return Ok(None);
+    }
+    let new_failure = failures
+        .into_iter()
+        .find(|frame| !reference_failures.iter().any(|reference| reference.same_frame(frame)));
+    Ok(Some(new_failure))
+}
+
+/// Whether a constrained run behaved like the unconstrained one
+///
+/// Both must succeed with the same output and emit the same logs.
+fn same_outcome(reference: &ExecutionResult, outcome: &ExecutionResult) -> bool {
+    match (reference, outcome) {
+        (
+            ExecutionResult::Success { output: expected, logs: expected_logs, .. },
+            ExecutionResult::Success { output, logs, .. },
+        ) => expected == output && expected_logs == logs,
+        _ => false,
+    }
+}
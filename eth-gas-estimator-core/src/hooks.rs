@@ -0,0 +1,59 @@
+//! Estimation pipeline hooks
+//!
+//! Hooks let deployments plug organisation-specific policies into the
+//! estimation pipeline without forking the crate. They are registered on the
+//! [`GasEstimatorBuilder`](crate::estimator::GasEstimatorBuilder) and run in
+//! registration order at four points of every estimation:
+//!
+//! 1. [`EstimationHook::pre_validate`] - reject a request before any work is done
+//! 2. [`EstimationHook::mutate_request`] - adjust the request before simulation
+//! 3. [`EstimationHook::post_process`] - adjust or veto the resulting estimate
+//! 4. [`EstimationHook::inspect_touched`] - veto an estimate by the accounts its
+//!    execution touched; only runs for hooks that opt in, as listing them takes
+//!    an extra traced simulation
+
+use crate::error::ServiceError;
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::TransactionRequest,
+};
+use std::collections::BTreeSet;
+
+/// A middleware-style hook into the estimation pipeline
+///
+/// Every method has a pass-through default, so implementations only override
+/// the stages they care about. Returning an error aborts the estimation;
+/// use [`ServiceError::Rejected`] for policy decisions.
+pub trait EstimationHook: Send + Sync {
+    /// Hook name, used in logs
+    fn name(&self) -> &'static str;
+
+    /// Validate the request before it is simulated
+    fn pre_validate(&self, _tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        Ok(())
+    }
+
+    /// Modify the request before it is simulated
+    fn mutate_request(&self, _tx_request: &mut TransactionRequest) -> Result<(), ServiceError> {
+        Ok(())
+    }
+
+    /// Inspect or adjust the estimate produced for the (mutated) request
+    fn post_process(&self, _tx_request: &TransactionRequest, gas: U256) -> Result<U256, ServiceError> {
+        Ok(gas)
+    }
+
+    /// Whether [`EstimationHook::inspect_touched`] should run for this hook
+    fn inspects_touched(&self) -> bool {
+        false
+    }
+
+    /// Inspect every account the simulated transaction touched: callers and
+    /// callees at any depth, created contracts and accounts whose balance or
+    /// code was read
+    ///
+    /// Only runs for locally simulated estimates.
+    fn inspect_touched(&self, _tx_request: &TransactionRequest, _touched: &BTreeSet<Address>) -> Result<(), ServiceError> {
+        Ok(())
+    }
+}
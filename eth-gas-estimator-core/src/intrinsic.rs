@@ -0,0 +1,140 @@
+//! Intrinsic-gas fast path
+//!
+//! Plain ETH transfers to externally owned accounts are the most common
+//! request, and their gas is known without executing anything: the 21000 base
+//! cost plus the intrinsic cost of the access list and of the EIP-7702
+//! authorizations. [`transfer_gas`] recognizes them from the request and two
+//! (cached) account reads, so they skip the EVM entirely.
+//!
+//! The analyzer is conservative: whenever the transaction could run code or
+//! fail validation (funds, nonce too low, fees, gas limits), it defers to the
+//! simulation, which reports the failure as usual.
+
+use crate::{
+    error::ServiceError,
+    foundry::{is_unpriced, run_simulation, tx_env_for_fork, Fork, SimulationMode},
+};
+use alloy::{primitives::TxKind, rpc::types::TransactionRequest};
+use revm::{
+    precompile::{PrecompileSpecId, Precompiles},
+    primitives::{SpecId, TxEnv, U256},
+    DatabaseRef,
+};
+use tracing::debug;
+
+/// Base cost of every transaction
+pub const TX_BASE_GAS: u64 = 21_000;
+
+/// Intrinsic cost of each address in the access list (EIP-2930)
+pub const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+
+/// Intrinsic cost of each storage key in the access list (EIP-2930)
+pub const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+
+/// Intrinsic cost of each authorization (EIP-7702)
+pub const AUTHORIZATION_GAS: u64 = 25_000;
+
+/// Intrinsic cost of each zero byte of call data
+pub const CALLDATA_ZERO_BYTE_GAS: u64 = 4;
+
+/// Intrinsic cost of each non-zero byte of call data (EIP-2028)
+pub const CALLDATA_NONZERO_BYTE_GAS: u64 = 16;
+
+/// Intrinsic cost of each non-zero byte of call data before Istanbul
+pub const CALLDATA_NONZERO_BYTE_GAS_FRONTIER: u64 = 68;
+
+/// Intrinsic cost of a contract deployment
+pub const CREATE_GAS: u64 = 32_000;
+
+/// Intrinsic cost of each 32-byte word of init code (EIP-3860)
+pub const INITCODE_WORD_GAS: u64 = 2;
+
+/// Intrinsic gas of a transaction without call data
+pub fn intrinsic_transfer_gas(tx_env: &TxEnv, authorizations: usize) -> u64 {
+    let storage_keys: usize = tx_env.access_list.iter().map(|item| item.storage_keys.len()).sum();
+    TX_BASE_GAS
+        + ACCESS_LIST_ADDRESS_GAS * tx_env.access_list.len() as u64
+        + ACCESS_LIST_STORAGE_KEY_GAS * storage_keys as u64
+        + AUTHORIZATION_GAS * authorizations as u64
+}
+
+/// Gas of a plain ETH transfer to an externally owned account, without simulating it
+///
+/// # Arguments
+///
+/// * `fork` - The fork whose state the target and sender are read from
+/// * `tx_request` - The transaction request
+///
+/// # Returns
+///
+/// * `Result<Option<u64>, ServiceError>` - The intrinsic gas if the request is a plain
+///   transfer that passes validation, `None` if it needs a simulation, or an error if
+///   the accounts can't be read
+pub async fn transfer_gas(fork: &Fork, tx_request: &TransactionRequest) -> Result<Option<u64>, ServiceError> {
+    // Only calls without data or blobs qualify
+    let Some(TxKind::Call(to)) = tx_request.to else {
+        return Ok(None);
+    };
+    let has_input = tx_request.input.input().is_some_and(|input| !input.is_empty());
+    let has_blobs = tx_request.blob_versioned_hashes.as_ref().is_some_and(|hashes| !hashes.is_empty())
+        || tx_request.sidecar.is_some();
+    if has_input || has_blobs {
+        return Ok(None);
+    }
+
+    // Precompiles have no code but still execute
+    if Precompiles::new(PrecompileSpecId::from_spec_id(fork.spec_id)).contains(&to)
+        || fork.precompiles.iter().any(|precompile| precompile.address == to)
+    {
+        return Ok(None);
+    }
+
+    // An authorization delegating the target would give it code
+    let authorizations = tx_request.authorization_list.as_deref().unwrap_or_default();
+    if !authorizations.is_empty() && !fork.spec_id.is_enabled_in(SpecId::PRAGUE) {
+        return Ok(None);
+    }
+    if authorizations.iter().any(|auth| auth.recover_authority().ok() == Some(to)) {
+        return Ok(None);
+    }
+
+    let tx_env = tx_env_for_fork(fork, tx_request, SimulationMode::Estimate)?;
+    let gas = intrinsic_transfer_gas(&tx_env, authorizations.len());
+
+    // Anything the EVM would reject up front is left to the simulation to report
+    let block_env = &fork.block_env;
+    let invalid_limit = tx_env.gas_limit < gas || U256::from(tx_env.gas_limit) > block_env.gas_limit;
+    let invalid_fees = (tx_env.gas_price < block_env.basefee && !is_unpriced(&tx_env))
+        || tx_env.gas_priority_fee.is_some_and(|priority| priority > tx_env.gas_price);
+    let wrong_chain = tx_env.chain_id.is_some_and(|chain_id| chain_id != fork.chain_id);
+    if invalid_limit || invalid_fees || wrong_chain {
+        return Ok(None);
+    }
+
+    let caller = tx_env.caller;
+    // The fork backend blocks while it fetches, keep it off the async runtime
+    let (sender, target) = run_simulation(fork, move |backend| {
+        let read_error = |e| ServiceError::Simulation(format!("Failed to read account: {}", e));
+        Ok((
+            backend.basic_ref(caller).map_err(read_error)?.unwrap_or_default(),
+            backend.basic_ref(to).map_err(read_error)?,
+        ))
+    })
+    .await?;
+
+    if target.is_some_and(|target| !target.is_empty_code_hash()) {
+        return Ok(None);
+    }
+    let cost = U256::from(tx_env.gas_limit)
+        .saturating_mul(tx_env.gas_price)
+        .saturating_add(tx_env.value);
+    let nonce_too_low = tx_env.nonce.is_some_and(|nonce| nonce < sender.nonce);
+    // Senders delegated with EIP-7702 are still EOAs, their code doesn't run
+    let sender_has_code = !sender.is_empty_code_hash() && !sender.code.as_ref().is_some_and(|code| code.is_eip7702());
+    if sender_has_code || nonce_too_low || sender.balance < cost {
+        return Ok(None);
+    }
+
+    debug!("Plain transfer to {}, intrinsic gas {}", to, gas);
+    Ok(Some(gas))
+}
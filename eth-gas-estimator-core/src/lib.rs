@@ -0,0 +1,57 @@
+//! Gas estimation and EVM simulation against forked Ethereum state
+//!
+//! The framework-free core of the gas estimator service: the estimator, the
+//! fork manager and its simulations, the upstream RPC clients and the request
+//! and response models. It doesn't depend on actix-web, so other Rust
+//! programs can embed it directly:
+//!
+//! ```no_run
+//! use eth_gas_estimator_core::{estimator::GasEstimator, rpc::EthereumClient};
+//! use alloy::rpc::types::TransactionRequest;
+//! use std::sync::Arc;
+//!
+//! # async fn run(rpc_url: &str, tx_request: TransactionRequest) -> eyre::Result<()> {
+//! let eth_client = Arc::new(EthereumClient::new(rpc_url).await?);
+//! let estimator = GasEstimator::new(eth_client, rpc_url);
+//! let estimate = estimator.estimate_raw_gas(&tx_request).await?;
+//! println!("{} gas at block {}", estimate.gas, estimate.block_number);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `actix` feature implements actix-web's `ResponseError` for
+//! [`error::ServiceError`] and adds the request timeout middleware of
+//! [`timeouts`], for servers built on actix-web such as the
+//! `eth-gas-estimator` binary.
+
+pub mod access_list;
+pub mod audit;
+pub mod backend;
+pub mod bundle;
+pub mod cache;
+pub mod chains;
+pub mod code_changes;
+pub mod correlation;
+pub mod degradation;
+pub mod divergence;
+pub mod error;
+pub mod estimator;
+pub mod explain;
+#[cfg(feature = "test_utils")]
+pub mod fixtures;
+pub mod foundry;
+pub mod gas_dependence;
+pub mod hooks;
+pub mod intrinsic;
+pub mod memory;
+pub mod models;
+pub mod profile;
+pub mod provenance;
+pub mod quotes;
+pub mod rpc;
+pub mod simulation_limit;
+pub mod state_diff;
+pub mod storage_batch;
+pub mod timeouts;
+pub mod tracers;
+pub mod units;
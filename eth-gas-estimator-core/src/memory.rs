@@ -0,0 +1,98 @@
+//! Return data and memory expansion accounting
+//!
+//! View-style transactions that build large results in memory pay the
+//! quadratic memory expansion cost (3 gas per word plus words² / 512) in every
+//! call frame, which is often what makes their estimates balloon. The
+//! transaction runs once under [`MemoryTracer`], which records the memory each
+//! frame reaches, and the verbose simulation report breaks the cost down:
+//!
+//! * `peakMemoryBytes` - the largest memory any single frame reached
+//! * `memoryExpansionGas` - the expansion gas paid across all frames
+//! * `returnDataSize` / `returnDataGas` - the size of the returned data and the
+//!   expansion gas of the top-level memory that holds it
+
+use crate::{
+    error::ServiceError,
+    foundry::{execute_on_fork, tx_env_for_fork, Fork, SimulationMode},
+    models::simulation::MemoryUsage,
+};
+use alloy::rpc::types::TransactionRequest;
+use revm::{interpreter::Interpreter, Database, EvmContext, Inspector};
+use tracing::debug;
+
+/// Linear cost of each memory word
+pub const MEMORY_WORD_GAS: u64 = 3;
+
+/// Divisor of the quadratic memory cost
+pub const MEMORY_QUADRATIC_DIVISOR: u64 = 512;
+
+/// Total expansion gas of a memory of `size` bytes
+pub fn memory_expansion_gas(size: usize) -> u64 {
+    let words = size.div_ceil(32) as u64;
+    MEMORY_WORD_GAS * words + words * words / MEMORY_QUADRATIC_DIVISOR
+}
+
+/// Inspector recording the memory reached by each call frame
+#[derive(Debug, Default)]
+pub struct MemoryTracer {
+    /// Memory size of each open call frame by depth, in bytes
+    frames: Vec<usize>,
+    /// Largest memory any frame reached, in bytes
+    peak_memory: usize,
+    /// Expansion gas of the frames that returned
+    expansion_gas: u64,
+}
+
+impl MemoryTracer {
+    /// Account for the frames deeper than `depth`, which have returned
+    fn close_frames(&mut self, depth: usize) {
+        while self.frames.len() > depth {
+            if let Some(size) = self.frames.pop() {
+                self.expansion_gas += memory_expansion_gas(size);
+            }
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for MemoryTracer {
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        // A step in a shallower frame means every deeper frame has returned
+        let depth = context.journaled_state.depth;
+        self.close_frames(depth + 1);
+        self.frames.resize(depth + 1, 0);
+
+        let size = interp.shared_memory.len();
+        if let Some(frame) = self.frames.last_mut() {
+            *frame = (*frame).max(size);
+        }
+        self.peak_memory = self.peak_memory.max(size);
+    }
+}
+
+/// Measure the return data and memory usage of a transaction on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction request
+///
+/// # Returns
+///
+/// * `Result<MemoryUsage, ServiceError>` - The sizes and their gas contributions, or an
+///   error if the transaction fails validation
+pub async fn memory_usage_on_fork(fork: &Fork, tx_request: &TransactionRequest) -> Result<MemoryUsage, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, SimulationMode::Estimate)?;
+    let (result, tracer) =
+        execute_on_fork(fork, tx_env, SimulationMode::Estimate, Some(MemoryTracer::default())).await?;
+    let mut tracer = tracer.unwrap_or_default();
+    tracer.close_frames(0);
+
+    let return_data_size = result.output().map_or(0, |output| output.len());
+    debug!("Peak memory {} bytes, {} bytes returned", tracer.peak_memory, return_data_size);
+    Ok(MemoryUsage {
+        return_data_size: return_data_size as u64,
+        return_data_gas: memory_expansion_gas(return_data_size),
+        peak_memory_bytes: tracer.peak_memory as u64,
+        memory_expansion_gas: tracer.expansion_gas,
+    })
+}
@@ -0,0 +1,28 @@
+//! Generated access lists (`eth_createAccessList`)
+
+use crate::estimator::EstimateSource;
+use alloy::{
+    eips::eip2930::AccessList,
+    primitives::{B256, U256, U64},
+};
+use serde::Serialize;
+
+/// Result of `eth_createAccessList`: the generated list and what including it saves
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListReport {
+    /// Accounts and storage slots worth declaring up front
+    pub access_list: AccessList,
+    /// Gas the transaction uses with the access list
+    pub gas_used: U256,
+    /// Gas the transaction uses as sent
+    pub gas_used_without_access_list: U256,
+    /// Gas the access list saves, negative if including it costs more
+    pub gas_saved: i64,
+    /// Where the gas figures came from
+    pub source: EstimateSource,
+    /// Head block the gas figures hold for
+    pub block_number: U64,
+    /// Hash of the head block the gas figures hold for
+    pub block_hash: B256,
+}
@@ -0,0 +1,79 @@
+//! Withdrawal batching comparison
+
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+/// One payout in a withdrawal batch
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    /// Recipient address
+    pub to: Address,
+    /// Amount in wei (or token base units)
+    pub amount: U256,
+}
+
+/// Request body of `POST /api/v1/batching/withdrawals`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalBatchRequest {
+    /// Hot wallet paying out the withdrawals
+    pub from: Address,
+    /// ERC-20 token being withdrawn (default: native ETH)
+    #[serde(default)]
+    pub token: Option<Address>,
+    /// Disperse contract to compare against (default: the configured one)
+    #[serde(default)]
+    pub disperse_contract: Option<Address>,
+    /// The payouts
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+/// Cost of paying out every withdrawal as its own transaction
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerTransactionStrategy {
+    /// Number of transactions sent
+    pub transactions: usize,
+    /// Gas used by all of them together
+    pub total_gas: U256,
+    /// Number of transactions that reverted or halted
+    pub failed: usize,
+}
+
+/// Cost of paying out every withdrawal in one disperse call
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchedStrategy {
+    /// Disperse contract used
+    pub contract: Address,
+    /// Gas of the token approval preceding the batch (token withdrawals only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_gas: Option<U256>,
+    /// Gas of the disperse call itself
+    pub batch_gas: U256,
+    /// Approval plus batch gas
+    pub total_gas: U256,
+    /// "success", "revert" or "halt"
+    pub status: &'static str,
+}
+
+/// Comparison of the two payout strategies
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalBatchReport {
+    /// Block both strategies were simulated against
+    pub block_number: u64,
+    /// Number of withdrawals
+    pub recipients: usize,
+    /// One transaction per withdrawal
+    pub per_transaction: PerTransactionStrategy,
+    /// One disperse call for all withdrawals (absent when no disperse contract is deployed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batched: Option<BatchedStrategy>,
+    /// Gas saved by batching (negative when batching is more expensive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub savings_gas: Option<i128>,
+    /// "batched" or "perTransaction"
+    pub recommended: &'static str,
+}
@@ -0,0 +1,38 @@
+//! Bulk estimation of independent transactions
+
+use crate::{estimator::EstimateSource, models::jsonrpc::BatchSummary};
+use alloy::primitives::{B256, U256, U64};
+use serde::Serialize;
+
+/// Outcome of one transaction in a bulk estimate
+///
+/// Exactly one of `gas` (with its `source`) and `error` is set.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEstimateItem {
+    /// Position in the request
+    pub index: usize,
+    /// Estimated gas limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas: Option<U256>,
+    /// Where the estimate came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<EstimateSource>,
+    /// Why the transaction couldn't be estimated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response of `POST /api/v1/eth/estimateGas/bulk`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkEstimateResponse {
+    /// Block every estimate holds for
+    pub block_number: U64,
+    /// Hash of the block every estimate holds for
+    pub block_hash: B256,
+    /// One entry per transaction, in request order
+    pub results: Vec<BulkEstimateItem>,
+    /// Item count and number of failed items
+    pub summary: BatchSummary,
+}
@@ -0,0 +1,98 @@
+//! Bundle simulation (`eth_callBundle`)
+
+use alloy::{
+    primitives::{Address, Bytes, B256, I256, U256, U64},
+    rpc::types::BlockNumberOrTag,
+};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// First param of `eth_callBundle`, as searchers send it to relays
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleParams {
+    /// Signed raw transactions (EIP-2718 encoded), in execution order
+    pub txs: Vec<Bytes>,
+    /// Number of the block the bundle targets
+    pub block_number: U64,
+    /// Block whose state the bundle runs on: `latest` (default) or `pending`
+    #[serde(default)]
+    pub state_block_number: Option<BlockNumberOrTag>,
+    /// Timestamp of the target block (default: state block + 12 seconds)
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub timestamp: Option<u64>,
+    /// Beneficiary of the target block (default: the state block's)
+    #[serde(default)]
+    pub coinbase: Option<Address>,
+    /// Base fee of the target block (default: the state block's)
+    #[serde(default)]
+    pub base_fee: Option<U256>,
+    /// Gas limit of the target block (default: the state block's)
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub gas_limit: Option<u64>,
+}
+
+/// Result of `eth_callBundle`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResponse {
+    /// Keccak-256 of the concatenated transaction hashes
+    pub bundle_hash: B256,
+    /// Block whose state the bundle ran on
+    pub state_block_number: U64,
+    /// Block the bundle was simulated as part of
+    pub block_number: U64,
+    /// Coinbase payment per unit of gas (`coinbaseDiff / totalGasUsed`, zero if the coinbase lost funds)
+    pub bundle_gas_price: U256,
+    /// Change of the coinbase balance over the whole bundle
+    #[serde(serialize_with = "signed_quantity")]
+    pub coinbase_diff: I256,
+    /// Part of the coinbase diff paid directly rather than as priority fees
+    #[serde(serialize_with = "signed_quantity")]
+    pub eth_sent_to_coinbase: I256,
+    /// Priority fees paid by the bundle
+    pub gas_fees: U256,
+    /// Gas used by all transactions
+    pub total_gas_used: U64,
+    /// One result per transaction, in order
+    pub results: Vec<BundleTransactionResult>,
+}
+
+/// Outcome of one transaction of a simulated bundle
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransactionResult {
+    /// Hash of the signed transaction
+    pub tx_hash: B256,
+    /// Recovered sender
+    pub from_address: Address,
+    /// Recipient, `None` for contract creations
+    pub to_address: Option<Address>,
+    /// Gas used by the transaction
+    pub gas_used: U64,
+    /// Effective gas price paid
+    pub gas_price: U256,
+    /// Priority fees paid (`gasUsed * (gasPrice - baseFee)`)
+    pub gas_fees: U256,
+    /// Change of the coinbase balance caused by the transaction
+    #[serde(serialize_with = "signed_quantity")]
+    pub coinbase_diff: I256,
+    /// Part of the coinbase diff paid directly rather than as priority fees
+    #[serde(serialize_with = "signed_quantity")]
+    pub eth_sent_to_coinbase: I256,
+    /// Returned data, on success
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Bytes>,
+    /// Why the transaction failed, if it reverted or halted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Revert data, if the transaction reverted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert: Option<Bytes>,
+}
+
+/// Serialize a signed amount as a hex quantity, prefixed with `-` when negative
+fn signed_quantity<S: Serializer>(value: &I256, serializer: S) -> Result<S::Ok, S::Error> {
+    let (sign, abs) = value.into_sign_and_abs();
+    let minus = if sign.is_negative() { "-" } else { "" };
+    serializer.collect_str(&format_args!("{}{:#x}", minus, abs))
+}
@@ -0,0 +1,56 @@
+//! Shadow comparisons and divergence alarms
+
+use alloy::primitives::Address;
+use serde::Serialize;
+
+/// A local estimate compared with the upstream node's
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    /// Target of the estimated transaction (absent for deployments)
+    pub to: Option<Address>,
+    /// Locally simulated estimate
+    pub local: u64,
+    /// Upstream `eth_estimateGas` result
+    pub upstream: u64,
+    /// Difference in percent of the upstream estimate
+    pub divergence_percent: f64,
+}
+
+/// Webhook payload sent when the divergence alarm fires or resolves
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergenceAlarm {
+    /// "firing" or "resolved"
+    pub status: &'static str,
+    /// Comparisons in the window
+    pub comparisons: usize,
+    /// Comparisons in the window beyond the threshold
+    pub divergent: usize,
+    /// Divergence (in percent) counted as divergent
+    pub threshold_percent: f64,
+    /// Length of the window in seconds
+    pub window_secs: u64,
+    /// Latest divergent comparison
+    pub example: Option<Comparison>,
+}
+
+/// Response of `GET /admin/divergence`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergenceStatus {
+    /// Whether the alarm is firing
+    pub firing: bool,
+    /// Unix timestamp (seconds) at which the alarm fired, while firing
+    pub firing_since: Option<u64>,
+    /// Length of the window in seconds
+    pub window_secs: u64,
+    /// Divergence (in percent) counted as divergent
+    pub threshold_percent: f64,
+    /// Comparisons in the window
+    pub comparisons: usize,
+    /// Comparisons in the window beyond the threshold
+    pub divergent: usize,
+    /// Latest divergent comparison
+    pub last_divergent: Option<Comparison>,
+}
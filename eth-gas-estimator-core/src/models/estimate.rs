@@ -0,0 +1,61 @@
+//! Extended estimation results (`/api/v2/eth/estimateGas`)
+
+use crate::{
+    estimator::EstimateSource,
+    models::{jsonrpc::BlobGasUsage, trace::CallFrame},
+};
+use alloy::primitives::{Address, Bytes, Log, B256, U256, U64};
+use serde::Serialize;
+
+/// Gas estimate together with what the transaction does under it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtendedEstimate {
+    /// Estimated gas limit
+    pub gas: U256,
+    /// Where the estimate came from
+    pub source: EstimateSource,
+    /// "success", "revert" or "halt" under the estimated limit
+    pub status: &'static str,
+    /// Gas used under the estimated limit
+    pub gas_used: U256,
+    /// Return data, or the revert data of a reverting transaction
+    pub return_data: Bytes,
+    /// Logs emitted by a successful transaction
+    pub logs: Vec<Log>,
+    /// Address of the contract a successful deployment creates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_address: Option<Address>,
+    /// Block the estimate holds for
+    pub block_number: U64,
+    /// Hash of the block the estimate holds for
+    pub block_hash: B256,
+    /// Blob gas and blob base fee, for type-3 transactions
+    #[serde(flatten)]
+    pub blob_gas: Option<BlobGasUsage>,
+    /// Call tree under the estimated limit, in `callTracer` format, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_frames: Option<CallFrame>,
+    /// Reasons the transaction may be hard or impossible to include in a block
+    pub warnings: Vec<EstimateWarning>,
+}
+
+/// Kind of an estimate warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EstimateWarningKind {
+    /// The estimate takes more than the configured fraction of the block gas limit
+    NearBlockGasLimit,
+    /// The estimate is above the block gas limit
+    ExceedsBlockGasLimit,
+}
+
+/// Warning about the includability of an estimated transaction
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateWarning {
+    /// What the estimate runs into
+    pub kind: EstimateWarningKind,
+    /// Human-readable explanation
+    pub message: String,
+}
@@ -0,0 +1,141 @@
+//! Estimate explanations
+//!
+//! Answers "why is this estimate 480k?" for support teams, without traces:
+//! the estimate is split into the intrinsic gas, the execution gas by kind of
+//! operation, the refunds and the margin above the gas actually used. Gas
+//! golfers can add a profile of the execution gas per opcode and per contract.
+
+use crate::estimator::EstimateSource;
+use alloy::primitives::{Address, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// Options of `x_explainEstimate` (second param)
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainOptions {
+    /// Add a plain-text summary of the explanation
+    #[serde(default)]
+    pub summary: bool,
+    /// Add the gas profile per opcode and per contract
+    #[serde(default)]
+    pub profile: bool,
+}
+
+/// Gas of one kind of operation
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct GasCategory {
+    /// Gas spent on these operations
+    pub gas: u64,
+    /// Number of operations
+    pub count: u64,
+}
+
+/// Intrinsic gas, charged before any code runs
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntrinsicGas {
+    /// Sum of the components
+    pub total: u64,
+    /// Base cost of every transaction
+    pub base: u64,
+    /// Cost of the call data (or init code)
+    pub calldata: u64,
+    /// Cost of a contract deployment, including its init code words
+    pub creation: u64,
+    /// Cost of the EIP-2930 access list
+    pub access_list: u64,
+    /// Cost of the EIP-7702 authorizations
+    pub authorizations: u64,
+}
+
+/// Execution gas, by kind of operation
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionGas {
+    /// Gas spent by the code, before refunds
+    pub total: u64,
+    /// Arithmetic, memory, hashing, control flow and every other operation
+    pub compute: GasCategory,
+    /// `SLOAD` and account reads (`BALANCE`, `EXTCODESIZE`, `EXTCODECOPY`, `EXTCODEHASH`)
+    pub storage_reads: GasCategory,
+    /// `SSTORE`
+    pub storage_writes: GasCategory,
+    /// Calls, contract creations and self-destructs, without the gas spent by the
+    /// callee's own operations; includes precompile execution
+    pub calls: GasCategory,
+    /// `LOG0` to `LOG4`
+    pub logs: GasCategory,
+    /// Code deposit of the deployed contracts
+    pub code_deposit: GasCategory,
+}
+
+/// Result of `x_explainEstimate`: what an estimate is made of
+///
+/// `gasUsed` is `intrinsic.total + execution.total - refund`, plus
+/// `calldataFloor` when the EIP-7623 floor applies; the estimate is `gasUsed`
+/// plus `margin`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasExplanation {
+    /// Estimated gas limit
+    pub gas: U256,
+    /// Where the estimate came from
+    pub source: EstimateSource,
+    /// "success", "revert" or "halt" under the estimated limit
+    pub status: &'static str,
+    /// Gas used under the estimated limit
+    pub gas_used: u64,
+    /// Intrinsic gas
+    pub intrinsic: IntrinsicGas,
+    /// Execution gas by kind of operation
+    pub execution: ExecutionGas,
+    /// Gas refunded at the end of the transaction (cleared storage, EIP-7702 authorities)
+    pub refund: u64,
+    /// Gas added to reach the EIP-7623 calldata floor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calldata_floor: Option<u64>,
+    /// Estimate above the gas used: gas needed up front but not used (refunds,
+    /// gas kept back by calls) and the configured headroom
+    pub margin: u64,
+    /// Plain-text summary, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Execution gas per opcode and per contract, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<GasProfile>,
+    /// Block the estimate holds for
+    pub block_number: U64,
+    /// Hash of the block the estimate holds for
+    pub block_hash: B256,
+}
+
+/// Execution gas per opcode and per contract (see [`crate::profile`])
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct GasProfile {
+    /// Opcodes, most expensive first
+    pub opcodes: Vec<OpcodeGas>,
+    /// Contracts whose code ran, most expensive first
+    pub contracts: Vec<ContractGas>,
+}
+
+/// Gas spent by one opcode
+#[derive(Debug, Clone, Serialize)]
+pub struct OpcodeGas {
+    /// Mnemonic, e.g. `SSTORE`
+    pub opcode: String,
+    /// Gas spent by its executions, without the gas forwarded to calls
+    pub gas: u64,
+    /// Number of executions
+    pub count: u64,
+}
+
+/// Gas spent running one contract's code
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractGas {
+    /// Address of the code
+    pub address: Address,
+    /// Gas spent by its operations, precompile execution and code deposit
+    pub gas: u64,
+    /// Number of operations executed
+    pub operations: u64,
+}
@@ -0,0 +1,112 @@
+//! Fee suggestions
+
+use alloy::primitives::{B256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// Query of `GET /api/v1/fees/next`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextFeesQuery {
+    /// Answer as soon as the head is past this block (default: the current head)
+    #[serde(default)]
+    pub after: Option<u64>,
+    /// How long to wait for a new head, in milliseconds
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Fees at a head block, with suggestions for a transaction in the next block
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeSuggestion {
+    /// Head block the fees were derived from
+    pub block_number: U64,
+    /// Hash of the head block
+    pub block_hash: B256,
+    /// Base fee of the head block
+    pub base_fee_per_gas: U256,
+    /// Base fee of the next block (EIP-1559)
+    pub next_base_fee_per_gas: U256,
+    /// Suggested tip, from the upstream node
+    pub max_priority_fee_per_gas: U256,
+    /// Suggested fee cap: twice the next base fee plus the tip
+    pub max_fee_per_gas: U256,
+}
+
+/// A `maxPriorityFeePerGas` suggestion at one percentile of recent tips
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeSuggestion {
+    /// Percentile of the tips paid in the window, between 0 and 100
+    pub percentile: f64,
+    /// Suggested tip
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Response of `GET /api/v1/fees/priority`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityFeeSuggestions {
+    /// Most recent block in the window, absent until the first block is read
+    pub block_number: Option<U64>,
+    /// Number of blocks in the window
+    pub window_blocks: usize,
+    /// Number of blocks in the window with transactions, whose tips were sampled
+    pub sampled_blocks: usize,
+    /// Suggestions, in the configured percentile order
+    pub suggestions: Vec<PriorityFeeSuggestion>,
+}
+
+/// Fee envelope for one urgency level
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeRecommendation {
+    /// "slow", "standard" or "fast"
+    pub urgency: &'static str,
+    /// Blocks the fee cap covers, even if every block until then is full
+    pub blocks: u64,
+    /// Suggested tip
+    pub max_priority_fee_per_gas: U256,
+    /// Suggested fee cap: the highest base fee within `blocks` blocks plus the tip
+    pub max_fee_per_gas: U256,
+}
+
+/// Response of `GET /api/v1/fees/recommendations`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeRecommendations {
+    /// Head block the base fees were derived from
+    pub block_number: U64,
+    /// Hash of the head block
+    pub block_hash: B256,
+    /// Base fee of the head block
+    pub base_fee_per_gas: U256,
+    /// Base fee of the next block (EIP-1559)
+    pub next_base_fee_per_gas: U256,
+    /// One fee envelope per urgency level, slowest first
+    pub levels: Vec<FeeRecommendation>,
+}
+
+/// Notification pushed to `newFees` subscribers at every new head
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewFees {
+    /// The new head block
+    pub block_number: U64,
+    /// Hash of the new head block
+    pub block_hash: B256,
+    /// Base fee of the new head block
+    pub base_fee_per_gas: U256,
+    /// Base fee of the next block (EIP-1559)
+    pub next_base_fee_per_gas: U256,
+    /// Suggested tip, from the upstream node
+    pub max_priority_fee_per_gas: U256,
+    /// Suggested fee cap: twice the next base fee plus the tip
+    pub max_fee_per_gas: U256,
+    /// Blob base fee of the next block, on chains with blobs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_base_fee_per_gas: Option<U256>,
+    /// Tips at the configured percentiles of recent blocks, when priority fee tracking is enabled
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub priority_fee_suggestions: Vec<PriorityFeeSuggestion>,
+}
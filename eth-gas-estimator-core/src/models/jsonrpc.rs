@@ -0,0 +1,735 @@
+use alloy::eips::{
+    eip2930::AccessList,
+    eip4844::BlobTransactionSidecar,
+    eip7702::SignedAuthorization,
+    BlockId,
+};
+use alloy::primitives::{Address, Bytes, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+use crate::estimator::EstimateSource;
+
+/// JSON-RPC 2.0 request structure
+///
+/// This structure represents a standard JSON-RPC request with generic parameters.
+#[derive(Debug, Deserialize)]
+pub struct JsonRpcRequest<T> {
+    /// JSON-RPC protocol version (should be "2.0")
+    pub jsonrpc: String,
+    
+    /// Method name to call
+    pub method: String,
+    
+    /// Method parameters (may be omitted for methods without parameters)
+    #[serde(default)]
+    pub params: T,
+    
+    /// Request identifier
+    #[serde(default)]
+    pub id: serde_json::Value,
+}
+
+/// JSON-RPC 2.0 successful response
+///
+/// This structure represents a standard JSON-RPC successful response with generic result.
+#[derive(Debug, Serialize)]
+pub struct JsonRpcSuccess<T> {
+    /// JSON-RPC protocol version (always "2.0")
+    pub jsonrpc: String,
+    
+    /// Request identifier (matching the request)
+    pub id: serde_json::Value,
+    
+    /// Method result
+    pub result: T,
+
+    /// Where a gas estimate came from ("simulation" or "upstream"), for `eth_estimateGas`
+    ///
+    /// Not a JSON-RPC member: the v1 schema reports it in the `X-Estimate-Source`
+    /// header, the v2 schema under `meta`.
+    #[serde(skip)]
+    pub source: Option<EstimateSource>,
+
+    /// How long the result may be cached, for results tied to the head block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheScope>,
+
+    /// Blob gas of a type-3 transaction, only reported in the v2 schema
+    #[serde(skip)]
+    pub blob_gas: Option<BlobGasUsage>,
+}
+
+/// Blob gas of an EIP-4844 transaction, on top of its execution gas
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobGasUsage {
+    /// Blob gas consumed by the transaction's blobs
+    pub blob_gas_used: U64,
+
+    /// Blob base fee at the head block
+    pub blob_base_fee: U256,
+}
+
+/// Caching metadata of a result computed against the head block
+///
+/// The result holds for as long as `blockHash` is the head, and must be
+/// discarded once a new head (or a reorg) is observed, so clients can cache it
+/// exactly instead of guessing a TTL.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheScope {
+    /// Number of the block the result was computed against
+    pub block_number: U64,
+
+    /// Hash of the block the result was computed against
+    pub block_hash: B256,
+
+    /// Event invalidating the result (always `"newHead"`)
+    pub invalidate_on: &'static str,
+}
+
+impl CacheScope {
+    /// Scope a result to the given head block
+    pub fn block(block_number: u64, block_hash: B256) -> Self {
+        Self {
+            block_number: U64::from(block_number),
+            block_hash,
+            invalidate_on: "newHead",
+        }
+    }
+}
+
+/// JSON-RPC 2.0 error response
+///
+/// This structure represents a standard JSON-RPC error response.
+#[derive(Debug, Serialize)]
+pub struct JsonRpcError {
+    /// JSON-RPC protocol version (always "2.0")
+    pub jsonrpc: String,
+    
+    /// Request identifier (matching the request)
+    pub id: serde_json::Value,
+    
+    /// Error details
+    pub error: JsonRpcErrorDetail,
+}
+
+/// JSON-RPC 2.0 error detail
+///
+/// This structure contains the detailed error information in a JSON-RPC error response.
+#[derive(Debug, Serialize)]
+pub struct JsonRpcErrorDetail {
+    /// Error code
+    pub code: i32,
+    
+    /// Error message
+    pub message: String,
+    
+    /// Additional error data (optional)
+    pub data: Option<serde_json::Value>,
+}
+
+/// JSON-RPC 2.0 response of either kind
+///
+/// Used where successes and errors are mixed in one payload, such as batch responses.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum JsonRpcResponse<T> {
+    /// Successful response carrying a result
+    Success(JsonRpcSuccess<T>),
+
+    /// Error response carrying error details
+    Error(JsonRpcError),
+}
+
+impl<T> From<Result<JsonRpcSuccess<T>, JsonRpcError>> for JsonRpcResponse<T> {
+    fn from(result: Result<JsonRpcSuccess<T>, JsonRpcError>) -> Self {
+        match result {
+            Ok(success) => JsonRpcResponse::Success(success),
+            Err(error) => JsonRpcResponse::Error(error),
+        }
+    }
+}
+
+/// JSON-RPC 2.0 successful response in the v2 schema
+///
+/// The non-standard members of the v1 schema (`source`, `cache`) are grouped
+/// under `meta` instead of sitting next to `result`, so the envelope itself
+/// stays plain JSON-RPC 2.0.
+#[derive(Debug, Serialize)]
+pub struct JsonRpcSuccessV2<T> {
+    /// JSON-RPC protocol version (always "2.0")
+    pub jsonrpc: String,
+
+    /// Request identifier (matching the request)
+    pub id: serde_json::Value,
+
+    /// Method result
+    pub result: T,
+
+    /// Service metadata about the result
+    pub meta: ResponseMeta,
+}
+
+/// Service metadata of a v2 response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseMeta {
+    /// Schema version of the response (always 2)
+    pub api_version: u8,
+
+    /// Where a gas estimate came from ("simulation" or "upstream")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<EstimateSource>,
+
+    /// How long the result may be cached, for results tied to the head block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheScope>,
+
+    /// Blob gas and blob base fee, for estimates of type-3 transactions
+    #[serde(flatten)]
+    pub blob_gas: Option<BlobGasUsage>,
+}
+
+impl<T> From<JsonRpcSuccess<T>> for JsonRpcSuccessV2<T> {
+    fn from(success: JsonRpcSuccess<T>) -> Self {
+        Self {
+            jsonrpc: success.jsonrpc,
+            id: success.id,
+            result: success.result,
+            meta: ResponseMeta {
+                api_version: 2,
+                source: success.source,
+                cache: success.cache,
+                blob_gas: success.blob_gas,
+            },
+        }
+    }
+}
+
+/// JSON-RPC 2.0 response of either kind, in the v2 schema
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum JsonRpcResponseV2<T> {
+    /// Successful response carrying a result
+    Success(JsonRpcSuccessV2<T>),
+
+    /// Error response carrying error details (unchanged from v1)
+    Error(JsonRpcError),
+}
+
+impl<T> From<JsonRpcResponse<T>> for JsonRpcResponseV2<T> {
+    fn from(response: JsonRpcResponse<T>) -> Self {
+        match response {
+            JsonRpcResponse::Success(success) => JsonRpcResponseV2::Success(success.into()),
+            JsonRpcResponse::Error(error) => JsonRpcResponseV2::Error(error),
+        }
+    }
+}
+
+/// Parameters for eth_estimateGas JSON-RPC method
+///
+/// This structure contains the parameters for the eth_estimateGas method
+/// following the Ethereum JSON-RPC specification. Fields deserialize straight
+/// into their typed values with alloy's RPC serde helpers, so a malformed field
+/// is rejected with its name and the reason (quantities accept hex or decimal).
+#[derive(Debug, Deserialize)]
+pub struct EthEstimateGasParams {
+    /// Sender address (optional)
+    #[serde(default)]
+    pub from: Option<Address>,
+    
+    /// Recipient address (optional for contract creation)
+    #[serde(default)]
+    pub to: Option<Address>,
+    
+    /// Gas limit (optional)
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub gas: Option<u64>,
+
+    /// Legacy gas price (optional)
+    #[serde(default, rename = "gasPrice", with = "alloy::serde::quantity::opt")]
+    pub gas_price: Option<u128>,
+
+    /// EIP-1559 max fee per gas (optional)
+    #[serde(default, rename = "maxFeePerGas", with = "alloy::serde::quantity::opt")]
+    pub max_fee_per_gas: Option<u128>,
+    
+    /// EIP-1559 max priority fee per gas (optional)
+    #[serde(default, rename = "maxPriorityFeePerGas", with = "alloy::serde::quantity::opt")]
+    pub max_priority_fee_per_gas: Option<u128>,
+
+    /// Transaction value in wei (optional)
+    #[serde(default)]
+    pub value: Option<U256>,
+
+    /// Transaction input data (optional)
+    /// Can be specified as either "data" or "input"
+    #[serde(default, rename = "data", alias = "input")]
+    pub input: Option<Bytes>,
+
+    /// Block number or tag; rejected, as the block is the method's second param
+    #[serde(default)]
+    pub block: Option<BlockId>,
+
+    /// Transaction nonce (optional)
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub nonce: Option<u64>,
+
+    /// Chain ID (optional)
+    #[serde(default, rename = "chainId", with = "alloy::serde::quantity::opt")]
+    pub chain_id: Option<u64>,
+
+    /// EIP-2930 access list (optional)
+    #[serde(default, rename = "accessList")]
+    pub access_list: Option<AccessList>,
+
+    /// EIP-2718 transaction type (optional)
+    #[serde(default, rename = "type", with = "alloy::serde::quantity::opt")]
+    pub transaction_type: Option<u8>,
+
+    /// EIP-4844 fields
+    #[serde(default, rename = "blobVersionedHashes")]
+    pub blob_versioned_hashes: Option<Vec<B256>>,
+
+    #[serde(default, rename = "maxFeePerBlobGas", with = "alloy::serde::quantity::opt")]
+    pub max_fee_per_blob_gas: Option<u128>,
+
+    /// Number of blobs to plan for, instead of `blobVersionedHashes` (non-standard)
+    #[serde(default, rename = "blobCount", with = "alloy::serde::quantity::opt")]
+    pub blob_count: Option<u64>,
+
+    #[serde(default)]
+    pub sidecar: Option<BlobTransactionSidecar>,
+
+    /// EIP-7702
+    #[serde(default, rename = "authorizationList")]
+    pub authorization_list: Option<Vec<SignedAuthorization>>,
+
+    /// Accept authorizations without recovering their signer, for draft estimations
+    /// with placeholder signatures (non-standard)
+    #[serde(default, rename = "skipAuthorizationCheck")]
+    pub skip_authorization_check: Option<bool>,
+
+    /// Safety multiplier applied to the estimate, replacing `ESTIMATE_GAS_MULTIPLIER` (non-standard)
+    #[serde(default, rename = "gasMultiplier")]
+    pub gas_multiplier: Option<f64>,
+
+    /// Gas added to the estimate after the multiplier, replacing `ESTIMATE_GAS_BUFFER` (non-standard)
+    #[serde(default, rename = "gasBuffer", with = "alloy::serde::quantity::opt")]
+    pub gas_buffer: Option<u64>,
+}
+
+/// One account of the `stateOverrides` param of `eth_estimateGas` and `eth_call`
+///
+/// Follows geth's state override set. Only `code` is supported; the other
+/// members are accepted so they can be rejected with a clear error.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverrideParams {
+    /// Code run at the account instead of its chain code
+    #[serde(default)]
+    pub code: Option<Bytes>,
+
+    /// Balance override (not supported)
+    #[serde(default)]
+    pub balance: Option<serde_json::Value>,
+
+    /// Nonce override (not supported)
+    #[serde(default)]
+    pub nonce: Option<serde_json::Value>,
+
+    /// Storage replacing the account's whole storage (not supported)
+    #[serde(default)]
+    pub state: Option<serde_json::Value>,
+
+    /// Storage slots overridden individually (not supported)
+    #[serde(default)]
+    pub state_diff: Option<serde_json::Value>,
+}
+
+impl AccountOverrideParams {
+    /// Names of the members set that can't be overridden
+    pub fn unsupported(&self) -> Vec<&'static str> {
+        [
+            ("balance", self.balance.is_some()),
+            ("nonce", self.nonce.is_some()),
+            ("state", self.state.is_some()),
+            ("stateDiff", self.state_diff.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+}
+
+/// Summary of a batch whose items succeed or fail independently
+///
+/// Batch endpoints report per-item errors inline and attach this summary so
+/// clients can tell at a glance whether any item needs attention.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    /// Number of items in the batch
+    pub total: usize,
+
+    /// Number of items that produced an error response
+    pub failed_count: usize,
+}
+
+impl BatchSummary {
+    /// Create a new batch summary
+    pub fn new(total: usize, failed_count: usize) -> Self {
+        Self { total, failed_count }
+    }
+}
+
+impl JsonRpcError {
+    /// Create a new JSON-RPC parse error
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier (usually null, as the request could not be read)
+    /// * `message` - Error message
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn parse_error(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32700,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC invalid request error
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier (or null if it could not be determined)
+    /// * `message` - Error message
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn invalid_request(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32600,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC method not found error
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `method` - The requested method name
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn method_not_found(id: serde_json::Value, method: &str) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32601,
+                message: format!("Method not found: {}", method),
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC invalid parameters error
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Error message
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn invalid_params(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32602,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC internal error
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Error message
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn internal_error(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32603,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC insufficient funds error (code -32000, as nodes report it)
+    ///
+    /// Wallets recognize this error by its message, so `message` should start with
+    /// `insufficient funds for gas * price + value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Error message naming the sender, its balance and the cost
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn insufficient_funds(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32000,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Whether this is the insufficient funds error built by [`JsonRpcError::insufficient_funds`]
+    ///
+    /// Nodes report other failures with code -32000 too, so the message tells them apart.
+    pub fn is_insufficient_funds(&self) -> bool {
+        self.error.code == -32000 && self.error.message.starts_with("insufficient funds")
+    }
+
+    /// Create a new JSON-RPC transaction rejected error (EIP-1474 code -32003)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Reason the transaction was rejected
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn transaction_rejected(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32003,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC method not supported error (EIP-1474 code -32004)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Why the method isn't available
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn method_not_supported(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32004,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC limit exceeded error (EIP-1474 code -32005)
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Which limit was exceeded
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn limit_exceeded(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32005,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC execution reverted error (code 3, as geth reports it)
+    ///
+    /// `error.data` holds the hex encoded revert data, which clients decode
+    /// against the contract's ABI.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - `execution reverted`, followed by the decoded reason if any
+    /// * `data` - Revert data returned by the transaction
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn execution_reverted(id: serde_json::Value, message: String, data: &Bytes) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: 3,
+                message,
+                data: Some(serde_json::Value::String(data.to_string())),
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC timeout error (code -32050)
+    ///
+    /// Returned when an upstream call, a simulation or the whole request took
+    /// longer than its configured timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - What timed out
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn timeout(id: serde_json::Value, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32050,
+                message,
+                data: None,
+            },
+        }
+    }
+
+    /// Create a new JSON-RPC upstream unavailable error (code -32051)
+    ///
+    /// Returned when the upstream node is unreachable and the degradation
+    /// ladder couldn't answer either; `error.data.retryAfter` holds the seconds
+    /// the client should wait before retrying.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `message` - Why the request couldn't be served
+    /// * `retry_after` - Seconds to wait before retrying
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC error response
+    pub fn unavailable(id: serde_json::Value, message: String, retry_after: u64) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            error: JsonRpcErrorDetail {
+                code: -32051,
+                message,
+                data: None,
+            },
+        }
+        .with_data_field("retryAfter", serde_json::json!(retry_after))
+    }
+
+    /// Attach additional error data to the response
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.error.data = Some(data);
+        self
+    }
+
+    /// Add a member to the error data object, keeping the members already set
+    ///
+    /// Data that isn't an object, such as the revert data of code `3` errors, is
+    /// what clients decode the error from, so it is kept as is, without the member.
+    pub fn with_data_field(mut self, key: &str, value: serde_json::Value) -> Self {
+        match &mut self.error.data {
+            Some(serde_json::Value::Object(data)) => {
+                data.insert(key.to_string(), value);
+            }
+            Some(_) => {}
+            None => self.error.data = Some(serde_json::json!({ key: value })),
+        }
+        self
+    }
+}
+
+impl<T> JsonRpcSuccess<T> {
+    /// Create a new JSON-RPC success response
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Request identifier
+    /// * `result` - Response result
+    ///
+    /// # Returns
+    ///
+    /// * A formatted JSON-RPC success response
+    pub fn new(id: serde_json::Value, result: T) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            source: None,
+            cache: None,
+            blob_gas: None,
+        }
+    }
+
+    /// Tag the response with the source of a gas estimate
+    pub fn with_source(mut self, source: Option<EstimateSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Attach the caching metadata of a block-bound result
+    pub fn with_cache(mut self, cache: Option<CacheScope>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Attach the blob gas of a type-3 estimate
+    pub fn with_blob_gas(mut self, blob_gas: Option<BlobGasUsage>) -> Self {
+        self.blob_gas = blob_gas;
+        self
+    }
+}
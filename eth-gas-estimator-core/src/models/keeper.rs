@@ -0,0 +1,83 @@
+//! Keeper job cost projection
+
+use crate::models::jsonrpc::EthEstimateGasParams;
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+/// How often a keeper job runs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeeperSchedule {
+    /// Seconds between executions
+    #[serde(default)]
+    pub interval_seconds: Option<u64>,
+    /// Executions per day (alternative to `intervalSeconds`)
+    #[serde(default)]
+    pub executions_per_day: Option<f64>,
+}
+
+/// Request body of `POST /api/v1/keeper/projection`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeeperProjectionRequest {
+    /// The recurring transaction
+    pub transaction: EthEstimateGasParams,
+    /// How often it runs
+    pub schedule: KeeperSchedule,
+    /// Number of recent blocks of fee history to base the projection on (at most 1024)
+    #[serde(default)]
+    pub history_blocks: Option<u64>,
+}
+
+/// Low / typical / high values of a projected quantity
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostRange {
+    /// Optimistic value
+    pub low: U256,
+    /// Typical value
+    pub typical: U256,
+    /// Pessimistic value
+    pub high: U256,
+    /// The same values as decimal strings, when human-readable units are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<FormattedRange>,
+}
+
+/// Low / typical / high values as decimal strings in a display unit
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormattedRange {
+    /// "gwei" or "ETH"
+    pub unit: &'static str,
+    /// Optimistic value
+    pub low: String,
+    /// Typical value
+    pub typical: String,
+    /// Pessimistic value
+    pub high: String,
+}
+
+/// Projected cost of a recurring keeper job
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeeperProjection {
+    /// Block the job was simulated against
+    pub block_number: u64,
+    /// Gas used by one execution at the current state
+    pub gas_used: U256,
+    /// Worst-case gas of one execution (the job's gas limit, if set, otherwise its estimated limit)
+    pub worst_case_gas: U256,
+    /// Executions per day
+    pub executions_per_day: f64,
+    /// Number of blocks of fee history the fee range is based on
+    pub history_blocks: u64,
+    /// Effective fee per gas, in wei
+    pub fee_per_gas: CostRange,
+    /// Cost of one execution, in wei
+    pub per_execution: CostRange,
+    /// Cost per day, in wei
+    pub daily: CostRange,
+    /// Cost per 30-day month, in wei
+    pub monthly: CostRange,
+}
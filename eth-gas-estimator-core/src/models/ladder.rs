@@ -0,0 +1,48 @@
+//! Nonce ladder estimation
+
+use crate::models::jsonrpc::EthEstimateGasParams;
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+/// Request body of `POST /api/v1/eth/estimateGas/ladder`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceLadderRequest {
+    /// Sender of every transaction in the ladder
+    pub from: Address,
+    /// Nonce of the first transaction (default: the sender's pending nonce)
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub start_nonce: Option<u64>,
+    /// Transactions in the order they will be sent; their `from` and `nonce` are ignored
+    pub transactions: Vec<EthEstimateGasParams>,
+}
+
+/// Outcome of one transaction in the ladder
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceLadderStep {
+    /// Position in the ladder
+    pub index: usize,
+    /// Nonce assigned to the transaction
+    pub nonce: u64,
+    /// "success", "revert" or "halt"
+    pub status: &'static str,
+    /// Gas used, given the state left by the earlier transactions
+    pub gas_used: U256,
+}
+
+/// Per-transaction estimates for a chain of dependent transactions
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceLadder {
+    /// Sender of every transaction in the ladder
+    pub from: Address,
+    /// Block the ladder was simulated against
+    pub block_number: u64,
+    /// Nonce of the first transaction
+    pub start_nonce: u64,
+    /// Gas used by the whole ladder
+    pub total_gas: U256,
+    /// One entry per transaction, in order
+    pub transactions: Vec<NonceLadderStep>,
+}
@@ -0,0 +1,63 @@
+//! Data models used throughout the application
+//!
+//! This module contains all the data structures and serialization/deserialization
+//! logic for the gas estimation service.
+
+// JSON-RPC protocol data structures
+pub mod jsonrpc;
+// Verbose simulation reports
+pub mod simulation;
+
+// Extended estimation results
+pub mod estimate;
+
+// Gas quote validation
+pub mod quote;
+
+// Nonce ladder estimation
+pub mod ladder;
+
+// Bulk estimation
+pub mod bulk;
+
+// Dependent transaction sequences
+pub mod sequence;
+
+// Fork state snapshots
+pub mod snapshot;
+
+// Withdrawal batching comparison
+pub mod batching;
+
+// Keeper job cost projection
+pub mod keeper;
+
+// Most-called contract telemetry
+pub mod telemetry;
+
+// Service level objective reporting
+pub mod slo;
+
+// Scheduled synthetic probes
+pub mod probes;
+
+// Shadow comparisons and divergence alarms
+pub mod divergence;
+
+// Multi-block simulation
+pub mod simulate;
+
+// Bundle simulation
+pub mod bundle;
+
+// Call traces
+pub mod trace;
+
+// Fee suggestions
+pub mod fees;
+
+// Access list generation
+pub mod access_list;
+
+// Estimate explanations
+pub mod explain;
@@ -0,0 +1,37 @@
+//! Scheduled synthetic probes
+
+use serde::Serialize;
+
+/// Results of one probe
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeStatus {
+    /// Name of the probe
+    pub name: String,
+    /// Whether the latest run passed, absent before the first run
+    pub passing: Option<bool>,
+    /// Unix timestamp (seconds) of the latest run
+    pub last_run: Option<u64>,
+    /// Gas estimated by the latest run, if it passed
+    pub last_gas: Option<u64>,
+    /// Why the latest run failed, if it did
+    pub last_error: Option<String>,
+    /// Duration of the latest run in milliseconds
+    pub last_latency_ms: Option<u64>,
+    /// Failed runs in a row
+    pub consecutive_failures: u64,
+    /// Runs within the metrics window
+    pub runs: u64,
+    /// Failed runs within the metrics window
+    pub failures: u64,
+}
+
+/// Response of `GET /admin/probes`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeReport {
+    /// Length of the window `runs` and `failures` cover, in seconds
+    pub window_secs: u64,
+    /// Every configured probe
+    pub probes: Vec<ProbeStatus>,
+}
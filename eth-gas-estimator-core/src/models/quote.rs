@@ -0,0 +1,126 @@
+//! Gas quote validation and total-cost quotes
+
+use crate::models::jsonrpc::EthEstimateGasParams;
+use alloy::primitives::{Address, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// A previously issued quote, as sent back to `POST /api/v1/quotes/validate`
+///
+/// The field names match [`crate::models::simulation::SimulationReport`], so a
+/// report can be posted back verbatim; extra fields are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteValidationRequest {
+    /// Block the quote was computed against
+    pub block_number: u64,
+    /// Last block the quote is considered valid for
+    pub valid_until_block: u64,
+    /// Base fee at the quoted block
+    pub base_fee_per_gas: U256,
+    /// Sender of the quoted transaction
+    #[serde(default)]
+    pub from: Option<Address>,
+    /// Sender nonce at the quoted block
+    #[serde(default)]
+    pub sender_nonce: Option<u64>,
+    /// Fee cap the caller intends to submit with
+    #[serde(default)]
+    pub max_fee_per_gas: Option<U256>,
+}
+
+/// Outcome of re-validating a quote against the current head
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteValidation {
+    /// Whether the quote can still be relied on
+    pub valid: bool,
+    /// Current head block
+    pub current_block: u64,
+    /// Base fee at the current head
+    pub current_base_fee_per_gas: U256,
+    /// `currentBaseFeePerGas` in gwei, when human-readable units are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_base_fee_per_gas_gwei: Option<String>,
+    /// Base fee change since the quote, in percent (positive when fees rose)
+    pub base_fee_drift_percent: f64,
+    /// Why the quote should be refreshed (empty when valid)
+    pub reasons: Vec<String>,
+}
+
+/// Request body of `POST /api/v1/quote`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostQuoteRequest {
+    /// The transaction to quote
+    pub transaction: EthEstimateGasParams,
+    /// "slow", "standard" or "fast" (default: "standard"); needs priority fee tracking
+    #[serde(default)]
+    pub urgency: Option<String>,
+    /// Price of one native token in the caller's currency, as a decimal string (e.g. "3150.25")
+    #[serde(default)]
+    pub fiat_price: Option<String>,
+    /// Label of the caller's currency, echoed in the response (e.g. "USD")
+    #[serde(default)]
+    pub fiat_currency: Option<String>,
+}
+
+/// Total cost converted into the caller's currency
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FiatCost {
+    /// Currency label, as sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Price of one native token, as sent
+    pub price: String,
+    /// `totalFee` in the caller's currency, with two decimals
+    pub total: String,
+}
+
+/// Everything a transaction will cost if sent now
+///
+/// Posting the quote back to `POST /api/v1/quotes/validate` tells whether it still holds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostQuote {
+    /// Block the quote was computed against
+    pub block_number: u64,
+    /// Hash of the block the quote was computed against
+    pub block_hash: B256,
+    /// Last block the quote is considered valid for
+    pub valid_until_block: u64,
+    /// Sender of the quoted transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    /// Recommended gas limit
+    pub gas_limit: U256,
+    /// Base fee at the quoted block
+    pub base_fee_per_gas: U256,
+    /// Base fee expected in the next block, which the fees are computed with
+    pub next_base_fee_per_gas: U256,
+    /// Suggested tip
+    pub max_priority_fee_per_gas: U256,
+    /// Suggested fee cap
+    pub max_fee_per_gas: U256,
+    /// Base fee burned for the gas limit
+    pub execution_fee: U256,
+    /// Tip paid for the gas limit
+    pub priority_fee: U256,
+    /// Blob gas used, for type-3 transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<U64>,
+    /// Blob fee at the current blob base fee, for type-3 transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_fee: Option<U256>,
+    /// Upper bound of the L1 data fee, on OP Stack chains
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_data_fee: Option<U256>,
+    /// Sum of all fees, in wei
+    pub total_fee: U256,
+    /// `totalFee` in ETH, when human-readable units are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_fee_eth: Option<String>,
+    /// `totalFee` in the caller's currency, when a price was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatCost>,
+}
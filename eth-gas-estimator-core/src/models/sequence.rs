@@ -0,0 +1,41 @@
+//! Dependent transaction sequences
+
+use crate::models::jsonrpc::EthEstimateGasParams;
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+
+/// Request body of `POST /api/v1/eth/estimateGas/sequence`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceRequest {
+    /// Transactions in execution order; each needs a `from`
+    pub transactions: Vec<EthEstimateGasParams>,
+}
+
+/// Outcome of one transaction in the sequence
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceStep {
+    /// Position in the sequence
+    pub index: usize,
+    /// "success", "revert" or "halt"
+    pub status: &'static str,
+    /// Gas used, given the state left by the earlier transactions
+    pub gas_used: U256,
+    /// Smallest gas limit under which the transaction succeeds, with the calldata floor
+    /// and headroom applied (absent when it fails)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<U256>,
+}
+
+/// Per-transaction estimates for transactions that depend on each other's state changes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceEstimate {
+    /// Block the sequence was simulated against
+    pub block_number: u64,
+    /// Gas used by the whole sequence
+    pub total_gas_used: U256,
+    /// One entry per transaction, in order
+    pub transactions: Vec<SequenceStep>,
+}
@@ -0,0 +1,145 @@
+//! Multi-block simulation (`eth_simulateV1`)
+
+use crate::{foundry::BlockOverrides, models::jsonrpc::EthEstimateGasParams};
+use alloy::primitives::{Address, Bytes, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+
+/// First param of `eth_simulateV1`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatePayload {
+    /// The blocks to simulate, in order
+    pub block_state_calls: Vec<BlockStateCalls>,
+    /// Validate calls as transactions (nonce, balance, base fee)
+    #[serde(default)]
+    pub validation: bool,
+    /// Report ETH transfers as logs (not supported)
+    #[serde(default)]
+    pub trace_transfers: bool,
+}
+
+/// One block of calls
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStateCalls {
+    /// Header fields overridden in this block
+    #[serde(default)]
+    pub block_overrides: Option<BlockOverridesParams>,
+    /// Account overrides applied before this block (not supported)
+    #[serde(default)]
+    pub state_overrides: Option<serde_json::Value>,
+    /// The calls, in execution order
+    #[serde(default)]
+    pub calls: Vec<EthEstimateGasParams>,
+}
+
+/// Block header overrides
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockOverridesParams {
+    /// Block number
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub number: Option<u64>,
+    /// Block timestamp
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub time: Option<u64>,
+    /// Block gas limit
+    #[serde(default, with = "alloy::serde::quantity::opt")]
+    pub gas_limit: Option<u64>,
+    /// Block beneficiary (`COINBASE`), also accepted as `coinbase`
+    #[serde(default, alias = "coinbase")]
+    pub fee_recipient: Option<Address>,
+    /// Base fee per gas
+    #[serde(default)]
+    pub base_fee_per_gas: Option<U256>,
+    /// `PREVRANDAO` value
+    #[serde(default)]
+    pub prev_randao: Option<B256>,
+}
+
+impl From<BlockOverridesParams> for BlockOverrides {
+    fn from(params: BlockOverridesParams) -> Self {
+        Self {
+            number: params.number,
+            time: params.time,
+            gas_limit: params.gas_limit,
+            fee_recipient: params.fee_recipient,
+            base_fee_per_gas: params.base_fee_per_gas,
+            prev_randao: params.prev_randao,
+        }
+    }
+}
+
+/// A simulated block
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedBlock {
+    /// Block number
+    pub number: U64,
+    /// Block hash; simulated blocks aren't sealed, so this only identifies the
+    /// block within the response
+    pub hash: B256,
+    /// Hash of the parent block
+    pub parent_hash: B256,
+    /// Block timestamp
+    pub timestamp: U64,
+    /// Block gas limit
+    pub gas_limit: U64,
+    /// Gas used by all calls in the block
+    pub gas_used: U64,
+    /// Block beneficiary
+    pub miner: Address,
+    /// Base fee per gas
+    pub base_fee_per_gas: U256,
+    /// One result per call, in order
+    pub calls: Vec<SimulatedCall>,
+}
+
+/// Result of one call in a simulated block
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCall {
+    /// `0x1` on success, `0x0` on revert or halt
+    pub status: U64,
+    /// Returned (or revert) data
+    pub return_data: Bytes,
+    /// Gas used by the call
+    pub gas_used: U64,
+    /// Logs emitted by the call (none if it failed)
+    pub logs: Vec<SimulatedLog>,
+    /// Why the call failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CallError>,
+}
+
+/// A log emitted by a simulated call
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedLog {
+    /// Emitting contract
+    pub address: Address,
+    /// Log topics
+    pub topics: Vec<B256>,
+    /// Log data
+    pub data: Bytes,
+    /// Number of the simulated block
+    pub block_number: U64,
+    /// Hash of the simulated block
+    pub block_hash: B256,
+    /// Index of the call in its block
+    pub transaction_index: U64,
+    /// Index of the log in its block
+    pub log_index: U64,
+}
+
+/// Failure of a simulated call
+#[derive(Debug, Serialize)]
+pub struct CallError {
+    /// `3` for reverts, `-32015` for other execution failures
+    pub code: i64,
+    /// Error message
+    pub message: String,
+    /// Revert data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Bytes>,
+}
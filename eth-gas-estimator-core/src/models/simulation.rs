@@ -0,0 +1,143 @@
+//! Verbose simulation reports
+
+use crate::{
+    gas_dependence::{Confidence, FailedFrame},
+    provenance::Provenance,
+};
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Options of `estimator_simulate` (second param)
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulateOptions {
+    /// Include the state changes of the transaction
+    #[serde(default)]
+    pub state_diff: bool,
+}
+
+/// Full outcome of simulating a transaction, with the block it ran against
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationReport {
+    /// Chain id of the forked chain
+    pub chain_id: u64,
+    /// Block the simulation ran against
+    pub block_number: u64,
+    /// Hash of that block
+    pub block_hash: B256,
+    /// Hash of the canonical JSON encoding of the simulated request
+    pub request_hash: B256,
+    /// "success", "revert" or "halt"
+    pub status: &'static str,
+    /// Gas used by the simulation
+    pub gas_used: u64,
+    /// Gas limit that preserves the simulated behavior
+    pub recommended_gas_limit: u64,
+    /// Whether execution was observed to depend on the gas it receives
+    pub gas_dependent: bool,
+    /// How much `recommendedGasLimit` can be trusted: "high", "medium" or "low"
+    pub confidence: Confidence,
+    /// Call frame that fails when the gas limit is lowered below `recommendedGasLimit`,
+    /// or that already ran out of gas under the requested limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limiting_frame: Option<FailedFrame>,
+    /// Returned (or revert) data
+    pub output: Bytes,
+    /// Hash of `output`
+    pub result_hash: B256,
+    /// Size of the returned data and memory reached, with their gas contributions
+    pub memory_usage: MemoryUsage,
+    /// Addresses whose code is destroyed or changed by the transaction
+    pub warnings: Vec<SimulationWarning>,
+    /// State changes of each changed account, with the `stateDiff` option
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<Address, AccountDiff>>,
+    /// Base fee at the simulated block
+    pub base_fee_per_gas: U256,
+    /// `baseFeePerGas` in gwei, when human-readable units are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas_gwei: Option<String>,
+    /// Last block this result should be relied on as a quote
+    pub valid_until_block: u64,
+    /// Sender of the simulated transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    /// Sender nonce at the simulated block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender_nonce: Option<u64>,
+    /// Service signature over the report, when a signing key is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Return data and memory usage of a simulation, with their gas contributions
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Size of the returned (or revert) data, in bytes
+    pub return_data_size: u64,
+    /// Expansion gas of the top-level memory holding the returned data
+    pub return_data_gas: u64,
+    /// Largest memory any call frame reached, in bytes
+    pub peak_memory_bytes: u64,
+    /// Memory expansion gas paid across all call frames
+    pub memory_expansion_gas: u64,
+}
+
+/// Kind of a simulation warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WarningKind {
+    /// The contract executed `SELFDESTRUCT`
+    SelfDestruct,
+    /// The code at the address changed (redeploy or EIP-7702 delegation)
+    CodeChanged,
+}
+
+/// Address whose estimates may not hold after the simulated transaction
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationWarning {
+    /// What happened at the address
+    pub kind: WarningKind,
+    /// The affected address
+    pub address: Address,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+/// A value before and after the simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValueChange<T> {
+    /// Value before the transaction
+    pub from: T,
+    /// Value after the transaction
+    pub to: T,
+}
+
+/// State changes of an account; unchanged fields are left out
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    /// Balance change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<ValueChange<U256>>,
+    /// Nonce change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<ValueChange<u64>>,
+    /// Code change (deployment, self-destruct or EIP-7702 delegation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ValueChange<Bytes>>,
+    /// Changed storage slots
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, ValueChange<B256>>,
+}
+
+impl AccountDiff {
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_none() && self.nonce.is_none() && self.code.is_none() && self.storage.is_empty()
+    }
+}
@@ -0,0 +1,35 @@
+//! Service level objective reporting
+
+use serde::Serialize;
+
+/// Attainment of one objective over the window
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectiveReport {
+    /// Required share of good requests (e.g. 0.999)
+    pub objective: f64,
+    /// Observed share of good requests, absent when no requests were served
+    pub attained: Option<f64>,
+    /// Share of the error budget left: 1 when untouched, 0 when spent, negative when overspent
+    pub error_budget_remaining: f64,
+    /// Whether the bad requests stay within the error budget
+    pub within_budget: bool,
+}
+
+/// Response of `GET /admin/slo`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloReport {
+    /// Length of the rolling window in seconds
+    pub window_secs: u64,
+    /// Requests served in the window
+    pub requests: u64,
+    /// Whether every objective is within its error budget
+    pub within_budget: bool,
+    /// Requests answered without a server error
+    pub success_rate: ObjectiveReport,
+    /// Latency threshold of the latency objective in milliseconds
+    pub latency_threshold_ms: u64,
+    /// Requests completed within the latency threshold
+    pub latency: ObjectiveReport,
+}
@@ -0,0 +1,37 @@
+//! Fork state snapshots exchanged between replicas
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Cached state of an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSnapshot {
+    /// Address of the account
+    pub address: Address,
+    /// Balance at the snapshot block
+    pub balance: U256,
+    /// Nonce at the snapshot block
+    pub nonce: u64,
+    /// Deployed code, empty for externally owned accounts
+    #[serde(default)]
+    pub code: Bytes,
+    /// Storage slots loaded so far
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// State a fork has loaded from the upstream node, as served by `GET /admin/snapshot`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+    /// Chain id of the forked chain
+    pub chain_id: u64,
+    /// Block the state was read at
+    pub block_number: u64,
+    /// Hash of that block
+    pub block_hash: B256,
+    /// Every account loaded, with its storage
+    pub accounts: Vec<AccountSnapshot>,
+}
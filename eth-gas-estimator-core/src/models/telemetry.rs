@@ -0,0 +1,32 @@
+//! Most-called contract telemetry
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// Calls to one contract within the telemetry window
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractCount {
+    /// Target contract
+    pub address: Address,
+    /// Number of requests targeting it
+    pub count: u64,
+}
+
+/// Query of `GET /admin/contracts/top`
+#[derive(Debug, Deserialize)]
+pub struct TopContractsQuery {
+    /// Number of contracts to return (default: 20)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Response of `GET /admin/contracts/top`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopContracts {
+    /// Length of the rolling window in seconds
+    pub window_secs: u64,
+    /// Most called contracts, most called first
+    pub contracts: Vec<ContractCount>,
+}
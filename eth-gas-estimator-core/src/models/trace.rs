@@ -0,0 +1,129 @@
+//! Call traces (`debug_traceCall`, `x_estimateGasDetailed`)
+
+use crate::{estimator::EstimateSource, models::jsonrpc::BlobGasUsage};
+use alloy::primitives::{Address, Bytes, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Tracer options of `debug_traceCall` (third param)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    /// `callTracer` or `prestateTracer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracer: Option<String>,
+    /// Options of the selected tracer
+    #[serde(default)]
+    pub tracer_config: TracerConfig,
+}
+
+/// Options of the built-in tracers
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TracerConfig {
+    /// `callTracer`: only trace the top-level call
+    #[serde(default)]
+    pub only_top_call: bool,
+    /// `callTracer`: include the logs emitted by each call
+    #[serde(default)]
+    pub with_log: bool,
+    /// `prestateTracer`: report the state before and after (not supported)
+    #[serde(default)]
+    pub diff_mode: bool,
+}
+
+/// A call frame in geth `callTracer` format
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    /// `CALL`, `STATICCALL`, `DELEGATECALL`, `CALLCODE`, `CREATE` or `CREATE2`
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Caller
+    pub from: Address,
+    /// Callee, or created contract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    /// Value transferred (absent for static and delegate calls)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    /// Gas given to the call
+    pub gas: U256,
+    /// Gas used by the call
+    pub gas_used: U256,
+    /// Call data, or init code
+    pub input: Bytes,
+    /// Returned (or revert) data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    /// Why the call failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Decoded `Error(string)` revert reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// Logs emitted by the call itself (with `withLog`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<CallLog>,
+    /// Calls made by this call
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+}
+
+/// A log in a `callTracer` frame
+#[derive(Debug, Clone, Serialize)]
+pub struct CallLog {
+    /// Emitting contract
+    pub address: Address,
+    /// Log topics
+    pub topics: Vec<B256>,
+    /// Log data
+    pub data: Bytes,
+}
+
+/// An account's state before the traced call, in geth `prestateTracer` format
+#[derive(Debug, Clone, Serialize)]
+pub struct PrestateAccount {
+    /// Balance
+    pub balance: U256,
+    /// Nonce
+    pub nonce: u64,
+    /// Deployed code, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Storage slots read or written by the call, with their prior values
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+/// Output of a tracer
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Trace {
+    /// `callTracer` output
+    Call(CallFrame),
+    /// `prestateTracer` output, keyed by account
+    Prestate(BTreeMap<Address, PrestateAccount>),
+    /// Output of the upstream node's own `debug_traceCall`, passed through as is
+    Upstream(serde_json::Value),
+}
+
+/// Result of `x_estimateGasDetailed`: a gas estimate with its per-frame breakdown
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailedEstimate {
+    /// Estimated gas limit
+    pub gas: U256,
+    /// Where the estimate came from
+    pub source: EstimateSource,
+    /// Head block the estimate holds for
+    pub block_number: U64,
+    /// Hash of the head block the estimate holds for
+    pub block_hash: B256,
+    /// Blob gas and blob base fee, for type-3 transactions
+    #[serde(flatten)]
+    pub blob_gas: Option<BlobGasUsage>,
+    /// Call tree under the estimated limit, with the gas used by each frame
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_frames: Option<CallFrame>,
+}
@@ -0,0 +1,179 @@
+//! Opcode-level gas profiles
+//!
+//! Gas-golfing developers want to know which operations and which contracts a
+//! transaction spends its gas on. When an explanation asks for a profile,
+//! [`GasProfiler`] runs alongside its category tracer (see [`crate::explain`])
+//! and aggregates the execution gas per opcode and per contract:
+//!
+//! * the gas forwarded to a call or creation is attributed to the callee's own
+//!   operations, so `CALL` and friends only hold the cost of calling
+//! * gas a frame spends outside its operations (precompile execution, code
+//!   deposit, the gas left in a failed frame) is attributed to the frame's
+//!   contract, and to the opcode that opened the frame
+//! * contracts are the code that runs: a library reached with `DELEGATECALL` is
+//!   profiled as itself, not as its caller
+//!
+//! The per-contract gas adds up to the execution gas. The per-opcode gas does
+//! too, except for what the top-level frame spends outside its operations,
+//! which no opcode of the transaction caused.
+
+use crate::models::explain::{ContractGas, GasProfile, OpcodeGas};
+use alloy::primitives::Address;
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter, InterpreterResult, OpCode},
+    Database, EvmContext, Inspector,
+};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// A call frame being profiled
+#[derive(Debug, Default)]
+struct ProfiledFrame {
+    /// Contract whose code runs, once known
+    address: Option<Address>,
+    /// Gas spent by the frame's own operations so far
+    spent: u64,
+    /// Opcode of the frame's last operation, which opened the child frame if any
+    last_op: Option<u8>,
+}
+
+/// Gas and operation count of an opcode or contract
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    gas: u64,
+    count: u64,
+}
+
+/// Inspector aggregating the execution gas per opcode and per contract
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+    /// Opcode of the operation being executed, and the gas left before it
+    pending: Option<(u8, u64)>,
+    /// Open frames, outermost first
+    frames: Vec<ProfiledFrame>,
+    /// Gas by opcode
+    opcodes: BTreeMap<u8, Tally>,
+    /// Gas by contract
+    contracts: BTreeMap<Address, Tally>,
+}
+
+impl GasProfiler {
+    /// Open a frame given `gas_limit`, running the code of `address` if already known
+    fn enter(&mut self, gas_limit: u64, address: Option<Address>) {
+        // The opening opcode was charged the forwarded gas, which the callee's operations spend
+        if let Some(parent) = self.frames.last_mut() {
+            parent.spent = parent.spent.saturating_sub(gas_limit);
+            if let Some(op) = parent.last_op {
+                let opcode = self.opcodes.entry(op).or_default();
+                opcode.gas = opcode.gas.saturating_sub(gas_limit);
+            }
+            if let Some(address) = parent.address {
+                let contract = self.contracts.entry(address).or_default();
+                contract.gas = contract.gas.saturating_sub(gas_limit);
+            }
+        }
+        self.frames.push(ProfiledFrame {
+            address,
+            ..ProfiledFrame::default()
+        });
+    }
+
+    /// Close the innermost frame, created at `created` for creations
+    fn exit(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        let spent = result.gas.spent();
+        let unaccounted = spent.saturating_sub(frame.spent);
+        if let Some(parent) = self.frames.last_mut() {
+            parent.spent += spent;
+            if let Some(op) = parent.last_op {
+                self.opcodes.entry(op).or_default().gas += unaccounted;
+            }
+        }
+        if unaccounted > 0 {
+            if let Some(address) = frame.address.or(created) {
+                self.contracts.entry(address).or_default().gas += unaccounted;
+            }
+        }
+    }
+
+    /// The profile, most expensive opcodes and contracts first
+    pub fn into_profile(self) -> GasProfile {
+        let mut opcodes: Vec<OpcodeGas> = self
+            .opcodes
+            .into_iter()
+            .map(|(op, tally)| OpcodeGas {
+                opcode: OpCode::new(op).map_or_else(|| format!("0x{:02x}", op), |opcode| opcode.as_str().to_string()),
+                gas: tally.gas,
+                count: tally.count,
+            })
+            .collect();
+        opcodes.sort_by_key(|opcode| Reverse(opcode.gas));
+        let mut contracts: Vec<ContractGas> = self
+            .contracts
+            .into_iter()
+            .map(|(address, tally)| ContractGas {
+                address,
+                gas: tally.gas,
+                operations: tally.count,
+            })
+            .collect();
+        contracts.sort_by_key(|contract| Reverse(contract.gas));
+        GasProfile { opcodes, contracts }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasProfiler {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        self.pending = Some((interp.current_opcode(), interp.gas.remaining()));
+        if let Some(frame) = self.frames.last_mut() {
+            frame.address.get_or_insert(interp.contract.target_address);
+        }
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Some((op, gas_left)) = self.pending.take() else {
+            return;
+        };
+        let cost = gas_left.saturating_sub(interp.gas.remaining());
+        let opcode = self.opcodes.entry(op).or_default();
+        opcode.gas += cost;
+        opcode.count += 1;
+        let Some(frame) = self.frames.last_mut() else {
+            return;
+        };
+        frame.spent += cost;
+        frame.last_op = Some(op);
+        if let Some(address) = frame.address {
+            let contract = self.contracts.entry(address).or_default();
+            contract.gas += cost;
+            contract.count += 1;
+        }
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.enter(inputs.gas_limit, Some(inputs.bytecode_address));
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut EvmContext<DB>, _inputs: &CallInputs, outcome: CallOutcome) -> CallOutcome {
+        self.exit(&outcome.result, None);
+        outcome
+    }
+
+    fn create(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        // The created address is known once the init code runs
+        self.enter(inputs.gas_limit, None);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.exit(&outcome.result, outcome.address);
+        outcome
+    }
+}
@@ -0,0 +1,150 @@
+//! Simulation provenance
+//!
+//! Optionally signs verbose simulation reports with a service key so
+//! downstream systems (e.g. other parties in a relayer setup) can verify that a
+//! result genuinely came from a specific estimator instance at a specific block.
+//!
+//! The signed digest is `keccak256(abi.encode(chainId, blockNumber, blockHash,
+//! requestHash, gasUsed, resultHash))`, signed as an EIP-191 personal message,
+//! so it can be checked with `ecrecover` on-chain or any standard wallet library.
+//! `requestHash` is [`request_hash`], the keccak256 of the request's canonical
+//! JSON encoding, so a verifier can recompute it from the request it sent.
+
+use crate::error::ServiceError;
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    rpc::types::{TransactionInput, TransactionRequest},
+    signers::{local::PrivateKeySigner, SignerSync},
+    sol_types::SolValue,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// The facts about a simulation that get signed
+#[derive(Debug, Clone)]
+pub struct ProvenanceClaim {
+    /// Chain id of the forked chain
+    pub chain_id: u64,
+    /// Block the simulation ran against
+    pub block_number: u64,
+    /// Hash of that block
+    pub block_hash: B256,
+    /// Hash of the canonical JSON encoding of the simulated request, see [`request_hash`]
+    pub request_hash: B256,
+    /// Gas used by the simulation
+    pub gas_used: u64,
+    /// Hash of the returned (or revert) data
+    pub result_hash: B256,
+}
+
+impl ProvenanceClaim {
+    /// Digest covered by the signature
+    pub fn digest(&self) -> B256 {
+        keccak256(
+            (
+                self.chain_id,
+                self.block_number,
+                self.block_hash,
+                self.request_hash,
+                self.gas_used,
+                self.result_hash,
+            )
+                .abi_encode(),
+        )
+    }
+}
+
+/// Hash of a request's canonical JSON encoding
+///
+/// The canonical encoding is the request's JSON without whitespace, with object
+/// keys sorted, absent fields omitted and the calldata under `input` only (never
+/// `data`), so requests that only differ in how they were written hash the same.
+///
+/// # Arguments
+///
+/// * `tx_request` - The request as simulated
+///
+/// # Returns
+///
+/// * `Result<B256, ServiceError>` - The keccak256 of the encoding, or an error if the
+///   request can't be encoded
+pub fn request_hash(tx_request: &TransactionRequest) -> Result<B256, ServiceError> {
+    let mut tx_request = tx_request.clone();
+    tx_request.input = TransactionInput {
+        input: tx_request.input.input().cloned(),
+        data: None,
+    };
+    let json = serde_json::to_value(&tx_request)
+        .map_err(|e| ServiceError::InvalidParams(format!("Request can't be encoded: {}", e)))?;
+    let encoding = serde_json::to_vec(&sort_keys(json))
+        .map_err(|e| ServiceError::InvalidParams(format!("Request can't be encoded: {}", e)))?;
+    Ok(keccak256(encoding))
+}
+
+/// Rebuild every object of a JSON value with its keys in sorted order
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(key, value)| (key, sort_keys(value))).collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+/// Signature attached to a simulation report
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Address of the service key
+    pub signer: Address,
+    /// Digest that was signed (see the module docs)
+    pub digest: B256,
+    /// 65-byte EIP-191 signature over the digest, hex encoded
+    pub signature: String,
+}
+
+/// Service key used to sign simulation reports
+pub struct ProvenanceSigner {
+    signer: PrivateKeySigner,
+}
+
+impl ProvenanceSigner {
+    /// Load the service key from a hex-encoded private key
+    pub fn from_private_key(private_key: &str) -> eyre::Result<Self> {
+        let signer = private_key
+            .parse::<PrivateKeySigner>()
+            .map_err(|e| eyre::eyre!("Invalid provenance signing key: {}", e))?;
+        Ok(Self { signer })
+    }
+
+    /// Address of the service key
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Sign a claim
+    ///
+    /// # Arguments
+    ///
+    /// * `claim` - The simulation facts to sign
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Provenance, ServiceError>` - The signature and what it covers, or an error
+    pub fn sign(&self, claim: &ProvenanceClaim) -> Result<Provenance, ServiceError> {
+        let digest = claim.digest();
+        let signature = self
+            .signer
+            .sign_message_sync(digest.as_slice())
+            // A key that parsed can't fail to sign, so a failure is the key's fault, not the request's
+            .map_err(|e| ServiceError::Configuration(format!("Failed to sign simulation report: {}", e)))?;
+        Ok(Provenance {
+            signer: self.address(),
+            digest,
+            signature: format!("0x{}", alloy::hex::encode(signature.as_bytes())),
+        })
+    }
+}
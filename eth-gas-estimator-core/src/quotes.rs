@@ -0,0 +1,116 @@
+//! Gas quote expiry and re-validation
+//!
+//! Simulation reports carry a `validUntilBlock`, after which the quote should
+//! be estimated again. The [`QuotePolicy`] decides how many blocks a quote
+//! lives and how much base fee drift it tolerates when it is re-validated.
+//! [`validate_quote`] checks a quote against the current head (expiry, base
+//! fee drift, sender nonce drift) without re-simulating.
+
+use crate::{
+    error::ServiceError,
+    estimator::GasEstimator,
+    models::quote::{QuoteValidation, QuoteValidationRequest},
+    rpc::upstream_failure,
+};
+use alloy::{eips::BlockId, primitives::U256, providers::Provider};
+use tracing::debug;
+
+/// Default number of blocks a quote stays valid for
+pub const DEFAULT_QUOTE_TTL_BLOCKS: u64 = 3;
+
+/// Default base fee drift (in percent) after which a quote is considered stale
+pub const DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT: f64 = 25.0;
+
+/// How long quotes live and how much drift they tolerate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuotePolicy {
+    /// Blocks after the quoted block for which the quote stays valid
+    pub ttl_blocks: u64,
+    /// Base fee change (in percent, either direction) after which a quote is stale
+    pub max_base_fee_drift_percent: f64,
+}
+
+impl Default for QuotePolicy {
+    fn default() -> Self {
+        Self {
+            ttl_blocks: DEFAULT_QUOTE_TTL_BLOCKS,
+            max_base_fee_drift_percent: DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT,
+        }
+    }
+}
+
+/// Check whether a previously issued quote still holds at the current head
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator that issued the quote
+/// * `quote` - The quote to check
+///
+/// # Returns
+///
+/// * `Result<QuoteValidation, ServiceError>` - The verdict, or an error if the node is unreachable
+pub async fn validate_quote(
+    estimator: &GasEstimator,
+    quote: &QuoteValidationRequest,
+) -> Result<QuoteValidation, ServiceError> {
+    let policy = estimator.quote_policy();
+    let head = estimator.forks.latest().await?;
+    let mut reasons = Vec::new();
+
+    if head.block_number > quote.valid_until_block {
+        reasons.push(format!(
+            "quote expired at block {} (head is {})",
+            quote.valid_until_block, head.block_number
+        ));
+    }
+
+    let drift = base_fee_drift_percent(quote.base_fee_per_gas, head.base_fee_per_gas);
+    if drift.abs() > policy.max_base_fee_drift_percent {
+        reasons.push(format!(
+            "base fee drifted {:.1}% since block {} (limit {:.1}%)",
+            drift, quote.block_number, policy.max_base_fee_drift_percent
+        ));
+    }
+
+    if let Some(max_fee) = quote.max_fee_per_gas {
+        if max_fee < head.base_fee_per_gas {
+            reasons.push(format!(
+                "maxFeePerGas {} is below the current base fee {}",
+                max_fee, head.base_fee_per_gas
+            ));
+        }
+    }
+
+    // State drift heuristic: a sender nonce change means the sender's state moved on
+    if let (Some(from), Some(quoted_nonce)) = (quote.from, quote.sender_nonce) {
+        let nonce = estimator
+            .eth_client
+            .provider
+            .get_transaction_count(from)
+            .block_id(BlockId::number(head.block_number))
+            .await
+            .map_err(|e| upstream_failure("Failed to get nonce", e))?;
+        if nonce != quoted_nonce {
+            reasons.push(format!("sender nonce changed from {} to {}", quoted_nonce, nonce));
+        }
+    }
+
+    debug!("Quote from block {} checked at head {}: {:?}", quote.block_number, head.block_number, reasons);
+    Ok(QuoteValidation {
+        valid: reasons.is_empty(),
+        current_block: head.block_number,
+        current_base_fee_per_gas: head.base_fee_per_gas,
+        current_base_fee_per_gas_gwei: estimator.display_units().map(|units| units.gwei(head.base_fee_per_gas)),
+        base_fee_drift_percent: drift,
+        reasons,
+    })
+}
+
+/// Relative base fee change from `quoted` to `current`, in percent
+fn base_fee_drift_percent(quoted: U256, current: U256) -> f64 {
+    let quoted = f64::from(quoted);
+    if quoted == 0.0 {
+        return 0.0;
+    }
+    (f64::from(current) - quoted) / quoted * 100.0
+}
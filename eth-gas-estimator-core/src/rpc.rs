@@ -0,0 +1,342 @@
+use std::{
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::{
+    correlation::{correlation_id, CORRELATION_HEADER},
+    error::ServiceError,
+    timeouts::{remaining_budget, Timeouts},
+};
+use alloy::{
+    // Import the pre-defined typed Ethereum network
+    network::Ethereum,
+    providers::{Provider, ProviderBuilder, WsConnect},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, ResponsePacket},
+    },
+    transports::{
+        http::{reqwest, Http},
+        RpcError, TransportError, TransportErrorKind, TransportFut,
+    },
+    // The typed RPC request / block / transaction types
+    rpc::types::{BlockId, BlockNumberOrTag, Block, Header, TransactionRequest},
+};
+use eyre::Result;
+use futures::StreamExt;
+use tokio::sync::broadcast;
+use tower::Service;
+use tracing::{debug, info, trace, warn};
+
+/// Heads buffered for each consumer of new heads; slower consumers skip the oldest
+const NEW_HEADS_CAPACITY: usize = 16;
+
+/// Delay before subscribing again after the upstream subscription dropped
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Ethereum RPC client for blockchain interactions
+///
+/// This client provides a typed interface for communicating with Ethereum nodes.
+/// It uses the Alloy typed providers to ensure type safety in RPC interactions.
+#[derive(Clone)]
+pub struct EthereumClient {
+    /// Typed provider for Ethereum network
+    pub provider: Arc<dyn Provider<Ethereum>>,
+    /// Upstream URL the provider sends requests to, replaceable at runtime
+    pub upstream: UpstreamUrl,
+}
+
+impl EthereumClient {
+    /// Create a new Ethereum client with an HTTP provider
+    ///
+    /// This constructor establishes a connection to an Ethereum node and
+    /// verifies the connection is working by fetching the latest block number.
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc_url` - URL of the Ethereum RPC endpoint
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - New client instance or an error
+    pub async fn new(rpc_url: &str) -> Result<Self> {
+        Self::with_timeout(rpc_url, Timeouts::default().rpc).await
+    }
+
+    /// Create a new Ethereum client whose RPC calls fail after `timeout`
+    ///
+    /// # Arguments
+    ///
+    /// * `rpc_url` - URL of the Ethereum RPC endpoint
+    /// * `timeout` - Upper bound on each RPC call
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - New client instance or an error
+    pub async fn with_timeout(rpc_url: &str, timeout: Duration) -> Result<Self> {
+        // Create a provider for the Ethereum network at the specified URL
+        let upstream = UpstreamUrl::new(rpc_url);
+        let provider = ProviderBuilder::new()
+            .network::<Ethereum>()
+            .on_client(timeout_rpc_client(&upstream, timeout)?);
+
+        // Test the connection by fetching the latest block number
+        let block_number = provider.get_block_number().await?;
+        println!("Connected! Latest block number: {block_number}");
+
+        Ok(Self {
+            provider: Arc::new(provider),
+            upstream,
+        })
+    }
+
+    /// Fetch the latest block from the Ethereum network
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Block>` - The latest block or an error
+    pub async fn get_latest_block(&self) -> Result<Block> {
+        // Request the latest block from the provider
+        let maybe_block = self
+            .provider
+            .get_block(BlockId::Number(BlockNumberOrTag::Latest))
+            .await?;
+
+        // Ensure a block was returned
+        let block = maybe_block.ok_or_else(|| eyre::eyre!("No latest block returned"))?;
+        Ok(block)
+    }
+
+    /// Ask the upstream node for its own gas estimate (`eth_estimateGas`)
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_request` - The transaction request to estimate
+    /// * `block` - The block the node estimates against
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64>` - The node's gas estimate or an error
+    pub async fn estimate_gas(&self, tx_request: &TransactionRequest, block: BlockId) -> Result<u64> {
+        let gas = self.provider.estimate_gas(tx_request.clone()).block(block).await?;
+        Ok(gas)
+    }
+}
+
+/// Upstream `newHeads` subscription, shared by every consumer of new heads
+///
+/// Holds a WebSocket connection to the upstream node and subscribes again
+/// whenever the connection or the subscription drops.
+pub struct NewHeads {
+    sender: broadcast::Sender<Header>,
+}
+
+impl NewHeads {
+    /// Subscribe to the upstream node's new heads in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `ws_url` - WebSocket URL of the upstream node
+    pub fn spawn(ws_url: &str) -> Arc<Self> {
+        let (sender, _) = broadcast::channel(NEW_HEADS_CAPACITY);
+        let heads = Arc::new(Self { sender });
+        let subscription = heads.clone();
+        let ws_url = ws_url.to_string();
+        tokio::spawn(async move {
+            loop {
+                match subscription.forward(&ws_url).await {
+                    Ok(()) => warn!("Upstream newHeads subscription closed, subscribing again"),
+                    Err(e) => warn!("Upstream newHeads subscription failed: {}", e),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+        heads
+    }
+
+    /// Receive the heads arriving from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Header> {
+        self.sender.subscribe()
+    }
+
+    /// Forward new heads to the consumers until the subscription ends
+    async fn forward(&self, ws_url: &str) -> Result<()> {
+        let provider = ProviderBuilder::new()
+            .network::<Ethereum>()
+            .on_ws(WsConnect::new(ws_url))
+            .await?;
+        let mut heads = provider.subscribe_blocks().await?.into_stream();
+        info!("Subscribed to upstream new heads at {}", ws_url);
+        while let Some(head) = heads.next().await {
+            // Fails only while nobody is listening, which is fine
+            let _ = self.sender.send(head);
+        }
+        Ok(())
+    }
+}
+
+/// Upstream RPC URL shared by the clients built from it, replaceable at runtime
+///
+/// Clients read the URL on every request, so replacing it redirects them
+/// without rebuilding providers or forks (and without losing fork caches).
+#[derive(Debug, Clone)]
+pub struct UpstreamUrl(Arc<RwLock<String>>);
+
+impl UpstreamUrl {
+    /// Share `url` as the upstream URL
+    pub fn new(url: &str) -> Self {
+        Self(Arc::new(RwLock::new(url.to_string())))
+    }
+
+    /// The current upstream URL
+    pub fn get(&self) -> String {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Point every client sharing this URL at `url`
+    pub fn set(&self, url: &str) -> Result<(), ServiceError> {
+        url.parse::<reqwest::Url>()
+            .map_err(|e| ServiceError::RPCConnection(format!("Bad URL: {e}")))?;
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = url.to_string();
+        Ok(())
+    }
+}
+
+/// HTTP transport sending each request to the current upstream URL, within the request deadline
+/// and tagged with the request's correlation id
+#[derive(Clone)]
+struct UpstreamTransport {
+    client: reqwest::Client,
+    url: UpstreamUrl,
+}
+
+impl Service<RequestPacket> for UpstreamTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let url: reqwest::Url = match self.url.get().parse() {
+            Ok(url) => url,
+            Err(e) => return Box::pin(async move { Err(TransportErrorKind::custom_str(&format!("Bad URL: {e}"))) }),
+        };
+        let call: TransportFut<'static> = match correlation_id() {
+            Some(correlation_id) => Box::pin(post_request(self.client.clone(), url, request, correlation_id)),
+            None => Http::with_client(self.client.clone(), url).call(request),
+        };
+        // Calls made while serving a request get no more than its remaining budget
+        match remaining_budget() {
+            Some(budget) => Box::pin(async move {
+                tokio::time::timeout(budget, call)
+                    .await
+                    .unwrap_or_else(|_| Err(TransportErrorKind::custom(DeadlineExceeded)))
+            }),
+            None => call,
+        }
+    }
+}
+
+/// Error of an upstream call abandoned at the deadline of the request it was made for
+#[derive(Debug, thiserror::Error)]
+#[error("Request deadline exceeded")]
+struct DeadlineExceeded;
+
+/// Whether an upstream call failed by running out of time, its own timeout or the request's deadline
+pub fn is_timeout(e: &TransportError) -> bool {
+    match e {
+        RpcError::Transport(TransportErrorKind::Custom(e)) => {
+            e.is::<DeadlineExceeded>() || e.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout)
+        }
+        _ => false,
+    }
+}
+
+/// Map a failed upstream call onto a service error
+///
+/// # Arguments
+///
+/// * `context` - What the call was for, prefixed to the error message
+/// * `e` - The call's error
+///
+/// # Returns
+///
+/// * `ServiceError` - [`ServiceError::Timeout`] (JSON-RPC `-32050`) for a call that ran out
+///   of time, [`ServiceError::RPCConnection`] for any other failure
+pub fn upstream_failure(context: &str, e: TransportError) -> ServiceError {
+    if is_timeout(&e) {
+        ServiceError::Timeout(format!("{}: {}", context, e))
+    } else {
+        ServiceError::RPCConnection(format!("{}: {}", context, e))
+    }
+}
+
+/// POST a JSON-RPC request packet, tagged with the correlation id of the request it is made for
+///
+/// The `Http` transport has no way to add a header to a single request, so
+/// tagged requests are posted here, answering exactly as the transport does:
+/// an HTTP error status fails with the body, and the body is logged at the
+/// same levels. Untagged requests go through the transport itself.
+async fn post_request(
+    client: reqwest::Client,
+    url: reqwest::Url,
+    request: RequestPacket,
+    correlation_id: String,
+) -> Result<ResponsePacket, TransportError> {
+    let response = client
+        .post(url)
+        .header(CORRELATION_HEADER, correlation_id)
+        .json(&request)
+        .send()
+        .await
+        .map_err(TransportErrorKind::custom)?;
+    let status = response.status();
+    debug!(%status, "received response from server");
+    let body = response.bytes().await.map_err(TransportErrorKind::custom)?;
+    debug!(bytes = body.len(), "retrieved response body. Use `trace` for full body");
+    trace!(body = %String::from_utf8_lossy(&body), "response body");
+    if !status.is_success() {
+        return Err(TransportErrorKind::http_error(status.as_u16(), String::from_utf8_lossy(&body).into_owned()));
+    }
+    serde_json::from_slice(&body).map_err(|e| TransportError::deser_err(e, String::from_utf8_lossy(&body)))
+}
+
+/// Chain id served by the node at `rpc_url`, asked without pointing any client at it
+///
+/// # Arguments
+///
+/// * `rpc_url` - URL of the node
+/// * `timeout` - Upper bound on the RPC call
+///
+/// # Returns
+///
+/// * `Result<u64, ServiceError>` - The chain id, or an error if the node can't be reached
+pub async fn chain_id_at(rpc_url: &str, timeout: Duration) -> Result<u64, ServiceError> {
+    let provider = ProviderBuilder::new()
+        .network::<Ethereum>()
+        .on_client(timeout_rpc_client(&UpstreamUrl::new(rpc_url), timeout)?);
+    provider
+        .get_chain_id()
+        .await
+        .map_err(|e| upstream_failure(&format!("Failed to get chain id from {}", rpc_url), e))
+}
+
+/// Build an HTTP RPC client whose requests go to the current `upstream` URL and fail after `timeout`
+pub(crate) fn timeout_rpc_client(upstream: &UpstreamUrl, timeout: Duration) -> Result<RpcClient, ServiceError> {
+    let url: reqwest::Url = upstream
+        .get()
+        .parse()
+        .map_err(|e| ServiceError::RPCConnection(format!("Bad URL: {e}")))?;
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| ServiceError::RPCConnection(format!("Failed to build HTTP client: {}", e)))?;
+    let is_local = Http::with_client(client.clone(), url).guess_local();
+    let transport = UpstreamTransport { client, url: upstream.clone() };
+    Ok(RpcClient::new(transport, is_local))
+}
@@ -0,0 +1,130 @@
+//! Bound on concurrent EVM simulations
+//!
+//! Simulations run on tokio's blocking thread pool, and a running EVM can't be
+//! interrupted: a simulation that timed out keeps its thread until it reaches
+//! its next state read. A burst of heavy contract simulations could otherwise
+//! take every blocking thread and starve unrelated work. The limiter hands out
+//! a fixed number of permits; simulations beyond it wait in a queue, within
+//! their simulation timeout, and a permit is only returned once its EVM has
+//! actually stopped. It also counts the simulations that panicked, since a
+//! caught panic has no request left to report it to.
+
+use serde::Serialize;
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Default number of simulations running at once (half of tokio's blocking pool)
+pub const DEFAULT_MAX_CONCURRENT_SIMULATIONS: usize = 256;
+
+/// Snapshot of the limiter's occupancy
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationStats {
+    /// Simulations allowed to run at once, `None` when unbounded
+    pub max_concurrent: Option<usize>,
+    /// Simulations running on the blocking pool
+    pub running: usize,
+    /// Simulations waiting for a permit
+    pub queued: usize,
+    /// Simulations that panicked since the limiter was created
+    pub panics: u64,
+}
+
+/// Semaphore around the simulations of every fork
+#[derive(Debug)]
+pub struct SimulationLimiter {
+    /// Permits, `None` when simulations are unbounded
+    permits: Option<Arc<Semaphore>>,
+    /// Configured number of permits
+    max_concurrent: usize,
+    /// Simulations holding a permit
+    running: Arc<AtomicUsize>,
+    /// Simulations waiting for a permit
+    queued: AtomicUsize,
+    /// Simulations that panicked
+    panics: AtomicU64,
+}
+
+/// Permission to run one simulation, returned when dropped
+pub struct SimulationPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for SimulationPermit {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl SimulationLimiter {
+    /// Create a limiter letting `max_concurrent` simulations run at once (0 for unbounded)
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+            max_concurrent,
+            running: Arc::new(AtomicUsize::new(0)),
+            queued: AtomicUsize::new(0),
+            panics: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait for a permit to run a simulation
+    ///
+    /// The wait is counted in the queue depth; a caller that stops waiting leaves the queue.
+    pub async fn acquire(&self) -> SimulationPermit {
+        let permit = match &self.permits {
+            Some(permits) => {
+                let _queued = QueuedGuard::enter(&self.queued);
+                // The semaphore is never closed
+                permits.clone().acquire_owned().await.ok()
+            }
+            None => None,
+        };
+        self.running.fetch_add(1, Ordering::Relaxed);
+        SimulationPermit {
+            _permit: permit,
+            running: self.running.clone(),
+        }
+    }
+
+    /// Count a simulation that panicked
+    pub fn record_panic(&self) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Current number of running and queued simulations, and of caught panics
+    pub fn stats(&self) -> SimulationStats {
+        SimulationStats {
+            max_concurrent: self.permits.is_some().then_some(self.max_concurrent),
+            running: self.running.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for SimulationLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_SIMULATIONS)
+    }
+}
+
+/// Counts a waiter in the queue until it gets a permit or gives up
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl<'a> QueuedGuard<'a> {
+    fn enter(queued: &'a AtomicUsize) -> Self {
+        queued.fetch_add(1, Ordering::Relaxed);
+        Self(queued)
+    }
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,89 @@
+//! Post-simulation state diffs
+//!
+//! Security tooling and transaction preview UIs want to show what a
+//! transaction would change, not just what it costs. With the `stateDiff`
+//! option, the verbose simulation report lists every account the transaction
+//! changes, keyed by address: its balance, nonce and code before and after,
+//! and each storage slot whose value changed. Unchanged fields are left out.
+//!
+//! The diff is read from the EVM journal of the first, unconstrained
+//! simulation of the report, compared with the state its `CacheDB` saw before
+//! the transaction (the fork state, with the sender's nonce advanced as for the
+//! estimate), so it costs no extra execution.
+
+use crate::{
+    error::ServiceError,
+    models::simulation::{AccountDiff, ValueChange},
+};
+use alloy::primitives::{Address, Bytes, B256};
+use revm::{
+    primitives::{AccountInfo, EvmState, KECCAK_EMPTY},
+    DatabaseRef,
+};
+use std::{collections::BTreeMap, fmt::Display};
+use tracing::debug;
+
+/// Compute the state changes of an uncommitted execution
+///
+/// # Arguments
+///
+/// * `db` - The database the transaction ran on, still holding the state before it
+/// * `state` - The state the execution left, from its `ResultAndState`
+///
+/// # Returns
+///
+/// * `Result<BTreeMap<Address, AccountDiff>, ServiceError>` - The changes of each changed
+///   account, or an error if the state before the transaction can't be read
+pub(crate) fn account_diffs<DB>(db: &DB, state: EvmState) -> Result<BTreeMap<Address, AccountDiff>, ServiceError>
+where
+    DB: DatabaseRef,
+    DB::Error: Display,
+{
+    let mut diffs = BTreeMap::new();
+    for (address, account) in state.into_iter().filter(|(_, account)| account.is_touched()) {
+        let read_error = |e: DB::Error| ServiceError::Database(format!("account {}: {}", address, e));
+        let before = db.basic_ref(address).map_err(read_error)?.unwrap_or_default();
+        let after = if account.is_selfdestructed() { AccountInfo::default() } else { account.info };
+
+        let code = if before.code_hash != after.code_hash {
+            Some(ValueChange {
+                from: code_of(db, &before).map_err(read_error)?,
+                to: code_of(db, &after).map_err(read_error)?,
+            })
+        } else {
+            None
+        };
+        let diff = AccountDiff {
+            balance: (before.balance != after.balance)
+                .then_some(ValueChange { from: before.balance, to: after.balance }),
+            nonce: (before.nonce != after.nonce).then_some(ValueChange { from: before.nonce, to: after.nonce }),
+            code,
+            storage: account
+                .storage
+                .iter()
+                .filter(|(_, value)| value.is_changed())
+                .map(|(slot, value)| {
+                    let change = ValueChange {
+                        from: B256::from(value.original_value()),
+                        to: B256::from(value.present_value()),
+                    };
+                    (B256::from(*slot), change)
+                })
+                .collect(),
+        };
+        if !diff.is_empty() {
+            diffs.insert(address, diff);
+        }
+    }
+    debug!("Simulation changed {} accounts", diffs.len());
+    Ok(diffs)
+}
+
+/// Code of an account, loaded by hash if the account info doesn't carry it
+fn code_of<DB: DatabaseRef>(db: &DB, info: &AccountInfo) -> Result<Bytes, DB::Error> {
+    match &info.code {
+        Some(code) => Ok(code.original_bytes()),
+        None if info.code_hash != KECCAK_EMPTY => db.code_by_hash_ref(info.code_hash).map(|code| code.original_bytes()),
+        None => Ok(Bytes::new()),
+    }
+}
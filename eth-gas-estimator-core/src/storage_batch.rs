@@ -0,0 +1,225 @@
+//! Batched storage fetches for the simulation forks
+//!
+//! The fork backend fetches state one `eth_getStorageAt` call at a time, and
+//! the EVM waits on every storage miss, so a contract walking an array or
+//! reading a struct pays one upstream round trip per slot. With batching
+//! enabled (`STORAGE_BATCH_SLOTS` above 1), slots are fetched several at a time
+//! in a single JSON-RPC batch request and cached in the fork:
+//!
+//! * a miss on the slot right after one the fork already has, as when walking an
+//!   array or a struct, reads ahead: the missed slot is fetched together with the
+//!   slots following it. Mapping entries live at unrelated hashed slots, so an
+//!   isolated miss is fetched alone rather than with neighbours nobody reads.
+//! * the storage keys of the transaction's access list are fetched before it
+//!   runs, wherever they are.
+//!
+//! The extra slots cost the upstream node work, so batching backs off under
+//! load: at most `STORAGE_BATCH_CONCURRENCY` batches are in flight across all
+//! forks, and a miss arriving while they are all busy is fetched alone by the
+//! fork backend, as without batching. Batches read at the fork's block hash,
+//! so forks at the moving `pending` tag don't batch.
+
+use alloy::{
+    eips::BlockId,
+    network::AnyNetwork,
+    primitives::{Address, B256, U256},
+    providers::{DynProvider, Provider},
+    rpc::client::Waiter,
+    transports::TransportResult,
+};
+use foundry_fork_db::BlockchainDb;
+use crate::{correlation::with_correlation_id, timeouts::with_deadline};
+use std::{sync::Arc, time::Instant};
+use tokio::{
+    runtime::Handle,
+    sync::{oneshot, Semaphore},
+};
+use tracing::debug;
+
+/// Default number of slots fetched per storage miss (1 disables batching)
+pub const DEFAULT_STORAGE_BATCH_SLOTS: usize = 1;
+
+/// Default number of storage batches in flight at once
+pub const DEFAULT_STORAGE_BATCH_CONCURRENCY: usize = 4;
+
+/// How storage misses are batched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageBatching {
+    /// Slots fetched per batch: a missed slot and the ones following it, or access list keys
+    pub slots: usize,
+    /// Batches in flight at once across all forks
+    pub concurrency: usize,
+}
+
+impl Default for StorageBatching {
+    fn default() -> Self {
+        Self {
+            slots: DEFAULT_STORAGE_BATCH_SLOTS,
+            concurrency: DEFAULT_STORAGE_BATCH_CONCURRENCY,
+        }
+    }
+}
+
+impl StorageBatching {
+    /// Whether storage slots are fetched in batches
+    pub fn is_enabled(&self) -> bool {
+        self.slots > 1 && self.concurrency > 0
+    }
+}
+
+/// Fetches storage slots of a fork in batched requests
+pub struct StorageBatcher {
+    /// Provider the batches are sent with
+    provider: DynProvider<AnyNetwork>,
+    /// Hash of the block the fork reads state at
+    block: BlockId,
+    /// Cache of the fork, filled with the fetched slots
+    state: BlockchainDb,
+    /// Slots fetched per batch
+    slots: usize,
+    /// Batches allowed in flight, shared by the forks of a fork manager
+    permits: Arc<Semaphore>,
+    /// Runtime the batches run on, as simulations run on blocking threads
+    runtime: Handle,
+}
+
+impl StorageBatcher {
+    /// Create a batcher for a fork, or `None` if batching is disabled
+    ///
+    /// Must be called from within the Tokio runtime the batches should run on.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - Provider the batches are sent with
+    /// * `block_hash` - Hash of the block the fork reads state at
+    /// * `state` - Cache of the fork
+    /// * `batching` - Batch size and concurrency
+    /// * `permits` - Batches allowed in flight, shared with the other forks
+    pub fn new(
+        provider: DynProvider<AnyNetwork>,
+        block_hash: B256,
+        state: BlockchainDb,
+        batching: StorageBatching,
+        permits: Arc<Semaphore>,
+    ) -> Option<Arc<Self>> {
+        batching.is_enabled().then(|| {
+            Arc::new(Self {
+                provider,
+                block: BlockId::hash(block_hash),
+                state,
+                slots: batching.slots,
+                permits,
+                runtime: Handle::current(),
+            })
+        })
+    }
+
+    /// Read ahead of a missed slot that follows a cached one into the fork's cache
+    ///
+    /// Blocks until the batch completes, so it must be called from a blocking
+    /// thread. Best effort: if the slot is already cached, doesn't follow a cached
+    /// slot, every batch is busy or the batch fails, nothing is cached and the
+    /// fork backend fetches the slot itself. The batch is abandoned at the
+    /// `deadline` of the request the simulation runs for, if any, and is tagged
+    /// with that request's `correlation_id`.
+    pub fn prefetch(&self, address: Address, slot: U256, deadline: Option<Instant>, correlation_id: Option<&str>) {
+        let slots: Vec<(Address, U256)> = {
+            let storage = self.state.storage().read();
+            let Some(cached) = storage.get(&address) else {
+                return;
+            };
+            // Only sequential reads are read ahead
+            let follows_cached = slot.checked_sub(U256::from(1)).is_some_and(|previous| cached.contains_key(&previous));
+            if cached.contains_key(&slot) || !follows_cached {
+                return;
+            }
+            (0..self.slots as u64)
+                .map_while(|offset| slot.checked_add(U256::from(offset)))
+                .filter(|slot| !cached.contains_key(slot))
+                .map(|slot| (address, slot))
+                .collect()
+        };
+        self.fetch(slots, deadline, correlation_id);
+    }
+
+    /// Fetch the uncached slots among `slots`, such as the keys of an access list, into the fork's cache
+    ///
+    /// Blocks until the batch completes, like [`StorageBatcher::prefetch`]. Slots
+    /// beyond `STORAGE_BATCH_SLOTS` are left to the fork backend, and so is a
+    /// single slot, which gains nothing from a batch.
+    pub fn prefetch_slots(
+        &self,
+        slots: impl IntoIterator<Item = (Address, U256)>,
+        deadline: Option<Instant>,
+        correlation_id: Option<&str>,
+    ) {
+        let slots: Vec<(Address, U256)> = {
+            let storage = self.state.storage().read();
+            slots
+                .into_iter()
+                .filter(|(address, slot)| !storage.get(address).is_some_and(|cached| cached.contains_key(slot)))
+                .take(self.slots)
+                .collect()
+        };
+        if slots.len() > 1 {
+            self.fetch(slots, deadline, correlation_id);
+        }
+    }
+
+    /// Fetch slots in one batch and cache them, unless every batch is busy
+    fn fetch(&self, slots: Vec<(Address, U256)>, deadline: Option<Instant>, correlation_id: Option<&str>) {
+        // Under load, don't add speculative reads to the upstream's work
+        let Ok(permit) = self.permits.clone().try_acquire_owned() else {
+            debug!("Every storage batch is busy, fetching {} slots alone", slots.len());
+            return;
+        };
+        let (sender, receiver) = oneshot::channel();
+        let provider = self.provider.clone();
+        let block = self.block;
+        let requested = slots.clone();
+        let correlation_id = correlation_id.map(str::to_string);
+        self.runtime.spawn(async move {
+            let _permit = permit;
+            let fetch = fetch_slots(&provider, &requested, block);
+            let fetch = async move {
+                match deadline {
+                    Some(deadline) => with_deadline(deadline, fetch).await,
+                    None => fetch.await,
+                }
+            };
+            // The batch runs on a task of its own, outside the request's
+            let fetched = match correlation_id {
+                Some(correlation_id) => with_correlation_id(correlation_id, fetch).await,
+                None => fetch.await,
+            };
+            let _ = sender.send(fetched);
+        });
+
+        match receiver.blocking_recv() {
+            Ok(Ok(values)) => {
+                debug!("Fetched {} storage slots in one batch", values.len());
+                let mut storage = self.state.storage().write();
+                for ((address, slot), value) in slots.into_iter().zip(values) {
+                    storage.entry(address).or_default().insert(slot, value);
+                }
+            }
+            Ok(Err(e)) => debug!("Storage batch failed, fetching its slots alone: {}", e),
+            Err(_) => debug!("Storage batch was dropped, fetching its slots alone"),
+        }
+    }
+}
+
+/// Read storage slots in a single JSON-RPC batch request
+async fn fetch_slots(
+    provider: &DynProvider<AnyNetwork>,
+    slots: &[(Address, U256)],
+    block: BlockId,
+) -> TransportResult<Vec<U256>> {
+    let mut batch = provider.client().new_batch();
+    let values = slots
+        .iter()
+        .map(|(address, slot)| batch.add_call("eth_getStorageAt", &(*address, *slot, block)))
+        .collect::<TransportResult<Vec<Waiter<U256>>>>()?;
+    batch.send().await?;
+    futures::future::try_join_all(values).await
+}
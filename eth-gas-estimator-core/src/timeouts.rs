@@ -0,0 +1,135 @@
+//! Upstream, simulation and request timeouts, and request deadlines
+//!
+//! A slow (or stuck) archive node would otherwise hold callers indefinitely.
+//! Upstream RPC calls and EVM simulations are bounded individually.
+//!
+//! A caller can also set a deadline for everything it runs within a future
+//! (see [`with_deadline`]): the deadline is carried through the task, so
+//! upstream RPC calls and simulations only get the budget that remains and
+//! work is abandoned as soon as the caller would no longer use the result.
+//!
+//! With the `actix` feature, the `request_timeout` middleware bounds each
+//! HTTP request end to end, answering with a JSON-RPC timeout error. Callers
+//! can shorten the request's budget with the `X-Deadline-Ms` header, which
+//! becomes its deadline.
+
+#[cfg(feature = "actix")]
+use crate::models::jsonrpc::JsonRpcError;
+#[cfg(feature = "actix")]
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "actix")]
+use tracing::{debug, warn};
+
+/// Request header with the caller's time budget, in milliseconds
+pub const DEADLINE_HEADER: &str = "x-deadline-ms";
+
+tokio::task_local! {
+    /// Deadline of the request being served by the current task
+    static DEADLINE: Instant;
+}
+
+/// Run a future with a deadline for the upstream calls and simulations it makes
+///
+/// # Arguments
+///
+/// * `deadline` - Instant after which no more work is started for the future
+/// * `future` - The future to run
+pub async fn with_deadline<F: Future>(deadline: Instant, future: F) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+/// Deadline of the current request, if it has one
+///
+/// Work the request hands to other threads or tasks (simulations, fork state
+/// fetches) doesn't see the task's deadline, and is given this instead.
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Time left until the current request's deadline, if it has one
+pub fn remaining_budget() -> Option<Duration> {
+    DEADLINE
+        .try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+/// Shorten `timeout` to the time left until the current request's deadline
+pub fn within_deadline(timeout: Duration) -> Duration {
+    remaining_budget().map_or(timeout, |remaining| remaining.min(timeout))
+}
+
+/// Timeouts applied while serving a request
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    /// Per upstream RPC call
+    pub rpc: Duration,
+    /// Per EVM simulation (or simulated sequence)
+    pub simulation: Duration,
+    /// Per HTTP request, end to end
+    pub request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            rpc: Duration::from_secs(10),
+            simulation: Duration::from_secs(10),
+            request: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Middleware bounding the time spent producing a response
+///
+/// Used with `App::wrap_fn`. The request's budget is `timeout`, or the
+/// caller's `X-Deadline-Ms` when shorter, and becomes the deadline seen by
+/// [`within_deadline`] while the request is served. When it elapses the
+/// request is answered with `504 Gateway Timeout` and a JSON-RPC timeout error.
+#[cfg(feature = "actix")]
+pub fn request_timeout<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+    timeout: Duration,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let http_req = req.request().clone();
+    let requested = req
+        .headers()
+        .get(DEADLINE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_millis);
+    let timeout = requested.map_or(timeout, |requested| requested.min(timeout));
+    // Nothing is started for callers that have already given up
+    let response = (!timeout.is_zero()).then(|| srv.call(req));
+    async move {
+        let Some(response) = response else {
+            debug!("Request to {} arrived past its deadline", http_req.path());
+            let rpc_error = JsonRpcError::timeout(serde_json::Value::Null, "Request deadline exceeded".to_string());
+            return Ok(ServiceResponse::new(http_req, HttpResponse::GatewayTimeout().json(rpc_error)));
+        };
+        let deadline = Instant::now() + timeout;
+        match tokio::time::timeout(timeout, with_deadline(deadline, response)).await {
+            Ok(response) => response.map(ServiceResponse::map_into_boxed_body),
+            Err(_) => {
+                warn!("Request to {} timed out after {:?}", http_req.path(), timeout);
+                let rpc_error = JsonRpcError::timeout(
+                    serde_json::Value::Null,
+                    format!("Request timed out after {} ms", timeout.as_millis()),
+                );
+                Ok(ServiceResponse::new(http_req, HttpResponse::GatewayTimeout().json(rpc_error)))
+            }
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Execution tracers for `debug_traceCall`
+//!
+//! A call is simulated on the fork under a REVM inspector that records either:
+//!
+//! * the call tree, in geth `callTracer` format: every call and contract
+//!   creation with its gas, input, output and error, plus the decoded revert
+//!   reason, so a failing estimate can be traced down to the frame that reverted
+//! * the state the call touched, in geth `prestateTracer` format: balance,
+//!   nonce, code and the storage slots read or written, as they were before the
+//!   call
+//!
+//! Traces run with `eth_call` semantics, like the call they debug.
+
+use crate::{
+    error::ServiceError,
+    foundry::{execute_on_fork, run_simulation, tx_env_for_fork, Fork, SimulationMode},
+    models::trace::{CallFrame, CallLog, PrestateAccount, Trace, TraceOptions, TracerConfig},
+};
+use alloy::{
+    primitives::{Address, Log, B256, U256},
+    rpc::types::TransactionRequest,
+    sol_types::{Revert, SolError},
+};
+use revm::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, CreateScheme,
+        InstructionResult, Interpreter, InterpreterResult,
+    },
+    primitives::{ExecutionResult, TransactTo, TxEnv, KECCAK_EMPTY},
+    Database, DatabaseRef, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Inspector recording the call tree in `callTracer` format
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    config: TracerConfig,
+    /// Open frames, outermost first
+    stack: Vec<CallFrame>,
+    /// The top-level frame, once it returned
+    root: Option<CallFrame>,
+    /// Accounts touched, if requested
+    touched: Option<PrestateTracer>,
+}
+
+impl CallTracer {
+    /// Create a tracer with the given `callTracer` options
+    pub fn new(config: TracerConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// The recorded call tree, if the top-level frame returned
+    pub fn into_frame(self) -> Option<CallFrame> {
+        self.root
+    }
+
+    /// Close the innermost frame and attach it to its caller
+    fn exit(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = U256::from(result.gas.spent());
+        if created.is_some() {
+            frame.to = created;
+        }
+        let reverted = result.result == InstructionResult::Revert;
+        if (result.result.is_ok() || reverted) && !result.output.is_empty() {
+            frame.output = Some(result.output.clone());
+        }
+        if !result.result.is_ok() {
+            frame.error = Some(frame_error(result.result));
+            frame.logs.clear();
+            if reverted {
+                frame.revert_reason = Revert::abi_decode(&result.output, true).ok().map(|revert| revert.reason);
+            }
+        }
+
+        match self.stack.last_mut() {
+            Some(parent) if !self.config.only_top_call => parent.calls.push(frame),
+            Some(_) => {}
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if let Some(touched) = &mut self.touched {
+            touched.step(interp, context);
+        }
+    }
+
+    fn log(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>, log: &Log) {
+        if !self.config.with_log {
+            return;
+        }
+        if let Some(frame) = self.stack.last_mut() {
+            frame.logs.push(CallLog {
+                address: log.address,
+                topics: log.data.topics().to_vec(),
+                data: log.data.data.clone(),
+            });
+        }
+    }
+
+    fn call(&mut self, context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        if let Some(touched) = &mut self.touched {
+            touched.call(context, inputs);
+        }
+        let (kind, from, value) = match inputs.scheme {
+            CallScheme::StaticCall => ("STATICCALL", inputs.caller, None),
+            CallScheme::DelegateCall => ("DELEGATECALL", inputs.target_address, None),
+            CallScheme::CallCode => ("CALLCODE", inputs.caller, Some(inputs.call_value())),
+            _ => ("CALL", inputs.caller, Some(inputs.call_value())),
+        };
+        self.stack.push(CallFrame {
+            kind,
+            from,
+            to: Some(inputs.bytecode_address),
+            value,
+            gas: U256::from(inputs.gas_limit),
+            gas_used: U256::ZERO,
+            input: inputs.input.clone(),
+            output: None,
+            error: None,
+            revert_reason: None,
+            logs: Vec::new(),
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.exit(&outcome.result, None);
+        outcome
+    }
+
+    fn create(&mut self, context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        if let Some(touched) = &mut self.touched {
+            touched.create(context, inputs);
+        }
+        let kind = match inputs.scheme {
+            CreateScheme::Create2 { .. } => "CREATE2",
+            _ => "CREATE",
+        };
+        self.stack.push(CallFrame {
+            kind,
+            from: inputs.caller,
+            to: None,
+            value: Some(inputs.value),
+            gas: U256::from(inputs.gas_limit),
+            gas_used: U256::ZERO,
+            input: inputs.init_code.clone(),
+            output: None,
+            error: None,
+            revert_reason: None,
+            logs: Vec::new(),
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let outcome = match &mut self.touched {
+            Some(touched) => touched.create_end(context, inputs, outcome),
+            None => outcome,
+        };
+        self.exit(&outcome.result, outcome.address);
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        if let Some(touched) = &mut self.touched {
+            Inspector::<DB>::selfdestruct(touched, contract, target, value);
+        }
+    }
+}
+
+/// geth-style error message of a failed frame
+fn frame_error(result: InstructionResult) -> String {
+    match result {
+        InstructionResult::Revert => "execution reverted".to_string(),
+        InstructionResult::OutOfGas
+        | InstructionResult::MemoryOOG
+        | InstructionResult::MemoryLimitOOG
+        | InstructionResult::PrecompileOOG
+        | InstructionResult::InvalidOperandOOG => "out of gas".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Inspector recording the accounts and storage slots a call touches
+#[derive(Debug, Default)]
+pub struct PrestateTracer {
+    /// Touched accounts and the storage slots read or written in each
+    touched: BTreeMap<Address, BTreeSet<B256>>,
+}
+
+impl PrestateTracer {
+    /// Record an account as touched
+    fn touch(&mut self, address: Address) -> &mut BTreeSet<B256> {
+        self.touched.entry(address).or_default()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for PrestateTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let Ok(top) = interp.stack().peek(0) else {
+            return;
+        };
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE => {
+                self.touch(interp.contract.target_address).insert(B256::from(top));
+            }
+            opcode::BALANCE | opcode::EXTCODESIZE | opcode::EXTCODECOPY | opcode::EXTCODEHASH => {
+                self.touch(Address::from_word(B256::from(top)));
+            }
+            _ => {}
+        }
+    }
+
+    fn call(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.touch(inputs.caller);
+        self.touch(inputs.target_address);
+        self.touch(inputs.bytecode_address);
+        None
+    }
+
+    fn create(&mut self, _context: &mut EvmContext<DB>, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        self.touch(inputs.caller);
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let Some(address) = outcome.address {
+            self.touch(address);
+        }
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, _value: U256) {
+        self.touch(contract);
+        self.touch(target);
+    }
+}
+
+/// Trace a call on a fork with the tracer selected in `options`
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The call to trace
+/// * `options` - The tracer (`callTracer` or `prestateTracer`) and its options
+/// * `mode` - Validation rules applied to the call
+///
+/// # Returns
+///
+/// * `Result<Trace, ServiceError>` - The tracer output, or an error if the tracer or
+///   its options aren't supported or the call fails validation
+pub async fn trace_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    options: &TraceOptions,
+    mode: SimulationMode,
+) -> Result<Trace, ServiceError> {
+    match options.tracer.as_deref() {
+        Some("callTracer") => call_frames_on_fork(fork, tx_request, options.tracer_config.clone(), mode)
+            .await
+            .map(Trace::Call),
+        Some("prestateTracer") => {
+            if options.tracer_config.diff_mode {
+                return Err(ServiceError::InvalidParams("prestateTracer diffMode is not supported".to_string()));
+            }
+            let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+            let mut touched = vec![tx_env.caller, fork.block_env.coinbase];
+            if let TransactTo::Call(to) = tx_env.transact_to {
+                touched.push(to);
+            }
+            let (_, tracer) = execute_on_fork(fork, tx_env, mode, Some(PrestateTracer::default())).await?;
+            let mut tracer = tracer.unwrap_or_default();
+            for address in touched {
+                tracer.touch(address);
+            }
+            prestate(fork, tracer.touched).await.map(Trace::Prestate)
+        }
+        Some(other) => Err(ServiceError::InvalidParams(format!("Unsupported tracer: {}", other))),
+        None => Err(ServiceError::InvalidParams(
+            "A tracer is required: callTracer or prestateTracer".to_string(),
+        )),
+    }
+}
+
+/// List the accounts a transaction touches on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction
+/// * `mode` - Validation rules applied to the transaction
+///
+/// # Returns
+///
+/// * `Result<BTreeSet<Address>, ServiceError>` - The sender, the recipient and every
+///   account touched during execution (the coinbase excluded), or an error
+pub async fn touched_accounts_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    mode: SimulationMode,
+) -> Result<BTreeSet<Address>, ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+    let (_, tracer) = execute_on_fork(fork, tx_env.clone(), mode, Some(PrestateTracer::default())).await?;
+    Ok(touched_accounts(&tx_env, tracer.unwrap_or_default()))
+}
+
+/// The sender, the recipient and every account a [`PrestateTracer`] saw touched
+pub(crate) fn touched_accounts(tx_env: &TxEnv, tracer: PrestateTracer) -> BTreeSet<Address> {
+    let mut touched = BTreeSet::from([tx_env.caller]);
+    if let TransactTo::Call(to) = tx_env.transact_to {
+        touched.insert(to);
+    }
+    touched.extend(tracer.touched.into_keys());
+    touched
+}
+
+/// Record the call tree of a transaction on a fork
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction
+/// * `config` - `callTracer` options
+/// * `mode` - Validation rules applied to the transaction
+///
+/// # Returns
+///
+/// * `Result<CallFrame, ServiceError>` - The top-level frame, accounting for the whole
+///   transaction (intrinsic gas included), or an error
+pub async fn call_frames_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    config: TracerConfig,
+    mode: SimulationMode,
+) -> Result<CallFrame, ServiceError> {
+    traced_execution_on_fork(fork, tx_request, config, mode)
+        .await
+        .map(|(_, frame)| frame)
+}
+
+/// Execute a transaction on a fork and record its call tree along the way
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction
+/// * `config` - `callTracer` options
+/// * `mode` - Validation rules applied to the transaction
+///
+/// # Returns
+///
+/// * `Result<(ExecutionResult, CallFrame), ServiceError>` - The execution result and the
+///   top-level frame, accounting for the whole transaction (intrinsic gas included), or an error
+pub async fn traced_execution_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    config: TracerConfig,
+    mode: SimulationMode,
+) -> Result<(ExecutionResult, CallFrame), ServiceError> {
+    traced_on_fork(fork, tx_request, CallTracer::new(config), mode)
+        .await
+        .map(|(result, frame, _)| (result, frame))
+}
+
+/// Record the call tree of a transaction on a fork, listing the accounts it touches in the same run
+///
+/// # Arguments
+///
+/// * `fork` - The fork to simulate against
+/// * `tx_request` - The transaction
+/// * `config` - `callTracer` options
+/// * `mode` - Validation rules applied to the transaction
+/// * `touched` - Whether to also list the accounts the transaction touches
+///
+/// # Returns
+///
+/// * `Result<(CallFrame, Option<BTreeSet<Address>>), ServiceError>` - The top-level frame and,
+///   if requested, the sender, the recipient and every account touched during execution, or an error
+pub async fn call_frames_and_touched_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    config: TracerConfig,
+    mode: SimulationMode,
+    touched: bool,
+) -> Result<(CallFrame, Option<BTreeSet<Address>>), ServiceError> {
+    let tracer = CallTracer {
+        touched: touched.then(PrestateTracer::default),
+        ..CallTracer::new(config)
+    };
+    traced_on_fork(fork, tx_request, tracer, mode)
+        .await
+        .map(|(_, frame, touched)| (frame, touched))
+}
+
+/// Execute a transaction on a fork under a [`CallTracer`]
+async fn traced_on_fork(
+    fork: &Fork,
+    tx_request: &TransactionRequest,
+    tracer: CallTracer,
+    mode: SimulationMode,
+) -> Result<(ExecutionResult, CallFrame, Option<BTreeSet<Address>>), ServiceError> {
+    let tx_env = tx_env_for_fork(fork, tx_request, mode)?;
+    let gas_limit = tx_env.gas_limit;
+    let (result, tracer) = execute_on_fork(fork, tx_env.clone(), mode, Some(tracer)).await?;
+    let mut tracer = tracer.unwrap_or_default();
+    let touched = tracer.touched.take().map(|touched| touched_accounts(&tx_env, touched));
+    let mut frame = tracer
+        .into_frame()
+        .ok_or_else(|| ServiceError::Simulation("No call frame was recorded".to_string()))?;
+    frame.gas = U256::from(gas_limit);
+    frame.gas_used = U256::from(result.gas_used());
+    Ok((result, frame, touched))
+}
+
+/// Read the pre-call state of the touched accounts from the fork
+///
+/// The fork backend only ever holds the state at its block, so it still
+/// reflects the state before the traced call.
+async fn prestate(
+    fork: &Fork,
+    touched: BTreeMap<Address, BTreeSet<B256>>,
+) -> Result<BTreeMap<Address, PrestateAccount>, ServiceError> {
+    run_simulation(fork, move |backend| {
+        let read_error = |e| ServiceError::Simulation(format!("Failed to read prestate: {}", e));
+        let mut accounts = BTreeMap::new();
+        for (address, slots) in touched {
+            let info = backend.basic_ref(address).map_err(read_error)?.unwrap_or_default();
+            let code = match info.code {
+                Some(code) => Some(code.original_bytes()),
+                None if info.code_hash != KECCAK_EMPTY => {
+                    Some(backend.code_by_hash_ref(info.code_hash).map_err(read_error)?.original_bytes())
+                }
+                None => None,
+            };
+            let mut storage = BTreeMap::new();
+            for slot in slots {
+                let value = backend.storage_ref(address, U256::from_be_bytes(slot.0)).map_err(read_error)?;
+                storage.insert(slot, B256::from(value));
+            }
+            accounts.insert(
+                address,
+                PrestateAccount {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code: code.filter(|code| !code.is_empty()),
+                    storage,
+                },
+            );
+        }
+        Ok(accounts)
+    })
+    .await
+}
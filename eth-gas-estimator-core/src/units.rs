@@ -0,0 +1,153 @@
+//! Wei amounts and JSON-RPC quantities
+//!
+//! Every numeric conversion the service performs lives here and is exported
+//! from the library, so embedders use the same conversions as the service:
+//!
+//! * hex quantities as written to JSON-RPC payloads
+//! * decimal gwei and ETH strings, both ways
+//!
+//! JSON-RPC inputs (addresses, quantities, hashes, data) are parsed by
+//! deserializing into alloy's types instead.
+//!
+//! Decimal conversions are plain fixed-point on integers: no floating point,
+//! no locale-dependent separators. Parsed decimals must use `.` as the
+//! separator and must not carry more fractional digits than the unit has.
+//!
+//! When enabled, verbose and REST responses carry decimal gwei (fee per gas)
+//! and ETH (costs) strings next to wei values, rendered with [`DisplayUnits`].
+
+use alloy::primitives::U256;
+
+/// Decimals of gwei relative to wei
+pub const GWEI_DECIMALS: u32 = 9;
+
+/// Decimals of ETH relative to wei
+pub const ETHER_DECIMALS: u32 = 18;
+
+/// Default number of decimals in ETH strings
+pub const DEFAULT_ETH_DISPLAY_DECIMALS: u32 = 6;
+
+/// How human-readable amounts are rendered
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayUnits {
+    /// Decimals kept in ETH strings (rounded half up)
+    pub eth_decimals: u32,
+}
+
+impl Default for DisplayUnits {
+    fn default() -> Self {
+        Self { eth_decimals: DEFAULT_ETH_DISPLAY_DECIMALS }
+    }
+}
+
+impl DisplayUnits {
+    /// A wei amount in gwei, exact, without trailing zeros (e.g. `"12.5"`)
+    pub fn gwei(&self, wei: U256) -> String {
+        format_gwei(wei)
+    }
+
+    /// A wei amount in ETH with the configured number of decimals (e.g. `"0.001234"`)
+    pub fn eth(&self, wei: U256) -> String {
+        format_units(wei, ETHER_DECIMALS, self.eth_decimals)
+    }
+}
+
+/// Format a wei amount in gwei, exact, without trailing zeros (e.g. `"12.5"`)
+pub fn format_gwei(wei: U256) -> String {
+    trim_fraction(format_units(wei, GWEI_DECIMALS, GWEI_DECIMALS))
+}
+
+/// Format a wei amount in ETH, exact, without trailing zeros (e.g. `"0.0015"`)
+pub fn format_ether(wei: U256) -> String {
+    trim_fraction(format_units(wei, ETHER_DECIMALS, ETHER_DECIMALS))
+}
+
+/// Format an integer amount of `10^-decimals` units as a fixed-point decimal string
+///
+/// # Arguments
+///
+/// * `value` - The amount in the smallest unit (e.g. wei)
+/// * `decimals` - Decimals of the display unit relative to the smallest unit
+/// * `precision` - Fractional digits in the result (rounded half up)
+///
+/// # Returns
+///
+/// * The amount with exactly `precision` fractional digits (no point when 0)
+pub fn format_units(value: U256, decimals: u32, precision: u32) -> String {
+    let ten = U256::from(10);
+    let value = if precision < decimals {
+        let divisor = ten.pow(U256::from(decimals - precision));
+        value.saturating_add(divisor / U256::from(2)) / divisor
+    } else {
+        value * ten.pow(U256::from(precision - decimals))
+    };
+    if precision == 0 {
+        return value.to_string();
+    }
+
+    let scale = ten.pow(U256::from(precision));
+    format!(
+        "{}.{:0>width$}",
+        value / scale,
+        (value % scale).to_string(),
+        width = precision as usize
+    )
+}
+
+/// Drop trailing zeros of the fractional part (and the point if nothing is left)
+fn trim_fraction(formatted: String) -> String {
+    if !formatted.contains('.') {
+        return formatted;
+    }
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Parse a decimal gwei string (e.g. `"1.5"`) into wei
+pub fn parse_gwei(gwei: &str) -> Result<U256, String> {
+    parse_units(gwei, GWEI_DECIMALS)
+}
+
+/// Parse a decimal ETH string (e.g. `"0.01"`) into wei
+pub fn parse_ether(ether: &str) -> Result<U256, String> {
+    parse_units(ether, ETHER_DECIMALS)
+}
+
+/// Parse a fixed-point decimal string into an integer amount of `10^-decimals` units
+///
+/// # Arguments
+///
+/// * `value` - Digits with an optional `.` and fractional part (e.g. `"12.5"`)
+/// * `decimals` - Decimals of the unit relative to the smallest unit
+///
+/// # Returns
+///
+/// * `Result<U256, String>` - The amount in the smallest unit or error message
+pub fn parse_units(value: &str, decimals: u32) -> Result<U256, String> {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(format!("Invalid decimal amount: {:?}", value));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!(
+            "Too many decimals in {:?}: at most {} allowed",
+            value, decimals
+        ));
+    }
+
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    U256::from_str_radix(&digits, 10).map_err(|e| format!("Invalid decimal amount {:?}: {}", value, e))
+}
+
+/// Format a `U256` value into a hexadecimal string prefixed with "0x".
+///
+/// # Arguments
+///
+/// * `value` - The U256 value to format
+///
+/// # Returns
+///
+/// * String representation of the value in hexadecimal
+pub fn format_hex_u256(value: U256) -> String {
+    format!("0x{:x}", value)
+}
@@ -0,0 +1,111 @@
+//! Tests of the core library that need no upstream node
+
+use alloy::{
+    eips::eip7702::SignedAuthorization,
+    primitives::U256,
+    rpc::types::TransactionRequest,
+};
+use std::time::{Duration, Instant};
+
+use eth_gas_estimator_core::{
+    error::ServiceError,
+    estimator::{calldata_floor_gas, Headroom},
+    fixtures::{FixtureChain, COUNTER_ADDRESS},
+    foundry::check_authorizations,
+    quotes::{QuotePolicy, DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT, DEFAULT_QUOTE_TTL_BLOCKS},
+    timeouts::{deadline, remaining_budget, with_deadline, within_deadline, Timeouts},
+};
+
+#[test]
+fn test_check_authorizations() {
+    let fixtures = FixtureChain::new();
+    let signed = fixtures.sign_authorization(1, COUNTER_ADDRESS, 0);
+    let with_signature = |y_parity: u8, s: U256| {
+        SignedAuthorization::new_unchecked(signed.inner().clone(), y_parity, signed.r(), s)
+    };
+    let request = |authorizations: Vec<SignedAuthorization>| TransactionRequest {
+        authorization_list: Some(authorizations),
+        ..fixtures.authorization_request()
+    };
+
+    // Requests without authorizations and properly signed ones pass
+    check_authorizations(&TransactionRequest::default(), true).expect("No authorizations to check");
+    check_authorizations(&fixtures.authorization_request(), true).expect("The fixture authorization is signed");
+
+    // A signature with s in the upper half of the curve order can't be recovered
+    let placeholder = with_signature(signed.y_parity(), U256::from_be_bytes([0xbb; 32]));
+    let request_with_placeholder = request(vec![signed.clone(), placeholder]);
+    match check_authorizations(&request_with_placeholder, true) {
+        Err(ServiceError::InvalidParams(message)) => {
+            assert!(message.starts_with("Invalid authorization 1: invalid signature"), "{}", message)
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    // Drafts skip the recovery, but not the yParity check
+    check_authorizations(&request_with_placeholder, false).expect("Placeholder signatures are accepted");
+    match check_authorizations(&request(vec![with_signature(2, signed.s())]), false) {
+        Err(ServiceError::InvalidParams(message)) => {
+            assert_eq!(message, "Invalid authorization 0: yParity must be 0 or 1")
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_request_deadline() {
+    // Outside a deadline, timeouts apply as configured
+    let timeout = Timeouts::default().rpc;
+    assert!(deadline().is_none());
+    assert!(remaining_budget().is_none());
+    assert_eq!(within_deadline(timeout), timeout);
+
+    // Within one, they are shortened to the budget that remains
+    let until = Instant::now() + Duration::from_millis(500);
+    with_deadline(until, async {
+        assert_eq!(deadline(), Some(until));
+        assert!(remaining_budget().unwrap() <= Duration::from_millis(500));
+        assert!(within_deadline(timeout) <= Duration::from_millis(500));
+        assert_eq!(within_deadline(Duration::from_millis(1)), Duration::from_millis(1));
+    })
+    .await;
+
+    // A deadline in the past leaves no budget
+    with_deadline(Instant::now(), async {
+        assert_eq!(within_deadline(timeout), Duration::ZERO);
+    })
+    .await;
+    assert!(deadline().is_none());
+}
+
+#[test]
+fn test_headroom() {
+    let block_gas_limit = U256::from(30_000_000u64);
+
+    // The multiplier rounds up, then the buffer is added
+    let headroom = Headroom::new(1.25, 1_000).unwrap();
+    assert_eq!(headroom.apply(U256::from(21_001u64), block_gas_limit), U256::from(27_252u64));
+    assert_eq!(Headroom::default().apply(U256::from(21_000u64), block_gas_limit), U256::from(21_000u64));
+
+    // The padded estimate never exceeds the block gas limit, nor drops below the estimate
+    assert_eq!(headroom.apply(U256::from(29_000_000u64), block_gas_limit), block_gas_limit);
+    assert_eq!(headroom.apply(U256::from(31_000_000u64), block_gas_limit), U256::from(31_000_000u64));
+
+    for multiplier in [0.9, f64::NAN, f64::INFINITY] {
+        assert!(Headroom::new(multiplier, 0).is_err(), "{} is accepted", multiplier);
+    }
+}
+
+#[test]
+fn test_calldata_floor_gas() {
+    assert_eq!(calldata_floor_gas(&[]), 21_000);
+    // One zero byte is one token, one other byte four
+    assert_eq!(calldata_floor_gas(&[0x00, 0x01]), 21_000 + 10 * 5);
+}
+
+#[test]
+fn test_quote_policy_default() {
+    let policy = QuotePolicy::default();
+    assert_eq!(policy.ttl_blocks, DEFAULT_QUOTE_TTL_BLOCKS);
+    assert_eq!(policy.max_base_fee_drift_percent, DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT);
+}
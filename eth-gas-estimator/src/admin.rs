@@ -0,0 +1,310 @@
+use crate::{
+    api::{process_raw_request, METHODS},
+    config::Config,
+    error::ServiceError,
+    divergence::DivergenceMonitor,
+    estimator::GasEstimator,
+    metrics::RequestMetrics,
+    models::{
+        jsonrpc::JsonRpcResponse,
+        probes::ProbeReport,
+        telemetry::{TopContracts, TopContractsQuery},
+    },
+    probes::Prober,
+    redaction::Redaction,
+    reload::ConfigReloader,
+    slo::slo_report,
+    snapshot,
+    telemetry::ContractTelemetry,
+};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::Serialize;
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tracing::{info, instrument::WithSubscriber, warn, Instrument, Level};
+
+/// Diagnostic bundle returned by the replay endpoint
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayReport {
+    /// Id of the replayed request
+    request_id: String,
+
+    /// Unix timestamp (seconds) at which the original request was received
+    received_at: u64,
+
+    /// Correlation id the original request was served under
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+
+    /// The original JSON-RPC request, as recorded in the audit log
+    request: serde_json::Value,
+
+    /// The JSON-RPC response produced by the replay
+    response: JsonRpcResponse<serde_json::Value>,
+
+    /// Wall-clock duration of the replay in milliseconds
+    duration_ms: u128,
+
+    /// Debug-level log lines emitted while the replay ran
+    logs: Vec<String>,
+}
+
+/// Replay a previously logged request with debug tracing enabled
+///
+/// Looks up the request id in the audit log, re-runs the exact request against
+/// the current chain head and returns the response together with every log line
+/// emitted during the replay, simulations included. Requests whose signed data
+/// was redacted are refused. Requires the `ADMIN_TOKEN` bearer token.
+#[post("/admin/replay/{request_id}")]
+async fn replay_request(
+    req: HttpRequest,
+    path: web::Path<String>,
+    config: Option<web::Data<Config>>,
+    estimator: web::Data<Arc<GasEstimator>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let request_id = path.into_inner();
+    let entry = estimator
+        .audit_log
+        .get(&request_id)
+        .ok_or_else(|| ServiceError::NotFound(format!("No logged request with id {}", request_id)))?;
+    // Without its signed material the request would only fail to decode
+    if entry.redacted {
+        return Err(ServiceError::InvalidParams(format!(
+            "Request {} carried signed data the audit log doesn't keep, so it can't be replayed",
+            request_id
+        )));
+    }
+    info!("Replaying request {} with debug tracing", request_id);
+
+    // Capture everything the replay logs, independently of the global filter
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let writer_buffer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || CaptureWriter(writer_buffer.clone()))
+        .finish();
+
+    let started = Instant::now();
+    let result = process_raw_request(&estimator, entry.payload.clone(), METHODS, &Redaction::default())
+        .instrument(tracing::info_span!("replay", request_id = %request_id))
+        .with_subscriber(subscriber)
+        .await;
+    let duration_ms = started.elapsed().as_millis();
+
+    let logs = {
+        let bytes = buffer.lock().unwrap_or_else(|e| e.into_inner());
+        String::from_utf8_lossy(&bytes).lines().map(str::to_owned).collect()
+    };
+
+    Ok(HttpResponse::Ok().json(ReplayReport {
+        request_id,
+        received_at: entry.received_at,
+        correlation_id: entry.correlation_id,
+        request: entry.payload,
+        response: JsonRpcResponse::from(result),
+        duration_ms,
+        logs,
+    }))
+}
+
+/// Fetch a logged request without replaying it
+#[get("/admin/requests/{request_id}")]
+async fn get_logged_request(
+    req: HttpRequest,
+    path: web::Path<String>,
+    config: Option<web::Data<Config>>,
+    estimator: web::Data<Arc<GasEstimator>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let request_id = path.into_inner();
+    let entry = estimator
+        .audit_log
+        .get(&request_id)
+        .ok_or_else(|| ServiceError::NotFound(format!("No logged request with id {}", request_id)))?;
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+/// Default number of contracts returned by the top contracts endpoint
+const DEFAULT_TOP_CONTRACTS: usize = 20;
+
+/// Most called target contracts over the telemetry window
+#[get("/admin/contracts/top")]
+async fn top_contracts(
+    req: HttpRequest,
+    query: web::Query<TopContractsQuery>,
+    config: Option<web::Data<Config>>,
+    telemetry: Option<web::Data<Arc<ContractTelemetry>>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let telemetry = telemetry
+        .ok_or_else(|| ServiceError::NotFound("Contract telemetry is disabled".to_string()))?;
+    Ok(HttpResponse::Ok().json(TopContracts {
+        window_secs: telemetry.window().as_secs(),
+        contracts: telemetry.top(query.limit.unwrap_or(DEFAULT_TOP_CONTRACTS)),
+    }))
+}
+
+/// Success-rate and latency SLO attainment and remaining error budgets
+#[get("/admin/slo")]
+async fn slo(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    metrics: Option<web::Data<Arc<RequestMetrics>>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let (Some(config), Some(metrics)) = (config, metrics) else {
+        return Err(ServiceError::NotFound("Request metrics are disabled".to_string()));
+    };
+    Ok(HttpResponse::Ok().json(slo_report(&metrics.totals(), metrics.window(), &config.slo_objectives())))
+}
+
+/// Latest result and recent failure counts of the scheduled probes
+#[get("/admin/probes")]
+async fn probes(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    prober: Option<web::Data<Arc<Prober>>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let prober = prober.ok_or_else(|| ServiceError::NotFound("Probes are disabled".to_string()))?;
+    Ok(HttpResponse::Ok().json(ProbeReport {
+        window_secs: prober.window().as_secs(),
+        probes: prober.statuses(),
+    }))
+}
+
+/// Shadow comparison counts and the state of the divergence alarm
+#[get("/admin/divergence")]
+async fn divergence(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    monitor: Option<web::Data<Arc<DivergenceMonitor>>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let monitor = monitor.ok_or_else(|| ServiceError::NotFound("Shadow comparisons are disabled".to_string()))?;
+    Ok(HttpResponse::Ok().json(monitor.status()))
+}
+
+/// Detected profile of the upstream chain
+#[get("/admin/chain-profile")]
+async fn chain_profile(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    estimator: web::Data<Arc<GasEstimator>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let profile = estimator
+        .forks
+        .profile()
+        .ok_or_else(|| ServiceError::NotFound("The chain profile hasn't been detected yet".to_string()))?;
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+/// State the current fork has loaded, for new replicas to preload (see [`crate::snapshot`])
+#[get("/admin/snapshot")]
+async fn state_snapshot(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    estimator: web::Data<Arc<GasEstimator>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let fork = estimator
+        .forks
+        .current()
+        .await
+        .ok_or_else(|| ServiceError::NotFound("No fork has been created yet".to_string()))?;
+    Ok(HttpResponse::Ok().json(snapshot::export(&fork)))
+}
+
+/// Result of a configuration reload
+#[derive(Serialize)]
+struct ReloadReport {
+    /// Settings groups that changed and were applied
+    applied: Vec<&'static str>,
+}
+
+/// Reload the configuration, applying upstream, rate limit and quote policy changes
+#[post("/admin/config/reload")]
+async fn reload_config(
+    req: HttpRequest,
+    config: Option<web::Data<Config>>,
+    reloader: Option<web::Data<Arc<ConfigReloader>>>,
+) -> Result<HttpResponse, ServiceError> {
+    authorize(&req, config.as_deref())?;
+
+    let reloader = reloader.ok_or_else(|| ServiceError::NotFound("Config reload is disabled".to_string()))?;
+    let applied = reloader.reload().await?;
+    Ok(HttpResponse::Ok().json(ReloadReport { applied }))
+}
+
+/// Configure the admin routes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(replay_request)
+       .service(get_logged_request)
+       .service(top_contracts)
+       .service(slo)
+       .service(reload_config)
+       .service(probes)
+       .service(divergence)
+       .service(chain_profile)
+       .service(state_snapshot);
+}
+
+/// Path prefix of the admin endpoints, which are authorized by [`authorize`] alone
+pub const ADMIN_PATH_PREFIX: &str = "/admin/";
+
+/// Check the request's bearer token against the configured admin token
+///
+/// Admin endpoints are disabled entirely when no `ADMIN_TOKEN` is configured.
+pub(crate) fn authorize(req: &HttpRequest, config: Option<&Config>) -> Result<(), ServiceError> {
+    let expected = config
+        .and_then(|config| config.admin_token.as_deref())
+        .ok_or_else(|| ServiceError::Unauthorized("Admin endpoints are disabled".to_string()))?;
+
+    let provided = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => {
+            warn!("Rejected admin request with missing or invalid token");
+            Err(ServiceError::Unauthorized("Invalid admin token".to_string()))
+        }
+    }
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `io::Write` adapter that appends formatted log output to a shared buffer
+struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for CaptureWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,24 +1,54 @@
 use tracing::debug;
 use crate::{
-    error::ServiceError,
-    estimator::{GasEstimator, DEFAULT_GAS_LIMIT, DEFAULT_GAS_PRICE},
+    config::Config,
+    correlation::{correlation_id, generate_correlation_id, with_correlation_id},
+    error::{retry_after_secs, ServiceError},
+    estimator::{EstimateOptions, EstimateSource, GasEstimator, Headroom},
+    chains::profile::BlobParams,
+    metrics::{InBandServerError, RequestMetrics},
+    foundry::{BlockCalls, BlockOverrides, BlockPins, BlockTarget, CodeOverrides},
+    rate_limit::charge_batch,
+    redaction::{request_redaction, Redaction},
+    rpc::upstream_failure,
     models:: {
         jsonrpc::{
-            JsonRpcRequest, JsonRpcSuccess, JsonRpcError, EthEstimateGasParams,
-            parse_hex_address, parse_hex_u256, parse_hex_bytes, format_hex_u256, parse_hex_u64, parse_hex_b256, parse_hex_or_dec_u8
-        }
-    }
+            JsonRpcRequest, JsonRpcSuccess, JsonRpcError, JsonRpcResponse, EthEstimateGasParams, BatchSummary,
+            CacheScope, JsonRpcResponseV2, JsonRpcSuccessV2, BlobGasUsage, AccountOverrideParams,
+        },
+        bundle::CallBundleParams,
+        explain::ExplainOptions,
+        simulate::{BlockOverridesParams, SimulatePayload},
+        simulation::SimulateOptions,
+        trace::{TraceOptions, TracerConfig},
+    },
+    units::format_hex_u256,
+    versioning::ApiVersion,
 };
 use actix_web::{
-    post, web, HttpRequest, HttpResponse
+    error::{InternalError, JsonPayloadError},
+    get, http::{header, StatusCode}, post, web, HttpRequest, HttpResponse
+};
+use futures::{
+    future::LocalBoxFuture,
+    stream::{self, StreamExt},
 };
-use std::sync::Arc;
-use tracing::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::error::Category;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tracing::{error, info, warn, Instrument, Span};
 use alloy::{
-    primitives::{Bytes, U256, B256},
-    rpc::types::{TransactionInput, TransactionRequest},
+    primitives::{keccak256, Address, U256, U64, B256},
+    providers::Provider,
+    rpc::types::{BlockNumberOrTag, TransactionInput, TransactionRequest},
     eips::{
-        eip2930::{AccessList, AccessListItem},
+        eip4844::VERSIONED_HASH_VERSION_KZG,
+        BlockId,
     }
 };
 
@@ -50,7 +80,7 @@ fn format_estimate_gas_params(params: &EthEstimateGasParams) -> String {
         lines.push(format!("input: {}", input));
     }
     if let Some(ref block) = params.block {
-        lines.push(format!("block: {}", block));
+        lines.push(format!("block: {:?}", block));
     }
     if let Some(ref nonce) = params.nonce {
         lines.push(format!("nonce: {}", nonce));
@@ -70,6 +100,9 @@ fn format_estimate_gas_params(params: &EthEstimateGasParams) -> String {
     if let Some(ref max_fee_per_blob_gas) = params.max_fee_per_blob_gas {
         lines.push(format!("maxFeePerBlobGas: {}", max_fee_per_blob_gas));
     }
+    if let Some(ref blob_count) = params.blob_count {
+        lines.push(format!("blobCount: {}", blob_count));
+    }
     if let Some(ref sidecar) = params.sidecar {
         lines.push(format!("sidecar: {:?}", sidecar));
     }
@@ -84,13 +117,93 @@ fn format_estimate_gas_params(params: &EthEstimateGasParams) -> String {
     }
 }
 
+/// Value produced by a JSON-RPC method handler
+pub(crate) struct MethodOutput {
+    /// Method result
+    result: serde_json::Value,
+    /// Source of a gas estimate, echoed in the response
+    source: Option<EstimateSource>,
+    /// Caching metadata, for results tied to the head block
+    cache: Option<CacheScope>,
+    /// Blob gas of a type-3 estimate, reported in the v2 `meta`
+    blob_gas: Option<BlobGasUsage>,
+}
+
+impl From<serde_json::Value> for MethodOutput {
+    fn from(result: serde_json::Value) -> Self {
+        Self { result, source: None, cache: None, blob_gas: None }
+    }
+}
+
+/// Result of a JSON-RPC method handler
+type MethodResult = Result<MethodOutput, JsonRpcError>;
+
+/// A JSON-RPC method handler: receives the request id and raw params
+pub(crate) type MethodHandler =
+    for<'a> fn(&'a GasEstimator, serde_json::Value, serde_json::Value) -> LocalBoxFuture<'a, MethodResult>;
+
+/// Registry of every JSON-RPC method served by the generic dispatch endpoint
+pub(crate) const METHODS: &[(&str, MethodHandler)] = &[
+    ("eth_estimateGas", eth_estimate_gas),
+    ("eth_call", eth_call),
+    ("eth_chainId", eth_chain_id),
+    ("eth_blockNumber", eth_block_number),
+    ("eth_gasPrice", eth_gas_price),
+    ("eth_feeHistory", eth_fee_history),
+    ("eth_getBalance", eth_get_balance),
+    ("eth_getTransactionCount", eth_get_transaction_count),
+    ("eth_simulateV1", eth_simulate_v1),
+    ("eth_callBundle", eth_call_bundle),
+    ("debug_traceCall", debug_trace_call),
+    ("x_estimateGasDetailed", x_estimate_gas_detailed),
+    ("x_explainEstimate", x_explain_estimate),
+    ("eth_createAccessList", eth_create_access_list),
+    ("estimator_simulate", estimator_simulate),
+];
+
+/// Registry for the dedicated estimateGas endpoints
+const ESTIMATE_METHODS: &[(&str, MethodHandler)] = &[("eth_estimateGas", eth_estimate_gas)];
+
+/// Registry for the v2 estimateGas endpoint, answering with extended results
+const ESTIMATE_METHODS_V2: &[(&str, MethodHandler)] = &[("eth_estimateGas", eth_estimate_gas_extended)];
+
+/// Response header naming where the gas estimate of a single `eth_estimateGas` request came from
+const ESTIMATE_SOURCE_HEADER: &str = "X-Estimate-Source";
+
+/// Generic JSON-RPC endpoint dispatching on `request.method`
+///
+/// Serves every method in [`METHODS`], so the service can be used as a (partial)
+/// node URL by standard tooling. Unknown methods yield `-32601 Method not found`.
+/// Accepts single requests and JSON-RPC 2.0 batches.
+async fn jsonrpc_dispatch(
+    req: HttpRequest,
+    estimator: web::Data<Arc<GasEstimator>>,
+    config: Option<web::Data<Config>>,
+    body: web::Json<serde_json::Value>,
+) -> HttpResponse {
+    if let Some(limited) = charge_batch(&req, payload_items(&body)) {
+        return limited;
+    }
+    let request = body.into_inner();
+    let version = ApiVersion::negotiate(&req);
+    if let Some(cached) = not_modified(&req, &estimator, &request, version).await {
+        return cached;
+    }
+    let reply = process_jsonrpc_payload(&estimator, request.clone(), METHODS, &request_redaction(&req)).await;
+    jsonrpc_http_response(reply, &request, version, config.as_deref())
+}
+
 /// Endpoint to estimate gas for Ethereum transactions following the JSON-RPC protocol
 /// This endpoint conforms to the Ethereum JSON-RPC specification for eth_estimateGas
-#[post("/api/v1/eth/estimateGas")]
+///
+/// The body may be a single request object or a JSON-RPC 2.0 batch (an array of
+/// request objects). Batch items are estimated concurrently and answered with an
+/// array of responses in the same order as the requests.
 async fn estimate_gas_jsonrpc(
     req: HttpRequest,
     estimator: web::Data<Arc<GasEstimator>>,
-    request: web::Json<JsonRpcRequest<Vec<EthEstimateGasParams>>>,
+    config: Option<web::Data<Config>>,
+    body: web::Json<serde_json::Value>,
 ) -> HttpResponse {
     debug!(
         "Received JSON-RPC gas estimation request from {}",
@@ -99,70 +212,1234 @@ async fn estimate_gas_jsonrpc(
             .unwrap_or_else(|| "unknown".into())
         );
 
+    if let Some(limited) = charge_batch(&req, payload_items(&body)) {
+        return limited;
+    }
+    let request = body.into_inner();
+    let version = ApiVersion::negotiate(&req);
+    if let Some(cached) = not_modified(&req, &estimator, &request, version).await {
+        return cached;
+    }
+    let reply = process_jsonrpc_payload(&estimator, request.clone(), ESTIMATE_METHODS, &request_redaction(&req)).await;
+    jsonrpc_http_response(reply, &request, version, config.as_deref())
+}
+
+/// Extended estimateGas endpoint (v2)
+///
+/// Takes the same requests and batches as `/api/v1/eth/estimateGas`, but the
+/// result of `eth_estimateGas` is an object carrying the estimate together with
+/// the status, return data, logs and created contract address of the transaction
+/// under it. Responses always use the v2 schema.
+async fn estimate_gas_extended_jsonrpc(
+    req: HttpRequest,
+    estimator: web::Data<Arc<GasEstimator>>,
+    config: Option<web::Data<Config>>,
+    body: web::Json<serde_json::Value>,
+) -> HttpResponse {
+    if let Some(limited) = charge_batch(&req, payload_items(&body)) {
+        return limited;
+    }
+    let request = body.into_inner();
+    if let Some(cached) = not_modified(&req, &estimator, &request, ApiVersion::V2).await {
+        return cached;
+    }
+    let reply =
+        process_jsonrpc_payload(&estimator, request.clone(), ESTIMATE_METHODS_V2, &request_redaction(&req)).await;
+    jsonrpc_http_response(reply, &request, ApiVersion::V2, config.as_deref())
+}
+
+/// Entity tag of a block-bound result: the head block it holds for and the request it answers
+fn entity_tag(block_hash: B256, request: &serde_json::Value, version: ApiVersion) -> String {
+    let mut preimage = block_hash.to_vec();
+    preimage.push(version as u8);
+    preimage.extend_from_slice(request.to_string().as_bytes());
+    format!("\"{}\"", keccak256(preimage))
+}
+
+/// Answer a conditional request whose cached result still holds with `304 Not Modified`
+///
+/// `If-None-Match` is compared against the tag the same request gets at the
+/// current head, so a result computed against an earlier head never matches.
+/// Batches carry no tag and are always answered in full.
+async fn not_modified(
+    req: &HttpRequest,
+    estimator: &GasEstimator,
+    request: &serde_json::Value,
+    version: ApiVersion,
+) -> Option<HttpResponse> {
+    let candidates = req.headers().get(header::IF_NONE_MATCH)?.to_str().ok()?;
+    if !request.is_object() {
+        return None;
+    }
+    let head = estimator.forks.latest().await.ok()?;
+    let tag = entity_tag(head.block_hash, request, version);
+    // Weak comparison, as for GET requests
+    candidates
+        .split(',')
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == tag)
+        .then(|| {
+            HttpResponse::NotModified()
+                .insert_header((header::CACHE_CONTROL, "private, no-cache"))
+                .insert_header((header::ETAG, tag))
+                .finish()
+        })
+}
+
+/// Turn a JSON-RPC reply into the HTTP response sent to the client, in the negotiated schema
+///
+/// `request` is the payload the reply answers, tagging block-bound results.
+fn jsonrpc_http_response(
+    reply: JsonRpcReply,
+    request: &serde_json::Value,
+    version: ApiVersion,
+    config: Option<&Config>,
+) -> HttpResponse {
+    let mut response = match reply {
+        JsonRpcReply::Single(Ok(success)) => {
+            let mut response = HttpResponse::Ok();
+            if let Some(source) = success.source {
+                response.insert_header((ESTIMATE_SOURCE_HEADER, source.as_str()));
+            }
+            // Block-bound results may be stored, but must be revalidated against the head
+            if let Some(cache) = &success.cache {
+                response
+                    .insert_header((header::CACHE_CONTROL, "private, no-cache"))
+                    .insert_header((header::ETAG, entity_tag(cache.block_hash, request, version)));
+            }
+            match version {
+                ApiVersion::V1 => response.json(success),
+                ApiVersion::V2 => response.json(JsonRpcSuccessV2::from(success)),
+            }
+        }
+        JsonRpcReply::Single(Err(rpc_error)) => {
+            let mut response = HttpResponse::build(error_status(&rpc_error));
+            let retry_after = rpc_error.error.data.as_ref().and_then(|data| data.get("retryAfter"));
+            if let Some(retry_after) = retry_after.and_then(|secs| secs.as_u64()) {
+                response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+            }
+            response.json(rpc_error)
+        }
+        // Per the JSON-RPC batch spec, item failures are reported in-band
+        JsonRpcReply::Batch(responses) => {
+            let server_error = responses
+                .iter()
+                .any(|response| matches!(response, JsonRpcResponse::Error(rpc_error) if is_server_error(rpc_error)));
+            let mut response = match version {
+                ApiVersion::V1 => HttpResponse::Ok().json(responses),
+                ApiVersion::V2 => HttpResponse::Ok()
+                    .json(responses.into_iter().map(JsonRpcResponseV2::from).collect::<Vec<_>>()),
+            };
+            InBandServerError::mark(&mut response, server_error);
+            response
+        }
+    };
+    config
+        .map(Config::v1_deprecation)
+        .unwrap_or_default()
+        .annotate(&mut response, version);
+    response
+}
+
+/// Number of requests in a JSON-RPC payload: the items of a batch, or one
+pub(crate) fn payload_items(payload: &serde_json::Value) -> usize {
+    match payload {
+        serde_json::Value::Array(items) => items.len(),
+        _ => 1,
+    }
+}
+
+/// Reply to a JSON-RPC payload, mirroring whether it was a single request or a batch
+pub(crate) enum JsonRpcReply {
+    /// Response to a single request object
+    Single(Result<JsonRpcSuccess<serde_json::Value>, JsonRpcError>),
+
+    /// Ordered responses to a batch of request objects
+    Batch(Vec<JsonRpcResponse<serde_json::Value>>),
+}
+
+/// Process a raw JSON-RPC payload that may be a single request or a batch
+///
+/// Batch items are processed concurrently, at most the estimator's batch
+/// concurrency at a time, and answered in request order.
+/// This is the entry point shared by every transport (HTTP and WebSocket);
+/// `redaction` holds the verbose fields withheld from the caller.
+pub(crate) async fn process_jsonrpc_payload(
+    estimator: &GasEstimator,
+    payload: serde_json::Value,
+    methods: &[(&str, MethodHandler)],
+    redaction: &Redaction,
+) -> JsonRpcReply {
+    match payload {
+        serde_json::Value::Array(items) => {
+            // An empty batch is answered with a single error, not an empty array
+            if items.is_empty() {
+                return JsonRpcReply::Single(Err(JsonRpcError::invalid_request(
+                    serde_json::Value::Null,
+                    "Empty batch".to_string(),
+                )));
+            }
+
+            info!("Received JSON-RPC batch of {} requests", items.len());
+            let concurrency = estimator.batch_concurrency(items.len());
+            let responses = stream::iter(items)
+                .map(|item| process_batch_item(estimator, item, methods, redaction))
+                .buffered(concurrency)
+                .map(JsonRpcResponse::from)
+                .collect()
+                .await;
+
+            JsonRpcReply::Batch(responses)
+        }
+        item => JsonRpcReply::Single(process_batch_item(estimator, item, methods, redaction).await),
+    }
+}
+
+/// Streaming batch endpoint for eth_estimateGas
+///
+/// Accepts a JSON array of eth_estimateGas JSON-RPC requests and simulates them
+/// concurrently, at most the estimator's batch concurrency at a time. Each response is written as a single NDJSON line as soon as its
+/// simulation finishes, so clients don't wait for the slowest item in the batch.
+/// Responses are emitted in completion order; use the `id` to correlate them.
+///
+/// Items are parsed individually: a malformed item yields its own JSON-RPC error
+/// line instead of rejecting the whole batch. The stream always ends with a
+/// summary line carrying the total item count and `failedCount`. Lines use the
+/// schema version negotiated with `Accept-Version`. Items failing on the
+/// server's side are counted in the request metrics once the stream ends.
+async fn estimate_gas_stream(
+    req: HttpRequest,
+    estimator: web::Data<Arc<GasEstimator>>,
+    config: Option<web::Data<Config>>,
+    metrics: Option<web::Data<Arc<RequestMetrics>>>,
+    items: web::Json<Vec<serde_json::Value>>,
+) -> HttpResponse {
+    let items = items.into_inner();
+    let total = items.len();
+    if let Some(limited) = charge_batch(&req, total) {
+        return limited;
+    }
+    info!("Received streaming batch of {} estimation requests", total);
+    let version = ApiVersion::negotiate(&req);
+
+    let estimator = estimator.get_ref().clone();
+    let failed = Arc::new(AtomicUsize::new(0));
+    let server_failed = Arc::new(AtomicBool::new(false));
+    // Items are simulated while the body streams, after the handler has returned,
+    // so they are run under the request's span and correlation id explicitly
+    let span = Span::current();
+    let request_correlation_id = correlation_id().unwrap_or_else(generate_correlation_id);
+    let concurrency = estimator.batch_concurrency(total);
+    let pending = {
+        let (failed, server_failed) = (failed.clone(), server_failed.clone());
+        stream::iter(items)
+            .map(move |item| {
+                let estimator = estimator.clone();
+                let failed = failed.clone();
+                let server_failed = server_failed.clone();
+                let line = async move {
+                    let line = match process_batch_item(&estimator, item, ESTIMATE_METHODS, &Redaction::default()).await {
+                        Ok(success) => {
+                            let id = success.id.clone();
+                            let line = match version {
+                                ApiVersion::V1 => ndjson_line(&success),
+                                ApiVersion::V2 => ndjson_line(&JsonRpcSuccessV2::from(success)),
+                            };
+                            // An item whose response can't be written fails on its own line
+                            line.map_err(|e| {
+                                error!("Failed to serialize the streamed response {}: {}", id, e);
+                                JsonRpcError::internal_error(id, "Failed to serialize the response".to_string())
+                            })
+                        }
+                        Err(rpc_error) => Err(rpc_error),
+                    };
+                    match line {
+                        Ok(line) => Ok(line),
+                        Err(rpc_error) => {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                            if is_server_error(&rpc_error) {
+                                server_failed.store(true, Ordering::Relaxed);
+                            }
+                            ndjson_line(&rpc_error)
+                        }
+                    }
+                };
+                with_correlation_id(request_correlation_id.clone(), line).instrument(span.clone())
+            })
+            .buffer_unordered(concurrency)
+    };
+
+    // Once every item has been written, close the stream with the batch summary
+    let metrics = metrics.map(|metrics| metrics.get_ref().clone());
+    let summary = stream::once(async move {
+        if let Some(metrics) = metrics.filter(|_| server_failed.load(Ordering::Relaxed)) {
+            metrics.record_late_server_error();
+        }
+        ndjson_line(&BatchSummaryLine {
+            summary: BatchSummary::new(total, failed.load(Ordering::Relaxed)),
+        })
+    });
+
+    let mut response = HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(pending.chain(summary));
+    config
+        .as_deref()
+        .map(Config::v1_deprecation)
+        .unwrap_or_default()
+        .annotate(&mut response, version);
+    response
+}
+
+/// Trailing NDJSON record of a streamed batch
+#[derive(Serialize)]
+struct BatchSummaryLine {
+    summary: BatchSummary,
+}
+
+/// Record one raw JSON-RPC request object in the audit log and process it
+///
+/// The assigned request id is attached to error responses (`error.data.requestId`)
+/// so users can quote it in bug reports and operators can replay the request,
+/// along with the correlation id of the HTTP request (`error.data.correlationId`).
+async fn process_batch_item(
+    estimator: &GasEstimator,
+    item: serde_json::Value,
+    methods: &[(&str, MethodHandler)],
+    redaction: &Redaction,
+) -> Result<JsonRpcSuccess<serde_json::Value>, JsonRpcError> {
+    let request_id = estimator.audit_log.record(&item);
+    process_raw_request(estimator, item, methods, redaction)
+        .await
+        .map_err(|rpc_error| {
+            let rpc_error = rpc_error.with_data_field("requestId", serde_json::json!(request_id));
+            match correlation_id() {
+                Some(correlation_id) => rpc_error.with_data_field("correlationId", serde_json::json!(correlation_id)),
+                None => rpc_error,
+            }
+        })
+}
+
+/// Parse one raw JSON-RPC request object and dispatch it to its method handler
+///
+/// Deserialization failures are reported as an `Invalid Request` error (echoing
+/// the item's `id` when one can be recovered), so inside a batch a single bad
+/// entry never discards the rest. Methods missing from `methods` yield
+/// `Method not found`, or `Unsupported method` at the dedicated endpoints (see
+/// [`unknown_method`]).
+pub(crate) async fn process_raw_request(
+    estimator: &GasEstimator,
+    item: serde_json::Value,
+    methods: &[(&str, MethodHandler)],
+    redaction: &Redaction,
+) -> Result<JsonRpcSuccess<serde_json::Value>, JsonRpcError> {
+    let id = item.get("id").cloned().unwrap_or(serde_json::Value::Null);
+    let request: JsonRpcRequest<serde_json::Value> = serde_json::from_value(item)
+        .map_err(|e| JsonRpcError::invalid_request(id, format!("Invalid request: {}", e)))?;
+
     // Validate JSON-RPC version
     if request.jsonrpc != "2.0" {
-        return HttpResponse::BadRequest().json(JsonRpcError::invalid_params(
-            request.id.clone(),
+        return Err(JsonRpcError::invalid_request(
+            request.id,
             "Invalid JSON-RPC version. Expected 2.0".to_string(),
         ));
     }
 
-    // Validate method
-    if request.method != "eth_estimateGas" {
-        return HttpResponse::BadRequest().json(JsonRpcError::invalid_params(
-            request.id.clone(),
-            format!("Unsupported method: {}", request.method),
-        ));
+    // Look up the method handler
+    let handler = methods
+        .iter()
+        .find(|(name, _)| *name == request.method)
+        .map(|(_, handler)| *handler)
+        .ok_or_else(|| unknown_method(request.id.clone(), &request.method, methods))?;
+
+    // Methods whose whole result is withheld from the caller aren't run at all
+    redaction
+        .check(&request.method, &request.params)
+        .map_err(|message| JsonRpcError::method_not_supported(request.id.clone(), message))?;
+
+    debug!("Dispatching JSON-RPC method {}", request.method);
+    let mut output = handler(estimator, request.id.clone(), request.params).await?;
+    redaction.apply(&mut output.result);
+    Ok(JsonRpcSuccess::new(request.id, output.result)
+        .with_source(output.source)
+        .with_cache(output.cache)
+        .with_blob_gas(output.blob_gas))
+}
+
+/// Error answering a method an endpoint doesn't serve
+///
+/// The generic endpoint answers like a node, with `-32601 Method not found`.
+/// The endpoints dedicated to `eth_estimateGas` (registries of that one method)
+/// keep answering any other method with `-32602 Unsupported method`, as they
+/// did before the generic endpoint existed.
+fn unknown_method(id: serde_json::Value, method: &str, methods: &[(&str, MethodHandler)]) -> JsonRpcError {
+    match methods {
+        [_] => JsonRpcError::invalid_params(id, format!("Unsupported method: {}", method)),
+        _ => JsonRpcError::method_not_found(id, method),
     }
+}
 
-    // Validate params - ensure we have transaction parameters
-    if request.params.is_empty() {
-        return HttpResponse::BadRequest().json(JsonRpcError::invalid_params(
-            request.id.clone(),
-            "Missing transaction parameters".to_string(),
-        ));
+/// Deserialize positional JSON-RPC params into a list of values
+fn positional_params(id: &serde_json::Value, params: serde_json::Value) -> Result<Vec<serde_json::Value>, JsonRpcError> {
+    match params {
+        serde_json::Value::Null => Ok(Vec::new()),
+        serde_json::Value::Array(values) => Ok(values),
+        _ => Err(JsonRpcError::invalid_params(
+            id.clone(),
+            "Params must be an array".to_string(),
+        )),
     }
+}
+
+/// Deserialize a positional param, naming the malformed field in the error
+///
+/// # Arguments
+///
+/// * `value` - The param's JSON value
+///
+/// # Returns
+///
+/// * `Result<T, String>` - The param, or the path of the offending field and the reason
+///   (e.g. `authorizationList[0].yParity: invalid value`)
+pub(crate) fn deserialize_param<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(|e| match e.path().to_string().as_str() {
+        "." => e.inner().to_string(),
+        path => format!("{}: {}", path, e.inner()),
+    })
+}
 
-    // Get the transaction parameters from the first element in the params array
-    let tx_params = &request.params[0];
+/// Deserialize the JSON body of a REST endpoint, naming the malformed field in the error
+///
+/// REST endpoints take their body as plain JSON and parse it here, so a bad
+/// transaction field is reported with its path (e.g. `transactions[2].gas`)
+/// like it is for JSON-RPC params.
+pub(crate) fn request_body<T: DeserializeOwned>(body: web::Json<serde_json::Value>) -> Result<T, ServiceError> {
+    deserialize_param(body.into_inner()).map_err(|e| ServiceError::InvalidParams(format!("Invalid request body: {}", e)))
+}
+
+/// Build a TransactionRequest from the first positional param of a call-style method
+async fn transaction_request_param(
+    id: &serde_json::Value,
+    params: serde_json::Value,
+) -> Result<(EthEstimateGasParams, TransactionRequest), JsonRpcError> {
+    // Validate params - ensure we have transaction parameters
+    let tx_params = positional_params(id, params)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| JsonRpcError::invalid_params(
+            id.clone(),
+            "Missing transaction parameters".to_string(),
+        ))?;
+    let tx_params: EthEstimateGasParams = deserialize_param(tx_params)
+        .map_err(|e| JsonRpcError::invalid_params(id.clone(), format!("Invalid transaction object: {}", e)))?;
     info!(
         "Received JSON-RPC params:\n  {}",
-        format_estimate_gas_params(tx_params)
+        format_estimate_gas_params(&tx_params)
     );
 
     // Convert JSON-RPC parameters to a TransactionRequest
-    let tx_request = match build_transaction_request(tx_params).await {
-        Ok(req) => req,
-        Err(err_msg) => {
-            return HttpResponse::BadRequest().json(JsonRpcError::invalid_params(
-                request.id.clone(),
-                err_msg,
-            ));
+    let tx_request = build_transaction_request(&tx_params)
+        .await
+        .map_err(|err_msg| JsonRpcError::invalid_params(id.clone(), err_msg))?;
+    Ok((tx_params, tx_request))
+}
+
+/// Block targeted by the `block` param (second)
+///
+/// `pending` targets the upstream's pending block, and an EIP-1898
+/// `{"blockHash": ...}` object the block with that hash, which must be
+/// canonical unless `requireCanonical` is `false`. `latest` and a missing or
+/// null param target the head; any other tag or number, bare or as a
+/// `{"blockNumber": ...}` object, is rejected.
+fn block_target_param(id: &serde_json::Value, params: &serde_json::Value) -> Result<BlockTarget, JsonRpcError> {
+    let block = positional_params(id, params.clone())?.into_iter().nth(1);
+    let Some(block) = block.filter(|value| !value.is_null()) else {
+        return Ok(BlockTarget::Latest);
+    };
+    let target: BlockId = serde_json::from_value(block.clone())
+        .map_err(|e| JsonRpcError::invalid_params(id.clone(), format!("Invalid block: {}", e)))?;
+    match target {
+        BlockId::Number(BlockNumberOrTag::Latest) => Ok(BlockTarget::Latest),
+        BlockId::Number(BlockNumberOrTag::Pending) => Ok(BlockTarget::Pending),
+        BlockId::Hash(hash) => Ok(BlockTarget::Hash {
+            hash: hash.block_hash,
+            require_canonical: hash.require_canonical.unwrap_or(true),
+        }),
+        // An EIP-1898 `{"blockNumber": ...}` object parses as a plain number
+        BlockId::Number(_) if block.is_object() => Err(JsonRpcError::invalid_params(
+            id.clone(),
+            format!("Unsupported block: {} (pin blocks by blockHash)", block),
+        )),
+        BlockId::Number(_) => Err(JsonRpcError::invalid_params(
+            id.clone(),
+            format!("Unsupported block: {} (use latest, pending or a block hash)", block),
+        )),
+    }
+}
+
+/// Header values pinned by the `blockOverrides` param (fourth, as in geth)
+///
+/// Only `prevRandao` and `feeRecipient` (or its alias `coinbase`) can be
+/// pinned for single-transaction simulations; the other overrides are rejected.
+fn block_pins_param(id: &serde_json::Value, params: &serde_json::Value) -> Result<BlockPins, JsonRpcError> {
+    let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+    let overrides = positional_params(id, params.clone())?.into_iter().nth(3);
+    let Some(overrides) = overrides.filter(|value| !value.is_null()) else {
+        return Ok(BlockPins::default());
+    };
+    let overrides: BlockOverridesParams =
+        deserialize_param(overrides).map_err(|e| invalid(format!("Invalid block overrides: {}", e)))?;
+    let overrides = BlockOverrides::from(overrides);
+    if overrides.number.is_some()
+        || overrides.time.is_some()
+        || overrides.gas_limit.is_some()
+        || overrides.base_fee_per_gas.is_some()
+    {
+        return Err(invalid("Only prevRandao and feeRecipient (coinbase) can be overridden".to_string()));
+    }
+    Ok(BlockPins {
+        prev_randao: overrides.prev_randao,
+        coinbase: overrides.fee_recipient,
+    })
+}
+
+/// Contract code overridden by the `stateOverrides` param (third, as in geth)
+///
+/// Each account may override its `code`, to estimate against code that isn't
+/// deployed yet; balance, nonce and storage overrides are rejected.
+fn code_overrides_param(id: &serde_json::Value, params: &serde_json::Value) -> Result<CodeOverrides, JsonRpcError> {
+    code_overrides(id, positional_params(id, params.clone())?.into_iter().nth(2))
+}
+
+/// Contract code overridden by a `stateOverrides` object, if any
+fn code_overrides(id: &serde_json::Value, overrides: Option<serde_json::Value>) -> Result<CodeOverrides, JsonRpcError> {
+    let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+    let Some(overrides) = overrides.filter(|value| !value.is_null()) else {
+        return Ok(CodeOverrides::default());
+    };
+    let overrides: BTreeMap<Address, AccountOverrideParams> =
+        deserialize_param(overrides).map_err(|e| invalid(format!("Invalid state overrides: {}", e)))?;
+    let mut code_overrides = CodeOverrides::new();
+    for (address, account) in overrides {
+        let unsupported = account.unsupported();
+        if !unsupported.is_empty() {
+            return Err(invalid(format!(
+                "Invalid state overrides: {}: only code can be overridden, not {}",
+                address,
+                unsupported.join(", ")
+            )));
         }
+        if let Some(code) = account.code {
+            code_overrides.insert(address, code);
+        }
+    }
+    Ok(code_overrides)
+}
+
+/// `callTracer` options of the v2 `traceOptions` param (fifth)
+///
+/// Takes the `debug_traceCall` tracer options; `None` when the call tree isn't requested.
+fn call_tracer_param(id: &serde_json::Value, params: &serde_json::Value) -> Result<Option<TracerConfig>, JsonRpcError> {
+    let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+    let options = positional_params(id, params.clone())?.into_iter().nth(4);
+    let Some(options) = options.filter(|value| !value.is_null()) else {
+        return Ok(None);
     };
+    let options: TraceOptions =
+        deserialize_param(options).map_err(|e| invalid(format!("Invalid tracer options: {}", e)))?;
+    match options.tracer.as_deref() {
+        Some("callTracer") => Ok(Some(options.tracer_config)),
+        Some(other) => Err(invalid(format!("Unsupported tracer for estimates: {} (only callTracer)", other))),
+        None => Err(invalid("A tracer is required: callTracer".to_string())),
+    }
+}
+
+/// Headroom requested by the non-standard `gasMultiplier` and `gasBuffer` members
+///
+/// A member left out keeps its configured value; `None` if neither is set.
+fn headroom_param(params: &EthEstimateGasParams, configured: Headroom) -> Result<Option<Headroom>, String> {
+    if params.gas_multiplier.is_none() && params.gas_buffer.is_none() {
+        return Ok(None);
+    }
+    Headroom::new(
+        params.gas_multiplier.unwrap_or(configured.multiplier),
+        params.gas_buffer.unwrap_or(configured.buffer),
+    )
+    .map(Some)
+}
+
+/// Transaction and estimation options of `eth_estimateGas` params
+async fn estimate_params(
+    estimator: &GasEstimator,
+    id: &serde_json::Value,
+    params: serde_json::Value,
+) -> Result<(TransactionRequest, EstimateOptions), JsonRpcError> {
+    let block = block_target_param(id, &params)?;
+    let pins = block_pins_param(id, &params)?;
+    let code_overrides = code_overrides_param(id, &params)?;
+    let (tx_params, tx_request) = transaction_request_param(id, params).await?;
+    let options = EstimateOptions {
+        block,
+        pins,
+        code_overrides,
+        headroom: headroom_param(&tx_params, estimator.headroom())
+            .map_err(|e| JsonRpcError::invalid_params(id.clone(), e))?,
+        skip_authorization_check: tx_params.skip_authorization_check.unwrap_or(false),
+    };
+    Ok((tx_request, options))
+}
+
+/// `eth_estimateGas`: estimate the gas limit for a transaction
+///
+/// Params: `[transaction, block?, stateOverrides?, blockOverrides?]`; tracer
+/// options (a fifth param) are rejected, only the v2 estimate returns a call tree. A
+/// `pending` block estimates against the pending block, a `{"blockHash": ...}`
+/// block against that exact block, state overrides run
+/// the given `code` at their addresses, and block overrides pin `prevRandao`
+/// and `feeRecipient` for reproducible estimates. The
+/// transaction may carry `gasMultiplier` and `gasBuffer` to replace the
+/// configured headroom.
+fn eth_estimate_gas(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        // The bare gas limit has nowhere to carry a call tree: tracing is a v2 option
+        if positional_params(&id, params.clone())?.get(4).is_some_and(|options| !options.is_null()) {
+            return Err(JsonRpcError::invalid_params(
+                id,
+                "Tracer options are only accepted by /api/v2/eth/estimateGas".to_string(),
+            ));
+        }
+        let (tx_request, options) = estimate_params(estimator, &id, params).await?;
 
-    // Estimate gas using the service
-    match estimator.estimate_raw_gas(&tx_request).await {
-        Ok(gas_limit) => {
-            info!("Gas estimation successful: {}", gas_limit);
-            // Return successful response with the estimated gas limit
-            HttpResponse::Ok().json(JsonRpcSuccess::new(
-                request.id.clone(),
-                format_hex_u256(gas_limit),
-            ))
+        // Estimate gas using the service
+        match estimator.estimate_raw_gas_with(&tx_request, &options).await {
+            Ok(estimate) => {
+                info!("Gas estimation successful: {} (source: {:?})", estimate.gas, estimate.source);
+                // Return successful response with the estimated gas limit, tagged with its source
+                Ok(MethodOutput {
+                    result: serde_json::Value::String(format_hex_u256(estimate.gas)),
+                    source: Some(estimate.source),
+                    // Pending and stale estimates aren't tied to the current head
+                    cache: (options.block == BlockTarget::Latest && estimate.source != EstimateSource::Stale)
+                        .then(|| CacheScope::block(estimate.block_number, estimate.block_hash)),
+                    blob_gas: estimate.blob_gas,
+                })
+            }
+            Err(e) => {
+                error!("Gas estimation failed: {:?}", e);
+                // Return error response
+                Err(estimation_error(id, &e))
+            }
         }
-        Err(e) => {
-            error!("Gas estimation failed: {:?}", e);
-            // Return error response
-            HttpResponse::InternalServerError().json(JsonRpcError::internal_error(
-                request.id.clone(),
-                format!("Gas estimation failed: {}", e),
-            ))
+    })
+}
+
+/// `eth_estimateGas` on the v2 endpoint: the estimate with the execution outcome under it
+///
+/// Takes the same params as `eth_estimateGas`, then optional tracer options
+/// (`{"tracer": "callTracer", "tracerConfig": ...}`). The result carries `gas`,
+/// `source`, `status`, `gasUsed`, `returnData`, `logs`, for deployments
+/// `contractAddress` and, when traced, `callFrames` in `callTracer` format.
+fn eth_estimate_gas_extended(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let trace = call_tracer_param(&id, &params)?;
+        let (tx_request, options) = estimate_params(estimator, &id, params).await?;
+
+        match estimator.estimate_extended(&tx_request, &options, trace).await {
+            Ok(extended) => {
+                info!("Extended gas estimation successful: {} ({})", extended.gas, extended.status);
+                let source = extended.source;
+                // Pending estimates move with the mempool, they aren't tied to a block
+                let cache = (options.block == BlockTarget::Latest)
+                    .then(|| CacheScope::block(extended.block_number.to(), extended.block_hash));
+                let blob_gas = extended.blob_gas;
+                serde_json::to_value(extended)
+                    .map(|result| MethodOutput { result, source: Some(source), cache, blob_gas })
+                    .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to encode estimate: {}", e)))
+            }
+            Err(e) => {
+                error!("Extended gas estimation failed: {:?}", e);
+                Err(estimation_error(id, &e))
+            }
         }
+    })
+}
+
+/// `x_estimateGasDetailed`: gas estimate with the call tree and the gas used by each frame
+///
+/// Takes the same params as `eth_estimateGas`. The result carries the estimate
+/// (`gas`, `source`) and, for simulated estimates, `callFrames` in `callTracer`
+/// format recorded under the estimated limit.
+fn x_estimate_gas_detailed(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let (tx_request, options) = estimate_params(estimator, &id, params).await?;
+
+        match estimator.estimate_detailed(&tx_request, &options).await {
+            Ok(detailed) => {
+                let source = detailed.source;
+                let cache = CacheScope::block(detailed.block_number.to(), detailed.block_hash);
+                let blob_gas = detailed.blob_gas;
+                serde_json::to_value(detailed)
+                    .map(|result| MethodOutput { result, source: Some(source), cache: Some(cache), blob_gas })
+                    .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to encode estimate: {}", e)))
+            }
+            Err(e) => {
+                error!("Detailed gas estimation failed: {:?}", e);
+                Err(estimation_error(id, &e))
+            }
+        }
+    })
+}
+
+/// `x_explainEstimate`: gas estimate with an explanation of what it is made of
+///
+/// Takes the `eth_estimateGas` transaction object, then optional options
+/// (`{"summary": true}` adds a plain-text summary, `{"profile": true}` the gas
+/// per opcode and per contract) and `stateOverrides` as in `eth_estimateGas`.
+/// The result splits the gas used into intrinsic
+/// gas, execution gas by kind of operation and refunds, and reports the margin
+/// between the estimate and the gas used.
+fn x_explain_estimate(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let options: ExplainOptions = positional_params(&id, params.clone())?
+            .get(1)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| JsonRpcError::invalid_params(id.clone(), format!("Invalid explain options: {}", e)))?
+            .unwrap_or_default();
+        let code_overrides = code_overrides_param(&id, &params)?;
+        let (tx_params, tx_request) = transaction_request_param(&id, params).await?;
+        let estimate_options = EstimateOptions {
+            code_overrides,
+            skip_authorization_check: tx_params.skip_authorization_check.unwrap_or(false),
+            ..EstimateOptions::default()
+        };
+
+        match estimator.explain_estimate(&tx_request, &options, &estimate_options).await {
+            Ok(explanation) => {
+                debug!("Estimate of {} gas has a margin of {}", explanation.gas, explanation.margin);
+                let source = explanation.source;
+                let cache = CacheScope::block(explanation.block_number.to(), explanation.block_hash);
+                serde_json::to_value(explanation)
+                    .map(|result| MethodOutput { result, source: Some(source), cache: Some(cache), blob_gas: None })
+                    .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to encode explanation: {}", e)))
+            }
+            Err(e) => {
+                error!("Estimate explanation failed: {:?}", e);
+                Err(estimation_error(id, &e))
+            }
+        }
+    })
+}
+
+/// `eth_createAccessList`: generate the access list of a transaction and report its saving
+///
+/// Takes the same params as `eth_estimateGas`. Besides the standard `accessList`
+/// and `gasUsed` (the gas used with the list, without headroom), the result
+/// carries `gasUsedWithoutAccessList` and `gasSaved`, so clients can decide
+/// whether including the list is worth it.
+fn eth_create_access_list(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let (tx_request, options) = estimate_params(estimator, &id, params).await?;
+
+        match estimator.create_access_list(&tx_request, &options).await {
+            Ok(report) => {
+                info!("Access list generated, saving {} gas", report.gas_saved);
+                let source = report.source;
+                let cache = CacheScope::block(report.block_number.to(), report.block_hash);
+                serde_json::to_value(report)
+                    .map(|result| MethodOutput { result, source: Some(source), cache: Some(cache), blob_gas: None })
+                    .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to encode access list: {}", e)))
+            }
+            Err(e) => {
+                error!("Access list generation failed: {:?}", e);
+                Err(estimation_error(id, &e))
+            }
+        }
+    })
+}
+
+/// Map an estimation failure onto the matching JSON-RPC error
+fn estimation_error(id: serde_json::Value, e: &eyre::Report) -> JsonRpcError {
+    simulation_error(id, e, "Gas estimation failed")
+}
+
+/// Map a failed estimation, call or simulation onto the matching JSON-RPC error
+///
+/// # Arguments
+///
+/// * `context` - Prefix of the internal error message for failures without a dedicated code
+fn simulation_error(id: serde_json::Value, e: &eyre::Report, context: &str) -> JsonRpcError {
+    match e.downcast_ref::<ServiceError>() {
+        Some(ServiceError::Rejected(reason)) => JsonRpcError::transaction_rejected(id, reason.clone()),
+        Some(error @ ServiceError::ChainMismatch { connected, .. }) => chain_mismatch_error(id, error, *connected),
+        Some(ServiceError::Timeout(reason)) => JsonRpcError::timeout(id, reason.clone()),
+        Some(error @ ServiceError::InsufficientFunds(_)) => JsonRpcError::insufficient_funds(id, error.to_string()),
+        Some(error @ ServiceError::Reverted(data)) => JsonRpcError::execution_reverted(id, error.to_string(), data),
+        Some(ServiceError::InvalidParams(reason)) => JsonRpcError::invalid_params(id, reason.clone()),
+        Some(error @ ServiceError::UnsupportedChain(_)) => JsonRpcError::method_not_supported(id, error.to_string()),
+        Some(error @ ServiceError::Unavailable(_, retry_after)) => {
+            JsonRpcError::unavailable(id, error.to_string(), retry_after_secs(*retry_after))
+        }
+        _ => with_out_of_gas_frame(JsonRpcError::internal_error(id, format!("{}: {}", context, e)), e),
     }
 }
 
+/// Invalid params error for a transaction bound to another chain, quoting the served chain in `error.data.chainId`
+fn chain_mismatch_error(id: serde_json::Value, error: &ServiceError, connected: u64) -> JsonRpcError {
+    JsonRpcError::invalid_params(id, error.to_string())
+        .with_data_field("chainId", serde_json::json!(format!("{:#x}", connected)))
+}
+
+/// Attach the call frame that ran out of gas (if that's why `e` failed) as `error.data.outOfGasFrame`
+fn with_out_of_gas_frame(rpc_error: JsonRpcError, e: &eyre::Report) -> JsonRpcError {
+    match e.downcast_ref::<ServiceError>() {
+        Some(ServiceError::OutOfGas(frame)) => match serde_json::to_value(frame) {
+            Ok(frame) => rpc_error.with_data_field("outOfGasFrame", frame),
+            Err(_) => rpc_error,
+        },
+        _ => rpc_error,
+    }
+}
+
+/// `eth_call`: execute a call against the fork and return its output
+///
+/// Unlike estimation, calls don't require a funded sender: `from` defaults to
+/// the zero address, the gas limit defaults to the block gas limit, and balance
+/// and base fee checks are skipped, matching node behavior.
+/// Like `eth_estimateGas`, a `pending` block runs against the pending block,
+/// `stateOverrides` (third param) can override contract code and
+/// `blockOverrides` (fourth param) can pin `prevRandao` and `feeRecipient`.
+fn eth_call(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let block = block_target_param(&id, &params)?;
+        let pins = block_pins_param(&id, &params)?;
+        let code_overrides = code_overrides_param(&id, &params)?;
+        let (tx_params, mut tx_request) = transaction_request_param(&id, params).await?;
+        let options = EstimateOptions {
+            block,
+            pins,
+            code_overrides,
+            headroom: None,
+            skip_authorization_check: tx_params.skip_authorization_check.unwrap_or(false),
+        };
+        if tx_request.from.is_none() {
+            tx_request.from = Some(Address::ZERO);
+        }
+
+        match estimator.call_with(&tx_request, &options).await {
+            Ok(output) => Ok(serde_json::Value::String(output.to_string()).into()),
+            Err(e) => {
+                error!("eth_call failed: {:?}", e);
+                Err(simulation_error(id, &e, "Call failed"))
+            }
+        }
+    })
+}
+
+/// `estimator_simulate`: verbose simulation report for a transaction
+///
+/// Returns the block the simulation ran against, the execution status, gas
+/// used and output, plus a provenance signature when a signing key is configured.
+/// Params: `[transaction, options?]`; `{"stateDiff": true}` adds the state changes.
+fn estimator_simulate(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let options: SimulateOptions = positional_params(&id, params.clone())?
+            .get(1)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| JsonRpcError::invalid_params(id.clone(), format!("Invalid simulate options: {}", e)))?
+            .unwrap_or_default();
+        let (tx_params, tx_request) = transaction_request_param(&id, params).await?;
+        require_authorization_check(&tx_params).map_err(|e| JsonRpcError::invalid_params(id.clone(), e))?;
+
+        match estimator.simulate(&tx_request, options).await {
+            Ok(report) => serde_json::to_value(report)
+                .map(MethodOutput::from)
+                .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to encode report: {}", e))),
+            Err(e) => {
+                error!("Simulation failed: {:?}", e);
+                Err(estimation_error(id, &e))
+            }
+        }
+    })
+}
+
+/// `eth_chainId`: chain id of the connected chain, fetched once from the upstream node
+fn eth_chain_id(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    _params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let chain_id = estimator
+            .chain_id()
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id, e.to_string()))?;
+        Ok(serde_json::Value::String(format_hex_u256(U256::from(chain_id))).into())
+    })
+}
+
+/// `eth_blockNumber`: block of the fork estimates currently run on
+///
+/// This is the head estimates are made against, which trails the upstream node
+/// by at most the head lookup interval.
+fn eth_block_number(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    _params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let head = estimator
+            .forks
+            .latest()
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id, e.to_string()))?;
+        Ok(serde_json::Value::String(format_hex_u256(U256::from(head.block_number))).into())
+    })
+}
+
+/// `eth_gasPrice`: current gas price reported by the upstream node
+fn eth_gas_price(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    _params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let gas_price = estimator
+            .eth_client
+            .provider
+            .get_gas_price()
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to fetch gas price: {}", e)))?;
+        Ok(serde_json::Value::String(format_hex_u256(U256::from(gas_price))).into())
+    })
+}
+
+/// `eth_feeHistory`: fee history for a range of blocks, fetched from the upstream node
+///
+/// Params: `[blockCount, newestBlock, rewardPercentiles?]`
+fn eth_fee_history(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let params = positional_params(&id, params)?;
+        let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+
+        let block_count: U64 = params
+            .first()
+            .cloned()
+            .ok_or_else(|| invalid("Missing blockCount".to_string()))
+            .and_then(|value| deserialize_param(value).map_err(|e| invalid(format!("Invalid blockCount: {}", e))))?;
+        let newest_block: BlockNumberOrTag = params
+            .get(1)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid newestBlock: {}", e)))?
+            .unwrap_or(BlockNumberOrTag::Latest);
+        let reward_percentiles: Vec<f64> = params
+            .get(2)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid rewardPercentiles: {}", e)))?
+            .unwrap_or_default();
+
+        let fee_history = estimator
+            .eth_client
+            .provider
+            .get_fee_history(block_count.to(), newest_block, &reward_percentiles)
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id.clone(), format!("Failed to fetch fee history: {}", e)))?;
+        serde_json::to_value(fee_history)
+            .map(MethodOutput::from)
+            .map_err(|e| JsonRpcError::internal_error(id.clone(), format!("Failed to encode fee history: {}", e)))
+    })
+}
+
+/// `eth_getBalance`: balance of an account, served from the fork cache at the head block
+///
+/// Params: `[address, block?]`
+fn eth_get_balance(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let (address, block) = account_params(&id, params)?;
+        let account = estimator
+            .account_state(address, block)
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to fetch balance: {}", e)))?;
+        Ok(serde_json::Value::String(format_hex_u256(account.balance)).into())
+    })
+}
+
+/// `eth_getTransactionCount`: nonce of an account, served from the fork cache at the head block
+///
+/// Params: `[address, block?]`
+fn eth_get_transaction_count(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let (address, block) = account_params(&id, params)?;
+        let account = estimator
+            .account_state(address, block)
+            .await
+            .map_err(|e| JsonRpcError::internal_error(id, format!("Failed to fetch transaction count: {}", e)))?;
+        Ok(serde_json::Value::String(format_hex_u256(U256::from(account.nonce))).into())
+    })
+}
+
+/// Parse the `[address, block?]` params of an account query; the block defaults to `latest`
+fn account_params(id: &serde_json::Value, params: serde_json::Value) -> Result<(Address, BlockId), JsonRpcError> {
+    let params = positional_params(id, params)?;
+    let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+
+    let address: Address = params
+        .first()
+        .cloned()
+        .ok_or_else(|| invalid("Missing address".to_string()))
+        .and_then(|value| deserialize_param(value).map_err(|e| invalid(format!("Invalid address: {}", e))))?;
+    let block: BlockId = params
+        .get(1)
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| invalid(format!("Invalid block: {}", e)))?
+        .unwrap_or_default();
+    Ok((address, block))
+}
+
+/// `eth_simulateV1`: simulate several blocks of calls on top of the latest block
+///
+/// Params: `[{blockStateCalls, validation?}, block?]`. State carries over between
+/// calls and blocks; each block may override its number, timestamp, gas limit,
+/// fee recipient, base fee and prevrandao. Without `validation`, calls get
+/// `eth_call` semantics (no balance or base fee checks, `from` defaults to the
+/// zero address).
+fn eth_simulate_v1(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let params = positional_params(&id, params)?;
+        let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+
+        let payload: SimulatePayload = params
+            .first()
+            .cloned()
+            .ok_or_else(|| invalid("Missing simulation payload".to_string()))
+            .and_then(|value| deserialize_param(value).map_err(|e| invalid(format!("Invalid simulation payload: {}", e))))?;
+        let block: BlockId = params
+            .get(1)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid block: {}", e)))?
+            .unwrap_or_default();
+        if block != BlockId::Number(BlockNumberOrTag::Latest) {
+            return Err(invalid("Only simulations on top of the latest block are supported".to_string()));
+        }
+        if payload.trace_transfers {
+            return Err(invalid("traceTransfers is not supported".to_string()));
+        }
+
+        let mut blocks = Vec::with_capacity(payload.block_state_calls.len());
+        for (index, block) in payload.block_state_calls.into_iter().enumerate() {
+            if block.state_overrides.is_some() {
+                return Err(invalid(format!("block {}: stateOverrides are not supported", index)));
+            }
+            let overrides = BlockOverrides::from(block.block_overrides.unwrap_or_default());
+            let mut calls = build_transaction_requests(&block.calls).await.map_err(|e| match e {
+                ServiceError::InvalidParams(reason) => invalid(format!("block {}: {}", index, reason)),
+                other => invalid(format!("block {}: {}", index, other)),
+            })?;
+            if !payload.validation {
+                for call in calls.iter_mut().filter(|call| call.from.is_none()) {
+                    call.from = Some(Address::ZERO);
+                }
+            }
+            blocks.push(BlockCalls { overrides, calls });
+        }
+
+        match estimator.simulate_blocks(blocks, payload.validation).await {
+            Ok(simulated) => serde_json::to_value(simulated)
+                .map(MethodOutput::from)
+                .map_err(|e| JsonRpcError::internal_error(id.clone(), format!("Failed to encode simulation: {}", e))),
+            Err(e) => {
+                error!("Multi-block simulation failed: {:?}", e);
+                Err(simulation_error(id.clone(), &e, "Simulation failed"))
+            }
+        }
+    })
+}
+
+/// `eth_callBundle`: simulate a bundle of signed transactions, as Flashbots relays do
+///
+/// Params: `[bundle]` with `txs` (signed raw transactions) and `blockNumber`,
+/// plus optional `stateBlockNumber` (`latest` or `pending`), `timestamp`,
+/// `coinbase`, `baseFee` and `gasLimit` of the target block. Reports the gas
+/// used, outcome and coinbase payment of every transaction and of the bundle.
+fn eth_call_bundle(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+        let bundle: CallBundleParams = positional_params(&id, params)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| invalid("Missing bundle".to_string()))
+            .and_then(|value| deserialize_param(value).map_err(|e| invalid(format!("Invalid bundle: {}", e))))?;
+
+        match estimator.call_bundle(&bundle).await {
+            Ok(response) => serde_json::to_value(response)
+                .map(MethodOutput::from)
+                .map_err(|e| JsonRpcError::internal_error(id.clone(), format!("Failed to encode bundle result: {}", e))),
+            Err(e) => {
+                error!("Bundle simulation failed: {:?}", e);
+                Err(simulation_error(id.clone(), &e, "Bundle simulation failed"))
+            }
+        }
+    })
+}
+
+/// `debug_traceCall`: trace a call on the fork with `callTracer` or `prestateTracer`
+///
+/// Params: `[transaction, block?, {tracer, tracerConfig?, stateOverrides?}]`. The
+/// call runs with `eth_call` semantics on top of the latest block; as in geth,
+/// `stateOverrides` sits in the tracer options and may override `code`.
+fn debug_trace_call(
+    estimator: &GasEstimator,
+    id: serde_json::Value,
+    params: serde_json::Value,
+) -> LocalBoxFuture<'_, MethodResult> {
+    Box::pin(async move {
+        let positional = positional_params(&id, params.clone())?;
+        let invalid = |message: String| JsonRpcError::invalid_params(id.clone(), message);
+
+        let block: BlockId = positional
+            .get(1)
+            .cloned()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid block: {}", e)))?
+            .unwrap_or_default();
+        if block != BlockId::Number(BlockNumberOrTag::Latest) {
+            return Err(invalid("Only traces at the latest block are supported".to_string()));
+        }
+        let mut options = positional.get(2).cloned();
+        let state_overrides = options
+            .as_mut()
+            .and_then(serde_json::Value::as_object_mut)
+            .and_then(|options| options.remove("stateOverrides"));
+        let code_overrides = code_overrides(&id, state_overrides)?;
+        let options: TraceOptions = options
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| invalid(format!("Invalid tracer options: {}", e)))?
+            .unwrap_or_default();
+
+        let (tx_params, mut tx_request) = transaction_request_param(&id, params).await?;
+        let estimate_options = EstimateOptions {
+            code_overrides,
+            skip_authorization_check: tx_params.skip_authorization_check.unwrap_or(false),
+            ..EstimateOptions::default()
+        };
+        if tx_request.from.is_none() {
+            tx_request.from = Some(Address::ZERO);
+        }
+
+        match estimator.trace_call(&tx_request, &options, &estimate_options).await {
+            Ok(trace) => serde_json::to_value(trace)
+                .map(MethodOutput::from)
+                .map_err(|e| JsonRpcError::internal_error(id.clone(), format!("Failed to encode trace: {}", e))),
+            Err(e) => {
+                error!("Trace failed: {:?}", e);
+                Err(simulation_error(id.clone(), &e, "Trace failed"))
+            }
+        }
+    })
+}
+
+/// Map a JSON-RPC error onto the HTTP status used by the single-request endpoint
+///
+/// `-32000` is the generic server error code nodes report many failures with;
+/// only the insufficient funds error is the client's fault.
+fn error_status(rpc_error: &JsonRpcError) -> StatusCode {
+    match rpc_error.error.code {
+        3 | -32600 | -32602 | -32700 => StatusCode::BAD_REQUEST,
+        -32000 if rpc_error.is_insufficient_funds() => StatusCode::BAD_REQUEST,
+        -32601 => StatusCode::NOT_FOUND,
+        -32003 | -32004 => StatusCode::FORBIDDEN,
+        -32005 => StatusCode::TOO_MANY_REQUESTS,
+        -32050 => StatusCode::GATEWAY_TIMEOUT,
+        -32051 => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Whether a JSON-RPC error is the server's fault rather than the client's
+fn is_server_error(rpc_error: &JsonRpcError) -> bool {
+    error_status(rpc_error).is_server_error()
+}
+
+/// Serialize a value as one newline-terminated NDJSON record
+///
+/// # Returns
+///
+/// * `Result<web::Bytes, serde_json::Error>` - The record, or the serialization error, which
+///   aborts a stream rather than writing an empty line
+fn ndjson_line<T: Serialize>(value: &T) -> Result<web::Bytes, serde_json::Error> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    Ok(web::Bytes::from(line))
+}
+
+/// Paths of the health checks and probes, which are never rate limited or gated by API key
+pub const PROBE_PATHS: &[&str] = &["/api/v1/health", "/livez", "/readyz", "/healthz"];
+
 /// Service health check endpoint that verifies RPC connection is working
+///
+/// While the upstream node is unreachable but the degradation ladder can still
+/// answer estimates, the service reports itself `degraded` rather than failing.
 #[post("/api/v1/health")]
 async fn health_check(
     estimator: web::Data<Arc<GasEstimator>>,
@@ -170,58 +1447,196 @@ async fn health_check(
     info!("Health check requested");
 
     // Try to get the latest block to verify RPC connection is working
-    let eth_client = &estimator.eth_client;
-    match eth_client.get_latest_block().await {
+    let latest = estimator
+        .eth_client
+        .provider
+        .get_block(BlockId::latest())
+        .await
+        .map_err(|e| upstream_failure("Failed to get latest block", e))
+        .and_then(|block| block.ok_or_else(|| ServiceError::RPCConnection("No latest block returned".to_string())));
+    match latest {
         Ok(block) => {
+            let simulations = estimator.forks.simulation_stats();
             // Return health status along with latest block info
             let response = serde_json::json!({
                 "status": "ok",
                 "latest_block": block.header.number,
                 "timestamp": block.header.timestamp,
+                "cache": estimator.estimate_cache.as_ref().map(|cache| cache.stats()),
+                "simulation_panics": simulations.panics,
+                "simulations": simulations,
+                "reorgs": estimator.forks.reorgs(),
             });
             Ok(HttpResponse::Ok().json(response))
         }
+        Err(e) if estimator.can_degrade() => {
+            warn!("Health check degraded: {}", e);
+            Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "degraded", "reason": e.to_string() })))
+        }
         Err(e) => {
             error!("Health check failed: {:?}", e);
-            Err(ServiceError::RPCConnection(format!("RPC connection error: {}", e)))
+            Err(e)
+        }
+    }
+}
+
+/// Liveness probe: the process is up and serving requests
+///
+/// Never touches the upstream, so an upstream outage doesn't get the
+/// replica restarted.
+#[get("/livez")]
+async fn livez() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness probe: the upstream is reachable
+///
+/// Only the head's number is looked up, through the fork manager, so probes
+/// arriving shortly after a request share its lookup and never build a fork.
+#[get("/readyz")]
+async fn readyz(estimator: web::Data<Arc<GasEstimator>>) -> HttpResponse {
+    readiness(&estimator).await
+}
+
+/// Readiness under the conventional aggregate health path, for load balancers
+#[get("/healthz")]
+async fn healthz(estimator: web::Data<Arc<GasEstimator>>) -> HttpResponse {
+    readiness(&estimator).await
+}
+
+/// Answer a readiness probe: `200` with the head block, `200` degraded while the
+/// degradation ladder can serve estimates, or `503` with the reason
+async fn readiness(estimator: &GasEstimator) -> HttpResponse {
+    match estimator.forks.head_number().await {
+        Ok(block) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "ok",
+            "block": block,
+        })),
+        Err(e) if estimator.can_degrade() => {
+            warn!("Readiness check degraded: {}", e);
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "degraded",
+                "reason": e.to_string(),
+            }))
+        }
+        Err(e) => {
+            error!("Readiness check failed: {}", e);
+            HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "status": "unavailable",
+                "reason": e.to_string(),
+            }))
         }
     }
 }
 
 /// Configure the API routes for the service
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(estimate_gas_jsonrpc)
-       .service(health_check);
+    cfg.app_data(web::JsonConfig::default().error_handler(json_payload_error))
+       .service(jsonrpc_resource("/").route(web::post().to(jsonrpc_dispatch)))
+       .service(crate::ws::ws_jsonrpc)
+       .service(jsonrpc_resource("/api/v1/eth/estimateGas/stream").route(web::post().to(estimate_gas_stream)))
+       .service(jsonrpc_resource("/api/v1/eth/estimateGas").route(web::post().to(estimate_gas_jsonrpc)))
+       .service(jsonrpc_resource("/api/v2/eth/estimateGas").route(web::post().to(estimate_gas_extended_jsonrpc)))
+       .service(health_check)
+       .service(livez)
+       .service(readyz)
+       .service(healthz);
+}
+
+/// A resource whose request bodies are JSON-RPC payloads
+fn jsonrpc_resource(path: &str) -> actix_web::Resource {
+    web::resource(path).app_data(web::JsonConfig::default().error_handler(jsonrpc_payload_error))
+}
+
+/// Answer REST request bodies that can't be read as JSON with `INVALID_PARAMS`
+fn json_payload_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ServiceError::InvalidParams(err.to_string()).into()
+}
+
+/// Answer JSON-RPC request bodies that can't be read as JSON
+///
+/// Malformed JSON is answered with `-32700 Parse error` and bodies of the
+/// wrong shape, content type or size with `-32600 Invalid request`, with a
+/// null `id` as the request couldn't be read.
+fn jsonrpc_payload_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let rpc_error = match &err {
+        JsonPayloadError::Deserialize(e) if e.classify() != Category::Data => {
+            JsonRpcError::parse_error(serde_json::Value::Null, format!("Parse error: {}", e))
+        }
+        _ => JsonRpcError::invalid_request(serde_json::Value::Null, format!("Invalid request: {}", err)),
+    };
+    let rpc_error = match correlation_id() {
+        Some(correlation_id) => rpc_error.with_data_field("correlationId", serde_json::json!(correlation_id)),
+        None => rpc_error,
+    };
+    let response = HttpResponse::build(error_status(&rpc_error)).json(rpc_error);
+    InternalError::from_response(err, response).into()
+}
+
+/// Build the transaction requests of a multi-transaction simulation (ladders, bundles)
+pub(crate) async fn build_transaction_requests(
+    params: &[EthEstimateGasParams],
+) -> Result<Vec<TransactionRequest>, ServiceError> {
+    let mut tx_requests = Vec::with_capacity(params.len());
+    for (index, tx_params) in params.iter().enumerate() {
+        let invalid = |e: String| ServiceError::InvalidParams(format!("transaction {}: {}", index, e));
+        require_authorization_check(tx_params).map_err(invalid)?;
+        let tx_request = build_transaction_request(tx_params).await.map_err(invalid)?;
+        tx_requests.push(tx_request);
+    }
+    Ok(tx_requests)
+}
+
+/// Reject `skipAuthorizationCheck` on methods that always recover authorization signers
+///
+/// Only single-transaction estimates, calls and traces carry the opt-out to
+/// the estimator; elsewhere it would be dropped and the request rejected over
+/// a signature the caller asked not to check.
+fn require_authorization_check(params: &EthEstimateGasParams) -> Result<(), String> {
+    if params.skip_authorization_check.unwrap_or(false) {
+        return Err("skipAuthorizationCheck is only accepted by single-transaction estimates, calls and traces".to_string());
+    }
+    Ok(())
+}
+
+/// Most blobs a `blobCount` may plan for: the per-transaction maximum of the latest
+/// hardfork; the simulated fork's own maximum is checked when simulating
+const MAX_BLOB_COUNT: u64 = BlobParams::PRAGUE.max;
+
+/// Placeholder versioned hashes standing in for `count` blobs
+///
+/// Each hash carries the KZG version byte (so it passes validation) followed by
+/// the blob index, making the hashes distinct as `BLOBHASH` would see them.
+fn placeholder_blob_hashes(count: u64) -> Vec<B256> {
+    (0..count)
+        .map(|index| {
+            let mut hash = B256::left_padding_from(&index.to_be_bytes());
+            hash[0] = VERSIONED_HASH_VERSION_KZG;
+            hash
+        })
+        .collect()
 }
 
 /// Build a transaction request from JSON-RPC parameters
 ///
 /// This function converts the JSON-RPC parameters into an Alloy TransactionRequest,
-/// validating and parsing each field as needed.
+/// filling in defaults and checking the constraints between fields; the fields
+/// themselves were already parsed when the parameters were deserialized.
 ///
 /// While this may seem redundant, its important as I wanted to build this leveraging Alloy
 /// due to the inherent speed and optimisation benefits and the future REVM interoperability.
 ///
-async fn build_transaction_request(
+pub(crate) async fn build_transaction_request(
     params: &EthEstimateGasParams,
 ) -> Result<TransactionRequest, String> {
     let mut tx_request = TransactionRequest::default();
     debug!("Building transaction request with params: {:?}", params);
 
-    // Parse and set the from address
-    if let Some(from_str) = &params.from {
-        debug!("Parsing 'from' address: {}", from_str);
-        let from = parse_hex_address(from_str)?;
-        tx_request.from = Some(from);
-        debug!("Parsed 'from' address: {:?}", from);
-    }
+    tx_request.from = params.from;
 
-    // Parse and set the to address (required for contract calls, optional for deployments)
-    if let Some(to_str) = &params.to {
-        debug!("Parsing 'to' address: {}", to_str);
-        let to = parse_hex_address(to_str)?;
+    // The to address is required for contract calls, optional for deployments
+    if let Some(to) = params.to {
         tx_request.to = Some(to.into());
-        debug!("Parsed 'to' address: {:?}", to);
     } else if params.input.is_none() {
         // Either 'to' or 'input' is required for a valid transaction
         let error_msg = "Either 'to' or 'input' must be provided";
@@ -229,147 +1644,50 @@ async fn build_transaction_request(
         return Err(error_msg.to_string());
     }
 
-    // Parse and set the gas limit (optional)
-    if let Some(gas_str) = &params.gas {
-        debug!("Parsing gas limit: {}", gas_str);
-        let gas = parse_hex_u64(gas_str)?;
-        tx_request.gas = Some(gas);
-        debug!("Parsed gas limit: {}", gas);
-    } else {
-        // Use default gas limit if not provided
-        debug!("No gas limit provided, using default: {}", DEFAULT_GAS_LIMIT);
-        tx_request.gas = Some(DEFAULT_GAS_LIMIT);
-    }
+    // A missing gas limit is left to the simulation, which caps it by the block gas limit
+    tx_request.gas = params.gas;
 
-    // Parse and set the transaction value (optional)
-    if let Some(value_str) = &params.value {
-        debug!("Parsing value: {}", value_str);
-        let value = parse_hex_u256(value_str)?;
-        tx_request.value = Some(value);
-        debug!("Parsed value: {:?}", value);
-    } else {
-        // Default to zero value if not provided
-        debug!("No value provided, defaulting to U256::ZERO");
-        tx_request.value = Some(U256::ZERO);
-    }
+    // Default to zero value and empty input if not provided
+    tx_request.value = Some(params.value.unwrap_or(U256::ZERO));
+    tx_request.input = TransactionInput::from(params.input.clone().unwrap_or_default());
 
-    // Parse and set the input data (optional)
-    if let Some(input_str) = &params.input {
-        debug!("Parsing input data: {}", input_str);
-        let input_data = parse_hex_bytes(input_str)?;
-        tx_request.input = TransactionInput::from(input_data.clone());
-        debug!("Parsed input data: {:?}", input_data);
-    } else {
-        // Default to empty input if not provided
-        debug!("No input data provided, using empty Bytes");
-        tx_request.input = TransactionInput::from(Bytes::new());
-    }
-
-    // Handle gas pricing - this can be legacy (gasPrice) or EIP-1559 (maxFeePerGas and maxPriorityFeePerGas)
-    if let Some(gas_price_str) = &params.gas_price {
-        debug!("Parsing legacy gas price: {}", gas_price_str);
-        let gas_price = parse_hex_u256(gas_price_str)?;
-        if let Ok(price) = u128::try_from(gas_price) {
-            tx_request.gas_price = Some(price);
-            debug!("Parsed legacy gas price: {}", price);
-        } else {
-            debug!("Failed to convert gas price to u128");
-        }
-    } else if let (Some(max_fee_str), Some(priority_fee_str)) = (&params.max_fee_per_gas, &params.max_priority_fee_per_gas) {
-        debug!("Parsing EIP-1559 gas pricing: maxFeePerGas: {}, maxPriorityFeePerGas: {}", max_fee_str, priority_fee_str);
-        let max_fee = parse_hex_u256(max_fee_str)?;
-        let priority_fee = parse_hex_u256(priority_fee_str)?;
-        
-        // Convert to u128 for the transaction request
-        if let Ok(max_fee_u128) = u128::try_from(max_fee) {
-            tx_request.max_fee_per_gas = Some(max_fee_u128);
-            debug!("Parsed max fee per gas: {}", max_fee_u128);
-        } else {
-            debug!("Failed to convert max fee per gas to u128");
-        }
-        
-        if let Ok(priority_fee_u128) = u128::try_from(priority_fee) {
-            tx_request.max_priority_fee_per_gas = Some(priority_fee_u128);
-            debug!("Parsed max priority fee per gas: {}", priority_fee_u128);
-        } else {
-            debug!("Failed to convert max priority fee per gas to u128");
-        }
-    } else {
-        // Use default gas price if none provided
-        debug!("No gas pricing provided, defaulting to gas price: {}", DEFAULT_GAS_PRICE);
-        let gas_price = DEFAULT_GAS_PRICE;
-        let price = u128::from(gas_price);
-        tx_request.gas_price = Some(price);
-        debug!("Parsed legacy gas price: {}", price);
-    }
-
-    // Handle additional transaction fields - nonce and chain_id
-    if let Some(nonce_str) = &params.nonce {
-        debug!("Parsing nonce: {}", nonce_str);
-        let nonce_u64 = parse_hex_u64(nonce_str)?;
-        tx_request.nonce = Some(nonce_u64);
-        debug!("Parsed nonce: {}", nonce_u64);
-    }
-
-    if let Some(chainid_str) = &params.chain_id {
-        debug!("Parsing chainId: {}", chainid_str);
-        let chainid_u64 = parse_hex_u64(chainid_str)?;
-        tx_request.chain_id = Some(chainid_u64);
-        debug!("Parsed chainId: {}", chainid_u64);
-    }
-
-    // Handle block parameter (defaults to latest)
-    let _block_tag = params.block.as_deref().unwrap_or("latest");
-    debug!("Using block tag: {}", _block_tag);
-    // Note: block parameter is used to replicate eth spec, but right now we always default to the latest - !TODO: implement arbitrary block requests
-
-    if let Some(access_list_vec) = &params.access_list {
-        let mut items = Vec::new();
-        for entry in access_list_vec {
-            let address = parse_hex_address(&entry.address)?;
-            let storage_keys = entry
-                .storage_keys
-                .iter()
-                .map(|key_str| parse_hex_b256(key_str))
-                .collect::<Result<Vec<B256>, _>>()?;
-            items.push(AccessListItem { address, storage_keys });
-        }
-        tx_request.access_list = Some(AccessList(items.clone()));
-        debug!("Parsed accessList with {} items", items.len());
+    // Gas pricing is legacy (gasPrice) or EIP-1559 (maxFeePerGas and maxPriorityFeePerGas), never both;
+    // a request without any runs at a zero gas price, as on a node
+    if params.gas_price.is_some() && (params.max_fee_per_gas.is_some() || params.max_priority_fee_per_gas.is_some()) {
+        return Err("Both gasPrice and (maxFeePerGas or maxPriorityFeePerGas) specified".to_string());
     }
+    tx_request.gas_price = params.gas_price;
+    tx_request.max_fee_per_gas = params.max_fee_per_gas;
+    tx_request.max_priority_fee_per_gas = params.max_priority_fee_per_gas;
+
+    tx_request.nonce = params.nonce;
+    tx_request.chain_id = params.chain_id;
 
-    // Transaction type (EIP-2718)
-    if let Some(tx_type_str) = &params.transaction_type {
-        debug!("Parsing transaction type: {}", tx_type_str);
-        let tx_type_u8 = parse_hex_or_dec_u8(tx_type_str)?;
-        tx_request.transaction_type = Some(tx_type_u8);
-        debug!("Parsed transactionType: {}", tx_type_u8);
+    // The block is the method's second param, not part of the transaction
+    if params.block.is_some() {
+        return Err("block is not a transaction field: pass the block as the second param".to_string());
     }
 
+    tx_request.access_list = params.access_list.clone();
+    tx_request.transaction_type = params.transaction_type;
+
     // EIP-4844: blobVersionedHashes
-    if let Some(hashes_rpc) = &params.blob_versioned_hashes {
-        debug!("Parsing blob versioned hashes");
-        let mut hashes = Vec::new();
-        for hash_str in hashes_rpc {
-            debug!("Parsing hash: {}", hash_str);
-            let h = parse_hex_b256(hash_str)
-                .map_err(|e| {
-                    debug!("Failed to parse hash {}: {:?}", hash_str, e);
-                    e
-                })?;
-            hashes.push(h);
-        }
-        tx_request.blob_versioned_hashes = Some(hashes.clone());
-        debug!("Parsed {} blob versioned hashes", hashes.len());
+    tx_request.blob_versioned_hashes = params.blob_versioned_hashes.clone();
+
+    // EIP-4844: blobCount (non-standard), placeholder hashes for planning
+    if let Some(count) = params.blob_count {
+        if params.blob_versioned_hashes.is_some() || params.sidecar.is_some() {
+            return Err("blobCount can't be combined with blobVersionedHashes or sidecar".to_string());
+        }
+        if count == 0 || count > MAX_BLOB_COUNT {
+            return Err(format!("blobCount must be between 1 and {}", MAX_BLOB_COUNT));
+        }
+        tx_request.blob_versioned_hashes = Some(placeholder_blob_hashes(count));
+        debug!("Generated {} placeholder blob versioned hashes", count);
     }
 
     // EIP-4844: maxFeePerBlobGas
-    if let Some(max_fee_blob_rpc) = &params.max_fee_per_blob_gas {
-        debug!("Parsing max fee per blob gas");
-        let max_fee_blob = parse_hex_u64(max_fee_blob_rpc)?;
-        tx_request.max_fee_per_blob_gas = Some(max_fee_blob.into());
-        debug!("Parsed max fee per blob gas: {:?}", max_fee_blob);
-    }
+    tx_request.max_fee_per_blob_gas = params.max_fee_per_blob_gas;
 
     // sidecar
     if let Some(sidecar_rpc) = &params.sidecar {
@@ -380,16 +1698,10 @@ async fn build_transaction_request(
         debug!("Parsed sidecar: {:?}", sidecar);
     }
 
-    // EIP-7702: authorizationList
-    if let Some(auth_list_rpc) = &params.authorization_list {
-        // Convert each item from the “AuthRpc” to the actual “SignedAuthorization”
-        let mut parsed_auth = Vec::new();
-        for auth_rpc_item in auth_list_rpc {
-            let item = auth_rpc_item.to_authorization()?;
-            parsed_auth.push(item);
-        }
-        tx_request.authorization_list = Some(parsed_auth.clone());
-        debug!("Parsed {} items in authorizationList", parsed_auth.len());
+    // EIP-7702: authorizationList, whose signers the estimator recovers before simulating
+    if let Some(authorization_list) = &params.authorization_list {
+        tx_request.authorization_list = Some(authorization_list.clone());
+        debug!("Parsed {} items in authorizationList", authorization_list.len());
     }
 
     debug!("Transaction request built: {:?}", tx_request);
@@ -0,0 +1,193 @@
+//! Withdrawal batching optimizer
+//!
+//! Exchanges and payroll operators regularly ask whether paying out a set of
+//! transfers one transaction at a time is cheaper than a single call to a
+//! multisend/disperse contract. Both strategies are simulated against the same
+//! fork of the head, whose state also tells whether the disperse contract is
+//! deployed, and the total gas of each is reported.
+
+use crate::{
+    api::request_body,
+    error::ServiceError,
+    estimator::{execution_summary, GasEstimator},
+    foundry::{has_code_on_fork, Fork},
+    models::{
+        batching::{BatchedStrategy, PerTransactionStrategy, WithdrawalBatchReport, WithdrawalBatchRequest},
+    },
+};
+use actix_web::{post, web, HttpResponse};
+use alloy::{
+    primitives::{address, Address, Bytes, U256},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// The Disperse contract (disperse.app), deployed at the same address on most EVM chains
+pub const DEFAULT_DISPERSE_CONTRACT: Address = address!("D152f549545093347A162Dce210e7293f1452150");
+
+sol! {
+    /// Disperse contract entry points
+    function disperseEther(address[] recipients, uint256[] values) external payable;
+    function disperseToken(address token, address[] recipients, uint256[] values) external;
+
+    /// ERC-20 functions used by the payouts
+    function transfer(address to, uint256 amount) external returns (bool);
+    function approve(address spender, uint256 amount) external returns (bool);
+}
+
+/// Compare per-transaction payouts with a single disperse call
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator to simulate with
+/// * `from` - Wallet paying out the withdrawals
+/// * `token` - ERC-20 token being withdrawn, or `None` for native ETH
+/// * `disperse` - Disperse contract to compare against
+/// * `withdrawals` - Recipients and amounts
+///
+/// # Returns
+///
+/// * `Result<WithdrawalBatchReport, ServiceError>` - Gas of both strategies, or an error
+pub async fn compare_withdrawal_strategies(
+    estimator: &GasEstimator,
+    from: Address,
+    token: Option<Address>,
+    disperse: Address,
+    withdrawals: &[(Address, U256)],
+) -> Result<WithdrawalBatchReport, ServiceError> {
+    let fork = estimator.forks.latest().await?;
+
+    // Strategy 1: one transaction per withdrawal, sent back-to-back
+    let per_tx_requests: Vec<TransactionRequest> = withdrawals
+        .iter()
+        .map(|(to, amount)| match token {
+            Some(token) => TransactionRequest::default()
+                .from(from)
+                .to(token)
+                .input(Bytes::from(transferCall { to: *to, amount: *amount }.abi_encode()).into()),
+            None => TransactionRequest::default().from(from).to(*to).value(*amount),
+        })
+        .collect();
+    let per_tx = estimator
+        .simulate_sequence_at(&fork, &per_tx_requests)
+        .await
+        .map_err(ServiceError::from_report)?;
+    let mut per_tx_gas = U256::ZERO;
+    let mut failed = 0;
+    for result in per_tx.results {
+        let (status, gas_used, _) = execution_summary(result);
+        per_tx_gas += U256::from(gas_used);
+        if status != "success" {
+            failed += 1;
+        }
+    }
+    let per_transaction = PerTransactionStrategy {
+        transactions: withdrawals.len(),
+        total_gas: per_tx_gas,
+        failed,
+    };
+
+    // Strategy 2: a single disperse call (plus an approval for tokens)
+    let batched = if has_code_on_fork(&fork, disperse).await? {
+        Some(simulate_batched(estimator, &fork, from, token, disperse, withdrawals).await?)
+    } else {
+        debug!("No disperse contract deployed at {}, skipping batched strategy", disperse);
+        None
+    };
+
+    let savings_gas = batched.as_ref().filter(|batched| batched.status == "success").map(|batched| {
+        i128::try_from(per_tx_gas).unwrap_or(i128::MAX) - i128::try_from(batched.total_gas).unwrap_or(i128::MAX)
+    });
+    let recommended = match savings_gas {
+        Some(savings) if savings > 0 => "batched",
+        _ => "perTransaction",
+    };
+
+    Ok(WithdrawalBatchReport {
+        block_number: per_tx.block_number,
+        recipients: withdrawals.len(),
+        per_transaction,
+        batched,
+        savings_gas,
+        recommended,
+    })
+}
+
+/// Simulate the disperse strategy
+async fn simulate_batched(
+    estimator: &GasEstimator,
+    fork: &Fork,
+    from: Address,
+    token: Option<Address>,
+    disperse: Address,
+    withdrawals: &[(Address, U256)],
+) -> Result<BatchedStrategy, ServiceError> {
+    let recipients: Vec<Address> = withdrawals.iter().map(|(to, _)| *to).collect();
+    let values: Vec<U256> = withdrawals.iter().map(|(_, amount)| *amount).collect();
+    let total: U256 = values.iter().copied().sum();
+
+    let requests = match token {
+        Some(token) => vec![
+            TransactionRequest::default()
+                .from(from)
+                .to(token)
+                .input(Bytes::from(approveCall { spender: disperse, amount: total }.abi_encode()).into()),
+            TransactionRequest::default()
+                .from(from)
+                .to(disperse)
+                .input(Bytes::from(disperseTokenCall { token, recipients, values }.abi_encode()).into()),
+        ],
+        None => vec![TransactionRequest::default()
+            .from(from)
+            .to(disperse)
+            .value(total)
+            .input(Bytes::from(disperseEtherCall { recipients, values }.abi_encode()).into())],
+    };
+
+    let sequence = estimator.simulate_sequence_at(fork, &requests).await.map_err(ServiceError::from_report)?;
+    let mut summaries: Vec<_> = sequence.results.into_iter().map(execution_summary).collect();
+    let (status, batch_gas, _) = summaries.pop().unwrap_or(("halt", 0, Bytes::new()));
+    let approval_gas = summaries.pop().map(|(_, gas_used, _)| U256::from(gas_used));
+
+    Ok(BatchedStrategy {
+        contract: disperse,
+        approval_gas,
+        batch_gas: U256::from(batch_gas),
+        total_gas: U256::from(batch_gas) + approval_gas.unwrap_or_default(),
+        status,
+    })
+}
+
+/// Compare per-transaction withdrawals with a single disperse call
+#[post("/api/v1/batching/withdrawals")]
+async fn compare_withdrawals(
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let request: WithdrawalBatchRequest = request_body(body)?;
+    if request.withdrawals.is_empty() {
+        return Err(ServiceError::InvalidParams("At least one withdrawal is required".to_string()));
+    }
+
+    let (from, token) = (request.from, request.token);
+    let disperse = request.disperse_contract.unwrap_or(DEFAULT_DISPERSE_CONTRACT);
+    let withdrawals: Vec<(Address, U256)> =
+        request.withdrawals.iter().map(|withdrawal| (withdrawal.to, withdrawal.amount)).collect();
+
+    info!("Comparing payout strategies for {} withdrawals from {}", withdrawals.len(), from);
+    let report = compare_withdrawal_strategies(&estimator, from, token, disperse, &withdrawals)
+        .await
+        .map_err(|e| {
+            error!("Withdrawal batching comparison failed: {:?}", e);
+            e
+        })?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Configure the batching routes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(compare_withdrawals);
+}
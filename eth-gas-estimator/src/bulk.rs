@@ -0,0 +1,101 @@
+//! Bulk estimation of independent transactions
+//!
+//! Portfolio and airdrop tooling often needs estimates for hundreds of
+//! unrelated transactions at once. Instead of one HTTP call per transaction,
+//! the bulk endpoint estimates them all against a single fork of the latest
+//! head, so the fork's cached state is shared and every estimate holds for the
+//! same block. Unlike the nonce ladder, the transactions don't see each other's
+//! effects.
+
+use crate::{
+    api::{build_transaction_requests, request_body},
+    error::ServiceError,
+    estimator::GasEstimator,
+    metrics::InBandServerError,
+    models::{
+        bulk::{BulkEstimateItem, BulkEstimateResponse},
+        jsonrpc::{BatchSummary, EthEstimateGasParams},
+    },
+    rate_limit::charge_batch,
+};
+use actix_web::{post, web, HttpRequest, HttpResponse, ResponseError};
+use alloy::primitives::U64;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Most transactions a single bulk request may carry
+pub const MAX_BULK_TRANSACTIONS: usize = 1_000;
+
+/// Estimate independent transactions against one shared fork
+///
+/// Malformed transactions reject the whole request; transactions that fail
+/// estimation carry their own `error` and leave the others unaffected.
+#[post("/api/v1/eth/estimateGas/bulk")]
+async fn estimate_gas_bulk(
+    req: HttpRequest,
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let params: Vec<EthEstimateGasParams> = request_body(body)?;
+    if params.is_empty() {
+        return Err(ServiceError::InvalidParams("Request must contain at least one transaction".to_string()));
+    }
+    if params.len() > MAX_BULK_TRANSACTIONS {
+        return Err(ServiceError::InvalidParams(format!(
+            "Request may contain at most {} transactions",
+            MAX_BULK_TRANSACTIONS
+        )));
+    }
+    // Each transaction costs what a request of its own would
+    if let Some(limited) = charge_batch(&req, params.len()) {
+        return Ok(limited);
+    }
+
+    let tx_requests = build_transaction_requests(&params).await?;
+
+    info!("Estimating {} transactions in bulk", tx_requests.len());
+    let bulk = estimator.estimate_bulk(&tx_requests).await.map_err(|e| {
+        error!("Bulk estimation failed: {:?}", e);
+        ServiceError::from_report(e)
+    })?;
+
+    let mut server_error = false;
+    let results: Vec<BulkEstimateItem> = bulk
+        .results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| match result {
+            Ok(estimate) => BulkEstimateItem {
+                index,
+                gas: Some(estimate.gas),
+                source: Some(estimate.source),
+                error: None,
+            },
+            Err(e) => {
+                let e = ServiceError::from_report(e);
+                server_error |= e.status_code().is_server_error();
+                BulkEstimateItem {
+                    index,
+                    gas: None,
+                    source: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        })
+        .collect();
+    let failed_count = results.iter().filter(|item| item.error.is_some()).count();
+
+    let mut response = HttpResponse::Ok().json(BulkEstimateResponse {
+        block_number: U64::from(bulk.block_number),
+        block_hash: bulk.block_hash,
+        summary: BatchSummary::new(results.len(), failed_count),
+        results,
+    });
+    InBandServerError::mark(&mut response, server_error);
+    Ok(response)
+}
+
+/// Configure the bulk estimation route
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(estimate_gas_bulk);
+}
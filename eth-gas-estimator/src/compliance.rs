@@ -0,0 +1,313 @@
+//! Address screening
+//!
+//! An optional compliance hook that checks the addresses referenced by a
+//! request against configurable allow/deny lists before anything is simulated,
+//! and the accounts its simulated execution touched (contracts called along the
+//! way included) against the deny list once it has been.
+//! Lists are loaded from a local file or an HTTP endpoint, cached in memory and
+//! refreshed periodically. The service doesn't start without its lists; a failed
+//! refresh later on keeps the last good list.
+
+use crate::{error::ServiceError, hooks::EstimationHook};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    Error,
+};
+use alloy::{
+    primitives::{Address, TxKind},
+    rpc::types::TransactionRequest,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+    future::Future,
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{debug, error, info, warn};
+
+/// Response header listing the screening hits of an annotated request
+pub const SCREENING_HITS_HEADER: &str = "x-screening-hits";
+
+tokio::task_local! {
+    /// Screening hits of the request being served by the current task
+    static SCREENING_HITS: RefCell<Vec<String>>;
+}
+
+/// Where an address list is loaded from
+#[derive(Debug, Clone)]
+pub enum ListSource {
+    /// A local file
+    File(String),
+    /// An HTTP(S) endpoint
+    Http(String),
+}
+
+impl ListSource {
+    /// Interpret a configured location as a URL (`http://`, `https://`) or a file path
+    pub fn parse(location: &str) -> Self {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            ListSource::Http(location.to_string())
+        } else {
+            ListSource::File(location.to_string())
+        }
+    }
+
+    /// Fetch and parse the list
+    async fn load(&self) -> Result<HashSet<Address>, String> {
+        let contents = match self {
+            ListSource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+            ListSource::Http(url) => reqwest::get(url)
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+                .text()
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", url, e))?,
+        };
+        parse_address_list(&contents)
+    }
+}
+
+/// What to do when a request references a screened address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreeningMode {
+    /// Reject the request
+    Reject,
+    /// Let the request through, logging the hit and reporting it in the
+    /// `X-Screening-Hits` response header (see [`annotate_screening_hits`])
+    Annotate,
+}
+
+impl FromStr for ScreeningMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "reject" => Ok(ScreeningMode::Reject),
+            "annotate" => Ok(ScreeningMode::Annotate),
+            other => Err(format!("Unknown screening mode: {}", other)),
+        }
+    }
+}
+
+/// Currently loaded lists
+#[derive(Debug, Default)]
+struct AddressLists {
+    /// Permitted senders; empty means every sender is permitted
+    allow: HashSet<Address>,
+    /// Addresses that may not appear anywhere in a request
+    deny: HashSet<Address>,
+}
+
+/// Estimation hook screening request addresses against allow/deny lists
+///
+/// The deny list applies to every address a request references: sender,
+/// recipient, access list entries and EIP-7702 delegation targets. The allow
+/// list, when configured, restricts which senders may request estimates.
+pub struct AddressScreener {
+    allow_source: Option<ListSource>,
+    deny_source: Option<ListSource>,
+    mode: ScreeningMode,
+    lists: RwLock<AddressLists>,
+}
+
+impl AddressScreener {
+    /// Create a screener; lists stay empty until [`AddressScreener::refresh`] runs
+    pub fn new(allow_source: Option<ListSource>, deny_source: Option<ListSource>, mode: ScreeningMode) -> Self {
+        Self {
+            allow_source,
+            deny_source,
+            mode,
+            lists: RwLock::new(AddressLists::default()),
+        }
+    }
+
+    /// Reload both lists from their sources
+    ///
+    /// A list that fails to load keeps its previous contents.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or the error of the first list that failed to
+    ///   load. At startup, when there is no previous list to keep, the caller should
+    ///   refuse to serve rather than screen against an empty list.
+    pub async fn refresh(&self) -> Result<(), String> {
+        let mut result = Ok(());
+        if let Some(source) = &self.allow_source {
+            match source.load().await {
+                Ok(allow) => {
+                    debug!("Loaded {} allow-listed addresses", allow.len());
+                    self.lists.write().unwrap_or_else(|e| e.into_inner()).allow = allow;
+                }
+                Err(e) => {
+                    error!("Failed to refresh allow list, keeping previous list: {}", e);
+                    result = Err(e);
+                }
+            }
+        }
+        if let Some(source) = &self.deny_source {
+            match source.load().await {
+                Ok(deny) => {
+                    debug!("Loaded {} deny-listed addresses", deny.len());
+                    self.lists.write().unwrap_or_else(|e| e.into_inner()).deny = deny;
+                }
+                Err(e) => {
+                    error!("Failed to refresh deny list, keeping previous list: {}", e);
+                    result = result.and(Err(e));
+                }
+            }
+        }
+        result
+    }
+
+    /// Refresh the lists in the background every `interval`, which must be non-zero
+    pub fn spawn_refresh_task(self: Arc<Self>, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately; the initial load happens at startup
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                // Failures are logged and the previous lists kept
+                let _ = self.refresh().await;
+            }
+        });
+        info!("Address screening lists refresh every {:?}", interval);
+    }
+
+    /// Find the first screening violation for a request, if any
+    fn violation(&self, tx_request: &TransactionRequest) -> Option<String> {
+        let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(from) = tx_request.from {
+            if !lists.allow.is_empty() && !lists.allow.contains(&from) {
+                return Some(format!("sender {} is not on the allow list", from));
+            }
+        }
+
+        referenced_addresses(tx_request)
+            .into_iter()
+            .find(|address| lists.deny.contains(address))
+            .map(|address| format!("address {} is on the deny list", address))
+    }
+
+    /// Apply the screening mode to a violation, if any
+    fn screen(&self, violation: Option<String>) -> Result<(), ServiceError> {
+        match (violation, self.mode) {
+            (None, _) => Ok(()),
+            (Some(reason), ScreeningMode::Reject) => {
+                warn!(target: "screening", "Rejected request: {}", reason);
+                Err(ServiceError::Rejected(reason))
+            }
+            (Some(reason), ScreeningMode::Annotate) => {
+                warn!(target: "screening", "Screening hit (annotate only): {}", reason);
+                record_hit(reason);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl EstimationHook for AddressScreener {
+    fn name(&self) -> &'static str {
+        "address-screening"
+    }
+
+    fn pre_validate(&self, tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        self.screen(self.violation(tx_request))
+    }
+
+    fn inspects_touched(&self) -> bool {
+        self.deny_source.is_some()
+    }
+
+    fn inspect_touched(&self, _tx_request: &TransactionRequest, touched: &BTreeSet<Address>) -> Result<(), ServiceError> {
+        let violation = {
+            let lists = self.lists.read().unwrap_or_else(|e| e.into_inner());
+            touched
+                .iter()
+                .find(|address| lists.deny.contains(*address))
+                .map(|address| format!("execution touches address {} on the deny list", address))
+        };
+        self.screen(violation)
+    }
+}
+
+/// Record an annotate-mode hit for the response of the request being served
+///
+/// Hits outside [`annotate_screening_hits`] (WebSocket messages, background
+/// work) are only logged.
+fn record_hit(reason: String) {
+    let _ = SCREENING_HITS.try_with(|hits| {
+        let mut hits = hits.borrow_mut();
+        if !hits.contains(&reason) {
+            hits.push(reason);
+        }
+    });
+}
+
+/// Middleware reporting annotate-mode screening hits in the `X-Screening-Hits` response header
+///
+/// Used with `App::wrap_fn`. Hits are separated by `; `; responses without a hit carry no header.
+pub fn annotate_screening_hits<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let response = srv.call(req);
+    SCREENING_HITS.scope(RefCell::default(), async move {
+        let mut response = response.await?;
+        let hits = SCREENING_HITS.with(|hits| hits.take());
+        if hits.is_empty() {
+            return Ok(response);
+        }
+        if let Ok(value) = HeaderValue::from_str(&hits.join("; ")) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(SCREENING_HITS_HEADER), value);
+        }
+        Ok(response)
+    })
+}
+
+/// Every address a request references
+fn referenced_addresses(tx_request: &TransactionRequest) -> Vec<Address> {
+    let mut addresses = Vec::new();
+    addresses.extend(tx_request.from);
+    if let Some(TxKind::Call(to)) = tx_request.to {
+        addresses.push(to);
+    }
+    if let Some(access_list) = &tx_request.access_list {
+        addresses.extend(access_list.0.iter().map(|item| item.address));
+    }
+    if let Some(authorizations) = &tx_request.authorization_list {
+        addresses.extend(authorizations.iter().map(|auth| auth.address));
+    }
+    addresses
+}
+
+/// Parse an address list: one address per line (`#` starts a comment) or a JSON array
+fn parse_address_list(contents: &str) -> Result<HashSet<Address>, String> {
+    if contents.trim_start().starts_with('[') {
+        let entries: Vec<String> = serde_json::from_str(contents)
+            .map_err(|e| format!("Invalid JSON address list: {}", e))?;
+        return entries
+            .iter()
+            .map(|entry| Address::from_str(entry.trim()).map_err(|e| format!("Invalid address {}: {}", entry, e)))
+            .collect();
+    }
+
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| Address::from_str(line).map_err(|e| format!("Invalid address {}: {}", line, e)))
+        .collect()
+}
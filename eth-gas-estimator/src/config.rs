@@ -1,6 +1,70 @@
-use eyre::Result;
+use crate::{
+    audit::DEFAULT_AUDIT_LOG_CAPACITY,
+    cache::DEFAULT_ESTIMATE_CACHE_CAPACITY,
+    compliance::ScreeningMode,
+    dev_node::AnvilOptions,
+    degradation::{DEFAULT_RETRY_AFTER, DEFAULT_STALE_MAX_AGE},
+    divergence::{
+        DivergencePolicy, DEFAULT_DIVERGENCE_ALARM_RATIO, DEFAULT_DIVERGENCE_THRESHOLD_PERCENT,
+        DEFAULT_DIVERGENCE_WINDOW,
+    },
+    estimator::{DEFAULT_BATCH_CONCURRENCY, DEFAULT_GAS_LIMIT_WARNING_FRACTION},
+    fees::{DEFAULT_FEE_STREAM_MAX_CLIENTS, DEFAULT_PRIORITY_FEE_PERCENTILES, DEFAULT_PRIORITY_FEE_WINDOW_BLOCKS},
+    metrics::DEFAULT_METRICS_WINDOW,
+    prewarm::DEFAULT_PREWARM_TOP_N,
+    quotes::{QuotePolicy, DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT, DEFAULT_QUOTE_TTL_BLOCKS},
+    rate_limit::RateLimit,
+    simulation_limit::DEFAULT_MAX_CONCURRENT_SIMULATIONS,
+    slo::{SloObjectives, DEFAULT_LATENCY_OBJECTIVE, DEFAULT_LATENCY_THRESHOLD, DEFAULT_SUCCESS_OBJECTIVE},
+    storage_batch::{StorageBatching, DEFAULT_STORAGE_BATCH_CONCURRENCY, DEFAULT_STORAGE_BATCH_SLOTS},
+    telemetry::DEFAULT_TELEMETRY_WINDOW,
+    timeouts::Timeouts,
+    units::{DEFAULT_ETH_DISPLAY_DECIMALS, ETHER_DECIMALS},
+    versioning::V1Deprecation,
+};
+use actix_web::http::KeepAlive;
+use alloy::primitives::Address;
+use eyre::{eyre, Result};
 use serde::Deserialize;
-use std::env;
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    env, fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::info;
+
+/// Default number of HTTP worker threads
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// Default idle timeout of keep-alive connections (actix-web's default)
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(5);
+
+/// Default time a client has to send its request headers (actix-web's default)
+pub const DEFAULT_CLIENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default concurrent connections per worker (actix-web's default)
+pub const DEFAULT_MAX_CONNECTIONS: usize = 25_000;
+
+/// Default listen backlog (actix-web's default)
+pub const DEFAULT_BACKLOG: u32 = 2048;
+
+/// Worker, connection and socket settings of the HTTP server
+#[derive(Debug, Clone, Copy)]
+pub struct ServerTuning {
+    /// HTTP worker threads
+    pub workers: usize,
+    /// Keep-alive of idle connections
+    pub keep_alive: KeepAlive,
+    /// Time a client has to send its request headers, zero to disable
+    pub client_request_timeout: Duration,
+    /// Concurrent connections accepted by each worker
+    pub max_connections: usize,
+    /// Pending connections queued by the listening socket
+    pub backlog: u32,
+}
 
 /// Service configuration structure
 ///
@@ -13,12 +77,352 @@ pub struct Config {
     
     /// Port to listen on (default: 8080)
     pub port: u16,
+
+    /// HTTP worker threads, at least 1 (default: 4)
+    pub workers: usize,
+
+    /// Idle time after which a keep-alive connection is closed in seconds, 0 to disable keep-alive (default: 5)
+    pub keep_alive_secs: u64,
+
+    /// Time a client has to send its request headers in milliseconds, 0 to disable (default: 5000)
+    pub client_request_timeout_ms: u64,
+
+    /// Concurrent connections accepted by each worker, at least 1 (default: 25000)
+    pub max_connections: usize,
+
+    /// Pending connections queued by the listening socket (default: 2048)
+    pub backlog: u32,
     
     /// Ethereum RPC endpoint URL for communicating with the blockchain
     pub ethereum_rpc_url: String,
+
+    /// Start a local Anvil node and estimate against it instead of `ethereum_rpc_url` (default: false)
+    pub dev_anvil: bool,
+
+    /// Port of the dev Anvil node (default: a free port)
+    pub dev_anvil_port: Option<u16>,
+
+    /// Hardfork of the dev Anvil node (default: "prague")
+    pub dev_anvil_hardfork: String,
+
+    /// RPC URL of a chain the dev Anvil node forks (default: none, an empty dev chain)
+    pub dev_anvil_fork_url: Option<String>,
+
+    /// Bearer token required by the admin endpoints (admin endpoints are disabled when unset)
+    pub admin_token: Option<String>,
+
+    /// API keys and their tiers, as `key=tier` entries (default: none)
+    pub api_keys: String,
+
+    /// Verbose fields withheld from each tier, as `tier=field|field` entries (default: none)
+    pub redaction_policies: String,
+
+    /// Number of recent requests kept in the audit log for replay (default: 1000)
+    pub audit_log_capacity: usize,
+
+    /// Sender used by the `selftest` command; must be funded on the target chain
+    pub selftest_from: String,
+
+    /// ERC-20 token used by the `selftest` transfer check (skipped when unset)
+    pub selftest_token: Option<String>,
+
+    /// Allow list of permitted senders (file path or HTTP URL)
+    pub screening_allow_list: Option<String>,
+
+    /// Deny list of addresses that may not appear in requests (file path or HTTP URL)
+    pub screening_deny_list: Option<String>,
+
+    /// What to do on a screening hit: "reject" or "annotate" (default: reject)
+    pub screening_mode: String,
+
+    /// How often screening lists are reloaded, in seconds (default: 300)
+    pub screening_refresh_secs: u64,
+
+    /// How long a latest block lookup is shared between requests, in milliseconds (default: 1000)
+    pub latest_block_ttl_ms: u64,
+
+    /// Private key used to sign simulation reports (default: unset, reports unsigned)
+    pub provenance_signing_key: Option<String>,
+
+    /// Number of blocks a gas quote stays valid for (default: 3)
+    pub quote_ttl_blocks: u64,
+
+    /// Base fee drift in percent after which a quote is stale (default: 25)
+    pub quote_max_base_fee_drift_percent: f64,
+
+    /// Number of estimation results cached per block, 0 to disable (default: 1024)
+    pub estimate_cache_capacity: usize,
+
+    /// Arbitrum estimation strategy, globally and/or per chain id (default: "node-interface")
+    pub arbitrum_estimation: String,
+
+    /// EVM hardfork for simulations, globally and/or per chain id (default: "auto")
+    pub evm_spec: String,
+
+    /// Backend serving estimates, calls and traces, globally and/or per chain id (default: "revm")
+    pub simulation_backend: String,
+
+    /// Gas limit of simulated blocks, "block" or a limit, globally and/or per chain id (default: "block")
+    pub simulation_block_gas_limit: String,
+
+    /// Fixed-behavior precompiles added to the EVM, globally and/or per chain id (default: none)
+    pub custom_precompiles: String,
+
+    /// Gas dependence detection of estimates, "on" or "off", globally and/or per chain id (default: "on")
+    pub gas_dependence_detection: String,
+
+    /// Simulate under the next fork's rules ahead of its activation (default: false)
+    pub upgrade_preview: bool,
+
+    /// Forward estimates to the upstream node when the simulation can't fetch state (default: false)
+    pub upstream_fallback: bool,
+
+    /// Steps tried when the upstream node is unreachable, e.g. "staleCache,secondary" (default: none)
+    pub degradation_ladder: String,
+
+    /// RPC URL of the provider the `secondary` degradation step passes estimates to (default: unset)
+    pub secondary_rpc_url: Option<String>,
+
+    /// Seconds announced in `Retry-After` once the degradation ladder is exhausted (default: 5)
+    pub degradation_retry_after_secs: u64,
+
+    /// Age in seconds after which an estimate is too stale to serve during an outage (default: 300)
+    pub stale_estimate_max_age_secs: u64,
+
+    /// Where omitted nonces are resolved from: "latest" or "pending" (default: "latest")
+    pub nonce_source: String,
+
+    /// Factor applied to every estimate for state drift (default: 1.0)
+    pub estimate_gas_multiplier: f64,
+
+    /// Gas added to every estimate after the multiplier (default: 0)
+    pub estimate_gas_buffer: u64,
+
+    /// Contracts whose state is loaded at every new head
+    pub prewarm_contracts: Vec<Address>,
+
+    /// Record upstream mempool traffic to prewarm popular calls (default: false)
+    pub mempool_warmup: bool,
+
+    /// Number of popular mempool calls re-simulated per warm-up (default: 20)
+    pub prewarm_top_n: usize,
+
+    /// Interval between warm-ups in seconds (default: 12)
+    pub prewarm_interval_secs: u64,
+
+    /// Number of most called contracts added to the prewarm set, 0 to disable telemetry (default: 10)
+    pub prewarm_auto_top_n: usize,
+
+    /// Rolling window of the most-called contract telemetry in seconds (default: 3600)
+    pub telemetry_window_secs: u64,
+
+    /// Base URL of a healthy replica whose fork state is preloaded at startup (default: unset)
+    pub snapshot_peer_url: Option<String>,
+
+    /// Bearer token for the peer's `/admin/snapshot` endpoint (default: `ADMIN_TOKEN`)
+    pub snapshot_peer_token: Option<String>,
+
+    /// Hosts the snapshot peer token may be sent to (default: none)
+    pub snapshot_peer_hosts: Vec<String>,
+
+    /// Number of recent blocks whose tips feed the priority fee suggestions, 0 to disable (default: 0)
+    pub priority_fee_window_blocks: usize,
+
+    /// Percentiles of the priority fee suggestions (default: 10, 50, 90)
+    pub priority_fee_percentiles: Vec<f64>,
+
+    /// Interval between checks for new blocks in milliseconds (default: 1000)
+    pub priority_fee_refresh_ms: u64,
+
+    /// WebSocket URL of the upstream node, feeding `newFees` subscriptions (default: unset)
+    pub upstream_ws_url: Option<String>,
+
+    /// Clients of the fee event stream served at once, at least 1 (default: 1000)
+    pub fee_stream_max_clients: usize,
+
+    /// Timeout of each upstream RPC call in milliseconds (default: 10000)
+    pub rpc_timeout_ms: u64,
+
+    /// Timeout of each EVM simulation in milliseconds (default: 10000)
+    pub simulation_timeout_ms: u64,
+
+    /// Timeout of each HTTP request, end to end, in milliseconds (default: 30000)
+    pub request_timeout_ms: u64,
+
+    /// Storage slots fetched per batch by a simulation fork, 1 to disable batching (default: 1)
+    pub storage_batch_slots: usize,
+
+    /// Storage batches in flight at once across all forks (default: 4)
+    pub storage_batch_concurrency: usize,
+
+    /// EVM simulations running at once, 0 for unbounded (default: 256)
+    pub max_concurrent_simulations: usize,
+
+    /// Transactions of a batch or bulk request estimated at once, at least 1 (default: 32)
+    pub batch_concurrency: usize,
+
+    /// Fraction of the block gas limit above which extended estimates warn, 0 to disable (default: 0.5)
+    pub gas_limit_warning_fraction: f64,
+
+    /// Requests per second accepted from all clients together, 0 to disable (default: 0)
+    pub rate_limit_global_rps: f64,
+
+    /// Burst size accepted from all clients together (default: the global rate)
+    pub rate_limit_global_burst: Option<f64>,
+
+    /// Requests per second accepted from each client IP, 0 to disable (default: 0)
+    pub rate_limit_per_ip_rps: f64,
+
+    /// Burst size accepted from each client IP (default: the per-IP rate)
+    pub rate_limit_per_ip_burst: Option<f64>,
+
+    /// Reverse proxies whose `X-Forwarded-For` header names the client IP (default: none)
+    pub rate_limit_trusted_proxies: Vec<IpAddr>,
+
+    /// Add gwei/ETH strings next to wei values in verbose and REST responses (default: false)
+    pub human_readable_units: bool,
+
+    /// Decimals kept in ETH strings (default: 6)
+    pub eth_display_decimals: u32,
+
+    /// Rolling window of the request metrics and SLO report in seconds (default: 3600)
+    pub metrics_window_secs: u64,
+
+    /// Share of requests that must not fail with a server error (default: 0.999)
+    pub slo_success_rate: f64,
+
+    /// Latency threshold of the latency SLO in milliseconds (default: 1000)
+    pub slo_latency_ms: u64,
+
+    /// Share of requests that must complete within the latency threshold (default: 0.99)
+    pub slo_latency_target: f64,
+
+    /// How often the config file is checked for changes in seconds, 0 to disable (default: 0)
+    pub config_watch_secs: u64,
+
+    /// Interval between scheduled probe runs in seconds, 0 to disable (default: 0)
+    pub probe_interval_secs: u64,
+
+    /// JSON file of probe cases (default: the self-test battery)
+    pub probes_file: Option<String>,
+
+    /// Compare one in this many simulated estimates with the upstream node, 0 to disable (default: 0)
+    pub shadow_sample_every: u64,
+
+    /// Divergence from upstream (in percent) counted as divergent (default: 10)
+    pub divergence_threshold_percent: f64,
+
+    /// Window over which divergent comparisons are counted in seconds (default: 300)
+    pub divergence_window_secs: u64,
+
+    /// Share of divergent comparisons in the window that fires the alarm (default: 0.5)
+    pub divergence_alarm_ratio: f64,
+
+    /// URL notified when the divergence alarm fires or resolves (default: unset)
+    pub divergence_webhook_url: Option<String>,
+
+    /// Interval between chain profile refreshes in seconds, 0 to only detect at startup (default: 3600)
+    pub chain_profile_refresh_secs: u64,
+
+    /// Unix timestamp from which the v1 response schema is announced as deprecated (default: unset)
+    pub api_v1_deprecated_at: Option<u64>,
+
+    /// Unix timestamp after which the v1 response schema may stop being served (default: unset)
+    pub api_v1_sunset_at: Option<u64>,
 }
 
 impl Config {
+    /// Global and per-client-IP rate limits, `None` where disabled
+    pub fn rate_limits(&self) -> (Option<RateLimit>, Option<RateLimit>) {
+        let limit = |per_second: f64, burst: Option<f64>| {
+            (per_second > 0.0).then(|| RateLimit {
+                per_second,
+                burst: burst.unwrap_or(per_second).max(1.0),
+            })
+        };
+        (
+            limit(self.rate_limit_global_rps, self.rate_limit_global_burst),
+            limit(self.rate_limit_per_ip_rps, self.rate_limit_per_ip_burst),
+        )
+    }
+
+    /// Upstream RPC, simulation and request timeouts
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            rpc: Duration::from_millis(self.rpc_timeout_ms),
+            simulation: Duration::from_millis(self.simulation_timeout_ms),
+            request: Duration::from_millis(self.request_timeout_ms),
+        }
+    }
+
+    /// Worker, connection and socket settings of the HTTP server
+    pub fn server_tuning(&self) -> ServerTuning {
+        ServerTuning {
+            workers: self.workers,
+            keep_alive: match self.keep_alive_secs {
+                0 => KeepAlive::Disabled,
+                secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+            },
+            client_request_timeout: Duration::from_millis(self.client_request_timeout_ms),
+            max_connections: self.max_connections,
+            backlog: self.backlog,
+        }
+    }
+
+    /// How the dev Anvil node is started
+    pub fn anvil_options(&self) -> AnvilOptions {
+        AnvilOptions {
+            port: self.dev_anvil_port,
+            hardfork: self.dev_anvil_hardfork.clone(),
+            fork_url: self.dev_anvil_fork_url.clone(),
+            ..AnvilOptions::default()
+        }
+    }
+
+    /// Batching of the simulation forks' storage misses
+    pub fn storage_batching(&self) -> StorageBatching {
+        StorageBatching {
+            slots: self.storage_batch_slots,
+            concurrency: self.storage_batch_concurrency,
+        }
+    }
+
+    /// Expiry and drift tolerance of issued quotes
+    pub fn quote_policy(&self) -> QuotePolicy {
+        QuotePolicy {
+            ttl_blocks: self.quote_ttl_blocks,
+            max_base_fee_drift_percent: self.quote_max_base_fee_drift_percent,
+        }
+    }
+
+    /// Shadow comparison sampling and divergence alarm settings
+    pub fn divergence_policy(&self) -> DivergencePolicy {
+        DivergencePolicy {
+            sample_every: self.shadow_sample_every,
+            threshold_percent: self.divergence_threshold_percent,
+            window: Duration::from_secs(self.divergence_window_secs),
+            alarm_ratio: self.divergence_alarm_ratio,
+            webhook_url: self.divergence_webhook_url.clone(),
+        }
+    }
+
+    /// Deprecation schedule announced on v1 responses
+    pub fn v1_deprecation(&self) -> V1Deprecation {
+        V1Deprecation {
+            deprecated_at: self.api_v1_deprecated_at,
+            sunset_at: self.api_v1_sunset_at,
+        }
+    }
+
+    /// Success-rate and latency objectives reported at `/admin/slo`
+    pub fn slo_objectives(&self) -> SloObjectives {
+        SloObjectives {
+            success_rate: self.slo_success_rate,
+            latency_threshold: Duration::from_millis(self.slo_latency_ms),
+            latency: self.slo_latency_target,
+        }
+    }
+
     /// Load configuration from environment variables
     ///
     /// This method reads configuration from environment variables,
@@ -32,19 +436,566 @@ impl Config {
     ///
     /// * `HOST` - Server host address (default: "127.0.0.1")
     /// * `PORT` - Server port (default: 8080)
+    /// * `WORKERS` - HTTP worker threads, at least 1 (default: 4)
+    /// * `KEEP_ALIVE_SECS` - Idle timeout of keep-alive connections, 0 to disable keep-alive (default: 5)
+    /// * `CLIENT_REQUEST_TIMEOUT_MS` - Time a client has to send its request headers, 0 to disable (default: 5000)
+    /// * `MAX_CONNECTIONS` - Concurrent connections accepted by each worker, at least 1 (default: 25000)
+    /// * `BACKLOG` - Pending connections queued by the listening socket (default: 2048)
     /// * `ETHEREUM_RPC_URL` - Ethereum RPC URL (default: "http://localhost:8545")
+    /// * `DEV_ANVIL` - "true" to start a local Anvil node and use it instead of
+    ///   `ETHEREUM_RPC_URL`, for development and demos (default: "false")
+    /// * `DEV_ANVIL_PORT` - Port of the dev Anvil node (default: a free port)
+    /// * `DEV_ANVIL_HARDFORK` - Hardfork of the dev Anvil node (default: "prague")
+    /// * `DEV_ANVIL_FORK_URL` - RPC URL of a chain the dev Anvil node forks (default: none)
+    /// * `ADMIN_TOKEN` - Bearer token for the admin endpoints (default: unset, admin disabled)
+    /// * `API_KEYS` - Comma-separated `key=tier` entries, sent in `X-API-Key` (default: none)
+    /// * `REDACTION_POLICIES` - Comma-separated `tier=field|field` entries, fields being
+    ///   "traces", "stateDiffs" or "logs" (default: none, every tier gets every field)
+    /// * `AUDIT_LOG_CAPACITY` - Number of requests retained for replay (default: 1000)
+    /// * `SELFTEST_FROM` - Funded sender for `selftest` (default: first Anvil dev account)
+    /// * `SELFTEST_TOKEN` - ERC-20 token address for `selftest` (default: unset)
+    /// * `SCREENING_ALLOW_LIST` - Sender allow list, file or URL (default: unset)
+    /// * `SCREENING_DENY_LIST` - Address deny list, file or URL (default: unset)
+    /// * `SCREENING_MODE` - "reject" or "annotate" (default: "reject")
+    /// * `SCREENING_REFRESH_SECS` - Screening list refresh interval (default: 300)
+    /// * `LATEST_BLOCK_TTL_MS` - Latest block cache lifetime in milliseconds (default: 1000)
+    /// * `PROVENANCE_SIGNING_KEY` - Hex private key signing simulation reports (default: unset)
+    /// * `QUOTE_TTL_BLOCKS` - Blocks a gas quote stays valid for (default: 3)
+    /// * `QUOTE_MAX_BASE_FEE_DRIFT_PERCENT` - Base fee drift invalidating a quote (default: 25)
+    /// * `ESTIMATE_CACHE_CAPACITY` - Estimation results cached per block, 0 disables (default: 1024)
+    /// * `ARBITRUM_ESTIMATION` - "node-interface" or "passthrough", optionally per chain
+    ///   as `42161=node-interface,42170=passthrough` (default: "node-interface")
+    /// * `EVM_SPEC` - "auto" or a hardfork name ("cancun", "prague", ...), optionally per
+    ///   chain as `auto,10=cancun` (default: "auto")
+    /// * `SIMULATION_BACKEND` - "revm" or "upstream", optionally per chain as
+    ///   `revm,324=upstream` (default: "revm")
+    /// * `SIMULATION_BLOCK_GAS_LIMIT` - "block" for the forked block's own gas limit, or a gas
+    ///   limit, optionally per chain as `block,10=60000000` (default: "block")
+    /// * `CUSTOM_PRECOMPILES` - Comma-separated `address=gas[/output]` precompiles added to the
+    ///   EVM, each optionally prefixed by a chain id as `1284:0x...0800=3000` (default: none)
+    /// * `GAS_DEPENDENCE_DETECTION` - "off" to estimate without looking for gas-dependent
+    ///   execution, optionally per chain as `on,137=off` (default: "on")
+    /// * `UPGRADE_PREVIEW` - "true" to simulate every request under the next fork's rules
+    ///   ahead of its activation (default: "false")
+    /// * `UPSTREAM_FALLBACK` - "true" to answer `eth_estimateGas` from the upstream node
+    ///   when the local simulation can't fetch fork state (default: "false")
+    /// * `DEGRADATION_LADDER` - Comma-separated steps tried when the upstream node is
+    ///   unreachable: "staleCache" and/or "secondary" (default: none, fail right away)
+    /// * `SECONDARY_RPC_URL` - Provider of the "secondary" degradation step (default: unset)
+    /// * `DEGRADATION_RETRY_AFTER_SECS` - `Retry-After` once the ladder is exhausted (default: 5)
+    /// * `STALE_ESTIMATE_MAX_AGE_SECS` - Oldest estimate served by "staleCache" (default: 300)
+    /// * `NONCE_SOURCE` - Where the nonce of requests omitting it is resolved from: "latest"
+    ///   (head block state) or "pending" (upstream pending nonce) (default: "latest")
+    /// * `ESTIMATE_GAS_MULTIPLIER` - Factor applied to every estimate, at least 1.0 (default: 1.0)
+    /// * `ESTIMATE_GAS_BUFFER` - Gas added to every estimate after the multiplier (default: 0)
+    /// * `PREWARM_CONTRACTS` - Comma-separated contract addresses loaded at every new head
+    /// * `MEMPOOL_WARMUP` - "true" to prewarm the calls most seen in the upstream mempool (default: "false")
+    /// * `PREWARM_TOP_N` - Popular mempool calls re-simulated per warm-up (default: 20)
+    /// * `PREWARM_INTERVAL_SECS` - Interval between warm-ups (default: 12)
+    /// * `PREWARM_AUTO_TOP_N` - Most called contracts added to the prewarm set, 0 to
+    ///   disable contract telemetry (default: 10)
+    /// * `TELEMETRY_WINDOW_SECS` - Rolling window of the contract telemetry (default: 3600)
+    /// * `SNAPSHOT_PEER_URL` - Base URL of a healthy replica whose fork state snapshot is
+    ///   downloaded and preloaded at startup (default: unset, start cold)
+    /// * `SNAPSHOT_PEER_TOKEN` - Bearer token for the peer's admin endpoint (default: `ADMIN_TOKEN`)
+    /// * `SNAPSHOT_PEER_HOSTS` - Comma-separated hosts the token may be sent to (default: none,
+    ///   the snapshot is only downloaded without a token)
+    /// * `PRIORITY_FEE_WINDOW_BLOCKS` - Recent blocks whose tips feed the priority fee
+    ///   suggestions, 0 to disable (default: 0, disabled)
+    /// * `PRIORITY_FEE_PERCENTILES` - Comma-separated percentiles suggested (default: "10,50,90")
+    /// * `PRIORITY_FEE_REFRESH_MS` - Interval between checks for new blocks (default: 1000)
+    /// * `UPSTREAM_WS_URL` - WebSocket URL of the upstream node whose new heads drive the
+    ///   `newFees` WebSocket subscriptions (default: unset, subscriptions disabled)
+    /// * `FEE_STREAM_MAX_CLIENTS` - Clients of the fee event stream served at once, at
+    ///   least 1 (default: 1000)
+    /// * `RPC_TIMEOUT_MS` - Timeout of each upstream RPC call (default: 10000)
+    /// * `SIMULATION_TIMEOUT_MS` - Timeout of each EVM simulation (default: 10000)
+    /// * `REQUEST_TIMEOUT_MS` - Timeout of each HTTP request (default: 30000)
+    /// * `STORAGE_BATCH_SLOTS` - Storage slots fetched per batch (a missed slot following a
+    ///   cached one and the slots after it, or access list keys), 1 to disable batching (default: 1)
+    /// * `STORAGE_BATCH_CONCURRENCY` - Storage batches in flight at once; misses arriving while
+    ///   all are busy are fetched alone (default: 4)
+    /// * `MAX_CONCURRENT_SIMULATIONS` - EVM simulations running at once; further simulations
+    ///   queue within their timeout, 0 for unbounded (default: 256)
+    /// * `BATCH_CONCURRENCY` - Transactions of a JSON-RPC batch or bulk request estimated in
+    ///   parallel, at least 1 (default: 32)
+    /// * `GAS_LIMIT_WARNING_FRACTION` - Fraction of the target block's gas limit, between 0 and 1,
+    ///   above which extended estimates carry a warning, 0 to only warn beyond the limit (default: 0.5)
+    /// * `RATE_LIMIT_GLOBAL_RPS` - Requests per second from all clients, 0 to disable (default: 0)
+    /// * `RATE_LIMIT_GLOBAL_BURST` - Global burst size (default: the global rate)
+    /// * `RATE_LIMIT_PER_IP_RPS` - Requests per second per client IP, 0 to disable (default: 0)
+    /// * `RATE_LIMIT_PER_IP_BURST` - Per-IP burst size (default: the per-IP rate)
+    /// * `RATE_LIMIT_TRUSTED_PROXIES` - Comma-separated IPs of the reverse proxies in front of
+    ///   the service, whose `X-Forwarded-For` header names the client (default: none)
+    /// * `HUMAN_READABLE_UNITS` - "true" to add gwei/ETH strings next to wei values (default: "false")
+    /// * `ETH_DISPLAY_DECIMALS` - Decimals kept in ETH strings, at most 18 (default: 6)
+    /// * `METRICS_WINDOW_SECS` - Rolling window of the request metrics and SLO report (default: 3600)
+    /// * `SLO_SUCCESS_RATE` - Share of requests that must not fail with a server error (default: 0.999)
+    /// * `SLO_LATENCY_MS` - Latency threshold of the latency SLO (default: 1000)
+    /// * `SLO_LATENCY_TARGET` - Share of requests that must complete within the threshold (default: 0.99)
+    /// * `CONFIG_WATCH_SECS` - How often the config file is checked for changes, 0 to disable (default: 0)
+    /// * `PROBE_INTERVAL_SECS` - Interval between scheduled probe runs, 0 to disable (default: 0)
+    /// * `PROBES_FILE` - JSON file of probe cases (default: the self-test battery)
+    /// * `SHADOW_SAMPLE_EVERY` - Compare one in this many simulated estimates with the
+    ///   upstream node, 0 to disable (default: 0)
+    /// * `DIVERGENCE_THRESHOLD_PERCENT` - Divergence counted as divergent (default: 10)
+    /// * `DIVERGENCE_WINDOW_SECS` - Window over which divergent comparisons are counted (default: 300)
+    /// * `DIVERGENCE_ALARM_RATIO` - Share of divergent comparisons that fires the alarm (default: 0.5)
+    /// * `DIVERGENCE_WEBHOOK_URL` - URL notified when the alarm fires or resolves
+    /// * `CHAIN_PROFILE_REFRESH_SECS` - Interval between chain profile refreshes, 0 to only
+    ///   detect at startup (default: 3600)
+    /// * `API_V1_DEPRECATED_AT` - Unix timestamp announced in the `Deprecation` header of v1
+    ///   responses (default: unset)
+    /// * `API_V1_SUNSET_AT` - Unix timestamp announced in the `Sunset` header of v1 responses
+    ///   (default: unset)
     pub fn from_env() -> Result<Self> {
+        Self::from_settings(&Settings::default())
+    }
+
+    /// Load configuration from a TOML or YAML file, with environment variable overrides
+    ///
+    /// The file holds the settings of [`Config::from_env`] under their lowercase
+    /// names, optionally grouped in sections (section names are free-form). Lists
+    /// are joined with commas, so `prewarm_contracts = ["0x..", "0x.."]` and
+    /// `evm_spec = ["auto", "10=cancun"]` are accepted. An environment variable
+    /// takes precedence over the file; unknown settings in the file are an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file; `.yaml`/`.yml` files are read as YAML, others as TOML
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` - The loaded configuration or an error
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let settings = Settings::from_file(path)?;
+        let config = Self::from_settings(&settings)?;
+        let unknown = settings.unused();
+        if !unknown.is_empty() {
+            return Err(eyre!("Unknown settings in {}: {}", path.display(), unknown.join(", ")));
+        }
+        Ok(config)
+    }
+
+    /// Load configuration from the file given by `--config <path>` or `CONFIG_PATH`,
+    /// or from the environment alone when neither is set
+    pub fn load() -> Result<Self> {
+        match Self::file_path() {
+            Some(path) => {
+                info!("Loading configuration from {}", path.display());
+                Self::from_file(&path)
+            }
+            None => Self::from_env(),
+        }
+    }
+
+    /// Config file path from `--config <path>` (or `--config=<path>`), else `CONFIG_PATH`
+    pub fn file_path() -> Option<PathBuf> {
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                return args.next().map(PathBuf::from);
+            }
+            if let Some(path) = arg.strip_prefix("--config=") {
+                return Some(PathBuf::from(path));
+            }
+        }
+        env::var("CONFIG_PATH").ok().filter(|path| !path.is_empty()).map(PathBuf::from)
+    }
+
+    /// Build the configuration from the given settings, applying defaults
+    fn from_settings(settings: &Settings) -> Result<Self> {
         // Load .env file if it exists (useful for development)
         let _ = dotenv::dotenv();
         
         // Create configuration with values from environment or defaults
         Ok(Config {
-            host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-            port: env::var("PORT")
+            host: settings.var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            port: settings.var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse::<u16>()?,
-            ethereum_rpc_url: env::var("ETHEREUM_RPC_URL")
+            workers: match settings.var("WORKERS") {
+                Ok(workers) => match workers.parse::<usize>()? {
+                    0 => return Err(eyre!("WORKERS must be at least 1")),
+                    workers => workers,
+                },
+                Err(_) => DEFAULT_WORKERS,
+            },
+            keep_alive_secs: settings.var("KEEP_ALIVE_SECS")
+                .unwrap_or_else(|_| DEFAULT_KEEP_ALIVE.as_secs().to_string())
+                .parse::<u64>()?,
+            client_request_timeout_ms: settings.var("CLIENT_REQUEST_TIMEOUT_MS")
+                .unwrap_or_else(|_| DEFAULT_CLIENT_REQUEST_TIMEOUT.as_millis().to_string())
+                .parse::<u64>()?,
+            max_connections: match settings.var("MAX_CONNECTIONS")
+                .unwrap_or_else(|_| DEFAULT_MAX_CONNECTIONS.to_string())
+                .parse::<usize>()?
+            {
+                0 => return Err(eyre!("MAX_CONNECTIONS must be at least 1")),
+                max_connections => max_connections,
+            },
+            backlog: settings.var("BACKLOG")
+                .unwrap_or_else(|_| DEFAULT_BACKLOG.to_string())
+                .parse::<u32>()?,
+            ethereum_rpc_url: settings.var("ETHEREUM_RPC_URL")
                 .unwrap_or_else(|_| "http://localhost:8545".to_string()),
+            dev_anvil: settings.var("DEV_ANVIL")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()?,
+            dev_anvil_port: settings.var("DEV_ANVIL_PORT")
+                .ok()
+                .map(|port| port.parse::<u16>())
+                .transpose()?,
+            dev_anvil_hardfork: settings.var("DEV_ANVIL_HARDFORK")
+                .unwrap_or_else(|_| "prague".to_string()),
+            dev_anvil_fork_url: settings.var("DEV_ANVIL_FORK_URL").ok().filter(|url| !url.is_empty()),
+            admin_token: settings.var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty()),
+            api_keys: settings.var("API_KEYS").unwrap_or_default(),
+            redaction_policies: settings.var("REDACTION_POLICIES").unwrap_or_default(),
+            audit_log_capacity: settings.var("AUDIT_LOG_CAPACITY")
+                .unwrap_or_else(|_| DEFAULT_AUDIT_LOG_CAPACITY.to_string())
+                .parse::<usize>()?,
+            selftest_from: settings.var("SELFTEST_FROM")
+                .unwrap_or_else(|_| "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string()),
+            selftest_token: settings.var("SELFTEST_TOKEN").ok().filter(|token| !token.is_empty()),
+            screening_allow_list: settings.var("SCREENING_ALLOW_LIST").ok().filter(|list| !list.is_empty()),
+            screening_deny_list: settings.var("SCREENING_DENY_LIST").ok().filter(|list| !list.is_empty()),
+            screening_mode: {
+                let mode = settings.var("SCREENING_MODE").unwrap_or_else(|_| "reject".to_string());
+                mode.parse::<ScreeningMode>().map_err(|e| eyre!("Invalid SCREENING_MODE: {}", e))?;
+                mode
+            },
+            screening_refresh_secs: match settings.var("SCREENING_REFRESH_SECS") {
+                Ok(secs) => match secs.parse::<u64>()? {
+                    0 => return Err(eyre!("SCREENING_REFRESH_SECS must be at least 1")),
+                    secs => secs,
+                },
+                Err(_) => 300,
+            },
+            latest_block_ttl_ms: settings.var("LATEST_BLOCK_TTL_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<u64>()?,
+            provenance_signing_key: settings.var("PROVENANCE_SIGNING_KEY").ok().filter(|key| !key.is_empty()),
+            quote_ttl_blocks: settings.var("QUOTE_TTL_BLOCKS")
+                .unwrap_or_else(|_| DEFAULT_QUOTE_TTL_BLOCKS.to_string())
+                .parse::<u64>()?,
+            quote_max_base_fee_drift_percent: settings.var("QUOTE_MAX_BASE_FEE_DRIFT_PERCENT")
+                .unwrap_or_else(|_| DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT.to_string())
+                .parse::<f64>()?,
+            estimate_cache_capacity: settings.var("ESTIMATE_CACHE_CAPACITY")
+                .unwrap_or_else(|_| DEFAULT_ESTIMATE_CACHE_CAPACITY.to_string())
+                .parse::<usize>()?,
+            arbitrum_estimation: settings.var("ARBITRUM_ESTIMATION")
+                .unwrap_or_else(|_| "node-interface".to_string()),
+            evm_spec: settings.var("EVM_SPEC")
+                .unwrap_or_else(|_| "auto".to_string()),
+            simulation_backend: settings.var("SIMULATION_BACKEND")
+                .unwrap_or_else(|_| "revm".to_string()),
+            simulation_block_gas_limit: settings.var("SIMULATION_BLOCK_GAS_LIMIT")
+                .unwrap_or_else(|_| "block".to_string()),
+            custom_precompiles: settings.var("CUSTOM_PRECOMPILES").unwrap_or_default(),
+            gas_dependence_detection: settings.var("GAS_DEPENDENCE_DETECTION")
+                .unwrap_or_else(|_| "on".to_string()),
+            upgrade_preview: settings.var("UPGRADE_PREVIEW")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()?,
+            upstream_fallback: settings.var("UPSTREAM_FALLBACK")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()?,
+            degradation_ladder: settings.var("DEGRADATION_LADDER").unwrap_or_default(),
+            secondary_rpc_url: settings.var("SECONDARY_RPC_URL").ok().filter(|url| !url.is_empty()),
+            degradation_retry_after_secs: settings.var("DEGRADATION_RETRY_AFTER_SECS")
+                .unwrap_or_else(|_| DEFAULT_RETRY_AFTER.as_secs().to_string())
+                .parse::<u64>()?,
+            stale_estimate_max_age_secs: settings.var("STALE_ESTIMATE_MAX_AGE_SECS")
+                .unwrap_or_else(|_| DEFAULT_STALE_MAX_AGE.as_secs().to_string())
+                .parse::<u64>()?,
+            nonce_source: settings.var("NONCE_SOURCE")
+                .unwrap_or_else(|_| "latest".to_string()),
+            estimate_gas_multiplier: settings.var("ESTIMATE_GAS_MULTIPLIER")
+                .unwrap_or_else(|_| "1.0".to_string())
+                .parse::<f64>()?,
+            estimate_gas_buffer: settings.var("ESTIMATE_GAS_BUFFER")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()?,
+            prewarm_contracts: settings.var("PREWARM_CONTRACTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::parse::<Address>)
+                .collect::<Result<_, _>>()?,
+            mempool_warmup: settings.var("MEMPOOL_WARMUP")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()?,
+            prewarm_top_n: settings.var("PREWARM_TOP_N")
+                .unwrap_or_else(|_| DEFAULT_PREWARM_TOP_N.to_string())
+                .parse::<usize>()?,
+            prewarm_interval_secs: match settings.var("PREWARM_INTERVAL_SECS") {
+                Ok(secs) => match secs.parse::<u64>()? {
+                    0 => return Err(eyre!("PREWARM_INTERVAL_SECS must be at least 1")),
+                    secs => secs,
+                },
+                Err(_) => 12,
+            },
+            prewarm_auto_top_n: settings.var("PREWARM_AUTO_TOP_N")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse::<usize>()?,
+            telemetry_window_secs: settings.var("TELEMETRY_WINDOW_SECS")
+                .unwrap_or_else(|_| DEFAULT_TELEMETRY_WINDOW.as_secs().to_string())
+                .parse::<u64>()?,
+            snapshot_peer_url: settings.var("SNAPSHOT_PEER_URL").ok().filter(|url| !url.is_empty()),
+            snapshot_peer_token: settings.var("SNAPSHOT_PEER_TOKEN").ok().filter(|token| !token.is_empty()),
+            snapshot_peer_hosts: settings.var("SNAPSHOT_PEER_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(str::to_string)
+                .collect(),
+            priority_fee_window_blocks: settings.var("PRIORITY_FEE_WINDOW_BLOCKS")
+                .unwrap_or_else(|_| DEFAULT_PRIORITY_FEE_WINDOW_BLOCKS.to_string())
+                .parse::<usize>()?,
+            priority_fee_percentiles: match settings.var("PRIORITY_FEE_PERCENTILES") {
+                Ok(percentiles) => percentiles
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|percentile| !percentile.is_empty())
+                    .map(str::parse::<f64>)
+                    .collect::<Result<_, _>>()?,
+                Err(_) => DEFAULT_PRIORITY_FEE_PERCENTILES.to_vec(),
+            },
+            priority_fee_refresh_ms: settings.var("PRIORITY_FEE_REFRESH_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<u64>()?,
+            upstream_ws_url: settings.var("UPSTREAM_WS_URL").ok().filter(|url| !url.is_empty()),
+            fee_stream_max_clients: match settings.var("FEE_STREAM_MAX_CLIENTS") {
+                Ok(clients) => match clients.parse::<usize>()? {
+                    0 => return Err(eyre!("FEE_STREAM_MAX_CLIENTS must be at least 1")),
+                    clients => clients,
+                },
+                Err(_) => DEFAULT_FEE_STREAM_MAX_CLIENTS,
+            },
+            rpc_timeout_ms: match settings.var("RPC_TIMEOUT_MS") {
+                Ok(ms) => match ms.parse::<u64>()? {
+                    0 => return Err(eyre!("RPC_TIMEOUT_MS must be at least 1")),
+                    ms => ms,
+                },
+                Err(_) => 10000,
+            },
+            simulation_timeout_ms: match settings.var("SIMULATION_TIMEOUT_MS") {
+                Ok(ms) => match ms.parse::<u64>()? {
+                    0 => return Err(eyre!("SIMULATION_TIMEOUT_MS must be at least 1")),
+                    ms => ms,
+                },
+                Err(_) => 10000,
+            },
+            request_timeout_ms: match settings.var("REQUEST_TIMEOUT_MS") {
+                Ok(ms) => match ms.parse::<u64>()? {
+                    0 => return Err(eyre!("REQUEST_TIMEOUT_MS must be at least 1")),
+                    ms => ms,
+                },
+                Err(_) => 30000,
+            },
+            storage_batch_slots: settings.var("STORAGE_BATCH_SLOTS")
+                .unwrap_or_else(|_| DEFAULT_STORAGE_BATCH_SLOTS.to_string())
+                .parse::<usize>()?,
+            storage_batch_concurrency: settings.var("STORAGE_BATCH_CONCURRENCY")
+                .unwrap_or_else(|_| DEFAULT_STORAGE_BATCH_CONCURRENCY.to_string())
+                .parse::<usize>()?,
+            max_concurrent_simulations: settings.var("MAX_CONCURRENT_SIMULATIONS")
+                .unwrap_or_else(|_| DEFAULT_MAX_CONCURRENT_SIMULATIONS.to_string())
+                .parse::<usize>()?,
+            batch_concurrency: match settings.var("BATCH_CONCURRENCY")
+                .unwrap_or_else(|_| DEFAULT_BATCH_CONCURRENCY.to_string())
+                .parse::<usize>()?
+            {
+                0 => return Err(eyre!("BATCH_CONCURRENCY must be at least 1")),
+                concurrency => concurrency,
+            },
+            gas_limit_warning_fraction: match settings.var("GAS_LIMIT_WARNING_FRACTION")
+                .unwrap_or_else(|_| DEFAULT_GAS_LIMIT_WARNING_FRACTION.to_string())
+                .parse::<f64>()?
+            {
+                fraction if (0.0..=1.0).contains(&fraction) => fraction,
+                fraction => return Err(eyre!("GAS_LIMIT_WARNING_FRACTION must be between 0 and 1, got {}", fraction)),
+            },
+            rate_limit_global_rps: rate_limit_value(
+                "RATE_LIMIT_GLOBAL_RPS",
+                &settings.var("RATE_LIMIT_GLOBAL_RPS").unwrap_or_else(|_| "0".to_string()),
+            )?,
+            rate_limit_global_burst: settings.var("RATE_LIMIT_GLOBAL_BURST")
+                .ok()
+                .map(|burst| rate_limit_value("RATE_LIMIT_GLOBAL_BURST", &burst))
+                .transpose()?,
+            rate_limit_per_ip_rps: rate_limit_value(
+                "RATE_LIMIT_PER_IP_RPS",
+                &settings.var("RATE_LIMIT_PER_IP_RPS").unwrap_or_else(|_| "0".to_string()),
+            )?,
+            rate_limit_per_ip_burst: settings.var("RATE_LIMIT_PER_IP_BURST")
+                .ok()
+                .map(|burst| rate_limit_value("RATE_LIMIT_PER_IP_BURST", &burst))
+                .transpose()?,
+            rate_limit_trusted_proxies: settings.var("RATE_LIMIT_TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(str::parse::<IpAddr>)
+                .collect::<Result<_, _>>()?,
+            human_readable_units: settings.var("HUMAN_READABLE_UNITS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()?,
+            eth_display_decimals: match settings.var("ETH_DISPLAY_DECIMALS")
+                .unwrap_or_else(|_| DEFAULT_ETH_DISPLAY_DECIMALS.to_string())
+                .parse::<u32>()?
+            {
+                decimals if decimals > ETHER_DECIMALS => {
+                    return Err(eyre!("ETH_DISPLAY_DECIMALS must be at most {}, got {}", ETHER_DECIMALS, decimals))
+                }
+                decimals => decimals,
+            },
+            metrics_window_secs: settings.var("METRICS_WINDOW_SECS")
+                .unwrap_or_else(|_| DEFAULT_METRICS_WINDOW.as_secs().to_string())
+                .parse::<u64>()?,
+            slo_success_rate: settings.var("SLO_SUCCESS_RATE")
+                .unwrap_or_else(|_| DEFAULT_SUCCESS_OBJECTIVE.to_string())
+                .parse::<f64>()?,
+            slo_latency_ms: settings.var("SLO_LATENCY_MS")
+                .unwrap_or_else(|_| DEFAULT_LATENCY_THRESHOLD.as_millis().to_string())
+                .parse::<u64>()?,
+            slo_latency_target: settings.var("SLO_LATENCY_TARGET")
+                .unwrap_or_else(|_| DEFAULT_LATENCY_OBJECTIVE.to_string())
+                .parse::<f64>()?,
+            config_watch_secs: settings.var("CONFIG_WATCH_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()?,
+            probe_interval_secs: settings.var("PROBE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()?,
+            probes_file: settings.var("PROBES_FILE").ok().filter(|path| !path.is_empty()),
+            shadow_sample_every: settings.var("SHADOW_SAMPLE_EVERY")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()?,
+            divergence_threshold_percent: settings.var("DIVERGENCE_THRESHOLD_PERCENT")
+                .unwrap_or_else(|_| DEFAULT_DIVERGENCE_THRESHOLD_PERCENT.to_string())
+                .parse::<f64>()?,
+            divergence_window_secs: settings.var("DIVERGENCE_WINDOW_SECS")
+                .unwrap_or_else(|_| DEFAULT_DIVERGENCE_WINDOW.as_secs().to_string())
+                .parse::<u64>()?,
+            divergence_alarm_ratio: settings.var("DIVERGENCE_ALARM_RATIO")
+                .unwrap_or_else(|_| DEFAULT_DIVERGENCE_ALARM_RATIO.to_string())
+                .parse::<f64>()?,
+            divergence_webhook_url: settings.var("DIVERGENCE_WEBHOOK_URL").ok().filter(|url| !url.is_empty()),
+            chain_profile_refresh_secs: settings.var("CHAIN_PROFILE_REFRESH_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse::<u64>()?,
+            api_v1_deprecated_at: settings.var("API_V1_DEPRECATED_AT")
+                .ok()
+                .map(|at| at.parse::<u64>())
+                .transpose()?,
+            api_v1_sunset_at: settings.var("API_V1_SUNSET_AT")
+                .ok()
+                .map(|at| at.parse::<u64>())
+                .transpose()?,
         })
     }
-}
\ No newline at end of file
+}
+
+/// Configuration values: environment variables, falling back to a config file
+#[derive(Default)]
+struct Settings {
+    /// Values from the config file, keyed by environment variable name
+    file: HashMap<String, String>,
+    /// Names looked up so far, used to report unknown file settings
+    used: RefCell<HashSet<String>>,
+}
+
+impl Settings {
+    /// Read a TOML or YAML config file
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read config file {}: {}", path.display(), e))?;
+        let is_yaml = matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml"));
+        let document: serde_json::Value = if is_yaml {
+            serde_yaml::from_str(&contents)?
+        } else {
+            toml::from_str(&contents)?
+        };
+
+        let serde_json::Value::Object(table) = document else {
+            return Err(eyre!("Config file {} must hold a table of settings", path.display()));
+        };
+        let mut file = HashMap::new();
+        flatten_settings(table, &mut file)?;
+        Ok(Self { file, used: RefCell::default() })
+    }
+
+    /// Look up a setting, preferring the environment over the config file
+    fn var(&self, name: &str) -> Result<String, env::VarError> {
+        self.used.borrow_mut().insert(name.to_string());
+        env::var(name).or_else(|e| self.file.get(name).cloned().ok_or(e))
+    }
+
+    /// File settings that were never looked up, i.e. unknown to the service
+    fn unused(&self) -> Vec<String> {
+        let used = self.used.borrow();
+        let mut unused: Vec<String> = self
+            .file
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .map(|name| name.to_lowercase())
+            .collect();
+        unused.sort();
+        unused
+    }
+}
+
+/// Collect the settings of a config file table, descending into sections
+///
+/// A setting found twice (in two sections, or spelt in different cases) is
+/// rejected rather than one value silently winning.
+fn flatten_settings(
+    table: serde_json::Map<String, serde_json::Value>,
+    settings: &mut HashMap<String, String>,
+) -> Result<()> {
+    for (key, value) in table {
+        let value = match value {
+            serde_json::Value::Object(section) => {
+                flatten_settings(section, settings)?;
+                continue;
+            }
+            serde_json::Value::Null => continue,
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(|item| setting_value(&key, item))
+                .collect::<Result<Vec<_>>>()?
+                .join(","),
+            scalar => setting_value(&key, &scalar)?,
+        };
+        // Sections only group settings, so a name may appear once across the whole file, in any case
+        match settings.entry(key.to_uppercase()) {
+            Entry::Occupied(_) => return Err(eyre!("Setting {} appears more than once in the config file", key)),
+            Entry::Vacant(entry) => entry.insert(value),
+        };
+    }
+    Ok(())
+}
+
+/// A rate limit rate or burst size: a finite, non-negative number
+fn rate_limit_value(key: &str, value: &str) -> Result<f64> {
+    match value.parse::<f64>()? {
+        value if value.is_finite() && value >= 0.0 => Ok(value),
+        value => Err(eyre!("{} must be a non-negative number, got {}", key, value)),
+    }
+}
+
+/// A scalar config file value as it would appear in an environment variable
+fn setting_value(key: &str, value: &serde_json::Value) -> Result<String> {
+    match value {
+        serde_json::Value::String(value) => Ok(value.clone()),
+        serde_json::Value::Number(value) => Ok(value.to_string()),
+        serde_json::Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(eyre!("Setting {} must be a string, number, boolean or list of those", key)),
+    }
+}
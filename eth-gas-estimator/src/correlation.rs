@@ -0,0 +1,90 @@
+//! Request correlation ids
+//!
+//! Every request gets a correlation id: the caller's `X-Request-Id` when it is
+//! a short printable ASCII string, otherwise a generated one. The id is:
+//!
+//! * a field of the request's root tracing span, so every log line emitted while
+//!   serving the request carries it
+//! * sent to the upstream node in the `X-Request-Id` header of the RPC calls
+//!   made for the request (see [`eth_gas_estimator_core::correlation`])
+//! * echoed in the `X-Request-Id` response header, and quoted in error bodies
+//!   (`correlation_id`, or `error.data.correlationId` for JSON-RPC errors)
+
+pub use eth_gas_estimator_core::correlation::{
+    correlation_id, generate_correlation_id, is_valid_correlation_id, with_correlation_id, CORRELATION_HEADER,
+};
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use std::future::Future;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+
+/// Correlation id of a request, stored in its extensions
+#[derive(Debug, Clone)]
+struct CorrelationId(String);
+
+/// The request's correlation id, resolved from its `X-Request-Id` header on first use
+///
+/// # Arguments
+///
+/// * `req` - The incoming request
+///
+/// # Returns
+///
+/// * `String` - The caller's id if valid, otherwise a generated one
+pub fn request_correlation_id(req: &ServiceRequest) -> String {
+    if let Some(CorrelationId(correlation_id)) = req.extensions().get::<CorrelationId>() {
+        return correlation_id.clone();
+    }
+    let correlation_id = req
+        .headers()
+        .get(CORRELATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_correlation_id(value))
+        .map(str::to_string)
+        .unwrap_or_else(generate_correlation_id);
+    req.extensions_mut().insert(CorrelationId(correlation_id.clone()));
+    correlation_id
+}
+
+/// Root span of the request logger, with the request's correlation id as a field
+pub struct CorrelatedRootSpan;
+
+impl RootSpanBuilder for CorrelatedRootSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let correlation_id = request_correlation_id(request);
+        tracing_actix_web::root_span!(request, correlation_id = %correlation_id)
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+/// Middleware serving each request under its correlation id
+///
+/// Used with `App::wrap_fn`. Upstream calls and error responses produced while
+/// the request is served see the id, and the response carries it in `X-Request-Id`.
+pub fn correlate<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let correlation_id = request_correlation_id(&req);
+    let response = with_correlation_id(correlation_id.clone(), srv.call(req));
+    async move {
+        let mut response = response.await?;
+        if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(CORRELATION_HEADER), value);
+        }
+        Ok(response)
+    }
+}
@@ -0,0 +1,190 @@
+//! Built-in Anvil node for local development
+//!
+//! With `DEV_ANVIL=true` the service starts its own Anvil node at startup and
+//! estimates against it instead of `ETHEREUM_RPC_URL`, so a demo or a local
+//! client needs nothing but the service (and Anvil in `PATH`). The node is a
+//! fresh dev chain, or a fork of `DEV_ANVIL_FORK_URL`, and is killed when the
+//! service stops. The test harness spawns its nodes the same way.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Upper bound on waiting for Anvil to answer (forking a remote chain takes a while)
+pub const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between readiness checks while Anvil starts
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Free ports tried before giving up, as another process may take a port before Anvil binds it
+const PORT_ATTEMPTS: usize = 3;
+
+/// How to start Anvil
+#[derive(Debug, Clone)]
+pub struct AnvilOptions {
+    /// Port to listen on (default: a free port)
+    pub port: Option<u16>,
+    /// Hardfork of the dev chain (default: "prague", needed for EIP-7702)
+    pub hardfork: String,
+    /// RPC URL of a chain to fork instead of starting an empty dev chain
+    pub fork_url: Option<String>,
+    /// Chain id of the dev chain (default: Anvil's 31337, or the forked chain's)
+    pub chain_id: Option<u64>,
+}
+
+impl Default for AnvilOptions {
+    fn default() -> Self {
+        Self {
+            port: None,
+            hardfork: "prague".to_string(),
+            fork_url: None,
+            chain_id: None,
+        }
+    }
+}
+
+/// An Anvil node owned by the service, killed when dropped
+pub struct DevNode {
+    /// RPC URL of the node
+    pub rpc_url: String,
+    child: Child,
+}
+
+impl DevNode {
+    /// Start Anvil and wait until it answers
+    ///
+    /// The wait runs on a blocking thread, so the async runtime keeps serving
+    /// while Anvil starts.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Port, hardfork, fork URL and chain id of the node
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<DevNode>` - The running node, or an error if Anvil can't be
+    ///   spawned or isn't ready within [`READY_TIMEOUT`]
+    pub async fn spawn(options: &AnvilOptions) -> io::Result<Self> {
+        let options = options.clone();
+        let (child, rpc_url) = tokio::task::spawn_blocking(move || spawn_anvil(&options))
+            .await
+            .map_err(io::Error::other)??;
+        Ok(Self { rpc_url, child })
+    }
+}
+
+impl Drop for DevNode {
+    fn drop(&mut self) {
+        info!("Stopping the dev Anvil node at {}", self.rpc_url);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Spawn an Anvil process and wait until it answers
+///
+/// The caller owns the process and must kill it. Without a configured port, a
+/// free port is picked; if another process takes it before Anvil binds it and
+/// Anvil exits, another free port is tried, up to [`PORT_ATTEMPTS`] ports.
+///
+/// # Arguments
+///
+/// * `options` - Port, hardfork, fork URL and chain id of the node
+///
+/// # Returns
+///
+/// * `io::Result<(Child, String)>` - The process handle and the RPC URL, or an error if
+///   Anvil can't be spawned or isn't ready within [`READY_TIMEOUT`]
+pub fn spawn_anvil(options: &AnvilOptions) -> io::Result<(Child, String)> {
+    let Some(port) = options.port else {
+        let mut attempt = 1;
+        loop {
+            match spawn_anvil_at(options, free_port()?) {
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse && attempt < PORT_ATTEMPTS => {
+                    warn!("{}, trying another port", e);
+                    attempt += 1;
+                }
+                spawned => return spawned,
+            }
+        }
+    };
+    spawn_anvil_at(options, port)
+}
+
+/// Spawn an Anvil process on `port` and wait until it answers
+///
+/// An Anvil exiting during startup is reported as [`io::ErrorKind::AddrInUse`],
+/// its usual cause when the port was free a moment before.
+fn spawn_anvil_at(options: &AnvilOptions, port: u16) -> io::Result<(Child, String)> {
+    let mut command = Command::new("anvil");
+    command
+        .arg("--port")
+        .arg(port.to_string())
+        .arg("--hardfork")
+        .arg(&options.hardfork)
+        // Anvil logs every request; nobody reads them
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(fork_url) = &options.fork_url {
+        command.arg("--fork-url").arg(fork_url);
+    }
+    if let Some(chain_id) = options.chain_id {
+        command.arg("--chain-id").arg(chain_id.to_string());
+    }
+    let mut child = command.spawn()?;
+
+    let started = Instant::now();
+    // Another process holding the port would accept connections too, so the node must answer as Anvil
+    while !answers_as_anvil(port) {
+        if let Some(status) = child.try_wait()? {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("Anvil exited during startup on port {}: {}", port, status),
+            ));
+        }
+        if started.elapsed() > READY_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("Anvil didn't answer within {:?}", READY_TIMEOUT),
+            ));
+        }
+        sleep(READY_POLL_INTERVAL);
+    }
+
+    Ok((child, format!("http://127.0.0.1:{}", port)))
+}
+
+/// Whether the process listening on `port` answers `web3_clientVersion` as Anvil
+fn answers_as_anvil(port: u16) -> bool {
+    let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) else {
+        return false;
+    };
+    let body = r#"{"jsonrpc":"2.0","id":1,"method":"web3_clientVersion","params":[]}"#;
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        port,
+        body.len(),
+        body
+    );
+    let mut response = String::new();
+    stream.set_read_timeout(Some(READY_POLL_INTERVAL * 10)).is_ok()
+        && stream.write_all(request.as_bytes()).is_ok()
+        && stream.read_to_string(&mut response).is_ok()
+        && response.contains("anvil")
+}
+
+/// A port nothing listens on right now
+///
+/// Another process may take it before it is used; see [`spawn_anvil`].
+fn free_port() -> io::Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
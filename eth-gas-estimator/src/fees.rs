@@ -0,0 +1,685 @@
+//! Fee suggestions and the next-block long-poll
+//!
+//! `GET /api/v1/fees/next` holds the request until a new head is observed and
+//! then answers with its base fee and fee suggestions for the next block, a
+//! simple push mechanism for clients that can't use WebSockets or SSE. When no
+//! new head shows up within the timeout, the request is answered with
+//! `204 No Content` and the client polls again.
+//!
+//! The [`PriorityFeeTracker`], when enabled, keeps the tip percentiles of a
+//! rolling window of recent blocks, read with `eth_feeHistory` as blocks
+//! arrive, and `GET /api/v1/fees/priority` answers with `maxPriorityFeePerGas`
+//! suggestions at the configured percentiles of those tips. `GET /api/v1/fees/recommendations`
+//! combines them with the base fee projection into complete fee envelopes for
+//! each urgency level.
+//!
+//! With an upstream WebSocket URL configured, the [`FeeFeed`] follows the
+//! upstream's new heads and pushes the base fees, tip suggestions and blob base
+//! fee of every head to WebSocket clients subscribed to `newFees`, and to
+//! clients of the `GET /api/v1/fees/stream` server-sent events stream. It
+//! keeps the fees of the latest heads for stream clients resuming with
+//! `Last-Event-ID`.
+
+use crate::{
+    chains,
+    error::ServiceError,
+    estimator::GasEstimator,
+    foundry::Fork,
+    models::fees::{
+        FeeRecommendation, FeeRecommendations, FeeSuggestion, NewFees, NextFeesQuery, PriorityFeeSuggestion,
+        PriorityFeeSuggestions,
+    },
+    rpc::{upstream_failure, EthereumClient, NewHeads},
+};
+use actix_web::{get, http::header, web, HttpRequest, HttpResponse};
+use alloy::{
+    eips::{
+        eip1559::{calc_next_block_base_fee, BaseFeeParams},
+        BlockId, BlockNumberOrTag,
+    },
+    primitives::{B256, U256, U64},
+    providers::Provider,
+    rpc::types::Header,
+};
+use futures::{stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    OwnedSemaphorePermit, Semaphore,
+};
+use tracing::{debug, info, warn};
+
+/// How long a long-poll waits for a new head when the client doesn't say
+pub const DEFAULT_LONG_POLL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Longest wait a client may ask for (kept below the default request timeout)
+pub const MAX_LONG_POLL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Interval between head checks while a long-poll waits
+const HEAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Long-polls waiting at once, across every worker
+pub const MAX_LONG_POLLS: usize = 1000;
+
+/// When a client turned away from a full long-poll should retry
+const LONG_POLL_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// One permit per waiting long-poll
+static LONG_POLLS: Semaphore = Semaphore::const_new(MAX_LONG_POLLS);
+
+/// Default number of recent blocks whose tips feed the priority fee suggestions; tracking is opt-in
+pub const DEFAULT_PRIORITY_FEE_WINDOW_BLOCKS: usize = 0;
+
+/// Default percentiles of the priority fee suggestions
+pub const DEFAULT_PRIORITY_FEE_PERCENTILES: &[f64] = &[10.0, 50.0, 90.0];
+
+/// How urgently a transaction should be included
+#[derive(Debug, Clone, Copy)]
+pub struct UrgencyLevel {
+    /// Name of the level in responses
+    pub name: &'static str,
+    /// Percentile of recent tips paid
+    pub percentile: f64,
+    /// Blocks the fee cap stays above the base fee for, even if every block is full
+    pub blocks: u64,
+}
+
+/// Urgency levels of the fee recommendations, slowest first
+///
+/// Faster levels tip more and cover more blocks of rising base fees, so they
+/// stay includable while the base fee climbs; a slow transaction accepts
+/// waiting for the base fee to come back down instead of capping above it.
+pub const URGENCY_LEVELS: &[UrgencyLevel] = &[
+    UrgencyLevel { name: "slow", percentile: 10.0, blocks: 1 },
+    UrgencyLevel { name: "standard", percentile: 50.0, blocks: 3 },
+    UrgencyLevel { name: "fast", percentile: 90.0, blocks: 6 },
+];
+
+/// Base fee of a head block and of the block after it (EIP-1559, with the chain's parameters)
+async fn head_base_fees(estimator: &GasEstimator, head: &Fork) -> Result<(u64, u64), ServiceError> {
+    let block = estimator
+        .eth_client
+        .provider
+        .get_block(BlockId::hash(head.block_hash))
+        .await
+        .map_err(|e| upstream_failure(&format!("Failed to get block {}", head.block_number), e))?
+        .ok_or_else(|| ServiceError::RPCConnection(format!("Failed to get block {}", head.block_number)))?;
+    let base_fee = block.header.base_fee_per_gas.unwrap_or_default();
+    let next_base_fee = calc_next_block_base_fee(
+        block.header.gas_used,
+        block.header.gas_limit,
+        base_fee,
+        chains::base_fee_params(head.chain_id),
+    );
+    Ok((base_fee, next_base_fee))
+}
+
+/// Highest base fee `blocks` blocks from now, if every block in between is full
+fn max_base_fee_after(next_base_fee: u64, gas_limit: u64, blocks: u64, params: BaseFeeParams) -> u64 {
+    (1..blocks).fold(next_base_fee, |base_fee, _| calc_next_block_base_fee(gas_limit, gas_limit, base_fee, params))
+}
+
+/// Derive the fees of a head block and suggestions for the next one
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator whose upstream node is asked for the tip
+/// * `head` - The fork at the head block
+///
+/// # Returns
+///
+/// * `Result<FeeSuggestion, ServiceError>` - The fees, or an error if the node is unreachable
+pub async fn fee_suggestion(estimator: &GasEstimator, head: &Fork) -> Result<FeeSuggestion, ServiceError> {
+    let (base_fee, next_base_fee) = head_base_fees(estimator, head).await?;
+
+    let priority_fee = estimator
+        .eth_client
+        .provider
+        .get_max_priority_fee_per_gas()
+        .await
+        .map_err(|e| upstream_failure("Failed to get the priority fee", e))?;
+
+    Ok(FeeSuggestion {
+        block_number: U64::from(head.block_number),
+        block_hash: head.block_hash,
+        base_fee_per_gas: U256::from(base_fee),
+        next_base_fee_per_gas: U256::from(next_base_fee),
+        max_priority_fee_per_gas: U256::from(priority_fee),
+        max_fee_per_gas: U256::from(next_base_fee) * U256::from(2) + U256::from(priority_fee),
+    })
+}
+
+/// Fee recommendations for each urgency level at a head block
+///
+/// The tip is the level's percentile of the tips in the tracker's window, and
+/// the fee cap covers the highest base fee the transaction can meet while it
+/// waits the level's number of blocks, plus the tip.
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator whose upstream node is asked for the head block
+/// * `tracker` - The tracker the tips are taken from
+/// * `head` - The fork at the head block
+///
+/// # Returns
+///
+/// * `Result<FeeRecommendations, ServiceError>` - The recommendations, or an error if the
+///   node is unreachable
+pub async fn fee_recommendations(
+    estimator: &GasEstimator,
+    tracker: &PriorityFeeTracker,
+    head: &Fork,
+) -> Result<FeeRecommendations, ServiceError> {
+    let (base_fee, next_base_fee) = head_base_fees(estimator, head).await?;
+    let gas_limit = head.block_env.gas_limit.saturating_to::<u64>();
+    let params = chains::base_fee_params(head.chain_id);
+
+    let levels = URGENCY_LEVELS
+        .iter()
+        .map(|level| {
+            let tip = tracker.tip_at(level.percentile);
+            let max_base_fee = U256::from(max_base_fee_after(next_base_fee, gas_limit, level.blocks, params));
+            FeeRecommendation {
+                urgency: level.name,
+                blocks: level.blocks,
+                max_priority_fee_per_gas: tip,
+                max_fee_per_gas: max_base_fee + tip,
+            }
+        })
+        .collect();
+    Ok(FeeRecommendations {
+        block_number: U64::from(head.block_number),
+        block_hash: head.block_hash,
+        base_fee_per_gas: U256::from(base_fee),
+        next_base_fee_per_gas: U256::from(next_base_fee),
+        levels,
+    })
+}
+
+/// Long-poll for the fees of the next head
+///
+/// Query: `after` (answer once the head is past this block, default: the
+/// current head) and `timeoutMs` (default 20000, at most 25000). Passing the
+/// `blockNumber` of the previous answer as `after` ensures no head is missed
+/// between two polls. At most [`MAX_LONG_POLLS`] polls wait at once; further
+/// ones are answered with `503 Service Unavailable`.
+#[get("/api/v1/fees/next")]
+async fn next_fees(
+    estimator: web::Data<Arc<GasEstimator>>,
+    query: web::Query<NextFeesQuery>,
+) -> Result<HttpResponse, ServiceError> {
+    let _waiting = LONG_POLLS.try_acquire().map_err(|_| {
+        ServiceError::Unavailable("Too many long-polls waiting".to_string(), LONG_POLL_RETRY_AFTER)
+    })?;
+    let timeout = query
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_LONG_POLL_TIMEOUT)
+        .min(MAX_LONG_POLL_TIMEOUT);
+    let deadline = Instant::now() + timeout;
+
+    let mut head = estimator.forks.latest().await?;
+    let after = query.after.unwrap_or(head.block_number);
+    while head.block_number <= after {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            debug!("No head after block {} within {:?}", after, timeout);
+            return Ok(HttpResponse::NoContent().finish());
+        }
+        tokio::time::sleep(remaining.min(HEAD_POLL_INTERVAL)).await;
+        head = estimator.forks.latest().await?;
+    }
+
+    let fees = fee_suggestion(&estimator, &head).await?;
+    debug!("New head {}, next base fee {}", head.block_number, fees.next_base_fee_per_gas);
+    Ok(HttpResponse::Ok().json(fees))
+}
+
+/// Tip percentiles of one block
+#[derive(Debug)]
+struct BlockTips {
+    /// Block number
+    number: u64,
+    /// Effective tip at each tracked percentile, in wei per gas
+    rewards: Vec<u128>,
+}
+
+/// Rolling window of recent blocks' tips, answering percentile-based priority fee suggestions
+#[derive(Debug)]
+pub struct PriorityFeeTracker {
+    /// Number of blocks kept in the window
+    window_blocks: usize,
+    /// Percentiles suggested, between 0 and 100
+    percentiles: Vec<f64>,
+    /// Percentiles read from `eth_feeHistory`: the suggested ones and those of the urgency levels
+    tracked: Vec<f64>,
+    /// Tips of the most recent blocks with transactions, oldest first
+    blocks: Mutex<VecDeque<BlockTips>>,
+    /// Most recent block read, with or without transactions
+    latest: Mutex<Option<u64>>,
+}
+
+impl PriorityFeeTracker {
+    /// Create a tracker over `window_blocks` blocks suggesting the given percentiles
+    ///
+    /// # Arguments
+    ///
+    /// * `window_blocks` - Number of recent blocks whose tips are kept, at least 1
+    /// * `percentiles` - Percentiles of the tips to suggest, each between 0 and 100
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The tracker, or an error describing the invalid parameter
+    pub fn new(window_blocks: usize, percentiles: Vec<f64>) -> Result<Self, String> {
+        if window_blocks == 0 {
+            return Err("the window must hold at least one block".to_string());
+        }
+        if percentiles.is_empty() {
+            return Err("at least one percentile is required".to_string());
+        }
+        if let Some(invalid) = percentiles.iter().find(|p| !(0.0..=100.0).contains(*p)) {
+            return Err(format!("percentile {} is not between 0 and 100", invalid));
+        }
+        // eth_feeHistory wants its reward percentiles in ascending order
+        let mut tracked: Vec<f64> = percentiles
+            .iter()
+            .copied()
+            .chain(URGENCY_LEVELS.iter().map(|level| level.percentile))
+            .collect();
+        tracked.sort_by(f64::total_cmp);
+        tracked.dedup();
+        Ok(Self {
+            window_blocks,
+            percentiles,
+            tracked,
+            blocks: Mutex::new(VecDeque::with_capacity(window_blocks)),
+            latest: Mutex::new(None),
+        })
+    }
+
+    /// Most recent block read
+    fn latest_block(&self) -> Option<u64> {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a new block, keeping its tips if it has transactions and dropping
+    /// the blocks that left the window
+    fn push(&self, number: u64, tips: Option<BlockTips>) {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(number);
+        let oldest = number.saturating_sub(self.window_blocks as u64 - 1);
+        let mut blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        blocks.extend(tips);
+        while blocks.front().is_some_and(|block| block.number < oldest) {
+            blocks.pop_front();
+        }
+    }
+
+    /// Read the blocks mined since the last refresh into the window
+    ///
+    /// Only the block number is polled; the tips of new blocks come from one
+    /// `eth_feeHistory` call, so no block bodies are transferred.
+    ///
+    /// # Arguments
+    ///
+    /// * `eth_client` - Client of the upstream node the blocks are read from
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), ServiceError>` - Nothing, or an error if the node is unreachable
+    pub async fn refresh(&self, eth_client: &EthereumClient) -> Result<(), ServiceError> {
+        let provider = &eth_client.provider;
+        let head = provider
+            .get_block_number()
+            .await
+            .map_err(|e| upstream_failure("Failed to get the block number", e))?;
+        let oldest = head.saturating_sub(self.window_blocks as u64 - 1);
+        let first = match self.latest_block() {
+            Some(latest) if latest >= head => return Ok(()),
+            Some(latest) => (latest + 1).max(oldest),
+            None => oldest,
+        };
+
+        let history = provider
+            .get_fee_history(head - first + 1, BlockNumberOrTag::Number(head), &self.tracked)
+            .await
+            .map_err(|e| upstream_failure("Failed to get the fee history", e))?;
+        let rewards = history.reward.unwrap_or_default();
+        for (offset, gas_used_ratio) in history.gas_used_ratio.iter().enumerate() {
+            let number = history.oldest_block + offset as u64;
+            // A block without gas used has no transactions, and no tips to sample
+            let tips = rewards
+                .get(offset)
+                .filter(|_| *gas_used_ratio > 0.0)
+                .map(|rewards| BlockTips { number, rewards: rewards.clone() });
+            self.push(number, tips);
+        }
+        debug!("Priority fee window refreshed up to block {}", head);
+        Ok(())
+    }
+
+    /// Tip at a percentile: that percentile, over the blocks in the window, of each
+    /// block's tip at the percentile (the nearest tracked one)
+    fn tip_in(&self, blocks: &VecDeque<BlockTips>, percentile: f64) -> u128 {
+        let index = (0..self.tracked.len())
+            .min_by(|a, b| {
+                (self.tracked[*a] - percentile)
+                    .abs()
+                    .total_cmp(&(self.tracked[*b] - percentile).abs())
+            })
+            .unwrap_or_default();
+        let mut rewards: Vec<u128> = blocks
+            .iter()
+            .filter_map(|block| block.rewards.get(index).copied())
+            .collect();
+        rewards.sort_unstable();
+        nearest_rank(&rewards, percentile)
+    }
+
+    /// Tip at a percentile of the tips in the window
+    pub fn tip_at(&self, percentile: f64) -> U256 {
+        let blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+        U256::from(self.tip_in(&blocks, percentile))
+    }
+
+    /// Suggestions at the configured percentiles of the tips in the window
+    ///
+    /// Each block's tips at the tracked percentiles come from `eth_feeHistory`,
+    /// which weighs them by gas used. A suggestion takes the nearest-rank
+    /// percentile, over the blocks with transactions, of each block's tip at the
+    /// same percentile; a window without transactions suggests a zero tip.
+    pub fn suggestions(&self) -> PriorityFeeSuggestions {
+        let latest = self.latest_block();
+        let blocks = self.blocks.lock().unwrap_or_else(|e| e.into_inner());
+
+        let suggestions = self
+            .percentiles
+            .iter()
+            .map(|&percentile| PriorityFeeSuggestion {
+                percentile,
+                max_priority_fee_per_gas: U256::from(self.tip_in(&blocks, percentile)),
+            })
+            .collect();
+        PriorityFeeSuggestions {
+            block_number: latest.map(U64::from),
+            window_blocks: latest.map_or(0, |latest| (latest + 1).min(self.window_blocks as u64) as usize),
+            sampled_blocks: blocks.len(),
+            suggestions,
+        }
+    }
+
+    /// Follow new blocks in the background
+    pub fn spawn_refresh_task(self: Arc<Self>, eth_client: Arc<EthereumClient>, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.refresh(&eth_client).await {
+                    warn!("Priority fee refresh failed: {}", e);
+                }
+            }
+        });
+        info!(
+            "Tracking priority fees over the last {} blocks at percentiles {:?}",
+            self.window_blocks, self.percentiles
+        );
+    }
+}
+
+/// Notifications buffered for each `newFees` subscriber; slower subscribers skip the oldest
+const FEE_FEED_CAPACITY: usize = 16;
+
+/// Latest heads whose fees are replayed to event stream clients resuming with `Last-Event-ID`
+const FEE_STREAM_REPLAY_HEADS: usize = 16;
+
+/// Interval of the comments keeping an idle event stream open through proxies
+const FEE_STREAM_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// When a client turned away from a full event stream should retry
+const FEE_STREAM_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Default number of clients of the fee event stream served at once
+pub const DEFAULT_FEE_STREAM_MAX_CLIENTS: usize = 1000;
+
+/// Fees of every new head, computed once and shared by all `newFees` subscribers
+pub struct FeeFeed {
+    sender: broadcast::Sender<Arc<NewFees>>,
+    /// Fees of the latest heads, oldest first
+    recent: Mutex<VecDeque<Arc<NewFees>>>,
+    /// One permit per event stream client served
+    stream_clients: Arc<Semaphore>,
+}
+
+impl FeeFeed {
+    /// Compute the fees of every head the upstream subscription delivers, in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `heads` - The upstream new heads subscription
+    /// * `eth_client` - Client asked for the tip and blob base fee suggestions
+    /// * `tracker` - Tracker adding percentile tips to the notifications, if enabled
+    /// * `max_stream_clients` - Clients of the event stream served at once
+    pub fn spawn(
+        heads: &NewHeads,
+        eth_client: Arc<EthereumClient>,
+        tracker: Option<Arc<PriorityFeeTracker>>,
+        max_stream_clients: usize,
+    ) -> Arc<Self> {
+        let (sender, _) = broadcast::channel(FEE_FEED_CAPACITY);
+        let feed = Arc::new(Self {
+            sender,
+            recent: Mutex::new(VecDeque::with_capacity(FEE_STREAM_REPLAY_HEADS)),
+            stream_clients: Arc::new(Semaphore::new(max_stream_clients.min(Semaphore::MAX_PERMITS))),
+        });
+        let publisher = feed.clone();
+        let mut receiver = heads.subscribe();
+        actix_web::rt::spawn(async move {
+            let mut schedule = None;
+            // Heads since the last subscriber left; nobody can resume before the first one
+            let mut unobserved = FEE_STREAM_REPLAY_HEADS;
+            loop {
+                let header = match receiver.recv().await {
+                    Ok(header) => header,
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Fee feed skipped {} heads", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                // Nobody listens, but a stream client may still resume with the heads it missed
+                if publisher.sender.receiver_count() > 0 {
+                    unobserved = 0;
+                } else if unobserved < FEE_STREAM_REPLAY_HEADS {
+                    unobserved += 1;
+                } else {
+                    continue;
+                }
+                // The chain's base fee schedule, looked up once
+                let base_fee_params = match schedule {
+                    Some(params) => params,
+                    None => match eth_client.provider.get_chain_id().await {
+                        Ok(chain_id) => *schedule.insert(chains::base_fee_params(chain_id)),
+                        Err(e) => {
+                            warn!("Failed to get the chain id for the fees of block {}: {}", header.number, e);
+                            continue;
+                        }
+                    },
+                };
+                match new_fees(&eth_client, tracker.as_deref(), &header, base_fee_params).await {
+                    Ok(fees) => publisher.publish(Arc::new(fees)),
+                    Err(e) => warn!("Failed to compute the fees of block {}: {}", header.number, e),
+                }
+            }
+        });
+        info!("Pushing fee updates to newFees subscribers at every new head");
+        feed
+    }
+
+    /// Receive the fees of the heads arriving from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<NewFees>> {
+        self.sender.subscribe()
+    }
+
+    /// Remember the fees of a head and push them to the subscribers
+    fn publish(&self, fees: Arc<NewFees>) {
+        {
+            let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+            if recent.len() == FEE_STREAM_REPLAY_HEADS {
+                recent.pop_front();
+            }
+            recent.push_back(fees.clone());
+        }
+        let _ = self.sender.send(fees);
+    }
+
+    /// Fees of the remembered heads past a block number, oldest first
+    fn recent_after(&self, block_number: u64) -> VecDeque<Arc<NewFees>> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent
+            .iter()
+            .filter(|fees| fees.block_number.to::<u64>() > block_number)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Fees of a new head and suggestions for the next block
+async fn new_fees(
+    eth_client: &EthereumClient,
+    tracker: Option<&PriorityFeeTracker>,
+    header: &Header,
+    base_fee_params: BaseFeeParams,
+) -> Result<NewFees, ServiceError> {
+    let base_fee = header.base_fee_per_gas.unwrap_or_default();
+    let next_base_fee = calc_next_block_base_fee(header.gas_used, header.gas_limit, base_fee, base_fee_params);
+    let priority_fee = eth_client
+        .provider
+        .get_max_priority_fee_per_gas()
+        .await
+        .map_err(|e| upstream_failure("Failed to get the priority fee", e))?;
+    // Heads without an excess blob gas predate blobs
+    let blob_base_fee = match header.excess_blob_gas {
+        Some(_) => Some(
+            eth_client
+                .provider
+                .get_blob_base_fee()
+                .await
+                .map_err(|e| upstream_failure("Failed to get the blob base fee", e))?,
+        ),
+        None => None,
+    };
+
+    Ok(NewFees {
+        block_number: U64::from(header.number),
+        block_hash: header.hash,
+        base_fee_per_gas: U256::from(base_fee),
+        next_base_fee_per_gas: U256::from(next_base_fee),
+        max_priority_fee_per_gas: U256::from(priority_fee),
+        max_fee_per_gas: U256::from(next_base_fee) * U256::from(2) + U256::from(priority_fee),
+        blob_base_fee_per_gas: blob_base_fee.map(U256::from),
+        priority_fee_suggestions: tracker.map(|tracker| tracker.suggestions().suggestions).unwrap_or_default(),
+    })
+}
+
+/// Nearest-rank percentile of sorted values, zero when there are none
+fn nearest_rank(sorted: &[u128], percentile: f64) -> u128 {
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.saturating_sub(1)).copied().unwrap_or_default()
+}
+
+/// Priority fee suggestions from the tips of recent blocks
+#[get("/api/v1/fees/priority")]
+async fn priority_fees(tracker: Option<web::Data<Arc<PriorityFeeTracker>>>) -> Result<HttpResponse, ServiceError> {
+    let tracker = tracker
+        .ok_or_else(|| ServiceError::NotFound("Priority fee tracking is disabled".to_string()))?;
+    Ok(HttpResponse::Ok().json(tracker.suggestions()))
+}
+
+/// Ready-to-use `maxFeePerGas`/`maxPriorityFeePerGas` pairs for each urgency level
+#[get("/api/v1/fees/recommendations")]
+async fn recommended_fees(
+    estimator: web::Data<Arc<GasEstimator>>,
+    tracker: Option<web::Data<Arc<PriorityFeeTracker>>>,
+) -> Result<HttpResponse, ServiceError> {
+    let tracker = tracker
+        .ok_or_else(|| ServiceError::NotFound("Priority fee tracking is disabled".to_string()))?;
+    let head = estimator.forks.latest().await?;
+    let recommendations = fee_recommendations(&estimator, &tracker, &head).await?;
+    Ok(HttpResponse::Ok().json(recommendations))
+}
+
+/// Server-sent events stream of the fees of every new head
+///
+/// Each head produces a `newFees` event whose data is the same JSON object as
+/// the `newFees` WebSocket notifications, and whose id is the block number.
+/// A client reconnecting with `Last-Event-ID` first receives the remembered
+/// heads it missed. A comment is sent whenever the stream has been idle for
+/// [`FEE_STREAM_KEEP_ALIVE`], so proxies don't close it. A client that falls
+/// behind skips the oldest heads. Clients past the configured maximum are
+/// answered with `503 Service Unavailable`.
+#[get("/api/v1/fees/stream")]
+async fn fee_stream(req: HttpRequest, fee_feed: Option<web::Data<Arc<FeeFeed>>>) -> Result<HttpResponse, ServiceError> {
+    let feed = fee_feed
+        .ok_or_else(|| ServiceError::NotFound("Fee streaming needs an upstream WebSocket URL".to_string()))?;
+    let permit = feed.stream_clients.clone().try_acquire_owned().map_err(|_| {
+        ServiceError::Unavailable("Too many fee stream clients".to_string(), FEE_STREAM_RETRY_AFTER)
+    })?;
+
+    // Subscribe before looking the missed heads up, so none falls in between
+    let receiver = feed.subscribe();
+    let missed = req
+        .headers()
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.trim().parse::<u64>().ok())
+        .map(|last| feed.recent_after(last))
+        .unwrap_or_default();
+    let replayed = missed.iter().map(|fees| fees.block_hash).collect();
+    let stream = FeeStream { receiver, missed, replayed, _permit: permit };
+
+    let events = stream::unfold(stream, |mut stream| async move {
+        if let Some(fees) = stream.missed.pop_front() {
+            return Some((sse_event(&fees), stream));
+        }
+        loop {
+            match tokio::time::timeout(FEE_STREAM_KEEP_ALIVE, stream.receiver.recv()).await {
+                Err(_) => return Some((web::Bytes::from_static(b": keep-alive\n\n"), stream)),
+                // Heads that arrived while the missed ones were looked up
+                Ok(Ok(fees)) if stream.replayed.contains(&fees.block_hash) => {}
+                Ok(Ok(fees)) => return Some((sse_event(&fees), stream)),
+                Ok(Err(RecvError::Lagged(skipped))) => debug!("Fee stream skipped {} fee updates", skipped),
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events.map(Ok::<_, Infallible>)))
+}
+
+/// State of one event stream client, releasing its slot when the client goes away
+struct FeeStream {
+    receiver: broadcast::Receiver<Arc<NewFees>>,
+    /// Remembered heads still to replay, oldest first
+    missed: VecDeque<Arc<NewFees>>,
+    /// Hashes of the replayed heads
+    replayed: Vec<B256>,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// One `newFees` server-sent event
+fn sse_event(fees: &NewFees) -> web::Bytes {
+    let data = serde_json::to_string(fees).unwrap_or_default();
+    web::Bytes::from(format!("id: {}\nevent: newFees\ndata: {}\n\n", fees.block_number, data))
+}
+
+/// Configure the fee routes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(next_fees).service(priority_fees).service(recommended_fees).service(fee_stream);
+}
@@ -0,0 +1,207 @@
+//! Keeper/automation job cost projection
+//!
+//! Teams running Chainlink Automation or Gelato-style keepers need a budget:
+//! given a recurring transaction and its schedule, this projects daily and
+//! monthly cost ranges from the job's simulated gas and recent fee history.
+
+use crate::{
+    api::{build_transaction_requests, request_body},
+    error::ServiceError,
+    estimator::{execution_summary, GasEstimator},
+    models::keeper::{CostRange, FormattedRange, KeeperProjection, KeeperProjectionRequest},
+    rpc::upstream_failure,
+    units::DisplayUnits,
+};
+use actix_web::{post, web, HttpResponse};
+use alloy::{
+    primitives::U256,
+    providers::Provider,
+    rpc::types::BlockNumberOrTag,
+};
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// Default number of blocks of fee history used for projections
+pub const DEFAULT_HISTORY_BLOCKS: u64 = 1024;
+
+/// Most blocks of fee history a projection may ask for, as most nodes serve no more per call
+pub const MAX_HISTORY_BLOCKS: u64 = 1024;
+
+/// Reward percentiles backing the low / typical / high fee estimates
+const REWARD_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
+/// Seconds per day
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Days per projected month
+const DAYS_PER_MONTH: u64 = 30;
+
+/// Project the cost of a recurring keeper job
+#[post("/api/v1/keeper/projection")]
+async fn keeper_projection(
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let request: KeeperProjectionRequest = request_body(body)?;
+
+    let executions_per_day = match (request.schedule.executions_per_day, request.schedule.interval_seconds) {
+        (Some(per_day), _) if per_day > 0.0 => per_day,
+        (None, Some(interval)) if interval > 0 => SECONDS_PER_DAY / interval as f64,
+        _ => {
+            return Err(ServiceError::InvalidParams(
+                "Schedule needs a positive executionsPerDay or intervalSeconds".to_string(),
+            ))
+        }
+    };
+    let history_blocks = match request.history_blocks.unwrap_or(DEFAULT_HISTORY_BLOCKS) {
+        blocks @ 1..=MAX_HISTORY_BLOCKS => blocks,
+        blocks => {
+            return Err(ServiceError::InvalidParams(format!(
+                "historyBlocks must be between 1 and {}, got {}",
+                MAX_HISTORY_BLOCKS, blocks
+            )))
+        }
+    };
+
+    let tx_requests = build_transaction_requests(std::slice::from_ref(&request.transaction)).await?;
+    let worst_case_gas = tx_requests[0].gas;
+
+    // Gas of one execution at the current state
+    let sequence = estimator
+        .simulate_sequence(&tx_requests)
+        .await
+        .map_err(ServiceError::from_report)?;
+    let (status, gas_used, _) = sequence
+        .results
+        .into_iter()
+        .next()
+        .map(execution_summary)
+        .ok_or_else(|| ServiceError::Estimation("Simulation returned no result".to_string()))?;
+    if status != "success" {
+        return Err(ServiceError::Simulation(format!("Keeper transaction {} at the current state", status)));
+    }
+    let gas_used = U256::from(gas_used);
+
+    // Without a limit of its own, the job is sent with the estimated one: the gas-dependence
+    // sweep finds the limit every frame succeeds under, plus headroom
+    let worst_case_gas = match worst_case_gas {
+        Some(limit) => U256::from(limit),
+        None => estimator.estimate_raw_gas(&tx_requests[0]).await.map_err(ServiceError::from_report)?.gas,
+    }
+    .max(gas_used);
+
+    let fee_per_gas = fee_range(&estimator, history_blocks).await?;
+    debug!("Keeper fee range over {} blocks: {:?}", history_blocks, fee_per_gas);
+
+    let per_execution = CostRange {
+        low: gas_used * fee_per_gas.low,
+        typical: gas_used * fee_per_gas.typical,
+        high: worst_case_gas * fee_per_gas.high,
+        formatted: None,
+    };
+    let daily = scale_range(&per_execution, executions_per_day);
+    let monthly = CostRange {
+        low: daily.low * U256::from(DAYS_PER_MONTH),
+        typical: daily.typical * U256::from(DAYS_PER_MONTH),
+        high: daily.high * U256::from(DAYS_PER_MONTH),
+        formatted: None,
+    };
+
+    info!("Projected keeper job: {} gas, {} executions/day", gas_used, executions_per_day);
+    let units = estimator.display_units();
+    Ok(HttpResponse::Ok().json(KeeperProjection {
+        block_number: sequence.block_number,
+        gas_used,
+        worst_case_gas,
+        executions_per_day,
+        history_blocks,
+        fee_per_gas: with_formatted(fee_per_gas, units, true),
+        per_execution: with_formatted(per_execution, units, false),
+        daily: with_formatted(daily, units, false),
+        monthly: with_formatted(monthly, units, false),
+    }))
+}
+
+/// Effective fee per gas range over the last `history_blocks` blocks
+///
+/// Low pairs the lowest base fee with the lowest 10th-percentile tip, typical
+/// the median base fee with the median 50th-percentile tip, and high the
+/// highest base fee with the highest 90th-percentile tip.
+async fn fee_range(estimator: &GasEstimator, history_blocks: u64) -> Result<CostRange, ServiceError> {
+    let history = estimator
+        .eth_client
+        .provider
+        .get_fee_history(history_blocks, BlockNumberOrTag::Latest, &REWARD_PERCENTILES)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch fee history: {:?}", e);
+            upstream_failure("Failed to fetch fee history", e)
+        })?;
+
+    let mut base_fees = history.base_fee_per_gas.clone();
+    base_fees.sort_unstable();
+    let rewards = history.reward.unwrap_or_default();
+    let mut tips: [Vec<u128>; 3] = Default::default();
+    for block_rewards in &rewards {
+        for (column, reward) in tips.iter_mut().zip(block_rewards) {
+            column.push(*reward);
+        }
+    }
+    tips.iter_mut().for_each(|column| column.sort_unstable());
+
+    Ok(CostRange {
+        low: U256::from(first(&base_fees) + first(&tips[0])),
+        typical: U256::from(median(&base_fees) + median(&tips[1])),
+        high: U256::from(last(&base_fees) + last(&tips[2])),
+        formatted: None,
+    })
+}
+
+/// Multiply every value of a range by a (fractional) factor
+fn scale_range(range: &CostRange, factor: f64) -> CostRange {
+    // Scale in thousandths to keep fractional executions per day
+    let factor = U256::from((factor * 1000.0).round() as u64);
+    let thousand = U256::from(1000);
+    CostRange {
+        low: range.low * factor / thousand,
+        typical: range.typical * factor / thousand,
+        high: range.high * factor / thousand,
+        formatted: None,
+    }
+}
+
+/// Attach decimal strings to a range of wei values when human-readable units are enabled
+///
+/// Fees per gas are shown in gwei, costs in ETH.
+fn with_formatted(mut range: CostRange, units: Option<DisplayUnits>, fee_per_gas: bool) -> CostRange {
+    if let Some(units) = units {
+        let format = |wei| if fee_per_gas { units.gwei(wei) } else { units.eth(wei) };
+        range.formatted = Some(FormattedRange {
+            unit: if fee_per_gas { "gwei" } else { "ETH" },
+            low: format(range.low),
+            typical: format(range.typical),
+            high: format(range.high),
+        });
+    }
+    range
+}
+
+/// Smallest value of a sorted slice (0 when empty)
+fn first(sorted: &[u128]) -> u128 {
+    sorted.first().copied().unwrap_or_default()
+}
+
+/// Median of a sorted slice (0 when empty)
+fn median(sorted: &[u128]) -> u128 {
+    sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+}
+
+/// Largest value of a sorted slice (0 when empty)
+fn last(sorted: &[u128]) -> u128 {
+    sorted.last().copied().unwrap_or_default()
+}
+
+/// Configure the keeper routes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(keeper_projection);
+}
@@ -0,0 +1,46 @@
+//! Per-sender nonce ladder estimation
+//!
+//! Batching senders (airdrops, exchange hot wallets) send many transactions
+//! back-to-back, where transaction N depends on the state left by N-1.
+//! Estimating each one in isolation against the head gives wrong answers, so
+//! the ladder endpoint assigns sequential nonces and simulates the whole chain.
+
+use crate::{
+    api::{build_transaction_requests, request_body},
+    error::ServiceError,
+    estimator::GasEstimator,
+    models::ladder::NonceLadderRequest,
+};
+use actix_web::{post, web, HttpResponse};
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Estimate a chain of transactions sent back-to-back by one sender
+#[post("/api/v1/eth/estimateGas/ladder")]
+async fn estimate_nonce_ladder(
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let request: NonceLadderRequest = request_body(body)?;
+    if request.transactions.is_empty() {
+        return Err(ServiceError::InvalidParams("Ladder must contain at least one transaction".to_string()));
+    }
+
+    let (from, start_nonce) = (request.from, request.start_nonce);
+    let tx_requests = build_transaction_requests(&request.transactions).await?;
+
+    info!("Estimating nonce ladder of {} transactions from {}", tx_requests.len(), from);
+    let ladder = estimator
+        .estimate_nonce_ladder(from, &tx_requests, start_nonce)
+        .await
+        .map_err(|e| {
+            error!("Nonce ladder estimation failed: {:?}", e);
+            ServiceError::from_report(e)
+        })?;
+    Ok(HttpResponse::Ok().json(ladder))
+}
+
+/// Configure the nonce ladder route
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(estimate_nonce_ladder);
+}
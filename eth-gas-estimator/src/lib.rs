@@ -1,8 +1,36 @@
+// The estimator, simulations and models live in the core library; re-exported
+// so the server modules and embedders keep their paths
+pub use eth_gas_estimator_core::{
+    access_list, audit, backend, bundle, cache, chains, code_changes, degradation, divergence, error, estimator, explain,
+    foundry, gas_dependence, hooks, intrinsic, memory, models, profile, provenance, rpc, simulation_limit, state_diff,
+    storage_batch, timeouts, tracers, units,
+};
+
 // Export modules for testing and benchmarking
+pub mod admin;
 pub mod api;
+pub mod batching;
+pub mod compliance;
 pub mod config;
-pub mod error;
-pub mod estimator;
-pub mod models;
-pub mod rpc;
-pub mod foundry;
\ No newline at end of file
+pub mod correlation;
+pub mod dev_node;
+pub mod fees;
+pub mod metrics;
+pub mod prewarm;
+pub mod probes;
+pub mod quotes;
+pub mod rate_limit;
+pub mod redaction;
+pub mod reload;
+pub mod selftest;
+pub mod slo;
+pub mod snapshot;
+pub mod telemetry;
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+pub mod versioning;
+pub mod keeper;
+pub mod ladder;
+pub mod bulk;
+pub mod sequence;
+pub mod ws;
\ No newline at end of file
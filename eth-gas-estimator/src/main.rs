@@ -1,16 +1,57 @@
 use tracing_subscriber::EnvFilter;
-use crate::estimator::GasEstimator;
+use crate::estimator::{GasEstimator, Headroom};
 use actix_web::{web, App, HttpServer};
-use std::sync::Arc;
+use crate::chains::ChainOptions;
+use crate::degradation::DegradationPolicy;
+use crate::error::ServiceError;
+use crate::divergence::DivergenceMonitor;
+use crate::compliance::{annotate_screening_hits, AddressScreener, ListSource, ScreeningMode};
+use crate::correlation::{correlate, CorrelatedRootSpan};
+use crate::fees::{FeeFeed, PriorityFeeTracker};
+use crate::prewarm::Prewarmer;
+use crate::probes::Prober;
+use crate::provenance::ProvenanceSigner;
+use crate::metrics::{record_metrics, RequestMetrics};
+use crate::rate_limit::{rate_limit, RateLimiter};
+use crate::redaction::{resolve_redaction, RedactionPolicies};
+use crate::reload::ConfigReloader;
+use crate::rpc::NewHeads;
+use crate::telemetry::ContractTelemetry;
+use crate::units::DisplayUnits;
+use crate::timeouts::request_timeout;
+use std::{path::Path, sync::Arc, time::Duration};
 use tracing_actix_web::TracingLogger;
 
+use eth_gas_estimator_core::{
+    audit, cache, chains, degradation, divergence, error, estimator, foundry, hooks, models, provenance, rpc,
+    simulation_limit, storage_batch, timeouts, units,
+};
+
+mod admin;
 mod api;
+mod batching;
+mod compliance;
 mod config;
-mod error;
-mod estimator;
-mod models;
-mod rpc;
-mod foundry;
+mod correlation;
+mod dev_node;
+mod fees;
+mod metrics;
+mod prewarm;
+mod probes;
+mod quotes;
+mod rate_limit;
+mod redaction;
+mod reload;
+mod selftest;
+mod slo;
+mod snapshot;
+mod telemetry;
+mod versioning;
+mod keeper;
+mod ladder;
+mod bulk;
+mod sequence;
+mod ws;
 
 /// Application entry point
 /// 
@@ -20,6 +61,9 @@ mod foundry;
 /// 3. Establishes connection to Ethereum node
 /// 4. Creates the gas estimator service
 /// 5. Starts the HTTP server with all endpoints
+///
+/// Running `eth-gas-estimator selftest` replaces step 5 with a battery of canonical
+/// estimations against the configured upstream, exiting non-zero on failure.
 #[actix_web::main] // Actix will build a multithreaded runtime
 async fn main() -> std::io::Result<()> {
     // Configure logging with appropriate log levels for different components
@@ -36,34 +80,315 @@ async fn main() -> std::io::Result<()> {
         .with_env_filter(filter)
         .init();
 
-    // Load configuration from environment variables
-    let config = config::Config::from_env().expect("Failed to load config");
+    // Load configuration from the config file (if any) and environment variables
+    let mut config = config::Config::load()
+        .map_err(|e| std::io::Error::other(format!("Failed to load config: {}", e)))?;
+
+    // Dev mode: a local Anvil node replaces the upstream, and is stopped along with the service
+    let dev_node = if config.dev_anvil {
+        let node = dev_node::DevNode::spawn(&config.anvil_options())
+            .await
+            .map_err(|e| std::io::Error::new(e.kind(), format!("Failed to start the dev Anvil node: {}", e)))?;
+        tracing::warn!("Dev mode: estimating against a local Anvil node at {}", node.rpc_url);
+        config.ethereum_rpc_url = node.rpc_url.clone();
+        Some(node)
+    } else {
+        None
+    };
 
     // Create Ethereum RPC client and handle potential connection errors
-    let eth_client = rpc::EthereumClient::new(&config.ethereum_rpc_url)
+    let timeouts = config.timeouts();
+    let eth_client = rpc::EthereumClient::with_timeout(&config.ethereum_rpc_url, timeouts.rpc)
         .await
-        .expect("Failed to connect to Ethereum");
+        .map_err(|e| std::io::Error::other(ServiceError::RPCConnection(format!("Failed to connect to Ethereum: {}", e))))?;
+
+    // Optional secondary provider, answering estimates while the upstream node is unreachable
+    let secondary = match &config.secondary_rpc_url {
+        Some(url) => Some(Arc::new(rpc::EthereumClient::with_timeout(url, timeouts.rpc).await.map_err(|e| {
+            std::io::Error::other(ServiceError::RPCConnection(format!("Failed to connect to the secondary RPC: {}", e)))
+        })?)),
+        None => None,
+    };
+    // DEGRADATION_LADDER is checked against SECONDARY_RPC_URL and ESTIMATE_CACHE_CAPACITY
+    let degradation = DegradationPolicy::new(
+        &config.degradation_ladder,
+        secondary,
+        Duration::from_secs(config.degradation_retry_after_secs),
+        Duration::from_secs(config.stale_estimate_max_age_secs),
+        config.estimate_cache_capacity,
+    )
+    .map_err(std::io::Error::other)?;
+
+    // Per-chain estimation strategy, EVM rules and simulation settings
+    let invalid = |setting: &'static str| move |e: String| std::io::Error::other(format!("Invalid {}: {}", setting, e));
+    let chain_options = ChainOptions::default()
+        .with_arbitrum_strategies(&config.arbitrum_estimation)
+        .map_err(invalid("ARBITRUM_ESTIMATION"))?
+        .with_spec_ids(&config.evm_spec)
+        .map_err(invalid("EVM_SPEC"))?
+        .with_simulation_backends(&config.simulation_backend)
+        .map_err(invalid("SIMULATION_BACKEND"))?
+        .with_block_gas_limits(&config.simulation_block_gas_limit)
+        .map_err(invalid("SIMULATION_BLOCK_GAS_LIMIT"))?
+        .with_custom_precompiles(&config.custom_precompiles)
+        .map_err(invalid("CUSTOM_PRECOMPILES"))?
+        .with_gas_dependence_detection(&config.gas_dependence_detection)
+        .map_err(invalid("GAS_DEPENDENCE_DETECTION"))?
+        .with_upgrade_preview(config.upgrade_preview)
+        .map_err(invalid("UPGRADE_PREVIEW"))?;
 
     // Build GasEstimator and wrap it in Arc for thread-safe sharing
-    let estimator = Arc::new(
-        GasEstimator::new(eth_client.into(), &config.ethereum_rpc_url),
+    let mut builder = GasEstimator::builder(eth_client.into(), &config.ethereum_rpc_url)
+        .audit_log_capacity(config.audit_log_capacity)
+        .latest_block_ttl(Duration::from_millis(config.latest_block_ttl_ms))
+        .estimate_cache_capacity(config.estimate_cache_capacity)
+        .upstream_fallback(config.upstream_fallback)
+        .degradation(degradation)
+        .nonce_source(config.nonce_source.parse().map_err(|e| std::io::Error::other(format!("Invalid NONCE_SOURCE: {}", e)))?)
+        .headroom(
+            Headroom::new(config.estimate_gas_multiplier, config.estimate_gas_buffer)
+                .map_err(invalid("ESTIMATE_GAS_MULTIPLIER"))?,
+        )
+        .timeouts(timeouts)
+        .storage_batching(config.storage_batching())
+        .max_concurrent_simulations(config.max_concurrent_simulations)
+        .batch_concurrency(config.batch_concurrency)
+        .gas_limit_warning_fraction(config.gas_limit_warning_fraction)
+        .chain_options(chain_options)
+        .quote_policy(config.quote_policy());
+    if config.upgrade_preview {
+        tracing::warn!("Upgrade preview enabled: simulating under the next fork's rules, not the active ones");
+    }
+
+    // Optional address screening against allow/deny lists
+    if config.screening_allow_list.is_some() || config.screening_deny_list.is_some() {
+        // SCREENING_MODE is checked when the config loads
+        let mode = config.screening_mode.parse::<ScreeningMode>().map_err(std::io::Error::other)?;
+        let screener = Arc::new(AddressScreener::new(
+            config.screening_allow_list.as_deref().map(ListSource::parse),
+            config.screening_deny_list.as_deref().map(ListSource::parse),
+            mode,
+        ));
+        // Serving without the lists would let every request through unscreened
+        screener.refresh().await.map_err(|e| {
+            std::io::Error::other(ServiceError::Configuration(format!("Failed to load the screening lists: {}", e)))
+        })?;
+        screener.clone().spawn_refresh_task(Duration::from_secs(config.screening_refresh_secs));
+        builder = builder.hook(screener);
+    }
+
+    // Optional human-readable amounts in responses
+    if config.human_readable_units {
+        builder = builder.display_units(DisplayUnits { eth_decimals: config.eth_display_decimals });
+    }
+
+    // Most-called contract telemetry, feeding the prewarm set
+    let telemetry = (config.prewarm_auto_top_n > 0)
+        .then(|| Arc::new(ContractTelemetry::new(Duration::from_secs(config.telemetry_window_secs))));
+    if let Some(telemetry) = &telemetry {
+        builder = builder.hook(telemetry.clone());
+    }
+
+    // Shadow comparisons with the upstream node and divergence alarms
+    let divergence = (config.shadow_sample_every > 0)
+        .then(|| Arc::new(DivergenceMonitor::new(config.divergence_policy())));
+    if let Some(monitor) = &divergence {
+        builder = builder.divergence_monitor(monitor.clone());
+    }
+
+    // Optional signing of simulation reports
+    if let Some(key) = &config.provenance_signing_key {
+        let signer = ProvenanceSigner::from_private_key(key)
+            .map_err(|e| std::io::Error::other(format!("Invalid PROVENANCE_SIGNING_KEY: {}", e)))?;
+        tracing::info!("Signing simulation reports as {}", signer.address());
+        builder = builder.provenance_signer(signer);
+    }
+
+    let estimator = Arc::new(builder.build());
+
+    // Optional bootstrap of the fork state from a healthy peer replica
+    if let Some(peer_url) = &config.snapshot_peer_url {
+        let token = config.snapshot_peer_token.as_deref().or(config.admin_token.as_deref());
+        match snapshot::download(peer_url, token, &config.snapshot_peer_hosts).await {
+            Ok(state) => {
+                if let Err(e) = snapshot::preload(&estimator.forks, &state).await {
+                    tracing::warn!("Failed to preload the peer snapshot, starting cold: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to download the peer snapshot, starting cold: {}", e),
+        }
+    }
+
+    // Optional fork state warm-up for hand-picked, most called and popular mempool targets
+    if !config.prewarm_contracts.is_empty() || config.mempool_warmup || telemetry.is_some() {
+        let mut prewarmer = Prewarmer::new(config.prewarm_contracts.clone(), config.prewarm_top_n);
+        if let Some(telemetry) = &telemetry {
+            prewarmer = prewarmer.with_telemetry(telemetry.clone(), config.prewarm_auto_top_n);
+        }
+        let prewarmer = Arc::new(prewarmer);
+        if config.mempool_warmup {
+            prewarmer.clone().spawn_mempool_listener(estimator.eth_client.clone());
+        }
+        prewarmer.spawn_warm_task(
+            estimator.forks.clone(),
+            Duration::from_secs(config.prewarm_interval_secs),
+        );
+    }
+
+    // Percentile-based priority fee suggestions from the tips of recent blocks
+    let priority_fees = if config.priority_fee_window_blocks > 0 {
+        let tracker = PriorityFeeTracker::new(config.priority_fee_window_blocks, config.priority_fee_percentiles.clone())
+            .map_err(invalid("PRIORITY_FEE_PERCENTILES"))?;
+        let tracker = Arc::new(tracker);
+        tracker.clone().spawn_refresh_task(
+            estimator.eth_client.clone(),
+            Duration::from_millis(config.priority_fee_refresh_ms),
+        );
+        Some(tracker)
+    } else {
+        None
+    };
+
+    // Fee updates pushed to `newFees` WebSocket subscribers, driven by the upstream's new heads
+    let fee_feed = config.upstream_ws_url.as_deref().map(|ws_url| {
+        FeeFeed::spawn(
+            &NewHeads::spawn(ws_url),
+            estimator.eth_client.clone(),
+            priority_fees.clone(),
+            config.fee_stream_max_clients,
+        )
+    });
+
+    // Chain profile (hardfork, blob parameters) detected from the upstream
+    if let Err(e) = estimator.forks.refresh_profile().await {
+        tracing::warn!("Chain profile detection failed, using configured defaults: {}", e);
+    }
+    if config.chain_profile_refresh_secs > 0 {
+        estimator
+            .forks
+            .clone()
+            .spawn_profile_refresh(Duration::from_secs(config.chain_profile_refresh_secs));
+    }
+
+    let bind_address = format!("{}:{}", config.host, config.port);
+
+    // Post-deploy verification: run the self-test battery instead of serving
+    if std::env::args().skip(1).any(|arg| arg == "selftest") {
+        let passed = selftest::run(&estimator, &config).await;
+        drop(dev_node);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // Scheduled synthetic probes
+    let prober = if config.probe_interval_secs > 0 {
+        let window = Duration::from_secs(config.metrics_window_secs);
+        let prober = match &config.probes_file {
+            Some(path) => Prober::from_file(Path::new(path), window).map_err(std::io::Error::other)?,
+            None => Prober::new(selftest::selftest_cases(&config), window),
+        };
+        let prober = Arc::new(prober);
+        prober.clone().spawn_probe_task(estimator.clone(), Duration::from_secs(config.probe_interval_secs));
+        Some(prober)
+    } else {
+        None
+    };
+
+    // Rate limiter shared by every worker
+    let (global_limit, per_ip_limit) = config.rate_limits();
+    let limiter = Arc::new(
+        RateLimiter::new(global_limit, per_ip_limit).with_trusted_proxies(config.rate_limit_trusted_proxies.clone()),
     );
 
+    // Runtime reload of upstream, rate limit and quote settings
+    let reloader = Arc::new(ConfigReloader::new(config.clone(), estimator.clone(), limiter.clone()));
+    if let (Some(path), true) = (config::Config::file_path(), config.config_watch_secs > 0) {
+        reloader.clone().spawn_watch_task(path, Duration::from_secs(config.config_watch_secs));
+    }
+
+    // Request metrics shared by every worker, feeding the SLO report
+    let metrics = Arc::new(RequestMetrics::new(Duration::from_secs(config.metrics_window_secs)));
+
+    // API key tiers and the verbose fields withheld from each
+    let redactions = Arc::new(
+        RedactionPolicies::new(&config.api_keys, &config.redaction_policies)
+            .map_err(invalid("API_KEYS or REDACTION_POLICIES"))?,
+    );
+
+    // Worker and connection settings, read before the config moves into the app factory
+    let tuning = config.server_tuning();
+
     // Create and start HTTP server
     HttpServer::new(move || {
-        App::new()
-            // Add logging middleware
-            .wrap(TracingLogger::default())
+        let limiter = limiter.clone();
+        let recorder = metrics.clone();
+        let redactions = redactions.clone();
+        let mut app = App::new()
+            // Resolve the caller's tier from its API key
+            .wrap_fn(move |req, srv| resolve_redaction(req, srv, &redactions))
+            // Bound the time spent on each request
+            .wrap_fn(move |req, srv| request_timeout(req, srv, timeouts.request))
+            // Record outcome and latency of requests that weren't rate limited
+            .wrap_fn(move |req, srv| record_metrics(req, srv, &recorder))
+            // Reject requests over the configured rate before doing any work
+            .wrap_fn(move |req, srv| rate_limit(req, srv, &limiter))
+            // Report annotate-mode screening hits in the response headers
+            .wrap_fn(|req, srv| annotate_screening_hits(req, srv))
+            // Serve each request under its correlation id
+            .wrap_fn(|req, srv| correlate(req, srv))
+            // Add logging middleware, with the correlation id in the request span
+            .wrap(TracingLogger::<CorrelatedRootSpan>::new())
             // Register the estimator as application data (shared between requests)
             .app_data(web::Data::new(estimator.clone())) 
+            // Register the configuration (used by the admin endpoints)
+            .app_data(web::Data::new(config.clone()))
+            // Register the request metrics (used by the admin endpoints)
+            .app_data(web::Data::new(metrics.clone()))
+            // Register the config reloader (used by the admin endpoints)
+            .app_data(web::Data::new(reloader.clone()))
             // Configure API routes
             .configure(api::configure)
+            .configure(quotes::configure)
+            .configure(ladder::configure)
+            .configure(bulk::configure)
+            .configure(sequence::configure)
+            .configure(batching::configure)
+            .configure(keeper::configure)
+            .configure(fees::configure)
+            .configure(admin::configure);
+        // Register the contract telemetry (used by the admin endpoints) when enabled
+        if let Some(telemetry) = &telemetry {
+            app = app.app_data(web::Data::new(telemetry.clone()));
+        }
+        // Register the priority fee tracker (used by the fee endpoints) when enabled
+        if let Some(tracker) = &priority_fees {
+            app = app.app_data(web::Data::new(tracker.clone()));
+        }
+        // Register the fee feed (used by the WebSocket endpoint) when enabled
+        if let Some(feed) = &fee_feed {
+            app = app.app_data(web::Data::new(feed.clone()));
+        }
+        // Register the divergence monitor (used by the admin endpoints) when enabled
+        if let Some(monitor) = &divergence {
+            app = app.app_data(web::Data::new(monitor.clone()));
+        }
+        // Register the prober (used by the admin endpoints) when enabled
+        if let Some(prober) = &prober {
+            app = app.app_data(web::Data::new(prober.clone()));
+        }
+        app
     })
-    // Set number of worker threads
-    .workers(4)
+    // Size the server for the workload (the backlog must be set before binding)
+    .workers(tuning.workers)
+    .keep_alive(tuning.keep_alive)
+    .client_request_timeout(tuning.client_request_timeout)
+    .max_connections(tuning.max_connections)
+    .backlog(tuning.backlog)
     // Bind to host/port from configuration
-    .bind(format!("{}:{}", config.host, config.port))?
+    .bind(bind_address)?
     // Start the server
     .run()
-    .await
+    .await?;
+
+    drop(dev_node);
+    Ok(())
 }
\ No newline at end of file
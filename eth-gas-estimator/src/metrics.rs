@@ -0,0 +1,219 @@
+//! Request metrics
+//!
+//! Records the outcome and latency of every served request over a rolling
+//! window. Latencies go into a fixed histogram rather than being kept
+//! individually, so memory stays bounded under any load. The SLO report (see
+//! [`crate::slo`]) is computed from these metrics.
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    Error, HttpResponse,
+};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Upper bounds (in milliseconds) of the latency histogram buckets; slower requests
+/// fall in a final overflow bucket
+pub const LATENCY_BOUNDS_MS: &[u64] = &[5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+/// Number of slices the rolling window is split into
+const WINDOW_SLICES: u32 = 12;
+
+/// Default length of the rolling window
+pub const DEFAULT_METRICS_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Paths not recorded (probes and admin traffic would skew the figures)
+const UNRECORDED_PATH_PREFIXES: &[&str] = &["/api/v1/health", "/livez", "/readyz", "/healthz", "/admin/"];
+
+/// Request counts for a span of time
+#[derive(Debug, Clone)]
+pub struct RequestCounts {
+    /// Requests served
+    pub requests: u64,
+    /// Requests answered with a server error (HTTP 5xx), or reporting one in-band
+    pub server_errors: u64,
+    /// Requests per latency bucket, aligned with [`LATENCY_BOUNDS_MS`] plus the overflow bucket
+    pub latency_buckets: Vec<u64>,
+}
+
+impl Default for RequestCounts {
+    fn default() -> Self {
+        Self {
+            requests: 0,
+            server_errors: 0,
+            latency_buckets: vec![0; LATENCY_BOUNDS_MS.len() + 1],
+        }
+    }
+}
+
+impl RequestCounts {
+    /// Requests that completed within `threshold_ms`
+    ///
+    /// Only whole histogram buckets are counted, so a threshold between two
+    /// bounds is rounded down to the lower one.
+    pub fn within_latency(&self, threshold_ms: u64) -> u64 {
+        LATENCY_BOUNDS_MS
+            .iter()
+            .zip(&self.latency_buckets)
+            .take_while(|(bound, _)| **bound <= threshold_ms)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Add another span's counts to these
+    fn merge(&mut self, other: &RequestCounts) {
+        self.requests += other.requests;
+        self.server_errors += other.server_errors;
+        for (total, count) in self.latency_buckets.iter_mut().zip(&other.latency_buckets) {
+            *total += count;
+        }
+    }
+}
+
+/// Marks a response whose status hides a server error reported in its body
+///
+/// JSON-RPC batches and bulk estimates answer `200 OK` with per-item errors. A
+/// handler marks the response when one of those items failed on the server's
+/// side, so [`record_metrics`] counts the request as a server error.
+#[derive(Debug, Clone, Copy)]
+pub struct InBandServerError;
+
+impl InBandServerError {
+    /// Mark a response if `server_error` holds
+    pub fn mark(response: &mut HttpResponse, server_error: bool) {
+        if server_error {
+            response.extensions_mut().insert(InBandServerError);
+        }
+    }
+}
+
+/// Counts recorded during one slice of the window
+struct Slice {
+    started: Instant,
+    counts: RequestCounts,
+}
+
+/// Rolling-window request outcome and latency metrics
+pub struct RequestMetrics {
+    /// Length of the rolling window
+    window: Duration,
+    /// Slices covering the window, oldest first
+    slices: Mutex<VecDeque<Slice>>,
+}
+
+impl RequestMetrics {
+    /// Create empty metrics over a rolling window of the given length
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            slices: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Length of the rolling window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Record a served request
+    ///
+    /// # Arguments
+    ///
+    /// * `latency` - Time taken to produce the response
+    /// * `server_error` - Whether the response was a server error
+    pub fn record(&self, latency: Duration, server_error: bool) {
+        let now = Instant::now();
+        let mut slices = self.slices.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut slices, now);
+
+        let slice_span = self.window / WINDOW_SLICES;
+        let needs_slice = slices
+            .back()
+            .map_or(true, |slice| now.duration_since(slice.started) >= slice_span);
+        if needs_slice {
+            slices.push_back(Slice { started: now, counts: RequestCounts::default() });
+        }
+        if let Some(slice) = slices.back_mut() {
+            let latency_ms = latency.as_millis() as u64;
+            let bucket = LATENCY_BOUNDS_MS
+                .iter()
+                .position(|bound| latency_ms <= *bound)
+                .unwrap_or(LATENCY_BOUNDS_MS.len());
+            slice.counts.requests += 1;
+            slice.counts.server_errors += server_error as u64;
+            slice.counts.latency_buckets[bucket] += 1;
+        }
+    }
+
+    /// Count a server error found after its request was recorded
+    ///
+    /// Streamed responses are recorded once their headers are sent, before their
+    /// items have run; an item failing on the server's side is counted here.
+    pub fn record_late_server_error(&self) {
+        let mut slices = self.slices.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut slices, Instant::now());
+        if let Some(slice) = slices.back_mut() {
+            slice.counts.server_errors = (slice.counts.server_errors + 1).min(slice.counts.requests);
+        }
+    }
+
+    /// Counts over the whole window
+    pub fn totals(&self) -> RequestCounts {
+        let mut slices = self.slices.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut slices, Instant::now());
+
+        let mut totals = RequestCounts::default();
+        for slice in slices.iter() {
+            totals.merge(&slice.counts);
+        }
+        totals
+    }
+
+    /// Drop slices that started before the window
+    fn expire(&self, slices: &mut VecDeque<Slice>, now: Instant) {
+        while slices
+            .front()
+            .is_some_and(|slice| now.duration_since(slice.started) >= self.window)
+        {
+            slices.pop_front();
+        }
+    }
+}
+
+/// Middleware recording the outcome and latency of each request
+///
+/// Used with `App::wrap_fn`. Health checks and admin requests are not recorded.
+/// A request counts as a server error when answered with HTTP 5xx or marked
+/// with [`InBandServerError`].
+pub fn record_metrics<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+    metrics: &Arc<RequestMetrics>,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let recorded = !UNRECORDED_PATH_PREFIXES.iter().any(|prefix| req.path().starts_with(prefix));
+    let metrics = metrics.clone();
+    let started = Instant::now();
+    let response = srv.call(req);
+    async move {
+        let response = response.await;
+        if recorded {
+            let server_error = match &response {
+                Ok(response) => {
+                    response.status().is_server_error() || response.response().extensions().contains::<InBandServerError>()
+                }
+                Err(e) => e.as_response_error().status_code().is_server_error(),
+            };
+            metrics.record(started.elapsed(), server_error);
+        }
+        response.map(ServiceResponse::map_into_boxed_body)
+    }
+}
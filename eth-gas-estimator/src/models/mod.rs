@@ -1,7 +0,0 @@
-//! Data models used throughout the application
-//!
-//! This module contains all the data structures and serialization/deserialization
-//! logic for the gas estimation service.
-
-// JSON-RPC protocol data structures
-pub mod jsonrpc;
\ No newline at end of file
@@ -0,0 +1,230 @@
+//! Fork state warm-up
+//!
+//! Simulations fetch account, code and storage state lazily from the upstream
+//! node, so the first request touching a contract at a new head pays for those
+//! round trips. The prewarmer keeps the state of likely targets warm instead:
+//!
+//! * contracts configured by hand are loaded at every new head
+//! * the contracts most called through the service (see [`crate::telemetry`])
+//!   are loaded at every new head, keeping the list current automatically
+//! * optionally, pending transactions are read from the upstream mempool (where
+//!   the node supports pending transaction filters), and the most popular
+//!   target contracts/selectors are re-simulated at every new head
+//!
+//! Popularity decays at every warm-up so it follows recent traffic.
+
+use crate::{
+    foundry::{simulate_on_fork, Fork, ForkManager, SimulationMode},
+    rpc::EthereumClient,
+    telemetry::ContractTelemetry,
+};
+use alloy::{
+    consensus::Transaction as _,
+    network::TransactionResponse as _,
+    primitives::{Address, Bytes, FixedBytes, B256},
+    providers::Provider,
+    rpc::types::{Transaction, TransactionRequest},
+};
+use futures::StreamExt;
+use revm::DatabaseRef;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+/// Default number of popular calls re-simulated per warm-up
+pub const DEFAULT_PREWARM_TOP_N: usize = 20;
+
+/// Target contract and selector of a call
+type CallKey = (Address, Option<FixedBytes<4>>);
+
+/// A call observed in the mempool
+struct PopularCall {
+    /// How often the call was seen (decayed at every warm-up)
+    count: u64,
+    /// Most recent instance, re-simulated to warm the state it touches
+    sample: TransactionRequest,
+}
+
+/// Keeps the fork state of likely simulation targets warm
+pub struct Prewarmer {
+    /// Contracts loaded at every new head
+    contracts: Vec<Address>,
+    /// Number of popular calls re-simulated per warm-up
+    top_n: usize,
+    /// Request telemetry and the number of most called contracts loaded per warm-up
+    telemetry: Option<(Arc<ContractTelemetry>, usize)>,
+    /// Calls observed in the mempool
+    popular: Mutex<HashMap<CallKey, PopularCall>>,
+    /// Head block of the last warm-up
+    last_warmed: Mutex<Option<B256>>,
+}
+
+impl Prewarmer {
+    /// Create a prewarmer
+    ///
+    /// # Arguments
+    ///
+    /// * `contracts` - Contracts to load at every new head
+    /// * `top_n` - Number of popular mempool calls to re-simulate per warm-up
+    pub fn new(contracts: Vec<Address>, top_n: usize) -> Self {
+        Self {
+            contracts,
+            top_n,
+            telemetry: None,
+            popular: Mutex::new(HashMap::new()),
+            last_warmed: Mutex::new(None),
+        }
+    }
+
+    /// Also load the `top_n` contracts most called through the service
+    pub fn with_telemetry(mut self, telemetry: Arc<ContractTelemetry>, top_n: usize) -> Self {
+        self.telemetry = Some((telemetry, top_n));
+        self
+    }
+
+    /// Configured contracts followed by the most called ones, without duplicates
+    fn contracts(&self) -> Vec<Address> {
+        let mut contracts = self.contracts.clone();
+        if let Some((telemetry, top_n)) = &self.telemetry {
+            for hot in telemetry.top(*top_n) {
+                if !contracts.contains(&hot.address) {
+                    contracts.push(hot.address);
+                }
+            }
+        }
+        contracts
+    }
+
+    /// Count a call towards the popular set
+    pub fn record(&self, tx_request: &TransactionRequest) {
+        let Some(to) = tx_request.to.and_then(|to| to.to().copied()) else {
+            return;
+        };
+        let selector = tx_request
+            .input
+            .input()
+            .and_then(|input| input.get(..4))
+            .map(FixedBytes::<4>::from_slice);
+
+        let mut popular = self.popular.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = popular.entry((to, selector)).or_insert_with(|| PopularCall {
+            count: 0,
+            sample: tx_request.clone(),
+        });
+        entry.count += 1;
+        entry.sample = tx_request.clone();
+    }
+
+    /// Warm the configured contracts and the most popular calls at the current head
+    ///
+    /// Does nothing if the head hasn't moved since the last warm-up.
+    pub async fn warm(&self, forks: &ForkManager) {
+        let fork = match forks.latest().await {
+            Ok(fork) => fork,
+            Err(e) => {
+                warn!("Prewarm skipped, failed to get the latest fork: {}", e);
+                return;
+            }
+        };
+        {
+            let mut last_warmed = self.last_warmed.lock().unwrap_or_else(|e| e.into_inner());
+            if *last_warmed == Some(fork.block_hash) {
+                return;
+            }
+            *last_warmed = Some(fork.block_hash);
+        }
+
+        let contracts = self.contracts();
+        for address in &contracts {
+            warm_account(&fork, *address).await;
+        }
+
+        let samples = self.take_top_samples();
+        for sample in &samples {
+            if let Err(e) = simulate_on_fork(&fork, sample, SimulationMode::Call).await {
+                debug!("Prewarm simulation failed: {}", e);
+            }
+        }
+        debug!(
+            "Prewarmed {} contracts and {} popular calls at block {}",
+            contracts.len(),
+            samples.len(),
+            fork.block_number
+        );
+    }
+
+    /// Samples of the most popular calls, decaying every count afterwards
+    fn take_top_samples(&self) -> Vec<TransactionRequest> {
+        let mut popular = self.popular.lock().unwrap_or_else(|e| e.into_inner());
+        let mut ranked: Vec<_> = popular.values().collect();
+        ranked.sort_by(|a, b| b.count.cmp(&a.count));
+        let samples = ranked.into_iter().take(self.top_n).map(|call| call.sample.clone()).collect();
+
+        popular.retain(|_, call| {
+            call.count /= 2;
+            call.count > 0
+        });
+        samples
+    }
+
+    /// Record pending transactions from the upstream mempool in the background
+    ///
+    /// Uses a pending transaction filter; nodes that don't support one are
+    /// logged and mempool warm-up stays disabled.
+    pub fn spawn_mempool_listener(self: Arc<Self>, eth_client: Arc<EthereumClient>) {
+        actix_web::rt::spawn(async move {
+            let poller = match eth_client.provider.watch_full_pending_transactions().await {
+                Ok(poller) => poller,
+                Err(e) => {
+                    warn!("Upstream doesn't support pending transaction filters, mempool warm-up disabled: {}", e);
+                    return;
+                }
+            };
+            info!("Recording pending transactions for state warm-up");
+            let mut batches = poller.into_stream();
+            while let Some(transactions) = batches.next().await {
+                for transaction in &transactions {
+                    self.record(&pending_call(transaction));
+                }
+            }
+            warn!("Pending transaction filter closed, mempool warm-up stopped");
+        });
+    }
+
+    /// Warm the fork state periodically in the background, every `interval`, which must be non-zero
+    pub fn spawn_warm_task(self: Arc<Self>, forks: Arc<ForkManager>, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.warm(&forks).await;
+            }
+        });
+        info!("Prewarming fork state every {:?}", interval);
+    }
+}
+
+/// The call a pending transaction makes
+fn pending_call(transaction: &Transaction) -> TransactionRequest {
+    let mut call = TransactionRequest::default()
+        .from(transaction.from())
+        .value(transaction.value())
+        .input(Bytes::copy_from_slice(transaction.input()).into());
+    if let Some(to) = transaction.to() {
+        call = call.to(to);
+    }
+    call
+}
+
+/// Load an account (and its code) into the fork's cache
+async fn warm_account(fork: &Fork, address: Address) {
+    let backend = fork.backend.clone();
+    // The fork backend blocks while it fetches, keep it off the async runtime
+    let loaded = tokio::task::spawn_blocking(move || backend.basic_ref(address)).await;
+    if let Ok(Err(e)) = loaded {
+        debug!("Prewarm of {} failed: {}", address, e);
+    }
+}
@@ -0,0 +1,154 @@
+//! Scheduled synthetic probes
+//!
+//! Runs a set of canonical estimations (the self-test battery by default, or
+//! the cases of `PROBES_FILE`, e.g. a WETH deposit or a USDC transfer) on a
+//! schedule, through the same path as real requests. Results and latencies are
+//! recorded per probe, giving continuous black-box verification that the
+//! simulation quality hasn't silently degraded after an upstream or dependency
+//! change.
+
+use crate::{
+    error::ServiceError,
+    estimator::GasEstimator,
+    metrics::RequestMetrics,
+    models::probes::ProbeStatus,
+    selftest::{run_case, SelfTestCase},
+};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+/// Outcome of the latest run of a probe
+#[derive(Default)]
+struct LastRun {
+    /// Unix timestamp (seconds) of the run
+    at: u64,
+    /// Estimated gas, if the probe passed
+    gas: Option<u64>,
+    /// Why the probe failed, if it did
+    error: Option<String>,
+    /// Duration of the estimation
+    latency: Duration,
+    /// Failed runs in a row, up to and including this one
+    consecutive_failures: u64,
+}
+
+/// A probe and its recorded results
+struct Probe {
+    case: SelfTestCase,
+    /// Outcomes and latencies over the metrics window
+    metrics: RequestMetrics,
+    last: Mutex<Option<LastRun>>,
+}
+
+/// Runs the probes and keeps their results
+pub struct Prober {
+    probes: Vec<Probe>,
+    /// Rolling window over which runs and failures are counted
+    window: Duration,
+}
+
+impl Prober {
+    /// Create a prober for the given cases
+    ///
+    /// # Arguments
+    ///
+    /// * `cases` - The canonical estimations to run
+    /// * `window` - Rolling window over which runs and failures are counted
+    pub(crate) fn new(cases: Vec<SelfTestCase>, window: Duration) -> Self {
+        Self {
+            probes: cases
+                .into_iter()
+                .map(|case| Probe {
+                    case,
+                    metrics: RequestMetrics::new(window),
+                    last: Mutex::new(None),
+                })
+                .collect(),
+            window,
+        }
+    }
+
+    /// Rolling window over which runs and failures are counted
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Create a prober for the cases of a JSON file
+    ///
+    /// The file holds a list of `{"name", "params", "expect"}` objects, where
+    /// `params` are `eth_estimateGas` params and `expect` is `{"exact": gas}` or
+    /// `{"atLeast": gas}`.
+    pub fn from_file(path: &Path, window: Duration) -> Result<Self, ServiceError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ServiceError::Configuration(format!("Failed to read probes file {}: {}", path.display(), e))
+        })?;
+        let cases = serde_json::from_str(&contents).map_err(|e| {
+            ServiceError::Configuration(format!("Invalid probes file {}: {}", path.display(), e))
+        })?;
+        Ok(Self::new(cases, window))
+    }
+
+    /// Run every probe once, recording the results
+    pub async fn run(&self, estimator: &GasEstimator) {
+        for (index, probe) in self.probes.iter().enumerate() {
+            let (verdict, latency) = run_case(estimator, &probe.case, index + 1).await;
+            // Failed probes count as server errors in the probe's metrics
+            probe.metrics.record(latency, verdict.is_err());
+
+            let mut last = probe.last.lock().unwrap_or_else(|e| e.into_inner());
+            let previous_failures = last.as_ref().map_or(0, |last| last.consecutive_failures);
+            let at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            *last = Some(match verdict {
+                Ok(gas) => LastRun { at, gas: Some(gas), latency, ..Default::default() },
+                Err(error) => {
+                    warn!("Probe {:?} failed: {}", probe.case.name, error);
+                    LastRun {
+                        at,
+                        error: Some(error),
+                        latency,
+                        consecutive_failures: previous_failures + 1,
+                        ..Default::default()
+                    }
+                }
+            });
+        }
+    }
+
+    /// Latest result and windowed counts of every probe
+    pub fn statuses(&self) -> Vec<ProbeStatus> {
+        self.probes
+            .iter()
+            .map(|probe| {
+                let totals = probe.metrics.totals();
+                let last = probe.last.lock().unwrap_or_else(|e| e.into_inner());
+                ProbeStatus {
+                    name: probe.case.name.clone(),
+                    passing: last.as_ref().map(|last| last.error.is_none()),
+                    last_run: last.as_ref().map(|last| last.at),
+                    last_gas: last.as_ref().and_then(|last| last.gas),
+                    last_error: last.as_ref().and_then(|last| last.error.clone()),
+                    last_latency_ms: last.as_ref().map(|last| last.latency.as_millis() as u64),
+                    consecutive_failures: last.as_ref().map_or(0, |last| last.consecutive_failures),
+                    runs: totals.requests,
+                    failures: totals.server_errors,
+                }
+            })
+            .collect()
+    }
+
+    /// Run the probes periodically in the background
+    pub fn spawn_probe_task(self: Arc<Self>, estimator: Arc<GasEstimator>, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.run(&estimator).await;
+            }
+        });
+        info!("Running {} probes every {:?}", self.probes.len(), interval);
+    }
+}
@@ -0,0 +1,223 @@
+//! Total-cost quotes and the quote endpoints
+//!
+//! Simulation reports carry a `validUntilBlock`. Relayers holding a quote can
+//! post it back to `POST /api/v1/quotes/validate`, which cheaply checks it
+//! against the current head without re-simulating (see
+//! [`eth_gas_estimator_core::quotes`]), so they know when a re-estimate is due.
+//!
+//! `POST /api/v1/quote` answers "what will this transaction cost if sent now"
+//! in one call: the recommended gas limit, the fees for each component (base
+//! fee, tip, blobs, and the L1 data fee on OP Stack chains) and their total, in
+//! wei and optionally in the caller's currency.
+
+use crate::{
+    api::{build_transaction_request, request_body},
+    error::ServiceError,
+    estimator::{EstimateOptions, GasEstimator},
+    fees::{fee_recommendations, fee_suggestion, PriorityFeeTracker, URGENCY_LEVELS},
+    foundry::Fork,
+    models::quote::{CostQuote, CostQuoteRequest, FiatCost, QuoteValidationRequest},
+    units::{format_units, parse_units, ETHER_DECIMALS},
+};
+use actix_web::{post, web, HttpResponse};
+pub use eth_gas_estimator_core::quotes::{
+    validate_quote, QuotePolicy, DEFAULT_MAX_BASE_FEE_DRIFT_PERCENT, DEFAULT_QUOTE_TTL_BLOCKS,
+};
+use alloy::primitives::U256;
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// Urgency level of cost quotes that don't name one
+pub const DEFAULT_QUOTE_URGENCY: &str = "standard";
+
+/// Decimals kept from fiat prices
+const FIAT_PRICE_DECIMALS: u32 = 8;
+
+/// Decimals of fiat totals
+const FIAT_DISPLAY_DECIMALS: u32 = 2;
+
+/// Per-gas fees a cost quote is computed with
+struct QuoteFees {
+    /// Base fee of the head block
+    base_fee: U256,
+    /// Base fee expected in the next block
+    next_base_fee: U256,
+    /// Suggested tip
+    tip: U256,
+    /// Suggested fee cap
+    max_fee: U256,
+}
+
+/// Tip and fee cap for a cost quote, with the base fees of the head
+///
+/// With priority fee tracking, the fees are the recommendation of the given
+/// urgency level; otherwise they are the node's suggestion. An unknown urgency
+/// level is invalid whether or not tracking is enabled.
+async fn quote_fees(
+    estimator: &GasEstimator,
+    tracker: Option<&PriorityFeeTracker>,
+    urgency: Option<&str>,
+    head: &Fork,
+) -> Result<QuoteFees, ServiceError> {
+    if let Some(urgency) = urgency {
+        if !URGENCY_LEVELS.iter().any(|level| level.name.eq_ignore_ascii_case(urgency)) {
+            return Err(ServiceError::InvalidParams(format!("Unknown urgency level: {}", urgency)));
+        }
+    }
+    let Some(tracker) = tracker else {
+        if urgency.is_some() {
+            return Err(ServiceError::NotFound("Priority fee tracking is disabled".to_string()));
+        }
+        let fees = fee_suggestion(estimator, head).await?;
+        return Ok(QuoteFees {
+            base_fee: fees.base_fee_per_gas,
+            next_base_fee: fees.next_base_fee_per_gas,
+            tip: fees.max_priority_fee_per_gas,
+            max_fee: fees.max_fee_per_gas,
+        });
+    };
+
+    let urgency = urgency.unwrap_or(DEFAULT_QUOTE_URGENCY);
+    let recommendations = fee_recommendations(estimator, tracker, head).await?;
+    let level = recommendations
+        .levels
+        .iter()
+        .find(|level| level.urgency.eq_ignore_ascii_case(urgency))
+        .ok_or_else(|| ServiceError::InvalidParams(format!("Unknown urgency level: {}", urgency)))?;
+    Ok(QuoteFees {
+        base_fee: recommendations.base_fee_per_gas,
+        next_base_fee: recommendations.next_base_fee_per_gas,
+        tip: level.max_priority_fee_per_gas,
+        max_fee: level.max_fee_per_gas,
+    })
+}
+
+/// Convert a wei amount at a decimal price per native token into the caller's currency
+fn fiat_cost(total_fee: U256, price: &str, currency: Option<String>) -> Result<FiatCost, ServiceError> {
+    let scaled_price = parse_units(price, FIAT_PRICE_DECIMALS).map_err(ServiceError::InvalidParams)?;
+    let total = total_fee.saturating_mul(scaled_price) / U256::from(10).pow(U256::from(ETHER_DECIMALS));
+    Ok(FiatCost {
+        currency,
+        price: price.to_string(),
+        total: format_units(total, FIAT_PRICE_DECIMALS, FIAT_DISPLAY_DECIMALS),
+    })
+}
+
+/// Quote the total cost of sending a transaction now
+///
+/// The fees are charged for the whole recommended gas limit, so the total is
+/// an upper bound of what the sender pays at the expected base fee; the unused
+/// gas of the executed transaction is not charged.
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator computing the gas limit
+/// * `tracker` - The priority fee tracker, if enabled
+/// * `request` - The transaction and the quote options
+///
+/// # Returns
+///
+/// * `Result<CostQuote, ServiceError>` - The quote, or an error if the transaction can't be
+///   estimated or the fees can't be fetched
+pub async fn cost_quote(
+    estimator: &GasEstimator,
+    tracker: Option<&PriorityFeeTracker>,
+    request: CostQuoteRequest,
+) -> Result<CostQuote, ServiceError> {
+    let mut tx_request = build_transaction_request(&request.transaction)
+        .await
+        .map_err(ServiceError::InvalidParams)?;
+    let options = EstimateOptions {
+        skip_authorization_check: request.transaction.skip_authorization_check.unwrap_or(false),
+        ..EstimateOptions::default()
+    };
+    let estimate = estimator
+        .estimate_raw_gas_with(&tx_request, &options)
+        .await
+        .map_err(ServiceError::from_report)?;
+    let head = estimator.forks.latest().await?;
+    let fees = quote_fees(estimator, tracker, request.urgency.as_deref(), &head).await?;
+
+    let gas_limit = estimate.gas;
+    let execution_fee = gas_limit.saturating_mul(fees.next_base_fee);
+    let priority_fee = gas_limit.saturating_mul(fees.tip);
+    let blob_fee = estimate
+        .blob_gas
+        .map(|blob| U256::from(blob.blob_gas_used).saturating_mul(blob.blob_base_fee));
+
+    // The L1 data fee depends on the encoded size, so it is priced with the quoted fields
+    tx_request.gas = Some(gas_limit.saturating_to());
+    tx_request.max_fee_per_gas = Some(fees.max_fee.saturating_to());
+    tx_request.max_priority_fee_per_gas = Some(fees.tip.saturating_to());
+    tx_request.gas_price = None;
+    tx_request.chain_id = Some(head.chain_id);
+    let l1_data_fee = estimator
+        .chain_adapter()
+        .await?
+        .l1_data_fee(&estimator.eth_client, &tx_request)
+        .await?;
+
+    let total_fee = execution_fee
+        .saturating_add(priority_fee)
+        .saturating_add(blob_fee.unwrap_or_default())
+        .saturating_add(l1_data_fee.unwrap_or_default());
+    let fiat = request
+        .fiat_price
+        .as_deref()
+        .map(|price| fiat_cost(total_fee, price, request.fiat_currency))
+        .transpose()?;
+    debug!("Quoted {} gas at block {} for a total of {} wei", gas_limit, estimate.block_number, total_fee);
+
+    Ok(CostQuote {
+        block_number: estimate.block_number,
+        block_hash: estimate.block_hash,
+        valid_until_block: estimate.block_number + estimator.quote_policy().ttl_blocks,
+        from: tx_request.from,
+        gas_limit,
+        base_fee_per_gas: fees.base_fee,
+        next_base_fee_per_gas: fees.next_base_fee,
+        max_priority_fee_per_gas: fees.tip,
+        max_fee_per_gas: fees.max_fee,
+        execution_fee,
+        priority_fee,
+        blob_gas_used: estimate.blob_gas.map(|blob| blob.blob_gas_used),
+        blob_fee,
+        l1_data_fee,
+        total_fee,
+        total_fee_eth: estimator.display_units().map(|units| units.eth(total_fee)),
+        fiat,
+    })
+}
+
+/// Endpoint quoting the total cost of a transaction
+#[post("/api/v1/quote")]
+async fn cost_quote_endpoint(
+    estimator: web::Data<Arc<GasEstimator>>,
+    tracker: Option<web::Data<Arc<PriorityFeeTracker>>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let tracker = tracker.as_ref().map(|tracker| tracker.get_ref().as_ref());
+    let quote = cost_quote(&estimator, tracker, request_body(body)?).await.map_err(|e| {
+        error!("Cost quote failed: {}", e);
+        e
+    })?;
+    info!("Quoted a total fee of {} wei at block {}", quote.total_fee, quote.block_number);
+    Ok(HttpResponse::Ok().json(quote))
+}
+
+/// Endpoint re-validating a previously issued quote against the current head
+#[post("/api/v1/quotes/validate")]
+async fn validate_quote_endpoint(
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let request: QuoteValidationRequest = request_body(body)?;
+    let validation = validate_quote(&estimator, &request).await?;
+    info!("Quote validation: valid={} reasons={:?}", validation.valid, validation.reasons);
+    Ok(HttpResponse::Ok().json(validation))
+}
+
+/// Configure the quote routes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(validate_quote_endpoint).service(cost_quote_endpoint);
+}
@@ -0,0 +1,280 @@
+//! Token-bucket rate limiting
+//!
+//! Simulations are expensive, so public deployments need a cap on how many
+//! requests they accept. Requests take a token from a global bucket and from a
+//! bucket per client IP; when either is empty the request is answered with
+//! `429 Too Many Requests` and a JSON-RPC `limit exceeded` error instead of
+//! being simulated. The health check and probes are never limited.
+//!
+//! The middleware charges one token per HTTP request (or WebSocket upgrade).
+//! Handlers charge the rest through the request's [`Admission`]: the other
+//! items of a batch, and every request sent over a WebSocket connection. A
+//! batch is admitted while a token is left and pays for all of its items, so a
+//! large batch delays the caller's next requests rather than being refused
+//! outright. Behind a reverse proxy, listed in `RATE_LIMIT_TRUSTED_PROXIES`,
+//! clients are told apart by the `X-Forwarded-For` address the proxy appended.
+
+use crate::{api::PROBE_PATHS, error::retry_after_secs, models::jsonrpc::JsonRpcError};
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderMap, RETRY_AFTER},
+    Error, HttpMessage, HttpRequest, HttpResponse,
+};
+use futures::{
+    future::{ready, Either},
+    FutureExt,
+};
+use lru::LruCache;
+use std::{
+    future::Future,
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Maximum number of client IPs with a tracked bucket (least recently seen are dropped)
+const MAX_TRACKED_CLIENTS: NonZeroUsize = NonZeroUsize::MIN.saturating_add(10_000 - 1);
+
+/// Header a reverse proxy appends the address of its client to
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Sustained rate and burst size of a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Tokens added per second
+    pub per_second: f64,
+    /// Bucket capacity, i.e. the largest burst accepted
+    pub burst: f64,
+}
+
+/// A token bucket
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self { limit, tokens: limit.burst, updated: now }
+    }
+
+    /// Refill the bucket up to `now`, then report whether it admits a request
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Duration>` - `Ok` if at least one token is left, otherwise how long
+    ///   until one is available
+    fn admits(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.limit.per_second))
+        }
+    }
+
+    /// Take `cost` tokens from a bucket that [`TokenBucket::admits`] a request
+    ///
+    /// A cost above the tokens left is still taken, leaving the bucket in debt
+    /// until it refills.
+    fn take(&mut self, cost: f64) {
+        self.tokens -= cost;
+    }
+}
+
+/// Global and per-client-IP rate limiter
+pub struct RateLimiter {
+    /// Bucket shared by every client, if limited
+    global: Mutex<Option<TokenBucket>>,
+    /// Limit applied to each client IP, if any
+    per_client: Mutex<Option<RateLimit>>,
+    /// Buckets of recently seen clients
+    clients: Mutex<LruCache<IpAddr, TokenBucket>>,
+    /// Reverse proxies whose `X-Forwarded-For` header names the client
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter; a `None` limit disables that level
+    ///
+    /// # Arguments
+    ///
+    /// * `global` - Limit shared by every client
+    /// * `per_client` - Limit applied to each client IP
+    pub fn new(global: Option<RateLimit>, per_client: Option<RateLimit>) -> Self {
+        let now = Instant::now();
+        Self {
+            global: Mutex::new(global.map(|limit| TokenBucket::new(limit, now))),
+            per_client: Mutex::new(per_client),
+            clients: Mutex::new(LruCache::new(MAX_TRACKED_CLIENTS)),
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    /// Tell clients behind these reverse proxies apart by their `X-Forwarded-For` header
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Take tokens for `count` requests from `client`
+    ///
+    /// Tokens are only taken when both the client's bucket and the global one
+    /// admit the requests, so a client isn't charged for requests the global
+    /// limit turned away.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Duration>` - `Ok` if the requests may proceed, otherwise how long
+    ///   the client should wait before retrying
+    pub fn check(&self, client: Option<IpAddr>, count: usize) -> Result<(), Duration> {
+        if count == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let cost = count as f64;
+        let per_client = *self.per_client.lock().unwrap_or_else(|e| e.into_inner());
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let mut client_bucket = match (per_client, client) {
+            (Some(limit), Some(client)) => Some(clients.get_or_insert_mut(client, || TokenBucket::new(limit, now))),
+            _ => None,
+        };
+        let mut global = self.global.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(bucket) = client_bucket.as_deref_mut() {
+            bucket.admits(now)?;
+        }
+        if let Some(bucket) = global.as_mut() {
+            bucket.admits(now)?;
+        }
+        client_bucket.into_iter().chain(global.as_mut()).for_each(|bucket| bucket.take(cost));
+        Ok(())
+    }
+
+    /// The client a request comes from
+    ///
+    /// The connection's peer, unless it is a trusted proxy: then the last
+    /// `X-Forwarded-For` address not added by a trusted proxy. Addresses further
+    /// left were written by the client itself and can't be trusted.
+    fn client(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = peer?;
+        if !self.trusted_proxies.contains(&client) {
+            return Some(client);
+        }
+        let forwarded: Vec<&str> = headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        for hop in forwarded.iter().rev() {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(hop) => {
+                    client = hop;
+                    if !self.trusted_proxies.contains(&hop) {
+                        break;
+                    }
+                }
+                // Nothing left of a malformed entry can be trusted
+                Err(_) => break,
+            }
+        }
+        Some(client)
+    }
+
+    /// Replace the limits at runtime; a `None` limit disables that level
+    ///
+    /// Every bucket starts over full under the new limits.
+    pub fn set_limits(&self, global: Option<RateLimit>, per_client: Option<RateLimit>) {
+        let now = Instant::now();
+        *self.global.lock().unwrap_or_else(|e| e.into_inner()) = global.map(|limit| TokenBucket::new(limit, now));
+        *self.per_client.lock().unwrap_or_else(|e| e.into_inner()) = per_client;
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// A request's standing with the rate limiter, attached to it by [`rate_limit`]
+#[derive(Clone)]
+pub struct Admission {
+    limiter: Arc<RateLimiter>,
+    client: Option<IpAddr>,
+}
+
+impl Admission {
+    /// Charge `count` more requests to the client, e.g. the other items of a batch
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Duration>` - `Ok` if the requests may proceed, otherwise how long
+    ///   the client should wait before retrying
+    pub fn charge(&self, count: usize) -> Result<(), Duration> {
+        let verdict = self.limiter.check(self.client, count);
+        if verdict.is_err() {
+            debug!("Rate limited {} requests from {:?}", count, self.client);
+        }
+        verdict
+    }
+}
+
+/// The admission of a request, `None` without a rate limiter (or for probes)
+pub fn request_admission(req: &HttpRequest) -> Option<Admission> {
+    req.extensions().get::<Admission>().cloned()
+}
+
+/// Charge the items of a JSON-RPC batch beyond the first, which the middleware already paid for
+///
+/// # Returns
+///
+/// * `Option<HttpResponse>` - The `429 Too Many Requests` response to send instead of
+///   serving the batch, if the client is over its rate
+pub fn charge_batch(req: &HttpRequest, items: usize) -> Option<HttpResponse> {
+    let admission = request_admission(req)?;
+    admission.charge(items.saturating_sub(1)).err().map(limit_exceeded)
+}
+
+/// The response to a request over the configured rate
+pub fn limit_exceeded(retry_after: Duration) -> HttpResponse {
+    let rpc_error = JsonRpcError::limit_exceeded(serde_json::Value::Null, "Rate limit exceeded".to_string());
+    HttpResponse::TooManyRequests()
+        .insert_header((RETRY_AFTER, retry_after_secs(retry_after).to_string()))
+        .json(rpc_error)
+}
+
+/// Middleware rejecting requests over the configured rate
+///
+/// Used with `App::wrap_fn`. Limited requests are answered with `429 Too Many
+/// Requests`, a `Retry-After` header and a JSON-RPC `limit exceeded` error.
+/// Admitted requests carry their [`Admission`], for handlers to charge the
+/// requests they serve on top.
+pub fn rate_limit<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+    limiter: &Arc<RateLimiter>,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    if PROBE_PATHS.contains(&req.path()) {
+        return Either::Left(srv.call(req).map(|response| response.map(ServiceResponse::map_into_boxed_body)));
+    }
+    let client = limiter.client(req.peer_addr().map(|addr| addr.ip()), req.headers());
+    match limiter.check(client, 1) {
+        Ok(()) => {
+            req.extensions_mut().insert(Admission { limiter: limiter.clone(), client });
+            Either::Left(srv.call(req).map(|response| response.map(ServiceResponse::map_into_boxed_body)))
+        }
+        Err(retry_after) => {
+            debug!("Rate limited request from {:?} to {}", client, req.path());
+            Either::Right(ready(Ok(req.into_response(limit_exceeded(retry_after)))))
+        }
+    }
+}
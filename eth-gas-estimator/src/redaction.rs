@@ -0,0 +1,249 @@
+//! Per-tenant redaction of verbose response fields
+//!
+//! One deployment can serve a public free tier plain estimates and internal
+//! callers full diagnostics. Operators map API keys (sent in the `X-API-Key`
+//! header) to tiers with `API_KEYS`, and list per tier the verbose fields it
+//! may not receive with `REDACTION_POLICIES`:
+//!
+//! * `traces` - `callTracer` output of `debug_traceCall` and the `callFrames`
+//!   of detailed and traced v2 estimates
+//! * `stateDiffs` - `prestateTracer` output of `debug_traceCall` and any
+//!   `stateDiff` field
+//! * `logs` - emitted logs, e.g. in `eth_simulateV1` results
+//!
+//! Requests without a key belong to the `anonymous` tier; requests with an
+//! unknown key are rejected with `401 Unauthorized`, except for the health
+//! check and probes, which need no key, and the admin endpoints, which are
+//! authorized by their own token. Keys are compared in constant time, so
+//! response times don't reveal how much of a key was right. Methods whose whole result
+//! is a withheld field are refused with a JSON-RPC `-32004` error; elsewhere
+//! the withheld fields are removed from the result.
+
+use crate::{
+    admin::{constant_time_eq, ADMIN_PATH_PREFIX},
+    api::PROBE_PATHS,
+    error::ServiceError,
+};
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    Error, HttpMessage, HttpRequest, ResponseError,
+};
+use futures::{
+    future::{ready, Either},
+    FutureExt,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+};
+use tracing::debug;
+
+/// Header carrying the caller's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Tier of requests sent without an API key
+pub const ANONYMOUS_TIER: &str = "anonymous";
+
+/// A kind of verbose response field that can be withheld
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VerboseField {
+    /// Call traces
+    Traces,
+    /// State diffs and prestates
+    StateDiffs,
+    /// Emitted logs
+    Logs,
+}
+
+impl VerboseField {
+    /// Keys holding this kind of field in JSON-RPC results
+    fn keys(&self) -> &'static [&'static str] {
+        match self {
+            VerboseField::Traces => &["callFrames"],
+            VerboseField::StateDiffs => &["stateDiff"],
+            VerboseField::Logs => &["logs"],
+        }
+    }
+}
+
+impl FromStr for VerboseField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "traces" => Ok(VerboseField::Traces),
+            "stateDiffs" => Ok(VerboseField::StateDiffs),
+            "logs" => Ok(VerboseField::Logs),
+            other => Err(format!("Unknown verbose field: {}", other)),
+        }
+    }
+}
+
+/// Verbose fields withheld from a request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redaction {
+    fields: BTreeSet<VerboseField>,
+}
+
+impl Redaction {
+    /// Whether nothing is withheld
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Check that a method's result isn't entirely made of withheld fields
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The JSON-RPC method
+    /// * `params` - Its params
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Nothing, or why the method is refused
+    pub fn check(&self, method: &str, params: &serde_json::Value) -> Result<(), String> {
+        if method != "debug_traceCall" {
+            return Ok(());
+        }
+        let tracer = params.get(2).and_then(|options| options.get("tracer")).and_then(|tracer| tracer.as_str());
+        let field = match tracer {
+            Some("prestateTracer") => VerboseField::StateDiffs,
+            _ => VerboseField::Traces,
+        };
+        if self.fields.contains(&field) {
+            return Err(format!("{} is not available to this API key", method));
+        }
+        Ok(())
+    }
+
+    /// Remove the withheld fields from a result, at any depth
+    pub fn apply(&self, value: &mut serde_json::Value) {
+        if self.is_empty() {
+            return;
+        }
+        match value {
+            serde_json::Value::Object(object) => {
+                for field in &self.fields {
+                    for key in field.keys() {
+                        object.remove(*key);
+                    }
+                }
+                object.values_mut().for_each(|value| self.apply(value));
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|value| self.apply(value)),
+            _ => {}
+        }
+    }
+}
+
+/// API key tiers and the fields withheld from each
+#[derive(Debug, Clone, Default)]
+pub struct RedactionPolicies {
+    /// Tier of each API key
+    tiers: HashMap<String, String>,
+    /// Fields withheld from each tier; tiers not listed receive everything
+    policies: HashMap<String, Redaction>,
+}
+
+impl RedactionPolicies {
+    /// Parse the API key and redaction settings
+    ///
+    /// # Arguments
+    ///
+    /// * `api_keys` - `key=tier` entries separated by commas, e.g. `k1=free,k2=internal`
+    /// * `policies` - `tier=field|field` entries separated by commas, e.g.
+    ///   `anonymous=traces|stateDiffs|logs,free=traces|stateDiffs`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The policies, or an error describing the invalid entry
+    pub fn new(api_keys: &str, policies: &str) -> Result<Self, String> {
+        let entries = |setting: &str| -> Result<Vec<(String, String)>, String> {
+            setting
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    entry
+                        .split_once('=')
+                        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                        .ok_or_else(|| format!("Expected name=value, got {}", entry))
+                })
+                .collect()
+        };
+
+        let tiers: HashMap<String, String> = entries(api_keys)?.into_iter().collect();
+        let mut parsed = HashMap::new();
+        for (tier, fields) in entries(policies)? {
+            let fields = fields
+                .split('|')
+                .filter(|field| !field.trim().is_empty())
+                .map(VerboseField::from_str)
+                .collect::<Result<BTreeSet<_>, _>>()?;
+            parsed.insert(tier, Redaction { fields });
+        }
+        Ok(Self { tiers, policies: parsed })
+    }
+
+    /// Fields withheld from a request sent with `api_key`, or `None` for an unknown key
+    ///
+    /// The key is compared with every known key, without stopping at a match.
+    pub fn resolve(&self, api_key: Option<&str>) -> Option<Redaction> {
+        let tier = match api_key {
+            Some(key) => self
+                .tiers
+                .iter()
+                .fold(None, |tier, (known, name)| {
+                    if constant_time_eq(known.as_bytes(), key.as_bytes()) {
+                        Some(name.as_str())
+                    } else {
+                        tier
+                    }
+                })?,
+            None => ANONYMOUS_TIER,
+        };
+        Some(self.policies.get(tier).cloned().unwrap_or_default())
+    }
+}
+
+/// Fields withheld from a request, as resolved by [`resolve_redaction`]
+pub fn request_redaction(req: &HttpRequest) -> Redaction {
+    req.extensions().get::<Redaction>().cloned().unwrap_or_default()
+}
+
+/// Middleware resolving the caller's tier from its API key
+///
+/// Used with `App::wrap_fn`. The fields withheld from the caller are attached
+/// to the request for the JSON-RPC handlers; requests with an unknown key are
+/// answered with `401 Unauthorized`, unless they are probes or admin requests.
+pub fn resolve_redaction<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+    policies: &Arc<RedactionPolicies>,
+) -> impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody + 'static,
+{
+    let api_key = req.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+    // Orchestrators probe without a key, and admin requests carry the admin token instead
+    let resolved = if PROBE_PATHS.contains(&req.path()) || req.path().starts_with(ADMIN_PATH_PREFIX) {
+        Some(Redaction::default())
+    } else {
+        policies.resolve(api_key)
+    };
+    match resolved {
+        Some(redaction) => {
+            req.extensions_mut().insert(redaction);
+            Either::Left(srv.call(req).map(|response| response.map(ServiceResponse::map_into_boxed_body)))
+        }
+        None => {
+            debug!("Rejected request to {} with an unknown API key", req.path());
+            let response = ServiceError::Unauthorized("Unknown API key".to_string()).error_response();
+            Either::Right(ready(Ok(req.into_response(response))))
+        }
+    }
+}
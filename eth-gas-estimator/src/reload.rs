@@ -0,0 +1,115 @@
+//! Runtime configuration reload
+//!
+//! Re-reads the configuration (file and environment) and applies the settings
+//! that can change without a restart:
+//!
+//! * the upstream RPC URL (`ETHEREUM_RPC_URL`); the current fork and its cached
+//!   state are kept, only state not yet cached is fetched from the new upstream,
+//!   so a node serving another chain is rejected
+//! * the rate limits (`RATE_LIMIT_*`)
+//! * the quote fee parameters (`QUOTE_TTL_BLOCKS`, `QUOTE_MAX_BASE_FEE_DRIFT_PERCENT`)
+//!
+//! Other settings are only read at startup. A reload is triggered from the
+//! admin API, or by changes to the config file when watching is enabled.
+
+use crate::{config::Config, error::ServiceError, estimator::GasEstimator, rate_limit::RateLimiter};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Applies configuration changes to the running service
+pub struct ConfigReloader {
+    /// The estimator whose upstream and quote policy are updated
+    estimator: Arc<GasEstimator>,
+    /// The rate limiter whose limits are updated
+    limiter: Arc<RateLimiter>,
+    /// Configuration currently applied, locked for the whole of a reload
+    current: Mutex<Config>,
+}
+
+impl ConfigReloader {
+    /// Create a reloader for a service started with `config`
+    pub fn new(config: Config, estimator: Arc<GasEstimator>, limiter: Arc<RateLimiter>) -> Self {
+        Self {
+            estimator,
+            limiter,
+            current: Mutex::new(config),
+        }
+    }
+
+    /// Reload the configuration and apply what changed
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<&'static str>, ServiceError>` - Names of the settings groups that
+    ///   changed and were applied, or an error if the configuration is invalid or the
+    ///   new upstream serves another chain (in which case nothing is applied)
+    pub async fn reload(&self) -> Result<Vec<&'static str>, ServiceError> {
+        // Reading the file blocks, so it's kept off the async runtime
+        let mut config = tokio::task::spawn_blocking(Config::load)
+            .await
+            .map_err(|e| ServiceError::Configuration(format!("Config reload failed: {}", e)))?
+            .map_err(|e| ServiceError::Configuration(e.to_string()))?;
+        let mut current = self.current.lock().await;
+        let mut applied = Vec::new();
+
+        // In dev mode the upstream is the service's own Anvil node, whatever the file says
+        if current.dev_anvil {
+            config.dev_anvil = true;
+            config.ethereum_rpc_url = current.ethereum_rpc_url.clone();
+        }
+
+        if config.ethereum_rpc_url != current.ethereum_rpc_url {
+            self.estimator.set_upstream_url(&config.ethereum_rpc_url).await?;
+            applied.push("upstream");
+        }
+        if config.rate_limits() != current.rate_limits() {
+            let (global, per_client) = config.rate_limits();
+            self.limiter.set_limits(global, per_client);
+            applied.push("rateLimits");
+        }
+        if config.quote_policy() != current.quote_policy() {
+            self.estimator.set_quote_policy(config.quote_policy());
+            applied.push("quotePolicy");
+        }
+
+        info!("Configuration reloaded, applied: {:?}", applied);
+        *current = config;
+        Ok(applied)
+    }
+
+    /// Reload whenever the config file's modification time changes
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The config file to watch
+    /// * `interval` - How often the file is checked
+    pub fn spawn_watch_task(self: Arc<Self>, path: PathBuf, interval: Duration) {
+        actix_web::rt::spawn(async move {
+            let mut last_modified = modified(&path).await;
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let current = modified(&path).await;
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                debug!("Config file {} changed", path.display());
+                if let Err(e) = self.reload().await {
+                    warn!("Config reload failed, keeping the previous configuration: {}", e);
+                }
+            }
+        });
+        info!("Watching {} for configuration changes every {:?}", path.display(), interval);
+    }
+}
+
+/// Modification time of a file, `None` if it can't be read
+async fn modified(path: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,249 @@
+use crate::{
+    api::{process_raw_request, METHODS},
+    config::Config,
+    estimator::GasEstimator,
+    redaction::Redaction,
+};
+use alloy::{
+    eips::eip7702::{Authorization, SignedAuthorization},
+    primitives::{keccak256, Address, U256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Recipient used by the transfer checks (the second default Anvil account)
+const SELFTEST_RECIPIENT: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+/// Phrase the signing key of the EIP-7702 check's authorization is derived from
+const SELFTEST_AUTHORITY_SEED: &[u8] = b"eth-gas-estimator selftest authority";
+
+/// Account the EIP-7702 check delegates to
+const SELFTEST_DELEGATE: Address = Address::repeat_byte(0xcc);
+
+/// Init code of a minimal storage contract, used by the deployment check
+const SELFTEST_INIT_CODE: &str = "0x608060405234801561001057600080fd5b5060c78061001f6000396000f3fe6080604052348015600f57600080fd5b506004361060325760003560e01c806360fe47b11460375780636d4ce63c146062575b600080fd5b606060048036036020811015604b57600080fd5b8101908080359060200190929190505050607e565b005b60686088565b6040518082815260200191505060405180910390f35b8060008190555050565b6000805490509056fea264697066735822122018e873e978df16c207f8f6ed18612b17e2c2a70d0916ff978c0755f6a45e26fc64736f6c634300060c0033";
+
+/// Expected outcome of a self-test estimation
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Expectation {
+    /// The estimate must match exactly
+    Exact(u64),
+    /// The estimate must be at least this much
+    AtLeast(u64),
+}
+
+/// A canonical estimation exercised by the self-test (and the scheduled probes)
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct SelfTestCase {
+    pub(crate) name: String,
+    params: serde_json::Value,
+    #[serde(rename = "expect")]
+    expectation: Expectation,
+}
+
+/// Run the self-test battery against the configured upstream and print a report
+///
+/// Each case mirrors one of the integration tests (transfer, ERC-20 transfer,
+/// contract deployment and the 1559/2930/4844/7702 variants) and is sent
+/// through the same JSON-RPC processing path as real requests.
+///
+/// # Returns
+///
+/// * `true` if every case passed
+pub async fn run(estimator: &GasEstimator, config: &Config) -> bool {
+    let cases = selftest_cases(config);
+    println!("Running {} self-test estimations against {}", cases.len(), config.ethereum_rpc_url);
+
+    let mut failures = 0;
+    for (index, case) in cases.iter().enumerate() {
+        let (verdict, elapsed) = run_case(estimator, case, index + 1).await;
+        let elapsed = elapsed.as_millis();
+
+        match verdict {
+            Ok(gas) => println!("PASS  {:<28} {:>10} gas  {:>6} ms", case.name, gas, elapsed),
+            Err(reason) => {
+                failures += 1;
+                println!("FAIL  {:<28} {}  {:>6} ms", case.name, reason, elapsed);
+            }
+        }
+    }
+
+    if failures == 0 {
+        println!("Self-test passed");
+    } else {
+        println!("Self-test failed: {} case(s) did not pass", failures);
+    }
+    failures == 0
+}
+
+/// Run one case through the JSON-RPC processing path
+///
+/// # Arguments
+///
+/// * `estimator` - The estimator to run against
+/// * `case` - The case to run
+/// * `id` - JSON-RPC id of the request
+///
+/// # Returns
+///
+/// * The estimated gas if it met the expectation, otherwise why not, and how long the estimation took
+pub(crate) async fn run_case(estimator: &GasEstimator, case: &SelfTestCase, id: usize) -> (Result<u64, String>, Duration) {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_estimateGas",
+        "params": [case.params],
+        "id": id,
+    });
+
+    let started = Instant::now();
+    let outcome = process_raw_request(estimator, request, METHODS, &Redaction::default()).await;
+    let elapsed = started.elapsed();
+
+    let verdict = match outcome {
+        Ok(success) => match success.result.as_str().map(parse_gas) {
+            Some(Ok(gas)) => match case.expectation {
+                Expectation::Exact(expected) if gas == expected => Ok(gas),
+                Expectation::AtLeast(minimum) if gas >= minimum => Ok(gas),
+                Expectation::Exact(expected) => Err(format!("expected {} gas, got {}", expected, gas)),
+                Expectation::AtLeast(minimum) => Err(format!("expected at least {} gas, got {}", minimum, gas)),
+            },
+            Some(Err(e)) => Err(e),
+            None => Err(format!("unexpected result: {}", success.result)),
+        },
+        Err(rpc_error) => Err(format!("{} ({})", rpc_error.error.message, rpc_error.error.code)),
+    };
+    (verdict, elapsed)
+}
+
+/// Build the canonical self-test cases for the configured sender
+pub(crate) fn selftest_cases(config: &Config) -> Vec<SelfTestCase> {
+    let from = config.selftest_from.as_str();
+    let mut cases = vec![
+        SelfTestCase {
+            name: "transfer (legacy)".to_string(),
+            params: json!({
+                "from": from,
+                "to": SELFTEST_RECIPIENT,
+                "gasPrice": "0x2540be400",
+                "value": "0x0"
+            }),
+            expectation: Expectation::Exact(21_000),
+        },
+        SelfTestCase {
+            name: "transfer (EIP-1559)".to_string(),
+            params: json!({
+                "from": from,
+                "to": SELFTEST_RECIPIENT,
+                "maxFeePerGas": "0x4a817c800",
+                "maxPriorityFeePerGas": "0x77359400",
+                "value": "0x0",
+                "type": "0x2"
+            }),
+            expectation: Expectation::Exact(21_000),
+        },
+        SelfTestCase {
+            name: "access list (EIP-2930)".to_string(),
+            params: json!({
+                "from": from,
+                "to": SELFTEST_RECIPIENT,
+                "gas": "0x7a120",
+                "gasPrice": "0x2540be400",
+                "value": "0x0",
+                "accessList": [{
+                    "address": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+                    "storageKeys": [
+                        "0x0101010101010101010101010101010101010101010101010101010101010101",
+                        "0x0202020202020202020202020202020202020202020202020202020202020202"
+                    ]
+                }],
+                "type": "0x1"
+            }),
+            expectation: Expectation::Exact(27_200),
+        },
+        SelfTestCase {
+            name: "blob transaction (EIP-4844)".to_string(),
+            params: json!({
+                "from": from,
+                "to": SELFTEST_RECIPIENT,
+                "gas": "0x7a120",
+                "maxFeePerGas": "0x4a817c800",
+                "maxPriorityFeePerGas": "0x77359400",
+                "maxFeePerBlobGas": "0x989680",
+                "blobVersionedHashes": [
+                    "0x01aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+                ],
+                "type": "0x3"
+            }),
+            expectation: Expectation::Exact(21_000),
+        },
+        SelfTestCase {
+            name: "contract deployment".to_string(),
+            params: json!({
+                "from": from,
+                "gas": "0x493e0",
+                "data": SELFTEST_INIT_CODE
+            }),
+            expectation: Expectation::AtLeast(53_000),
+        },
+    ];
+
+    if let Some(authorization) = selftest_authorization() {
+        cases.push(SelfTestCase {
+            name: "authorization (EIP-7702)".to_string(),
+            params: json!({
+                "from": from,
+                "to": SELFTEST_RECIPIENT,
+                "gas": "0x7a120",
+                "maxFeePerGas": "0x4a817c800",
+                "maxPriorityFeePerGas": "0x77359400",
+                "value": "0x0",
+                "authorizationList": [authorization],
+                "type": "0x4"
+            }),
+            // 21000 plus 25000 per authorization
+            expectation: Expectation::Exact(46_000),
+        });
+    }
+
+    // The ERC-20 check needs a token deployed on the target chain
+    if let Some(token) = &config.selftest_token {
+        // transfer(SELFTEST_RECIPIENT, 0)
+        let calldata = format!(
+            "0xa9059cbb000000000000000000000000{}{}",
+            SELFTEST_RECIPIENT.trim_start_matches("0x").to_lowercase(),
+            "0".repeat(64)
+        );
+        cases.push(SelfTestCase {
+            name: "ERC-20 transfer".to_string(),
+            params: json!({
+                "from": from,
+                "to": token,
+                "gas": "0x186a0",
+                "data": calldata
+            }),
+            expectation: Expectation::AtLeast(21_000),
+        });
+    }
+
+    cases
+}
+
+/// A signed EIP-7702 authorization for the delegation check, valid on every chain
+///
+/// The key is derived from a fixed phrase and public, so anyone may have used
+/// its account; the estimate doesn't depend on it, since an authorization costs
+/// the same whether or not its nonce still matches. `None` if signing fails.
+fn selftest_authorization() -> Option<SignedAuthorization> {
+    let signer = PrivateKeySigner::from_bytes(&keccak256(SELFTEST_AUTHORITY_SEED)).ok()?;
+    let authorization = Authorization { chain_id: U256::ZERO, address: SELFTEST_DELEGATE, nonce: 0 };
+    let signature = signer.sign_hash_sync(&authorization.signature_hash()).ok()?;
+    Some(authorization.into_signed(signature))
+}
+
+/// Parse a hex gas quantity returned by eth_estimateGas
+fn parse_gas(hex: &str) -> Result<u64, String> {
+    u64::from_str_radix(hex.trim_start_matches("0x"), 16).map_err(|e| format!("invalid gas value {}: {}", hex, e))
+}
@@ -0,0 +1,57 @@
+//! Estimation of dependent transaction sequences
+//!
+//! Multi-step flows such as approve → swap can't be estimated one transaction
+//! at a time: the swap reverts against the head because the allowance it needs
+//! only exists after the approval. The sequence endpoint runs the transactions
+//! in order on one mutable fork, committing each result before the next, and
+//! reports the gas of every step. Unlike the nonce ladder, the transactions may
+//! come from different senders and keep their own nonces.
+
+use crate::{
+    api::{build_transaction_requests, request_body},
+    error::ServiceError,
+    estimator::GasEstimator,
+    models::sequence::SequenceRequest,
+};
+use actix_web::{post, web, HttpResponse};
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Most transactions a single sequence may carry
+pub const MAX_SEQUENCE_TRANSACTIONS: usize = 64;
+
+/// Estimate transactions that depend on each other's state changes
+#[post("/api/v1/eth/estimateGas/sequence")]
+async fn estimate_gas_sequence(
+    estimator: web::Data<Arc<GasEstimator>>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, ServiceError> {
+    let request: SequenceRequest = request_body(body)?;
+    if request.transactions.is_empty() {
+        return Err(ServiceError::InvalidParams("Sequence must contain at least one transaction".to_string()));
+    }
+    // Every step is searched for its limit on top of all earlier ones, in one blocking task
+    if request.transactions.len() > MAX_SEQUENCE_TRANSACTIONS {
+        return Err(ServiceError::InvalidParams(format!(
+            "Sequence may contain at most {} transactions",
+            MAX_SEQUENCE_TRANSACTIONS
+        )));
+    }
+    // Every step's state depends on who sent the earlier ones
+    if let Some(index) = request.transactions.iter().position(|tx_params| tx_params.from.is_none()) {
+        return Err(ServiceError::InvalidParams(format!("transaction {}: from is required", index)));
+    }
+    let tx_requests = build_transaction_requests(&request.transactions).await?;
+
+    info!("Estimating sequence of {} dependent transactions", tx_requests.len());
+    let sequence = estimator.estimate_sequence(&tx_requests).await.map_err(|e| {
+        error!("Sequence estimation failed: {:?}", e);
+        ServiceError::from_report(e)
+    })?;
+    Ok(HttpResponse::Ok().json(sequence))
+}
+
+/// Configure the dependent transaction sequence route
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(estimate_gas_sequence);
+}
@@ -0,0 +1,98 @@
+//! Service level objectives and error budgets
+//!
+//! Compares the rolling request metrics (see [`crate::metrics`]) against a
+//! success-rate and a latency objective. Each objective allows a share of
+//! "bad" requests over the window (its error budget); the report tells how
+//! much of that budget is left, so on-call staff can see at a glance whether
+//! the estimator is within it.
+
+use crate::{
+    metrics::RequestCounts,
+    models::slo::{ObjectiveReport, SloReport},
+};
+use std::time::Duration;
+
+/// Default share of requests that must not fail with a server error
+pub const DEFAULT_SUCCESS_OBJECTIVE: f64 = 0.999;
+
+/// Default latency threshold of the latency objective
+pub const DEFAULT_LATENCY_THRESHOLD: Duration = Duration::from_millis(1_000);
+
+/// Default share of requests that must complete within the latency threshold
+pub const DEFAULT_LATENCY_OBJECTIVE: f64 = 0.99;
+
+/// Success-rate and latency objectives
+#[derive(Debug, Clone, Copy)]
+pub struct SloObjectives {
+    /// Share of requests that must not fail with a server error
+    pub success_rate: f64,
+    /// Latency threshold of the latency objective
+    pub latency_threshold: Duration,
+    /// Share of requests that must complete within `latency_threshold`
+    pub latency: f64,
+}
+
+impl Default for SloObjectives {
+    fn default() -> Self {
+        Self {
+            success_rate: DEFAULT_SUCCESS_OBJECTIVE,
+            latency_threshold: DEFAULT_LATENCY_THRESHOLD,
+            latency: DEFAULT_LATENCY_OBJECTIVE,
+        }
+    }
+}
+
+/// Compute SLO attainment and remaining error budgets
+///
+/// # Arguments
+///
+/// * `counts` - Request counts over the window
+/// * `window` - Length of the window the counts cover
+/// * `objectives` - The objectives to report against
+///
+/// # Returns
+///
+/// * The attainment and remaining error budget of each objective
+pub fn slo_report(counts: &RequestCounts, window: Duration, objectives: &SloObjectives) -> SloReport {
+    let threshold_ms = objectives.latency_threshold.as_millis() as u64;
+    let successful = counts.requests - counts.server_errors;
+    let fast = counts.within_latency(threshold_ms);
+
+    let success_rate = objective_report(objectives.success_rate, successful, counts.requests);
+    let latency = objective_report(objectives.latency, fast, counts.requests);
+    SloReport {
+        window_secs: window.as_secs(),
+        requests: counts.requests,
+        within_budget: success_rate.within_budget && latency.within_budget,
+        latency_threshold_ms: threshold_ms,
+        success_rate,
+        latency,
+    }
+}
+
+/// Attainment and error budget of one objective
+///
+/// # Arguments
+///
+/// * `objective` - Required share of good requests
+/// * `good` - Requests meeting the objective
+/// * `total` - Requests in the window
+fn objective_report(objective: f64, good: u64, total: u64) -> ObjectiveReport {
+    let bad = (total - good) as f64;
+    let allowed_bad = (1.0 - objective) * total as f64;
+    let error_budget_remaining = if total == 0 || bad == 0.0 {
+        1.0
+    } else if allowed_bad > 0.0 {
+        1.0 - bad / allowed_bad
+    } else {
+        // A 100% objective has no budget to spend
+        0.0
+    };
+
+    ObjectiveReport {
+        objective,
+        attained: (total > 0).then(|| good as f64 / total as f64),
+        error_budget_remaining,
+        within_budget: bad <= allowed_bad,
+    }
+}
@@ -0,0 +1,195 @@
+//! Replica bootstrap from a peer's fork state
+//!
+//! A replica started after a scaling event has empty fork caches, so its first
+//! simulations pay for every account, code and storage fetch. `GET /admin/snapshot`
+//! exports the state the current fork has loaded; with `SNAPSHOT_PEER_URL` set,
+//! a new replica downloads a healthy peer's snapshot at startup and preloads it
+//! before serving:
+//!
+//! * if the peer's block is still the head, the state is imported as is
+//! * otherwise the same accounts and storage slots are fetched again at the
+//!   current head, so the peer's working set is warm once serving starts
+//!
+//! Snapshots of another chain, or of a block that isn't on the upstream's
+//! canonical chain, are rejected before anything is imported. The admin token
+//! is only sent to peers whose host is allow-listed. A failed download or
+//! preload is logged and the replica starts cold.
+
+use crate::{
+    error::ServiceError,
+    foundry::{Fork, ForkManager},
+    models::snapshot::{AccountSnapshot, StateSnapshot},
+};
+use alloy::primitives::U256;
+use futures::{stream, StreamExt};
+use revm::{
+    primitives::{AccountInfo, Bytecode},
+    DatabaseRef,
+};
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Upper bound on downloading a peer's snapshot
+pub const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of accounts fetched again concurrently when the head has moved
+const REFETCH_CONCURRENCY: usize = 32;
+
+/// Export the state a fork has loaded so far
+pub fn export(fork: &Fork) -> StateSnapshot {
+    // Copied out first, so simulations aren't blocked on the locks while the snapshot is built
+    let cached_accounts = fork.state.accounts().read().clone();
+    let cached_storage = fork.state.storage().read().clone();
+    let mut accounts: Vec<AccountSnapshot> = cached_accounts
+        .iter()
+        .map(|(address, info)| AccountSnapshot {
+            address: *address,
+            balance: info.balance,
+            nonce: info.nonce,
+            code: info.code.as_ref().map(|code| code.original_bytes()).unwrap_or_default(),
+            storage: cached_storage
+                .get(address)
+                .map(|slots| slots.iter().map(|(slot, value)| (*slot, *value)).collect())
+                .unwrap_or_default(),
+        })
+        .collect();
+    accounts.sort_by_key(|account| account.address);
+
+    StateSnapshot {
+        chain_id: fork.chain_id,
+        block_number: fork.block_number,
+        block_hash: fork.block_hash,
+        accounts,
+    }
+}
+
+/// Download a peer's snapshot
+///
+/// # Arguments
+///
+/// * `peer_url` - Base URL of the peer replica
+/// * `token` - Bearer token for the peer's admin endpoints
+/// * `allowed_hosts` - Hosts the token may be sent to
+///
+/// # Returns
+///
+/// * `Result<StateSnapshot, String>` - The snapshot, or an error describing the failed download
+pub async fn download(peer_url: &str, token: Option<&str>, allowed_hosts: &[String]) -> Result<StateSnapshot, String> {
+    let url = format!("{}/admin/snapshot", peer_url.trim_end_matches('/'));
+    let host = reqwest::Url::parse(&url)
+        .map_err(|e| format!("Invalid peer URL {}: {}", peer_url, e))?
+        .host_str()
+        .map(str::to_string)
+        .unwrap_or_default();
+    let mut request = reqwest::Client::new().get(&url).timeout(DOWNLOAD_TIMEOUT);
+    if let Some(token) = token {
+        // A misconfigured peer URL must not leak the admin token
+        if !allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(&host)) {
+            return Err(format!("Refusing to send the admin token to {}: the host isn't allow-listed", host));
+        }
+        request = request.bearer_auth(token);
+    }
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid snapshot from {}: {}", url, e))
+}
+
+/// Preload a snapshot into the fork at the current head
+///
+/// # Arguments
+///
+/// * `forks` - The fork manager to preload
+/// * `snapshot` - The peer's snapshot
+///
+/// # Returns
+///
+/// * `Result<(), ServiceError>` - Nothing, or an error if the upstream is unreachable or
+///   the snapshot is of another chain or of a block off the upstream's canonical chain
+pub async fn preload(forks: &ForkManager, snapshot: &StateSnapshot) -> Result<(), ServiceError> {
+    let fork = forks.latest().await?;
+    if fork.chain_id != snapshot.chain_id {
+        return Err(ServiceError::Configuration(format!(
+            "Peer snapshot is of chain {}, the upstream is chain {}",
+            snapshot.chain_id, fork.chain_id
+        )));
+    }
+    // A peer on another network with the same chain id (or one that followed a
+    // reorged block) holds state this upstream never had
+    let canonical = if snapshot.block_number == fork.block_number {
+        Some(fork.block_hash)
+    } else {
+        forks.canonical_hash(snapshot.block_number).await?
+    };
+    if canonical != Some(snapshot.block_hash) {
+        return Err(ServiceError::Configuration(format!(
+            "Peer snapshot is of block {} ({}), which isn't on the upstream's canonical chain",
+            snapshot.block_number, snapshot.block_hash
+        )));
+    }
+
+    let slots: usize = snapshot.accounts.iter().map(|account| account.storage.len()).sum();
+    if fork.block_hash == snapshot.block_hash {
+        import(&fork, snapshot);
+        info!(
+            "Imported {} accounts and {} storage slots of block {} from the peer snapshot",
+            snapshot.accounts.len(),
+            slots,
+            fork.block_number
+        );
+    } else {
+        refetch(&fork, snapshot).await;
+        info!(
+            "Peer snapshot is of block {}, fetched its {} accounts and {} storage slots again at block {}",
+            snapshot.block_number,
+            snapshot.accounts.len(),
+            slots,
+            fork.block_number
+        );
+    }
+    Ok(())
+}
+
+/// Insert a snapshot of the fork's own block into its cache
+fn import(fork: &Fork, snapshot: &StateSnapshot) {
+    let mut accounts = fork.state.accounts().write();
+    let mut storage = fork.state.storage().write();
+    for account in &snapshot.accounts {
+        let code = Bytecode::new_raw(account.code.clone());
+        accounts.insert(
+            account.address,
+            AccountInfo::new(account.balance, account.nonce, code.hash_slow(), code),
+        );
+        if !account.storage.is_empty() {
+            storage
+                .entry(account.address)
+                .or_default()
+                .extend(account.storage.iter().map(|(slot, value)| (*slot, *value)));
+        }
+    }
+}
+
+/// Load the accounts and storage slots of a snapshot of an older block through the fork's backend
+async fn refetch(fork: &Fork, snapshot: &StateSnapshot) {
+    let loads = snapshot.accounts.iter().map(|account| {
+        let backend = fork.backend.clone();
+        let address = account.address;
+        let slots: Vec<U256> = account.storage.keys().copied().collect();
+        async move {
+            // The fork backend blocks while it fetches, keep it off the async runtime
+            let loaded = tokio::task::spawn_blocking(move || {
+                backend.basic_ref(address)?;
+                slots.into_iter().try_for_each(|slot| backend.storage_ref(address, slot).map(|_| ()))
+            })
+            .await;
+            if let Ok(Err(e)) = loaded {
+                debug!("Preload of {} failed: {}", address, e);
+            }
+        }
+    });
+    stream::iter(loads).buffer_unordered(REFETCH_CONCURRENCY).collect::<Vec<()>>().await;
+}
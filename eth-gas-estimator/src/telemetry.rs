@@ -0,0 +1,134 @@
+//! Most-called contract telemetry
+//!
+//! Counts the target contract of every successful estimate over a rolling
+//! window. The most called contracts feed the prewarm set (see
+//! [`crate::prewarm`]), so the hot-contract list follows actual traffic instead
+//! of being maintained by hand, and are reported by the admin API.
+//!
+//! Callers pick the addresses counted, so the counts are kept bounded and hard
+//! to steer: requests that are rejected or fail to estimate aren't counted, and
+//! each slice of the window tracks at most [`MAX_TRACKED_CONTRACTS`] addresses.
+//! Once a slice is full, a new address replaces the least called one, so a flood
+//! of one-off addresses churns among themselves while the contracts called
+//! repeatedly keep their counts.
+
+use crate::{
+    error::ServiceError,
+    hooks::EstimationHook,
+    models::telemetry::ContractCount,
+};
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::TransactionRequest,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of buckets the rolling window is split into
+const WINDOW_BUCKETS: u32 = 12;
+
+/// Default length of the rolling window
+pub const DEFAULT_TELEMETRY_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Most contracts counted per slice of the window
+pub const MAX_TRACKED_CONTRACTS: usize = 10_000;
+
+/// Calls counted during one slice of the window
+struct Bucket {
+    started: Instant,
+    counts: HashMap<Address, u64>,
+}
+
+/// Rolling-window call counts per target contract
+pub struct ContractTelemetry {
+    /// Length of the rolling window
+    window: Duration,
+    /// Buckets covering the window, oldest first
+    buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl ContractTelemetry {
+    /// Create an empty telemetry window of the given length
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Length of the rolling window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Count a call to `address`
+    pub fn record(&self, address: Address) {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut buckets, now);
+
+        let bucket_span = self.window / WINDOW_BUCKETS;
+        let needs_bucket = buckets
+            .back()
+            .map_or(true, |bucket| now.duration_since(bucket.started) >= bucket_span);
+        if needs_bucket {
+            buckets.push_back(Bucket { started: now, counts: HashMap::new() });
+        }
+        if let Some(bucket) = buckets.back_mut() {
+            if bucket.counts.len() >= MAX_TRACKED_CONTRACTS && !bucket.counts.contains_key(&address) {
+                let least_called = bucket.counts.iter().min_by_key(|(_, count)| **count).map(|(address, _)| *address);
+                if let Some(least_called) = least_called {
+                    bucket.counts.remove(&least_called);
+                }
+            }
+            *bucket.counts.entry(address).or_insert(0) += 1;
+        }
+    }
+
+    /// The `n` most called contracts in the window, most called first
+    pub fn top(&self, n: usize) -> Vec<ContractCount> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        self.expire(&mut buckets, Instant::now());
+
+        let mut totals: HashMap<Address, u64> = HashMap::new();
+        for bucket in buckets.iter() {
+            for (address, count) in &bucket.counts {
+                *totals.entry(*address).or_insert(0) += count;
+            }
+        }
+
+        let mut ranked: Vec<ContractCount> = totals
+            .into_iter()
+            .map(|(address, count)| ContractCount { address, count })
+            .collect();
+        ranked.sort_by(|a, b| b.count.cmp(&a.count).then(a.address.cmp(&b.address)));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// Drop buckets that started before the window
+    fn expire(&self, buckets: &mut VecDeque<Bucket>, now: Instant) {
+        while buckets
+            .front()
+            .is_some_and(|bucket| now.duration_since(bucket.started) >= self.window)
+        {
+            buckets.pop_front();
+        }
+    }
+}
+
+impl EstimationHook for ContractTelemetry {
+    fn name(&self) -> &'static str {
+        "contract-telemetry"
+    }
+
+    fn post_process(&self, tx_request: &TransactionRequest, gas: U256) -> Result<U256, ServiceError> {
+        if let Some(to) = tx_request.to.and_then(|to| to.to().copied()) {
+            self.record(to);
+        }
+        Ok(gas)
+    }
+}
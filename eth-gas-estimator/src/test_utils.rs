@@ -0,0 +1,170 @@
+//! Integration test harness
+//!
+//! Enabled with the `test_utils` feature, so applications embedding the
+//! library can test their estimator setup the way this crate tests its own:
+//!
+//! * [`TestNode`] starts a throwaway Anvil node (Anvil must be in `PATH`) with
+//!   a client and estimators connected to it, and [`api_app`] serves the API
+//!   endpoints of an estimator
+//! * canonical requests between the funded Anvil dev accounts
+//! * helpers reading JSON-RPC responses and asserting on their outcome
+//! * [`fixtures`], deterministic chain states for the EIP-4844 and EIP-7702
+//!   paths that need no Anvil node at all (from the core library)
+
+pub use eth_gas_estimator_core::fixtures;
+
+use crate::{
+    api,
+    dev_node::{self, AnvilOptions},
+    estimator::GasEstimator,
+    rpc::EthereumClient,
+};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    test, web, App,
+};
+use alloy::primitives::U256;
+use serde_json::json;
+use std::{process::Child, sync::Arc};
+use tracing_actix_web::TracingLogger;
+
+/// First Anvil dev account, funded with 10000 ETH
+pub const DEV_ACCOUNT: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+/// Second Anvil dev account, funded with 10000 ETH
+pub const DEV_RECIPIENT: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+/// Spawns a Prague Anvil process on a free port and returns the process handle and the RPC URL.
+///
+/// Same as the service's dev mode (see [`crate::dev_node`]); returns once Anvil answers.
+///
+/// # Panics
+///
+/// Panics if it fails to bind to a free port or spawn Anvil.
+pub fn spawn_anvil() -> (Child, String) {
+    dev_node::spawn_anvil(&AnvilOptions::default()).expect("Failed to spawn anvil")
+}
+
+/// A throwaway Anvil node and a client connected to it, killed when dropped
+pub struct TestNode {
+    /// The Anvil process
+    pub anvil: Child,
+    /// RPC URL of the node
+    pub rpc_url: String,
+    /// Client connected to the node
+    pub client: Arc<EthereumClient>,
+}
+
+impl TestNode {
+    /// Spawn a node (see [`spawn_anvil`]) and connect a client to it
+    ///
+    /// # Panics
+    ///
+    /// Panics if Anvil can't be spawned or the client can't connect.
+    pub async fn spawn() -> Self {
+        Self::spawn_with(&AnvilOptions::default()).await
+    }
+
+    /// Spawn a node with custom options and connect a client to it
+    ///
+    /// # Panics
+    ///
+    /// Panics if Anvil can't be spawned or the client can't connect.
+    pub async fn spawn_with(options: &AnvilOptions) -> Self {
+        let (anvil, rpc_url) = dev_node::spawn_anvil(options).expect("Failed to spawn anvil");
+        let client = Arc::new(EthereumClient::new(&rpc_url).await.expect("Failed to connect to anvil"));
+        Self { anvil, rpc_url, client }
+    }
+
+    /// An estimator with the default settings on the node
+    pub fn estimator(&self) -> Arc<GasEstimator> {
+        Arc::new(GasEstimator::new(self.client.clone(), &self.rpc_url))
+    }
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        let _ = self.anvil.kill();
+        let _ = self.anvil.wait();
+    }
+}
+
+/// An app serving the API endpoints of an estimator, for [`test::init_service`]
+///
+/// More app data and endpoints can be added to the returned app.
+pub fn api_app(
+    estimator: Arc<GasEstimator>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .wrap(TracingLogger::default())
+        .app_data(web::Data::new(estimator))
+        .configure(api::configure)
+}
+
+/// Transaction params of a plain transfer between the dev accounts
+///
+/// # Arguments
+///
+/// * `value` - Hex quantity of wei transferred, e.g. `"0x1"`
+pub fn transfer_params(value: &str) -> serde_json::Value {
+    json!({
+        "from": DEV_ACCOUNT,
+        "to": DEV_RECIPIENT,
+        "value": value
+    })
+}
+
+/// A JSON-RPC 2.0 request
+pub fn jsonrpc_request(method: &str, params: serde_json::Value, id: u64) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id
+    })
+}
+
+/// An `eth_estimateGas` request for a transaction
+pub fn estimate_gas_request(tx_params: serde_json::Value) -> serde_json::Value {
+    jsonrpc_request("eth_estimateGas", json!([tx_params]), 1)
+}
+
+/// Read a response body as JSON
+///
+/// # Panics
+///
+/// Panics if the body isn't valid JSON.
+pub async fn read_json<B: MessageBody>(resp: ServiceResponse<B>) -> serde_json::Value {
+    let body = test::read_body(resp).await;
+    serde_json::from_slice(&body).expect("Failed to parse JSON response")
+}
+
+/// Assert that a JSON-RPC response succeeded with a hex quantity, and return it
+///
+/// # Panics
+///
+/// Panics if the response is an error or its result isn't a hex quantity.
+pub fn assert_quantity_result(response: &serde_json::Value) -> U256 {
+    assert!(response.get("error").is_none(), "Unexpected error response: {}", response);
+    let result = response["result"].as_str().expect("No result field in response");
+    U256::from_str_radix(result.trim_start_matches("0x"), 16).expect("Result is not a hex quantity")
+}
+
+/// Assert that a JSON-RPC response failed with the given error code
+///
+/// # Panics
+///
+/// Panics if the response succeeded or failed with another code.
+pub fn assert_error_code(response: &serde_json::Value, code: i64) {
+    assert!(response.get("result").is_none(), "Unexpected success response: {}", response);
+    assert_eq!(response["error"]["code"], code, "Unexpected error: {}", response["error"]);
+}
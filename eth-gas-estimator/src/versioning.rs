@@ -0,0 +1,97 @@
+//! Response schema negotiation
+//!
+//! Clients advertise the response schema versions they understand in the
+//! `Accept-Version` request header (e.g. `Accept-Version: 1, 2`); the server
+//! answers with the highest version both sides support and names it in the
+//! `Api-Version` response header. Clients that send nothing keep getting the
+//! v1 schema, so the v2 schema can roll out without breaking them.
+//!
+//! Once v1 is scheduled for deprecation, its responses also carry the structured
+//! `Deprecation` (RFC 9745) and `Sunset` (RFC 8594) headers, so clients can
+//! detect the deprecation programmatically.
+
+use actix_web::{
+    http::header::{HeaderName, HeaderValue, HttpDate},
+    HttpRequest, HttpResponse,
+};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Request header listing the schema versions a client supports
+pub const ACCEPT_VERSION_HEADER: &str = "accept-version";
+
+/// Response header naming the schema version of the response
+pub const API_VERSION_HEADER: &str = "api-version";
+
+/// Response schema version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// Original schema: metadata members next to `result`
+    #[default]
+    V1,
+    /// Metadata grouped under `meta`
+    V2,
+}
+
+impl ApiVersion {
+    /// Every version the server can produce, oldest first
+    pub const SUPPORTED: &'static [ApiVersion] = &[ApiVersion::V1, ApiVersion::V2];
+
+    /// Version number, as used in the headers
+    pub fn number(self) -> u8 {
+        match self {
+            ApiVersion::V1 => 1,
+            ApiVersion::V2 => 2,
+        }
+    }
+
+    /// Pick the response version for a request
+    ///
+    /// The highest version listed in `Accept-Version` that the server supports
+    /// wins (a leading `v` is accepted, unknown entries are ignored). Requests
+    /// without the header, or listing no supported version, get v1.
+    pub fn negotiate(req: &HttpRequest) -> ApiVersion {
+        req.headers()
+            .get_all(ACCEPT_VERSION_HEADER)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|entry| {
+                let entry = entry.trim();
+                let number = entry.strip_prefix(['v', 'V']).unwrap_or(entry);
+                number.parse::<u8>().ok()
+            })
+            .filter_map(|number| Self::SUPPORTED.iter().copied().find(|version| version.number() == number))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Deprecation schedule of the v1 schema
+#[derive(Debug, Clone, Copy, Default)]
+pub struct V1Deprecation {
+    /// Unix timestamp (seconds) from which v1 is deprecated
+    pub deprecated_at: Option<u64>,
+    /// Unix timestamp (seconds) after which v1 may stop being served
+    pub sunset_at: Option<u64>,
+}
+
+impl V1Deprecation {
+    /// Name the negotiated version in `response`, announcing the v1 deprecation if scheduled
+    pub fn annotate(&self, response: &mut HttpResponse, version: ApiVersion) {
+        let headers = response.headers_mut();
+        headers.insert(HeaderName::from_static(API_VERSION_HEADER), HeaderValue::from(u32::from(version.number())));
+        if version != ApiVersion::V1 {
+            return;
+        }
+        if let Some(deprecated_at) = self.deprecated_at {
+            if let Ok(value) = HeaderValue::from_str(&format!("@{}", deprecated_at)) {
+                headers.insert(HeaderName::from_static("deprecation"), value);
+            }
+        }
+        if let Some(sunset_at) = self.sunset_at {
+            let date = HttpDate::from(UNIX_EPOCH + Duration::from_secs(sunset_at));
+            if let Ok(value) = HeaderValue::from_str(&date.to_string()) {
+                headers.insert(HeaderName::from_static("sunset"), value);
+            }
+        }
+    }
+}
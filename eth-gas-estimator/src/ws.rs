@@ -0,0 +1,339 @@
+use crate::{
+    api::{payload_items, process_jsonrpc_payload, JsonRpcReply, METHODS},
+    config::Config,
+    correlation::{generate_correlation_id, with_correlation_id},
+    error::retry_after_secs,
+    estimator::GasEstimator,
+    fees::FeeFeed,
+    models::jsonrpc::{JsonRpcError, JsonRpcResponse, JsonRpcResponseV2, JsonRpcSuccess},
+    rate_limit::{request_admission, Admission},
+    redaction::{request_redaction, Redaction},
+    versioning::ApiVersion,
+};
+use actix_web::{get, rt, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, Session};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+use tracing::{debug, info, info_span, warn, Instrument};
+
+/// How often the server pings an idle client to keep the connection alive
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a connection may stay silent (no messages, no pongs) before it is closed
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Subscriptions a connection may hold at once
+const MAX_SUBSCRIPTIONS: usize = 16;
+
+/// WebSocket JSON-RPC endpoint
+///
+/// Serves the same JSON-RPC methods as the HTTP endpoints over a long-lived
+/// WebSocket connection. Every text (or UTF-8 binary) frame is a JSON-RPC
+/// request (or batch) and is answered with a text frame carrying the matching
+/// `id`. Requests on the same connection are processed concurrently, so
+/// responses may arrive out of order.
+///
+/// `eth_subscribe` with `["newFees"]` pushes the fees of every new head as
+/// `eth_subscription` notifications, until `eth_unsubscribe` or the connection
+/// closes. Subscription requests may be part of a batch; a connection holds at
+/// most [`MAX_SUBSCRIPTIONS`] subscriptions.
+///
+/// Every request sent over the connection is rate limited like an HTTP request
+/// of its own; a frame over the caller's rate is answered with a `-32005` error.
+///
+/// The response schema is negotiated once, from the `Accept-Version` header of
+/// the upgrade request, and applies to every response sent on the connection.
+#[get("/api/v1/ws")]
+pub async fn ws_jsonrpc(
+    req: HttpRequest,
+    body: web::Payload,
+    estimator: web::Data<Arc<GasEstimator>>,
+    fee_feed: Option<web::Data<Arc<FeeFeed>>>,
+    config: Option<web::Data<Config>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (mut response, session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let version = ApiVersion::negotiate(&req);
+    config
+        .as_deref()
+        .map(Config::v1_deprecation)
+        .unwrap_or_default()
+        .annotate(&mut response, version);
+    let estimator = estimator.get_ref().clone();
+    let mut subscriptions = Subscriptions::new(fee_feed.map(|feed| feed.get_ref().clone()));
+    // The caller's tier is resolved once, from the upgrade request
+    let redaction = Arc::new(request_redaction(&req));
+    let admission = request_admission(&req);
+    let peer = req
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_else(|| "unknown".into());
+    info!("WebSocket connection opened from {}", peer);
+
+    rt::spawn(async move {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut last_seen = Instant::now();
+        let mut control = session.clone();
+
+        loop {
+            tokio::select! {
+                msg = msg_stream.recv() => {
+                    let msg = match msg {
+                        Some(Ok(msg)) => msg,
+                        Some(Err(e)) => {
+                            warn!("WebSocket protocol error from {}: {}", peer, e);
+                            break;
+                        }
+                        None => break,
+                    };
+                    last_seen = Instant::now();
+
+                    let text = match msg {
+                        Message::Text(text) => text.to_string(),
+                        Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                        Message::Ping(bytes) => {
+                            if control.pong(&bytes).await.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+                        Message::Close(reason) => {
+                            debug!("WebSocket close from {}: {:?}", peer, reason);
+                            break;
+                        }
+                        _ => continue,
+                    };
+                    match route_frame(&mut subscriptions, &session, admission.as_ref(), version, &text) {
+                        Routed::Answered(reply) => {
+                            if control.text(reply).await.is_err() {
+                                break;
+                            }
+                        }
+                        Routed::Forwarded(payload, answered) => {
+                            let session = session.clone();
+                            spawn_request(estimator.clone(), redaction.clone(), session, version, payload, answered)
+                        }
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if last_seen.elapsed() > CLIENT_TIMEOUT {
+                        info!("WebSocket client {} timed out", peer);
+                        break;
+                    }
+                    if control.ping(b"").await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let _ = control.close(None).await;
+        info!("WebSocket connection closed for {}", peer);
+    });
+
+    Ok(response)
+}
+
+/// Where the requests of a frame are answered
+enum Routed {
+    /// Every request was answered on the connection (parse errors and subscriptions)
+    Answered(String),
+    /// The payload is served by the JSON-RPC methods; a batch carries the replies
+    /// to its subscription requests, answered on the connection
+    Forwarded(serde_json::Value, Vec<serde_json::Value>),
+}
+
+/// Answer the subscription requests of a frame and leave the rest to the JSON-RPC methods
+///
+/// The requests of the frame are charged to the connection's admission first.
+fn route_frame(
+    subscriptions: &mut Subscriptions,
+    session: &Session,
+    admission: Option<&Admission>,
+    version: ApiVersion,
+    text: &str,
+) -> Routed {
+    let payload: serde_json::Value = match serde_json::from_str(text) {
+        Ok(payload) => payload,
+        Err(e) => {
+            let error = JsonRpcError::parse_error(serde_json::Value::Null, format!("Parse error: {}", e));
+            return Routed::Answered(encode(&error));
+        }
+    };
+    if let Some(Err(retry_after)) = admission.map(|admission| admission.charge(payload_items(&payload))) {
+        let error = JsonRpcError::limit_exceeded(
+            serde_json::Value::Null,
+            format!("Rate limit exceeded, retry in {}s", retry_after_secs(retry_after)),
+        );
+        return Routed::Answered(encode(&error));
+    }
+    match payload {
+        serde_json::Value::Array(requests) if !requests.is_empty() => {
+            let mut answered = Vec::new();
+            let mut forwarded = Vec::new();
+            for request in requests {
+                match subscriptions.handle(&request, session) {
+                    Some(reply) => answered.push(versioned(reply, version)),
+                    None => forwarded.push(request),
+                }
+            }
+            if forwarded.is_empty() {
+                Routed::Answered(encode(&answered))
+            } else {
+                Routed::Forwarded(serde_json::Value::Array(forwarded), answered)
+            }
+        }
+        payload => match subscriptions.handle(&payload, session) {
+            Some(reply) => Routed::Answered(encode(&versioned(reply, version))),
+            None => Routed::Forwarded(payload, Vec::new()),
+        },
+    }
+}
+
+/// Handle one JSON-RPC frame in its own task so slow simulations don't block the connection
+///
+/// Each frame is a request of its own, served under a new correlation id. The
+/// replies already given to the subscription requests of a batch are sent along
+/// with the replies to the rest of the batch.
+fn spawn_request(
+    estimator: Arc<GasEstimator>,
+    redaction: Arc<Redaction>,
+    mut session: Session,
+    version: ApiVersion,
+    payload: serde_json::Value,
+    answered: Vec<serde_json::Value>,
+) {
+    let correlation_id = generate_correlation_id();
+    let span = info_span!("ws_request", correlation_id = %correlation_id);
+    rt::spawn(
+        with_correlation_id(correlation_id, async move {
+            let reply = match process_jsonrpc_payload(&estimator, payload, METHODS, &redaction).await {
+                JsonRpcReply::Single(result) => encode(&versioned(JsonRpcResponse::from(result), version)),
+                JsonRpcReply::Batch(responses) => {
+                    let mut replies = answered;
+                    replies.extend(responses.into_iter().map(|response| versioned(response, version)));
+                    encode(&replies)
+                }
+            };
+            if session.text(reply).await.is_err() {
+                debug!("WebSocket closed before the response could be sent");
+            }
+        })
+        .instrument(span),
+    );
+}
+
+/// Serialize a reply frame
+fn encode<T: serde::Serialize>(reply: &T) -> String {
+    serde_json::to_string(reply).unwrap_or_default()
+}
+
+/// A response in the connection's schema version
+fn versioned(response: JsonRpcResponse<serde_json::Value>, version: ApiVersion) -> serde_json::Value {
+    match version {
+        ApiVersion::V1 => serde_json::to_value(response),
+        ApiVersion::V2 => serde_json::to_value(JsonRpcResponseV2::from(response)),
+    }
+    .unwrap_or_default()
+}
+
+/// `newFees` subscriptions of one connection, cancelled when the connection closes
+struct Subscriptions {
+    /// Source of the fee notifications, if enabled
+    feed: Option<Arc<FeeFeed>>,
+    /// Task forwarding the notifications of each subscription id
+    active: HashMap<String, JoinHandle<()>>,
+    /// Id of the next subscription
+    next_id: u64,
+}
+
+impl Subscriptions {
+    fn new(feed: Option<Arc<FeeFeed>>) -> Self {
+        Self { feed, active: HashMap::new(), next_id: 1 }
+    }
+
+    /// Handle an `eth_subscribe` or `eth_unsubscribe` request
+    ///
+    /// # Returns
+    ///
+    /// * `Option<JsonRpcResponse<serde_json::Value>>` - The reply, or `None` for any other request
+    fn handle(&mut self, payload: &serde_json::Value, session: &Session) -> Option<JsonRpcResponse<serde_json::Value>> {
+        let id = payload.get("id").cloned().unwrap_or(serde_json::Value::Null);
+        let param = payload.get("params").and_then(|params| params.get(0)).and_then(|param| param.as_str());
+        let result = match payload.get("method")?.as_str()? {
+            "eth_subscribe" => self.subscribe(id.clone(), param, session),
+            "eth_unsubscribe" => {
+                let removed = param.and_then(|subscription| self.active.remove(subscription));
+                if let Some(task) = &removed {
+                    task.abort();
+                }
+                Ok(serde_json::Value::Bool(removed.is_some()))
+            }
+            _ => return None,
+        };
+        Some(JsonRpcResponse::from(result.map(|result| JsonRpcSuccess::new(id, result))))
+    }
+
+    /// Start forwarding fee notifications to the client under a new subscription id
+    fn subscribe(
+        &mut self,
+        id: serde_json::Value,
+        kind: Option<&str>,
+        session: &Session,
+    ) -> Result<serde_json::Value, JsonRpcError> {
+        if kind != Some("newFees") {
+            return Err(JsonRpcError::invalid_params(
+                id,
+                format!("Unsupported subscription: {}", kind.unwrap_or_default()),
+            ));
+        }
+        let Some(feed) = self.feed.as_ref() else {
+            return Err(JsonRpcError::method_not_supported(id, "newFees subscriptions are disabled".to_string()));
+        };
+        if self.active.len() >= MAX_SUBSCRIPTIONS {
+            return Err(JsonRpcError::limit_exceeded(
+                id,
+                format!("A connection may hold at most {} subscriptions", MAX_SUBSCRIPTIONS),
+            ));
+        }
+
+        let subscription = format!("0x{:x}", self.next_id);
+        self.next_id += 1;
+        let mut receiver = feed.subscribe();
+        let mut session = session.clone();
+        let forwarded = subscription.clone();
+        let task = rt::spawn(async move {
+            loop {
+                let fees = match receiver.recv().await {
+                    Ok(fees) => fees,
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Subscription {} skipped {} fee updates", forwarded, skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {"subscription": forwarded, "result": fees.as_ref()},
+                });
+                if session.text(notification.to_string()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        self.active.insert(subscription.clone(), task);
+        debug!("Subscription {} to newFees started", subscription);
+        Ok(serde_json::Value::String(subscription))
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        self.active.values().for_each(JoinHandle::abort);
+    }
+}
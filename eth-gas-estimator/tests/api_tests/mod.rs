@@ -1,39 +1,76 @@
 //! Integration tests for the API endpoints
 
-use tracing_actix_web::TracingLogger;
 use tracing_subscriber::EnvFilter;
 use actix_web::{
     test, web, App,
     http::StatusCode,
 };
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
 use serde_json::json;
+use revm::primitives::SpecId;
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559, TxEnvelope},
+    eips::{eip2718::Encodable2718, eip2930::{AccessList, AccessListItem}, BlockId},
+    network::TxSignerSync,
+    primitives::{address, Address, Bytes, TxKind, B256, U256, U64},
+    providers::Provider,
+    rpc::types::{TransactionInput, TransactionRequest},
+};
 
 use eth_gas_estimator::{
-    api,
-    estimator::GasEstimator,
-    rpc::EthereumClient,
+    admin,
+    batching,
+    bulk,
+    chains::{precompiles::CustomPrecompile, profile::SpecSource, ChainOptions},
+    compliance::{annotate_screening_hits, AddressScreener, ListSource, ScreeningMode},
+    config::Config,
+    correlation::correlate,
+    degradation::DegradationPolicy,
+    dev_node::{self, AnvilOptions},
+    divergence::{DivergenceMonitor, DivergencePolicy},
+    error::ServiceError,
+    fees::{self, FeeFeed, PriorityFeeTracker},
+    keeper,
+    ladder,
+    metrics::{record_metrics, RequestMetrics},
+    probes::Prober,
+    quotes,
+    rate_limit::{rate_limit, RateLimit, RateLimiter},
+    redaction::{resolve_redaction, RedactionPolicies},
+    selftest,
+    sequence,
+    slo::{slo_report, SloObjectives},
+    snapshot,
+    estimator::{EstimateOptions, EstimateSource, GasEstimator, Headroom, NonceSource},
+    foundry::{account_on_fork, estimate_gas_from_request_foundry, BlockPins, BlockTarget, Fork},
+    gas_dependence::{recommend_gas_limit, Confidence},
+    hooks::EstimationHook,
+    intrinsic::transfer_gas,
+    models::{
+        explain::ExplainOptions,
+        simulation::{SimulateOptions, SimulationWarning, WarningKind},
+        trace::TraceOptions,
+    },
+    provenance::{ProvenanceClaim, ProvenanceSigner},
+    rpc::{chain_id_at, EthereumClient, NewHeads},
+    storage_batch::StorageBatching,
+    telemetry::{ContractTelemetry, MAX_TRACKED_CONTRACTS},
+    timeouts::{request_timeout, Timeouts},
+    units::{
+        format_ether, format_gwei, format_hex_u256, format_units, parse_ether, parse_gwei, parse_units, DisplayUnits,
+    },
 };
 
-mod helpers;
-use helpers::spawn_anvil;
+use eth_gas_estimator::test_utils::{
+    api_app, assert_quantity_result, estimate_gas_request, fixtures::FixtureChain, jsonrpc_request, read_json,
+    transfer_params, TestNode,
+};
 
 #[actix_web::test]
 async fn test_health_check() {
-    // Spawn an Anvil process.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
+    let node = TestNode::spawn().await;
     
-    // Create an Ethereum client from the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-    
-    // Build a GasEstimator using the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
-
-    let app = test::init_service(
-        App::new()
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
     
     // Make request to the health check endpoint.
     let req = test::TestRequest::post()
@@ -54,9 +91,121 @@ async fn test_health_check() {
     assert_eq!(response["status"], "ok");
     assert!(response.get("latest_block").is_some());
     assert!(response.get("timestamp").is_some());
-    
-    // Clean up: kill the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
+    assert!(response["simulation_panics"].is_u64());
+}
+
+#[actix_web::test]
+async fn test_probe_endpoints() {
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+
+    let app = test::init_service(api_app(estimator.clone())).await;
+
+    // Liveness doesn't depend on the upstream.
+    let req = test::TestRequest::get().uri("/livez").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["status"], "ok");
+
+    // Readiness reports the head block, under both paths, without loading a fork.
+    for path in ["/readyz", "/healthz"] {
+        let req = test::TestRequest::get().uri(path).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{} should be ready", path);
+        let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(response["status"], "ok");
+        assert!(response["block"].is_u64());
+    }
+    assert!(estimator.forks.current().await.is_none(), "a probe built a fork");
+}
+
+#[actix_web::test]
+async fn test_conditional_estimate() {
+    let node = TestNode::spawn().await;
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+    let estimate = |etag: Option<&str>, value: &str| {
+        let mut req = test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(estimate_gas_request(transfer_params(value)));
+        if let Some(etag) = etag {
+            req = req.insert_header(("If-None-Match", etag.to_string()));
+        }
+        req.to_request()
+    };
+
+    let resp = test::call_service(&app, estimate(None, "0x1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+
+    // The same request at the same head is not modified
+    let resp = test::call_service(&app, estimate(Some(&etag), "0x1")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+    assert!(test::read_body(resp).await.is_empty());
+
+    // A different request doesn't match the tag
+    let resp = test::call_service(&app, estimate(Some(&etag), "0x2")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // A new head invalidates it
+    node.client.provider.raw_request::<_, serde_json::Value>("evm_mine".into(), ()).await.unwrap();
+    let resp = test::call_service(&app, estimate(Some(&etag), "0x1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_ne!(resp.headers().get("etag").unwrap().to_str().unwrap(), etag);
+}
+
+#[actix_web::test]
+async fn test_probes_file() {
+    let node = TestNode::spawn().await;
+    let write = |name: &str, contents: &str| {
+        let path = std::env::temp_dir().join(format!("probes-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    };
+
+    // Probes run through the estimation path and record their outcome
+    let path = write("valid", &json!([
+        { "name": "transfer", "params": transfer_params("0x1"), "expect": { "exact": 21000 } },
+        { "name": "too cheap", "params": transfer_params("0x1"), "expect": { "exact": 20000 } }
+    ]).to_string());
+    let prober = Prober::from_file(&path, Duration::from_secs(60)).expect("A valid probes file is loaded");
+    let _ = std::fs::remove_file(&path);
+    prober.run(&node.estimator()).await;
+    let statuses = prober.statuses();
+    assert_eq!(statuses[0].passing, Some(true));
+    assert_eq!(statuses[0].last_gas, Some(21000));
+    assert_eq!(statuses[1].passing, Some(false));
+    assert_eq!((statuses[1].consecutive_failures, statuses[1].failures), (1, 1));
+
+    // An invalid file is a configuration error, not a panic
+    let path = write("invalid", "[{\"name\": \"no params\"}]");
+    let result = Prober::from_file(&path, Duration::from_secs(60));
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(result, Err(ServiceError::Configuration(_))));
+}
+
+#[actix_web::test]
+async fn test_readiness_without_upstream() {
+    let mut node = TestNode::spawn().await;
+    // Stop the upstream before any fork was loaded.
+    node.anvil.kill().expect("Failed to kill Anvil process");
+    node.anvil.wait().expect("Failed to wait for Anvil process");
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let req = test::TestRequest::get().uri("/livez").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["status"], "unavailable");
+    assert!(response["reason"].is_string());
 }
 
 #[actix_web::test]
@@ -71,21 +220,9 @@ async fn test_estimate_gas_endpoint() {
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .init();
-    // Spawn an Anvil process.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
+    let node = TestNode::spawn().await;
     
-    // Create an Ethereum client from the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-    
-    // Build a GasEstimator using the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
-
-    let app = test::init_service(
-        App::new()
-            .wrap(TracingLogger::default())
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
     
     // Construct a JSON-RPC request for a simple ETH transfer.
     let request = json!({
@@ -109,6 +246,8 @@ async fn test_estimate_gas_endpoint() {
     
     // Verify a successful response.
     assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("cache-control").unwrap(), "private, no-cache");
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
     
     // Parse the response JSON.
     let body = test::read_body(resp).await;
@@ -121,58 +260,5822 @@ async fn test_estimate_gas_endpoint() {
     assert!(response["result"].is_string());
     assert_eq!(response["result"].as_str().unwrap(), "0x5208"); // 21000 in hex
 
-    // Clean up the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
+    // The estimate is scoped to the head block it was computed against
+    let block_hash = response["cache"]["blockHash"].as_str().unwrap();
+    assert_eq!(block_hash.len(), 66);
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert_eq!(response["cache"]["invalidateOn"], "newHead");
 }
 
 #[actix_web::test]
-async fn test_invalid_request_handling() {
-    // Spawn an Anvil process.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
-    
-    // Create an Ethereum client from the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-    
-    // Build a GasEstimator using the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
+async fn test_extended_estimate_endpoint() {
+    let node = TestNode::spawn().await;
 
-    let app = test::init_service(
-        App::new()
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
-    
-    // Construct an invalid JSON-RPC request (missing required fields).
-    let request = json!({
-        "jsonrpc": "2.0",
-        "method": "eth_estimateGas",
-        "params": [{}],
-        "id": 2
-    });
-    
-    // Make request.
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // A deployment whose init code just stops, creating an empty contract at the
+    // address derived from the sender's first nonce.
     let req = test::TestRequest::post()
-        .uri("/api/v1/eth/estimateGas")
-        .set_json(&request)
+        .uri("/api/v2/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x00"
+            }],
+            "id": 1
+        }))
         .to_request();
-        
     let resp = test::call_service(&app, req).await;
-    
-    // Verify an error response.
-    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-    
-    // Parse the error response JSON.
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("api-version").unwrap(), "2");
     let body = test::read_body(resp).await;
     let response: serde_json::Value = serde_json::from_slice(&body)
         .expect("Failed to parse JSON response");
-        
-    // Check error response format.
-    assert_eq!(response["jsonrpc"], "2.0");
-    assert_eq!(response["id"], 2);
-    assert!(response["error"].is_object());
-    assert_eq!(response["error"]["code"], -32602); // Invalid params
-    assert!(response["error"]["message"].as_str().unwrap().contains("Either 'to' or 'input' must be provided"));
 
-    // Clean up the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
-}
\ No newline at end of file
+    let result = &response["result"];
+    assert!(result["gas"].is_string());
+    assert_eq!(result["source"], "simulation");
+    assert_eq!(result["status"], "success");
+    assert_eq!(result["returnData"], "0x");
+    assert_eq!(result["logs"], json!([]));
+    assert_eq!(result["contractAddress"], "0x5FbDB2315678afecb367f032d93F642f64180aa3");
+    assert_eq!(response["meta"]["apiVersion"], 2);
+
+    // Calls don't create contracts.
+    let req = test::TestRequest::post()
+        .uri("/api/v2/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"]["gas"], "0x5208");
+    assert_eq!(response["result"]["gasUsed"], "0x5208");
+    assert!(response["result"].get("contractAddress").is_none());
+    assert!(response["result"].get("callFrames").is_none());
+    assert_eq!(response["result"]["warnings"], json!([]));
+}
+
+#[actix_web::test]
+async fn test_extended_estimate_call_tracer() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+    let estimate = |uri: &str, tracer: serde_json::Value| {
+        test::TestRequest::post()
+            .uri(uri)
+            .set_json(jsonrpc_request("eth_estimateGas", json!([transfer_params("0x1"), "latest", null, null, tracer]), 1))
+            .to_request()
+    };
+
+    // With tracer options the call tree comes back in callTracer format.
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate("/api/v2/eth/estimateGas", json!({"tracer": "callTracer"}))).await;
+    assert_eq!(response["result"]["gas"], "0x5208");
+    let frame = &response["result"]["callFrames"];
+    assert_eq!(frame["type"], "CALL");
+    assert_eq!(frame["from"], "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    assert_eq!(frame["to"], "0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+    assert_eq!(frame["value"], "0x1");
+    assert_eq!(frame["gas"], "0x5208");
+    assert_eq!(frame["gasUsed"], "0x5208");
+    assert_eq!(frame["input"], "0x");
+
+    // A null tracer param is no trace
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate("/api/v2/eth/estimateGas", serde_json::Value::Null)).await;
+    assert_eq!(response["result"]["gas"], "0x5208");
+    assert!(response["result"].get("callFrames").is_none());
+
+    // Only callTracer is available for estimates.
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate("/api/v2/eth/estimateGas", json!({"tracer": "prestateTracer"}))).await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    // Malformed options name the offending field
+    let response: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        estimate("/api/v2/eth/estimateGas", json!({"tracer": "callTracer", "tracerConfig": {"withLog": "yes"}})),
+    )
+    .await;
+    assert_eq!(response["error"]["code"], -32602);
+    let message = response["error"]["message"].as_str().unwrap();
+    assert!(message.contains("tracerConfig.withLog"), "{}", message);
+
+    // The v1 estimate has no room for a call tree, so it refuses the options rather than dropping them
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate("/api/v1/eth/estimateGas", json!({"tracer": "callTracer"}))).await;
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(response["error"]["message"].as_str().unwrap().contains("/api/v2/eth/estimateGas"));
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate("/api/v1/eth/estimateGas", serde_json::Value::Null)).await;
+    assert_eq!(response["result"], "0x5208");
+}
+
+#[actix_web::test]
+async fn test_extended_estimate_gas_limit_warnings() {
+    let node = TestNode::spawn().await;
+
+    // A transfer takes 21000 gas, about 0.07% of Anvil's 30M block gas limit:
+    // warn from 0.05% on.
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .gas_limit_warning_fraction(0.0005)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v2/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["result"]["gas"], "0x5208");
+    let warnings = response["result"]["warnings"].as_array().expect("warnings should be a list");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "nearBlockGasLimit");
+    assert!(warnings[0]["message"].as_str().unwrap().contains("21000"));
+}
+
+#[actix_web::test]
+async fn test_extended_estimate_exceeds_block_gas_limit() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Init code counting down from 0x150000 at 26 gas per iteration, about 35.8M gas
+    // in all: more than Anvil's 30M block gas limit holds.
+    // PUSH3 0x150000 JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH1 4 JUMPI STOP
+    let req = test::TestRequest::post()
+        .uri("/api/v2/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x621500005b600190038060045700"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let gas = response["result"]["gas"].as_str().expect("Missing gas").parse::<U256>().unwrap();
+    assert!(gas > U256::from(30_000_000u64), "Expected more than a block's gas, got {}", gas);
+    assert_eq!(response["result"]["status"], "success");
+    let warnings = response["result"]["warnings"].as_array().expect("warnings should be a list");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "exceedsBlockGasLimit");
+
+    // With a gas limit of its own, the request is estimated as sent and fails.
+    let req = test::TestRequest::post()
+        .uri("/api/v2/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x621500005b600190038060045700",
+                "gas": "0x1c9c380"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(response["error"].is_object(), "Expected an error, got {}", response);
+}
+
+#[actix_web::test]
+async fn test_dev_node() {
+    // The node answers once spawned, on the chain it was asked for.
+    let node = dev_node::DevNode::spawn(&AnvilOptions { chain_id: Some(1337), ..AnvilOptions::default() })
+        .await
+        .expect("Failed to start the dev node");
+    let rpc_url = node.rpc_url.clone();
+    let client = EthereumClient::new(&rpc_url).await.expect("The dev node doesn't answer");
+    assert_eq!(client.provider.get_chain_id().await.unwrap(), 1337);
+
+    // A port taken by another process is reported instead of probing the other process.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let taken = listener.local_addr().unwrap().port();
+    let spawned = dev_node::DevNode::spawn(&AnvilOptions { port: Some(taken), ..AnvilOptions::default() }).await;
+    assert!(spawned.is_err(), "Anvil started on a taken port");
+
+    // Dropping the node stops it.
+    drop(node);
+    let port: u16 = rpc_url.rsplit(':').next().unwrap().parse().unwrap();
+    assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_err(), "the dev node outlived its handle");
+}
+
+#[actix_web::test]
+async fn test_upstream_switch_checks_chain_id() {
+    let node = TestNode::spawn().await;
+    // A replica of the same chain, and a node serving another one.
+    let replica = TestNode::spawn().await;
+    let other = dev_node::DevNode::spawn(&AnvilOptions { chain_id: Some(10), ..AnvilOptions::default() })
+        .await
+        .expect("Failed to spawn anvil");
+
+    let estimator = node.estimator();
+    assert_eq!(estimator.chain_id().await.expect("Chain id lookup failed"), 31337);
+
+    // The forks keep the state they cached, so another chain is refused and the upstream stays
+    match estimator.set_upstream_url(&other.rpc_url).await {
+        Err(ServiceError::Configuration(message)) => assert!(message.contains("chain 10"), "{}", message),
+        other => panic!("Expected a configuration error, got {:?}", other),
+    }
+    assert_eq!(estimator.eth_client.upstream.get(), node.rpc_url);
+    assert_eq!(estimator.forks.upstream().get(), node.rpc_url);
+
+    // A node of the same chain is switched to
+    estimator.set_upstream_url(&replica.rpc_url).await.expect("Switch failed");
+    assert_eq!(estimator.eth_client.upstream.get(), replica.rpc_url);
+    assert_eq!(estimator.forks.upstream().get(), replica.rpc_url);
+}
+
+#[actix_web::test]
+async fn test_shadow_comparison_of_contract_deployment() {
+    let node = TestNode::spawn().await;
+
+    let monitor = Arc::new(DivergenceMonitor::new(DivergencePolicy {
+        sample_every: 1,
+        threshold_percent: 1.0,
+        window: Duration::from_secs(300),
+        alarm_ratio: 0.5,
+        webhook_url: None,
+    }));
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .divergence_monitor(monitor.clone())
+        .build();
+
+    // A deployment needs far more than 21000 gas; the node must be asked the request as sent
+    // to come up with the same estimate. Init code storing 42 in slot 0.
+    let tx_request = TransactionRequest::default()
+        .from(address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"))
+        .input(Bytes::from_static(&[0x60, 0x2a, 0x60, 0x00, 0x55]).into());
+    let local = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed").gas;
+    assert!(local > U256::from(21_000u64));
+
+    // The shadow request runs in the background
+    let mut status = monitor.status();
+    for _ in 0..50 {
+        if status.comparisons > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        status = monitor.status();
+    }
+    assert_eq!(status.comparisons, 1);
+    assert_eq!(status.divergent, 0);
+    assert!(status.last_divergent.is_none());
+}
+
+/// Accept one webhook notification, answer it and return its JSON body
+async fn next_webhook(listener: &tokio::net::TcpListener) -> serde_json::Value {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (mut socket, _) = listener.accept().await.expect("No webhook connection");
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let read = socket.read(&mut buffer).await.expect("Failed to read the webhook");
+        assert!(read > 0, "Webhook connection closed early");
+        request.extend_from_slice(&buffer[..read]);
+        let text = String::from_utf8_lossy(&request).into_owned();
+        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+            continue;
+        };
+        let length = head
+            .lines()
+            .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|value| value.trim().parse::<usize>()))
+            .expect("Missing content length")
+            .expect("Invalid content length");
+        if body.len() >= length {
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+            return serde_json::from_str(body).expect("Invalid webhook body");
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_divergence_alarm() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let webhook_url = format!("http://{}", listener.local_addr().unwrap());
+    let monitor = Arc::new(DivergenceMonitor::new(DivergencePolicy {
+        sample_every: 1,
+        threshold_percent: 10.0,
+        window: Duration::from_millis(500),
+        alarm_ratio: 0.5,
+        webhook_url: Some(webhook_url),
+    }));
+    let target = Some(address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"));
+
+    // Comparisons within the threshold never fire the alarm
+    for _ in 0..10 {
+        monitor.compare(target, 52_000, 50_000).await;
+    }
+    let status = monitor.status();
+    assert_eq!((status.comparisons, status.divergent), (10, 0));
+    assert!(!status.firing);
+
+    // Once half of them diverge, the alarm fires and the webhook hears of it
+    let notified = tokio::spawn({
+        let monitor = monitor.clone();
+        async move {
+            for _ in 0..10 {
+                monitor.compare(target, 60_000, 50_000).await;
+            }
+        }
+    });
+    let alarm = next_webhook(&listener).await;
+    notified.await.unwrap();
+    assert_eq!(alarm["status"], "firing");
+    assert_eq!(alarm["comparisons"], 20);
+    assert_eq!(alarm["divergent"], 10);
+    assert_eq!(alarm["example"]["local"], 60_000);
+    assert_eq!(alarm["example"]["divergencePercent"], 20.0);
+    let status = monitor.status();
+    assert!(status.firing);
+    assert!(status.firing_since.is_some());
+
+    // When the divergent comparisons leave the window, the next comparison resolves it
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let resolved = tokio::spawn({
+        let monitor = monitor.clone();
+        async move { monitor.compare(target, 50_000, 50_000).await }
+    });
+    let alarm = next_webhook(&listener).await;
+    resolved.await.unwrap();
+    assert_eq!(alarm["status"], "resolved");
+    assert!(!monitor.status().firing);
+}
+
+#[actix_web::test]
+async fn test_upgrade_preview() {
+    // Forks are simulated under the rules following the pinned Cancun ones
+    let node = TestNode::spawn_with(&AnvilOptions {
+        hardfork: "cancun".to_string(),
+        ..AnvilOptions::default()
+    })
+    .await;
+    let options = ChainOptions::default()
+        .with_spec_ids("cancun")
+        .unwrap()
+        .with_upgrade_preview(true)
+        .expect("Cancun has a later fork to preview");
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(options)
+        .build();
+    let fork = estimator.forks.latest().await.expect("Failed to fork");
+    assert_eq!(fork.spec_id, SpecId::PRAGUE);
+
+    // Auto-detected chains preview their next scheduled fork, and keep their rules once nothing is scheduled
+    let options = ChainOptions::default().with_upgrade_preview(true).unwrap();
+    let cancun = 1_710_338_135;
+    let prague = 1_746_612_311;
+    assert_eq!(options.spec_id(1, cancun - 1, None), SpecId::CANCUN);
+    assert_eq!(options.spec_id(1, prague - 1, None), SpecId::PRAGUE);
+    assert_eq!(options.spec_id(1, prague, None), SpecId::PRAGUE);
+    // Chains without a schedule preview the fork following their detected one
+    assert_eq!(options.spec_id(999, prague, Some(SpecId::SHANGHAI)), SpecId::CANCUN);
+    assert_eq!(ChainOptions::default().spec_id(1, prague - 1, None), SpecId::CANCUN);
+
+    // Osaka rules aren't implemented: it can't be pinned, and Prague has nothing to preview
+    assert!(ChainOptions::default().with_spec_ids("osaka").is_err());
+    let pinned = ChainOptions::default().with_spec_ids("auto,10=prague").unwrap();
+    assert!(pinned.clone().with_upgrade_preview(true).is_err());
+    assert!(pinned.with_upgrade_preview(false).is_ok());
+}
+
+#[actix_web::test]
+async fn test_chain_profile_of_unknown_chain() {
+    // A Cancun chain no fork schedule is bundled for.
+    let node = TestNode::spawn_with(&AnvilOptions {
+        hardfork: "cancun".to_string(),
+        chain_id: Some(999),
+        ..AnvilOptions::default()
+    })
+    .await;
+
+    let estimator = node.estimator();
+    let profile = estimator.forks.refresh_profile().await.expect("Profile detection failed");
+    assert_eq!(profile.chain_id, 999);
+    let fork = estimator.forks.latest().await.expect("Failed to fork");
+
+    match profile.spec_source {
+        // Header fields only show the chain has Cancun at least: simulations keep the latest rules
+        SpecSource::Header => {
+            assert_eq!(profile.spec_id, SpecId::CANCUN);
+            assert!(profile.blob_params.is_none());
+            assert_eq!(fork.spec_id, SpecId::LATEST);
+        }
+        // A node describing its own fork is followed
+        _ => assert_eq!(fork.spec_id, profile.spec_id),
+    }
+
+    // The blob base fee derives from the excess blob gas, which is 0 on a fresh chain
+    let blob = fork.block_env.blob_excess_gas_and_price.as_ref().expect("Missing blob fee");
+    assert_eq!(blob.excess_blob_gas, 0);
+    assert_eq!(blob.blob_gasprice, 1);
+}
+
+#[test]
+fn test_config_file_duplicate_settings() {
+    let load = |name: &str, contents: &str| {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(
+        load("duplicates-none.toml", "[server]\nbatch_concurrency = 9\n").expect("A single setting is valid").batch_concurrency,
+        9
+    );
+    for (name, contents) in [
+        ("duplicates-sections.toml", "[server]\nbatch_concurrency = 8\n[listener]\nbatch_concurrency = 9\n"),
+        ("duplicates-case.toml", "batch_concurrency = 8\nBATCH_CONCURRENCY = 9\n"),
+        ("duplicates-sections.yaml", "server:\n  batch_concurrency: 8\nlistener:\n  batch_concurrency: 9\n"),
+    ] {
+        let error = load(name, contents).expect_err("A setting given twice is rejected");
+        assert!(error.to_string().contains("more than once"), "{}: {}", name, error);
+    }
+}
+
+#[test]
+fn test_gas_limit_warning_fraction_validation() {
+    let load = |fraction: &str| {
+        let path = std::env::temp_dir().join(format!("gas-limit-warning-fraction-{}.toml", fraction));
+        std::fs::write(&path, format!("gas_limit_warning_fraction = \"{}\"\n", fraction)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(load("0.25").expect("A fraction is valid").gas_limit_warning_fraction, 0.25);
+    assert_eq!(load("0").expect("0 disables the warning").gas_limit_warning_fraction, 0.0);
+    for invalid in ["NaN", "-0.1", "1.5"] {
+        let error = load(invalid).expect_err("Out of range fractions are rejected");
+        assert!(error.to_string().contains("GAS_LIMIT_WARNING_FRACTION"), "{}", error);
+    }
+}
+
+#[test]
+fn test_unit_conversions() {
+    let gwei = U256::from(1_000_000_000u64);
+    let ether = gwei * gwei;
+
+    // Gwei and ETH strings are exact, without trailing zeros
+    assert_eq!(format_gwei(U256::ZERO), "0");
+    assert_eq!(format_gwei(gwei * U256::from(12) + gwei / U256::from(2)), "12.5");
+    assert_eq!(format_gwei(U256::from(1)), "0.000000001");
+    assert_eq!(format_ether(ether * U256::from(3)), "3");
+    assert_eq!(format_ether(ether / U256::from(1000) + ether / U256::from(2000)), "0.0015");
+
+    // Fixed precision pads, and rounds half up
+    assert_eq!(format_units(U256::from(1_234_500), 6, 4), "1.2345");
+    assert_eq!(format_units(U256::from(1_234_550), 6, 4), "1.2346");
+    assert_eq!(format_units(U256::from(1_234_549), 6, 4), "1.2345");
+    assert_eq!(format_units(U256::from(999_950), 6, 4), "1.0000");
+    assert_eq!(format_units(U256::from(1_500_000), 6, 0), "2");
+    assert_eq!(format_units(U256::from(15), 1, 3), "1.500");
+    assert_eq!(format_units(U256::MAX, 18, 18).len(), U256::MAX.to_string().len() + 1);
+    assert_eq!(DisplayUnits { eth_decimals: 6 }.eth(ether / U256::from(3)), "0.333333");
+    assert_eq!(DisplayUnits { eth_decimals: 2 }.eth(ether * U256::from(2) / U256::from(3)), "0.67");
+    assert_eq!(DisplayUnits::default().gwei(gwei / U256::from(4)), "0.25");
+
+    // Parsing is the inverse of formatting
+    assert_eq!(parse_gwei("12.5"), Ok(gwei * U256::from(12) + gwei / U256::from(2)));
+    assert_eq!(parse_gwei(".5"), Ok(gwei / U256::from(2)));
+    assert_eq!(parse_gwei("7."), Ok(gwei * U256::from(7)));
+    assert_eq!(parse_ether("0.0015"), Ok(ether / U256::from(1000) + ether / U256::from(2000)));
+    assert_eq!(parse_ether("0.000000000000000001"), Ok(U256::from(1)));
+    for wei in [U256::ZERO, U256::from(1), gwei * U256::from(42) + U256::from(7), ether * U256::from(1_000_001)] {
+        assert_eq!(parse_gwei(&format_gwei(wei)), Ok(wei));
+        assert_eq!(parse_ether(&format_ether(wei)), Ok(wei));
+    }
+
+    // Anything but plain digits with an optional `.` is rejected, as are digits finer than a wei
+    for invalid in ["", ".", "1,5", "-1", "+1", "1e9", " 1", "1.2.3", "0x10", "١"] {
+        assert!(parse_gwei(invalid).is_err(), "{:?} was accepted", invalid);
+    }
+    assert!(parse_gwei("0.0000000001").unwrap_err().contains("at most 9"));
+    assert!(parse_ether("0.0000000000000000001").unwrap_err().contains("at most 18"));
+    assert!(parse_units(&"9".repeat(80), 0).is_err());
+
+    // JSON-RPC quantities are minimal lowercase hex
+    assert_eq!(format_hex_u256(U256::ZERO), "0x0");
+    assert_eq!(format_hex_u256(U256::from(0x5208)), "0x5208");
+    assert_eq!(format_hex_u256(U256::from(0xABCDEFu64)), "0xabcdef");
+}
+
+#[test]
+fn test_eth_display_decimals_validation() {
+    let load = |decimals: &str| {
+        let path = std::env::temp_dir().join(format!("eth-display-decimals-{}.toml", decimals));
+        std::fs::write(&path, format!("eth_display_decimals = \"{}\"\n", decimals)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(load("0").expect("Whole ETH is valid").eth_display_decimals, 0);
+    assert_eq!(load("18").expect("Every wei digit is valid").eth_display_decimals, 18);
+    let error = load("19").expect_err("More decimals than wei has are rejected");
+    assert!(error.to_string().contains("ETH_DISPLAY_DECIMALS"), "{}", error);
+}
+
+#[test]
+fn test_screening_config_validation() {
+    let load = |setting: &str| {
+        let path = std::env::temp_dir().join(format!("screening-{}.toml", setting.len()));
+        std::fs::write(&path, format!("{}\n", setting)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(load("screening_mode = \"annotate\"").expect("A known mode is valid").screening_mode, "annotate");
+    let error = load("screening_mode = \"block\"").expect_err("Unknown modes are rejected");
+    assert!(error.to_string().contains("SCREENING_MODE"), "{}", error);
+    let error = load("screening_refresh_secs = \"0\"").expect_err("A zero interval is rejected");
+    assert!(error.to_string().contains("SCREENING_REFRESH_SECS"), "{}", error);
+}
+
+#[test]
+fn test_prewarm_interval_validation() {
+    let load = |secs: &str| {
+        let path = std::env::temp_dir().join(format!("prewarm-interval-{}.toml", secs));
+        std::fs::write(&path, format!("prewarm_interval_secs = \"{}\"\n", secs)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(load("30").expect("A positive interval is valid").prewarm_interval_secs, 30);
+    let error = load("0").expect_err("A zero interval is rejected");
+    assert!(error.to_string().contains("PREWARM_INTERVAL_SECS"), "{}", error);
+}
+
+#[test]
+fn test_server_tuning_config() {
+    let load = |name: &str, contents: &str| {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+
+    // Unset settings keep actix-web's defaults
+    let tuning = load("tuning-defaults.toml", "").expect("Defaults are valid").server_tuning();
+    assert_eq!(tuning.workers, 4);
+    assert_eq!(tuning.keep_alive, actix_web::http::KeepAlive::Timeout(Duration::from_secs(5)));
+    assert_eq!(tuning.client_request_timeout, Duration::from_secs(5));
+    assert_eq!(tuning.max_connections, 25_000);
+    assert_eq!(tuning.backlog, 2048);
+
+    let tuning = load(
+        "tuning-custom.toml",
+        "workers = 2\nkeep_alive_secs = 0\nclient_request_timeout_ms = 250\nmax_connections = 64\nbacklog = 16\n",
+    )
+    .expect("Custom settings are valid")
+    .server_tuning();
+    assert_eq!(tuning.workers, 2);
+    assert_eq!(tuning.keep_alive, actix_web::http::KeepAlive::Disabled);
+    assert_eq!(tuning.client_request_timeout, Duration::from_millis(250));
+    assert_eq!(tuning.max_connections, 64);
+    assert_eq!(tuning.backlog, 16);
+
+    // A server that accepts no connection, or has no worker to serve them, is rejected
+    let error = load("tuning-no-connections.toml", "max_connections = 0\n").expect_err("Zero connections are rejected");
+    assert!(error.to_string().contains("MAX_CONNECTIONS"), "{}", error);
+    let error = load("tuning-no-workers.toml", "workers = 0\n").expect_err("Zero workers are rejected");
+    assert!(error.to_string().contains("WORKERS"), "{}", error);
+}
+
+#[test]
+fn test_batch_concurrency_validation() {
+    let load = |concurrency: &str| {
+        let path = std::env::temp_dir().join(format!("batch-concurrency-{}.toml", concurrency));
+        std::fs::write(&path, format!("batch_concurrency = \"{}\"\n", concurrency)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    assert_eq!(load("8").expect("A positive bound is valid").batch_concurrency, 8);
+    let error = load("0").expect_err("An unbounded batch is rejected");
+    assert!(error.to_string().contains("BATCH_CONCURRENCY"), "{}", error);
+}
+
+#[actix_web::test]
+async fn test_nonce_sources() {
+    let node = TestNode::spawn().await;
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+
+    // A transaction waiting in the pool puts the pending nonce ahead of the head's
+    let provider = &node.client.provider;
+    let _: serde_json::Value = provider.raw_request("evm_setAutomine".into(), (false,)).await.unwrap();
+    let _: B256 = provider
+        .raw_request("eth_sendTransaction".into(), (transfer_params("0x1"),))
+        .await
+        .expect("Failed to queue a transfer");
+
+    // Init code deploying its own address, which derives from the sender's nonce
+    let mut deploy = TransactionRequest::default()
+        .from(from)
+        .input(TransactionInput::new(Bytes::from_static(&[0x30, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3])));
+    deploy.to = Some(TxKind::Create);
+
+    for (source, nonce) in [(NonceSource::Latest, 0), (NonceSource::Pending, 1)] {
+        let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+            .nonce_source(source)
+            .build();
+        let output = estimator
+            .call_with(&deploy, &EstimateOptions::default())
+            .await
+            .expect("Call failed");
+        assert_eq!(Address::from_slice(&output[12..]), from.create(nonce), "{:?} nonce", source);
+
+        // Traces simulate the same deployment
+        let options: TraceOptions = serde_json::from_value(json!({ "tracer": "callTracer" })).unwrap();
+        let trace = estimator
+            .trace_call(&deploy, &options, &EstimateOptions::default())
+            .await
+            .expect("Trace failed");
+        let trace = serde_json::to_value(&trace).unwrap();
+        assert_eq!(trace["type"], "CREATE");
+        assert_eq!(trace["to"].as_str().unwrap().parse::<Address>().unwrap(), from.create(nonce));
+    }
+}
+
+#[actix_web::test]
+async fn test_simulate_v1_contract_calls_and_caps() {
+    let node = TestNode::spawn().await;
+    // A counter logging and returning its new value:
+    // sstore(0, add(sload(0), 1)) mstore(0, sload(0)) log0(0, 32) return(0, 32)
+    let counter = "0x00000000000000000000000000000000000c0417";
+    let reverter = "0x0000000000000000000000000000000000000bad";
+    for (address, code) in [(counter, "0x6000546001018060005560005260206000a060206000f3"), (reverter, "0x60006000fd")] {
+        let _: serde_json::Value = node.client
+            .provider
+            .raw_request("anvil_setCode".into(), (address, code))
+            .await
+            .expect("Failed to set the contract code");
+    }
+    let app = test::init_service(api_app(node.estimator())).await;
+    let simulate = |block_state_calls: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_simulateV1",
+                "params": [{ "blockStateCalls": block_state_calls }, "latest"],
+                "id": 1
+            }))
+            .to_request()
+    };
+    let word = |value: u8| format!("0x{:064x}", value);
+
+    // Storage written in one block is read in the next; reverts are reported per call.
+    let response = read_json(test::call_service(&app, simulate(json!([
+        { "calls": [{ "to": counter }, { "to": counter }] },
+        { "calls": [{ "to": counter }, { "to": reverter }] }
+    ]))).await).await;
+    let blocks = response["result"].as_array().expect("Simulation result is not an array");
+    assert_eq!(blocks.len(), 2);
+    let returned: Vec<_> = blocks
+        .iter()
+        .flat_map(|block| block["calls"].as_array().unwrap())
+        .map(|call| call["returnData"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(returned[..3], [word(1), word(2), word(3)]);
+    let call = &blocks[0]["calls"][1];
+    assert_eq!(call["status"], "0x1");
+    assert_eq!(call["logs"][0]["address"].as_str().unwrap().to_lowercase(), counter);
+    assert_eq!(call["logs"][0]["data"], word(2));
+    let reverted = &blocks[1]["calls"][1];
+    assert_eq!(reverted["status"], "0x0");
+    assert_eq!(reverted["error"]["code"], 3);
+    assert!(reverted["logs"].as_array().unwrap().is_empty());
+
+    // Too many blocks, or too many calls across them, are invalid.
+    let blocks = vec![json!({ "calls": [] }); 257];
+    let response = read_json(test::call_service(&app, simulate(json!(blocks))).await).await;
+    assert_eq!(response["error"]["code"], -32602);
+    let block = json!({ "calls": vec![json!({ "to": counter }); 501] });
+    let response = read_json(test::call_service(&app, simulate(json!([block.clone(), block]))).await).await;
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[actix_web::test]
+async fn test_pending_fork_replays_the_pending_block() {
+    let node = TestNode::spawn().await;
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let provider = &node.client.provider;
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let to: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+    let head = estimator.forks.latest().await.expect("Failed to fork");
+    let sender = account_on_fork(&head, from).await.expect("Failed to read the sender");
+    let recipient = account_on_fork(&head, to).await.expect("Failed to read the recipient");
+
+    let _: serde_json::Value = provider.raw_request("evm_setAutomine".into(), (false,)).await.unwrap();
+    let _: B256 = provider
+        .raw_request("eth_sendTransaction".into(), (transfer_params("0x64"),))
+        .await
+        .expect("Failed to queue a transfer");
+
+    // The pending fork sees the queued transfer, the head's forks don't
+    let pending = estimator.forks.pending().await.expect("Failed to fork the pending block");
+    assert_eq!(pending.block_number, head.block_number + 1);
+    assert_eq!(account_on_fork(&pending, from).await.unwrap().nonce, sender.nonce + 1);
+    assert_eq!(account_on_fork(&pending, to).await.unwrap().balance, recipient.balance + U256::from(100));
+    assert_eq!(account_on_fork(&head, to).await.unwrap().balance, recipient.balance);
+
+    // A transaction queued later doesn't show up until the head moves
+    let late = Address::repeat_byte(0x77);
+    let _: B256 = provider
+        .raw_request("eth_sendTransaction".into(), (json!({ "from": from, "to": late, "value": "0x1" }),))
+        .await
+        .expect("Failed to queue a transfer");
+    let same = estimator.forks.pending().await.expect("Failed to fork the pending block");
+    assert!(Arc::ptr_eq(&same, &pending), "The pending fork is reused while the head stays");
+    assert_eq!(account_on_fork(&pending, late).await.unwrap().balance, U256::ZERO);
+
+    // Once mined, the transfers are in the next pending fork's head state
+    let _: serde_json::Value = provider.raw_request("evm_mine".into(), ()).await.unwrap();
+    let next = estimator.forks.pending().await.expect("Failed to fork the pending block");
+    assert_eq!(account_on_fork(&next, late).await.unwrap().balance, U256::from(1));
+}
+
+#[actix_web::test]
+async fn test_intrinsic_transfer_fast_path() {
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let to: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+    let contract = Address::repeat_byte(0x42);
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x00"))
+        .await
+        .expect("Failed to set the contract code");
+    let fork = estimator.forks.latest().await.expect("Failed to fork");
+
+    // Plain transfers are answered without simulating, with the gas the simulation charges
+    let transfer = TransactionRequest::default().from(from).to(to).value(U256::from(1));
+    let with_access_list = transfer.clone().access_list(AccessList(vec![AccessListItem {
+        address: contract,
+        storage_keys: vec![B256::ZERO, B256::repeat_byte(1)],
+    }]));
+    for (tx_request, gas) in [(&transfer, 21_000), (&with_access_list, 21_000 + 2_400 + 2 * 1_900)] {
+        assert_eq!(transfer_gas(&fork, tx_request).await.unwrap(), Some(gas));
+        let simulated = estimate_gas_from_request_foundry(&fork, tx_request).await.expect("Simulation failed");
+        assert_eq!(simulated, U256::from(gas));
+    }
+
+    // Anything that could run code is simulated
+    let with_data = transfer.clone().input(TransactionInput::new(Bytes::from_static(&[1])));
+    let to_contract = transfer.clone().to(contract);
+    let to_precompile = transfer.clone().to(address!("0000000000000000000000000000000000000004"));
+    for tx_request in [&with_data, &to_contract, &to_precompile] {
+        assert_eq!(transfer_gas(&fork, tx_request).await.unwrap(), None);
+    }
+
+    // Through the API, a transfer with call data reaches the simulation, which charges for the data
+    let app = test::init_service(api_app(estimator)).await;
+    let mut params = transfer_params("0x1");
+    params["data"] = json!("0x01");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(estimate_gas_request(params))
+        .to_request();
+    let response = read_json(test::call_service(&app, req).await).await;
+    let gas = u64::from_str_radix(response["result"].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+    assert!(gas > 21_000, "call data wasn't charged: {}", gas);
+}
+
+#[actix_web::test]
+async fn test_contract_telemetry() {
+    let node = TestNode::spawn().await;
+    let telemetry = Arc::new(ContractTelemetry::new(Duration::from_secs(3600)));
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .hook(telemetry.clone())
+        .build();
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let to: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+
+    // Successful estimates are counted, failing ones aren't
+    let transfer = TransactionRequest::default().from(from).to(to).value(U256::from(1));
+    estimator.estimate_raw_gas(&transfer).await.expect("Estimation failed");
+    let unfunded = TransactionRequest::default().from(Address::repeat_byte(0x77)).to(Address::repeat_byte(0x78)).value(U256::from(1));
+    assert!(estimator.estimate_raw_gas(&unfunded).await.is_err());
+    let top = telemetry.top(10);
+    assert_eq!(top.len(), 1);
+    assert_eq!((top[0].address, top[0].count), (to, 1));
+
+    // A flood of one-off addresses neither grows the counts nor pushes the hot contract out
+    for _ in 0..4 {
+        telemetry.record(to);
+    }
+    for index in 0..(MAX_TRACKED_CONTRACTS as u64 + 100) {
+        telemetry.record(Address::left_padding_from(&index.to_be_bytes()));
+    }
+    let top = telemetry.top(usize::MAX);
+    assert_eq!(top.len(), MAX_TRACKED_CONTRACTS);
+    assert_eq!((top[0].address, top[0].count), (to, 5));
+}
+
+#[actix_web::test]
+async fn test_address_screening() {
+    let node = TestNode::spawn().await;
+
+    // A contract that calls the deny-listed address.
+    let denied = "0x000000000000000000000000000000000000bad1";
+    let caller = "0x000000000000000000000000000000000000c0de";
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request(
+            "anvil_setCode".into(),
+            (caller, "0x600060006000600060007300000000000000000000000000000000000000bad15af100"),
+        )
+        .await
+        .expect("Failed to set the contract code");
+
+    let deny_list = std::env::temp_dir().join("address-screening-deny.txt");
+    std::fs::write(&deny_list, format!("# sanctioned\n{}\n", denied)).unwrap();
+
+    // A list that can't be loaded is reported, so startup can fail closed.
+    let missing = AddressScreener::new(
+        None,
+        Some(ListSource::parse("/nonexistent/address-screening-deny.txt")),
+        ScreeningMode::Reject,
+    );
+    assert!(missing.refresh().await.is_err());
+
+    let app_with = |mode: ScreeningMode| {
+        let client = node.client.clone();
+        let rpc_url = node.rpc_url.clone();
+        let deny_list = deny_list.clone();
+        async move {
+            let screener = Arc::new(AddressScreener::new(
+                None,
+                Some(ListSource::parse(deny_list.to_str().unwrap())),
+                mode,
+            ));
+            screener.refresh().await.expect("Failed to load the deny list");
+            let estimator = GasEstimator::builder(client, &rpc_url).hook(screener).build();
+            test::init_service(api_app(Arc::new(estimator)).wrap_fn(|req, srv| annotate_screening_hits(req, srv))).await
+        }
+    };
+    let estimate = |to: &str| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": to }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // In reject mode, the deny-listed address is refused as the recipient...
+    let app = app_with(ScreeningMode::Reject).await;
+    let response: serde_json::Value = test::call_and_read_body_json(&app, estimate(denied)).await;
+    assert_eq!(response["error"]["code"], -32003);
+
+    // ... and when a contract calls it.
+    let response: serde_json::Value = test::call_and_read_body_json(&app, estimate(caller)).await;
+    assert_eq!(response["error"]["code"], -32003);
+    let message = response["error"]["message"].as_str().unwrap().to_lowercase();
+    assert!(message.contains("execution touches") && message.contains(denied), "{}", message);
+
+    // Explanations screen the accounts their own run touches.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "x_explainEstimate",
+            "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": caller }],
+            "id": 1
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["error"]["code"], -32003);
+    let message = response["error"]["message"].as_str().unwrap().to_lowercase();
+    assert!(message.contains("execution touches") && message.contains(denied), "{}", message);
+
+    // In annotate mode, the estimate is served with the hit in a response header.
+    let app = app_with(ScreeningMode::Annotate).await;
+    let resp = test::call_service(&app, estimate(caller)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let hits = resp
+        .headers()
+        .get("x-screening-hits")
+        .expect("Missing X-Screening-Hits")
+        .to_str()
+        .unwrap()
+        .to_lowercase();
+    assert!(hits.contains(denied), "{}", hits);
+    let response: serde_json::Value = read_json(resp).await;
+    assert!(response["result"].is_string());
+
+    // Requests without a hit carry no header.
+    let resp = test::call_service(&app, estimate("0x0000000000000000000000000000000000000001")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-screening-hits").is_none());
+
+    let _ = std::fs::remove_file(&deny_list);
+}
+
+/// Pipeline hook of the hook tests: rejects value transfers, appends `0xff` to the
+/// calldata and adds 1000 gas to estimates
+struct TestPolicy;
+
+impl EstimationHook for TestPolicy {
+    fn name(&self) -> &'static str {
+        "test-policy"
+    }
+
+    fn pre_validate(&self, tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        match tx_request.value {
+            Some(value) if !value.is_zero() => Err(ServiceError::Rejected("value transfers are not allowed".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    fn mutate_request(&self, tx_request: &mut TransactionRequest) -> Result<(), ServiceError> {
+        let mut input = tx_request.input.input().map(|input| input.to_vec()).unwrap_or_default();
+        input.push(0xff);
+        tx_request.input = TransactionInput::new(input.into());
+        Ok(())
+    }
+
+    fn post_process(&self, _tx_request: &TransactionRequest, gas: U256) -> Result<U256, ServiceError> {
+        Ok(gas + U256::from(1000))
+    }
+}
+
+/// Pipeline hook counting the requests it validates
+#[derive(Default)]
+struct CountingHook(std::sync::atomic::AtomicUsize);
+
+impl EstimationHook for CountingHook {
+    fn name(&self) -> &'static str {
+        "counting"
+    }
+
+    fn pre_validate(&self, _tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[actix_web::test]
+async fn test_extended_estimate_prepares_once() {
+    let node = TestNode::spawn().await;
+    let counter = Arc::new(CountingHook::default());
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url).hook(counter.clone()).build();
+
+    // The estimate and the run under it share one prepared request
+    let mut tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .input(TransactionInput::new(Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3])));
+    tx_request.to = Some(TxKind::Create);
+    let extended = estimator
+        .estimate_extended(&tx_request, &EstimateOptions::default(), None)
+        .await
+        .expect("Extended estimate failed");
+    assert!(extended.gas_used > U256::ZERO);
+    assert_eq!(counter.0.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+/// Pipeline hook counting the requests it validates and the executions it inspects
+#[derive(Default)]
+struct TouchCountingHook {
+    validated: std::sync::atomic::AtomicUsize,
+    inspected: std::sync::atomic::AtomicUsize,
+}
+
+impl EstimationHook for TouchCountingHook {
+    fn name(&self) -> &'static str {
+        "touch-counting"
+    }
+
+    fn pre_validate(&self, _tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        self.validated.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn inspects_touched(&self) -> bool {
+        true
+    }
+
+    fn inspect_touched(&self, _tx_request: &TransactionRequest, touched: &BTreeSet<Address>) -> Result<(), ServiceError> {
+        assert!(!touched.is_empty());
+        self.inspected.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[actix_web::test]
+async fn test_detailed_estimate_runs_once_under_the_estimate() {
+    let node = TestNode::spawn().await;
+    let counter = Arc::new(TouchCountingHook::default());
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url).hook(counter.clone()).build();
+
+    // The frames come from the run that supplies the touched accounts, on the request the estimate prepared
+    let detailed = estimator
+        .estimate_detailed(&serde_json::from_value(transfer_params("0x1")).unwrap(), &EstimateOptions::default())
+        .await
+        .expect("Detailed estimate failed");
+    let frames = detailed.call_frames.expect("A simulated estimate has call frames");
+    assert_eq!(frames.gas_used, U256::from(21_000));
+    assert!(frames.gas <= detailed.gas);
+    assert_eq!(counter.validated.load(std::sync::atomic::Ordering::Relaxed), 1);
+    assert_eq!(counter.inspected.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+#[actix_web::test]
+async fn test_pipeline_hooks() {
+    let node = TestNode::spawn().await;
+
+    // Returns its calldata: calldatasize push1(0) push1(0) calldatacopy calldatasize push1(0) return
+    let echo = "0x000000000000000000000000000000000000ec40";
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (echo, "0x366000600037366000f3"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let hooked = Arc::new(GasEstimator::builder(node.client.clone(), &node.rpc_url).hook(Arc::new(TestPolicy)).build());
+    let app = test::init_service(api_app(hooked)).await;
+    let plain = test::init_service(api_app(node.estimator())).await;
+    let rpc = |method: &str, params: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({ "jsonrpc": "2.0", "method": method, "params": [params, "latest"], "id": 1 }))
+            .to_request()
+    };
+    let call = |data: &str| json!({ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": echo, "data": data });
+
+    // Estimates run the mutated request and are post-processed.
+    let hooked_estimate = read_json(test::call_service(&app, rpc("eth_estimateGas", call("0x01"))).await).await;
+    let plain_estimate = read_json(test::call_service(&plain, rpc("eth_estimateGas", call("0x01ff"))).await).await;
+    assert_eq!(
+        assert_quantity_result(&hooked_estimate),
+        assert_quantity_result(&plain_estimate) + U256::from(1000)
+    );
+
+    // Calls run the mutated request too.
+    let response = read_json(test::call_service(&app, rpc("eth_call", call("0x01"))).await).await;
+    assert_eq!(response["result"], "0x01ff");
+
+    // A request the hook rejects is rejected whichever way it is run.
+    let mut transfer = call("0x01");
+    transfer["value"] = json!("0x1");
+    for method in ["eth_estimateGas", "eth_call", "debug_traceCall"] {
+        let resp = test::call_service(&app, rpc(method, transfer.clone())).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", method);
+        let response = read_json(resp).await;
+        assert_eq!(response["error"]["code"], -32003, "{}", method);
+        assert!(response["error"]["message"].as_str().unwrap().contains("value transfers are not allowed"), "{}", response);
+    }
+}
+
+#[actix_web::test]
+async fn test_degradation_ladder() {
+    let mut node = TestNode::spawn().await;
+
+    // Look the head up on every request, so the outage is noticed right away,
+    // and serve remembered estimates while the node is down.
+    let degradation = DegradationPolicy::new("staleCache", None, Duration::from_secs(7), Duration::from_secs(300), 16)
+        .expect("Invalid degradation ladder");
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .degradation(degradation)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    let estimate_request = |value: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                    "value": value
+                }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, estimate_request("0x1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Take the upstream node down.
+    node.anvil.kill().expect("Failed to kill Anvil process");
+    node.anvil.wait().expect("Failed to wait for Anvil process");
+
+    // The same request is answered from the last simulation, flagged as stale.
+    let resp = test::call_service(&app, estimate_request("0x1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("etag").is_none());
+    assert_eq!(resp.headers().get("x-estimate-source").unwrap(), "stale");
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x5208");
+    assert!(response.get("source").is_none());
+
+    // The remembered estimate can still be served, so the probes report degraded instead of unavailable.
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["status"], "degraded");
+    assert!(response["reason"].is_string());
+    let req = test::TestRequest::post().uri("/api/v1/health").to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["status"], "degraded");
+
+    // A request never simulated before exhausts the ladder.
+    let resp = test::call_service(&app, estimate_request("0x2")).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "7");
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32051);
+    assert_eq!(response["error"]["data"]["retryAfter"], 7);
+}
+
+#[actix_web::test]
+async fn test_degradation_secondary_step() {
+    // The upstream node and the secondary provider.
+    let mut node = TestNode::spawn().await;
+    let mut secondary = TestNode::spawn().await;
+
+    // Only the secondary step is on the ladder.
+    let degradation = DegradationPolicy::new(
+        "secondary",
+        Some(secondary.client.clone()),
+        Duration::from_secs(7),
+        Duration::from_secs(300),
+        16,
+    )
+    .expect("Invalid degradation ladder");
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .degradation(degradation)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    let estimate_request = || {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(estimate_gas_request(json!({
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            })))
+            .to_request()
+    };
+
+    // Take the upstream node down before anything was simulated.
+    node.anvil.kill().expect("Failed to kill Anvil process");
+    node.anvil.wait().expect("Failed to wait for Anvil process");
+
+    // The secondary provider answers, flagged as such.
+    let resp = test::call_service(&app, estimate_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("x-estimate-source").unwrap(), "secondary");
+    assert!(resp.headers().get("etag").is_none());
+    let response = read_json(resp).await;
+    assert_eq!(response["result"], "0x5208");
+
+    // Once the secondary provider is down too, the ladder is exhausted.
+    secondary.anvil.kill().expect("Failed to kill Anvil process");
+    secondary.anvil.wait().expect("Failed to wait for Anvil process");
+    let resp = test::call_service(&app, estimate_request()).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "7");
+}
+
+#[actix_web::test]
+async fn test_degradation_empty_ladder() {
+    let mut node = TestNode::spawn().await;
+
+    // Without a ladder, the stale cache capacity is irrelevant.
+    let degradation = DegradationPolicy::new("", None, Duration::from_secs(3), Duration::from_secs(300), 0)
+        .expect("Invalid degradation ladder");
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .degradation(degradation)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    let estimate_request = || {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(estimate_gas_request(json!({
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            })))
+            .to_request()
+    };
+    let resp = test::call_service(&app, estimate_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Take the upstream node down.
+    node.anvil.kill().expect("Failed to kill Anvil process");
+    node.anvil.wait().expect("Failed to wait for Anvil process");
+
+    // Even a request simulated before fails right away.
+    let resp = test::call_service(&app, estimate_request()).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "3");
+    let response = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32051);
+}
+
+#[test]
+fn test_degradation_ladder_validation() {
+    let policy = |ladder: &str, capacity: usize| {
+        DegradationPolicy::new(ladder, None, Duration::from_secs(5), Duration::from_secs(300), capacity)
+    };
+    assert!(policy("staleCache", 16).is_ok());
+    assert!(policy("", 0).is_ok());
+
+    // A stale cache that can't remember anything, a secondary step without a provider
+    // and unknown steps are configuration errors.
+    for (ladder, capacity) in [("staleCache", 0), ("secondary", 16), ("staleCache,fallback", 16)] {
+        match policy(ladder, capacity) {
+            Err(ServiceError::Configuration(_)) => {}
+            Err(e) => panic!("{} should be a configuration error, got {}", ladder, e),
+            Ok(_) => panic!("{} with capacity {} should be rejected", ladder, capacity),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_upstream_fallback_on_dead_fork_backend() {
+    // One node backing the fork, one answering as the upstream node.
+    let mut fork_node = TestNode::spawn().await;
+    let upstream = TestNode::spawn().await;
+
+    // Keep the head for the whole test, so only the fork's state fetches hit the dead node.
+    let estimator = GasEstimator::builder(upstream.client.clone(), &fork_node.rpc_url)
+        .latest_block_ttl(Duration::from_secs(3600))
+        .upstream_fallback(true)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    let estimate_request = |to: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{"to": to}],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // The fork answers while its node is up.
+    let resp = test::call_service(&app, estimate_request("0x70997970C51812dc3A010C7d01b50e0d17dc79C8")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("x-estimate-source").unwrap(), "simulation");
+
+    // Take the fork's node down: state the fork hasn't loaded yet can't be fetched anymore.
+    fork_node.anvil.kill().expect("Failed to kill Anvil process");
+    fork_node.anvil.wait().expect("Failed to wait for Anvil process");
+
+    // The client's request is answered by the upstream node instead.
+    let resp = test::call_service(&app, estimate_request("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("x-estimate-source").unwrap(), "upstream");
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x5208");
+    assert!(response.get("source").is_none());
+}
+
+#[actix_web::test]
+async fn test_invalid_request_handling() {
+    let node = TestNode::spawn().await;
+    
+    let app = test::init_service(api_app(node.estimator())).await;
+    
+    // Construct an invalid JSON-RPC request (missing required fields).
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_estimateGas",
+        "params": [{}],
+        "id": 2
+    });
+    
+    // Make request.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(&request)
+        .to_request();
+        
+    let resp = test::call_service(&app, req).await;
+    
+    // Verify an error response.
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    
+    // Parse the error response JSON.
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+        
+    // Check error response format.
+    assert_eq!(response["jsonrpc"], "2.0");
+    assert_eq!(response["id"], 2);
+    assert!(response["error"].is_object());
+    assert_eq!(response["error"]["code"], -32602); // Invalid params
+    assert!(response["error"]["message"].as_str().unwrap().contains("Either 'to' or 'input' must be provided"));
+}
+
+#[actix_web::test]
+async fn test_chain_id_mismatch() {
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let estimate = |chain_id: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f",
+                    "value": "0x1",
+                    "chainId": chain_id
+                }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // Anvil serves chain 31337 (0x7a69); a mainnet transaction is rejected
+    let resp = test::call_service(&app, estimate("0x1")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(response["error"]["message"].as_str().unwrap().starts_with("Chain mismatch"));
+    assert_eq!(response["error"]["data"]["chainId"], "0x7a69");
+
+    let resp = test::call_service(&app, estimate("0x7a69")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_code_overrides() {
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let tx = json!({
+        "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "to": "0x00000000000000000000000000000000000c0de1"
+    });
+    let call = |method: &str, params: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 }))
+            .to_request()
+    };
+    let request =
+        |method: &str, state_overrides: serde_json::Value| call(method, json!([tx, "latest", state_overrides]));
+    let quantity = |value: &serde_json::Value| {
+        u64::from_str_radix(value.as_str().expect("Not a quantity").trim_start_matches("0x"), 16).unwrap()
+    };
+
+    // Without overrides the address has no code: a plain transfer
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, request("eth_estimateGas", serde_json::Value::Null)).await;
+    assert_eq!(response["result"], "0x5208", "unexpected response: {}", response);
+
+    // A counter (SLOAD slot 0, add 1, SSTORE) runs at the address instead
+    let counter = json!({ "0x00000000000000000000000000000000000c0de1": { "code": "0x60005460010160005500" } });
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, request("eth_estimateGas", counter.clone())).await;
+    let gas = quantity(&response["result"]);
+    assert!(gas > 21_000 + 20_000, "the storage write isn't estimated: {}", response);
+
+    // The estimates that re-run the transaction run the overridden code too
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, request("x_estimateGasDetailed", counter.clone())).await;
+    assert_eq!(quantity(&response["result"]["gas"]), gas, "unexpected response: {}", response);
+    let frame_gas = quantity(&response["result"]["callFrames"]["gasUsed"]);
+    assert!(frame_gas > 21_000 + 20_000, "unexpected response: {}", response);
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, request("eth_createAccessList", counter.clone())).await;
+    let without_list = quantity(&response["result"]["gasUsedWithoutAccessList"]);
+    assert!(without_list > 21_000 + 20_000, "unexpected response: {}", response);
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, call("x_explainEstimate", json!([tx, {}, counter]))).await;
+    let gas_used = response["result"]["gasUsed"].as_u64().unwrap_or_default();
+    assert!(gas_used > 21_000 + 20_000, "unexpected response: {}", response);
+
+    // eth_call returns what the overridden code returns (MSTORE 42, RETURN 32 bytes)
+    let answer = json!({ "0x00000000000000000000000000000000000c0de1": { "code": "0x602a60005260206000f3" } });
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, request("eth_call", answer.clone())).await;
+    assert_eq!(response["result"], format!("0x{:064x}", 42), "unexpected response: {}", response);
+
+    // debug_traceCall takes them in its tracer options, as geth does
+    let options = json!({ "tracer": "callTracer", "stateOverrides": answer });
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, call("debug_traceCall", json!([tx, "latest", options]))).await;
+    assert_eq!(response["result"]["output"], format!("0x{:064x}", 42), "unexpected response: {}", response);
+
+    // Only code can be overridden
+    let balance = json!({ "0x00000000000000000000000000000000000c0de1": { "balance": "0x1" } });
+    for method in ["eth_estimateGas", "x_estimateGasDetailed", "eth_createAccessList"] {
+        let response: serde_json::Value = test::call_and_read_body_json(&app, request(method, balance.clone())).await;
+        assert_eq!(response["error"]["code"], -32602, "{} accepted a balance override: {}", method, response);
+        assert!(response["error"]["message"].as_str().unwrap().contains("only code can be overridden"));
+    }
+}
+
+#[actix_web::test]
+async fn test_debug_trace_call_contracts() {
+    let node = TestNode::spawn().await;
+    // The outer contract calls the counter, then the reverter, and stops:
+    // call(gas(), counter, 0, 0, 0, 0, 0) pop call(gas(), reverter, 0, 0, 0, 0, 0) pop stop
+    let outer = "0x00000000000000000000000000000000000c0de1";
+    // sstore(0, add(sload(0), 1)) mstore(0, sload(0)) log0(0, 32) return(0, 32)
+    let counter = "0x00000000000000000000000000000000000c0de2";
+    // Reverts with Error("no"), copied from the end of its code
+    let reverter = "0x00000000000000000000000000000000000c0de3";
+    let revert_data = format!("08c379a0{:064x}{:064x}{:0<64}", 0x20, 2, "6e6f");
+    for (address, code) in [
+        (outer, "0x60006000600060006000620c0de25af15060006000600060006000620c0de35af15000".to_string()),
+        (counter, "0x6000546001018060005560005260206000a060206000f3".to_string()),
+        (reverter, format!("0x6064600c60003960646000fd{}", revert_data)),
+    ] {
+        let _: serde_json::Value = node.client
+            .provider
+            .raw_request("anvil_setCode".into(), (address, code))
+            .await
+            .expect("Failed to set the contract code");
+    }
+    let app = test::init_service(api_app(node.estimator())).await;
+    let trace = |options: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "debug_traceCall",
+                "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": outer }, "latest", options],
+                "id": 1
+            }))
+            .to_request()
+    };
+    let quantity = |value: &serde_json::Value| {
+        u64::from_str_radix(value.as_str().expect("Not a quantity").trim_start_matches("0x"), 16).unwrap()
+    };
+    let address = |value: &serde_json::Value| value.as_str().expect("Not an address").to_lowercase();
+
+    // The call tree has a frame per nested call, with its own gas, output, logs and error.
+    let response = read_json(test::call_service(&app, trace(json!({
+        "tracer": "callTracer",
+        "tracerConfig": { "withLog": true }
+    }))).await).await;
+    let root = &response["result"];
+    assert_eq!(root["type"], "CALL", "unexpected response: {}", response);
+    assert!(root.get("error").is_none(), "the outer call doesn't revert: {}", response);
+    let calls = root["calls"].as_array().expect("No nested calls were traced");
+    assert_eq!(calls.len(), 2, "unexpected response: {}", response);
+
+    let (counted, reverted) = (&calls[0], &calls[1]);
+    assert_eq!(counted["type"], "CALL");
+    assert_eq!(address(&counted["from"]), outer);
+    assert_eq!(address(&counted["to"]), counter);
+    assert_eq!(counted["output"], format!("0x{:064x}", 1));
+    assert!(quantity(&counted["gasUsed"]) > 20_000, "the storage write isn't traced: {}", response);
+    assert!(quantity(&counted["gas"]) < quantity(&root["gas"]));
+    assert_eq!(counted["logs"].as_array().map(Vec::len), Some(1), "unexpected response: {}", response);
+    assert_eq!(address(&counted["logs"][0]["address"]), counter);
+    assert_eq!(counted["logs"][0]["data"], format!("0x{:064x}", 1));
+
+    assert_eq!(address(&reverted["to"]), reverter);
+    assert_eq!(reverted["error"], "execution reverted");
+    assert_eq!(reverted["revertReason"], "no");
+    assert_eq!(reverted["output"], format!("0x{}", revert_data));
+    assert!(quantity(&root["gasUsed"]) > quantity(&counted["gasUsed"]) + quantity(&reverted["gasUsed"]));
+
+    // Only the top-level call is traced on request.
+    let response = read_json(test::call_service(&app, trace(json!({
+        "tracer": "callTracer",
+        "tracerConfig": { "onlyTopCall": true }
+    }))).await).await;
+    assert_eq!(response["result"]["type"], "CALL", "unexpected response: {}", response);
+    assert!(response["result"].get("calls").is_none(), "unexpected response: {}", response);
+
+    // The prestate holds the code of every contract called and the slots read or written, before the call.
+    let response = read_json(test::call_service(&app, trace(json!({ "tracer": "prestateTracer" }))).await).await;
+    let prestate = response["result"].as_object().expect("Prestate is not an object");
+    let account = |contract: &str| {
+        prestate
+            .iter()
+            .find(|(address, _)| address.to_lowercase() == contract)
+            .map(|(_, account)| account.clone())
+            .unwrap_or_else(|| panic!("{} is missing: {}", contract, response))
+    };
+    for contract in [outer, counter, reverter] {
+        assert!(account(contract)["code"].is_string(), "{} has no code: {}", contract, response);
+    }
+    assert_eq!(account(counter)["storage"], json!({ format!("0x{:064x}", 0): format!("0x{:064x}", 0) }));
+    assert!(account(outer).get("storage").is_none(), "unexpected response: {}", response);
+    account("0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+}
+
+#[actix_web::test]
+async fn test_block_hash_target() {
+    let node = TestNode::spawn().await;
+    let genesis = node.client
+        .provider
+        .get_block(BlockId::number(0))
+        .await
+        .unwrap()
+        .expect("Anvil has a genesis block")
+        .header
+        .hash;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let estimate = |block: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f",
+                    "value": "0x1"
+                }, block],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // The genesis block is canonical
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, estimate(json!({ "blockHash": genesis }))).await;
+    assert_eq!(response["result"], "0x5208");
+
+    // An unknown hash is rejected, canonical or not
+    let unknown = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    for block in [json!({ "blockHash": unknown }), json!({ "blockHash": unknown, "requireCanonical": false })] {
+        let response: serde_json::Value = test::call_and_read_body_json(&app, estimate(block)).await;
+        assert_eq!(response["error"]["code"], -32602);
+        assert!(response["error"]["message"].as_str().unwrap().contains("Unknown block hash"));
+    }
+
+    // A block number object isn't served at the head, even for the head's own number
+    let head = node.client.provider.get_block_number().await.unwrap();
+    for number in [0, head] {
+        let block = json!({ "blockNumber": format!("0x{:x}", number) });
+        let response: serde_json::Value = test::call_and_read_body_json(&app, estimate(block)).await;
+        assert_eq!(response["error"]["code"], -32602, "{}", response);
+        assert!(response["error"]["message"].as_str().unwrap().contains("pin blocks by blockHash"));
+        assert!(response["result"].is_null());
+    }
+}
+
+#[actix_web::test]
+async fn test_block_hash_target_state() {
+    let node = TestNode::spawn().await;
+
+    // The contract returns its slot 0, which is 1 at block 1 and 2 at block 2
+    let contract = address!("00000000000000000000000000000000005107e0");
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x60005460005260206000f3"))
+        .await
+        .expect("Failed to set the contract code");
+    let mine_with_slot = |value: u64| {
+        let provider = node.client.provider.clone();
+        async move {
+            let _: serde_json::Value = provider
+                .raw_request("anvil_setStorageAt".into(), (contract, U256::ZERO, B256::from(U256::from(value))))
+                .await
+                .expect("Failed to set the storage");
+            let _: serde_json::Value = provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+            provider.get_block(BlockId::latest()).await.unwrap().expect("No latest block").header
+        }
+    };
+    let first = mine_with_slot(1).await;
+    mine_with_slot(2).await;
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+    let call = |block: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_call",
+                "params": [{ "to": contract }, block],
+                "id": 1
+            }))
+            .to_request()
+    };
+    let slot = |value: u64| format!("{}", B256::from(U256::from(value)));
+
+    // State is read at the requested block, not at the head
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, call(json!({ "blockHash": first.hash }))).await;
+    assert_eq!(response["result"], slot(1));
+    let response: serde_json::Value = test::call_and_read_body_json(&app, call(json!("latest"))).await;
+    assert_eq!(response["result"], slot(2));
+
+    // Mine block 3 and fork at it, then replace it with another block at the same height
+    let snapshot: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_snapshot".into(), ())
+        .await
+        .expect("Failed to take a snapshot");
+    let orphaned = mine_with_slot(3).await;
+    let block = json!({ "blockHash": orphaned.hash, "requireCanonical": false });
+    let response: serde_json::Value = test::call_and_read_body_json(&app, call(block.clone())).await;
+    assert_eq!(response["result"], slot(3));
+    let _: bool = node.client
+        .provider
+        .raw_request("evm_revert".into(), (snapshot,))
+        .await
+        .expect("Failed to revert");
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_setNextBlockTimestamp".into(), (orphaned.timestamp + 100,))
+        .await
+        .expect("Failed to set the next timestamp");
+    let replacement = mine_with_slot(4).await;
+    assert_ne!(replacement.hash, orphaned.hash);
+
+    // The orphaned block is refused unless the node.client.clone() accepts non-canonical blocks
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, call(json!({ "blockHash": orphaned.hash }))).await;
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(response["error"]["message"].as_str().unwrap().contains("not canonical"));
+    let response: serde_json::Value = test::call_and_read_body_json(&app, call(block)).await;
+    assert_eq!(response["result"], slot(3));
+}
+
+#[actix_web::test]
+async fn test_malformed_field_errors() {
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let estimate = |transaction: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [transaction],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // Each malformed field is named in the error message
+    let cases = [
+        (json!({"to": "0x1234"}), "Invalid transaction object: to: "),
+        (
+            json!({"to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f", "gas": "0xzz"}),
+            "Invalid transaction object: gas: ",
+        ),
+        (
+            json!({"to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f", "data": "0x123"}),
+            "Invalid transaction object: data: ",
+        ),
+        (
+            json!({
+                "to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f",
+                "accessList": [{"address": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f", "storageKeys": ["0x01"]}]
+            }),
+            "Invalid transaction object: accessList[0].storageKeys[0]: ",
+        ),
+    ];
+    for (transaction, expected) in cases {
+        let resp = test::call_service(&app, estimate(transaction)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+        assert_eq!(response["error"]["code"], -32602);
+        let message = response["error"]["message"].as_str().unwrap();
+        assert!(message.starts_with(expected), "{}", message);
+    }
+
+    // Quantities may be decimal as well as hex
+    let resp = test::call_service(
+        &app,
+        estimate(json!({
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": "0x742d35Cc6634C0532925a3b844Bc454e4438f44f",
+            "gas": 30000,
+            "value": "0x1"
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+#[actix_web::test]
+async fn test_typed_param_errors() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .configure(bulk::configure)
+    ).await;
+
+    let transfer = json!({
+        "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+        "value": "0x1"
+    });
+    let rpc = |params: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({"jsonrpc": "2.0", "method": "eth_estimateGas", "params": params, "id": 1}))
+            .to_request()
+    };
+
+    // Block overrides are typed: a malformed member is named.
+    let resp = test::call_service(&app, rpc(json!([transfer, "latest", null, {"prevRandao": "0x01"}]))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32602);
+    let message = response["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("Invalid block overrides: prevRandao: "), "{}", message);
+
+    // A block inside the transaction object isn't silently ignored.
+    let mut with_block = transfer.clone();
+    with_block["block"] = json!("0x0");
+    let resp = test::call_service(&app, rpc(json!([with_block]))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(response["error"]["message"].as_str().unwrap().contains("second param"));
+
+    // REST bodies name the malformed field too.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/bulk")
+        .set_json(json!([transfer, {"to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "gas": "0xzz"}]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response = read_json(resp).await;
+    assert_eq!(response["error_code"], "INVALID_PARAMS");
+    let details = response["details"].as_str().unwrap();
+    assert!(details.starts_with("Invalid request body: [1].gas: "), "{}", details);
+}
+
+#[actix_web::test]
+async fn test_estimate_gas_stream_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Construct a batch with one valid transfer, one invalid request and one malformed item.
+    let request = json!([
+        {
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0xde0b6b3a7640000" // 1 ETH
+            }],
+            "id": 1
+        },
+        {
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{}],
+            "id": 2
+        },
+        {
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": "not-an-array",
+            "id": 3
+        }
+    ]);
+
+    // Make request to the streaming endpoint.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/stream")
+        .set_json(&request)
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Every line of the body is a complete JSON-RPC response.
+    let body = test::read_body(resp).await;
+    let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+        .expect("Body is not UTF-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Failed to parse NDJSON line"))
+        .collect();
+    assert_eq!(lines.len(), 4);
+
+    // Responses arrive in completion order, so look them up by id.
+    let success = lines.iter().find(|line| line["id"] == 1).expect("Missing response for id 1");
+    assert_eq!(success["result"], "0x5208"); // 21000 in hex
+    let failure = lines.iter().find(|line| line["id"] == 2).expect("Missing response for id 2");
+    assert_eq!(failure["error"]["code"], -32602); // Invalid params
+    let malformed = lines.iter().find(|line| line["id"] == 3).expect("Missing response for id 3");
+    assert_eq!(malformed["error"]["code"], -32600); // Invalid request
+
+    // The stream always closes with the batch summary.
+    let summary = &lines[3]["summary"];
+    assert_eq!(summary["total"], 3);
+    assert_eq!(summary["failedCount"], 2);
+}
+
+/// Tracks how many estimates are between validation and post-processing at once
+#[derive(Default)]
+struct InFlightHook {
+    in_flight: std::sync::atomic::AtomicUsize,
+    peak: std::sync::atomic::AtomicUsize,
+}
+
+impl EstimationHook for InFlightHook {
+    fn name(&self) -> &'static str {
+        "in-flight"
+    }
+
+    fn pre_validate(&self, _tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        let in_flight = self.in_flight.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        self.peak.fetch_max(in_flight, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn post_process(&self, _tx_request: &TransactionRequest, gas: U256) -> Result<U256, ServiceError> {
+        self.in_flight.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(gas)
+    }
+}
+
+#[actix_web::test]
+async fn test_estimate_gas_stream_bounded_concurrency() {
+    let node = TestNode::spawn().await;
+    let in_flight = Arc::new(InFlightHook::default());
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .batch_concurrency(2)
+        .hook(in_flight.clone())
+        .build();
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+
+    // Six distinct transfers, so none is answered from another's cached estimate
+    let request: Vec<serde_json::Value> = (1..=6)
+        .map(|value| jsonrpc_request("eth_estimateGas", json!([transfer_params(&format!("0x{:x}", value))]), value))
+        .collect();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/stream")
+        .set_json(&request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+        .expect("Body is not UTF-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Failed to parse NDJSON line"))
+        .collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[6]["summary"]["failedCount"], 0);
+
+    // Two items were simulated side by side, and never more
+    assert_eq!(in_flight.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert_eq!(in_flight.in_flight.load(std::sync::atomic::Ordering::SeqCst), 0);
+}
+
+#[actix_web::test]
+async fn test_estimate_gas_batch_request() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Construct a JSON-RPC batch with a valid transfer and an invalid request.
+    let request = json!([
+        {
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0xde0b6b3a7640000" // 1 ETH
+            }],
+            "id": 1
+        },
+        {
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{}],
+            "id": 2
+        }
+    ]);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(&request)
+        .to_request();
+
+    // Batch item failures are reported in-band, so the batch itself succeeds.
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+
+    // Responses keep the order of the requests.
+    let responses = response.as_array().expect("Batch response is not an array");
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["result"], "0x5208"); // 21000 in hex
+    assert_eq!(responses[1]["id"], 2);
+    assert_eq!(responses[1]["error"]["code"], -32602); // Invalid params
+}
+
+#[actix_web::test]
+async fn test_jsonrpc_dispatch_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // A known method is dispatched to its handler.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_gasPrice",
+            "params": [],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["id"], 1);
+    assert!(response["result"].as_str().unwrap().starts_with("0x"));
+
+    // The chain id is served too, so SDKs need no second endpoint (Anvil's default is 31337).
+    for id in [10, 11] {
+        let req = test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_chainId",
+                "params": [],
+                "id": id
+            }))
+            .to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(response["id"], id);
+        assert_eq!(response["result"], "0x7a69");
+    }
+
+    // The block number is the head of the fork estimates run on, Anvil's genesis here.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_blockNumber",
+            "params": [],
+            "id": 12
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response["id"], 12);
+    assert_eq!(response["result"], "0x0");
+
+    // Clients advertising the v2 schema get their metadata grouped under `meta`.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("Accept-Version", "1, 2, 9"))
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("api-version").unwrap(), "2");
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x5208");
+    assert_eq!(response["meta"]["apiVersion"], 2);
+    assert_eq!(response["meta"]["source"], "simulation");
+    assert!(response.get("source").is_none());
+
+    // Blob transactions also report their blob gas and the blob base fee in `meta`.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("Accept-Version", "2"))
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "maxFeePerBlobGas": "0x3b9aca00",
+                "blobVersionedHashes": [
+                    "0x0100000000000000000000000000000000000000000000000000000000000001",
+                    "0x0100000000000000000000000000000000000000000000000000000000000002"
+                ]
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x5208");
+    assert_eq!(response["meta"]["blobGasUsed"], "0x40000"); // 2 * 131072
+    assert!(response["meta"]["blobBaseFee"].as_str().unwrap().starts_with("0x"));
+
+    // A blob count stands in for the versioned hashes, with a default blob fee cap.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("Accept-Version", "2"))
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "blobCount": 3
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x5208");
+    assert_eq!(response["meta"]["blobGasUsed"], "0x60000"); // 3 * 131072
+
+    // Under Prague, data-heavy transactions are estimated at the EIP-7623 calldata floor.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "data": format!("0x{}", "01".repeat(100))
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x61a8"); // 21000 + 10 * 4 * 100, above 21000 + 16 * 100
+
+    // A request can pad its estimate with a multiplier and a fixed buffer.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "gasMultiplier": 1.5,
+                "gasBuffer": "0x3e8"
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], "0x7ef4"); // 21000 * 1.5 + 1000
+
+    // Senders that can't pay for the transaction get the node's insufficient funds error.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{
+                "from": "0x000000000000000000000000000000000000dEaD",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0xde0b6b3a7640000"
+            }],
+            "id": 3
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32000);
+    assert!(response["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("insufficient funds for gas * price + value"));
+
+    // Account queries are served from the fork, answering what the node holds for the account.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!([
+            {
+                "jsonrpc": "2.0",
+                "method": "eth_getBalance",
+                "params": ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "latest"],
+                "id": 3
+            },
+            {
+                "jsonrpc": "2.0",
+                "method": "eth_getTransactionCount",
+                "params": ["0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"],
+                "id": 4
+            }
+        ]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let responses: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let sender: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let balance = node.client.provider.get_balance(sender).await.expect("Failed to get the balance");
+    let nonce = node.client.provider.get_transaction_count(sender).await.expect("Failed to get the nonce");
+    assert_eq!(responses[0]["result"], json!(balance), "unexpected response: {}", responses);
+    assert!(balance > U256::ZERO);
+    assert_eq!(responses[1]["result"], json!(U64::from(nonce)), "unexpected response: {}", responses);
+
+    // Blocks of calls are simulated on top of the head, one block after another.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_simulateV1",
+            "params": [{
+                "blockStateCalls": [
+                    {
+                        "calls": [{
+                            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                            "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                            "value": "0xde0b6b3a7640000"
+                        }]
+                    },
+                    {
+                        "blockOverrides": { "baseFeePerGas": "0x0" },
+                        "calls": [{
+                            "from": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                            "to": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                            "value": "0xde0b6b3a7640000"
+                        }]
+                    }
+                ],
+                "validation": true
+            }, "latest"],
+            "id": 5
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let blocks = response["result"].as_array().expect("Simulation result is not an array");
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[1]["parentHash"], blocks[0]["hash"]);
+    assert_eq!(blocks[1]["baseFeePerGas"], "0x0");
+    for block in blocks {
+        assert_eq!(block["gasUsed"], "0x5208");
+        assert_eq!(block["calls"][0]["status"], "0x1");
+    }
+
+    // Pinned PREVRANDAO values are seen by the call (init code returning PREVRANDAO).
+    let prev_randao = "0x0000000000000000000000000000000000000000000000000000000000000042";
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [
+                { "data": "0x4460005260206000f3" },
+                "latest",
+                null,
+                { "prevRandao": prev_randao }
+            ],
+            "id": 6
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"], prev_randao);
+
+    // A builder's fee recipient can be set as `coinbase` (init code returning COINBASE).
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [
+                { "data": "0x4160005260206000f3" },
+                "latest",
+                null,
+                { "coinbase": "0x95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5" }
+            ],
+            "id": 6
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(
+        response["result"],
+        "0x00000000000000000000000095222290dd7278aa3ddd389cc1e1d165cc4bafe5"
+    );
+
+    // The pending block is one past the head: NUMBER returns head + 1.
+    let mut block_numbers = Vec::new();
+    for tag in ["latest", "pending"] {
+        let req = test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_call",
+                "params": [{ "data": "0x4360005260206000f3" }, tag],
+                "id": 7
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body)
+            .expect("Failed to parse JSON response");
+        let number = response["result"].as_str().unwrap().trim_start_matches("0x");
+        block_numbers.push(u64::from_str_radix(number, 16).unwrap());
+    }
+    assert_eq!(block_numbers[1], block_numbers[0] + 1);
+
+    // Other tags and block numbers are rejected rather than served at the head.
+    for tag in ["earliest", "safe", "finalized", "0x10"] {
+        let req = test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_call",
+                "params": [{ "data": "0x4360005260206000f3" }, tag],
+                "id": 7
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body)
+            .expect("Failed to parse JSON response");
+        assert_eq!(response["error"]["code"], -32602, "{} is served", tag);
+        assert!(
+            response["error"]["message"].as_str().unwrap().starts_with("Unsupported block"),
+            "{}",
+            response
+        );
+    }
+
+    // Calls can be traced with the geth call tracer.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "debug_traceCall",
+            "params": [
+                {
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                    "value": "0x1"
+                },
+                "latest",
+                { "tracer": "callTracer" }
+            ],
+            "id": 6
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"]["type"], "CALL");
+    assert_eq!(response["result"]["gasUsed"], "0x5208");
+    assert_eq!(response["result"]["value"], "0x1");
+
+    // A plain transfer accesses no cold state, so its generated access list is empty.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_createAccessList",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 7
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"]["accessList"], json!([]));
+    assert_eq!(response["result"]["gasUsed"], "0x5208");
+    assert_eq!(response["result"]["gasUsedWithoutAccessList"], "0x5208");
+    assert_eq!(response["result"]["gasSaved"], 0);
+
+    // An unknown method is rejected with "Method not found".
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_sendRawTransaction",
+            "params": ["0x00"],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["error"]["code"], -32601); // Method not found
+
+    // The dedicated estimateGas endpoint answers other methods as invalid params, as it always has.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [{ "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8" }],
+            "id": 3
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response = read_json(resp).await;
+    assert_eq!(response["id"], 3);
+    assert_eq!(response["error"]["code"], -32602);
+    assert_eq!(response["error"]["message"], "Unsupported method: eth_call");
+}
+
+#[actix_web::test]
+async fn test_insufficient_funds_of_partially_funded_sender() {
+    let node = TestNode::spawn().await;
+
+    let estimator = node.estimator();
+    let base_fee = estimator.forks.latest().await.expect("Failed to fork").base_fee_per_gas;
+
+    // The sender can pay for a transfer at the base fee plus its tip, but not at its fee cap.
+    let sender = address!("000000000000000000000000000000000000bEEF");
+    let max_fee = U256::from(100_000_000_000u64);
+    let tip = U256::from(1_000_000_000u64);
+    let value = U256::from(1_000_000_000_000_000u64);
+    let balance = value + U256::from(21_000) * (base_fee + tip);
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setBalance".into(), (sender, balance))
+        .await
+        .expect("Failed to fund the sender");
+
+    let app = test::init_service(api_app(estimator)).await;
+    let transfer = |value: U256, gas: Option<&str>| {
+        let mut tx = json!({
+            "from": sender,
+            "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "value": value,
+            "maxFeePerGas": max_fee,
+            "maxPriorityFeePerGas": tip
+        });
+        if let Some(gas) = gas {
+            tx["gas"] = json!(gas);
+        }
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [tx],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // The exact balance covers the transfer, with or without a gas limit.
+    for gas in [None, Some("0x5208")] {
+        let resp = test::call_service(&app, transfer(value, gas)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body)
+            .expect("Failed to parse JSON response");
+        assert_eq!(response["result"], "0x5208", "{}", response);
+    }
+
+    // One wei more is refused with the node's error, as a node.client.clone() error.
+    for gas in [None, Some("0x5208")] {
+        let resp = test::call_service(&app, transfer(value + U256::from(1), gas)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = test::read_body(resp).await;
+        let response: serde_json::Value = serde_json::from_slice(&body)
+            .expect("Failed to parse JSON response");
+        assert_eq!(response["error"]["code"], -32000);
+        assert!(response["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("insufficient funds for gas * price + value"));
+    }
+}
+
+#[actix_web::test]
+async fn test_selftest_passes() {
+    let node = TestNode::spawn().await;
+
+    let path = std::env::temp_dir().join("selftest.toml");
+    std::fs::write(&path, format!("ethereum_rpc_url = \"{}\"\n", node.rpc_url)).unwrap();
+    let config = Config::from_file(&path).expect("Failed to load the config");
+    let _ = std::fs::remove_file(&path);
+
+    // Every canonical case, the signed EIP-7702 authorization included, passes against a dev node.
+    assert!(selftest::run(&node.estimator(), &config).await);
+
+    // A sender that can't pay for the transfers fails the run.
+    let path = std::env::temp_dir().join("selftest-unfunded.toml");
+    std::fs::write(
+        &path,
+        format!(
+            "ethereum_rpc_url = \"{}\"\nselftest_from = \"0x00000000000000000000000000000000000051f7\"\n",
+            node.rpc_url
+        ),
+    )
+    .unwrap();
+    let config = Config::from_file(&path).expect("Failed to load the config");
+    let _ = std::fs::remove_file(&path);
+    assert!(!selftest::run(&node.estimator(), &config).await);
+}
+
+#[actix_web::test]
+async fn test_audit_log_replay() {
+    let node = TestNode::spawn().await;
+
+    // Admin endpoints need a token.
+    let path = std::env::temp_dir().join("audit-log-replay.toml");
+    std::fs::write(&path, "admin_token = \"replay-secret\"\n").unwrap();
+    let config = Config::from_file(&path).expect("Failed to load the config");
+    let _ = std::fs::remove_file(&path);
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .app_data(web::Data::new(config))
+            .configure(admin::configure)
+    ).await;
+    let rpc = |method: &str, params: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 }))
+            .to_request()
+    };
+
+    // An unfunded deployment fails inside the simulation, with the request's id.
+    let deployment = json!([{
+        "from": "0x000000000000000000000000000000000000dEaD",
+        "data": "0x00",
+        "value": "0xde0b6b3a7640000"
+    }]);
+    let response: serde_json::Value = test::call_and_read_body_json(&app, rpc("eth_estimateGas", deployment.clone())).await;
+    assert_eq!(response["error"]["code"], -32000);
+    let request_id = response["error"]["data"]["requestId"].as_str().expect("Missing requestId").to_string();
+
+    // The replay reproduces the error and captures the logs of the simulation's blocking task.
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/replay/{}", request_id))
+        .insert_header(("Authorization", "Bearer replay-secret"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: serde_json::Value = read_json(resp).await;
+    assert_eq!(report["requestId"], request_id);
+    assert_eq!(report["request"]["params"], deployment);
+    assert_eq!(report["response"]["error"]["code"], -32000);
+    let logs = report["logs"].as_array().expect("Expected logs array");
+    assert!(logs.iter().any(|line| line.as_str().unwrap().contains("Inside spawn_blocking")), "{:?}", logs);
+
+    // Revert data isn't replaced by the request id.
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, rpc("eth_estimateGas", json!([{ "data": "0x60006000fd" }]))).await;
+    assert_eq!(response["error"]["code"], 3);
+    assert_eq!(response["error"]["data"], "0x");
+
+    // Signed bundle transactions are redacted before they are stored, and can't be replayed.
+    let response: serde_json::Value =
+        test::call_and_read_body_json(&app, rpc("eth_callBundle", json!([{ "txs": ["0x01"], "blockNumber": "0x1" }]))).await;
+    let request_id = response["error"]["data"]["requestId"].as_str().expect("Missing requestId").to_string();
+    let req = test::TestRequest::get()
+        .uri(&format!("/admin/requests/{}", request_id))
+        .insert_header(("Authorization", "Bearer replay-secret"))
+        .to_request();
+    let entry: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(entry["payload"]["params"][0]["txs"][0], "[redacted]");
+    assert_eq!(entry["redacted"], true);
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/replay/{}", request_id))
+        .insert_header(("Authorization", "Bearer replay-secret"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_call_bundle() {
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // EIP-1559 transfers from the first dev account, paying 1 gwei over a 1 gwei base fee
+    let signer = FixtureChain::new().signers()[0].clone();
+    let coinbase = address!("000000000000000000000000000000000000c0b1");
+    let sign = |nonce: u64, to: Address, value: U256| {
+        let mut tx = TxEip1559 {
+            chain_id: 31337,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 10_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(to),
+            value,
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut tx).unwrap();
+        Bytes::from(TxEnvelope::from(tx.into_signed(signature)).encoded_2718())
+    };
+    let call_bundle = |txs: Vec<Bytes>| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_callBundle",
+                "params": [{
+                    "txs": txs,
+                    "blockNumber": "0x1",
+                    "coinbase": coinbase,
+                    "baseFee": "0x3b9aca00"
+                }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // A transfer, then a direct payment of 0.01 ETH to the coinbase
+    let recipient = address!("70997970C51812dc3A010C7d01b50e0d17dc79C8");
+    let resp = test::call_service(
+        &app,
+        call_bundle(vec![sign(0, recipient, U256::from(1)), sign(1, coinbase, U256::from(10u64.pow(16)))]),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    let bundle = &response["result"];
+    let results = bundle["results"].as_array().expect("Expected results array");
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert_eq!(result["gasUsed"], "0x5208");
+        assert_eq!(result["gasPrice"], "0x77359400");
+        assert_eq!(result["gasFees"], "0x1319718a5000");
+        assert_eq!(result["value"], "0x");
+    }
+    assert_eq!(results[0]["ethSentToCoinbase"], "0x0");
+    assert_eq!(results[1]["ethSentToCoinbase"], "0x2386f26fc10000");
+    assert_eq!(results[1]["coinbaseDiff"], "0x239a0be14b5000");
+    assert_eq!(bundle["totalGasUsed"], "0xa410");
+    assert_eq!(bundle["gasFees"], "0x2632e314a000");
+    assert_eq!(bundle["ethSentToCoinbase"], "0x2386f26fc10000");
+    assert_eq!(bundle["blockNumber"], "0x1");
+
+    // A transaction failing validation (a reused nonce) fails the whole bundle
+    let resp = test::call_service(
+        &app,
+        call_bundle(vec![sign(0, recipient, U256::from(1)), sign(0, recipient, U256::from(1))]),
+    )
+    .await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    assert!(response["error"]["message"].as_str().unwrap().contains("transaction 1"));
+}
+
+#[actix_web::test]
+async fn test_call_bundle_limits() {
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // EIP-1559 transfers from the first dev account, paying 1 gwei over a 1 gwei base fee
+    let signer = FixtureChain::new().signers()[0].clone();
+    let sign = |nonce: u64, value: U256| {
+        let mut tx = TxEip1559 {
+            chain_id: 31337,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 10_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(address!("70997970C51812dc3A010C7d01b50e0d17dc79C8")),
+            value,
+            ..Default::default()
+        };
+        let signature = signer.sign_transaction_sync(&mut tx).unwrap();
+        Bytes::from(TxEnvelope::from(tx.into_signed(signature)).encoded_2718())
+    };
+    let call_bundle = |bundle: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_callBundle",
+                "params": [bundle],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // A coinbase sending a transaction of the bundle loses funds: the diffs are negative.
+    let resp = test::call_service(
+        &app,
+        call_bundle(json!({
+            "txs": [sign(0, U256::from(10u64.pow(18)))],
+            "blockNumber": "0x1",
+            "coinbase": signer.address(),
+            "baseFee": "0x3b9aca00"
+        })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    let bundle = &response["result"];
+    // 1 ETH and the 1 gwei base fee leave the coinbase; the 1 gwei tip comes back to it
+    let lost = U256::from(10u64.pow(18)) + U256::from(21_000u64 * 1_000_000_000);
+    assert_eq!(bundle["coinbaseDiff"], format!("-{:#x}", lost));
+    assert_eq!(bundle["results"][0]["coinbaseDiff"], format!("-{:#x}", lost));
+    assert_eq!(bundle["bundleGasPrice"], "0x0");
+
+    // Two transfers don't fit in a block with room for 40000 gas.
+    let resp = test::call_service(
+        &app,
+        call_bundle(json!({
+            "txs": [sign(0, U256::from(1)), sign(1, U256::from(1))],
+            "blockNumber": "0x1",
+            "gasLimit": "0x9c40"
+        })),
+    )
+    .await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32602);
+    assert!(response["error"]["message"].as_str().unwrap().contains("transaction 1"));
+
+    // Bundles are capped in size.
+    let txs: Vec<Bytes> = (0..101).map(|nonce| sign(nonce, U256::from(1))).collect();
+    let resp = test::call_service(&app, call_bundle(json!({ "txs": txs, "blockNumber": "0x1" }))).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body).expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32602);
+}
+
+#[actix_web::test]
+async fn test_simulation_provenance() {
+    let node = TestNode::spawn().await;
+
+    // The third Anvil dev key signs the reports
+    let signer = ProvenanceSigner::from_private_key("0x5de4111afa1a4b94908f83103eb1f1706367c2e68ca870fc3fb9a804cdab365a")
+        .expect("Invalid signing key");
+    let signer_address = signer.address();
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .provenance_signer(signer)
+        .build();
+
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+    let simulate = |tx: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(jsonrpc_request("estimator_simulate", json!([tx]), 1))
+            .to_request()
+    };
+
+    let req = simulate(json!({
+        "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+        "data": "0x1234"
+    }));
+    let report: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+    let report = &report["result"];
+
+    // The signature covers the reported facts and recovers to the service key.
+    let field = |name: &str| report[name].as_str().unwrap().parse::<B256>().unwrap();
+    let claim = ProvenanceClaim {
+        chain_id: report["chainId"].as_u64().unwrap(),
+        block_number: report["blockNumber"].as_u64().unwrap(),
+        block_hash: field("blockHash"),
+        request_hash: field("requestHash"),
+        gas_used: report["gasUsed"].as_u64().unwrap(),
+        result_hash: field("resultHash"),
+    };
+    let provenance = &report["provenance"];
+    assert_eq!(provenance["digest"].as_str().unwrap().parse::<B256>().unwrap(), claim.digest());
+    let signature: alloy::primitives::PrimitiveSignature = provenance["signature"].as_str().unwrap().parse().unwrap();
+    assert_eq!(signature.recover_address_from_msg(claim.digest()).unwrap(), signer_address);
+    assert_eq!(provenance["signer"].as_str().unwrap().parse::<Address>().unwrap(), signer_address);
+
+    // The request hash doesn't depend on field order or on naming the calldata `input` or `data`.
+    let req = simulate(json!({
+        "input": "0x1234",
+        "to": "0x70997970c51812dc3a010c7d01b50e0d17dc79c8",
+        "from": "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266"
+    }));
+    let rewritten: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(rewritten["result"]["requestHash"], report["requestHash"]);
+}
+
+#[actix_web::test]
+async fn test_simulation_memory_usage() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Init code returning 64 bytes of memory: PUSH1 0x40 PUSH1 0x00 RETURN
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "estimator_simulate",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x60406000f3"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let usage = &response["result"]["memoryUsage"];
+
+    // Two words of memory: 3 * 2 + 2 * 2 / 512 = 6 gas
+    assert_eq!(usage["returnDataSize"], 64);
+    assert_eq!(usage["returnDataGas"], 6);
+    assert_eq!(usage["peakMemoryBytes"], 64);
+    assert_eq!(usage["memoryExpansionGas"], 6);
+}
+
+#[actix_web::test]
+async fn test_simulation_self_destruct_warning() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Init code that self-destructs right away: CALLER SELFDESTRUCT
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "estimator_simulate",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x33ff"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let warnings = response["result"]["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "selfDestruct");
+    // The contract created by the sender's first transaction
+    assert_eq!(
+        warnings[0]["address"].as_str().unwrap().to_lowercase(),
+        "0x5fbdb2315678afecb367f032d93f642f64180aa3"
+    );
+
+    // A plain transfer raises no warnings.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "estimator_simulate",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"]["warnings"], json!([]));
+}
+
+#[actix_web::test]
+async fn test_simulation_state_diff() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // Init code storing 42 in slot 0: PUSH1 0x2a PUSH1 0x00 SSTORE
+    let simulate = |options: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "estimator_simulate",
+                "params": [{
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "data": "0x602a600055"
+                }, options],
+                "id": 1
+            }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, simulate(json!({"stateDiff": true}))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let diff = response["result"]["stateDiff"].as_object().expect("Missing state diff");
+    let account = |address: &str| {
+        diff.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(address))
+            .map(|(_, account)| account)
+            .unwrap_or_else(|| panic!("{} missing from the state diff", address))
+    };
+
+    // The sender's nonce is bumped and it pays for the gas
+    let sender = account("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    assert_eq!(sender["nonce"], json!({"from": 0, "to": 1}));
+    assert!(sender["balance"].is_object());
+
+    // The created contract starts at nonce 1 with slot 0 set
+    let contract = account("0x5FbDB2315678afecb367f032d93F642f64180aa3");
+    assert_eq!(contract["nonce"], json!({"from": 0, "to": 1}));
+    let slot = format!("0x{:064x}", 0);
+    assert_eq!(contract["storage"][&slot]["from"], format!("0x{:064x}", 0));
+    assert_eq!(contract["storage"][&slot]["to"], format!("0x{:064x}", 0x2a));
+    assert!(contract.get("code").is_none());
+
+    // The sender pays for exactly the gas the report says was used, as both come from one run
+    let resp = test::call_service(&app, test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "estimator_simulate",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "data": "0x602a600055",
+                "gasPrice": "0x77359400"
+            }, {"stateDiff": true}],
+            "id": 1
+        }))
+        .to_request()).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let gas_used = response["result"]["gasUsed"].as_u64().expect("Missing gas used");
+    let sender = response["result"]["stateDiff"].as_object().expect("Missing state diff")
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"))
+        .map(|(_, account)| account.clone())
+        .expect("Sender missing from the state diff");
+    let balance = |value: &serde_json::Value| value.as_str().expect("Missing balance").parse::<U256>().unwrap();
+    let paid = balance(&sender["balance"]["from"]) - balance(&sender["balance"]["to"]);
+    assert_eq!(paid, U256::from(gas_used) * U256::from(2_000_000_000u64));
+
+    // Without the option the report carries no state diff.
+    let resp = test::call_service(&app, simulate(json!({}))).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert!(response["result"].get("stateDiff").is_none());
+}
+
+#[actix_web::test]
+async fn test_self_destruct_warning_follows_eip_6780() {
+    // A deployed contract that self-destructs when called: CALLER SELFDESTRUCT
+    let contract: Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let tx_request = TransactionRequest::default()
+        .from(address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"))
+        .to(contract);
+    let self_destructs = |warnings: &[SimulationWarning]| {
+        warnings.iter().filter(|warning| warning.kind == WarningKind::SelfDestruct).count()
+    };
+
+    for (hardfork, expected) in [("shanghai", 1), ("cancun", 0)] {
+        let node = TestNode::spawn_with(&AnvilOptions {
+            hardfork: hardfork.to_string(),
+            ..AnvilOptions::default()
+        })
+        .await;
+        let _: serde_json::Value = node.client
+            .provider
+            .raw_request("anvil_setCode".into(), (contract, "0x33ff"))
+            .await
+            .expect("Failed to set the contract code");
+        let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+            .chain_options(ChainOptions::default().with_spec_ids(hardfork).unwrap())
+            .build();
+
+        // Before Cancun the contract is destroyed; since, it only sends its balance away
+        let report = estimator
+            .simulate(&tx_request, SimulateOptions::default())
+            .await
+            .expect("Simulation failed");
+        assert_eq!(self_destructs(&report.warnings), expected, "{}", hardfork);
+    }
+}
+
+#[actix_web::test]
+async fn test_blob_count_limits() {
+    let estimate_with_blobs = |blob_count: u64| {
+        test::TestRequest::post()
+            .uri("/")
+            .insert_header(("Accept-Version", "2"))
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                    "blobCount": blob_count
+                }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // Under Prague a transaction carries up to 9 blobs (EIP-7691)
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+    let resp = test::call_service(&app, estimate_with_blobs(9)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let response = read_json(resp).await;
+    assert_eq!(response["meta"]["blobGasUsed"], "0x120000"); // 9 * 131072
+    let resp = test::call_service(&app, estimate_with_blobs(10)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(read_json(resp).await["error"]["code"], -32602);
+
+    // Under Cancun the simulated fork allows only 6
+    let node = TestNode::spawn_with(&AnvilOptions {
+        hardfork: "cancun".to_string(),
+        ..AnvilOptions::default()
+    })
+    .await;
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(ChainOptions::default().with_spec_ids("cancun").unwrap())
+        .build();
+    let app = test::init_service(api_app(Arc::new(estimator))).await;
+    let resp = test::call_service(&app, estimate_with_blobs(6)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, estimate_with_blobs(7)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(read_json(resp).await["error"]["code"], -32602);
+}
+
+#[actix_web::test]
+async fn test_quote_validation_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .configure(quotes::configure)
+    ).await;
+
+    // Obtain a quote through a verbose simulation.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "estimator_simulate",
+            "params": [{
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            }],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let mut report = response["result"].clone();
+    assert_eq!(report["status"], "success");
+    assert_eq!(report["gasUsed"], 21000);
+    assert!(report["validUntilBlock"].as_u64().unwrap() > report["blockNumber"].as_u64().unwrap());
+
+    // The unchanged report is still valid at the same head.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quotes/validate")
+        .set_json(&report)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let validation: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(validation["valid"], true);
+
+    // A quote taken at a much lower base fee is stale.
+    report["baseFeePerGas"] = json!("0x1");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quotes/validate")
+        .set_json(&report)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let validation: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(validation["valid"], false);
+    assert!(!validation["reasons"].as_array().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_simulation_report_sender_nonce() {
+    let node = TestNode::spawn().await;
+    let provider = &node.client.provider;
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    // A mined transfer moves the sender's nonce off zero
+    let _: B256 = provider
+        .raw_request("eth_sendTransaction".into(), (transfer_params("0x1"),))
+        .await
+        .expect("Failed to send a transfer");
+    let nonce = provider.get_transaction_count(from).await.expect("Failed to get the nonce");
+    assert!(nonce > 0);
+
+    // The report carries the sender's nonce at the simulated block, read from the fork
+    let estimator = Arc::new(GasEstimator::builder(node.client.clone(), &node.rpc_url).latest_block_ttl(Duration::ZERO).build());
+    let report = estimator
+        .simulate(&serde_json::from_value(transfer_params("0x1")).unwrap(), SimulateOptions::default())
+        .await
+        .expect("Simulation failed");
+    assert_eq!(report.sender_nonce, Some(nonce));
+    let fork = estimator.forks.latest().await.expect("Failed to fork the head");
+    assert_eq!(report.block_hash, fork.block_hash);
+    assert_eq!(account_on_fork(&fork, from).await.unwrap().nonce, nonce);
+}
+
+#[actix_web::test]
+async fn test_op_stack_l1_data_fee() {
+    // An Anvil node with OP Mainnet's chain id.
+    let node = TestNode::spawn_with(&AnvilOptions { chain_id: Some(10), ..AnvilOptions::default() }).await;
+
+    // A GasPriceOracle pricing every transaction's L1 data at 0x1234 wei: mstore(0, 0x1234) return(0, 32)
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request(
+            "anvil_setCode".into(),
+            ("0x420000000000000000000000000000000000000F", "0x61123460005260206000f3"),
+        )
+        .await
+        .expect("Failed to set the GasPriceOracle code");
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .configure(quotes::configure)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(estimate_gas_request(transfer_params("0x1")))
+        .to_request();
+    let estimate: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quote")
+        .set_json(json!({ "transaction": transfer_params("0x1") }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(jsonrpc_request("eth_call", json!([transfer_params("0x1"), "latest"]), 2))
+        .to_request();
+    let call: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+
+    #[cfg(feature = "optimism")]
+    {
+        // The estimate is L2 execution only...
+        assert_eq!(estimate["result"], "0x5208");
+        assert_eq!(call["result"], "0x");
+
+        // ... and the L1 data fee is priced by the quote, on top of the execution cost
+        assert_eq!(resp.status(), StatusCode::OK);
+        let quote: serde_json::Value = read_json(resp).await;
+        assert_eq!(quote["gasLimit"], "0x5208");
+        assert_eq!(quote["l1DataFee"], "0x1234");
+        let hex = |field: &str| u128::from_str_radix(quote[field].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+        assert_eq!(hex("totalFee"), hex("executionFee") + hex("priorityFee") + 0x1234);
+    }
+
+    // Without the adapter, OP Stack chains aren't estimated with the L1 gas model
+    #[cfg(not(feature = "optimism"))]
+    {
+        assert_eq!(estimate["error"]["code"], -32004);
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        // Calls are refused with the same error, not as an internal failure
+        assert_eq!(call["error"]["code"], -32004, "{}", call);
+    }
+}
+
+#[actix_web::test]
+async fn test_cost_quote_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(quotes::configure)
+    ).await;
+
+    // A plain transfer costs its gas limit at the next base fee plus the tip.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quote")
+        .set_json(json!({
+            "transaction": {
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            },
+            "fiatPrice": "2000",
+            "fiatCurrency": "USD"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let quote: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(quote["gasLimit"], "0x5208");
+    assert!(quote.get("blobFee").is_none());
+    assert!(quote.get("l1DataFee").is_none());
+    let hex = |field: &str| u128::from_str_radix(quote[field].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+    assert_eq!(hex("executionFee"), 21000 * hex("nextBaseFeePerGas"));
+    assert_eq!(hex("totalFee"), hex("executionFee") + hex("priorityFee"));
+    assert_eq!(quote["fiat"]["currency"], "USD");
+    assert!(quote["fiat"]["total"].as_str().unwrap().contains('.'));
+
+    // Urgency levels need priority fee tracking.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quote")
+        .set_json(json!({
+            "transaction": {
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+            },
+            "urgency": "fast"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // An unknown urgency level is invalid, tracking or not.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quote")
+        .set_json(json!({
+            "transaction": {
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+            },
+            "urgency": "urgent"
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response: serde_json::Value = read_json(resp).await;
+    assert_eq!(response["error_code"], "INVALID_PARAMS");
+}
+
+#[actix_web::test]
+async fn test_nonce_ladder_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(ladder::configure)
+    ).await;
+
+    // Two transfers sent back-to-back get consecutive nonces.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/ladder")
+        .set_json(json!({
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "transactions": [
+                { "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "value": "0x1" },
+                { "to": "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC", "value": "0x1" }
+            ]
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let ladder: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(ladder["startNonce"], 0);
+    assert_eq!(ladder["totalGas"], "0xa410"); // 2 * 21000
+    let steps = ladder["transactions"].as_array().expect("Expected transactions array");
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[1]["nonce"], 1);
+    assert_eq!(steps[1]["status"], "success");
+    assert_eq!(steps[1]["gasUsed"], "0x5208");
+
+    // An empty ladder is rejected.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/ladder")
+        .set_json(json!({
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "transactions": []
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_keeper_projection_endpoint() {
+    let node = TestNode::spawn().await;
+
+    // A counter: sstore(0, add(sload(0), 1))
+    let contract: Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x60005460010160005500"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let estimator = node.estimator();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(estimator.clone()))
+            .configure(keeper::configure)
+    ).await;
+    let transaction = json!({ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract });
+
+    // Without a gas limit, the worst case is the limit eth_estimateGas would return
+    let req = test::TestRequest::post()
+        .uri("/api/v1/keeper/projection")
+        .set_json(json!({ "transaction": transaction, "schedule": { "intervalSeconds": 3600 } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let projection = read_json(resp).await;
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract);
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    let worst_case: U256 = serde_json::from_value(projection["worstCaseGas"].clone()).unwrap();
+    let gas_used: U256 = serde_json::from_value(projection["gasUsed"].clone()).unwrap();
+    assert_eq!(worst_case, estimate.gas);
+    assert!(worst_case >= gas_used);
+    assert_eq!(projection["executionsPerDay"], 24.0);
+
+    // The job's own limit is its worst case
+    let mut limited = transaction.clone();
+    limited["gas"] = json!("0x30000");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/keeper/projection")
+        .set_json(json!({ "transaction": limited, "schedule": { "executionsPerDay": 2 } }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(read_json(resp).await["worstCaseGas"], "0x30000");
+
+    // More fee history than a node serves per call is rejected
+    let req = test::TestRequest::post()
+        .uri("/api/v1/keeper/projection")
+        .set_json(json!({
+            "transaction": transaction,
+            "schedule": { "intervalSeconds": 3600 },
+            "historyBlocks": keeper::MAX_HISTORY_BLOCKS + 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_bulk_estimate_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(bulk::configure)
+    ).await;
+
+    // Two transfers are estimated against the same block; the unfunded sender
+    // fails on its own without affecting the other.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/bulk")
+        .set_json(json!([
+            {
+                "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0x1"
+            },
+            {
+                "from": "0x0000000000000000000000000000000000000001",
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0xde0b6b3a7640000"
+            }
+        ]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let bulk: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert!(bulk["blockNumber"].is_string());
+    let results = bulk["results"].as_array().expect("Expected results array");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["index"], 0);
+    assert_eq!(results[0]["gas"], "0x5208");
+    assert_eq!(results[0]["source"], "simulation");
+    assert!(results[1]["error"].is_string());
+    assert_eq!(bulk["summary"]["total"], 2);
+    assert_eq!(bulk["summary"]["failedCount"], 1);
+
+    // An empty request is rejected.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/bulk")
+        .set_json(json!([]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_bulk_estimate_bounded_concurrency() {
+    let node = TestNode::spawn().await;
+
+    // Two workers estimate five transfers of increasing value.
+    let in_flight = Arc::new(InFlightHook::default());
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .batch_concurrency(2)
+        .hook(in_flight.clone())
+        .build();
+    assert_eq!(estimator.batch_concurrency(5), 2);
+    assert_eq!(estimator.batch_concurrency(1), 1);
+    let unbounded = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .batch_concurrency(0)
+        .build();
+    assert_eq!(unbounded.batch_concurrency(1_000), 1, "Zero can't lift the bound");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Arc::new(estimator)))
+            .configure(bulk::configure)
+    ).await;
+
+    let transfers: Vec<serde_json::Value> = (1..=5)
+        .map(|value| json!({
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "value": format!("0x{:x}", value)
+        }))
+        .collect();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/bulk")
+        .set_json(json!(transfers))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let bulk: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+
+    // Results keep the request order.
+    let results = bulk["results"].as_array().expect("Expected results array");
+    assert_eq!(results.len(), 5);
+    for (index, result) in results.iter().enumerate() {
+        assert_eq!(result["index"], index);
+        assert_eq!(result["gas"], "0x5208");
+    }
+    assert_eq!(bulk["summary"]["failedCount"], 0);
+
+    // The transfers ran two at a time, never more
+    assert_eq!(in_flight.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert_eq!(in_flight.in_flight.load(std::sync::atomic::Ordering::SeqCst), 0);
+}
+
+#[actix_web::test]
+async fn test_sequence_estimate_endpoint() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(sequence::configure)
+    ).await;
+
+    // The second transfer only succeeds because the first one funds its sender.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/sequence")
+        .set_json(json!({
+            "transactions": [
+                {
+                    "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                    "to": "0x0000000000000000000000000000000000000001",
+                    "value": "0x1bc16d674ec80000"
+                },
+                {
+                    "from": "0x0000000000000000000000000000000000000001",
+                    "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                    "value": "0xde0b6b3a7640000"
+                }
+            ]
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let sequence: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let transactions = sequence["transactions"].as_array().expect("Expected transactions array");
+    assert_eq!(transactions.len(), 2);
+    for (index, step) in transactions.iter().enumerate() {
+        assert_eq!(step["index"], index);
+        assert_eq!(step["status"], "success");
+        assert_eq!(step["gasLimit"], "0x5208");
+    }
+    assert_eq!(sequence["totalGasUsed"], "0xa410");
+
+    // Every transaction must name its sender.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/sequence")
+        .set_json(json!({
+            "transactions": [
+                { "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "value": "0x1" }
+            ]
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_sequence_of_dependent_contract_calls() {
+    let node = TestNode::spawn().await;
+
+    // Called with data, the contract sets slot 0; called without, it reverts unless slot 0 is set
+    // and then writes slot 1.
+    let contract = address!("0000000000000000000000000000000000005e0a");
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x36600e57600054601557600080fd5b6001600055005b600160015500"))
+        .await
+        .expect("Failed to set the contract code");
+
+    // Build a GasEstimator with a fixed headroom on top of every limit.
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .headroom(Headroom::new(1.0, 10_000).unwrap())
+        .build();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Arc::new(estimator)))
+            .configure(sequence::configure)
+    ).await;
+    let estimate_sequence = |transactions: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/eth/estimateGas/sequence")
+            .set_json(json!({ "transactions": transactions }))
+            .to_request()
+    };
+    let use_call = json!({ "from": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8", "to": contract });
+
+    // On its own, the call reverts.
+    let resp = test::call_service(&app, estimate_sequence(json!([use_call]))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let sequence: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(sequence["transactions"][0]["status"], "revert");
+    assert!(sequence["transactions"][0]["gasLimit"].is_null());
+
+    // After the call that sets slot 0, it succeeds and pays for its fresh storage write.
+    let set_call = json!({ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract, "data": "0x01" });
+    let resp = test::call_service(&app, estimate_sequence(json!([set_call, use_call]))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let sequence: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let quantity = |value: &serde_json::Value| {
+        u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    };
+    for step in sequence["transactions"].as_array().expect("Expected transactions array") {
+        assert_eq!(step["status"], "success");
+        // The headroom is added to the limit the search found, not to the gas used
+        assert!(quantity(&step["gasLimit"]) >= quantity(&step["gasUsed"]) + 10_000, "{}", step);
+    }
+    assert!(quantity(&sequence["transactions"][1]["gasUsed"]) > 21_000 + 20_000);
+
+    // Sequences are capped in length.
+    let transfers = vec![json!({ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract }); 65];
+    let resp = test::call_service(&app, estimate_sequence(json!(transfers))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_next_fees_long_poll() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(fees::configure)
+    ).await;
+
+    // Mine a block so the head is past block 0.
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_mine".into(), ())
+        .await
+        .expect("Failed to mine a block");
+
+    // A head past `after` is answered right away.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fees/next?after=0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let fees: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(fees["blockNumber"], "0x1");
+    assert!(fees["nextBaseFeePerGas"].as_str().unwrap().starts_with("0x"));
+    assert!(fees["maxFeePerGas"].as_str().unwrap().starts_with("0x"));
+
+    // Without a new head, the poll times out with no content.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fees/next?timeoutMs=200")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn test_next_fees_follow_the_chain_base_fee_params() {
+    // OP Mainnet lowers its base fee in smaller steps than Ethereum
+    let node = TestNode::spawn_with(&AnvilOptions {
+        chain_id: Some(10),
+        ..AnvilOptions::default()
+    })
+    .await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .configure(fees::configure)
+    ).await;
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_mine".into(), ())
+        .await
+        .expect("Failed to mine a block");
+
+    let req = test::TestRequest::get().uri("/api/v1/fees/next?after=0").to_request();
+    let fees = read_json(test::call_service(&app, req).await).await;
+    let head = node.client.provider.get_block(BlockId::number(1)).await.unwrap().unwrap().header;
+    let base_fee = head.base_fee_per_gas.unwrap();
+    let expected = alloy::eips::eip1559::calc_next_block_base_fee(
+        head.gas_used,
+        head.gas_limit,
+        base_fee,
+        alloy::eips::eip1559::BaseFeeParams::optimism_canyon(),
+    );
+    assert_ne!(expected, alloy::eips::eip1559::calc_next_block_base_fee(
+        head.gas_used,
+        head.gas_limit,
+        base_fee,
+        alloy::eips::eip1559::BaseFeeParams::ethereum(),
+    ));
+    assert_eq!(fees["nextBaseFeePerGas"], format!("{:#x}", expected));
+}
+
+#[actix_web::test]
+async fn test_priority_fee_suggestions() {
+    let node = TestNode::spawn().await;
+
+    let tracker = Arc::new(PriorityFeeTracker::new(5, vec![0.0, 50.0, 100.0]).unwrap());
+
+    // Mine transfers paying tips of 1, 2 and 3 gwei, one block each.
+    let accounts = node.client.provider.get_accounts().await.unwrap();
+    for tip in 1..=3u128 {
+        let tx = TransactionRequest::default()
+            .from(accounts[0])
+            .to(accounts[1])
+            .max_priority_fee_per_gas(tip * 1_000_000_000)
+            .max_fee_per_gas(100_000_000_000);
+        node.client
+            .provider
+            .send_transaction(tx)
+            .await
+            .expect("Failed to send transaction")
+            .get_receipt()
+            .await
+            .expect("Failed to get receipt");
+    }
+    tracker.refresh(&node.client).await.expect("Failed to refresh the priority fee window");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(tracker))
+            .configure(fees::configure)
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fees/priority")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let fees: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+
+    // Blocks 0 to 3 are in the window, with one transaction in each mined block.
+    assert_eq!(fees["blockNumber"], "0x3");
+    assert_eq!(fees["windowBlocks"], 4);
+    assert_eq!(fees["sampledBlocks"], 3);
+    assert_eq!(fees["suggestions"][0]["maxPriorityFeePerGas"], "0x3b9aca00");
+    assert_eq!(fees["suggestions"][1]["maxPriorityFeePerGas"], "0x77359400");
+    assert_eq!(fees["suggestions"][2]["maxPriorityFeePerGas"], "0xb2d05e00");
+}
+
+#[actix_web::test]
+async fn test_priority_fee_window_follows_new_blocks() {
+    let node = TestNode::spawn().await;
+
+    let tracker = PriorityFeeTracker::new(2, vec![50.0]).unwrap();
+    let accounts = node.client.provider.get_accounts().await.unwrap();
+    let send_with_tip = |tip: u128| {
+        let tx = TransactionRequest::default()
+            .from(accounts[0])
+            .to(accounts[1])
+            .max_priority_fee_per_gas(tip)
+            .max_fee_per_gas(100_000_000_000);
+        async move {
+            node.client
+                .provider
+                .send_transaction(tx)
+                .await
+                .expect("Failed to send transaction")
+                .get_receipt()
+                .await
+                .expect("Failed to get receipt");
+        }
+    };
+
+    // Block 1 tips 1 gwei
+    send_with_tip(1_000_000_000).await;
+    tracker.refresh(&node.client).await.expect("Failed to refresh the priority fee window");
+    let suggestions = tracker.suggestions();
+    assert_eq!(suggestions.block_number, Some(U64::from(1)));
+    assert_eq!(suggestions.sampled_blocks, 1);
+    assert_eq!(suggestions.suggestions[0].max_priority_fee_per_gas, U256::from(1_000_000_000u64));
+
+    // Block 2 is empty and block 3 tips 3 gwei; block 1 leaves the two-block window
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_mine".into(), ())
+        .await
+        .expect("Failed to mine a block");
+    send_with_tip(3_000_000_000).await;
+    tracker.refresh(&node.client).await.expect("Failed to refresh the priority fee window");
+    let suggestions = tracker.suggestions();
+    assert_eq!(suggestions.block_number, Some(U64::from(3)));
+    assert_eq!(suggestions.window_blocks, 2);
+    assert_eq!(suggestions.sampled_blocks, 1);
+    assert_eq!(suggestions.suggestions[0].max_priority_fee_per_gas, U256::from(3_000_000_000u64));
+}
+
+#[actix_web::test]
+async fn test_fee_recommendations() {
+    let node = TestNode::spawn().await;
+
+    let tracker = Arc::new(PriorityFeeTracker::new(5, vec![50.0]).unwrap());
+
+    // Mine a transfer paying a 2 gwei tip.
+    let accounts = node.client.provider.get_accounts().await.unwrap();
+    let tx = TransactionRequest::default()
+        .from(accounts[0])
+        .to(accounts[1])
+        .max_priority_fee_per_gas(2_000_000_000)
+        .max_fee_per_gas(100_000_000_000);
+    node.client
+        .provider
+        .send_transaction(tx)
+        .await
+        .expect("Failed to send transaction")
+        .get_receipt()
+        .await
+        .expect("Failed to get receipt");
+    tracker.refresh(&node.client).await.expect("Failed to refresh the priority fee window");
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .app_data(web::Data::new(tracker))
+            .configure(fees::configure)
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fees/recommendations")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let fees: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+
+    let quantity = |value: &serde_json::Value| {
+        u128::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    };
+    let next_base_fee = quantity(&fees["nextBaseFeePerGas"]);
+    let levels = fees["levels"].as_array().unwrap();
+    let urgencies: Vec<_> = levels.iter().map(|level| level["urgency"].as_str().unwrap()).collect();
+    assert_eq!(urgencies, ["slow", "standard", "fast"]);
+
+    // Every level tips the only observed tip; the slow cap only covers the next block.
+    for level in levels {
+        assert_eq!(level["maxPriorityFeePerGas"], "0x77359400");
+    }
+    assert_eq!(quantity(&levels[0]["maxFeePerGas"]), next_base_fee + 2_000_000_000);
+    // Faster levels cover more blocks of base fee increases, so their caps are never lower.
+    assert!(quantity(&levels[2]["maxFeePerGas"]) > quantity(&levels[1]["maxFeePerGas"]));
+    assert!(quantity(&levels[1]["maxFeePerGas"]) > quantity(&levels[0]["maxFeePerGas"]));
+}
+
+#[actix_web::test]
+async fn test_rate_limiting() {
+    let node = TestNode::spawn().await;
+
+    // Three requests per client, refilled too slowly to matter; 10.0.0.1 is the reverse proxy
+    let proxy: std::net::SocketAddr = "10.0.0.1:40000".parse().unwrap();
+    let limit = RateLimit { per_second: 0.001, burst: 3.0 };
+    let limiter = Arc::new(RateLimiter::new(None, Some(limit)).with_trusted_proxies(vec![proxy.ip()]));
+    let app = test::init_service(
+        api_app(node.estimator())
+            .wrap_fn(move |req, srv| rate_limit(req, srv, &limiter))
+    ).await;
+    let chain_id = |id: u64| json!({ "jsonrpc": "2.0", "method": "eth_chainId", "params": [], "id": id });
+    let request = |peer: &str, forwarded_for: Option<&str>, body: serde_json::Value| {
+        let mut req = test::TestRequest::post().uri("/").peer_addr(peer.parse().unwrap()).set_json(body);
+        if let Some(forwarded_for) = forwarded_for {
+            req = req.insert_header(("X-Forwarded-For", forwarded_for));
+        }
+        req.to_request()
+    };
+
+    // Every item of a batch costs a token: the batch uses the client's whole burst
+    let batch = json!([chain_id(1), chain_id(2), chain_id(3)]);
+    let resp = test::call_service(&app, request("192.0.2.1:1000", None, batch)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, request("192.0.2.1:1001", None, chain_id(4))).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("retry-after"));
+    assert_eq!(read_json(resp).await["error"]["code"], -32005);
+
+    // A batch larger than the burst is admitted once, and the client then waits for it to be paid
+    let batch = json!([chain_id(1), chain_id(2), chain_id(3), chain_id(4), chain_id(5)]);
+    let resp = test::call_service(&app, request("192.0.2.2:1000", None, batch)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, request("192.0.2.2:1001", None, chain_id(6))).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Behind the trusted proxy, clients are told apart by the address the proxy appended
+    let resp = test::call_service(&app, request("10.0.0.1:40000", Some("192.0.2.1, 192.0.2.3"), chain_id(7))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, request("10.0.0.1:40001", Some("192.0.2.2"), chain_id(8))).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Anyone else's X-Forwarded-For is ignored, so a limited client can't claim another address
+    let resp = test::call_service(&app, request("192.0.2.1:1002", Some("192.0.2.4"), chain_id(9))).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Probes are never limited
+    let req = test::TestRequest::get().uri("/livez").peer_addr("192.0.2.1:1003".parse().unwrap()).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_global_rate_limit_spares_client_allowance() {
+    // One global token every 50ms; two tokens per client, refilled too slowly to matter
+    let global = RateLimit { per_second: 20.0, burst: 1.0 };
+    let per_client = RateLimit { per_second: 0.001, burst: 2.0 };
+    let limiter = RateLimiter::new(Some(global), Some(per_client));
+    let client = Some("192.0.2.1".parse().unwrap());
+
+    limiter.check(client, 1).expect("The first request is within both limits");
+
+    // Retries turned away by the saturated global limit don't cost the client anything
+    for _ in 0..5 {
+        assert!(limiter.check(client, 1).is_err());
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    limiter.check(client, 1).expect("The client still has its second token");
+
+    // Once the global limit recovers, the client's own limit applies
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(limiter.check(client, 1).is_err());
+}
+
+#[test]
+fn test_rate_limit_config_validation() {
+    let load = |setting: &str| {
+        let path = std::env::temp_dir().join(format!("rate-limit-{}.toml", setting.len()));
+        std::fs::write(&path, format!("{}\n", setting)).unwrap();
+        let config = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        config
+    };
+    let config = load("rate_limit_per_ip_rps = \"2.5\"\nrate_limit_trusted_proxies = [\"10.0.0.1\", \"::1\"]")
+        .expect("A positive rate is valid");
+    assert_eq!(config.rate_limits().1, Some(RateLimit { per_second: 2.5, burst: 2.5 }));
+    assert_eq!(config.rate_limit_trusted_proxies.len(), 2);
+    for invalid in ["rate_limit_global_rps = \"NaN\"", "rate_limit_per_ip_rps = \"-1\"", "rate_limit_per_ip_burst = \"inf\""] {
+        let error = load(invalid).expect_err("Invalid rates are rejected");
+        assert!(error.to_string().contains("RATE_LIMIT_"), "{}", error);
+    }
+}
+
+/// Pipeline hook failing estimates of 2 wei transfers on the server's side
+struct FailingHook;
+
+impl EstimationHook for FailingHook {
+    fn name(&self) -> &'static str {
+        "failing"
+    }
+
+    fn pre_validate(&self, tx_request: &TransactionRequest) -> Result<(), ServiceError> {
+        match tx_request.value == Some(U256::from(2)) {
+            true => Err(ServiceError::Estimation("backend exploded".to_string())),
+            false => Ok(()),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_request_metrics_count_in_band_server_errors() {
+    let node = TestNode::spawn().await;
+    let estimator = Arc::new(GasEstimator::builder(node.client.clone(), &node.rpc_url).hook(Arc::new(FailingHook)).build());
+    let metrics = Arc::new(RequestMetrics::new(Duration::from_secs(60)));
+    let recorder = metrics.clone();
+    let app = test::init_service(
+        api_app(estimator)
+            .app_data(web::Data::new(metrics.clone()))
+            .wrap_fn(move |req, srv| record_metrics(req, srv, &recorder)),
+    )
+    .await;
+    let batch = |values: &[&str]| {
+        let items: Vec<_> = values
+            .iter()
+            .enumerate()
+            .map(|(id, value)| jsonrpc_request("eth_estimateGas", json!([transfer_params(value)]), id as u64))
+            .collect();
+        serde_json::Value::Array(items)
+    };
+    let server_errors = || metrics.totals().server_errors;
+
+    // A batch with only client errors is a success
+    let req = test::TestRequest::post().uri("/").set_json(batch(&["0x1", "not-a-quantity"])).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let _ = test::read_body(resp).await;
+    assert_eq!((metrics.totals().requests, server_errors()), (1, 0));
+
+    // A batch with an item failing on the server's side is answered 200 but counts as a server error
+    let req = test::TestRequest::post().uri("/").set_json(batch(&["0x1", "0x2"])).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let responses = read_json(resp).await;
+    assert_eq!(responses[1]["error"]["code"], -32603, "unexpected response: {}", responses);
+    assert_eq!((metrics.totals().requests, server_errors()), (2, 1));
+
+    // So does a streamed batch, once its items have run
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/stream")
+        .set_json(batch(&["0x1", "0x2"]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert!(String::from_utf8_lossy(&body).contains("\"failedCount\":1"));
+    assert_eq!((metrics.totals().requests, server_errors()), (3, 2));
+
+    // The SLO report sees them
+    let report = slo_report(&metrics.totals(), metrics.window(), &SloObjectives::default());
+    assert!(!report.success_rate.within_budget);
+}
+
+#[actix_web::test]
+async fn test_tier_redaction() {
+    let node = TestNode::spawn().await;
+
+    // Anonymous callers get plain estimates, the internal key gets everything.
+    let policies = Arc::new(
+        RedactionPolicies::new("internal-key=internal", "anonymous=traces|stateDiffs|logs").unwrap(),
+    );
+    let app = test::init_service(
+        api_app(node.estimator())
+            .wrap_fn(move |req, srv| resolve_redaction(req, srv, &policies))
+    ).await;
+
+    let transfer = json!({
+        "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+        "value": "0x1"
+    });
+    let detailed = json!({
+        "jsonrpc": "2.0",
+        "method": "x_estimateGasDetailed",
+        "params": [transfer],
+        "id": 1
+    });
+
+    // The call frames are withheld from anonymous callers.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(&detailed)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["result"]["gas"], "0x5208");
+    assert!(response["result"].get("callFrames").is_none());
+
+    // The internal tier receives them.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("X-API-Key", "internal-key"))
+        .set_json(&detailed)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert!(response["result"]["callFrames"].is_object());
+
+    // Traces as a whole are refused.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "debug_traceCall",
+            "params": [transfer, "latest", { "tracer": "callTracer" }],
+            "id": 2
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32004);
+
+    // Unknown keys are rejected.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("X-API-Key", "guessed-key"))
+        .set_json(&detailed)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Probes need no key, whatever key they carry.
+    let req = test::TestRequest::get()
+        .uri("/readyz")
+        .insert_header(("X-API-Key", "guessed-key"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_api_keys_leave_health_and_admin_alone() {
+    let node = TestNode::spawn().await;
+
+    let path = std::env::temp_dir().join("api-keys-admin.toml");
+    std::fs::write(&path, "admin_token = \"admin-secret\"\n").unwrap();
+    let config = Config::from_file(&path).expect("Failed to load the config");
+    let _ = std::fs::remove_file(&path);
+
+    let policies = RedactionPolicies::new("internal-key=internal", "anonymous=traces").unwrap();
+    assert!(policies.resolve(Some("internal-key")).is_some());
+    assert!(policies.resolve(Some("internal-ke")).is_none(), "A key prefix isn't a key");
+    let policies = Arc::new(policies);
+    let app = test::init_service(
+        api_app(node.estimator())
+            .app_data(web::Data::new(config))
+            .configure(admin::configure)
+            .wrap_fn(move |req, srv| resolve_redaction(req, srv, &policies))
+    ).await;
+
+    // The health check answers whatever key it carries.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/health")
+        .insert_header(("X-API-Key", "guessed-key"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Admin requests are authorized by the admin token alone.
+    let req = test::TestRequest::get()
+        .uri("/admin/requests/unknown")
+        .insert_header(("X-API-Key", "guessed-key"))
+        .insert_header(("Authorization", "Bearer admin-secret"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get()
+        .uri("/admin/requests/unknown")
+        .insert_header(("X-API-Key", "internal-key"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_request_deadline() {
+    let node = TestNode::spawn().await;
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .wrap_fn(|req, srv| request_timeout(req, srv, Duration::from_secs(30)))
+    ).await;
+
+    let estimate = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_estimateGas",
+        "params": [{
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "value": "0x1"
+        }],
+        "id": 1
+    });
+
+    // A caller's budget that leaves enough time is served as usual.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("X-Deadline-Ms", "5000"))
+        .set_json(&estimate)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // A caller that has already given up gets a timeout without any work being started.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("X-Deadline-Ms", "0"))
+        .set_json(&estimate)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32050);
+}
+
+#[test]
+fn test_timeout_validation() {
+    for setting in ["rpc_timeout_ms", "simulation_timeout_ms", "request_timeout_ms"] {
+        let path = std::env::temp_dir().join(format!("{}.toml", setting));
+        std::fs::write(&path, format!("{} = \"0\"\n", setting)).unwrap();
+        let error = Config::from_file(&path).expect_err("A zero timeout is rejected");
+        let _ = std::fs::remove_file(&path);
+        assert!(error.to_string().contains(&setting.to_uppercase()), "{}", error);
+    }
+}
+
+#[actix_web::test]
+async fn test_upstream_timeout() {
+    // A node that accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let stalled_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let error = chain_id_at(&stalled_url, Duration::from_millis(200))
+        .await
+        .expect_err("A node that never answers times out");
+    assert!(matches!(error, ServiceError::Timeout(_)), "{:?}", error);
+    drop(listener);
+}
+
+#[actix_web::test]
+async fn test_timed_out_simulation_stops() {
+    let node = TestNode::spawn().await;
+
+    // An endless loop, which a block gas limit of 10^12 lets run for minutes.
+    let looping = "0x000000000000000000000000000000000000100f";
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (looping, "0x5b600056"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let estimator = Arc::new(
+        GasEstimator::builder(node.client.clone(), &node.rpc_url)
+            .timeouts(Timeouts { simulation: Duration::from_millis(200), ..Timeouts::default() })
+            .chain_options(ChainOptions::default().with_block_gas_limits("1000000000000").unwrap())
+            .build(),
+    );
+    let app = test::init_service(api_app(estimator.clone())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": looping }],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let response: serde_json::Value = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32050);
+
+    // The abandoned EVM stops instead of looping on its blocking thread.
+    let mut running = estimator.forks.simulation_stats().running;
+    for _ in 0..50 {
+        if running == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        running = estimator.forks.simulation_stats().running;
+    }
+    assert_eq!(running, 0);
+}
+
+#[actix_web::test]
+async fn test_deadline_bounds_fork_state_fetches() {
+    let node = TestNode::spawn().await;
+
+    // Keep the head, so only the fork's state fetches reach the upstream.
+    let estimator = Arc::new(
+        GasEstimator::builder(node.client.clone(), &node.rpc_url)
+            .latest_block_ttl(Duration::from_secs(600))
+            .build(),
+    );
+    let app = test::init_service(
+        api_app(estimator.clone())
+            .wrap_fn(|req, srv| request_timeout(req, srv, Duration::from_secs(30)))
+    ).await;
+    let estimate = |to: &str| {
+        test::TestRequest::post()
+            .uri("/")
+            .insert_header(("X-Deadline-Ms", "300"))
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": to, "value": "0x1" }],
+                "id": 1
+            }))
+            .to_request()
+    };
+
+    // Warm the fork with the sender's account.
+    let resp = test::call_service(&app, estimate("0x70997970C51812dc3A010C7d01b50e0d17dc79C8")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // The fork now fetches from a node that accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    estimator.forks.upstream().set(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+    // A recipient the fork hasn't cached is waited for no longer than the caller's budget...
+    let started = std::time::Instant::now();
+    let resp = test::call_service(&app, estimate("0x00000000000000000000000000000000000051a1")).await;
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let response: serde_json::Value = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32050);
+
+    // ... and the simulation gives its thread back well before the fetch's own 10 s timeout.
+    let mut running = estimator.forks.simulation_stats().running;
+    while running > 0 && started.elapsed() < Duration::from_secs(3) {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        running = estimator.forks.simulation_stats().running;
+    }
+    assert_eq!(running, 0);
+    drop(listener);
+}
+
+#[actix_web::test]
+async fn test_abandoned_simulation_stops() {
+    let node = TestNode::spawn().await;
+
+    // An endless loop, which a block gas limit of 10^12 lets run for minutes.
+    let looping: Address = "0x000000000000000000000000000000000000100f".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (looping, "0x5b600056"))
+        .await
+        .expect("Failed to set the contract code");
+
+    // The simulation timeout alone would keep the EVM busy for a minute.
+    let estimator = Arc::new(
+        GasEstimator::builder(node.client.clone(), &node.rpc_url)
+            .timeouts(Timeouts { simulation: Duration::from_secs(60), ..Timeouts::default() })
+            .chain_options(ChainOptions::default().with_block_gas_limits("1000000000000").unwrap())
+            .build(),
+    );
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(looping);
+    let call = tokio::spawn({
+        let estimator = estimator.clone();
+        async move { estimator.call(&tx_request, BlockTarget::Latest, &BlockPins::default()).await }
+    });
+
+    let mut running = 0;
+    for _ in 0..100 {
+        running = estimator.forks.simulation_stats().running;
+        if running > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(running, 1, "The simulation never started");
+
+    // The caller goes away, as a client disconnecting does; its EVM stops at the next jump.
+    call.abort();
+    assert!(call.await.unwrap_err().is_cancelled());
+    for _ in 0..100 {
+        running = estimator.forks.simulation_stats().running;
+        if running == 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(running, 0);
+    assert_eq!(estimator.forks.simulation_stats().panics, 0);
+}
+
+#[actix_web::test]
+async fn test_simulation_panic_is_caught() {
+    let node = TestNode::spawn().await;
+
+    let precompile: Address = "0x0000000000000000000000000000000000000800".parse().unwrap();
+    let options = ChainOptions::default().with_precompile(
+        None,
+        CustomPrecompile::with_handler(precompile, |_, _| panic!("precompile handler failed")),
+    );
+    let estimator = Arc::new(GasEstimator::builder(node.client.clone(), &node.rpc_url).chain_options(options).build());
+    let app = test::init_service(api_app(estimator.clone())).await;
+
+    // The panic is answered as a simulation error naming it
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_call",
+            "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": precompile }, "latest"],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let response: serde_json::Value = read_json(resp).await;
+    assert_eq!(response["error"]["code"], -32603);
+    let message = response["error"]["message"].as_str().unwrap_or_default();
+    assert!(message.contains("panicked: precompile handler failed"), "{}", response);
+
+    // ... counted by the estimator that ran it, and not by another one ...
+    let req = test::TestRequest::post().uri("/api/v1/health").to_request();
+    let response: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(response["simulation_panics"], 1);
+    assert_eq!(response["simulations"]["panics"], 1);
+    assert_eq!(response["simulations"]["running"], 0);
+    assert_eq!(node.estimator().forks.simulation_stats().panics, 0);
+
+    // ... and the estimator keeps serving simulations
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse::<Address>().unwrap())
+        .input(Bytes::from(vec![0x01]).into());
+    let output = estimator
+        .call(&tx_request, BlockTarget::Latest, &BlockPins::default())
+        .await
+        .expect("Call failed");
+    assert!(output.is_empty());
+}
+
+#[actix_web::test]
+async fn test_head_lookup_doesnt_block_requests() {
+    let node = TestNode::spawn().await;
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let forks = estimator.forks.clone();
+    let fork = forks.latest().await.expect("Failed to fork");
+
+    // The head lookup now goes to a node that accepts connections but never answers.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    forks.upstream().set(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let lookup = tokio::spawn({
+        let forks = forks.clone();
+        async move { forks.latest().await.map(|fork| fork.block_number) }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Requests meanwhile are served the previous fork instead of queueing behind the lookup.
+    let served = tokio::time::timeout(Duration::from_secs(1), forks.latest())
+        .await
+        .expect("Request waited for the head lookup")
+        .expect("Failed to fork");
+    assert_eq!(served.block_hash, fork.block_hash);
+    lookup.abort();
+    drop(listener);
+}
+
+#[actix_web::test]
+async fn test_fork_reused_per_head() {
+    let node = TestNode::spawn().await;
+
+    // Every request looks the head up, yet gets the same fork while the head stays.
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let fork = estimator.forks.latest().await.expect("Failed to fork");
+    let again = estimator.forks.latest().await.expect("Failed to fork");
+    assert!(Arc::ptr_eq(&fork, &again));
+
+    // The block environment is the head's, PREVRANDAO included.
+    let head = node.client.get_latest_block().await.unwrap();
+    assert_eq!(fork.block_hash, head.header.hash);
+    assert_eq!(fork.block_env.prevrandao, head.header.mix_hash);
+
+    // A new head gets a fork of its own.
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_mine".into(), ())
+        .await
+        .expect("Failed to mine a block");
+    let next = estimator.forks.latest().await.expect("Failed to fork");
+    assert_eq!(next.block_number, fork.block_number + 1);
+    assert!(!Arc::ptr_eq(&fork, &next));
+    assert!(Arc::ptr_eq(&next, &estimator.forks.current().await.expect("No fork was created")));
+}
+
+#[actix_web::test]
+async fn test_forks_share_block_state() {
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+    let sender: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let tx_request = TransactionRequest::default()
+        .from(sender)
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(U256::from(1));
+    estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+
+    // The head requested by hash is served from the state the head estimate fetched
+    let head = estimator.forks.current().await.expect("No fork was created");
+    let by_hash = estimator.forks.at_hash(head.block_hash, true).await.expect("Fork at hash failed");
+    assert!(std::ptr::eq(head.state.accounts(), by_hash.state.accounts()));
+    assert!(by_hash.state.accounts().read().contains_key(&sender));
+}
+
+#[actix_web::test]
+async fn test_reorg_purges_cached_estimates() {
+    let node = TestNode::spawn().await;
+    // Look the head up on every request
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(U256::from(1));
+
+    // Mine block 1 and cache an estimate at it
+    let snapshot: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_snapshot".into(), ())
+        .await
+        .expect("Failed to take a snapshot");
+    let _: serde_json::Value = node.client.provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    let orphaned = estimate.block_hash;
+    let cache = estimator.estimate_cache.as_ref().expect("The cache is enabled by default");
+    assert_eq!(cache.stats().size, 1);
+
+    // Replace block 1 with another block at the same height
+    let _: bool = node.client
+        .provider
+        .raw_request("evm_revert".into(), (snapshot,))
+        .await
+        .expect("Failed to revert");
+    let block = node.client.provider.get_block(BlockId::latest()).await.unwrap().expect("No latest block");
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_setNextBlockTimestamp".into(), (block.header.timestamp + 100,))
+        .await
+        .expect("Failed to set the next timestamp");
+    let _: serde_json::Value = node.client.provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+
+    // Only the head's number is polled, so the reorg shows once a block builds on the replacement
+    let _: serde_json::Value = node.client.provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+
+    // The new head gets a fresh fork, and what was cached at the orphaned block is purged
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_ne!(estimate.block_hash, orphaned);
+    assert_eq!(estimator.forks.reorgs(), 1);
+    assert!(estimator.forks.is_orphaned(orphaned));
+    assert_eq!(cache.stats().reorg_purges, 1);
+    assert_eq!(cache.stats().block_hash, estimate.block_hash);
+}
+
+#[actix_web::test]
+async fn test_reorg_across_skipped_heads() {
+    let node = TestNode::spawn().await;
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let mine = || async {
+        let _: serde_json::Value = node.client.provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+    };
+
+    // Fork at block 1, then replace it with a chain the service only sees from block 3 on
+    let snapshot: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_snapshot".into(), ())
+        .await
+        .expect("Failed to take a snapshot");
+    mine().await;
+    let orphaned = estimator.forks.latest().await.expect("Failed to fork the head").block_hash;
+    let _: bool = node.client
+        .provider
+        .raw_request("evm_revert".into(), (snapshot,))
+        .await
+        .expect("Failed to revert");
+    let block = node.client.provider.get_block(BlockId::latest()).await.unwrap().expect("No latest block");
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("evm_setNextBlockTimestamp".into(), (block.header.timestamp + 100,))
+        .await
+        .expect("Failed to set the next timestamp");
+    for _ in 0..3 {
+        mine().await;
+    }
+
+    // The head's parent says nothing about block 1; its canonical hash, fetched along, does
+    let head = estimator.forks.latest().await.expect("Failed to fork the head");
+    assert_eq!(head.block_number, 3);
+    assert_eq!(estimator.forks.reorgs(), 1);
+    assert!(estimator.forks.is_orphaned(orphaned));
+
+    // Skipping blocks on the canonical chain is no reorg
+    mine().await;
+    mine().await;
+    assert_eq!(estimator.forks.latest().await.expect("Failed to fork the head").block_number, 5);
+    assert_eq!(estimator.forks.reorgs(), 1);
+}
+
+#[actix_web::test]
+async fn test_snapshot_preload() {
+    let node = TestNode::spawn().await;
+
+    // A warm replica loads the accounts of a transfer while estimating it
+    let peer = node.estimator();
+    let sender = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let tx_request = TransactionRequest::default()
+        .from(sender)
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(alloy::primitives::U256::from(1));
+    peer.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+
+    let fork = peer.forks.current().await.expect("No fork was created");
+    let exported = snapshot::export(&fork);
+    assert_eq!(exported.chain_id, 31337);
+    assert!(exported.accounts.iter().any(|account| account.address == sender));
+
+    // A new replica preloads it; the head hasn't moved, so the state is imported as is
+    let replica = node.estimator();
+    snapshot::preload(&replica.forks, &exported).await.expect("Preload failed");
+    let fork = replica.forks.current().await.expect("No fork was created");
+    assert_eq!(snapshot::export(&fork), exported);
+}
+
+#[actix_web::test]
+async fn test_snapshot_preload_rejects_mismatched_peer() {
+    let node = TestNode::spawn().await;
+
+    let peer = node.estimator();
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(alloy::primitives::U256::from(1));
+    peer.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    let exported = snapshot::export(&peer.forks.current().await.expect("No fork was created"));
+
+    // A peer of another chain, or on a block this upstream never had, is rejected
+    let mut other_chain = exported.clone();
+    other_chain.chain_id = 1;
+    let mut other_block = exported.clone();
+    other_block.block_hash = alloy::primitives::B256::repeat_byte(0x42);
+    for snapshot in [other_chain, other_block] {
+        let replica = node.estimator();
+        match snapshot::preload(&replica.forks, &snapshot).await {
+            Err(ServiceError::Configuration(_)) => {}
+            other => panic!("Mismatched snapshot should be rejected, got {:?}", other.map(|_| ())),
+        }
+        // Nothing of the peer's state was imported
+        let fork = replica.forks.current().await.expect("No fork was created");
+        assert!(snapshot::export(&fork).accounts.is_empty());
+    }
+
+    // The admin token is never sent to a host that isn't allow-listed
+    let error = snapshot::download("http://estimator-0:8080", Some("secret"), &["estimator-1".to_string()])
+        .await
+        .expect_err("Download should be refused");
+    assert!(error.contains("allow-listed"), "{}", error);
+}
+
+#[actix_web::test]
+async fn test_new_fees_feed() {
+    let node = TestNode::spawn().await;
+
+    // Anvil serves WebSocket subscriptions on its HTTP port
+    let heads = NewHeads::spawn(&node.rpc_url.replace("http://", "ws://"));
+    let feed = FeeFeed::spawn(&heads, node.client.clone(), None, fees::DEFAULT_FEE_STREAM_MAX_CLIENTS);
+    let mut updates = feed.subscribe();
+
+    // Mine until a head arrives, since the upstream subscription starts in the background.
+    let fees = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let _: serde_json::Value = node.client
+                .provider
+                .raw_request("evm_mine".into(), ())
+                .await
+                .expect("Failed to mine a block");
+            if let Ok(Ok(fees)) = tokio::time::timeout(Duration::from_millis(500), updates.recv()).await {
+                return fees;
+            }
+        }
+    })
+    .await
+    .expect("No fee update was pushed");
+
+    let fees = serde_json::to_value(fees.as_ref()).unwrap();
+    assert!(fees["blockNumber"].as_str().is_some());
+    assert!(fees["nextBaseFeePerGas"].as_str().is_some());
+    assert!(fees["maxFeePerGas"].as_str().is_some());
+    // Prague heads carry an excess blob gas, so the blob base fee is included
+    assert!(fees["blobBaseFeePerGas"].as_str().is_some());
+}
+
+#[actix_web::test]
+async fn test_fee_sse_stream() {
+    use actix_web::body::MessageBody;
+    use futures::future::poll_fn;
+
+    let node = TestNode::spawn().await;
+
+    // Anvil serves WebSocket subscriptions on its HTTP port. One stream client at a time.
+    let heads = NewHeads::spawn(&node.rpc_url.replace("http://", "ws://"));
+    let feed = FeeFeed::spawn(&heads, node.client.clone(), None, 1);
+    let mut updates = feed.subscribe();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(node.estimator()))
+            .app_data(web::Data::new(feed))
+            .configure(fees::configure)
+    ).await;
+    let mine = || async {
+        let _: serde_json::Value = node.client
+            .provider
+            .raw_request("evm_mine".into(), ())
+            .await
+            .expect("Failed to mine a block");
+    };
+    // Block number (the id) and fees of an event
+    let parse_event = |event: web::Bytes| {
+        let event = String::from_utf8(event.to_vec()).unwrap();
+        assert!(event.ends_with("\n\n"), "Unterminated event: {}", event);
+        let mut lines = event.lines();
+        let id: u64 = lines.next().unwrap().strip_prefix("id: ").expect("No id line").parse().unwrap();
+        assert_eq!(lines.next().unwrap(), "event: newFees");
+        let data = lines.next().unwrap().strip_prefix("data: ").expect("No data line");
+        (id, serde_json::from_str::<serde_json::Value>(data).unwrap())
+    };
+
+    let req = test::TestRequest::get().uri("/api/v1/fees/stream").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+
+    // Mine until an event arrives, since the upstream subscription starts in the background.
+    let mut body = Box::pin(resp.into_body());
+    let event = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            mine().await;
+            let next = poll_fn(|cx| body.as_mut().poll_next(cx));
+            if let Ok(Some(chunk)) = tokio::time::timeout(Duration::from_millis(500), next).await {
+                return chunk.expect("Failed to read the event stream");
+            }
+        }
+    })
+    .await
+    .expect("No fee event was streamed");
+    let (last_seen, fees) = parse_event(event);
+    assert!(fees["blockNumber"].as_str().is_some());
+    assert!(fees["maxFeePerGas"].as_str().is_some());
+
+    // The stream is full while its only client is connected.
+    let req = test::TestRequest::get().uri("/api/v1/fees/stream").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "5");
+
+    // The client goes away and a head arrives meanwhile.
+    drop(body);
+    tokio::time::timeout(Duration::from_secs(10), async {
+        mine().await;
+        loop {
+            match updates.recv().await {
+                Ok(fees) if fees.block_number.to::<u64>() > last_seen => break,
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                Err(e) => panic!("The fee feed stopped: {}", e),
+            }
+        }
+    })
+    .await
+    .expect("No fee update was pushed");
+
+    // Resuming from the last event seen replays the missed head right away.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/fees/stream")
+        .insert_header(("Last-Event-ID", last_seen.to_string()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = Box::pin(resp.into_body());
+    let event = tokio::time::timeout(Duration::from_secs(1), poll_fn(|cx| body.as_mut().poll_next(cx)))
+        .await
+        .expect("The missed head wasn't replayed")
+        .expect("The event stream ended")
+        .expect("Failed to read the event stream");
+    let (id, _) = parse_event(event);
+    assert!(id > last_seen, "Replayed head {} was already seen", id);
+}
+
+#[actix_web::test]
+async fn test_ws_jsonrpc() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+
+    // The WebSocket upgrade needs a real connection
+    let server = actix_web::HttpServer::new(move || api_app(estimator.clone()))
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .expect("Failed to bind the test server");
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/v1/ws", addr))
+        .await
+        .expect("Failed to connect to the WebSocket endpoint");
+    let send = |payload: serde_json::Value| Message::Text(payload.to_string().into());
+
+    // Requests sent back to back on one connection are each answered under their own id
+    let requests = [
+        json!({"jsonrpc": "2.0", "method": "eth_estimateGas", "params": [transfer_params("0x1")], "id": 1}),
+        json!({"jsonrpc": "2.0", "method": "eth_estimateGas", "params": [{}], "id": 2}),
+        json!({"jsonrpc": "2.0", "method": "eth_chainId", "params": [], "id": "chain"}),
+        json!({"jsonrpc": "2.0", "method": "eth_sendTransaction", "params": [], "id": 4}),
+    ];
+    for request in &requests {
+        ws.send(send(request.clone())).await.unwrap();
+    }
+    let mut replies = Vec::new();
+    while replies.len() < requests.len() {
+        let message = tokio::time::timeout(Duration::from_secs(10), ws.next())
+            .await
+            .expect("No reply on the WebSocket")
+            .expect("WebSocket closed")
+            .expect("Failed to read the WebSocket");
+        let Message::Text(text) = message else { continue };
+        replies.push(serde_json::from_str::<serde_json::Value>(&text).unwrap());
+    }
+    let reply = |id: serde_json::Value| replies.iter().find(|reply| reply["id"] == id).expect("Missing reply");
+    assert_eq!(reply(json!(1))["result"], "0x5208");
+    assert_eq!(reply(json!(2))["error"]["code"], -32602);
+    assert_eq!(reply(json!("chain"))["result"], "0x7a69");
+    assert_eq!(reply(json!(4))["error"]["code"], -32601);
+
+    // A frame that isn't JSON gets a parse error and leaves the connection open
+    ws.send(Message::Text("{not json".into())).await.unwrap();
+    let message = tokio::time::timeout(Duration::from_secs(10), ws.next()).await.unwrap().unwrap().unwrap();
+    let Message::Text(text) = message else { panic!("Unexpected frame {:?}", message) };
+    let error: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(error["error"]["code"], -32700);
+    assert!(error["id"].is_null());
+
+    // Pings are answered with their payload
+    ws.send(Message::Ping(b"alive".to_vec().into())).await.unwrap();
+    let message = tokio::time::timeout(Duration::from_secs(10), ws.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(message, Message::Pong(b"alive".to_vec().into()));
+
+    // The connection keeps serving requests, and is closed once the client closes it
+    ws.send(send(estimate_gas_request(transfer_params("0x2")))).await.unwrap();
+    let message = tokio::time::timeout(Duration::from_secs(10), ws.next()).await.unwrap().unwrap().unwrap();
+    let Message::Text(text) = message else { panic!("Unexpected frame {:?}", message) };
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&text).unwrap()["result"], "0x5208");
+    ws.close(None).await.unwrap();
+    let closed = tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(Ok(message)) = ws.next().await {
+            if message.is_close() {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "The server didn't close the connection");
+}
+
+#[actix_web::test]
+async fn test_versioned_ws_and_stream() {
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+
+    // Streamed lines use the negotiated schema
+    let app = test::init_service(api_app(estimator.clone())).await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/stream")
+        .insert_header(("Accept-Version", "1, 2"))
+        .set_json(json!([estimate_gas_request(transfer_params("0x1"))]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("api-version").unwrap(), "2");
+    let body = test::read_body(resp).await;
+    let line: serde_json::Value = serde_json::from_str(std::str::from_utf8(&body).unwrap().lines().next().unwrap()).unwrap();
+    assert_eq!(line["result"], "0x5208");
+    assert_eq!(line["meta"]["apiVersion"], 2);
+    assert_eq!(line["meta"]["source"], "simulation");
+    assert!(line.get("source").is_none());
+
+    // So does every response on a WebSocket connection, negotiated at the upgrade
+    let server = actix_web::HttpServer::new(move || api_app(estimator.clone()))
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .expect("Failed to bind the test server");
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let mut upgrade = format!("ws://{}/api/v1/ws", addr).into_client_request().unwrap();
+    upgrade.headers_mut().insert("Accept-Version", "2".parse().unwrap());
+    let (mut ws, handshake) = tokio_tungstenite::connect_async(upgrade)
+        .await
+        .expect("Failed to connect to the WebSocket endpoint");
+    assert_eq!(handshake.headers().get("api-version").unwrap(), "2");
+
+    let batch = json!([estimate_gas_request(transfer_params("0x1")), jsonrpc_request("eth_chainId", json!([]), 2)]);
+    for payload in [estimate_gas_request(transfer_params("0x1")), batch] {
+        ws.send(Message::Text(payload.to_string().into())).await.unwrap();
+        let message = tokio::time::timeout(Duration::from_secs(10), ws.next())
+            .await
+            .expect("No reply on the WebSocket")
+            .expect("WebSocket closed")
+            .expect("Failed to read the WebSocket");
+        let Message::Text(text) = message else { panic!("Unexpected frame {:?}", message) };
+        let reply: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let estimate = match &reply {
+            serde_json::Value::Array(replies) => {
+                let chain_id = replies.iter().find(|reply| reply["id"] == 2).unwrap();
+                assert_eq!(chain_id["meta"]["apiVersion"], 2);
+                replies.iter().find(|reply| reply["id"] == 1).unwrap().clone()
+            }
+            reply => reply.clone(),
+        };
+        assert_eq!(estimate["result"], "0x5208");
+        assert_eq!(estimate["meta"]["apiVersion"], 2);
+        assert_eq!(estimate["meta"]["source"], "simulation");
+        assert!(estimate.get("source").is_none());
+    }
+}
+
+#[actix_web::test]
+async fn test_ws_subscriptions() {
+    use futures::{SinkExt, Stream, StreamExt};
+    use tokio_tungstenite::tungstenite::{Error, Message};
+
+    /// Next reply on the connection, skipping subscription notifications
+    async fn next_reply<S: Stream<Item = Result<Message, Error>> + Unpin>(ws: &mut S) -> serde_json::Value {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(10), ws.next())
+                .await
+                .expect("No reply on the WebSocket")
+                .expect("WebSocket closed")
+                .expect("Failed to read the WebSocket");
+            let Message::Text(text) = message else { continue };
+            let reply: serde_json::Value = serde_json::from_str(&text).unwrap();
+            if reply["method"] != "eth_subscription" {
+                return reply;
+            }
+        }
+    }
+
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+    let heads = NewHeads::spawn(&node.rpc_url.replace("http://", "ws://"));
+    let feed = FeeFeed::spawn(&heads, node.client.clone(), None, fees::DEFAULT_FEE_STREAM_MAX_CLIENTS);
+
+    // The WebSocket upgrade needs a real connection
+    let server = actix_web::HttpServer::new(move || {
+        api_app(estimator.clone())
+            .app_data(web::Data::new(feed.clone()))
+    })
+    .workers(1)
+    .bind("127.0.0.1:0")
+    .expect("Failed to bind the test server");
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/v1/ws", addr))
+        .await
+        .expect("Failed to connect to the WebSocket endpoint");
+    let send = |payload: serde_json::Value| Message::Text(payload.to_string().into());
+
+    ws.send(send(json!({"jsonrpc": "2.0", "id": 1, "method": "eth_subscribe", "params": ["newFees"]})))
+        .await
+        .unwrap();
+    let reply = next_reply(&mut ws).await;
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["result"], "0x1");
+
+    // Mine until a notification arrives, since the upstream subscription starts in the background.
+    let notification = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let _: serde_json::Value = node.client
+                .provider
+                .raw_request("evm_mine".into(), ())
+                .await
+                .expect("Failed to mine a block");
+            if let Ok(Some(Ok(Message::Text(text)))) = tokio::time::timeout(Duration::from_millis(500), ws.next()).await {
+                return serde_json::from_str::<serde_json::Value>(&text).unwrap();
+            }
+        }
+    })
+    .await
+    .expect("No fee notification was pushed");
+    assert_eq!(notification["method"], "eth_subscription");
+    assert_eq!(notification["params"]["subscription"], "0x1");
+    assert!(notification["params"]["result"]["maxFeePerGas"].as_str().is_some());
+
+    // A batch in a binary frame: the subscription is answered alongside the forwarded request
+    let batch = json!([
+        {"jsonrpc": "2.0", "id": 2, "method": "eth_subscribe", "params": ["newFees"]},
+        {"jsonrpc": "2.0", "id": 3, "method": "eth_chainId", "params": []},
+    ]);
+    ws.send(Message::Binary(batch.to_string().into_bytes().into())).await.unwrap();
+    let replies = next_reply(&mut ws).await;
+    let replies = replies.as_array().expect("Batch reply is not an array");
+    assert_eq!(replies.len(), 2);
+    let reply = |id: u64| replies.iter().find(|reply| reply["id"] == id).expect("Missing batch reply");
+    assert_eq!(reply(2)["result"], "0x2");
+    assert_eq!(reply(3)["result"], "0x7a69");
+
+    // Unsubscribing stops the subscription once
+    for expected in [true, false] {
+        ws.send(send(json!({"jsonrpc": "2.0", "id": 4, "method": "eth_unsubscribe", "params": ["0x1"]})))
+            .await
+            .unwrap();
+        let reply = next_reply(&mut ws).await;
+        assert_eq!(reply["result"], expected);
+    }
+
+    // One subscription (0x2) is left; the connection may hold 16
+    for id in 5..20 {
+        ws.send(send(json!({"jsonrpc": "2.0", "id": id, "method": "eth_subscribe", "params": ["newFees"]})))
+            .await
+            .unwrap();
+        let reply = next_reply(&mut ws).await;
+        assert!(reply["result"].is_string(), "Subscription {} was refused: {}", id, reply);
+    }
+    ws.send(send(json!({"jsonrpc": "2.0", "id": 20, "method": "eth_subscribe", "params": ["newFees"]})))
+        .await
+        .unwrap();
+    let reply = next_reply(&mut ws).await;
+    assert_eq!(reply["error"]["code"], -32005);
+}
+
+#[actix_web::test]
+async fn test_storage_batching() {
+    let node = TestNode::spawn().await;
+
+    // Reads slots 0, 1, 2 and 0x1000: sload(0) pop sload(1) pop sload(2) pop sload(0x1000) pop stop
+    let contract: alloy::primitives::Address = "0x00000000000000000000000000000000000051a7".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x600054506001545060025450611000545000"))
+        .await
+        .expect("Failed to set the contract code");
+    // Declares two slots the contract never reads
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract)
+        .access_list(alloy::eips::eip2930::AccessList(vec![alloy::eips::eip2930::AccessListItem {
+            address: contract,
+            storage_keys: vec![B256::with_last_byte(0x50), B256::with_last_byte(0x90)],
+        }]));
+    let cached_slots = |fork: Arc<Fork>| {
+        let storage = fork.state.storage().read();
+        let mut slots: Vec<u64> = storage[&contract].keys().map(|slot| slot.to::<u64>()).collect();
+        slots.sort();
+        slots
+    };
+
+    // Without batching, the fork fetches what the transaction reads, one slot at a time.
+    let unbatched = node.estimator();
+    let expected = unbatched.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(cached_slots(unbatched.forks.current().await.unwrap()), vec![0, 1, 2, 0x50, 0x90, 0x1000]);
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .storage_batching(StorageBatching { slots: 8, concurrency: 1 })
+        .build();
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(estimate.gas, expected.gas);
+
+    // The access list was fetched up front. Slot 0 was fetched alone; the miss on slot 1, right
+    // after it, read slots 1 to 8 ahead, serving slot 2. The isolated slot 0x1000 was fetched alone.
+    assert_eq!(cached_slots(estimator.forks.current().await.unwrap()), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 0x50, 0x90, 0x1000]);
+}
+
+#[actix_web::test]
+async fn test_simulation_concurrency_limit() {
+    let node = TestNode::spawn().await;
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .max_concurrent_simulations(1)
+        .build();
+
+    // Simulations beyond the limit queue for the single slot instead of failing
+    let requests: Vec<TransactionRequest> = (1..=4u64)
+        .map(|value| {
+            TransactionRequest::default()
+                .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+                .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+                .value(alloy::primitives::U256::from(value))
+        })
+        .collect();
+    let estimates =
+        futures::future::join_all(requests.iter().map(|tx_request| estimator.estimate_raw_gas(tx_request))).await;
+    for estimate in estimates {
+        assert_eq!(estimate.expect("Estimation failed").gas, alloy::primitives::U256::from(21000));
+    }
+
+    // Every slot was returned once the simulations finished
+    let stats = estimator.forks.simulation_stats();
+    assert_eq!(stats.max_concurrent, Some(1));
+    assert_eq!(stats.running, 0);
+    assert_eq!(stats.queued, 0);
+}
+
+#[actix_web::test]
+async fn test_simulation_queue_depth() {
+    let node = TestNode::spawn().await;
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .max_concurrent_simulations(1)
+        .build();
+
+    // Hold the single slot, so the estimate has to wait for it
+    let head = estimator.forks.latest().await.expect("Failed to fork the head");
+    let permit = head.simulation_limiter.acquire().await;
+    assert_eq!(estimator.forks.simulation_stats().running, 1);
+
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(U256::from(1));
+    let release = async {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while estimator.forks.simulation_stats().queued == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("The estimate never queued for the slot");
+        let stats = estimator.forks.simulation_stats();
+        assert_eq!((stats.running, stats.queued), (1, 1));
+        drop(permit);
+    };
+    let (estimate, ()) = futures::join!(estimator.estimate_raw_gas(&tx_request), release);
+    assert_eq!(estimate.expect("Estimation failed").gas, U256::from(21000));
+
+    // The queued simulation took the slot once it was released, and returned it
+    let stats = estimator.forks.simulation_stats();
+    assert_eq!((stats.running, stats.queued), (0, 0));
+}
+
+#[actix_web::test]
+async fn test_explain_estimate() {
+    let node = TestNode::spawn().await;
+
+    // A counter: sstore(0, add(sload(0), 1))
+    let contract: alloy::primitives::Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x60005460010160005500"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "x_explainEstimate",
+            "params": [
+                { "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract },
+                { "summary": true }
+            ],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    let explanation = &response["result"];
+
+    // Nothing but the base cost is intrinsic
+    assert_eq!(explanation["intrinsic"]["total"], 21000);
+    assert_eq!(explanation["intrinsic"]["calldata"], 0);
+
+    // One cold SLOAD, one SSTORE setting the (now warm) zero slot, and four cheap operations plus STOP
+    let execution = &explanation["execution"];
+    assert_eq!(execution["storageReads"], json!({ "gas": 2100, "count": 1 }));
+    assert_eq!(execution["storageWrites"], json!({ "gas": 20000, "count": 1 }));
+    assert_eq!(execution["compute"], json!({ "gas": 12, "count": 5 }));
+    assert_eq!(execution["total"], 22112);
+
+    // The parts add up to the gas used, and the margin to the estimate
+    assert_eq!(explanation["status"], "success");
+    assert_eq!(explanation["gasUsed"], 43112);
+    assert_eq!(explanation["refund"], 0);
+    let gas = u64::from_str_radix(explanation["gas"].as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+    assert_eq!(gas, 43112 + explanation["margin"].as_u64().unwrap());
+
+    let summary = explanation["summary"].as_str().expect("Missing summary");
+    assert!(summary.contains("Intrinsic: 21000 gas (21000 base)."));
+    assert!(summary.contains("20000 for 1 storage writes"));
+    assert!(explanation.get("profile").is_none());
+
+    // The profile splits the same execution gas per opcode and per contract
+    let req = test::TestRequest::post()
+        .uri("/")
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "x_explainEstimate",
+            "params": [
+                { "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract },
+                { "profile": true }
+            ],
+            "id": 2
+        }))
+        .to_request();
+    let response: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let profile = &response["result"]["profile"];
+    let opcodes = profile["opcodes"].as_array().expect("Missing opcode profile");
+    assert_eq!(opcodes[0], json!({ "opcode": "SSTORE", "gas": 20000, "count": 1 }));
+    assert_eq!(opcodes[1], json!({ "opcode": "SLOAD", "gas": 2100, "count": 1 }));
+    assert!(opcodes.contains(&json!({ "opcode": "PUSH1", "gas": 9, "count": 3 })));
+    let total: u64 = opcodes.iter().map(|opcode| opcode["gas"].as_u64().unwrap()).sum();
+    assert_eq!(total, 22112);
+    let contracts = profile["contracts"].as_array().expect("Missing contract profile");
+    assert_eq!(contracts.len(), 1);
+    assert_eq!(contracts[0]["address"].as_str().unwrap().parse::<alloy::primitives::Address>().unwrap(), contract);
+    assert_eq!(contracts[0]["gas"], 22112);
+    assert_eq!(contracts[0]["operations"], 7);
+}
+
+#[actix_web::test]
+async fn test_upstream_simulation_backend() {
+    let node = TestNode::spawn().await;
+
+    // A counter: sstore(0, add(sload(0), 1))
+    let contract: alloy::primitives::Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x60005460010160005500"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(ChainOptions::default().with_simulation_backends("upstream").unwrap())
+        .build();
+    assert_eq!(estimator.simulation_backend().await.unwrap().name(), "upstream");
+
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract);
+
+    // The estimate is the node's own
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(estimate.source, EstimateSource::Upstream);
+    assert!(estimate.gas >= alloy::primitives::U256::from(43112));
+
+    // eth_call is answered by the node
+    let output = estimator
+        .call(&tx_request, BlockTarget::Latest, &BlockPins::default())
+        .await
+        .expect("Call failed");
+    assert!(output.is_empty());
+
+    // Pinned header values can't be passed through
+    let pins = BlockPins { coinbase: Some(contract), ..Default::default() };
+    assert!(estimator.call(&tx_request, BlockTarget::Latest, &pins).await.is_err());
+
+    // The node's callTracer output is passed through as is
+    let options: TraceOptions = serde_json::from_value(json!({ "tracer": "callTracer" })).unwrap();
+    let trace = estimator.trace_call(&tx_request, &options, &EstimateOptions::default()).await.expect("Trace failed");
+    let trace = serde_json::to_value(&trace).unwrap();
+    assert_eq!(trace["type"], "CALL");
+    assert_eq!(trace["to"], "0x0000000000000000000000000000000000c0ffee");
+}
+
+#[actix_web::test]
+async fn test_withdrawal_batching_reads_the_fork() {
+    let node = TestNode::spawn().await;
+    let from: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap();
+    let disperse = Address::repeat_byte(0xd1);
+    let withdrawals = [(Address::repeat_byte(0x01), U256::from(1)), (Address::repeat_byte(0x02), U256::from(1))];
+
+    // Without a contract, only the per-transaction strategy is simulated
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .latest_block_ttl(Duration::ZERO)
+        .build();
+    let report = batching::compare_withdrawal_strategies(&estimator, from, None, disperse, &withdrawals)
+        .await
+        .expect("Comparison failed");
+    assert!(report.batched.is_none());
+    assert_eq!(report.per_transaction.total_gas, U256::from(42000));
+    assert_eq!(report.recommended, "perTransaction");
+
+    // A contract deployed in a later block is seen at that block's fork (STOP accepts the value)
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (disperse, "0x00"))
+        .await
+        .expect("Failed to set the contract code");
+    let _: serde_json::Value = node.client.provider.raw_request("evm_mine".into(), ()).await.expect("Failed to mine a block");
+    let report = batching::compare_withdrawal_strategies(&estimator, from, None, disperse, &withdrawals)
+        .await
+        .expect("Comparison failed");
+    let batched = report.batched.expect("The disperse contract is deployed");
+    assert_eq!(batched.status, "success");
+    assert!(batched.total_gas < report.per_transaction.total_gas);
+    assert_eq!(report.recommended, "batched");
+    assert_eq!(report.block_number, estimator.forks.current().await.unwrap().block_number);
+}
+
+#[actix_web::test]
+async fn test_estimate_cache_serves_simulations_only() {
+    let node = TestNode::spawn().await;
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap())
+        .value(U256::from(1));
+
+    // A repeated simulation at the same head is answered from the cache
+    let estimator = node.estimator();
+    let first = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    let second = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(first.gas, second.gas);
+    assert_eq!(second.source, EstimateSource::Simulation);
+    let stats = estimator.estimate_cache.as_ref().expect("The cache is enabled by default").stats();
+    assert_eq!((stats.size, stats.hits, stats.misses), (1, 1, 1));
+
+    // The upstream node's answers are never cached
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(ChainOptions::default().with_simulation_backends("upstream").unwrap())
+        .build();
+    for _ in 0..2 {
+        let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+        assert_eq!(estimate.source, EstimateSource::Upstream);
+    }
+    let stats = estimator.estimate_cache.as_ref().expect("The cache is enabled by default").stats();
+    assert_eq!((stats.size, stats.hits, stats.misses), (0, 0, 0));
+}
+
+#[actix_web::test]
+async fn test_upstream_backend_answers_as_is() {
+    let node = TestNode::spawn().await;
+
+    // A counter: sstore(0, add(sload(0), 1))
+    let contract: Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x60005460010160005500"))
+        .await
+        .expect("Failed to set the contract code");
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract);
+    let node_estimate = node.client.provider.estimate_gas(tx_request.clone()).await.unwrap();
+
+    // The node is asked the request as sent, and no headroom is added to its answer
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(ChainOptions::default().with_simulation_backends("upstream").unwrap())
+        .headroom(Headroom::new(1.5, 10_000).unwrap())
+        .build();
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(estimate.source, EstimateSource::Upstream);
+    assert_eq!(estimate.gas, U256::from(node_estimate));
+
+    // Reports re-running the transaction locally are rejected instead of contradicting the node
+    let unsupported = |e: eyre::Report| matches!(ServiceError::from_report(e), ServiceError::UnsupportedChain(_));
+    let extended = estimator.estimate_extended(&tx_request, &EstimateOptions::default(), None).await;
+    assert!(extended.is_err_and(unsupported));
+    let explanation = estimator
+        .explain_estimate(&tx_request, &ExplainOptions::default(), &EstimateOptions::default())
+        .await;
+    assert!(explanation.is_err_and(unsupported));
+    let access_list = estimator.create_access_list(&tx_request, &EstimateOptions::default()).await;
+    assert!(access_list.is_err_and(unsupported));
+}
+
+#[actix_web::test]
+async fn test_out_of_gas_frame() {
+    let node = TestNode::spawn().await;
+
+    // Loops until it runs out of gas: JUMPDEST PUSH1 0 JUMP
+    let contract = "0x000000000000000000000000000000000000100f";
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x5b600056"))
+        .await
+        .expect("Failed to set the contract code");
+    let app = test::init_service(
+        api_app(node.estimator())
+            .configure(quotes::configure)
+    ).await;
+    let tx = json!({ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract, "data": "0x12345678" });
+
+    // Estimates and calls name the frame that ran out of gas, here the transaction's own.
+    for method in ["eth_estimateGas", "eth_call"] {
+        let req = test::TestRequest::post()
+            .uri("/")
+            .set_json(jsonrpc_request(method, json!([tx]), 1))
+            .to_request();
+        let response: serde_json::Value = read_json(test::call_service(&app, req).await).await;
+        let frame = &response["error"]["data"]["outOfGasFrame"];
+        assert_eq!(frame["address"].as_str().unwrap().to_lowercase(), contract, "{}: {}", method, response);
+        assert_eq!(frame["selector"], "0x12345678");
+        assert_eq!(frame["depth"], 0);
+    }
+
+    // REST errors carry it as an object.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/quote")
+        .set_json(json!({ "transaction": tx }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let error: serde_json::Value = read_json(resp).await;
+    assert_eq!(error["error_code"], "OUT_OF_GAS");
+    assert_eq!(error["details"]["depth"], 0);
+    assert_eq!(error["details"]["selector"], "0x12345678");
+}
+
+#[actix_web::test]
+async fn test_jsonrpc_error_codes() {
+    let node = TestNode::spawn().await;
+
+    // Reverts with 0xdeadbeef: mstore(0, 0xdeadbeef) revert(28, 4)
+    let contract: alloy::primitives::Address = "0x0000000000000000000000000000000000bad000".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x63deadbeef6000526004601cfd"))
+        .await
+        .expect("Failed to set the contract code");
+
+    let app = test::init_service(
+        api_app(node.estimator())
+            .configure(bulk::configure)
+    ).await;
+
+    // An unparseable body is a parse error, with a null id
+    let malformed = |uri: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"jsonrpc": "2.0", "method": "#)
+            .to_request()
+    };
+    let resp = test::call_service(&app, malformed("/")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["error"]["code"], -32700);
+    assert!(response["id"].is_null());
+
+    // ... but REST endpoints answer in their own error shape
+    let resp = test::call_service(&app, malformed("/api/v1/eth/estimateGas/bulk")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let response = read_json(resp).await;
+    assert_eq!(response["error_code"], "INVALID_PARAMS");
+    assert!(response.get("jsonrpc").is_none());
+
+    let call = |method: &str| {
+        test::TestRequest::post()
+            .uri("/")
+            .set_json(json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": [{ "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266", "to": contract }],
+                "id": 7
+            }))
+            .to_request()
+    };
+
+    // Unknown methods
+    let resp = test::call_service(&app, call("eth_sendTransaction")).await;
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["error"]["code"], -32601);
+
+    // Reverting calls and estimates carry the revert data under geth's code 3
+    for method in ["eth_call", "eth_estimateGas"] {
+        let resp = test::call_service(&app, call(method)).await;
+        let status = resp.status();
+        let response = read_json(resp).await;
+        assert_eq!(response["error"]["code"], 3, "{}: {}", method, response);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["message"], "execution reverted");
+        assert_eq!(response["error"]["data"], "0xdeadbeef");
+    }
+}
+
+#[actix_web::test]
+async fn test_existing_delegation() {
+    let node = TestNode::spawn().await;
+
+    // A counter: sstore(0, add(sload(0), 1))
+    let counter: alloy::primitives::Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let set_code = |address: alloy::primitives::Address, code: String| {
+        let client = node.client.clone();
+        async move {
+            let _: serde_json::Value = client
+                .provider
+                .raw_request("anvil_setCode".into(), (address, code))
+                .await
+                .expect("Failed to set the code");
+        }
+    };
+    set_code(counter, "0x60005460010160005500".to_string()).await;
+
+    // Two dev accounts already delegated to the counter on chain
+    let designator = format!("0xef0100{}", alloy::hex::encode(counter));
+    let authority: alloy::primitives::Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+    let sender: alloy::primitives::Address = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC".parse().unwrap();
+    set_code(authority, designator.clone()).await;
+    set_code(sender, designator).await;
+
+    let estimator = node.estimator();
+
+    // Calling the authority runs the counter in the authority's own storage, as on the node
+    let call = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(authority);
+    let node_estimate = node.client.provider.estimate_gas(call.clone()).await.expect("Node estimation failed");
+    let estimate = estimator.estimate_raw_gas(&call).await.expect("Estimation failed");
+    assert_eq!(estimate.source, EstimateSource::Simulation);
+    // Base gas, the delegate's cold access and a fresh counter slot written
+    assert!(estimate.gas >= alloy::primitives::U256::from(21000 + 2600 + 22100), "{}", estimate.gas);
+    assert!(estimate.gas >= alloy::primitives::U256::from(node_estimate), "{} < {}", estimate.gas, node_estimate);
+    let fork = estimator.forks.current().await.expect("No fork was created");
+    assert!(fork.state.storage().read().get(&authority).is_some_and(|slots| slots.contains_key(&alloy::primitives::U256::ZERO)));
+    assert!(fork.state.storage().read().get(&counter).is_none());
+
+    // A delegated sender is still an EOA: a transfer costs the base gas
+    let transfer = TransactionRequest::default()
+        .from(sender)
+        .to("0x90F79bf6EB2c4f870365E785982E1f101E93b906".parse().unwrap())
+        .value(alloy::primitives::U256::from(1));
+    let estimate = estimator.estimate_raw_gas(&transfer).await.expect("Estimation failed");
+    assert_eq!(estimate.gas, alloy::primitives::U256::from(21000));
+}
+
+#[actix_web::test]
+async fn test_correlation_ids() {
+    let node = TestNode::spawn().await;
+
+    let estimator = node.estimator();
+    let app = test::init_service(
+        api_app(estimator.clone())
+            .wrap_fn(|req, srv| correlate(req, srv))
+            .configure(ladder::configure)
+    ).await;
+
+    // A caller's id is echoed in the response header and quoted in JSON-RPC errors.
+    let req = test::TestRequest::post()
+        .uri("/")
+        .insert_header(("X-Request-Id", "client-trace-42"))
+        .set_json(json!({
+            "jsonrpc": "2.0",
+            "method": "eth_estimateGas",
+            "params": [{ "to": "not an address" }],
+            "id": 1
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("x-request-id").unwrap(), "client-trace-42");
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["error"]["data"]["correlationId"], "client-trace-42");
+    let request_id = response["error"]["data"]["requestId"].as_str().expect("Missing requestId");
+
+    // The audit log keeps the id with the request.
+    let entry = estimator.audit_log.get(request_id).expect("The request wasn't logged");
+    assert_eq!(entry.correlation_id.as_deref(), Some("client-trace-42"));
+
+    // Without a usable header, an id is generated and quoted in REST error bodies.
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas/ladder")
+        .insert_header(("X-Request-Id", "has spaces"))
+        .insert_header(("content-type", "application/json"))
+        .set_payload("{not json")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let header = resp.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    assert_ne!(header, "has spaces");
+    let response: serde_json::Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+    assert_eq!(response["correlation_id"], header);
+
+    // Successful responses carry the id too.
+    let req = test::TestRequest::get().uri("/livez").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().contains_key("x-request-id"));
+}
+
+#[actix_web::test]
+async fn test_simulation_block_gas_limit() {
+    let node = TestNode::spawn().await;
+
+    let block_gas_limit = node.client.get_latest_block().await.unwrap().header.gas_limit;
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to("0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap());
+
+    // By default simulations run under the forked block's own gas limit
+    let estimator = node.estimator();
+    estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    let fork = estimator.forks.current().await.expect("No fork was created");
+    assert_eq!(fork.block_env.gas_limit, revm::primitives::U256::from(block_gas_limit));
+
+    // A configured limit replaces it; a transfer can't fit in a block of 20,000 gas
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .chain_options(ChainOptions::default().with_block_gas_limits("20_000").unwrap())
+        .build();
+    assert!(estimator.estimate_raw_gas(&tx_request).await.is_err());
+    let fork = estimator.forks.current().await.expect("No fork was created");
+    assert_eq!(fork.block_env.gas_limit, revm::primitives::U256::from(20_000));
+
+    // Limits must be positive numbers
+    assert!(ChainOptions::default().with_block_gas_limits("0").is_err());
+    assert!(ChainOptions::default().with_block_gas_limits("1=lots").is_err());
+}
+
+#[actix_web::test]
+async fn test_custom_precompiles() {
+    let node = TestNode::spawn().await;
+
+    let chain_id = node.client.provider.get_chain_id().await.unwrap();
+    let precompile: Address = "0x0000000000000000000000000000000000000800".parse().unwrap();
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(precompile);
+
+    // Without registration the address is an empty account
+    let estimator = node.estimator();
+    let extended = estimator
+        .estimate_extended(&tx_request, &EstimateOptions::default(), None)
+        .await
+        .expect("Estimation failed");
+    assert_eq!(extended.gas_used, U256::from(21000));
+    assert!(extended.return_data.is_empty());
+
+    // Registered for this chain, every call costs 3000 gas and returns 0x01
+    let options = ChainOptions::default()
+        .with_custom_precompiles(&format!("{}:{}=3000/0x01", chain_id, precompile))
+        .unwrap();
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url).chain_options(options).build();
+    let extended = estimator
+        .estimate_extended(&tx_request, &EstimateOptions::default(), None)
+        .await
+        .expect("Estimation failed");
+    assert_eq!(extended.status, "success");
+    assert_eq!(extended.gas_used, U256::from(24000));
+    assert_eq!(extended.gas, U256::from(24000));
+    assert_eq!(extended.return_data, Bytes::from(vec![1u8]));
+
+    // The precompile charges its gas in full: one gas less runs out of gas
+    assert!(estimator.estimate_raw_gas(&tx_request.clone().gas_limit(23999)).await.is_err());
+
+    // Handlers registered from code see the input
+    let options = ChainOptions::default().with_precompile(
+        None,
+        CustomPrecompile::with_handler(precompile, |input, _| {
+            Ok(revm::primitives::PrecompileOutput::new(100, input.clone()))
+        }),
+    );
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url).chain_options(options).build();
+    let echoed = estimator
+        .estimate_extended(
+            &tx_request.clone().input(Bytes::from(vec![0xab]).into()),
+            &EstimateOptions::default(),
+            None,
+        )
+        .await
+        .expect("Estimation failed");
+    assert_eq!(echoed.return_data, Bytes::from(vec![0xab]));
+
+    // Precompiles registered for other chains don't apply
+    let options = ChainOptions::default()
+        .with_custom_precompiles(&format!("{}:{}=3000", chain_id + 1, precompile))
+        .unwrap();
+    assert!(options.precompiles(chain_id).is_empty());
+
+    // Malformed entries are rejected
+    assert!(ChainOptions::default().with_custom_precompiles("0x0800=3000").is_err());
+    assert!(ChainOptions::default().with_custom_precompiles(&format!("{}=lots", precompile)).is_err());
+    assert!(ChainOptions::default().with_custom_precompiles(&format!("{}=1/0xzz", precompile)).is_err());
+}
+
+#[actix_web::test]
+async fn test_gas_dependent_estimate() {
+    let node = TestNode::spawn().await;
+
+    // Reverts unless gasleft() is at least 20000: gas push2(20000) gt jumpi(revert) stop revert
+    let contract: Address = "0x0000000000000000000000000000000000006a5d".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setCode".into(), (contract, "0x5a614e2011600957005b600080fd"))
+        .await
+        .expect("Failed to set the contract code");
+    let params = json!({
+        "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+        "to": contract
+    });
+
+    // Without a gas limit the request runs under the block gas limit, not 21000, and the
+    // limit is raised until the 2 gas of GAS leave 20000 after the intrinsic 21000
+    let estimator = node.estimator();
+    let app = test::init_service(api_app(estimator)).await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(estimate_gas_request(params))
+        .to_request();
+    let response = read_json(test::call_service(&app, req).await).await;
+    assert_eq!(assert_quantity_result(&response), U256::from(41002));
+
+    // With detection off, the gas used by the unconstrained run is the estimate
+    let chain_id = node.client.provider.get_chain_id().await.unwrap();
+    let options = ChainOptions::default()
+        .with_gas_dependence_detection(&format!("on,{}=off", chain_id))
+        .unwrap();
+    assert!(!options.gas_dependence_detection(chain_id));
+    assert!(options.gas_dependence_detection(chain_id + 1));
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url).chain_options(options).build();
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract);
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(estimate.gas, U256::from(21021));
+
+    assert!(ChainOptions::default().with_gas_dependence_detection("sometimes").is_err());
+}
+
+#[actix_web::test]
+async fn test_nested_call_gas_limits() {
+    let node = TestNode::spawn().await;
+    let set_code = |address: Address, code: String| {
+        let client = node.client.clone();
+        async move {
+            let _: serde_json::Value = client
+                .provider
+                .raw_request("anvil_setCode".into(), (address, code))
+                .await
+                .expect("Failed to set the contract code");
+        }
+    };
+
+    // Writes a fresh slot, 22106 gas: sstore(0, 1) stop
+    let inner: Address = "0x0000000000000000000000000000000000001111".parse().unwrap();
+    set_code(inner, "0x600160005500".to_string()).await;
+    // Forwards all its gas to the inner contract and ignores the outcome: pop(call(gas(), inner, 0, 0, 0, 0, 0))
+    let forwarding: Address = "0x0000000000000000000000000000000000002222".parse().unwrap();
+    set_code(forwarding, format!("0x6000600060006000600073{:x}5af15000", inner)).await;
+    // Gives the inner contract a fixed 5000 gas, never enough: pop(call(5000, inner, 0, 0, 0, 0, 0))
+    let starving: Address = "0x0000000000000000000000000000000000003333".parse().unwrap();
+    set_code(starving, format!("0x6000600060006000600073{:x}611388f15000", inner)).await;
+    // Deploys a child whose constructor writes a fresh slot: pop(create(0, 26, 6))
+    let factory: Address = "0x0000000000000000000000000000000000004444".parse().unwrap();
+    set_code(factory, "0x656001600055006000526006601a6000f05000".to_string()).await;
+
+    let estimator = node.estimator();
+    let fork = estimator.forks.latest().await.expect("Failed to fork");
+    let request = |to: Address| {
+        TransactionRequest::default()
+            .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+            .to(to)
+    };
+
+    // Under its gas used, the subcall only gets 63/64 of what's left and runs out of gas; the minimum
+    // limit leaves 22456 gas at the call (after the 21000 intrinsic, 20 of pushes and 2600 of cold access)
+    let recommendation = recommend_gas_limit(&fork, &request(forwarding)).await.expect("Recommendation failed");
+    assert_eq!(recommendation.confidence, Confidence::High);
+    assert_eq!(recommendation.gas_limit, 46076);
+    assert!(recommendation.gas_used < recommendation.gas_limit);
+    assert!(recommendation.iterations <= eth_gas_estimator::gas_dependence::MAX_RESIMULATIONS);
+    let frame = recommendation.limiting_frame.expect("No limiting frame");
+    assert_eq!((frame.address, frame.depth, frame.out_of_gas), (inner, 1, true));
+
+    // A subcall running out of gas under any limit is caught: that's the outcome to reproduce
+    let recommendation = recommend_gas_limit(&fork, &request(starving)).await.expect("Recommendation failed");
+    assert_eq!(recommendation.confidence, Confidence::Medium);
+    assert_eq!(recommendation.gas_limit, recommendation.gas_used);
+
+    // Contract creations are frames too
+    let recommendation = recommend_gas_limit(&fork, &request(factory)).await.expect("Recommendation failed");
+    assert!(recommendation.gas_used < recommendation.gas_limit);
+    let frame = recommendation.limiting_frame.expect("No limiting frame");
+    assert_eq!((frame.selector, frame.depth, frame.out_of_gas), (None, 1, true));
+}
+
+#[actix_web::test]
+async fn test_access_list_saving_is_unpadded() {
+    let node = TestNode::spawn().await;
+
+    // Reads the balance of a cold account: PUSH20 0xdead BALANCE STOP
+    let contract: Address = "0x0000000000000000000000000000000000c0ffee".parse().unwrap();
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request(
+            "anvil_setCode".into(),
+            (contract, "0x73000000000000000000000000000000000000dead3100"),
+        )
+        .await
+        .expect("Failed to set the contract code");
+    let tx_request = TransactionRequest::default()
+        .from("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse().unwrap())
+        .to(contract);
+
+    // Headroom pads estimates but not the gas used the saving is computed from
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .headroom(Headroom::new(1.5, 10_000).unwrap())
+        .build();
+    let report = estimator
+        .create_access_list(&tx_request, &EstimateOptions::default())
+        .await
+        .expect("Access list generation failed");
+    assert_eq!(
+        report.access_list,
+        AccessList(vec![AccessListItem {
+            address: address!("000000000000000000000000000000000000dead"),
+            storage_keys: vec![],
+        }])
+    );
+    // 21000 + PUSH20 (3) + cold BALANCE (2600)
+    assert_eq!(report.gas_used_without_access_list, U256::from(23_603));
+    // 21000 + listed address (2400) + PUSH20 (3) + warm BALANCE (100)
+    assert_eq!(report.gas_used, U256::from(23_503));
+    assert_eq!(report.gas_saved, 100);
+    assert_eq!(report.source, EstimateSource::Simulation);
+}
+
+#[actix_web::test]
+async fn test_headroom_pads_only_the_returned_estimate() {
+    let node = TestNode::spawn().await;
+
+    // The sender can pay for the transfer it needs, but not for twice that
+    let sender = address!("000000000000000000000000000000000000bEEF");
+    let max_fee = U256::from(100_000_000_000u64);
+    let _: serde_json::Value = node.client
+        .provider
+        .raw_request("anvil_setBalance".into(), (sender, U256::from(21_000) * max_fee))
+        .await
+        .expect("Failed to fund the sender");
+    let tx_request = TransactionRequest::default()
+        .from(sender)
+        .to(address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"))
+        .max_fee_per_gas(max_fee.to())
+        .max_priority_fee_per_gas(1_000_000_000);
+
+    let estimator = GasEstimator::builder(node.client.clone(), &node.rpc_url)
+        .headroom(Headroom::new(2.0, 0).unwrap())
+        .build();
+
+    // The balance is checked against the gas the transfer needs, not the padded limit
+    let estimate = estimator.estimate_raw_gas(&tx_request).await.expect("Estimation failed");
+    assert_eq!(estimate.gas, U256::from(42_000));
+    assert_eq!(estimate.headroom, U256::from(21_000));
+    assert_eq!(estimate.unpadded(), U256::from(21_000));
+
+    // The trace re-runs the transfer under the limit it needs, leaving its call no gas
+    let detailed = estimator
+        .estimate_detailed(&tx_request, &EstimateOptions::default())
+        .await
+        .expect("Detailed estimation failed");
+    assert_eq!(detailed.gas, U256::from(42_000));
+    assert_eq!(detailed.call_frames.expect("Missing call frames").gas, U256::ZERO);
+
+    // The explanation reports the padded estimate, with the headroom in its margin
+    let explanation = estimator
+        .explain_estimate(&tx_request, &ExplainOptions::default(), &EstimateOptions::default())
+        .await
+        .expect("Explanation failed");
+    assert_eq!(explanation.gas, U256::from(42_000));
+    assert_eq!(explanation.gas_used, 21_000);
+    assert_eq!(explanation.margin, 21_000);
+}
@@ -5,41 +5,19 @@
 //! using an EIP-1559 style transaction.
 
 use crate::init_logger;
-use tracing_actix_web::TracingLogger;
-use actix_web::{test, web, App, http::StatusCode};
+use actix_web::{test, http::StatusCode};
 use alloy::primitives::{U256};
 use serde_json::json;
-use std::sync::Arc;
 
-use eth_gas_estimator::{
-    api,
-    estimator::GasEstimator,
-    rpc::EthereumClient,
-};
-
-#[path = "../api_tests/helpers.rs"]
-mod helpers; // Assumes your helpers are in tests/helpers.rs
-use helpers::spawn_anvil;
+use eth_gas_estimator::test_utils::{api_app, TestNode};
 
 #[actix_web::test]
 async fn test_eip1559_transaction_estimation_rpc() {
     init_logger();
-    // Spawn an Anvil instance and get its RPC URL.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
-
-    // Create an Ethereum client using the RPC URL (unwrap the result)
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-
-    // Build a GasEstimator from the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
+    let node = TestNode::spawn().await;
 
     // Initialize the Actix application with the API endpoints.
-    let app = test::init_service(
-        App::new()
-            .wrap(TracingLogger::default())
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
 
     // Build a JSON-RPC request for eth_estimateGas with EIP-1559 parameters.
     // Using two of Anvil's pre-funded accounts:
@@ -87,7 +65,4 @@ async fn test_eip1559_transaction_estimation_rpc() {
     let gas_estimate = U256::from_str_radix(&gas_estimate_str.trim_start_matches("0x"), 16)
         .expect("Failed to parse gas estimate");
     assert_eq!(gas_estimate, U256::from(21000));
-
-    // Clean up: kill the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
 }
\ No newline at end of file
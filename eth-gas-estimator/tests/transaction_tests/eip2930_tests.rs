@@ -4,43 +4,21 @@
 //! EIP-2930 with explicitly specified account and storage access lists.
 
 use crate::init_logger;
-use actix_web::{test, web, App, http::StatusCode};
+use actix_web::{test, http::StatusCode};
 use alloy::primitives::{U256};
 use serde_json::json;
-use std::sync::Arc;
-use tracing_actix_web::TracingLogger;
 
-use eth_gas_estimator::{
-    api,
-    estimator::GasEstimator,
-    rpc::EthereumClient,
-};
-
-#[path = "../api_tests/helpers.rs"]
-mod helpers; // Assumes your helpers are in tests/helpers.rs
-use helpers::spawn_anvil;
+use eth_gas_estimator::test_utils::{api_app, TestNode};
 
 #[actix_web::test]
 async fn test_eip2930_access_list_transaction_rpc() {
 
     init_logger();
 
-    // Spawn an Anvil instance and obtain its RPC URL.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
-
-    // Create an Ethereum client using the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-
-    // Build a GasEstimator from the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
+    let node = TestNode::spawn().await;
 
     // Initialize the Actix application with your API endpoints and the tracing logger.
-    let app = test::init_service(
-        App::new()
-            .wrap(TracingLogger::default())
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
 
     // Build a JSON-RPC request for eth_estimateGas with EIP-2930 access list parameters.
     // In this example, we use placeholder addresses; you may replace these with real addresses.
@@ -95,7 +73,4 @@ async fn test_eip2930_access_list_transaction_rpc() {
     ).expect("Failed to parse gas estimate");
 
     assert_eq!(gas_estimate, U256::from(27200));
-
-    // Clean up: kill the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
 }
\ No newline at end of file
@@ -4,43 +4,26 @@
 //! EIP-4844 blob transactions with versioned hashes.
 
 use crate::init_logger;
-use actix_web::{test, web, App, http::StatusCode};
+use actix_web::{test, http::StatusCode};
 use alloy::primitives::{U256};
 use serde_json::json;
-use std::sync::Arc;
-use tracing_actix_web::TracingLogger;
 
-use eth_gas_estimator::{
-    api,
-    estimator::GasEstimator,
-    rpc::EthereumClient,
+use eth_gas_estimator::test_utils::{
+    api_app,
+    fixtures::{FixtureChain, BLOB_HASH_STORE_ADDRESS},
+    TestNode,
 };
-
-#[path = "../api_tests/helpers.rs"]
-mod helpers; // Assumes your helpers (including spawn_anvil) are in tests/helpers.rs
-use helpers::spawn_anvil;
+use revm::{primitives::ExecutionResult, DatabaseRef};
 
 #[actix_web::test]
 async fn test_eip4844_blob_transaction_rpc() {
 
     init_logger();
 
-    // Spawn an Anvil instance and obtain its RPC URL.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
-
-    // Create an Ethereum client using the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-
-    // Build a GasEstimator using the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
+    let node = TestNode::spawn().await;
 
     // Initialize the Actix application with your API endpoints and the tracing logger.
-    let app = test::init_service(
-        App::new()
-            .wrap(TracingLogger::default())
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
 
     // Build a JSON-RPC request for eth_estimateGas with EIP-4844 parameters.
     // We use placeholder addresses for sender and receiver.
@@ -90,7 +73,31 @@ async fn test_eip4844_blob_transaction_rpc() {
 
     // Assert that the estimated gas exactly matches the expected value.
     assert_eq!(gas_estimate, U256::from(21000));
+}
+
+#[test]
+fn test_eip4844_blob_transaction_fixture() {
+
+    init_logger();
 
-    // Clean up: kill the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
+    // The fixture chain needs no Anvil node, and its blobs carry valid KZG commitments.
+    let fixtures = FixtureChain::new();
+    let mut db = fixtures.db();
+    let hashes = fixtures.blob_versioned_hashes();
+    assert_eq!(hashes.len(), fixtures.blob_sidecar().blobs.len());
+    assert!(hashes.iter().all(|hash| hash[0] == 0x01), "Versioned hashes must be KZG hashes");
+
+    // The blob hash store keeps the first blob hash it was sent.
+    let result = fixtures
+        .transact(&mut db, &fixtures.blob_request())
+        .expect("Blob transaction failed validation");
+    assert!(matches!(result, ExecutionResult::Success { .. }), "Unexpected result: {:?}", result);
+
+    let stored = db.storage_ref(BLOB_HASH_STORE_ADDRESS, U256::ZERO).unwrap();
+    assert_eq!(stored, U256::from_be_bytes(hashes[0].0));
+
+    // The same fixtures always run the same way.
+    let mut replay = fixtures.db();
+    let replayed = fixtures.transact(&mut replay, &fixtures.blob_request()).unwrap();
+    assert_eq!(replayed.gas_used(), result.gas_used());
 }
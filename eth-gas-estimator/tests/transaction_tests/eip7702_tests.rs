@@ -4,21 +4,24 @@
 //! EIP-7702 with signed authorization lists.
 
 use crate::init_logger;
-use actix_web::{test, web, App, http::StatusCode};
-use alloy::primitives::{U256};
+use actix_web::{test, http::StatusCode};
+use alloy::{
+    eips::eip7702::SignedAuthorization,
+    primitives::{address, U256},
+    rpc::types::TransactionRequest,
+};
 use serde_json::json;
-use std::sync::Arc;
-use tracing_actix_web::TracingLogger;
 
 use eth_gas_estimator::{
-    api,
-    estimator::GasEstimator,
-    rpc::EthereumClient,
+    error::ServiceError,
+    estimator::EstimateOptions,
+    test_utils::{
+        api_app,
+        fixtures::{FixtureChain, COUNTER_ADDRESS},
+        TestNode,
+    },
 };
-
-#[path = "../api_tests/helpers.rs"]
-mod helpers; // Assumes your helpers (including spawn_anvil) are in tests/helpers.rs
-use helpers::spawn_anvil;
+use revm::{primitives::ExecutionResult, DatabaseRef};
 
 #[actix_web::test]
 async fn test_eip7702_authorization_transaction_rpc() {
@@ -26,22 +29,10 @@ async fn test_eip7702_authorization_transaction_rpc() {
     // Initialize the logger.
     init_logger();
 
-    // Spawn an Anvil instance and obtain its RPC URL.
-    let (mut anvil_process, rpc_url) = spawn_anvil();
-
-    // Create an Ethereum client using the RPC URL and wrap it in an Arc.
-    let client = Arc::new(EthereumClient::new(&rpc_url).await.unwrap());
-
-    // Build a GasEstimator from the client and RPC URL.
-    let estimator = GasEstimator::new(client, &rpc_url);
+    let node = TestNode::spawn().await;
 
     // Initialize the Actix application with your API endpoints and the tracing logger.
-    let app = test::init_service(
-        App::new()
-            .wrap(TracingLogger::default())
-            .app_data(web::Data::new(Arc::new(estimator)))
-            .configure(api::configure)
-    ).await;
+    let app = test::init_service(api_app(node.estimator())).await;
 
     // Build a JSON-RPC request for eth_estimateGas that includes an authorization list.
     let request = json!({
@@ -63,7 +54,8 @@ async fn test_eip7702_authorization_transaction_rpc() {
                 "r": "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
                 "s": "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
             }],
-            "transactionType": "0x4"               // Type 4 transaction with an auth list (EIP-7702)
+            "transactionType": "0x4",              // Type 4 transaction with an auth list (EIP-7702)
+            "skipAuthorizationCheck": true         // Placeholder signature of a draft estimation
         }],
         "id": 1
     });
@@ -93,7 +85,115 @@ async fn test_eip7702_authorization_transaction_rpc() {
 
     // Assert that the gas estimate exactly matches the expected value.
     assert_eq!(gas_estimate, U256::from(46000));
+}
+
+#[actix_web::test]
+async fn test_eip7702_invalid_authorization_signature() {
+
+    init_logger();
+
+    let node = TestNode::spawn().await;
+    let app = test::init_service(api_app(node.estimator())).await;
+
+    // A validly signed authorization, then the same one with an s value in the upper half of the curve order
+    let signed = FixtureChain::new().sign_authorization(1, COUNTER_ADDRESS, 0);
+    let authorization = |s: String| json!({
+        "chainId": format!("{:#x}", signed.chain_id),
+        "address": signed.address,
+        "nonce": format!("{:#x}", signed.nonce),
+        "yParity": format!("{:#x}", signed.y_parity()),
+        "r": format!("{:#x}", signed.r()),
+        "s": s
+    });
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_estimateGas",
+        "params": [{
+            "from": "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            "authorizationList": [
+                authorization(format!("{:#x}", signed.s())),
+                authorization("0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_string())
+            ]
+        }],
+        "id": 1
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/eth/estimateGas")
+        .set_json(&request)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body = test::read_body(resp).await;
+    let response: serde_json::Value = serde_json::from_slice(&body)
+        .expect("Failed to parse JSON response");
+    assert_eq!(response["error"]["code"], -32602);
+    let message = response["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("Invalid authorization 1: invalid signature"), "{}", message);
+}
+
+#[actix_web::test]
+async fn test_eip7702_invalid_authorization_signature_estimator() {
+
+    init_logger();
+
+    let node = TestNode::spawn().await;
+    let estimator = node.estimator();
+
+    // Callers of the estimator get the check too, not only the API's clients
+    let signed = FixtureChain::new().sign_authorization(1, COUNTER_ADDRESS, 0);
+    let placeholder = SignedAuthorization::new_unchecked(
+        signed.inner().clone(),
+        signed.y_parity(),
+        signed.r(),
+        U256::from_be_bytes([0xbb; 32]),
+    );
+    let tx_request = TransactionRequest {
+        authorization_list: Some(vec![signed, placeholder]),
+        ..TransactionRequest::default()
+            .from(address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"))
+            .to(address!("70997970C51812dc3A010C7d01b50e0d17dc79C8"))
+    };
+    let error = estimator.estimate_raw_gas(&tx_request).await.unwrap_err();
+    match error.downcast_ref::<ServiceError>() {
+        Some(ServiceError::InvalidParams(message)) => {
+            assert!(message.starts_with("Invalid authorization 1: invalid signature"), "{}", message)
+        }
+        other => panic!("Unexpected error: {:?}", other),
+    }
+
+    // Draft estimations opt out per request
+    let options = EstimateOptions { skip_authorization_check: true, ..EstimateOptions::default() };
+    estimator
+        .estimate_raw_gas_with(&tx_request, &options)
+        .await
+        .expect("Estimation with placeholder signatures failed");
+}
+
+#[test]
+fn test_eip7702_authorization_transaction_fixture() {
+
+    init_logger();
 
-    // Clean up: kill the Anvil process.
-    anvil_process.kill().expect("Failed to kill Anvil process");
+    // The fixture authorization is signed by the second dev account, no placeholder signature needed.
+    let fixtures = FixtureChain::new();
+    let mut db = fixtures.db();
+    let authority = fixtures.account(1);
+    let authorization = fixtures.sign_authorization(1, COUNTER_ADDRESS, 0);
+    assert_eq!(authorization.recover_authority().unwrap(), authority);
+
+    // Calling the authority runs the counter code in the authority's own storage.
+    let result = fixtures
+        .transact(&mut db, &fixtures.authorization_request())
+        .expect("Authorization transaction failed validation");
+    assert!(matches!(result, ExecutionResult::Success { .. }), "Unexpected result: {:?}", result);
+
+    let info = db.basic_ref(authority).unwrap().expect("Authority account missing");
+    assert_eq!(info.nonce, 1, "Applying the authorization bumps the authority's nonce");
+    let code = info.code.expect("Authority has no code").original_bytes();
+    assert_eq!(&code[..3], &[0xef, 0x01, 0x00]);
+    assert_eq!(&code[3..], COUNTER_ADDRESS.as_slice());
+    assert_eq!(db.storage_ref(authority, U256::ZERO).unwrap(), U256::from(1));
 }